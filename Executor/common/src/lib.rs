@@ -1,7 +1,7 @@
 #![no_std]
 extern crate alloc;
 
-use core::sync::atomic::{AtomicU32, AtomicU64};
+use core::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering};
 
 // ─── Type aliases ────────────────────────────────────────────────────────────
 
@@ -89,7 +89,7 @@ pub const INITIAL_SHM_SIZE: ShmOffset = 64 * MIB;
 
 /// **Engine page size — the single tunable knob for the page-chain granularity.**
 ///
-/// Every derived constant (`PAGE_DATA_SIZE`, `SUPERBLOCK_SIZE`, `BUCKET_COUNT`,
+/// Every derived constant (`PAGE_DATA_SIZE`, `BUCKET_COUNT`,
 /// `RDMA_SCRATCH_SIZE`, …) and every page-arithmetic site (guest allocator +
 /// `stream_area`/`shared_area`, host extended pool, RDMA data plane in
 /// `remote/{shm,rdma,receiver_initiated}.rs` + `mesh/staging.rs`, `rdma_pool`,
@@ -108,19 +108,22 @@ pub const INITIAL_SHM_SIZE: ShmOffset = 64 * MIB;
 /// its tail) and the ~3.48 GiB wasm32 direct window holds only `CAPACITY_HARD_LIMIT/PAGE_SIZE`
 /// pages (≈1.7k at 2 MiB, vs ≈913k at 4 KiB). **4 KiB is therefore the default.** A larger
 /// page is only expected to help bulk-shuffle workloads (WordCount 4 GB, TeraSort) whose
-/// whole dataset crosses the network — untested as of 2026-06-21; re-tune here and rebuild
-/// every node if revisiting. 2 MiB additionally matches the host THP frame.
+/// whole dataset crosses the network — untested as of 2026-06-21. Those slots can be
+/// given a larger page class instead (see [`PAGE_CLASS_SIZES`]), which keeps this
+/// default for everything else. 2 MiB additionally matches the host THP frame.
 pub const PAGE_SIZE: ShmOffset = 4 * KIB;   // default; tunable — see trade-offs above
 
 // PAGE_SIZE must be a power of two (page-id ⇄ slot-index math relies on it).
 const _: () = assert!(PAGE_SIZE.is_power_of_two());
 
-// Number of independent stream slots (upstream producers + downstream routing targets).
-// Increase this to support more concurrent actors; SUPERBLOCK_SIZE adjusts automatically.
-pub const STREAM_SLOT_COUNT: usize = 2048;
+// Default number of independent stream slots (upstream producers + downstream
+// routing targets).  The count a region actually has is chosen when it is
+// formatted (`EngineConfig::stream_slot_count`) and recorded in its
+// [`ShmLayout`]; the slot table after the Superblock header is sized to it.
+pub const DEFAULT_STREAM_SLOT_COUNT: usize = 2048;
 
-// Number of dedicated I/O slots — a separate, non-overlapping area from the stream slots.
-pub const IO_SLOT_COUNT: usize = 512;
+// Default number of dedicated I/O slots — a separate, non-overlapping area from the stream slots.
+pub const DEFAULT_IO_SLOT_COUNT: usize = 512;
 
 // Default IO slot assignments for the conventional single-input / single-output workflow.
 pub const INPUT_IO_SLOT:  u32 = 0;
@@ -129,41 +132,23 @@ pub const OUTPUT_IO_SLOT: u32 = 1;
 // Number of independent Treiber-stack shards for the page free list.
 pub const FREE_LIST_SHARD_COUNT: usize = 16;
 
-// Automatically derived: size of the Superblock struct rounded up to the next full page.
-pub const SUPERBLOCK_SIZE: ShmOffset = {
-    let sz = core::mem::size_of::<Superblock>() as ShmOffset;
-    (sz + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE
-};
+// Byte offset of the per-region slot table: the writer/IO head and tail
+// arrays and the barrier counters follow the fixed Superblock header.
+pub const SLOT_TABLE_OFFSET: ShmOffset = core::mem::size_of::<Superblock>() as ShmOffset;
 
-pub const REGISTRY_SIZE: ShmOffset = 1 * MIB;
-pub const REGISTRY_OFFSET: ShmOffset = SUPERBLOCK_SIZE;
+// Default sizes of the arenas between the Superblock and the bump allocator.
+// Like the slot counts these are format-time settings; a region's own
+// offsets are in its `ShmLayout`.
+pub const DEFAULT_REGISTRY_SIZE: ShmOffset = MIB;
+pub const DEFAULT_ATOMIC_ARENA_SIZE: ShmOffset = MIB;
+pub const DEFAULT_LOG_ARENA_SIZE: ShmOffset = 16 * MIB;
 
 // Maximum number of nodes in the RDMA full mesh.
 pub const MAX_MESH_NODES: usize = 32;
 
-// RDMA Atomic Result Scratch: one 8-byte slot per (node_id, peer_id) pair.
+// RDMA Atomic Result Scratch: one 8-byte slot per (node_id, peer_id) pair,
+// between the registry and the atomic arena.
 pub const RDMA_SCRATCH_SIZE: ShmOffset = 2 * PAGE_SIZE;
-pub const RDMA_SCRATCH_OFFSET: ShmOffset = REGISTRY_OFFSET + REGISTRY_SIZE;
-
-pub const ATOMIC_ARENA_SIZE: ShmOffset = 1 * MIB;
-pub const ATOMIC_ARENA_OFFSET: ShmOffset = RDMA_SCRATCH_OFFSET + RDMA_SCRATCH_SIZE;
-
-pub const LOG_ARENA_SIZE: ShmOffset = 16 * MIB;
-pub const LOG_ARENA_OFFSET: ShmOffset = ATOMIC_ARENA_OFFSET + ATOMIC_ARENA_SIZE;
-
-pub const BUMP_ALLOCATOR_START: ShmOffset = LOG_ARENA_OFFSET + LOG_ARENA_SIZE;
-
-/// SHM byte offset of the `AtomicU64` at `idx` in the atomic arena.
-#[inline]
-pub const fn atomic_shm_offset(idx: usize) -> ShmOffset {
-    ATOMIC_ARENA_OFFSET + (idx as ShmOffset) * 8
-}
-
-/// SHM byte offset of the 8-byte RDMA result scratch slot for `(node_id, peer_id)`.
-#[inline]
-pub const fn rdma_scratch_shm_offset(node_id: usize, peer_id: usize) -> ShmOffset {
-    RDMA_SCRATCH_OFFSET + ((node_id * MAX_MESH_NODES + peer_id) as ShmOffset) * 8
-}
 
 // Dynamic Hash Map — one bucket per `PageId`-sized atomic slot in a single
 // 4 KiB backing page.  After widening bucket slots to `AtomicPageId` this
@@ -176,19 +161,188 @@ pub const PARALLEL_THRESHOLD: usize = 50;
 
 // ─── Intra-wave barrier ──────────────────────────────────────────────────────
 
-/// Maximum number of concurrent barrier slots available in the Superblock.
-pub const BARRIER_COUNT: usize = 64;
+/// Default number of barrier counters in the Superblock slot table.
+pub const DEFAULT_BARRIER_COUNT: usize = 64;
+
+// ─── Stream signals ──────────────────────────────────────────────────────────
+//
+// Every stream slot has a futex word in the slot table
+// (`Superblock::stream_signal`), so a consumer can sleep until a producer in
+// the same wave appends instead of polling:
+//
+//   bit 31      STREAM_CLOSED    the producer called `close_stream`
+//   bit 30      STREAM_WAITERS   a consumer is asleep on the word
+//   bits 0..30  append sequence, bumped after every published record
+//
+// A consumer loads the word, counts the complete records, and if it needs
+// more sets STREAM_WAITERS with a CAS against the value it loaded and
+// FUTEX_WAITs on the result.  A producer bumps the sequence (clearing
+// STREAM_WAITERS) after publishing a record and issues FUTEX_WAKE only when
+// the flag was set, so an append nobody waits for costs one CAS.  The host
+// zeroes the word when it frees or clears the slot.
+
+pub const STREAM_CLOSED: u32 = 1 << 31;
+pub const STREAM_WAITERS: u32 = 1 << 30;
+pub const STREAM_SEQUENCE_MASK: u32 = STREAM_WAITERS - 1;
+
+/// Records one published append on a stream signal word.  Returns whether a
+/// consumer is asleep on it and must be woken.
+#[inline]
+pub fn stream_signal_append(word: &AtomicU32) -> bool {
+    stream_signal_bump(word, 0)
+}
+
+/// Marks a stream closed: no more records will be appended.  Returns whether
+/// a consumer is asleep on it and must be woken.
+#[inline]
+pub fn stream_signal_close(word: &AtomicU32) -> bool {
+    stream_signal_bump(word, STREAM_CLOSED)
+}
+
+fn stream_signal_bump(word: &AtomicU32, flags: u32) -> bool {
+    let prev = word.fetch_update(Ordering::AcqRel, Ordering::Relaxed, |w| {
+        Some((w & STREAM_CLOSED) | flags | (w.wrapping_add(1) & STREAM_SEQUENCE_MASK))
+    });
+    // The closure never returns `None`.
+    prev.unwrap_or_else(|w| w) & STREAM_WAITERS != 0
+}
 
 // ─── Page data sizing ────────────────────────────────────────────────────────
 
-/// Header bytes consumed by `(next_offset + cursor)` in each [`Page`].
-/// `next_offset` is a [`PageId`] (8 bytes) and `cursor` is a [`ShmOffset`] (4 bytes).
-pub const PAGE_HEADER_SIZE: usize = PAGE_ID_SIZE + SHM_OFFSET_SIZE;
+/// Header bytes consumed by `(next_offset + cursor + class)` in each [`Page`].
+/// `next_offset` is a [`PageId`] (8 bytes); `cursor` and `class` are 4 bytes each.
+pub const PAGE_HEADER_SIZE: usize = PAGE_ID_SIZE + 2 * SHM_OFFSET_SIZE;
 
 /// Usable data bytes per page (PAGE_SIZE minus header).
-/// = PAGE_SIZE − PAGE_HEADER_SIZE (e.g. 4096−12=4084 at 4 KiB; 2097152−12 at 2 MiB).
+/// = PAGE_SIZE − PAGE_HEADER_SIZE (e.g. 4096−16=4080 at 4 KiB).
 pub const PAGE_DATA_SIZE: usize = PAGE_SIZE as usize - PAGE_HEADER_SIZE;
 
+// ─── Page classes ────────────────────────────────────────────────────────────
+//
+// A slot can be given larger pages than `PAGE_SIZE` so bulk transfers walk
+// (and RDMA-send) far fewer pages; see the trade-offs on `PAGE_SIZE` for why
+// that is not the default.  Every page records its class in its header, so a
+// chain may mix classes (e.g. after a `Bridge` splices a 64 KiB chain onto a
+// 4 KiB one) and walkers size each page individually.  Class 0 pages live on
+// the sharded free list; each larger class has its own free list in
+// `Superblock::class_free_lists`.  Large pages always come from the direct
+// window — the extended pool only deals in `PAGE_SIZE` pages.
+
+/// Number of page classes.
+pub const PAGE_CLASS_COUNT: usize = 3;
+
+/// The default class: [`PAGE_SIZE`] pages.
+pub const PAGE_CLASS_SMALL: u32 = 0;
+/// 64 KiB pages.
+pub const PAGE_CLASS_MEDIUM: u32 = 1;
+/// 2 MiB pages.
+pub const PAGE_CLASS_LARGE: u32 = 2;
+
+/// Byte size of a page of each class, indexed by class.
+pub const PAGE_CLASS_SIZES: [ShmOffset; PAGE_CLASS_COUNT] = [PAGE_SIZE, 64 * KIB, 2 * MIB];
+
+/// DAG spelling of each class (`"page_class": "64k"`), indexed by class.
+pub const PAGE_CLASS_NAMES: [&str; PAGE_CLASS_COUNT] = ["4k", "64k", "2m"];
+
+const _: () = assert!(PAGE_CLASS_SIZES[PAGE_CLASS_SMALL as usize] == PAGE_SIZE);
+const _: () = assert!(PAGE_CLASS_SIZES[PAGE_CLASS_LARGE as usize] == HUGE_PAGE_SIZE);
+
+/// Byte size of a page of `class`.  Unknown classes (a corrupt header) are
+/// treated as [`PAGE_CLASS_SMALL`] so a walker never runs past a page.
+#[inline]
+pub const fn page_class_size(class: u32) -> ShmOffset {
+    if (class as usize) < PAGE_CLASS_COUNT { PAGE_CLASS_SIZES[class as usize] } else { PAGE_SIZE }
+}
+
+/// Usable data bytes in a page of `class`.
+#[inline]
+pub const fn page_class_data_size(class: u32) -> usize {
+    page_class_size(class) as usize - PAGE_HEADER_SIZE
+}
+
+/// Parses a DAG page-class name (`"4k"`, `"64k"`, `"2m"`).
+pub fn page_class_from_name(name: &str) -> Option<u32> {
+    PAGE_CLASS_NAMES.iter().position(|n| n.eq_ignore_ascii_case(name)).map(|c| c as u32)
+}
+
+// ─── Mapped inputs ───────────────────────────────────────────────────────────
+//
+// A zero-copy `Input` (`"zero_copy": true`) overlays the file onto a run of
+// contiguous `PAGE_CLASS_LARGE` pages instead of copying it.  Only the first
+// 4 KiB unit of each page is region memory: it holds the page header and the
+// next `PAGE_DATA_SIZE` file bytes, copied.  Units 1.. are the file itself,
+// mapped `MAP_PRIVATE | PROT_READ` over the region at the same offset in
+// every attached process.  For the file offsets to stay page-aligned each
+// page holds exactly `MAPPED_PAGE_FILL` data bytes, so page `j` (at
+// `offset + j · 2 MiB`) overlays file bytes from
+// `PAGE_SIZE + j · MAPPED_PAGE_FILL` onwards; the record header and the
+// first `PAGE_HEADER_SIZE` file bytes sit in ordinary pages ahead of it.
+// The overlays are listed in `Superblock::mapped_inputs`.
+
+/// Entries in `Superblock::mapped_inputs` — zero-copy inputs mapped at once.
+pub const MAPPED_INPUT_COUNT: usize = 4;
+
+/// Bytes reserved for a mapped input's path (NUL-padded, no terminator
+/// needed at full length).
+pub const MAPPED_INPUT_PATH_LEN: usize = 204;
+
+/// Page class of mapped-input pages.
+pub const MAPPED_PAGE_CLASS: u32 = PAGE_CLASS_LARGE;
+
+/// Data bytes in each mapped-input page: one 4 KiB unit short of the class
+/// capacity, which keeps the next page's file offsets page-aligned.
+pub const MAPPED_PAGE_FILL: usize = PAGE_CLASS_SIZES[MAPPED_PAGE_CLASS as usize] as usize - PAGE_SIZE as usize;
+
+/// [`MappedInput::state`]: entry unused.
+pub const MAPPED_INPUT_FREE: u32 = 0;
+/// [`MappedInput::state`]: overlay installed, pages owned by a slot chain.
+pub const MAPPED_INPUT_LIVE: u32 = 1;
+/// [`MappedInput::state`]: every page freed; the extent is kept for the next
+/// zero-copy load instead of going back to the class free list, since other
+/// processes may still have the old overlay in place.
+pub const MAPPED_INPUT_RETIRED: u32 = 2;
+
+/// One input file overlaid onto region pages (see "Mapped inputs" above).
+/// Written only by the DAG runner; `state` is stored last (Release).
+#[repr(C)]
+pub struct MappedInput {
+    pub state: AtomicU32,
+    /// Pages of this entry still in a slot chain; the entry retires at zero.
+    pub live_pages: AtomicU32,
+    /// Offset of the first page of the extent.
+    pub offset: AtomicShmOffset,
+    /// Pages currently overlaid (`<= capacity`).
+    pub pages: AtomicU32,
+    /// Pages reserved in the extent.
+    pub capacity: AtomicU32,
+    /// Absolute path of the mapped file, NUL-padded.
+    pub path: [u8; MAPPED_INPUT_PATH_LEN],
+}
+
+impl MappedInput {
+    /// The mapped file's path; `None` if the stored bytes are not UTF-8.
+    pub fn path_str(&self) -> Option<&str> {
+        let len = self.path.iter().position(|&b| b == 0).unwrap_or(MAPPED_INPUT_PATH_LEN);
+        core::str::from_utf8(&self.path[..len]).ok()
+    }
+
+    /// Region byte range `[start, end)` of page `j`'s file overlay.
+    #[inline]
+    pub fn overlay_range(&self, j: u32) -> (usize, usize) {
+        let page = self.offset.load(core::sync::atomic::Ordering::Relaxed) as usize
+            + j as usize * page_class_size(MAPPED_PAGE_CLASS) as usize;
+        (page + PAGE_SIZE as usize, page + page_class_size(MAPPED_PAGE_CLASS) as usize)
+    }
+
+    /// File offset overlaid at the start of [`MappedInput::overlay_range`]`(j)`.
+    #[inline]
+    pub const fn overlay_file_offset(j: u32) -> u64 {
+        PAGE_SIZE as u64 + j as u64 * MAPPED_PAGE_FILL as u64
+    }
+}
+
+const _: () = assert!(core::mem::size_of::<MappedInput>() == 224);
+
 // ─── Capacity guards ─────────────────────────────────────────────────────────
 
 /// Soft upper bound for bump allocation (one page below `DIRECT_LIMIT`/the
@@ -329,6 +483,53 @@ pub const FREE_LIST_TRIM_ENABLED: bool = true;
 /// the OS will zero-fill them again on the next write (soft page fault).
 pub const FREE_LIST_TRIM_THRESHOLD: usize = 204800; // 800 MiB total free list (across all shards)
 
+// ─── Extended pool sizing ─────────────────────────────────────────────────────
+
+/// Initial committed size of the host-side `GlobalPool` backing file created
+/// on the first `Direct → Paged` flip.  Doubles on demand.
+pub const GLOBAL_POOL_INITIAL_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Residency slots the extended pool's `ResolutionBuffer` is seeded with at
+/// the flip, taken from the direct bump allocator (16 MiB at 4 KiB pages).
+pub const FLIP_SEED_SLOTS: u64 = 4096;
+
+// ─── Huge pages ──────────────────────────────────────────────────────────────
+
+/// `EngineConfig::huge_pages` values.  The logical [`PAGE_SIZE`] is unchanged
+/// in every mode; huge pages only back the mappings, cutting the TLB misses
+/// of long page-chain walks.
+///
+/// - `OFF`: plain 4 KiB pages (tmpfs under `/dev/shm`).
+/// - `THP`: the SHM and the extended-pool backing files stay on tmpfs and
+///   every mapping is `madvise(MADV_HUGEPAGE)`d.  Needs
+///   `/sys/kernel/mm/transparent_hugepage/shmem_enabled` set to `advise`
+///   (or `within_size` / `always`).
+/// - `HUGETLBFS`: the SHM file must live on a hugetlbfs mount with
+///   [`HUGE_PAGE_SIZE`] pages (the DAG's `shm_path`), and the RDMA MR2 file
+///   is created there too.  Incompatible with the extended pool, whose
+///   resolution buffer overlays single 4 KiB pages into the SHM window, and
+///   with Python guests, which `write(2)` the SHM file.
+pub const HUGE_PAGES_OFF: u32 = 0;
+pub const HUGE_PAGES_THP: u32 = 1;
+pub const HUGE_PAGES_HUGETLBFS: u32 = 2;
+
+/// Default huge-page mode.
+pub const HUGE_PAGES: u32 = HUGE_PAGES_OFF;
+
+/// Environment variable naming the hugetlbfs directory that the RDMA MR2
+/// backing files are created in with `HUGE_PAGES_HUGETLBFS`.
+pub const HUGETLBFS_DIR_ENV: &str = "WEBS_HUGETLBFS_DIR";
+pub const DEFAULT_HUGETLBFS_DIR: &str = "/dev/hugepages";
+
+/// Huge-page size (x86-64 PMD).  With huge pages on, SHM capacity and pool
+/// sizes grow in multiples of this, and free-list trimming releases whole
+/// huge-page extents only.  `INITIAL_SHM_SIZE` and `CAPACITY_HARD_LIMIT` are
+/// multiples of it, so guest-side doubling stays aligned.
+pub const HUGE_PAGE_SIZE: ShmOffset = 2 * MIB;
+
+const _: () = assert!(INITIAL_SHM_SIZE.is_multiple_of(HUGE_PAGE_SIZE));
+const _: () = assert!(CAPACITY_HARD_LIMIT.is_multiple_of(HUGE_PAGE_SIZE));
+
 // -------------------------------------------------------
 // Shared Data Structures (Guarantees ABI matching)
 // -------------------------------------------------------
@@ -341,20 +542,167 @@ pub struct Superblock {
     pub registry_lock: AtomicU32,
     pub next_atomic_idx: AtomicU32,
     pub shared_map_base: AtomicShmOffset,
-    // 4 bytes of implicit padding here to 8-align the next AtomicU64 array.
+    /// Byte offset of [`Superblock::layout`].  Sits in what used to be the
+    /// padding before `free_list_heads`, so it is at the same place in every
+    /// build regardless of the slot counts — readers find the descriptor
+    /// through it before trusting any other field.  `0` means the region was
+    /// formatted by a build that predates layout descriptors.
+    pub layout_offset: ShmOffset,
     /// Sharded page free list (Treiber stacks).  Head PageIds are u64 so a
     /// direct-freelist slot retired during paged mode may host any PageId.
     pub free_list_heads: [AtomicPageId; FREE_LIST_SHARD_COUNT],
-    /// Per-slot head PageIds for the stream area (0..STREAM_SLOT_COUNT).
-    pub writer_heads: [AtomicPageId; STREAM_SLOT_COUNT],
-    /// Per-slot tail PageIds for the stream area.
-    pub writer_tails: [AtomicPageId; STREAM_SLOT_COUNT],
-    /// Per-slot head PageIds for the dedicated I/O area (0..IO_SLOT_COUNT).
-    pub io_heads: [AtomicPageId; IO_SLOT_COUNT],
-    /// Per-slot tail PageIds for the dedicated I/O area.
-    pub io_tails: [AtomicPageId; IO_SLOT_COUNT],
-    /// Intra-wave barrier counters (futex-backed).
-    pub barriers: [AtomicU32; BARRIER_COUNT],
+    /// Layout this region was formatted with, including its slot counts and
+    /// arena offsets.  Written once by `format_shared_memory`; read-only
+    /// afterwards.
+    pub layout: ShmLayout,
+    /// Engine tunables the formatting process resolved at startup.  Worker
+    /// subprocesses adopt these on attach so every process sharing the region
+    /// uses the same thresholds.  Read-only after format.
+    pub engine: EngineConfig,
+    /// One Treiber-stack free list per large page class
+    /// (`class_free_lists[c - 1]` holds class `c`).  Unsharded: large pages
+    /// are few and allocated in bulk.
+    pub class_free_lists: [AtomicPageId; PAGE_CLASS_COUNT - 1],
+    /// Bumped each time an entry of `mapped_inputs` goes live.  Processes
+    /// that keep a mapping across DAG nodes re-apply the overlays when they
+    /// see a new value.
+    pub mapped_generation: AtomicU32,
+    /// Zero-copy input files overlaid onto region pages.
+    pub mapped_inputs: [MappedInput; MAPPED_INPUT_COUNT],
+    /// Byte offset of the key-value store's root directory page, or `0`
+    /// until the first write creates it (see "Key-value store").  Sits in
+    /// what used to be the tail padding, so the slot table does not move.
+    pub kv_directory: AtomicShmOffset,
+    // The slot table follows at `SLOT_TABLE_OFFSET`, sized by `layout`:
+    //   writer_heads        [AtomicPageId; stream_slot_count]
+    //   writer_tails        [AtomicPageId; stream_slot_count]
+    //   io_heads            [AtomicPageId; io_slot_count]
+    //   io_tails            [AtomicPageId; io_slot_count]
+    //   barriers            [AtomicU32;    barrier_count]
+    //   stream_signals      [AtomicU32;    stream_slot_count]
+    //   stream_rings        [AtomicShmOffset; stream_slot_count]
+    //   stream_subscriptions [AtomicShmOffset; stream_slot_count]
+    //   stream_page_classes [AtomicU8;     stream_slot_count]
+    //   io_page_classes     [AtomicU8;     io_slot_count]
+    // Use the accessors below rather than computing offsets by hand.
+}
+
+impl Superblock {
+    /// Stream slots in this region (`0..stream_slot_count()`).
+    #[inline]
+    pub fn stream_slot_count(&self) -> usize { self.layout.stream_slot_count as usize }
+
+    /// I/O slots in this region (`0..io_slot_count()`).
+    #[inline]
+    pub fn io_slot_count(&self) -> usize { self.layout.io_slot_count as usize }
+
+    /// Barrier counters in this region (`0..barrier_count()`).
+    #[inline]
+    pub fn barrier_count(&self) -> usize { self.layout.barrier_count as usize }
+
+    /// Head PageId of stream slot `slot`.  Panics if `slot` is out of range.
+    #[inline]
+    pub fn writer_head(&self, slot: usize) -> &AtomicPageId {
+        assert!(slot < self.stream_slot_count(), "stream slot {} out of range ({} slots)",
+                slot, self.stream_slot_count());
+        unsafe { self.table_entry(self.layout.writer_heads_offset(), slot) }
+    }
+
+    /// Tail PageId of stream slot `slot`.  Panics if `slot` is out of range.
+    #[inline]
+    pub fn writer_tail(&self, slot: usize) -> &AtomicPageId {
+        assert!(slot < self.stream_slot_count(), "stream slot {} out of range ({} slots)",
+                slot, self.stream_slot_count());
+        unsafe { self.table_entry(self.layout.writer_tails_offset(), slot) }
+    }
+
+    /// Head PageId of I/O slot `slot`.  Panics if `slot` is out of range.
+    #[inline]
+    pub fn io_head(&self, slot: usize) -> &AtomicPageId {
+        assert!(slot < self.io_slot_count(), "I/O slot {} out of range ({} slots)",
+                slot, self.io_slot_count());
+        unsafe { self.table_entry(self.layout.io_heads_offset(), slot) }
+    }
+
+    /// Tail PageId of I/O slot `slot`.  Panics if `slot` is out of range.
+    #[inline]
+    pub fn io_tail(&self, slot: usize) -> &AtomicPageId {
+        assert!(slot < self.io_slot_count(), "I/O slot {} out of range ({} slots)",
+                slot, self.io_slot_count());
+        unsafe { self.table_entry(self.layout.io_tails_offset(), slot) }
+    }
+
+    /// Intra-wave barrier counter `id` (futex-backed).  Panics if `id` is out
+    /// of range.
+    #[inline]
+    pub fn barrier(&self, id: usize) -> &AtomicU32 {
+        assert!(id < self.barrier_count(), "barrier {} out of range ({} barriers)",
+                id, self.barrier_count());
+        unsafe { self.table_entry(self.layout.barriers_offset(), id) }
+    }
+
+    /// Futex word of stream slot `slot` (see "Stream signals").  Panics if
+    /// `slot` is out of range.
+    #[inline]
+    pub fn stream_signal(&self, slot: usize) -> &AtomicU32 {
+        assert!(slot < self.stream_slot_count(), "stream slot {} out of range ({} slots)",
+                slot, self.stream_slot_count());
+        unsafe { self.table_entry(self.layout.stream_signals_offset(), slot) }
+    }
+
+    /// Byte offset of stream slot `slot`'s [`RingHeader`] page, or `0` for a
+    /// slot that is an ordinary chain (see "Ring slots").  Set by the DAG
+    /// runner from the slot declarations.  Panics if `slot` is out of range.
+    #[inline]
+    pub fn stream_ring(&self, slot: usize) -> &AtomicShmOffset {
+        assert!(slot < self.stream_slot_count(), "stream slot {} out of range ({} slots)",
+                slot, self.stream_slot_count());
+        unsafe { self.table_entry(self.layout.stream_rings_offset(), slot) }
+    }
+
+    /// Byte offset of stream slot `slot`'s [`SubscriptionTable`] page, or `0`
+    /// for a slot without subscribers (see "Stream subscriptions").  Set by
+    /// the DAG runner from the slot declarations.  Panics if `slot` is out of
+    /// range.
+    #[inline]
+    pub fn stream_subscriptions(&self, slot: usize) -> &AtomicShmOffset {
+        assert!(slot < self.stream_slot_count(), "stream slot {} out of range ({} slots)",
+                slot, self.stream_slot_count());
+        unsafe { self.table_entry(self.layout.stream_subscriptions_offset(), slot) }
+    }
+
+    /// Page class new pages of stream slot `slot` are allocated in.  Set by
+    /// the DAG runner from the slot declarations; `0` ([`PAGE_CLASS_SMALL`])
+    /// unless declared.  Panics if `slot` is out of range.
+    #[inline]
+    pub fn stream_page_class(&self, slot: usize) -> &AtomicU8 {
+        assert!(slot < self.stream_slot_count(), "stream slot {} out of range ({} slots)",
+                slot, self.stream_slot_count());
+        unsafe { self.table_entry(self.layout.stream_page_classes_offset(), slot) }
+    }
+
+    /// Page class of I/O slot `slot`; see [`Superblock::stream_page_class`].
+    #[inline]
+    pub fn io_page_class(&self, slot: usize) -> &AtomicU8 {
+        assert!(slot < self.io_slot_count(), "I/O slot {} out of range ({} slots)",
+                slot, self.io_slot_count());
+        unsafe { self.table_entry(self.layout.io_page_classes_offset(), slot) }
+    }
+
+    /// Free-list head for the large page `class` (`1..PAGE_CLASS_COUNT`).
+    #[inline]
+    pub fn class_free_list(&self, class: u32) -> &AtomicPageId {
+        &self.class_free_lists[class as usize - 1]
+    }
+
+    /// Element `idx` of the slot-table array at byte `offset`.
+    ///
+    /// Safety: `self` must be the header of a formatted region, mapped at
+    /// least up to `layout.superblock_size`, and `idx` in range for the array.
+    #[inline]
+    unsafe fn table_entry<T>(&self, offset: ShmOffset, idx: usize) -> &T {
+        unsafe { &*((self as *const Self as *const u8).add(offset as usize) as *const T).add(idx) }
+    }
 }
 
 #[repr(C, align(4096))] // must equal PAGE_SIZE (4 KiB default)
@@ -367,26 +715,822 @@ pub struct Page {
     /// resident cache + `host_fetch_page` slow path.
     pub next_offset: AtomicPageId,
     pub cursor: AtomicShmOffset,
+    /// Page class ([`PAGE_CLASS_SMALL`] …): how many bytes this page spans.
+    /// Written when the page is first carved from the bump allocator and
+    /// kept across free/reuse, since a page returns to its class's list.
+    pub class: AtomicU32,
+    /// The first `PAGE_DATA_SIZE` data bytes.  A larger-class page continues
+    /// past the end of this array; use [`Page::capacity`] for its real size.
     pub data: [u8; PAGE_DATA_SIZE],
 }
 
+impl Page {
+    /// Data bytes this page holds when full (depends on its class).
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        page_class_data_size(self.class.load(core::sync::atomic::Ordering::Relaxed))
+    }
+
+    /// Total bytes this page spans, header included.
+    #[inline]
+    pub fn span(&self) -> ShmOffset {
+        page_class_size(self.class.load(core::sync::atomic::Ordering::Relaxed))
+    }
+}
+
 // Compile-time assertions: Page must be exactly PAGE_SIZE bytes and the
-// header layout must be { next_offset: u64 @ 0, cursor: u32 @ 8, data @ 12 }.
+// header layout must be
+// { next_offset: u64 @ 0, cursor: u32 @ 8, class: u32 @ 12, data @ 16 }.
 const _: () = assert!(core::mem::size_of::<Page>() == PAGE_SIZE as usize);
-const _: () = assert!(PAGE_HEADER_SIZE == 12);
+const _: () = assert!(core::mem::offset_of!(Page, class) == 12);
+const _: () = assert!(PAGE_HEADER_SIZE == 16);
 const _: () = assert!(PAGE_DATA_SIZE == PAGE_SIZE as usize - PAGE_HEADER_SIZE);
 
-// Compile-time assertions for the Superblock layout after widening slot
-// atomics to AtomicPageId (u64).  These offsets are mirrored by the Python
-// guest (`py_guest/python/shm.py`) as literals; if any assert fires, update
-// shm.py in the same commit.
+// Compile-time assertions for the fixed Superblock header.  These offsets
+// are mirrored by the Python guest (`py_guest/python/shm.py`) as literals; if
+// any assert fires, update shm.py in the same commit.  The slot-table offsets
+// are per region and read from the descriptor.
 const _: () = assert!(core::mem::offset_of!(Superblock, bump_allocator)  == 4);
+const _: () = assert!(core::mem::offset_of!(Superblock, layout_offset)   == 28);
 const _: () = assert!(core::mem::offset_of!(Superblock, free_list_heads) == 32);
-const _: () = assert!(core::mem::offset_of!(Superblock, writer_heads)    == 160);
-const _: () = assert!(core::mem::offset_of!(Superblock, writer_tails)    == 16544);
-const _: () = assert!(core::mem::offset_of!(Superblock, io_heads)        == 32928);
-const _: () = assert!(core::mem::offset_of!(Superblock, io_tails)        == 37024);
-const _: () = assert!(core::mem::offset_of!(Superblock, barriers)        == 41120);
+const _: () = assert!(core::mem::offset_of!(Superblock, layout)          == 160);
+const _: () = assert!(core::mem::offset_of!(Superblock, engine)          == 216);
+const _: () = assert!(core::mem::offset_of!(Superblock, class_free_lists) == 320);
+const _: () = assert!(core::mem::offset_of!(Superblock, mapped_generation) == 336);
+const _: () = assert!(core::mem::offset_of!(Superblock, mapped_inputs) == 340);
+const _: () = assert!(core::mem::offset_of!(Superblock, kv_directory) == 1236);
+const _: () = assert!(SLOT_TABLE_OFFSET == 1240);
+
+// ─── Layout descriptor ───────────────────────────────────────────────────────
+
+/// Value of [`Superblock::magic`] in a formatted region.
+pub const SHM_MAGIC: u32 = 0xDEAD_BEEF;
+
+/// Version of the SHM layout.  Bump whenever a structure in this file changes
+/// in a way the fields of [`ShmLayout`] do not already capture (e.g. a new
+/// Superblock field or a different `Page` header).
+pub const SHM_LAYOUT_VERSION: u32 = 11;
+
+/// Byte size of a serialized [`ShmLayout`].
+pub const SHM_LAYOUT_SIZE: usize = core::mem::size_of::<ShmLayout>();
+
+/// Layout descriptor stored in the Superblock at format time.
+///
+/// Two kinds of field live here.  `version`, `page_size`,
+/// `free_list_shard_count` and `page_header_size` are fixed by the build;
+/// together with the Superblock header offsets and record sizes they feed
+/// [`SHM_LAYOUT_HASH`].  Every process that attaches to a region — the DAG
+/// runner, `wasm-call` workers, the guest module, the Python runner and the
+/// NodeAgent — checks them against its own build via [`check_shm_layout`] and
+/// refuses to attach on any difference.
+///
+/// The rest is the region's geometry, chosen when it is formatted (see
+/// [`ShmLayout::new`]): slot counts, barrier count and the arena offsets,
+/// from which the arena sizes follow.  Readers index the slot table and the
+/// arenas through these instead of through constants, so regions with
+/// different geometries work with the same binaries.
+///
+/// All fields are little-endian `u32`s followed by the `u64` hash, so the
+/// descriptor can be decoded without the struct (see `py_guest/python/shm.py`).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShmLayout {
+    pub version: u32,
+    pub page_size: u32,
+    pub stream_slot_count: u32,
+    pub io_slot_count: u32,
+    pub free_list_shard_count: u32,
+    pub barrier_count: u32,
+    /// End of the slot table rounded up to [`PAGE_SIZE`].
+    pub superblock_size: u32,
+    /// Start of the registry; equals `superblock_size`.  The RDMA scratch
+    /// area follows the registry and ends at `atomic_arena_offset`.
+    pub registry_offset: u32,
+    pub atomic_arena_offset: u32,
+    pub log_arena_offset: u32,
+    /// End of the log arena; the first page the bump allocator hands out.
+    pub bump_allocator_start: u32,
+    pub page_header_size: u32,
+    /// [`SHM_LAYOUT_HASH`] of the formatting build.
+    pub layout_hash: u64,
+}
+
+const _: () = assert!(SHM_LAYOUT_SIZE == 56);
+
+/// FNV-1a over [`ShmLayout::hash_words`]: identifies the build-fixed part of
+/// the layout.  Also exported by the guest module (`shm_layout_hash`) so the
+/// host can reject a guest compiled against a different `common`.
+pub const SHM_LAYOUT_HASH: u64 = {
+    let words = ShmLayout::hash_words();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < words.len() {
+        let bytes = words[i].to_le_bytes();
+        let mut j = 0;
+        while j < 4 {
+            hash ^= bytes[j] as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            j += 1;
+        }
+        i += 1;
+    }
+    hash
+};
+
+/// Number of `u32` fields in [`ShmLayout`] before `layout_hash`.
+const SHM_LAYOUT_FIELDS: usize = 12;
+
+/// Field names in declaration order, for mismatch reports.
+const SHM_LAYOUT_FIELD_NAMES: [&str; SHM_LAYOUT_FIELDS] = [
+    "version",
+    "page_size",
+    "stream_slot_count",
+    "io_slot_count",
+    "free_list_shard_count",
+    "barrier_count",
+    "superblock_size",
+    "registry_offset",
+    "atomic_arena_offset",
+    "log_arena_offset",
+    "bump_allocator_start",
+    "page_header_size",
+];
+
+/// Indices into [`SHM_LAYOUT_FIELD_NAMES`] of the build-fixed fields.
+const SHM_LAYOUT_BUILD_FIELDS: [usize; 4] = [0, 1, 4, 11];
+
+impl ShmLayout {
+    /// Geometry of a region formatted with the default slot counts and arena
+    /// sizes.
+    pub const DEFAULT: ShmLayout = ShmLayout::new(
+        DEFAULT_STREAM_SLOT_COUNT as u32,
+        DEFAULT_IO_SLOT_COUNT as u32,
+        DEFAULT_BARRIER_COUNT as u32,
+        DEFAULT_REGISTRY_SIZE,
+        DEFAULT_ATOMIC_ARENA_SIZE,
+        DEFAULT_LOG_ARENA_SIZE,
+    );
+
+    /// Lays out the slot table and arenas for the given counts and sizes.
+    /// The arena sizes must be multiples of [`PAGE_SIZE`] and the whole
+    /// header must fit in `u32`; [`EngineConfig::validate`] checks both.
+    pub const fn new(
+        stream_slot_count: u32,
+        io_slot_count: u32,
+        barrier_count: u32,
+        registry_size: ShmOffset,
+        atomic_arena_size: ShmOffset,
+        log_arena_size: ShmOffset,
+    ) -> Self {
+        let table_end = slot_table_end(stream_slot_count, io_slot_count, barrier_count) as ShmOffset;
+        let superblock_size = table_end.next_multiple_of(PAGE_SIZE);
+        let atomic_arena_offset = superblock_size + registry_size + RDMA_SCRATCH_SIZE;
+        let log_arena_offset = atomic_arena_offset + atomic_arena_size;
+        ShmLayout {
+            version: SHM_LAYOUT_VERSION,
+            page_size: PAGE_SIZE,
+            stream_slot_count,
+            io_slot_count,
+            free_list_shard_count: FREE_LIST_SHARD_COUNT as u32,
+            barrier_count,
+            superblock_size,
+            registry_offset: superblock_size,
+            atomic_arena_offset,
+            log_arena_offset,
+            bump_allocator_start: log_arena_offset + log_arena_size,
+            page_header_size: PAGE_HEADER_SIZE as u32,
+            layout_hash: SHM_LAYOUT_HASH,
+        }
+    }
+
+    /// Layout for the slot counts and arena sizes in `cfg`.
+    pub const fn for_config(cfg: &EngineConfig) -> Self {
+        ShmLayout::new(
+            cfg.stream_slot_count,
+            cfg.io_slot_count,
+            cfg.barrier_count,
+            cfg.registry_size,
+            cfg.atomic_arena_size,
+            cfg.log_arena_size,
+        )
+    }
+
+    /// The words [`SHM_LAYOUT_HASH`] is computed over: the build-fixed
+    /// descriptor fields, the offsets of the fixed Superblock header, and the
+    /// sizes of the registry, conflict-chain, key-value entry, ring header and
+    /// subscription table records.  The Python guest rebuilds this list from
+    /// its own literals, so keep the order stable.
+    pub const fn hash_words() -> [u32; 17] {
+        [
+            SHM_LAYOUT_VERSION,
+            PAGE_SIZE,
+            FREE_LIST_SHARD_COUNT as u32,
+            PAGE_HEADER_SIZE as u32,
+            core::mem::offset_of!(Superblock, layout_offset) as u32,
+            core::mem::offset_of!(Superblock, free_list_heads) as u32,
+            core::mem::offset_of!(Superblock, layout) as u32,
+            core::mem::offset_of!(Superblock, engine) as u32,
+            core::mem::offset_of!(Superblock, class_free_lists) as u32,
+            core::mem::offset_of!(Superblock, mapped_inputs) as u32,
+            SLOT_TABLE_OFFSET,
+            core::mem::size_of::<RegistryEntry>() as u32,
+            core::mem::size_of::<ChainNodeHeader>() as u32,
+            core::mem::offset_of!(Superblock, kv_directory) as u32,
+            core::mem::size_of::<KvEntryHeader>() as u32,
+            core::mem::size_of::<RingHeader>() as u32,
+            core::mem::size_of::<SubscriptionTable>() as u32,
+        ]
+    }
+
+    /// Byte offset of the stream-slot head array.
+    #[inline]
+    pub const fn writer_heads_offset(&self) -> ShmOffset { SLOT_TABLE_OFFSET }
+
+    /// Byte offset of the stream-slot tail array.
+    #[inline]
+    pub const fn writer_tails_offset(&self) -> ShmOffset {
+        self.writer_heads_offset() + self.stream_slot_count * PAGE_ID_SIZE as u32
+    }
+
+    /// Byte offset of the I/O-slot head array.
+    #[inline]
+    pub const fn io_heads_offset(&self) -> ShmOffset {
+        self.writer_tails_offset() + self.stream_slot_count * PAGE_ID_SIZE as u32
+    }
+
+    /// Byte offset of the I/O-slot tail array.
+    #[inline]
+    pub const fn io_tails_offset(&self) -> ShmOffset {
+        self.io_heads_offset() + self.io_slot_count * PAGE_ID_SIZE as u32
+    }
+
+    /// Byte offset of the barrier counters.
+    #[inline]
+    pub const fn barriers_offset(&self) -> ShmOffset {
+        self.io_tails_offset() + self.io_slot_count * PAGE_ID_SIZE as u32
+    }
+
+    /// Byte offset of the per-stream-slot futex words.
+    #[inline]
+    pub const fn stream_signals_offset(&self) -> ShmOffset {
+        self.barriers_offset() + self.barrier_count * 4
+    }
+
+    /// Byte offset of the per-stream-slot ring header offsets.
+    #[inline]
+    pub const fn stream_rings_offset(&self) -> ShmOffset {
+        self.stream_signals_offset() + self.stream_slot_count * 4
+    }
+
+    /// Byte offset of the per-stream-slot subscription table offsets.
+    #[inline]
+    pub const fn stream_subscriptions_offset(&self) -> ShmOffset {
+        self.stream_rings_offset() + self.stream_slot_count * SHM_OFFSET_SIZE as u32
+    }
+
+    /// Byte offset of the per-stream-slot page classes (one byte each).
+    #[inline]
+    pub const fn stream_page_classes_offset(&self) -> ShmOffset {
+        self.stream_subscriptions_offset() + self.stream_slot_count * SHM_OFFSET_SIZE as u32
+    }
+
+    /// Byte offset of the per-I/O-slot page classes (one byte each).
+    #[inline]
+    pub const fn io_page_classes_offset(&self) -> ShmOffset {
+        self.stream_page_classes_offset() + self.stream_slot_count
+    }
+
+    /// Byte offset of the RDMA result scratch area.
+    #[inline]
+    pub const fn rdma_scratch_offset(&self) -> ShmOffset {
+        self.atomic_arena_offset - RDMA_SCRATCH_SIZE
+    }
+
+    #[inline]
+    pub const fn registry_size(&self) -> ShmOffset {
+        self.rdma_scratch_offset() - self.registry_offset
+    }
+
+    #[inline]
+    pub const fn atomic_arena_size(&self) -> ShmOffset {
+        self.log_arena_offset - self.atomic_arena_offset
+    }
+
+    #[inline]
+    pub const fn log_arena_size(&self) -> ShmOffset {
+        self.bump_allocator_start - self.log_arena_offset
+    }
+
+    /// Number of `RegistryEntry`s (and therefore named atomics) the region
+    /// holds: the smaller of the registry and atomic-arena capacities.
+    pub const fn named_atomic_capacity(&self) -> usize {
+        let entries = self.registry_size() as usize / core::mem::size_of::<RegistryEntry>();
+        let atomics = self.atomic_arena_size() as usize / 8;
+        if entries < atomics { entries } else { atomics }
+    }
+
+    /// SHM byte offset of the `AtomicU64` at `idx` in the atomic arena.
+    #[inline]
+    pub const fn atomic_offset(&self, idx: usize) -> ShmOffset {
+        self.atomic_arena_offset + (idx as ShmOffset) * 8
+    }
+
+    /// SHM byte offset of the 8-byte RDMA result scratch slot for `(node_id, peer_id)`.
+    #[inline]
+    pub const fn rdma_scratch_slot_offset(&self, node_id: usize, peer_id: usize) -> ShmOffset {
+        self.rdma_scratch_offset() + ((node_id * MAX_MESH_NODES + peer_id) as ShmOffset) * 8
+    }
+
+    /// Why this descriptor's geometry is not one [`ShmLayout::new`] could
+    /// have produced, if it is not.  Readers index the region through these
+    /// offsets, so a damaged descriptor must not be trusted.
+    pub fn geometry_error(&self) -> Option<&'static str> {
+        if self.stream_slot_count == 0 || self.io_slot_count == 0 || self.barrier_count == 0 {
+            return Some("slot and barrier counts must be non-zero");
+        }
+        let end = slot_table_end(self.stream_slot_count, self.io_slot_count, self.barrier_count);
+        if end.next_multiple_of(PAGE_SIZE as u64) != self.superblock_size as u64 {
+            return Some("superblock_size does not match the slot counts");
+        }
+        if self.registry_offset != self.superblock_size {
+            return Some("registry_offset must equal superblock_size");
+        }
+        let ordered = self.registry_offset as u64 + PAGE_SIZE as u64 + RDMA_SCRATCH_SIZE as u64
+            <= self.atomic_arena_offset as u64
+            && self.atomic_arena_offset < self.log_arena_offset
+            && self.log_arena_offset < self.bump_allocator_start;
+        if !ordered {
+            return Some("arena offsets are not in ascending order");
+        }
+        let aligned = self.atomic_arena_offset.is_multiple_of(PAGE_SIZE)
+            && self.log_arena_offset.is_multiple_of(PAGE_SIZE)
+            && self.bump_allocator_start.is_multiple_of(PAGE_SIZE);
+        if !aligned {
+            return Some("arena offsets are not page-aligned");
+        }
+        None
+    }
+
+    const fn fields(&self) -> [u32; SHM_LAYOUT_FIELDS] {
+        [
+            self.version,
+            self.page_size,
+            self.stream_slot_count,
+            self.io_slot_count,
+            self.free_list_shard_count,
+            self.barrier_count,
+            self.superblock_size,
+            self.registry_offset,
+            self.atomic_arena_offset,
+            self.log_arena_offset,
+            self.bump_allocator_start,
+            self.page_header_size,
+        ]
+    }
+
+    /// Little-endian encoding, identical to the in-memory `repr(C)` layout.
+    pub fn to_le_bytes(&self) -> [u8; SHM_LAYOUT_SIZE] {
+        let mut out = [0u8; SHM_LAYOUT_SIZE];
+        for (i, w) in self.fields().iter().enumerate() {
+            out[i * 4..i * 4 + 4].copy_from_slice(&w.to_le_bytes());
+        }
+        out[SHM_LAYOUT_FIELDS * 4..].copy_from_slice(&self.layout_hash.to_le_bytes());
+        out
+    }
+
+    /// Decodes a descriptor written by [`ShmLayout::to_le_bytes`].
+    pub fn from_le_bytes(b: &[u8]) -> Option<Self> {
+        if b.len() < SHM_LAYOUT_SIZE {
+            return None;
+        }
+        let w = |i: usize| u32::from_le_bytes([b[i * 4], b[i * 4 + 1], b[i * 4 + 2], b[i * 4 + 3]]);
+        let mut hash = [0u8; 8];
+        hash.copy_from_slice(&b[SHM_LAYOUT_FIELDS * 4..SHM_LAYOUT_SIZE]);
+        Some(ShmLayout {
+            version: w(0),
+            page_size: w(1),
+            stream_slot_count: w(2),
+            io_slot_count: w(3),
+            free_list_shard_count: w(4),
+            barrier_count: w(5),
+            superblock_size: w(6),
+            registry_offset: w(7),
+            atomic_arena_offset: w(8),
+            log_arena_offset: w(9),
+            bump_allocator_start: w(10),
+            page_header_size: w(11),
+            layout_hash: u64::from_le_bytes(hash),
+        })
+    }
+}
+
+/// Byte offset just past the page-class table, in `u64` so oversized counts
+/// cannot wrap.
+const fn slot_table_end(stream_slot_count: u32, io_slot_count: u32, barrier_count: u32) -> u64 {
+    SLOT_TABLE_OFFSET as u64
+        + (2 * PAGE_ID_SIZE as u64 + 1) * (stream_slot_count as u64 + io_slot_count as u64)
+        + 4 * barrier_count as u64
+        + 4 * stream_slot_count as u64
+        + 2 * SHM_OFFSET_SIZE as u64 * stream_slot_count as u64
+}
+
+const _: () = assert!(ShmLayout::DEFAULT.superblock_size == 69632);
+const _: () = assert!(ShmLayout::DEFAULT.bump_allocator_start == 69632 + 18 * MIB + RDMA_SCRATCH_SIZE);
+
+/// Why a region was rejected by [`check_shm_layout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// Fewer bytes than the Superblock header / descriptor were supplied.
+    Truncated { len: usize },
+    /// `magic` is not [`SHM_MAGIC`] — not a formatted region at all.
+    BadMagic { found: u32 },
+    /// Formatted by a build that predates layout descriptors.
+    Unversioned,
+    /// The stored descriptor differs from this build's in `field`.
+    Mismatch { field: &'static str, found: u64, expected: u64 },
+    /// The stored geometry is not self-consistent (see [`ShmLayout::geometry_error`]).
+    Geometry { reason: &'static str },
+}
+
+impl core::fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            LayoutError::Truncated { len } =>
+                write!(f, "region too small for a superblock ({} bytes)", len),
+            LayoutError::BadMagic { found } =>
+                write!(f, "bad superblock magic {:#010x} (expected {:#010x}); region is not formatted",
+                       found, SHM_MAGIC),
+            LayoutError::Unversioned =>
+                write!(f, "region has no layout descriptor (formatted by an older build); re-format it"),
+            LayoutError::Mismatch { field: "layout_hash", found, expected } =>
+                write!(f, "layout hash mismatch: region {:#018x}, this build {:#018x} \
+                           (Superblock/record offsets differ)", found, expected),
+            LayoutError::Mismatch { field, found, expected } =>
+                write!(f, "layout mismatch on {}: region has {}, this build expects {}",
+                       field, found, expected),
+            LayoutError::Geometry { reason } =>
+                write!(f, "corrupt layout descriptor: {}", reason),
+        }
+    }
+}
+
+impl core::error::Error for LayoutError {}
+
+/// Verifies that `superblock` — the first bytes of a mapped or copied region,
+/// at least up to the end of the descriptor — was formatted by a build with
+/// the same fixed layout as this one, and that its stored geometry is
+/// consistent.  Returns the stored descriptor on success; callers index the
+/// slot table and arenas through it.
+///
+/// Only `magic` (@ 0) and `layout_offset` (@ 28) are read before the
+/// descriptor itself; both sit at the same place in every build since layout
+/// descriptors were introduced, so an older region is still decoded safely.
+pub fn check_shm_layout(superblock: &[u8]) -> Result<ShmLayout, LayoutError> {
+    let at = |off: usize| u32::from_le_bytes([
+        superblock[off], superblock[off + 1], superblock[off + 2], superblock[off + 3],
+    ]);
+    if superblock.len() < 32 {
+        return Err(LayoutError::Truncated { len: superblock.len() });
+    }
+    let magic = at(0);
+    if magic != SHM_MAGIC {
+        return Err(LayoutError::BadMagic { found: magic });
+    }
+    let off = at(28) as usize;
+    if off == 0 {
+        return Err(LayoutError::Unversioned);
+    }
+    let stored = superblock
+        .get(off..)
+        .and_then(ShmLayout::from_le_bytes)
+        .ok_or(LayoutError::Truncated { len: superblock.len() })?;
+    let expected = ShmLayout::DEFAULT;
+    let (found, want) = (stored.fields(), expected.fields());
+    for i in SHM_LAYOUT_BUILD_FIELDS {
+        if found[i] != want[i] {
+            return Err(LayoutError::Mismatch {
+                field: SHM_LAYOUT_FIELD_NAMES[i],
+                found: found[i] as u64,
+                expected: want[i] as u64,
+            });
+        }
+    }
+    if stored.layout_hash != SHM_LAYOUT_HASH {
+        return Err(LayoutError::Mismatch {
+            field: "layout_hash",
+            found: stored.layout_hash,
+            expected: SHM_LAYOUT_HASH,
+        });
+    }
+    if let Some(reason) = stored.geometry_error() {
+        return Err(LayoutError::Geometry { reason });
+    }
+    Ok(stored)
+}
+
+// ─── Workload metadata ───────────────────────────────────────────────────────
+//
+// Every `#[workload]` export (see `guest-macros`) adds one line of JSON to the
+// `WORKLOAD_META_SECTION` custom section of the guest module: its name,
+// argument schema, return kind and the slots it consumes and produces.  The
+// linker concatenates the lines of all workloads into one section.  The DAG
+// runner and the partitioner read it back to check the nodes that call a
+// workload against its signature.
+
+/// Name of the custom section holding workload metadata.
+pub const WORKLOAD_META_SECTION: &str = "webs_workloads";
+
+/// Payloads of every custom section called `name` in the WebAssembly binary
+/// `module`, in file order.  Stops at the first malformed section header, so
+/// a truncated module or a precompiled `.cwasm` yields nothing rather than
+/// garbage.
+pub fn wasm_custom_sections<'a>(module: &'a [u8], name: &'a str) -> impl Iterator<Item = &'a [u8]> + 'a {
+    fn leb_u32(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let b = *bytes.get(*pos)?;
+            *pos += 1;
+            value |= ((b & 0x7F) as u32) << shift;
+            if b & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    let mut pos = if module.starts_with(b"\0asm") { 8 } else { module.len() };
+    core::iter::from_fn(move || {
+        while pos < module.len() {
+            let id = module[pos];
+            pos += 1;
+            let size = leb_u32(module, &mut pos)? as usize;
+            let body = module.get(pos..pos.checked_add(size)?)?;
+            pos += size;
+            if id != 0 {
+                continue;
+            }
+            let mut at = 0;
+            let name_len = leb_u32(body, &mut at)? as usize;
+            let section_name = body.get(at..at.checked_add(name_len)?)?;
+            if section_name == name.as_bytes() {
+                return Some(&body[at + name_len..]);
+            }
+        }
+        None
+    })
+    .fuse()
+}
+
+/// The metadata lines of every `#[workload]` export in `module`: one JSON
+/// object each, surrounding whitespace and linker padding stripped.  Sections
+/// that are not UTF-8 are skipped.
+pub fn workload_meta_lines(module: &[u8]) -> impl Iterator<Item = &str> {
+    wasm_custom_sections(module, WORKLOAD_META_SECTION)
+        .filter_map(|section| core::str::from_utf8(section).ok())
+        .flat_map(str::lines)
+        .map(|line| line.trim_matches(|c: char| c == '\0' || c.is_whitespace()))
+        .filter(|line| !line.is_empty())
+}
+
+// ─── DAG slot numbers ────────────────────────────────────────────────────────
+//
+// The partitioner and the DAG runner both allocate fresh slot indices above
+// every slot a DAG already names.  They share one walker so the two cannot
+// disagree on which numbers count as taken.  Behind the `json` feature, since
+// the guest has no JSON.
+
+/// Collect every non-negative integer that fits a `u32` under `v`, a node's
+/// `kind` JSON (or any part of a DAG), into `out`.
+///
+/// `wasm_arg` is skipped: it is a guest-side function parameter, not a slot —
+/// e.g. the SGD workloads pack `input_slot | output_slot << 16` into it, a
+/// huge integer that would push every freshly allocated slot out of range.
+#[cfg(feature = "json")]
+pub fn collect_dag_slots(v: &serde_json::Value, out: &mut alloc::collections::BTreeSet<u32>) {
+    use serde_json::Value;
+    match v {
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64().and_then(|u| u32::try_from(u).ok()) {
+                out.insert(u);
+            }
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                collect_dag_slots(item, out);
+            }
+        }
+        Value::Object(map) => {
+            for (k, val) in map {
+                if k == "wasm_arg" {
+                    continue;
+                }
+                collect_dag_slots(val, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+// ─── Typed records ───────────────────────────────────────────────────────────
+//
+// An optional typed layer over plain byte records.  A typed record's payload
+// is `[schema tag: u32 LE][body]`: the body is the postcard encoding of the
+// value (varint integers, zigzag for signed ones, length-prefixed strings,
+// fields in declaration order) and the tag is the FNV-1a hash of the value's
+// schema descriptor, e.g.
+//
+//   WordCount{word:str,count:u64}
+//
+// Field types: `bool`, `u8`…`u64`, `i8`…`i64`, `f32`, `f64`, `str`, `bytes`,
+// `opt<T>` and `seq<T>`.  Guests encode with serde (`api::typed`); the host
+// decodes by walking the descriptor (`input_output::typed_record`), so it can
+// render records of any schema.  The tag travels with every record, so it
+// survives `Aggregate`, remote transfer and spilling, which move pages rather
+// than slots: a consumer expecting another schema fails on the first record
+// instead of parsing garbage.
+
+/// Bytes of the schema tag in front of a typed record's body.
+pub const SCHEMA_TAG_SIZE: usize = 4;
+
+/// 32-bit FNV-1a over `bytes`.
+pub const fn fnv1a_32(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+/// Tag of the schema `descriptor`.
+pub const fn schema_tag(descriptor: &str) -> u32 {
+    fnv1a_32(descriptor.as_bytes())
+}
+
+/// Schema tag and body of a typed record payload; `None` when it is too
+/// short to carry a tag.
+pub fn split_typed_record(payload: &[u8]) -> Option<(u32, &[u8])> {
+    let tag = payload.get(..SCHEMA_TAG_SIZE)?;
+    Some((u32::from_le_bytes([tag[0], tag[1], tag[2], tag[3]]), &payload[SCHEMA_TAG_SIZE..]))
+}
+
+/// Records `wc_map` emits and `wc_reduce` (or `host merge wordcount`) folds.
+pub const WORD_COUNT_SCHEMA: &str = "WordCount{word:str,count:u64}";
+
+// ─── Engine configuration ────────────────────────────────────────────────────
+
+/// Engine tunables that used to be compile-time constants.
+///
+/// The DAG runner resolves one of these at startup (defaults, then an optional
+/// TOML file, the DAG's `engine` block and `WEBS_*` environment variables — see
+/// `host/src/runtime/engine_config.rs`) and `format_shared_memory` stores it in
+/// [`Superblock::engine`].  Worker subprocesses and guests read it from there
+/// instead of from the constants, which only supply [`EngineConfig::DEFAULT`].
+///
+/// Flags are `u32` (0 or 1) so the block has the same layout in every language
+/// that reads the Superblock.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Extended-pool paged-mode allocation (default [`EXTENDED_POOL_ENABLED`]).
+    pub extended_pool_enabled: u32,
+    /// RDMA MR2 overflow (default [`EXTENDED_RDMA_ENABLED`]).
+    pub extended_rdma_enabled: u32,
+    /// `Direct → Paged` flip at `bump / DIRECT_LIMIT >= enter_num / enter_den`.
+    pub paged_mode_enter_num: u32,
+    pub paged_mode_enter_den: u32,
+    /// `Paged → Direct` once nothing is paged and `bump / DIRECT_LIMIT <
+    /// exit_num / exit_den`.
+    pub paged_mode_exit_num: u32,
+    pub paged_mode_exit_den: u32,
+    /// Huge-page backing: one of the `HUGE_PAGES_*` modes (default [`HUGE_PAGES`]).
+    pub huge_pages: u32,
+    /// Format-time geometry (defaults `DEFAULT_*`): slot and barrier counts
+    /// and arena sizes.  `format_shared_memory` lays the region out with
+    /// [`ShmLayout::for_config`]; attaching processes read the result from
+    /// [`Superblock::layout`], not from here.
+    pub stream_slot_count: u32,
+    pub io_slot_count: u32,
+    pub barrier_count: u32,
+    pub registry_size: u32,
+    pub atomic_arena_size: u32,
+    pub log_arena_size: u32,
+    /// Keeps the `u64` fields 8-aligned; always zero.
+    pub reserved: u32,
+    /// Free-list page count above which `trim_free_list` releases memory.
+    pub free_list_trim_threshold: u64,
+    pub rdma_mr1_budget: u64,
+    pub rdma_mr2_initial_size: u64,
+    pub mr2_idle_timeout_nanos: u64,
+    pub global_pool_initial_size: u64,
+    pub flip_seed_slots: u64,
+}
+
+const _: () = assert!(core::mem::size_of::<EngineConfig>() == 104);
+
+impl EngineConfig {
+    /// The compile-time constants.
+    pub const DEFAULT: EngineConfig = EngineConfig {
+        extended_pool_enabled: EXTENDED_POOL_ENABLED as u32,
+        extended_rdma_enabled: EXTENDED_RDMA_ENABLED as u32,
+        paged_mode_enter_num: PAGED_MODE_ENTER_NUM as u32,
+        paged_mode_enter_den: PAGED_MODE_ENTER_DEN as u32,
+        paged_mode_exit_num: PAGED_MODE_EXIT_NUM as u32,
+        paged_mode_exit_den: PAGED_MODE_EXIT_DEN as u32,
+        huge_pages: HUGE_PAGES,
+        stream_slot_count: DEFAULT_STREAM_SLOT_COUNT as u32,
+        io_slot_count: DEFAULT_IO_SLOT_COUNT as u32,
+        barrier_count: DEFAULT_BARRIER_COUNT as u32,
+        registry_size: DEFAULT_REGISTRY_SIZE,
+        atomic_arena_size: DEFAULT_ATOMIC_ARENA_SIZE,
+        log_arena_size: DEFAULT_LOG_ARENA_SIZE,
+        reserved: 0,
+        free_list_trim_threshold: FREE_LIST_TRIM_THRESHOLD as u64,
+        rdma_mr1_budget: RDMA_MR1_BUDGET,
+        rdma_mr2_initial_size: RDMA_MR2_INITIAL_SIZE,
+        mr2_idle_timeout_nanos: MR2_IDLE_TIMEOUT_NANOS,
+        global_pool_initial_size: GLOBAL_POOL_INITIAL_SIZE,
+        flip_seed_slots: FLIP_SEED_SLOTS,
+    };
+
+    pub fn extended_pool(&self) -> bool { self.extended_pool_enabled != 0 }
+    pub fn extended_rdma(&self) -> bool { self.extended_rdma_enabled != 0 }
+
+    /// Granularity of SHM capacity changes: [`HUGE_PAGE_SIZE`] with huge
+    /// pages on, else [`PAGE_SIZE`].
+    pub fn shm_granularity(&self) -> ShmOffset {
+        if self.huge_pages == HUGE_PAGES_OFF { PAGE_SIZE } else { HUGE_PAGE_SIZE }
+    }
+
+    /// Rejects settings the allocators cannot work with: zero denominators,
+    /// fractions above 1, an exit threshold that is not below the entry
+    /// threshold (the FSM would flap), zero sizes, and a region geometry
+    /// that does not fit below the initial SHM capacity.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.extended_pool_enabled > 1 || self.extended_rdma_enabled > 1 {
+            return Err("extended_pool_enabled / extended_rdma_enabled must be 0 or 1");
+        }
+        if self.paged_mode_enter_den == 0 || self.paged_mode_exit_den == 0 {
+            return Err("paged_mode_*_den must be non-zero");
+        }
+        if self.paged_mode_enter_num > self.paged_mode_enter_den
+            || self.paged_mode_exit_num > self.paged_mode_exit_den
+        {
+            return Err("paged_mode_*_num must not exceed its denominator");
+        }
+        // exit_num / exit_den < enter_num / enter_den, cross-multiplied.
+        if self.paged_mode_exit_num as u64 * self.paged_mode_enter_den as u64
+            >= self.paged_mode_enter_num as u64 * self.paged_mode_exit_den as u64
+        {
+            return Err("paged_mode_exit fraction must be below paged_mode_enter");
+        }
+        if self.rdma_mr1_budget == 0 || self.rdma_mr2_initial_size == 0 || self.global_pool_initial_size == 0 {
+            return Err("rdma_mr1_budget, rdma_mr2_initial_size and global_pool_initial_size must be non-zero");
+        }
+        if self.rdma_mr2_initial_size > RDMA_MR2_HARD_LIMIT {
+            return Err("rdma_mr2_initial_size exceeds RDMA_MR2_HARD_LIMIT");
+        }
+        if self.huge_pages > HUGE_PAGES_HUGETLBFS {
+            return Err("huge_pages must be 0 (off), 1 (THP) or 2 (hugetlbfs)");
+        }
+        if self.huge_pages == HUGE_PAGES_HUGETLBFS && self.extended_pool_enabled != 0 {
+            return Err("huge_pages = 2 (hugetlbfs) requires extended_pool_enabled = false: \
+                        the resolution buffer maps 4 KiB pages into the SHM window");
+        }
+        if self.huge_pages != HUGE_PAGES_OFF
+            && !(self.rdma_mr2_initial_size.is_multiple_of(HUGE_PAGE_SIZE as u64)
+                && self.global_pool_initial_size.is_multiple_of(HUGE_PAGE_SIZE as u64))
+        {
+            return Err("with huge pages on, rdma_mr2_initial_size and global_pool_initial_size \
+                        must be multiples of HUGE_PAGE_SIZE");
+        }
+        if self.flip_seed_slots == 0 {
+            return Err("flip_seed_slots must be non-zero");
+        }
+        if self.stream_slot_count == 0 || self.barrier_count == 0 {
+            return Err("stream_slot_count and barrier_count must be non-zero");
+        }
+        if self.io_slot_count <= OUTPUT_IO_SLOT {
+            return Err("io_slot_count must cover INPUT_IO_SLOT and OUTPUT_IO_SLOT");
+        }
+        let arenas = [self.registry_size, self.atomic_arena_size, self.log_arena_size];
+        if arenas.iter().any(|&size| size == 0 || !size.is_multiple_of(PAGE_SIZE)) {
+            return Err("registry_size, atomic_arena_size and log_arena_size must be \
+                        non-zero multiples of PAGE_SIZE");
+        }
+        let header = slot_table_end(self.stream_slot_count, self.io_slot_count, self.barrier_count)
+            .next_multiple_of(PAGE_SIZE as u64);
+        let bump_start = header
+            + RDMA_SCRATCH_SIZE as u64
+            + arenas.iter().map(|&size| size as u64).sum::<u64>();
+        if bump_start + PAGE_SIZE as u64 > INITIAL_SHM_SIZE as u64 {
+            return Err("slot tables and arenas leave no room for pages in INITIAL_SHM_SIZE");
+        }
+        Ok(())
+    }
+}
 
 #[repr(C)]
 pub struct ChainNodeHeader {
@@ -397,7 +1541,11 @@ pub struct ChainNodeHeader {
     pub writer_id: u32,
     pub data_len: u32,
     pub registry_index: u32,
-    // 4 bytes of implicit padding to 8-align `next_payload_page`.
+    /// [`SHARED_STATE_TAG`] on entries written by `write_shared_state`
+    /// (resolved per registry key by `ResolveSharedState`), `0` on raw
+    /// keyed entries from `insert_shared_data`, which carry no payload
+    /// chain and are never resolved.
+    pub tag: u32,
     /// Next page in this entry's payload chain.  Overflow pages carry a
     /// bare [`PageId`] at byte 0 as their own next-pointer (see
     /// `guest/src/api/shared_area.rs`), so widening here must stay in sync
@@ -405,12 +1553,234 @@ pub struct ChainNodeHeader {
     pub next_payload_page: PageId,
 }
 
+/// [`ChainNodeHeader::tag`] of a consensus (`write_shared_state`) entry.
+pub const SHARED_STATE_TAG: u32 = u32::from_le_bytes(*b"STAT");
+
 // ChainNodeHeader after widening: next_node u64 @ 0, writer_id @ 8,
-// data_len @ 12, registry_index @ 16, (4 pad), next_payload_page @ 24.
+// data_len @ 12, registry_index @ 16, tag @ 20, next_payload_page @ 24.
 // Total size = 32 bytes (was 20).
 const _: () = assert!(core::mem::size_of::<ChainNodeHeader>() == 32);
+const _: () = assert!(core::mem::offset_of!(ChainNodeHeader, tag) == 20);
 const _: () = assert!(core::mem::offset_of!(ChainNodeHeader, next_payload_page) == 24);
 
+// ─── Key-value store ─────────────────────────────────────────────────────────
+//
+// A concurrent hash map in the SHM keyed by full byte keys.  Two levels of
+// directory pages lead to the buckets:
+//
+//   Superblock::kv_directory → root page  [PageId; KV_FANOUT]
+//                            → leaf page  [PageId; KV_FANOUT]   (bucket heads)
+//                            → entry → entry → …                (newest first)
+//
+// Directory pages are created on first write, zero-filled and published with
+// a CAS.  An entry page starts with a `KvEntryHeader`, followed by the key and
+// then the value; bytes that do not fit continue on overflow pages that carry
+// their next PageId at byte 0, like shared-state payloads.
+//
+// Entries are never modified once published.  A put or delete prepends a new
+// entry (a tombstone for a delete) with one CAS on the bucket head, so the
+// first entry for a key in its bucket holds its current value.  A
+// compare-and-put reads the current value and CASes against the head it read,
+// so any concurrent write to the same bucket makes it retry.  Shadowed entries
+// and tombstones keep their pages until the host compacts the store between
+// DAG nodes (`FreeSlots` with `kv: true`), when no guest can be walking a chain.
+
+/// PageIds per directory page.
+pub const KV_FANOUT: usize = PAGE_SIZE as usize / PAGE_ID_SIZE;
+
+/// Buckets reachable through a full directory.
+pub const KV_BUCKET_COUNT: usize = KV_FANOUT * KV_FANOUT;
+
+/// [`KvEntryHeader::flags`] of a delete entry.
+pub const KV_TOMBSTONE: u32 = 1;
+
+#[repr(C)]
+pub struct KvEntryHeader {
+    /// Next (older) entry of the bucket.
+    pub next_entry: AtomicPageId,
+    /// [`kv_hash`] of the key.
+    pub hash: u32,
+    pub key_len: u32,
+    pub value_len: u32,
+    /// [`KV_TOMBSTONE`] or `0`.
+    pub flags: u32,
+    /// First overflow page of the key and value bytes.  At the same offset as
+    /// [`ChainNodeHeader::next_payload_page`], so one payload walker serves
+    /// both kinds of entry.
+    pub next_payload_page: PageId,
+}
+
+const _: () = assert!(core::mem::size_of::<KvEntryHeader>() == 32);
+const _: () = assert!(core::mem::offset_of!(KvEntryHeader, next_payload_page)
+    == core::mem::offset_of!(ChainNodeHeader, next_payload_page));
+
+/// Hash of a key-value store key.
+pub const fn kv_hash(key: &[u8]) -> u32 {
+    fnv1a_32(key)
+}
+
+/// Root and leaf directory index of the bucket for `hash`.
+pub const fn kv_bucket(hash: u32) -> (usize, usize) {
+    let bucket = hash as usize % KV_BUCKET_COUNT;
+    (bucket / KV_FANOUT, bucket % KV_FANOUT)
+}
+
+// ─── Ring slots ──────────────────────────────────────────────────────────────
+//
+// A stream slot declared with a `ring` is a bounded ring of `capacity` pages
+// instead of a chain that grows until the slot is freed.
+// `Superblock::stream_ring(slot)` holds the offset of a zero-filled page with
+// the slot's `RingHeader`; its pages are allocated once, in the slot's page
+// class, when the DAG runner applies the declaration.
+//
+// Pages are numbered by a sequence that only grows: page `seq` lives in
+// `pages[seq % capacity]` and holds whole `[len][origin][payload]` records,
+// published through `Page::cursor` as in a chain.  Records never span pages.
+// The single producer fills page `tail_seq`; when a record does not fit it
+// starts page `tail_seq + 1`, resetting that page's cursor and then
+// publishing `tail_seq`.  The page last held sequence `tail_seq + 1 -
+// capacity`, so it may be reused only once every consumer's cursor has moved
+// past it (`min_consumer_seq() + capacity > next`).  Otherwise the ring is
+// full: the producer fails, or sleeps on `space` until a consumer moves on.
+//
+// Each of the `consumer_count` consumers owns a cursor and reads every
+// record.  It leaves page `page_seq` only after seeing `tail_seq` move past
+// it, then bumps `space` (stream-signal encoding) to wake a blocked producer.
+// An idle consumer sleeps on the slot's stream signal, which the producer
+// bumps after each record like an ordinary stream append.
+
+/// Consumer cursors in a [`RingHeader`].
+pub const RING_MAX_CONSUMERS: usize = 16;
+
+/// Pages a ring can hold; bounded by the header page.
+pub const RING_MAX_PAGES: usize = 448;
+
+#[repr(C)]
+pub struct RingCursor {
+    /// Sequence of the page the consumer is reading.
+    pub page_seq: AtomicU64,
+    /// Byte offset of the consumer's next record in that page.
+    pub offset: AtomicU32,
+    pub _reserved: u32,
+}
+
+#[repr(C)]
+pub struct RingHeader {
+    /// Pages in the ring (`2..=RING_MAX_PAGES`).
+    pub capacity: u32,
+    /// Consumers that read every record (`1..=RING_MAX_CONSUMERS`).
+    pub consumer_count: u32,
+    /// Sequence of the page the producer is filling.
+    pub tail_seq: AtomicU64,
+    /// Futex word a producer waiting for space sleeps on, in the encoding of
+    /// the stream signals (see "Stream signals").
+    pub space: AtomicU32,
+    pub _reserved: u32,
+    pub consumers: [RingCursor; RING_MAX_CONSUMERS],
+    /// PageIds of the ring's pages; the first `capacity` are used.
+    pub pages: [AtomicPageId; RING_MAX_PAGES],
+}
+
+impl RingHeader {
+    /// Lowest page sequence any consumer is still reading.
+    #[inline]
+    pub fn min_consumer_seq(&self) -> u64 {
+        self.consumers[..self.consumer_count as usize]
+            .iter()
+            .map(|c| c.page_seq.load(Ordering::Acquire))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Whether the producer may start page `next`: the page it reuses has
+    /// been left by every consumer.
+    #[inline]
+    pub fn has_space_for(&self, next: u64) -> bool {
+        self.min_consumer_seq() + self.capacity as u64 > next
+    }
+
+    /// PageId of page sequence `seq`.
+    #[inline]
+    pub fn page(&self, seq: u64) -> PageId {
+        self.pages[(seq % self.capacity as u64) as usize].load(Ordering::Relaxed)
+    }
+}
+
+const _: () = assert!(core::mem::size_of::<RingCursor>() == 16);
+const _: () = assert!(core::mem::size_of::<RingHeader>() <= PAGE_SIZE as usize);
+const _: () = assert!(core::mem::offset_of!(RingHeader, consumers) == 24);
+
+// ─── Stream subscriptions ────────────────────────────────────────────────────
+//
+// A stream slot declared with `subscribers` keeps one named read cursor per
+// subscriber in SHM, so several consumers can each read every record of the
+// slot's ordinary chain at their own pace.
+// `Superblock::stream_subscriptions(slot)` holds the offset of a zero-filled
+// page with the slot's `SubscriptionTable`, allocated when the DAG runner
+// applies the declaration.
+//
+// A cursor is the PageId of the page holding the subscriber's next record and
+// the byte offset of that record in the page's data; `page == 0` means the
+// head of the chain (nothing read yet, or the slot has been freed since).
+// Records span pages as in any chain.  A subscriber moves its cursor only
+// after reading a whole record, and only forward, so it never touches a page
+// before its cursor page.  Between waves the host frees the chain prefix that
+// lies before every cursor and moves `writer_head` to the earliest cursor
+// page; the producer keeps appending at the tail.
+
+/// Subscribers a [`SubscriptionTable`] can hold.
+pub const SUBSCRIBER_MAX: usize = 32;
+
+/// Bytes of a subscriber name, NUL-padded.
+pub const SUBSCRIBER_NAME_LEN: usize = 48;
+
+#[repr(C)]
+pub struct SubscriberCursor {
+    /// Name from the slot declaration, NUL-padded.
+    pub name: [u8; SUBSCRIBER_NAME_LEN],
+    /// PageId of the page holding the next record; `0` for the chain head.
+    pub page: AtomicPageId,
+    /// Byte offset of the next record in that page's data.
+    pub offset: AtomicU32,
+    pub _reserved: u32,
+    /// Records read since the slot was last freed.
+    pub records: AtomicU64,
+}
+
+#[repr(C)]
+pub struct SubscriptionTable {
+    /// Subscribers in use (`1..=SUBSCRIBER_MAX`).
+    pub count: u32,
+    pub _reserved: u32,
+    pub subscribers: [SubscriberCursor; SUBSCRIBER_MAX],
+}
+
+impl SubscriptionTable {
+    /// The subscribers in use.
+    #[inline]
+    pub fn active(&self) -> &[SubscriberCursor] {
+        &self.subscribers[..(self.count as usize).min(SUBSCRIBER_MAX)]
+    }
+
+    /// Index of the subscriber called `name`.
+    pub fn find(&self, name: &[u8]) -> Option<usize> {
+        self.active().iter().position(|s| s.name() == name)
+    }
+}
+
+impl SubscriberCursor {
+    /// The name without its NUL padding.
+    #[inline]
+    pub fn name(&self) -> &[u8] {
+        let len = self.name.iter().position(|&b| b == 0).unwrap_or(SUBSCRIBER_NAME_LEN);
+        &self.name[..len]
+    }
+}
+
+const _: () = assert!(core::mem::size_of::<SubscriberCursor>() == 72);
+const _: () = assert!(core::mem::size_of::<SubscriptionTable>() <= PAGE_SIZE as usize);
+const _: () = assert!(core::mem::offset_of!(SubscriptionTable, subscribers) == 8);
+
 #[repr(C)]
 pub struct RegistryEntry {
     pub name: [u8; 52],
@@ -1,7 +1,7 @@
 // shm_put_probe — isolate WHY the engine page-chain PUT is ~2x slower than a flat
 // contiguous memcpy, by sweeping the three suspects independently:
 //
-//   1. chunk size   — the page-chain writes in PAGE_DATA_SIZE (4084 B) spans;
+//   1. chunk size   — the page-chain writes in PAGE_DATA_SIZE (4080 B) spans;
 //                     a flat copy writes the whole payload in one span.
 //   2. header gaps  — a 12-byte page header sits between every data span, so the
 //                     write destination is non-contiguous.
@@ -21,7 +21,7 @@ use std::time::Instant;
 
 use anyhow::{bail, Result};
 
-const GAP: usize = 12; // page header bytes between data spans (next_offset u64 + cursor u32)
+const GAP: usize = 16; // page header bytes between data spans (next_offset u64 + cursor u32 + class u32)
 
 #[derive(Clone, Copy, PartialEq)]
 enum Fence {
@@ -98,8 +98,8 @@ fn main() -> Result<()> {
     let iters = flag(&args, "--iters").and_then(|s| s.parse().ok()).unwrap_or(20);
     let warmup = flag(&args, "--warmup").and_then(|s| s.parse().ok()).unwrap_or(5);
 
-    // Page-data sizes to sweep.  4084 = the real engine page (PAGE_DATA_SIZE).
-    let chunks = [4084usize, 16 * 1024, 64 * 1024, 256 * 1024,
+    // Page-data sizes to sweep.  4080 = the real engine page (PAGE_DATA_SIZE).
+    let chunks = [4080usize, 16 * 1024, 64 * 1024, 256 * 1024,
                   1024 * 1024, 4 * 1024 * 1024, 16 * 1024 * 1024, 64 * 1024 * 1024];
 
     // Map a region big enough for the worst case (smallest chunk = most gaps).
@@ -129,11 +129,11 @@ fn main() -> Result<()> {
         let gap     = unsafe { bench(dst, &payload, c, GAP, Fence::None,    iters, warmup) };
         let relaxed = unsafe { bench(dst, &payload, c, GAP, Fence::Relaxed, iters, warmup) };
         let release = unsafe { bench(dst, &payload, c, GAP, Fence::Release, iters, warmup) };
-        let tag = if c == 4084 { " (engine)" } else { "" };
+        let tag = if c == 4080 { " (engine)" } else { "" };
         println!("  {:<10}{:>14.3}{:>14.3}{:>16.3}{:>16.3}{}", fmt_chunk(c), nogap, gap, relaxed, release, tag);
     }
     println!("\nRead across a row: chunk-size effect.  Compare columns: gap and fence effects.");
-    println!("4084 B + gap + RELEASE ≈ the current engine PUT; 'flat' ≈ the modelled mmap PUT.");
+    println!("4080 B + gap + RELEASE ≈ the current engine PUT; 'flat' ≈ the modelled mmap PUT.");
 
     unsafe { libc::munmap(base, region); }
     Ok(())
@@ -42,7 +42,7 @@ use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::time::Instant;
 
 use anyhow::{bail, Result};
-use common::{PageId, ShmOffset, Superblock, BUMP_ALLOCATOR_START};
+use common::{PageId, ShmLayout, ShmOffset, Superblock};
 
 // Backing file large enough to hold the biggest single payload's page chain
 // plus the fixed arenas below the bump start.  Only one payload lives at a time
@@ -65,10 +65,10 @@ enum Mode {
 // ── SHM bring-up ────────────────────────────────────────────────────────────
 
 // Page header byte offsets — mirror common::Page (asserted in common/src/lib.rs):
-//   next_offset u64 @ 0,  cursor u32 @ 8,  data @ 12.
+//   next_offset u64 @ 0,  cursor u32 @ 8,  class u32 @ 12,  data @ 16.
 const PG_NEXT: usize = 0;
 const PG_CURSOR: usize = 8;
-const PG_DATA: usize = 12;
+const PG_DATA: usize = 16;
 
 struct Shm {
     base: usize,
@@ -113,11 +113,13 @@ impl Shm {
             page_data: page_bytes - PG_DATA,
         };
 
-        // Format the superblock: magic + bump start + capacity.  Every other
-        // field (slot head/tail arrays, free list) is zero from the fresh map.
+        // Format the superblock: magic + default geometry + bump start +
+        // capacity.  Every other field (slot head/tail tables, free list) is
+        // zero from the fresh map.
         let sb = shm.sb();
         sb.magic = 0xDEAD_BEEF;
-        sb.bump_allocator.store(BUMP_ALLOCATOR_START, Ordering::Release);
+        sb.layout = ShmLayout::DEFAULT;
+        sb.bump_allocator.store(sb.layout.bump_allocator_start, Ordering::Release);
         sb.global_capacity.store(SHM_BYTES as ShmOffset, Ordering::Release);
         Ok(shm)
     }
@@ -155,13 +157,13 @@ impl Shm {
     /// single-key overwrite).  Resetting dst slots clears any prior splice.
     fn reset(&self, readers: usize) {
         let sb = self.sb();
-        sb.writer_heads[SRC_SLOT].store(0, Ordering::Release);
-        sb.writer_tails[SRC_SLOT].store(0, Ordering::Release);
+        sb.writer_head(SRC_SLOT).store(0, Ordering::Release);
+        sb.writer_tail(SRC_SLOT).store(0, Ordering::Release);
         for r in 0..readers {
-            sb.writer_heads[DST_SLOT_BASE + r].store(0, Ordering::Release);
-            sb.writer_tails[DST_SLOT_BASE + r].store(0, Ordering::Release);
+            sb.writer_head(DST_SLOT_BASE + r).store(0, Ordering::Release);
+            sb.writer_tail(DST_SLOT_BASE + r).store(0, Ordering::Release);
         }
-        sb.bump_allocator.store(BUMP_ALLOCATOR_START, Ordering::Release);
+        sb.bump_allocator.store(sb.layout.bump_allocator_start, Ordering::Release);
     }
 
     // ── PUT: append [len u32][origin u32][payload] to the slot's page chain ──
@@ -177,11 +179,11 @@ impl Shm {
             return Ok(());
         }
         let sb = self.sb();
-        let mut tail: PageId = sb.writer_tails[slot].load(Ordering::Acquire);
+        let mut tail: PageId = sb.writer_tail(slot).load(Ordering::Acquire);
         if tail == 0 {
             tail = self.alloc_page()?;
-            sb.writer_heads[slot].store(tail, Ordering::Release);
-            sb.writer_tails[slot].store(tail, Ordering::Release);
+            sb.writer_head(slot).store(tail, Ordering::Release);
+            sb.writer_tail(slot).store(tail, Ordering::Release);
         }
         while !data.is_empty() {
             let cursor = self.cursor_at(tail).load(Ordering::Relaxed) as usize;
@@ -189,7 +191,7 @@ impl Shm {
             if space == 0 {
                 let next = self.alloc_page()?;
                 self.next_at(tail).store(next, Ordering::Release);
-                sb.writer_tails[slot].store(next, Ordering::Release);
+                sb.writer_tail(slot).store(next, Ordering::Release);
                 tail = next;
                 continue;
             }
@@ -228,15 +230,15 @@ impl Shm {
             }
             prev = id;
         }
-        sb.writer_heads[slot].store(head, Ordering::Release);
-        sb.writer_tails[slot].store(head, Ordering::Release);
+        sb.writer_head(slot).store(head, Ordering::Release);
+        sb.writer_tail(slot).store(head, Ordering::Release);
         Ok(())
     }
 
     /// Zero every page's cursor in `slot`'s pre-allocated chain (untimed reset
     /// between iterations) — keeps the next_offset links intact.
     fn reset_cursors(&self, slot: usize) {
-        let mut id = self.sb().writer_heads[slot].load(Ordering::Acquire);
+        let mut id = self.sb().writer_head(slot).load(Ordering::Acquire);
         while id != 0 {
             self.cursor_at(id).store(0, Ordering::Release);
             id = self.next_at(id).load(Ordering::Acquire);
@@ -247,8 +249,8 @@ impl Shm {
     fn reset_dst(&self, readers: usize) {
         let sb = self.sb();
         for r in 0..readers {
-            sb.writer_heads[DST_SLOT_BASE + r].store(0, Ordering::Release);
-            sb.writer_tails[DST_SLOT_BASE + r].store(0, Ordering::Release);
+            sb.writer_head(DST_SLOT_BASE + r).store(0, Ordering::Release);
+            sb.writer_tail(DST_SLOT_BASE + r).store(0, Ordering::Release);
         }
     }
 
@@ -257,7 +259,7 @@ impl Shm {
     /// cursors.  This is the PUT-minus-allocation cost.
     fn write_record_prealloc(&self, slot: usize, payload: &[u8]) {
         let sb = self.sb();
-        let mut id = sb.writer_heads[slot].load(Ordering::Acquire);
+        let mut id = sb.writer_head(slot).load(Ordering::Acquire);
         let mut cur = 0usize;
         let mut hdr = [0u8; 8];
         hdr[0..4].copy_from_slice(&(payload.len() as u32).to_le_bytes());
@@ -280,13 +282,13 @@ impl Shm {
                 data = &data[n..];
             }
         }
-        sb.writer_tails[slot].store(id, Ordering::Release); // tail = last written page
+        sb.writer_tail(slot).store(id, Ordering::Release); // tail = last written page
     }
 
     // ── GET (copy): materialize the payload out of the chain (one memcpy per
     // page span).  Mirrors persistence::read_chain_records.  Returns bytes. ──
     fn get_copy(&self, slot: usize) -> usize {
-        let head = self.sb().writer_heads[slot].load(Ordering::Acquire);
+        let head = self.sb().writer_head(slot).load(Ordering::Acquire);
         let mut r = ChainReader::new(self, head);
         let mut hdr = [0u8; 8];
         if !r.read(&mut hdr) {
@@ -310,19 +312,19 @@ impl Shm {
     // Returns the delivered byte count (read from the record header, 1 page). ──
     fn chain_onto(&self, dst: usize, src: usize) {
         let sb = self.sb();
-        let src_head = sb.writer_heads[src].load(Ordering::Acquire);
+        let src_head = sb.writer_head(src).load(Ordering::Acquire);
         if src_head == 0 {
             return;
         }
-        let src_tail = sb.writer_tails[src].load(Ordering::Acquire);
+        let src_tail = sb.writer_tail(src).load(Ordering::Acquire);
         std::hint::black_box(src_tail); // keep the loads/splice from being elided
-        let dst_tail = sb.writer_tails[dst].load(Ordering::Acquire);
+        let dst_tail = sb.writer_tail(dst).load(Ordering::Acquire);
         if dst_tail == 0 {
-            sb.writer_heads[dst].store(src_head, Ordering::Release);
+            sb.writer_head(dst).store(src_head, Ordering::Release);
         } else {
             self.next_at(dst_tail).store(src_head, Ordering::Release);
         }
-        sb.writer_tails[dst].store(src_tail, Ordering::Release);
+        sb.writer_tail(dst).store(src_tail, Ordering::Release);
     }
 }
 
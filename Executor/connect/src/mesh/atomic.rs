@@ -5,7 +5,9 @@
 //                   operates on a fixed (result_shm_offset, remote_shm_offset) pair.
 //   MeshNode SHM  — methods that target any 8-byte-aligned offset in the full
 //                   SHM MR (registered via connect_all_on_shm).  Safe to call
-//                   from spawned threads via a shared reference.
+//                   from spawned threads via a shared reference; the runner's
+//                   atomics service (host runtime/remote/atomics.rs) drives
+//                   them on behalf of guests.
 
 use std::sync::atomic;
 
@@ -30,12 +32,12 @@ impl AtomicChannel {
         let result_addr = self.local_mr_base  + result_shm_offset as u64;
         let remote_addr = self.remote_mr_base + remote_shm_offset as u64;
 
-        {
-            let qp = self.qp.lock().unwrap();
-            qp.post_fetch_and_add(result_addr, self.local_lkey,
-                                   remote_addr, self.remote_rkey, add_val)?;
-            qp.poll_one_blocking()?;
-        }
+        // Read the result before releasing the QP: another atomic to this
+        // peer lands in the same scratch slot.
+        let qp = self.qp.lock().unwrap();
+        qp.post_fetch_and_add(result_addr, self.local_lkey,
+                               remote_addr, self.remote_rkey, add_val)?;
+        qp.poll_one_blocking()?;
 
         atomic::fence(atomic::Ordering::Acquire);
         let old = unsafe { (result_addr as *const u64).read_volatile() };
@@ -56,13 +58,13 @@ impl AtomicChannel {
         let result_addr = self.local_mr_base  + result_shm_offset as u64;
         let remote_addr = self.remote_mr_base + remote_shm_offset as u64;
 
-        {
-            let qp = self.qp.lock().unwrap();
-            qp.post_compare_and_swap(result_addr, self.local_lkey,
-                                      remote_addr, self.remote_rkey,
-                                      compare, swap)?;
-            qp.poll_one_blocking()?;
-        }
+        // Read the result before releasing the QP: another atomic to this
+        // peer lands in the same scratch slot.
+        let qp = self.qp.lock().unwrap();
+        qp.post_compare_and_swap(result_addr, self.local_lkey,
+                                  remote_addr, self.remote_rkey,
+                                  compare, swap)?;
+        qp.poll_one_blocking()?;
 
         atomic::fence(atomic::Ordering::Acquire);
         let old = unsafe { (result_addr as *const u64).read_volatile() };
@@ -74,14 +76,16 @@ impl AtomicChannel {
 //
 // These methods target any 8-byte-aligned offset in the full SHM MR
 // (registered via connect_all_on_shm).  The result is written to
-// `result_shm_offset` in OUR local SHM — use common::rdma_scratch_shm_offset
+// `result_shm_offset` in OUR local SHM — use ShmLayout::rdma_scratch_slot_offset
 // to compute a race-free per-(self.id, peer_id) slot.
 //
-// Both methods take &self so they can be called from spawned threads that
+// These methods take &self so they can be called from spawned threads that
 // hold a shared reference to the MeshNode.
 
 impl MeshNode {
     /// RDMA Fetch-and-Add on any 8-byte-aligned location in `peer_id`'s SHM.
+    /// Returns the old value.  None of the `_shm` methods log: guests may
+    /// issue them in a loop, so callers report results themselves.
     pub fn rdma_fetch_add_shm(
         &self,
         peer_id:           usize,
@@ -100,23 +104,14 @@ impl MeshNode {
         let remote_addr = link.remote_mr_base + remote_shm_offset as u64;
         let remote_rkey = link.remote_rkey;
 
-        {
-            let qp = link.qp.lock().unwrap();
-            qp.post_fetch_and_add(result_addr, result_lkey, remote_addr, remote_rkey, add_val)?;
-            qp.poll_one_blocking()?;
-        }
-
-        atomic::fence(atomic::Ordering::Acquire);
-        let off = result_shm_offset as usize;
-        let old = u64::from_ne_bytes(self.mr.as_bytes()[off..off + 8].try_into().unwrap());
-        println!(
-            "[mesh] node {} → node {}: FAA shm+{:#x} += {} (old={})",
-            self.id, peer_id, remote_shm_offset, add_val, old
-        );
-        Ok(old)
+        let qp = link.qp.lock().unwrap();
+        qp.post_fetch_and_add(result_addr, result_lkey, remote_addr, remote_rkey, add_val)?;
+        qp.poll_one_blocking()?;
+        Ok(self.read_result(result_shm_offset))
     }
 
     /// RDMA Compare-and-Swap on any 8-byte-aligned location in `peer_id`'s SHM.
+    /// Returns the old value; the swap happened iff it equals `compare`.
     pub fn rdma_compare_swap_shm(
         &self,
         peer_id:           usize,
@@ -136,26 +131,47 @@ impl MeshNode {
         let remote_addr = link.remote_mr_base + remote_shm_offset as u64;
         let remote_rkey = link.remote_rkey;
 
-        {
-            let qp = link.qp.lock().unwrap();
-            qp.post_compare_and_swap(
-                result_addr, result_lkey,
-                remote_addr, remote_rkey,
-                compare, swap,
-            )?;
-            qp.poll_one_blocking()?;
-        }
+        let qp = link.qp.lock().unwrap();
+        qp.post_compare_and_swap(
+            result_addr, result_lkey,
+            remote_addr, remote_rkey,
+            compare, swap,
+        )?;
+        qp.poll_one_blocking()?;
+        Ok(self.read_result(result_shm_offset))
+    }
+
+    /// Atomic read of the u64 at any 8-byte-aligned location in `peer_id`'s
+    /// SHM: a Fetch-and-Add of 0, so it is ordered with concurrent FAA / CAS
+    /// on the same word.
+    pub fn rdma_read_atomic_shm(
+        &self,
+        peer_id:           usize,
+        remote_shm_offset: ShmOffset,
+        result_shm_offset: ShmOffset,
+    ) -> Result<u64> {
+        assert_eq!(remote_shm_offset % 8, 0, "remote_shm_offset must be 8-byte aligned");
+        assert_eq!(result_shm_offset % 8, 0, "result_shm_offset must be 8-byte aligned");
 
+        let link = self.peers.get(&peer_id)
+            .ok_or_else(|| anyhow!("no connection to peer {}", peer_id))?;
+
+        let result_addr = self.mr.addr() + result_shm_offset as u64;
+        let remote_addr = link.remote_mr_base + remote_shm_offset as u64;
+
+        let qp = link.qp.lock().unwrap();
+        qp.post_fetch_and_add(result_addr, self.mr.lkey(), remote_addr, link.remote_rkey, 0)?;
+        qp.poll_one_blocking()?;
+        Ok(self.read_result(result_shm_offset))
+    }
+
+    /// Old value the NIC wrote to `result_shm_offset`.  Callers hold the
+    /// peer's QP lock, so a concurrent atomic to the same peer — which shares
+    /// the scratch slot — cannot overwrite it first.
+    fn read_result(&self, result_shm_offset: ShmOffset) -> u64 {
         atomic::fence(atomic::Ordering::Acquire);
         let off = result_shm_offset as usize;
-        let old = u64::from_ne_bytes(self.mr.as_bytes()[off..off + 8].try_into().unwrap());
-        let swapped = old == compare;
-        println!(
-            "[mesh] node {} → node {}: CAS shm+{:#x} ({} → {}) {}",
-            self.id, peer_id, remote_shm_offset, compare, swap,
-            if swapped { "swapped" } else { "not swapped" }
-        );
-        Ok(old)
+        u64::from_ne_bytes(self.mr.as_bytes()[off..off + 8].try_into().unwrap())
     }
 
     /// Atomic Fetch-and-Add on a u64 at `byte_offset` inside `peer_id`'s MR slot.
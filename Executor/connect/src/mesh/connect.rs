@@ -223,6 +223,9 @@ impl MeshNode {
             shm_file:      std::sync::Mutex::new(None),
             shm_base:      std::sync::Mutex::new(0),
             python_compat: std::sync::atomic::AtomicBool::new(false),
+            mr2_initial_size:       std::sync::atomic::AtomicU64::new(common::RDMA_MR2_INITIAL_SIZE),
+            mr2_idle_timeout_nanos: std::sync::atomic::AtomicU64::new(common::MR2_IDLE_TIMEOUT_NANOS),
+            mr2_huge_pages: std::sync::atomic::AtomicU32::new(common::HUGE_PAGES),
         })
     }
 }
@@ -19,7 +19,7 @@ use std::collections::HashMap;
 use std::net::TcpStream;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 
 use anyhow::{Context, Result};
 
@@ -163,6 +163,16 @@ pub struct MeshNode {
     /// Set once at DAG startup via `set_python_compat`; atomic only so
     /// the recv threads can read without locking.
     pub(in crate::mesh) python_compat: AtomicBool,
+    /// Initial size of MR2 and of the sender-side staging MR.  Defaults to
+    /// `common::RDMA_MR2_INITIAL_SIZE`; set from the engine configuration
+    /// at DAG startup via `set_mr2_config`.
+    pub(in crate::mesh) mr2_initial_size: AtomicU64,
+    /// Idle time after which MR2 / MR-src-ext / MR-src-stage are torn down.
+    /// Defaults to `common::MR2_IDLE_TIMEOUT_NANOS`.
+    pub(in crate::mesh) mr2_idle_timeout_nanos: AtomicU64,
+    /// Huge-page mode of MR2 (`common::HUGE_PAGES_*`).  Defaults to
+    /// `common::HUGE_PAGES`.
+    pub(in crate::mesh) mr2_huge_pages: AtomicU32,
 }
 
 // ── Channel handle accessors ──────────────────────────────────────────────────
@@ -247,8 +257,12 @@ impl MeshNode {
     ///
     /// Calling this with `at_least == 0` is a no-op if MR2 already exists.
     pub fn ensure_mr2(&self, at_least: usize) -> Result<Mr2Info> {
-        let initial = common::RDMA_MR2_INITIAL_SIZE as usize;
-        let target  = at_least.max(initial);
+        let initial = self.mr2_initial_size.load(Ordering::Acquire) as usize;
+        let huge_pages = self.mr2_huge_pages.load(Ordering::Acquire);
+        let mut target = at_least.max(initial);
+        if huge_pages != common::HUGE_PAGES_OFF {
+            target = target.next_multiple_of(common::HUGE_PAGE_SIZE as usize);
+        }
 
         let mut guard = self.mr2.lock().expect("MR2 mutex poisoned");
         match guard.as_mut() {
@@ -260,18 +274,14 @@ impl MeshNode {
                 // any in-flight RDMA WRITEs would be observed only through
                 // a DestReply we're about to send (and have not sent yet).
                 *guard = None;
-                let pid = std::process::id();
-                let path = PathBuf::from(format!("/dev/shm/webs-rdma-mr2-{pid}"));
-                let storage = mr2::Mr2Storage::new(&self.ctx, path, target)
+                let storage = mr2::Mr2Storage::new(&self.ctx, self.mr2_path(), target, huge_pages)
                     .context("grow MR2")?;
                 let info = storage.info();
                 *guard = Some(storage);
                 Ok(info)
             }
             None => {
-                let pid = std::process::id();
-                let path = PathBuf::from(format!("/dev/shm/webs-rdma-mr2-{pid}"));
-                let storage = mr2::Mr2Storage::new(&self.ctx, path, target)
+                let storage = mr2::Mr2Storage::new(&self.ctx, self.mr2_path(), target, huge_pages)
                     .context("create MR2")?;
                 let info = storage.info();
                 *guard = Some(storage);
@@ -280,6 +290,18 @@ impl MeshNode {
         }
     }
 
+    /// MR2 backing file: tmpfs, or the hugetlbfs directory in hugetlbfs mode.
+    fn mr2_path(&self) -> PathBuf {
+        let pid = std::process::id();
+        let dir = if self.mr2_huge_pages.load(Ordering::Acquire) == common::HUGE_PAGES_HUGETLBFS {
+            std::env::var(common::HUGETLBFS_DIR_ENV)
+                .unwrap_or_else(|_| common::DEFAULT_HUGETLBFS_DIR.to_string())
+        } else {
+            "/dev/shm".to_string()
+        };
+        PathBuf::from(format!("{dir}/webs-rdma-mr2-{pid}"))
+    }
+
     /// Reserve a region inside MR2 for one RDMA receive.  Caller must have
     /// called `ensure_mr2` first with a size that covers this reservation.
     ///
@@ -320,11 +342,11 @@ impl MeshNode {
     }
 
     /// Drop MR2 if it exists and has been idle longer than the configured
-    /// timeout (`common::MR2_IDLE_TIMEOUT_NANOS`).  Called at the top of
+    /// timeout (`mr2_idle_timeout_nanos`, see `set_mr2_config`).  Called at the top of
     /// `recv_si` / `send_si` so pinning is returned without a background
     /// thread.
     pub fn mr2_try_shrink_idle(&self) {
-        let timeout = std::time::Duration::from_nanos(common::MR2_IDLE_TIMEOUT_NANOS);
+        let timeout = self.mr2_idle_timeout();
         let mut guard = match self.mr2.lock() {
             Ok(g) => g,
             Err(_) => return,
@@ -416,7 +438,7 @@ impl MeshNode {
 
     /// Tear down MR-src-ext if idle for the configured timeout.
     pub fn src_ext_try_shrink_idle(&self) {
-        let timeout = std::time::Duration::from_nanos(common::MR2_IDLE_TIMEOUT_NANOS);
+        let timeout = self.mr2_idle_timeout();
         let mut guard = match self.src_ext.lock() {
             Ok(g) => g,
             Err(_) => return,
@@ -448,7 +470,7 @@ impl MeshNode {
                     // Stage is full — drop it, double the size, re-create.
                     let doubled = stage.len().saturating_mul(2);
                     let new_len = doubled.max(src_bytes.len())
-                                         .max(common::RDMA_MR2_INITIAL_SIZE as usize);
+                                         .max(self.mr2_initial_size.load(Ordering::Acquire) as usize);
                     *guard = None;
                     let pid = std::process::id();
                     let path = PathBuf::from(format!("/dev/shm/webs-rdma-src-stage-{pid}"));
@@ -460,7 +482,7 @@ impl MeshNode {
                     let pid = std::process::id();
                     let path = PathBuf::from(format!("/dev/shm/webs-rdma-src-stage-{pid}"));
                     let initial = src_bytes.len()
-                        .max(common::RDMA_MR2_INITIAL_SIZE as usize);
+                        .max(self.mr2_initial_size.load(Ordering::Acquire) as usize);
                     let storage = src_mr::SrcStageStorage::new(&self.ctx, path, initial)?;
                     *guard = Some(storage);
                 }
@@ -479,7 +501,7 @@ impl MeshNode {
 
     /// Tear down MR-src-stage if idle for the configured timeout.
     pub fn src_stage_try_shrink_idle(&self) {
-        let timeout = std::time::Duration::from_nanos(common::MR2_IDLE_TIMEOUT_NANOS);
+        let timeout = self.mr2_idle_timeout();
         let mut guard = match self.src_stage.lock() {
             Ok(g) => g,
             Err(_) => return,
@@ -510,6 +532,21 @@ impl MeshNode {
         self.python_compat.load(Ordering::Acquire)
     }
 
+    // ── MR2 sizing ──────────────────────────────────────────────────────────
+
+    /// Override the MR2 initial size, idle timeout and huge-page mode (engine
+    /// configuration `rdma_mr2_initial_size` / `mr2_idle_timeout_nanos` /
+    /// `huge_pages`).  Set once at DAG startup, before any transfer.
+    pub fn set_mr2_config(&self, initial_size: u64, idle_timeout_nanos: u64, huge_pages: u32) {
+        self.mr2_initial_size.store(initial_size, Ordering::Release);
+        self.mr2_idle_timeout_nanos.store(idle_timeout_nanos, Ordering::Release);
+        self.mr2_huge_pages.store(huge_pages, Ordering::Release);
+    }
+
+    fn mr2_idle_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_nanos(self.mr2_idle_timeout_nanos.load(Ordering::Acquire))
+    }
+
     // ── SHM capacity growth ─────────────────────────────────────────────────
 
     /// Ensure the SHM direct window covers `required` bytes.  If the current
@@ -557,6 +594,10 @@ impl MeshNode {
         } else {
             common::CAPACITY_HARD_LIMIT as usize
         };
+        // Sizes move in whole huge pages when the region was formatted with
+        // them on.
+        let granularity = sb.engine.shm_granularity() as usize;
+        let hard_limit = hard_limit - hard_limit % granularity;
         if required > hard_limit {
             return Err(anyhow::anyhow!(
                 "ensure_shm_capacity: required {} exceeds hard limit {} (python_compat={})",
@@ -566,7 +607,9 @@ impl MeshNode {
         }
 
         // Grow geometrically to keep the amortized cost low.
-        let new_size = (current.saturating_mul(2)).max(required).min(hard_limit);
+        let new_size = (current.saturating_mul(2)).max(required)
+            .next_multiple_of(granularity)
+            .min(hard_limit);
         file.set_len(new_size as u64)
             .with_context(|| format!("set_len SHM to {}", new_size))?;
 
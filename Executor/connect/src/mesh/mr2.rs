@@ -75,7 +75,10 @@ unsafe impl Send for Mr2Storage {}
 unsafe impl Sync for Mr2Storage {}
 
 impl Mr2Storage {
-    pub(in crate::mesh) fn new(ctx: &RdmaContext, path: PathBuf, size: usize) -> Result<Self> {
+    /// `huge_pages` is the engine's `common::HUGE_PAGES_*` mode: with THP
+    /// the mapping is advised `MADV_HUGEPAGE`; with hugetlbfs `path` is on
+    /// a hugetlbfs mount and `size` a multiple of `common::HUGE_PAGE_SIZE`.
+    pub(in crate::mesh) fn new(ctx: &RdmaContext, path: PathBuf, size: usize, huge_pages: u32) -> Result<Self> {
         if size == 0 {
             return Err(anyhow!("Mr2Storage size must be non-zero"));
         }
@@ -100,6 +103,11 @@ impl Mr2Storage {
         if base == libc::MAP_FAILED {
             return Err(anyhow!("mmap MR2 backing file: {}", std::io::Error::last_os_error()));
         }
+        if huge_pages == common::HUGE_PAGES_THP
+            && unsafe { libc::madvise(base, size, libc::MADV_HUGEPAGE) } != 0
+        {
+            return Err(anyhow!("madvise MR2 MADV_HUGEPAGE: {}", std::io::Error::last_os_error()));
+        }
         let base = base as *mut u8;
 
         let mr = MemoryRegion::register_external(ctx, base, size)
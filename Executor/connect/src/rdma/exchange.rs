@@ -171,6 +171,9 @@ pub fn recv_shm_offset(stream: &mut TcpStream) -> Result<common::ShmOffset> {
 //
 //   SingleMr — page chain allocated inside the existing shared MR (MR1).
 //              Sender uses the peer's MR1 rkey (known from QpInfo exchange).
+//              `page_class` is the class of the chain's pages (the slot's
+//              class), which fixes the stride and per-page data size the
+//              sender splits its bytes by.
 //
 //   UseMr2   — region allocated inside a separately-registered host-side
 //              MR2 backing file.  Sender must target (addr, rkey) from this
@@ -181,7 +184,7 @@ pub fn recv_shm_offset(stream: &mut TcpStream) -> Result<common::ShmOffset> {
 
 #[derive(Clone, Copy, Debug)]
 pub enum DestReply {
-    SingleMr { dest_off: common::ShmOffset },
+    SingleMr { dest_off: common::ShmOffset, page_class: u8 },
     UseMr2   { dest_off: u64, addr: u64, rkey: u32 },
 }
 
@@ -190,9 +193,10 @@ const DEST_TAG_USE_MR2:   u8 = 1;
 
 pub fn send_dest_reply(stream: &mut TcpStream, reply: &DestReply) -> Result<()> {
     match *reply {
-        DestReply::SingleMr { dest_off } => {
+        DestReply::SingleMr { dest_off, page_class } => {
             stream.write_all(&[DEST_TAG_SINGLE_MR])?;
             stream.write_all(&dest_off.to_le_bytes())?;
+            stream.write_all(&[page_class])?;
         }
         DestReply::UseMr2 { dest_off, addr, rkey } => {
             stream.write_all(&[DEST_TAG_USE_MR2])?;
@@ -211,7 +215,12 @@ pub fn recv_dest_reply(stream: &mut TcpStream) -> Result<DestReply> {
         DEST_TAG_SINGLE_MR => {
             let mut b = [0u8; core::mem::size_of::<common::ShmOffset>()];
             stream.read_exact(&mut b)?;
-            Ok(DestReply::SingleMr { dest_off: common::ShmOffset::from_le_bytes(b) })
+            let mut class = [0u8; 1];
+            stream.read_exact(&mut class)?;
+            Ok(DestReply::SingleMr {
+                dest_off:   common::ShmOffset::from_le_bytes(b),
+                page_class: class[0],
+            })
         }
         DEST_TAG_USE_MR2 => {
             let mut off = [0u8; 8]; stream.read_exact(&mut off)?;
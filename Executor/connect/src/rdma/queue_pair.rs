@@ -17,7 +17,7 @@ pub const MAX_SEND_SGE: usize = 16;
 /// than this × `PAGE_DATA_SIZE` must be posted in batches separated by a
 /// signaled WR + `poll_one_blocking` so the queue doesn't overflow (see
 /// `runtime/remote/rdma.rs::rdma_write_page_chain`).  Chosen to comfortably
-/// fit typical RDMA transfers (a few MiB at 4088 B/page = ~1000 WRs) while
+/// fit typical RDMA transfers (a few MiB at 4080 B/page = ~1000 WRs) while
 /// staying well under ConnectX-3's `max_qp_wr = 16351`.
 pub const MAX_SEND_WR:  usize = 1024;
 
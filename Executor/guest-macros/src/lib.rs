@@ -0,0 +1,344 @@
+//! `#[workload]` — the attribute that turns a plain Rust function into a guest
+//! workload export.
+//!
+//! ```ignore
+//! use guest_macros::workload;
+//! use crate::api::workload::{StreamIn, StreamOut};
+//!
+//! /// Counts words in stream `slot`, one record per word to `slot + 100`.
+//! #[workload]
+//! fn wc_map(slot: u32, #[slot(slot)] input: StreamIn, #[slot(slot + 100)] out: StreamOut) {
+//!     for (_origin, rec) in input { /* … */ out.push(&rec); }
+//! }
+//! ```
+//!
+//! expands to a `#[no_mangle] pub extern "C" fn wc_map(slot: u32)` that builds
+//! the slot handles and calls the original body, plus one line of JSON in the
+//! `common::WORKLOAD_META_SECTION` custom section:
+//!
+//! ```json
+//! {"name":"wc_map","args":[{"name":"slot","type":"u32"}],"ret":"void",
+//!  "consumes":[{"kind":"Stream","slot":"slot"}],
+//!  "produces":[{"kind":"Stream","slot":"slot+100"}]}
+//! ```
+//!
+//! Parameters come in two sorts:
+//!
+//! | Parameter | WASM argument | Meaning |
+//! |---|---|---|
+//! | `x: u32` | yes | passed through |
+//! | `#[fanout(default_base = B)] x: Fanout` | yes | `unpack_fanout_arg`-style `(base, n)` |
+//! | `#[slot(E)] x: StreamIn` / `IoIn` | no | consumed slot `E` |
+//! | `#[slot(E)] x: StreamOut` / `IoOut` | no | produced slot `E` |
+//!
+//! `E` is an integer literal, a `u32` argument, or `arg + literal`; literals
+//! only, so the metadata can carry the slot.  A workload takes one or two WASM
+//! arguments — the `WasmVoid`/`WasmU32`/`WasmFatPtr` and the `StreamPipeline`/
+//! `WasmGrouping` calling conventions.  The return type picks the node kind:
+//! none → `"void"`, `u32` → `"u32"`, `u64` (an already packed fat pointer) or
+//! `Vec<u8>` → `"fatptr"`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, BinOp, Error, Expr, FnArg, Ident, ItemFn, Lit, Pat, ReturnType, Type};
+
+#[proc_macro_attribute]
+pub fn workload(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return Error::new(Span::call_site(), "#[workload] takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let func = parse_macro_input!(item as ItemFn);
+    expand(func).unwrap_or_else(Error::into_compile_error).into()
+}
+
+// ── Signature model ──
+
+enum ArgType {
+    U32,
+    Fanout { default_base: Expr },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SlotKind {
+    Stream,
+    Io,
+}
+
+/// `#[slot(...)]`: `arg + offset`, or the bare `offset`.
+struct SlotExpr {
+    arg: Option<Ident>,
+    offset: u32,
+}
+
+enum Param {
+    Arg { ident: Ident, ty: ArgType },
+    Slot { ident: Ident, kind: SlotKind, produces: bool, handle: Ident, expr: SlotExpr },
+}
+
+enum Ret {
+    Void,
+    U32,
+    PackedFatPtr,
+    Bytes,
+}
+
+impl Ret {
+    fn name(&self) -> &'static str {
+        match self {
+            Ret::Void => "void",
+            Ret::U32 => "u32",
+            Ret::PackedFatPtr | Ret::Bytes => "fatptr",
+        }
+    }
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+// ── Parsing ──
+
+fn parse_params(func: &mut ItemFn) -> syn::Result<Vec<Param>> {
+    let mut params = Vec::new();
+    for input in func.sig.inputs.iter_mut() {
+        let FnArg::Typed(pat_ty) = input else {
+            return Err(Error::new(input.span(), "#[workload] functions take no `self`"));
+        };
+        let Pat::Ident(pat) = &*pat_ty.pat else {
+            return Err(Error::new(pat_ty.pat.span(), "#[workload] parameters must be plain identifiers"));
+        };
+        let ident = pat.ident.clone();
+        let attrs = std::mem::take(&mut pat_ty.attrs);
+        let ty_name = type_name(&pat_ty.ty).unwrap_or_default();
+
+        let param = match ty_name.as_str() {
+            "u32" => {
+                if let Some(a) = attrs.first() {
+                    return Err(Error::new(a.span(), "unexpected attribute on a `u32` argument"));
+                }
+                Param::Arg { ident, ty: ArgType::U32 }
+            }
+            "Fanout" => {
+                let mut default_base = None;
+                for a in &attrs {
+                    if !a.path().is_ident("fanout") {
+                        return Err(Error::new(a.span(), "expected #[fanout(default_base = ...)]"));
+                    }
+                    a.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("default_base") {
+                            default_base = Some(meta.value()?.parse::<Expr>()?);
+                            Ok(())
+                        } else {
+                            Err(meta.error("expected `default_base`"))
+                        }
+                    })?;
+                }
+                let default_base = default_base.unwrap_or_else(|| syn::parse_quote!(0));
+                Param::Arg { ident, ty: ArgType::Fanout { default_base } }
+            }
+            "StreamIn" | "StreamOut" | "IoIn" | "IoOut" => {
+                let [a] = attrs.as_slice() else {
+                    return Err(Error::new(pat_ty.span(), format!("`{}` parameters need one #[slot(...)]", ty_name)));
+                };
+                if !a.path().is_ident("slot") {
+                    return Err(Error::new(a.span(), "expected #[slot(...)]"));
+                }
+                let expr = parse_slot_expr(&a.parse_args::<Expr>()?)?;
+                Param::Slot {
+                    ident,
+                    kind: if ty_name.starts_with("Stream") { SlotKind::Stream } else { SlotKind::Io },
+                    produces: ty_name.ends_with("Out"),
+                    handle: format_ident!("{}", ty_name),
+                    expr,
+                }
+            }
+            _ => {
+                return Err(Error::new(
+                    pat_ty.ty.span(),
+                    "#[workload] parameters are `u32`, `Fanout`, `StreamIn`, `StreamOut`, `IoIn` or `IoOut`",
+                ));
+            }
+        };
+        params.push(param);
+    }
+
+    // Slot expressions may only name plain `u32` arguments.
+    for p in &params {
+        let Param::Slot { expr: SlotExpr { arg: Some(arg), .. }, .. } = p else { continue };
+        let is_u32_arg = params.iter().any(|q| matches!(q, Param::Arg { ident, ty: ArgType::U32 } if ident == arg));
+        if !is_u32_arg {
+            return Err(Error::new(arg.span(), format!("`{}` is not a `u32` argument of this workload", arg)));
+        }
+    }
+    Ok(params)
+}
+
+fn parse_slot_expr(expr: &Expr) -> syn::Result<SlotExpr> {
+    fn literal(e: &Expr) -> Option<syn::Result<u32>> {
+        match e {
+            Expr::Lit(l) => match &l.lit {
+                Lit::Int(i) => Some(i.base10_parse()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    fn ident(e: &Expr) -> Option<Ident> {
+        match e {
+            Expr::Path(p) => p.path.get_ident().cloned(),
+            _ => None,
+        }
+    }
+
+    if let Some(offset) = literal(expr) {
+        return Ok(SlotExpr { arg: None, offset: offset? });
+    }
+    if let Some(arg) = ident(expr) {
+        return Ok(SlotExpr { arg: Some(arg), offset: 0 });
+    }
+    if let Expr::Binary(b) = expr {
+        if let (BinOp::Add(_), Some(arg), Some(offset)) = (&b.op, ident(&b.left), literal(&b.right)) {
+            return Ok(SlotExpr { arg: Some(arg), offset: offset? });
+        }
+    }
+    Err(Error::new(expr.span(), "slot must be a literal, an argument, or `argument + literal`"))
+}
+
+fn parse_ret(output: &ReturnType) -> syn::Result<Ret> {
+    let ReturnType::Type(_, ty) = output else { return Ok(Ret::Void) };
+    match type_name(ty).as_deref() {
+        Some("u32") => Ok(Ret::U32),
+        Some("u64") => Ok(Ret::PackedFatPtr),
+        Some("Vec") => Ok(Ret::Bytes),
+        _ if matches!(&**ty, Type::Tuple(t) if t.elems.is_empty()) => Ok(Ret::Void),
+        _ => Err(Error::new(ty.span(), "#[workload] functions return nothing, `u32`, `u64` or `Vec<u8>`")),
+    }
+}
+
+// ── Metadata ──
+
+fn slot_json(kind: SlotKind, expr: &SlotExpr) -> String {
+    let kind = match kind {
+        SlotKind::Stream => "Stream",
+        SlotKind::Io => "Io",
+    };
+    let slot = match (&expr.arg, expr.offset) {
+        (Some(arg), 0) => arg.to_string(),
+        (Some(arg), offset) => format!("{}+{}", arg, offset),
+        (None, offset) => offset.to_string(),
+    };
+    format!(r#"{{"kind":"{}","slot":"{}"}}"#, kind, slot)
+}
+
+/// One line of the metadata section; the names are Rust identifiers, so
+/// nothing needs escaping.
+fn metadata_json(name: &Ident, params: &[Param], ret: &Ret) -> String {
+    let mut args = Vec::new();
+    let mut consumes = Vec::new();
+    let mut produces = Vec::new();
+    for p in params {
+        match p {
+            Param::Arg { ident, ty } => {
+                let ty = match ty {
+                    ArgType::U32 => "u32",
+                    ArgType::Fanout { .. } => "fanout",
+                };
+                args.push(format!(r#"{{"name":"{}","type":"{}"}}"#, ident, ty));
+            }
+            Param::Slot { kind, produces: true, expr, .. } => produces.push(slot_json(*kind, expr)),
+            Param::Slot { kind, produces: false, expr, .. } => consumes.push(slot_json(*kind, expr)),
+        }
+    }
+    format!(
+        r#"{{"name":"{}","args":[{}],"ret":"{}","consumes":[{}],"produces":[{}]}}"#,
+        name, args.join(","), ret.name(), consumes.join(","), produces.join(","),
+    ) + "\n"
+}
+
+// ── Expansion ──
+
+fn expand(mut func: ItemFn) -> syn::Result<TokenStream2> {
+    if !func.sig.generics.params.is_empty() || func.sig.asyncness.is_some() || func.sig.abi.is_some() {
+        return Err(Error::new(func.sig.span(), "#[workload] functions must be plain, non-generic Rust functions"));
+    }
+    let params = parse_params(&mut func)?;
+    let ret = parse_ret(&func.sig.output)?;
+    let name = func.sig.ident.clone();
+
+    let wasm_args: Vec<&Ident> = params.iter()
+        .filter_map(|p| match p { Param::Arg { ident, .. } => Some(ident), _ => None })
+        .collect();
+    if !(1..=2).contains(&wasm_args.len()) {
+        return Err(Error::new(
+            func.sig.inputs.span(),
+            "#[workload] functions take one or two `u32`/`Fanout` arguments",
+        ));
+    }
+
+    // Slot handles are built from the raw arguments, so they are bound before
+    // any `Fanout` argument shadows its raw value.
+    let mut bindings = Vec::new();
+    for p in &params {
+        let Param::Slot { ident, handle, expr, .. } = p else { continue };
+        let offset = expr.offset;
+        let slot = match &expr.arg {
+            Some(arg) => quote! { #arg + #offset },
+            None => quote! { #offset },
+        };
+        bindings.push(quote! {
+            let #ident = crate::api::workload::#handle::new(#slot);
+        });
+    }
+    for p in &params {
+        let Param::Arg { ident, ty: ArgType::Fanout { default_base } } = p else { continue };
+        bindings.push(quote! {
+            let #ident = crate::api::workload::Fanout::decode(#ident, #default_base);
+        });
+    }
+
+    let call_args: Vec<&Ident> = params.iter()
+        .map(|p| match p { Param::Arg { ident, .. } | Param::Slot { ident, .. } => ident })
+        .collect();
+    let (abi_ret, convert) = match ret {
+        Ret::Void => (quote! {}, quote! { __body(#(#call_args),*) }),
+        Ret::U32 => (quote! { -> u32 }, quote! { __body(#(#call_args),*) }),
+        Ret::PackedFatPtr => (quote! { -> u64 }, quote! { __body(#(#call_args),*) }),
+        Ret::Bytes => (quote! { -> u64 }, quote! { crate::api::workload::fat_ptr(__body(#(#call_args),*)) }),
+    };
+
+    let json = metadata_json(&name, &params, &ret);
+    let meta = syn::LitByteStr::new(json.as_bytes(), Span::call_site());
+    let meta_len = json.len();
+    let meta_ident = format_ident!("__WORKLOAD_META_{}", name.to_string().to_uppercase());
+    let section = common::WORKLOAD_META_SECTION;
+
+    let attrs = std::mem::take(&mut func.attrs);
+    let mut body = func;
+    body.sig.ident = format_ident!("__body");
+    body.vis = syn::Visibility::Inherited;
+
+    Ok(quote! {
+        #(#attrs)*
+        #[no_mangle]
+        pub extern "C" fn #name(#(#wasm_args: u32),*) #abi_ret {
+            // An argument may be used only in a `#[slot(...)]`.
+            #[inline(always)]
+            #[allow(unused_variables)]
+            #body
+            #(#bindings)*
+            #convert
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        #[link_section = #section]
+        #[used]
+        static #meta_ident: [u8; #meta_len] = *#meta;
+    })
+}
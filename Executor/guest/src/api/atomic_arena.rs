@@ -11,7 +11,7 @@ extern "C" {
 impl ShmApi {
     /// Returns a reference to the `AtomicU64` at the given index in the shared atomic arena.
     fn get_atomic_by_index(index: usize) -> &'static AtomicU64 {
-        let base = SHM_BASE + ATOMIC_ARENA_OFFSET as usize;
+        let base = SHM_BASE + Self::superblock().layout.atomic_arena_offset as usize;
         unsafe { &*((base as *const AtomicU64).add(index)) }
     }
 
@@ -17,13 +17,14 @@ impl ShmApi {
     /// produce → barrier 0 → exchange → barrier 1 → consume).
     ///
     /// # Arguments
-    /// * `barrier_id`   — index into the Superblock barrier array (0..63).
+    /// * `barrier_id`   — index into the Superblock barrier counters
+    ///                    (`0..barrier_count`, 64 by default).
     /// * `party_count`  — number of processes that must arrive before any
     ///                    are released.
     ///
     /// # Panics
-    /// Panics (trap) if `barrier_id >= BARRIER_COUNT` (bounds checked on the
-    /// host side via the Superblock array access).
+    /// Panics (trap) if `barrier_id` is not below the region's barrier count
+    /// (bounds checked on the host side by `Superblock::barrier`).
     pub fn barrier_wait(barrier_id: u32, party_count: u32) {
         unsafe { host_barrier_wait(barrier_id, party_count) }
     }
@@ -1,8 +1,8 @@
 // Input/Output channels for the guest ↔ host data boundary.
 //
-// Both directions use the dedicated I/O area (io_heads / io_tails in the
+// Both directions use the dedicated I/O area (the I/O head and tail arrays in the
 // Superblock), which is completely separate from the stream slots used by
-// inter-worker pipelines.  This means all STREAM_SLOT_COUNT stream slots are
+// inter-worker pipelines.  This means all of the region's stream slots are
 // free for application use — none are reserved.
 //
 // OUTPUT — final results written by a pipeline stage:
@@ -1,22 +1,23 @@
 // Dedicated I/O area: a separate, non-overlapping slot pool for host↔guest
 // data exchange.
 //
-// Stream slots (0..STREAM_SLOT_COUNT) are purely for inter-worker pipelines.
-// I/O slots (0..IO_SLOT_COUNT) are reserved for host-injected input and
+// Stream slots (0..stream_slot_count) are purely for inter-worker pipelines.
+// I/O slots (0..io_slot_count) are reserved for host-injected input and
 // guest-emitted output that the host `Inputer` / `Outputer` manages.
 //
 // The page-chain mechanics are identical to the stream area; this module just
-// indexes `sb.io_heads` / `sb.io_tails` instead of `sb.writer_heads` /
-// `sb.writer_tails`, using the shared helpers from `stream_area`.
+// indexes `sb.io_head` / `sb.io_tail` instead of `sb.writer_head` /
+// `sb.writer_tail`, using the shared helpers from `stream_area`.
 //
 // Public API:
 //   append_io_data(slot, data)         — write a record to an I/O slot
 //   read_latest_io_data(slot)           — read the most recent record
 //   read_all_io_records(slot)           — read all records in order
+//   io_records(slot)                    — borrow all records in order (see `records`)
 
 use core::sync::atomic::Ordering;
 use alloc::vec::Vec;
-use common::{IO_SLOT_COUNT, ShmOffset};
+use common::ShmOffset;
 use super::ShmApi;
 use super::stream_area::{chain_append_prefixed, chain_read_latest, chain_read_all};
 
@@ -25,17 +26,14 @@ impl ShmApi {
     ///
     /// Used by guests to emit output destined for the host `Outputer`.
     /// Pass `OUTPUT_IO_SLOT` (or any slot the DAG `Output` node targets)
-    /// as `io_slot`.
+    /// as `io_slot`.  Traps if `io_slot` is not below the region's
+    /// `io_slot_count`.
     pub fn append_io_data(io_slot: u32, payload: &[u8]) {
-        debug_assert!(
-            (io_slot as usize) < IO_SLOT_COUNT,
-            "I/O slot {} out of range (IO_SLOT_COUNT={})",
-            io_slot, IO_SLOT_COUNT,
-        );
         let sb = Self::superblock();
         chain_append_prefixed(
-            &sb.io_heads[io_slot as usize],
-            &sb.io_tails[io_slot as usize],
+            sb.io_head(io_slot as usize),
+            sb.io_tail(io_slot as usize),
+            sb.io_page_class(io_slot as usize).load(Ordering::Relaxed) as u32,
             io_slot,
             payload,
         );
@@ -45,7 +43,7 @@ impl ShmApi {
     ///
     /// Returns `None` when the host has not written any data into this slot.
     pub fn read_latest_io_data(io_slot: u32) -> Option<(u32, Vec<u8>)> {
-        let head = Self::superblock().io_heads[io_slot as usize].load(Ordering::Acquire) as ShmOffset;
+        let head = Self::superblock().io_head(io_slot as usize).load(Ordering::Acquire) as ShmOffset;
         chain_read_latest(head)
     }
 
@@ -55,7 +53,7 @@ impl ShmApi {
     /// file; this returns them all so the guest can iterate over every line.
     /// Returns an empty `Vec` when no input has been written.
     pub fn read_all_io_records(io_slot: u32) -> Vec<(u32, Vec<u8>)> {
-        let head = Self::superblock().io_heads[io_slot as usize].load(Ordering::Acquire) as ShmOffset;
+        let head = Self::superblock().io_head(io_slot as usize).load(Ordering::Acquire) as ShmOffset;
         chain_read_all(head)
     }
 
@@ -64,7 +62,7 @@ impl ShmApi {
     /// `read_all_io_records`, the live heap footprint is bounded by the largest
     /// record, so it scales to multi-GB inputs that would OOM `read_all`.
     pub fn for_each_io_record<F: FnMut(u32, &[u8])>(io_slot: u32, f: F) {
-        let head = Self::superblock().io_heads[io_slot as usize].load(Ordering::Acquire) as ShmOffset;
+        let head = Self::superblock().io_head(io_slot as usize).load(Ordering::Acquire) as ShmOffset;
         super::stream_area::chain_for_each(head, f);
     }
 
@@ -78,9 +76,8 @@ impl ShmApi {
         let name = alloc::format!("io_cursor_{}", io_slot);
         let atomic = Self::get_named_atomic(&name);
         let idx = atomic.load(Ordering::Acquire) as usize;
-        let records = Self::read_all_io_records(io_slot);
-        if idx >= records.len() { return None; }
+        let rec = Self::io_records(io_slot).nth(idx)?;
         atomic.fetch_add(1, Ordering::Release);
-        records.into_iter().nth(idx)
+        Some((rec.origin(), rec.to_vec()))
     }
 }
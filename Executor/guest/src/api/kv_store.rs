@@ -0,0 +1,260 @@
+// Key-value store over the shared area (see "Key-value store" in `common`).
+//
+//   ShmApi::kv_put(b"user:42", b"alice");
+//   let name = ShmApi::kv_get(b"user:42");                      // Some(b"alice")
+//   ShmApi::kv_compare_and_put(b"hits", Some(&old), &new);      // false if raced
+//   for (key, value) in ShmApi::kv_scan(b"user:") { … }          // sorted by key
+//   ShmApi::kv_delete(b"user:42");
+//
+// Every write builds a complete entry in fresh pages and then publishes it
+// with one CAS on the bucket head; readers never see a half-written entry.
+// Entries a writer built but could not publish (a lost compare-and-put, a
+// delete of an absent key) go straight back to the free list.  Published
+// entries are reclaimed by the host only (`FreeSlots` with `kv: true`).
+
+use core::sync::atomic::Ordering;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use common::*;
+use super::{ShmApi, SHM_BASE};
+use super::stream_area::ensure_mapped;
+
+const HEADER_SIZE: usize = core::mem::size_of::<KvEntryHeader>();
+const LINK_SIZE: usize = core::mem::size_of::<PageId>();
+
+/// Address of the page at `offset`, mapped.
+fn page_ptr(offset: ShmOffset) -> *mut u8 {
+    if !ensure_mapped(offset + PAGE_SIZE) {
+        panic!("kv store: page {:#x} lies past the SHM capacity", offset);
+    }
+    (SHM_BASE + offset as usize) as *mut u8
+}
+
+/// Entry `idx` of the directory page at `page`.
+fn directory_slot(page: ShmOffset, idx: usize) -> &'static AtomicPageId {
+    unsafe { &*(page_ptr(page) as *const AtomicPageId).add(idx) }
+}
+
+fn header(entry: ShmOffset) -> &'static KvEntryHeader {
+    unsafe { &*(page_ptr(entry) as *const KvEntryHeader) }
+}
+
+/// A page with every byte zero: an empty directory.
+fn zeroed_page() -> ShmOffset {
+    let page = ShmApi::try_allocate_page();
+    unsafe { core::ptr::write_bytes(page_ptr(page), 0, PAGE_SIZE as usize) };
+    page
+}
+
+/// Head of the bucket for `hash`.  With `create`, missing directory pages are
+/// added (the loser of a racing creation frees its page); otherwise `None`
+/// when the bucket was never written.
+fn bucket(hash: u32, create: bool) -> Option<&'static AtomicPageId> {
+    let (root_idx, leaf_idx) = kv_bucket(hash);
+    let sb = ShmApi::superblock();
+    let mut root = sb.kv_directory.load(Ordering::Acquire);
+    if root == 0 {
+        if !create { return None; }
+        let page = zeroed_page();
+        root = match sb.kv_directory.compare_exchange(0, page, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => page,
+            Err(existing) => { ShmApi::free_page(page); existing }
+        };
+    }
+    let link = directory_slot(root, root_idx);
+    let mut leaf = link.load(Ordering::Acquire) as ShmOffset;
+    if leaf == 0 {
+        if !create { return None; }
+        let page = zeroed_page();
+        leaf = match link.compare_exchange(0, page as PageId, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => page,
+            Err(existing) => { ShmApi::free_page(page); existing as ShmOffset }
+        };
+    }
+    Some(directory_slot(leaf, leaf_idx))
+}
+
+// ── Entry bytes ──
+
+/// Copies `out.len()` bytes of the entry's key-then-value bytes, starting
+/// `start` bytes in.
+fn read_entry(entry: ShmOffset, mut start: usize, mut out: &mut [u8]) {
+    let (mut page, mut data, mut capacity) = (entry, HEADER_SIZE, PAGE_SIZE as usize - HEADER_SIZE);
+    let mut next = header(entry).next_payload_page as ShmOffset;
+    while !out.is_empty() {
+        if start < capacity {
+            let n = core::cmp::min(capacity - start, out.len());
+            unsafe { core::ptr::copy_nonoverlapping(page_ptr(page).add(data + start), out.as_mut_ptr(), n) };
+            out = &mut out[n..];
+            start = 0;
+        } else {
+            start -= capacity;
+        }
+        if out.is_empty() { break; }
+        page = next;
+        next = unsafe { *(page_ptr(page) as *const PageId) } as ShmOffset;
+        (data, capacity) = (LINK_SIZE, PAGE_SIZE as usize - LINK_SIZE);
+    }
+}
+
+fn entry_key(entry: ShmOffset) -> Vec<u8> {
+    let mut key = alloc::vec![0u8; header(entry).key_len as usize];
+    read_entry(entry, 0, &mut key);
+    key
+}
+
+/// The value of a live entry, `None` for a tombstone.
+fn entry_value(entry: ShmOffset) -> Option<Vec<u8>> {
+    let h = header(entry);
+    if h.flags & KV_TOMBSTONE != 0 { return None; }
+    let mut value = alloc::vec![0u8; h.value_len as usize];
+    read_entry(entry, h.key_len as usize, &mut value);
+    Some(value)
+}
+
+/// The newest entry for `key` in the bucket chain starting at `head`.
+fn find(head: PageId, hash: u32, key: &[u8]) -> Option<ShmOffset> {
+    let mut current = head as ShmOffset;
+    while current != 0 {
+        let h = header(current);
+        if h.hash == hash && h.key_len as usize == key.len() && entry_key(current) == key {
+            return Some(current);
+        }
+        current = h.next_entry.load(Ordering::Acquire) as ShmOffset;
+    }
+    None
+}
+
+/// Builds an unpublished entry holding `key` then `value`.
+fn write_entry(hash: u32, key: &[u8], value: &[u8], flags: u32) -> ShmOffset {
+    let entry = ShmApi::try_allocate_page();
+    unsafe {
+        (page_ptr(entry) as *mut KvEntryHeader).write(KvEntryHeader {
+            next_entry: AtomicPageId::new(0),
+            hash,
+            key_len: key.len() as u32,
+            value_len: value.len() as u32,
+            flags,
+            next_payload_page: 0,
+        });
+    }
+    let mut link = unsafe { &mut (*(page_ptr(entry) as *mut KvEntryHeader)).next_payload_page as *mut PageId };
+    let (mut dest, mut room) = (unsafe { page_ptr(entry).add(HEADER_SIZE) }, PAGE_SIZE as usize - HEADER_SIZE);
+    for mut bytes in [key, value] {
+        while !bytes.is_empty() {
+            if room == 0 {
+                let page = ShmApi::try_allocate_page();
+                let ptr = page_ptr(page);
+                unsafe {
+                    *(ptr as *mut PageId) = 0;
+                    *link = page as PageId;
+                }
+                link = ptr as *mut PageId;
+                (dest, room) = (unsafe { ptr.add(LINK_SIZE) }, PAGE_SIZE as usize - LINK_SIZE);
+            }
+            let n = core::cmp::min(room, bytes.len());
+            unsafe {
+                core::ptr::copy_nonoverlapping(bytes.as_ptr(), dest, n);
+                dest = dest.add(n);
+            }
+            room -= n;
+            bytes = &bytes[n..];
+        }
+    }
+    entry
+}
+
+/// Frees an entry that was never published.
+fn free_entry(entry: ShmOffset) {
+    let mut next = header(entry).next_payload_page as ShmOffset;
+    ShmApi::free_page(entry);
+    while next != 0 {
+        let page = next;
+        next = unsafe { *(page_ptr(page) as *const PageId) } as ShmOffset;
+        ShmApi::free_page(page);
+    }
+}
+
+/// Publishes `entry` if the bucket head is still `head`.
+fn publish(bucket: &AtomicPageId, head: PageId, entry: ShmOffset) -> bool {
+    header(entry).next_entry.store(head, Ordering::Relaxed);
+    bucket.compare_exchange(head, entry as PageId, Ordering::Release, Ordering::Acquire).is_ok()
+}
+
+impl ShmApi {
+    /// The current value of `key`.
+    pub fn kv_get(key: &[u8]) -> Option<Vec<u8>> {
+        let hash = kv_hash(key);
+        let head = bucket(hash, false)?.load(Ordering::Acquire);
+        find(head, hash, key).and_then(entry_value)
+    }
+
+    /// Sets `key` to `value`.
+    pub fn kv_put(key: &[u8], value: &[u8]) {
+        let hash = kv_hash(key);
+        let bucket = bucket(hash, true).unwrap();
+        let entry = write_entry(hash, key, value, 0);
+        while !publish(bucket, bucket.load(Ordering::Acquire), entry) {}
+    }
+
+    /// Sets `key` to `value` only if its current value is `expected`
+    /// (`None`: the key is absent).  Returns whether the value was set.
+    pub fn kv_compare_and_put(key: &[u8], expected: Option<&[u8]>, value: &[u8]) -> bool {
+        let hash = kv_hash(key);
+        let bucket = bucket(hash, true).unwrap();
+        let mut entry = None;
+        loop {
+            let head = bucket.load(Ordering::Acquire);
+            if find(head, hash, key).and_then(entry_value).as_deref() != expected {
+                if let Some(entry) = entry { free_entry(entry); }
+                return false;
+            }
+            let e = *entry.get_or_insert_with(|| write_entry(hash, key, value, 0));
+            if publish(bucket, head, e) { return true; }
+        }
+    }
+
+    /// Removes `key`.  Returns whether it had a value.
+    pub fn kv_delete(key: &[u8]) -> bool {
+        let hash = kv_hash(key);
+        let Some(bucket) = bucket(hash, false) else { return false };
+        let mut tombstone = None;
+        loop {
+            let head = bucket.load(Ordering::Acquire);
+            if find(head, hash, key).and_then(entry_value).is_none() {
+                if let Some(entry) = tombstone { free_entry(entry); }
+                return false;
+            }
+            let entry = *tombstone.get_or_insert_with(|| write_entry(hash, key, &[], KV_TOMBSTONE));
+            if publish(bucket, head, entry) { return true; }
+        }
+    }
+
+    /// Every live `(key, value)` whose key starts with `prefix`, sorted by
+    /// key.  An empty prefix scans the whole store.  Not a snapshot: a write
+    /// racing the scan may or may not be seen.
+    pub fn kv_scan(prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let root = Self::superblock().kv_directory.load(Ordering::Acquire);
+        let mut out = BTreeMap::new();
+        if root == 0 { return Vec::new(); }
+        for r in 0..KV_FANOUT {
+            let leaf = directory_slot(root, r).load(Ordering::Acquire) as ShmOffset;
+            if leaf == 0 { continue; }
+            for l in 0..KV_FANOUT {
+                let mut current = directory_slot(leaf, l).load(Ordering::Acquire) as ShmOffset;
+                let mut seen = BTreeSet::new();
+                while current != 0 {
+                    let key = entry_key(current);
+                    if key.starts_with(prefix) && !seen.contains(&key) {
+                        if let Some(value) = entry_value(current) {
+                            out.insert(key.clone(), value);
+                        }
+                        seen.insert(key);
+                    }
+                    current = header(current).next_entry.load(Ordering::Acquire) as ShmOffset;
+                }
+            }
+        }
+        out.into_iter().collect()
+    }
+}
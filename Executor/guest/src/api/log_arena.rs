@@ -11,8 +11,8 @@ impl ShmApi {
         let len = bytes.len() as ShmOffset;
 
         let offset = sb.log_offset.fetch_add(len, Ordering::Relaxed);
-        if offset + len <= LOG_ARENA_SIZE as ShmOffset {
-            let dest = (SHM_BASE + LOG_ARENA_OFFSET as usize + offset as usize) as *mut u8;
+        if offset + len <= sb.layout.log_arena_size() {
+            let dest = (SHM_BASE + sb.layout.log_arena_offset as usize + offset as usize) as *mut u8;
             unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), dest, bytes.len()); }
         }
     }
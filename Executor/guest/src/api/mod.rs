@@ -21,6 +21,14 @@ pub enum SeekFrom {
 
 pub struct ShmApi;
 
+/// Layout hash of the `common` build this module was compiled against.
+/// The host calls it right after instantiation and refuses to run a guest
+/// whose SHM layout differs from its own (see `worker::check_guest_layout`).
+#[no_mangle]
+pub extern "C" fn shm_layout_hash() -> u64 {
+    SHM_LAYOUT_HASH
+}
+
 mod page_allocator;
 mod atomic_arena;
 mod barrier;
@@ -29,4 +37,13 @@ mod io_area;
 mod input_output;
 mod log_arena;
 mod shared_area;
+mod kv_store;
 mod stream_area;
+mod stream_signal;
+mod ring;
+mod subscription;
+mod remote_atomic;
+pub use stream_signal::StreamWait;
+pub mod records;
+pub mod typed;
+pub mod workload;
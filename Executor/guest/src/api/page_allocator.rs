@@ -17,6 +17,41 @@ impl ShmApi {
     /// Returns a shared reference to the Superblock at the base of shared memory.
     pub(crate) fn superblock() -> &'static Superblock { unsafe { &*(SHM_BASE as *const Superblock) } }
 
+    /// Allocates a page of `class` ([`PAGE_CLASS_SMALL`] …) with its header
+    /// initialised: `next_offset` and `cursor` zero, `class` set.
+    ///
+    /// Small pages come from [`ShmApi::try_allocate_page`].  Larger classes
+    /// pop their class free list and otherwise bump-allocate a whole page of
+    /// that class, so a large page is always contiguous in the direct window.
+    pub(crate) fn try_allocate_page_in(class: u32) -> ShmOffset {
+        if class == PAGE_CLASS_SMALL {
+            return Self::try_allocate_page();
+        }
+        let sb = Self::superblock();
+        let list = sb.class_free_list(class);
+        loop {
+            let head = list.load(Ordering::Acquire) as ShmOffset;
+            if head == 0 {
+                break;
+            }
+            let page_ptr = (SHM_BASE + head as usize) as *const Page;
+            let next_free = unsafe { (*page_ptr).next_offset.load(Ordering::Relaxed) } as ShmOffset;
+            if list.compare_exchange(head as u64, next_free as u64, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+                let page = unsafe { &*page_ptr };
+                page.next_offset.store(0, Ordering::Relaxed);
+                page.cursor.store(0, Ordering::Relaxed);
+                return head;
+            }
+            spin_loop();
+        }
+        let offset = Self::bump_allocate(page_class_size(class));
+        let page = unsafe { &*((SHM_BASE + offset as usize) as *const Page) };
+        page.next_offset.store(0, Ordering::Relaxed);
+        page.cursor.store(0, Ordering::Relaxed);
+        page.class.store(class, Ordering::Relaxed);
+        offset
+    }
+
     /// Allocates a 4 KiB page from the shared memory pool.
     ///
     /// # Strategy
@@ -53,6 +88,7 @@ impl ShmApi {
                         let mut_page = unsafe { &mut *(page_ptr as *mut Page) };
                         mut_page.next_offset.store(0, Ordering::Relaxed);
                         mut_page.cursor.store(0, Ordering::Relaxed);
+                        mut_page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
                         return head;
                     }
                     Err(_) => spin_loop(), // another thread won — retry same shard
@@ -61,6 +97,36 @@ impl ShmApi {
         }
 
         // ── Bump allocate (all shards empty) ─────────────────────────────────
+        let offset = Self::bump_allocate(PAGE_SIZE);
+        let page = unsafe { &*((SHM_BASE + offset as usize) as *const Page) };
+        page.next_offset.store(0, Ordering::Relaxed);
+        page.cursor.store(0, Ordering::Relaxed);
+        page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
+        offset
+    }
+
+    /// Returns a 4 KiB page the caller allocated but never published to the
+    /// free-list shard `(offset / PAGE_SIZE) % FREE_LIST_SHARD_COUNT`, as the
+    /// host's `free_page_chain` would.
+    pub(crate) fn free_page(offset: ShmOffset) {
+        let sb = Self::superblock();
+        let shard = &sb.free_list_heads[(offset / PAGE_SIZE) as usize % FREE_LIST_SHARD_COUNT];
+        let page = unsafe { &*((SHM_BASE + offset as usize) as *const Page) };
+        page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
+        let mut head = shard.load(Ordering::Acquire);
+        loop {
+            page.next_offset.store(head, Ordering::Relaxed);
+            match shard.compare_exchange(head, offset as u64, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return,
+                Err(current) => { head = current; spin_loop(); }
+            }
+        }
+    }
+
+    /// Claims `size` bytes from `sb.bump_allocator`, expanding the VMA via
+    /// `host_remap` when the current capacity is exhausted.
+    fn bump_allocate(size: ShmOffset) -> ShmOffset {
+        let sb = Self::superblock();
         loop {
             let current_alloc = sb.bump_allocator.load(Ordering::Acquire);
 
@@ -73,7 +139,7 @@ impl ShmApi {
 
             let local_cap = unsafe { super::LOCAL_CAPACITY };
 
-            if current_alloc + PAGE_SIZE > local_cap {
+            if current_alloc + size > local_cap {
                 let global_cap = sb.global_capacity.load(Ordering::Acquire);
                 if global_cap > local_cap {
                     unsafe { host_remap(global_cap); super::LOCAL_CAPACITY = global_cap; }
@@ -97,7 +163,7 @@ impl ShmApi {
             }
 
             if sb.bump_allocator.compare_exchange(
-                current_alloc, current_alloc + PAGE_SIZE, Ordering::SeqCst, Ordering::SeqCst,
+                current_alloc, current_alloc + size, Ordering::SeqCst, Ordering::SeqCst,
             ).is_ok() {
                 return current_alloc;
             }
@@ -0,0 +1,349 @@
+// Borrowed record iteration and in-place record writes.
+//
+// `read_all_stream_records` / `read_all_io_records` copy every payload into
+// the guest heap.  `ShmApi::records` / `ShmApi::io_records` walk the same
+// page chain but yield a `RecordRef` per record that points into SHM:
+//
+//   - a record that fits in one page is a plain `&[u8]` into the page data;
+//   - a record that straddles pages is a scatter view (start page + offset)
+//     whose `chunks()` yields one slice per page, with no allocation.
+//
+// Only the 8-byte `[len:u32][origin:u32]` header is ever copied.  The pages
+// stay valid for the rest of the guest call: the host frees a slot only after
+// every node that reads it has returned.
+//
+// `RecordWriter` is the write side: `push_with(len, fill)` reserves the
+// header and `len` bytes in the tail page and hands the caller the payload
+// bytes to fill in place; the page cursor is published once the record is
+// complete.
+
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+use alloc::vec::Vec;
+use common::*;
+use super::{ShmApi, SHM_BASE};
+use super::stream_area::{chain_append_prefixed, map_page};
+
+/// `[len:u32][origin:u32]` in front of every payload.
+const RECORD_HEADER_SIZE: usize = 8;
+
+// ── Chain position ──
+
+/// A byte position in a page chain: `page == 0` is the end of the chain.
+#[derive(Clone, Copy)]
+struct ChainPos {
+    page: ShmOffset,
+    offset: usize,
+}
+
+impl ChainPos {
+    /// Moves past exhausted pages; the page holding the next byte, or `None`
+    /// at the end of the chain.
+    fn settle(&mut self) -> Option<&'static Page> {
+        while self.page != 0 {
+            let page = map_page(self.page)?;
+            if self.offset < page.cursor.load(Ordering::Acquire) as usize {
+                return Some(page);
+            }
+            self.page = page.next_offset.load(Ordering::Acquire) as ShmOffset;
+            self.offset = 0;
+        }
+        None
+    }
+
+    /// Copies `dest.len()` bytes, crossing pages; `false` if the chain ends first.
+    fn read(&mut self, mut dest: &mut [u8]) -> bool {
+        while !dest.is_empty() {
+            let Some(page) = self.settle() else { return false };
+            let written = page.cursor.load(Ordering::Acquire) as usize;
+            let n = core::cmp::min(written - self.offset, dest.len());
+            unsafe {
+                core::ptr::copy_nonoverlapping(page.data.as_ptr().add(self.offset), dest.as_mut_ptr(), n);
+            }
+            self.offset += n;
+            dest = &mut dest[n..];
+        }
+        true
+    }
+
+    /// Skips `len` bytes without copying; `false` if the chain ends first.
+    fn skip(&mut self, mut len: usize) -> bool {
+        while len > 0 {
+            let Some(page) = self.settle() else { return false };
+            let written = page.cursor.load(Ordering::Acquire) as usize;
+            let n = core::cmp::min(written - self.offset, len);
+            self.offset += n;
+            len -= n;
+        }
+        true
+    }
+}
+
+// ── Borrowed records ──
+
+/// One record of a slot, borrowed from its pages.
+#[derive(Clone, Copy)]
+pub struct RecordRef<'a> {
+    origin: u32,
+    len: usize,
+    body: Body<'a>,
+}
+
+#[derive(Clone, Copy)]
+enum Body<'a> {
+    Contiguous(&'a [u8]),
+    /// Starts at `start` and continues along `next_offset`.
+    Scattered { start: ChainPos },
+}
+
+impl<'a> RecordRef<'a> {
+    /// The writer id (stream) or slot (I/O) stored with the record.
+    pub fn origin(&self) -> u32 {
+        self.origin
+    }
+
+    /// Payload length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The payload as one slice, or `None` when it spans pages.
+    pub fn as_slice(&self) -> Option<&'a [u8]> {
+        match self.body {
+            Body::Contiguous(bytes) => Some(bytes),
+            Body::Scattered { .. } => None,
+        }
+    }
+
+    /// The payload as one slice per page it occupies.
+    pub fn chunks(&self) -> Chunks<'a> {
+        match self.body {
+            Body::Contiguous(bytes) => Chunks { single: Some(bytes), pos: ChainPos { page: 0, offset: 0 }, remaining: 0, _shm: PhantomData },
+            Body::Scattered { start } => Chunks { single: None, pos: start, remaining: self.len, _shm: PhantomData },
+        }
+    }
+
+    /// The payload as one slice: borrowed from SHM when it lies in one page,
+    /// otherwise gathered into `scratch` (cleared first), so a loop that
+    /// reuses `scratch` allocates only for its largest straddling record.
+    pub fn bytes<'b>(&self, scratch: &'b mut Vec<u8>) -> &'b [u8]
+    where
+        'a: 'b,
+    {
+        if let Body::Contiguous(bytes) = self.body {
+            return bytes;
+        }
+        scratch.clear();
+        scratch.reserve(self.len);
+        for chunk in self.chunks() {
+            scratch.extend_from_slice(chunk);
+        }
+        scratch
+    }
+
+    /// The payload, copied.
+    pub fn to_vec(self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.len);
+        for chunk in self.chunks() {
+            out.extend_from_slice(chunk);
+        }
+        out
+    }
+}
+
+/// Slices of one record's payload, page by page.
+pub struct Chunks<'a> {
+    single: Option<&'a [u8]>,
+    pos: ChainPos,
+    remaining: usize,
+    _shm: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if let Some(bytes) = self.single.take() {
+            return Some(bytes);
+        }
+        if self.remaining == 0 {
+            return None;
+        }
+        let page = self.pos.settle()?;
+        let written = page.cursor.load(Ordering::Acquire) as usize;
+        let n = core::cmp::min(written - self.pos.offset, self.remaining);
+        let chunk = unsafe { core::slice::from_raw_parts(page.data.as_ptr().add(self.pos.offset), n) };
+        self.pos.offset += n;
+        self.remaining -= n;
+        Some(chunk)
+    }
+}
+
+/// Every complete record of a page chain, in order, borrowed from SHM.
+/// Stops at the end of the chain or at a record still being written.
+pub struct Records<'a> {
+    pos: ChainPos,
+    _shm: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Records<'a> {
+    pub(super) fn new(head: ShmOffset) -> Self {
+        Records { pos: ChainPos { page: head, offset: 0 }, _shm: PhantomData }
+    }
+
+    /// Records from byte `offset` of page `page` on.
+    pub(super) fn resume(page: ShmOffset, offset: usize) -> Self {
+        Records { pos: ChainPos { page, offset }, _shm: PhantomData }
+    }
+
+    /// Page and byte offset just past the last record returned.
+    pub(super) fn position(&self) -> (ShmOffset, usize) {
+        (self.pos.page, self.pos.offset)
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = RecordRef<'a>;
+
+    fn next(&mut self) -> Option<RecordRef<'a>> {
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        if !self.pos.read(&mut header) {
+            return None;
+        }
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let origin = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if len == 0 {
+            return Some(RecordRef { origin, len, body: Body::Contiguous(&[]) });
+        }
+        let page = self.pos.settle()?;
+        let written = page.cursor.load(Ordering::Acquire) as usize;
+        if written - self.pos.offset >= len {
+            let bytes = unsafe { core::slice::from_raw_parts(page.data.as_ptr().add(self.pos.offset), len) };
+            self.pos.offset += len;
+            return Some(RecordRef { origin, len, body: Body::Contiguous(bytes) });
+        }
+        let start = self.pos;
+        if !self.pos.skip(len) {
+            return None;
+        }
+        Some(RecordRef { origin, len, body: Body::Scattered { start } })
+    }
+}
+
+// ── In-place writer ──
+
+/// Appends records to one slot, filling each in place in the tail page.
+///
+/// A record that does not fit in the tail page's free space starts a fresh
+/// page (the rest of the old one stays unused); one larger than a whole page
+/// of the slot's class is filled in a heap buffer and appended with the
+/// copying path.
+pub struct RecordWriter {
+    head: &'static AtomicPageId,
+    tail: &'static AtomicPageId,
+    class: u32,
+    origin: u32,
+    /// Stream slot whose signal is bumped after each record; `None` for I/O
+    /// slots, which nobody waits on.
+    signal: Option<u32>,
+}
+
+impl RecordWriter {
+    /// Reserves a `len`-byte record and calls `fill` with its payload bytes
+    /// (stale page contents; `fill` must write all of them).
+    pub fn push_with<F: FnOnce(&mut [u8])>(&mut self, len: usize, fill: F) {
+        let need = RECORD_HEADER_SIZE + len;
+        if need > page_class_data_size(self.class) {
+            let mut payload = alloc::vec![0u8; len];
+            fill(&mut payload);
+            chain_append_prefixed(self.head, self.tail, self.class, self.origin, &payload);
+            if let Some(slot) = self.signal {
+                ShmApi::notify_append(slot);
+            }
+            return;
+        }
+
+        let mut tail_offset = self.tail.load(Ordering::Acquire) as ShmOffset;
+        let mut cursor = 0usize;
+        if tail_offset != 0 {
+            let page = unsafe { &*((SHM_BASE + tail_offset as usize) as *const Page) };
+            cursor = page.cursor.load(Ordering::Relaxed) as usize;
+            if page.capacity().saturating_sub(cursor) < need {
+                let new_offset = ShmApi::try_allocate_page_in(self.class);
+                page.next_offset.store(new_offset as u64, Ordering::Release);
+                self.tail.store(new_offset as u64, Ordering::Release);
+                tail_offset = new_offset;
+                cursor = 0;
+            }
+        } else {
+            tail_offset = ShmApi::try_allocate_page_in(self.class);
+            self.head.store(tail_offset as u64, Ordering::Release);
+            self.tail.store(tail_offset as u64, Ordering::Release);
+        }
+
+        let page = unsafe { &mut *((SHM_BASE + tail_offset as usize) as *mut Page) };
+        let record = unsafe { core::slice::from_raw_parts_mut(page.data.as_mut_ptr().add(cursor), need) };
+        record[..4].copy_from_slice(&(len as u32).to_le_bytes());
+        record[4..RECORD_HEADER_SIZE].copy_from_slice(&self.origin.to_le_bytes());
+        fill(&mut record[RECORD_HEADER_SIZE..]);
+        page.cursor.store((cursor + need) as ShmOffset, Ordering::Release);
+        if let Some(slot) = self.signal {
+            ShmApi::notify_append(slot);
+        }
+    }
+
+    /// Appends `payload` as one record.
+    pub fn push(&mut self, payload: &[u8]) {
+        self.push_with(payload.len(), |dest| dest.copy_from_slice(payload));
+    }
+}
+
+// ── ShmApi entry points ──
+
+impl ShmApi {
+    /// Every record of stream slot `id`, borrowed from its pages.
+    ///
+    /// ```ignore
+    /// let mut scratch = Vec::new();
+    /// for rec in ShmApi::records(slot) {
+    ///     let line = rec.bytes(&mut scratch);   // copies only if it spans pages
+    /// }
+    /// ```
+    pub fn records(id: u32) -> Records<'static> {
+        Records::new(Self::superblock().writer_head(id as usize).load(Ordering::Acquire) as ShmOffset)
+    }
+
+    /// Every record of I/O slot `io_slot`, borrowed from its pages.
+    pub fn io_records(io_slot: u32) -> Records<'static> {
+        Records::new(Self::superblock().io_head(io_slot as usize).load(Ordering::Acquire) as ShmOffset)
+    }
+
+    /// In-place writer for stream slot `writer_id`; records carry
+    /// `writer_id` as their origin, like `append_stream_data`.
+    pub fn stream_writer(writer_id: u32) -> RecordWriter {
+        let sb = Self::superblock();
+        RecordWriter {
+            head: sb.writer_head(writer_id as usize),
+            tail: sb.writer_tail(writer_id as usize),
+            class: sb.stream_page_class(writer_id as usize).load(Ordering::Relaxed) as u32,
+            origin: writer_id,
+            signal: Some(writer_id),
+        }
+    }
+
+    /// In-place writer for I/O slot `io_slot`, like `append_io_data`.
+    pub fn io_writer(io_slot: u32) -> RecordWriter {
+        let sb = Self::superblock();
+        RecordWriter {
+            head: sb.io_head(io_slot as usize),
+            tail: sb.io_tail(io_slot as usize),
+            class: sb.io_page_class(io_slot as usize).load(Ordering::Relaxed) as u32,
+            origin: io_slot,
+            signal: None,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// Guest side of remote atomics: operations on a named atomic owned by
+// another node of the DAG's mesh, issued mid-computation (the host half is
+// `host/src/runtime/remote/atomics.rs`).
+//
+//   let ticket = ShmApi::remote_fetch_add(0, "next_task", 1);        // work queue on node 0
+//   let leader = ShmApi::remote_compare_swap(0, "leader", 0, me + 1) == 0;
+//   let done   = ShmApi::remote_read_atomic(0, "finished");
+//
+// The owner's copy lives at the index the name has in this node's registry,
+// as with the `RemoteAtomic*` DAG nodes, so every node must register its
+// remote atomics in the same order.  Each call is a round trip to the
+// runner, and over RDMA to the peer's NIC: far slower than `get_named_atomic`.
+
+use common::*;
+use super::ShmApi;
+
+extern "C" {
+    fn host_remote_faa(peer: u32, ptr: WasmPtr, len: WasmPtr, add: u64) -> u64;
+    fn host_remote_cas(peer: u32, ptr: WasmPtr, len: WasmPtr, compare: u64, swap: u64) -> u64;
+    fn host_remote_read_atomic(peer: u32, ptr: WasmPtr, len: WasmPtr) -> u64;
+}
+
+impl ShmApi {
+    /// Adds `add` to atomic `name` on mesh node `peer` and returns its
+    /// previous value.  `peer` may be this node.
+    ///
+    /// # Panics
+    /// Traps if the DAG has no `rdma` block, `peer` is not a mesh node, or
+    /// the peer cannot be reached.
+    pub fn remote_fetch_add(peer: u32, name: &str, add: u64) -> u64 {
+        unsafe { host_remote_faa(peer, name.as_ptr() as WasmPtr, name.len() as WasmPtr, add) }
+    }
+
+    /// Stores `swap` in atomic `name` on mesh node `peer` if it holds
+    /// `compare`, and returns its previous value — equal to `compare` iff the
+    /// swap happened.  Same traps as [`Self::remote_fetch_add`].
+    pub fn remote_compare_swap(peer: u32, name: &str, compare: u64, swap: u64) -> u64 {
+        unsafe { host_remote_cas(peer, name.as_ptr() as WasmPtr, name.len() as WasmPtr, compare, swap) }
+    }
+
+    /// Current value of atomic `name` on mesh node `peer`.  Same traps as
+    /// [`Self::remote_fetch_add`].
+    pub fn remote_read_atomic(peer: u32, name: &str) -> u64 {
+        unsafe { host_remote_read_atomic(peer, name.as_ptr() as WasmPtr, name.len() as WasmPtr) }
+    }
+}
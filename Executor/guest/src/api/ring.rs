@@ -0,0 +1,181 @@
+// Guest side of ring slots (see "Ring slots" in `common`; the host half is
+// `host/src/runtime/mem_operation/ring.rs`).
+//
+//   ShmApi::ring_push(slot, &record);                          // sleeps while full
+//   if !ShmApi::try_ring_push(slot, &record) { … }             // full: back off
+//   while let Some(rec) = ShmApi::ring_recv(slot, me, u32::MAX) { … }
+//   ShmApi::close_stream(slot);                                // producer is done
+//
+// A ring has one producer and `consumers` consumers, numbered `0..consumers`
+// in the slot declaration; every consumer reads every record.  Records are
+// `[len][origin][payload]` like a chain's, but never span pages, so a record
+// must fit in one page of the slot's class.
+
+use core::sync::atomic::Ordering;
+use alloc::vec::Vec;
+use common::*;
+use super::ShmApi;
+use super::stream_area::{ensure_mapped, map_page};
+
+extern "C" {
+    fn host_ring_wait(slot: u32, observed: u32);
+    fn host_ring_wake(slot: u32);
+    fn host_stream_wait(slot: u32, observed: u32, timeout_ms: u32) -> u32;
+}
+
+const RECORD_HEADER_SIZE: usize = 8;
+
+/// Header of ring slot `slot`; traps if the slot is an ordinary chain.
+fn ring(slot: u32) -> &'static RingHeader {
+    let header = ShmApi::superblock().stream_ring(slot as usize).load(Ordering::Acquire);
+    if header == 0 {
+        panic!("stream slot {} is not a ring slot", slot);
+    }
+    if !ensure_mapped(header + PAGE_SIZE) {
+        panic!("ring slot {}: header {:#x} lies past the SHM capacity", slot, header);
+    }
+    unsafe { &*((super::SHM_BASE + header as usize) as *const RingHeader) }
+}
+
+fn ring_page(h: &RingHeader, seq: u64) -> &'static Page {
+    map_page(h.page(seq) as ShmOffset).expect("ring page lies past the SHM capacity")
+}
+
+fn record_ptr(page: &Page, offset: usize) -> *mut u8 {
+    unsafe { (page as *const Page as *mut u8).add(PAGE_HEADER_SIZE + offset) }
+}
+
+fn read_u32(ptr: *const u8) -> u32 {
+    let mut word = [0u8; 4];
+    unsafe { core::ptr::copy_nonoverlapping(ptr, word.as_mut_ptr(), 4) };
+    u32::from_le_bytes(word)
+}
+
+impl ShmApi {
+    /// Append `payload` to ring slot `slot`, or return `false` without
+    /// writing if the ring is full (its slowest consumer still reads the page
+    /// the record needs).  Only the slot's single producer may call this.
+    ///
+    /// # Panics
+    /// Traps if `slot` is not a ring, or if the record does not fit in one
+    /// page of the slot's class.
+    pub fn try_ring_push(slot: u32, payload: &[u8]) -> bool {
+        let h = ring(slot);
+        let need = RECORD_HEADER_SIZE + payload.len();
+        let mut seq = h.tail_seq.load(Ordering::Relaxed);
+        let mut page = ring_page(h, seq);
+        if need > page.capacity() {
+            panic!("ring slot {}: {}-byte record exceeds a {}-byte page", slot, payload.len(), page.capacity());
+        }
+        let mut cursor = page.cursor.load(Ordering::Relaxed) as usize;
+        if page.capacity() - cursor < need {
+            if !h.has_space_for(seq + 1) {
+                return false;
+            }
+            seq += 1;
+            page = ring_page(h, seq);
+            page.cursor.store(0, Ordering::Relaxed);
+            h.tail_seq.store(seq, Ordering::Release);
+            cursor = 0;
+        }
+        unsafe {
+            let rec = record_ptr(page, cursor);
+            core::ptr::copy_nonoverlapping((payload.len() as u32).to_le_bytes().as_ptr(), rec, 4);
+            core::ptr::copy_nonoverlapping(slot.to_le_bytes().as_ptr(), rec.add(4), 4);
+            core::ptr::copy_nonoverlapping(payload.as_ptr(), rec.add(RECORD_HEADER_SIZE), payload.len());
+        }
+        page.cursor.store((cursor + need) as ShmOffset, Ordering::Release);
+        Self::notify_append(slot);
+        true
+    }
+
+    /// Append `payload` to ring slot `slot`, sleeping while the ring is full
+    /// until a consumer frees a page.  Same contract as [`Self::try_ring_push`].
+    pub fn ring_push(slot: u32, payload: &[u8]) {
+        let h = ring(slot);
+        loop {
+            // Load `space` before trying: a consumer that moves on after the
+            // attempt changes it, so the wait below returns at once.
+            let observed = h.space.load(Ordering::Acquire);
+            if Self::try_ring_push(slot, payload) {
+                return;
+            }
+            let waiting = observed | STREAM_WAITERS;
+            if observed != waiting
+                && h.space
+                    .compare_exchange(observed, waiting, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+            {
+                continue;
+            }
+            unsafe { host_ring_wait(slot, waiting) };
+        }
+    }
+
+    /// Consumer `consumer`'s next record from ring slot `slot`, or `None` if
+    /// it has read everything published so far.  Each consumer index must be
+    /// used by one caller only.
+    ///
+    /// # Panics
+    /// Traps if `slot` is not a ring or `consumer` is not below its
+    /// declared consumer count.
+    pub fn try_ring_recv(slot: u32, consumer: u32) -> Option<Vec<u8>> {
+        let h = ring(slot);
+        if consumer >= h.consumer_count {
+            panic!("ring slot {}: no consumer {} ({} declared)", slot, consumer, h.consumer_count);
+        }
+        let cur = &h.consumers[consumer as usize];
+        loop {
+            let seq = cur.page_seq.load(Ordering::Relaxed);
+            let offset = cur.offset.load(Ordering::Relaxed) as usize;
+            let page = ring_page(h, seq);
+            if offset < page.cursor.load(Ordering::Acquire) as usize {
+                let rec = record_ptr(page, offset);
+                let len = read_u32(rec) as usize;
+                let payload = unsafe { core::slice::from_raw_parts(rec.add(RECORD_HEADER_SIZE), len) }.to_vec();
+                cur.offset.store((offset + RECORD_HEADER_SIZE + len) as u32, Ordering::Relaxed);
+                return Some(payload);
+            }
+            if h.tail_seq.load(Ordering::Acquire) == seq {
+                return None;
+            }
+            // The producer moved on, so the cursor read above may be stale:
+            // re-check before leaving the page.
+            if page.cursor.load(Ordering::Acquire) as usize > offset {
+                continue;
+            }
+            cur.offset.store(0, Ordering::Relaxed);
+            cur.page_seq.store(seq + 1, Ordering::Release);
+            if stream_signal_append(&h.space) {
+                unsafe { host_ring_wake(slot) }
+            }
+        }
+    }
+
+    /// Consumer `consumer`'s next record from ring slot `slot`, sleeping
+    /// until one arrives.  `None` once the producer has called
+    /// `close_stream` and this consumer has read everything, or when
+    /// `timeout_ms` elapses first (`u32::MAX` waits without a deadline).
+    pub fn ring_recv(slot: u32, consumer: u32, timeout_ms: u32) -> Option<Vec<u8>> {
+        let signal = Self::superblock().stream_signal(slot as usize);
+        let mut remaining = timeout_ms;
+        loop {
+            let observed = signal.load(Ordering::Acquire);
+            if let Some(record) = Self::try_ring_recv(slot, consumer) {
+                return Some(record);
+            }
+            if observed & STREAM_CLOSED != 0 || remaining == 0 {
+                return None;
+            }
+            let waiting = observed | STREAM_WAITERS;
+            if observed != waiting
+                && signal
+                    .compare_exchange(observed, waiting, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+            {
+                continue;
+            }
+            remaining = unsafe { host_stream_wait(slot, waiting, remaining) };
+        }
+    }
+}
@@ -48,7 +48,7 @@ impl ShmApi {
 
     /// Inserts a keyed data node into the shared lock-free hash map.
     /// Multiple writers may concurrently insert under the same `key_hash`; all nodes are
-    /// prepended to the bucket's linked list and resolved later by the Manager via a policy.
+    /// prepended to the bucket's linked list and stay there (`ResolveSharedState` skips them).
     pub fn insert_shared_data(key_hash: u32, writer_id: u32, data: &[u8]) {
         let map_base_offset = Self::get_bucket_array();
         let bucket_idx = (key_hash as usize) % BUCKET_COUNT;
@@ -66,6 +66,7 @@ impl ShmApi {
             let header = node_ptr as *mut ChainNodeHeader;
             (*header).writer_id = writer_id;
             (*header).data_len = data.len() as u32;
+            (*header).tag = 0;
             let data_dest = node_ptr.add(core::mem::size_of::<ChainNodeHeader>());
             core::ptr::copy_nonoverlapping(data.as_ptr(), data_dest, data.len());
         }
@@ -122,8 +123,8 @@ impl ShmApi {
     }
 
     /// Writes `data` as shared state for `task_name`.
-    /// The node enters the bucket conflict pool; the Manager resolves concurrent writers
-    /// using the active `ConsumptionPolicy` and commits the winner to the Registry.
+    /// The node enters the bucket conflict pool; a `ResolveSharedState` DAG node resolves
+    /// concurrent writers with its `ConsumptionPolicy` and commits the winner to the Registry.
     pub fn write_shared_state(task_name: &str, writer_id: u32, data: &[u8]) {
         let reg_idx = Self::resolve_name_to_index(task_name);
         let map_base_offset = Self::get_bucket_array();
@@ -145,6 +146,7 @@ impl ShmApi {
             (*header).writer_id = writer_id;
             (*header).data_len = total_len as u32;
             (*header).registry_index = reg_idx;
+            (*header).tag = SHARED_STATE_TAG;
             (*header).next_payload_page = 0;
 
             let head_capacity = PAGE_SIZE as usize - core::mem::size_of::<ChainNodeHeader>();
@@ -203,7 +205,7 @@ impl ShmApi {
     pub fn read_shared_state(task_name: &str) -> Option<Vec<u8>> {
         let reg_idx = Self::resolve_name_to_index(task_name);
         let entry_ptr = unsafe {
-            let base = SHM_BASE + REGISTRY_OFFSET as usize;
+            let base = SHM_BASE + Self::superblock().layout.registry_offset as usize;
             (base + reg_idx as usize * 64) as *const RegistryEntry
         };
         let entry = unsafe { &*entry_ptr };
@@ -251,7 +253,7 @@ impl ShmApi {
 
         let reg_idx = Self::resolve_name_to_index(task_name);
         let entry_ptr = unsafe {
-            let base = SHM_BASE + REGISTRY_OFFSET as usize;
+            let base = SHM_BASE + Self::superblock().layout.registry_offset as usize;
             (base + reg_idx as usize * 64) as *const RegistryEntry
         };
         let entry = unsafe { &*entry_ptr };
@@ -322,7 +324,7 @@ impl ShmApi {
     pub fn lseek_shared_state(task_name: &str, current_pos: u32, whence: SeekFrom) -> Option<u32> {
         let reg_idx = Self::resolve_name_to_index(task_name);
         let entry_ptr = {
-            let base = SHM_BASE + REGISTRY_OFFSET as usize;
+            let base = SHM_BASE + Self::superblock().layout.registry_offset as usize;
             (base + reg_idx as usize * 64) as *const RegistryEntry
         };
         let entry = unsafe { &*entry_ptr };
@@ -10,31 +10,53 @@ extern "C" {
 // ─── Shared page-chain core helpers ──────────────────────────────────────────
 //
 // These `pub(super)` free functions implement the page-chain mechanics without
-// being tied to a specific slot array.  Both the stream API (`writer_heads` /
-// `writer_tails`) and the I/O API (`io_heads` / `io_tails`) delegate to them,
+// being tied to a specific slot array.  Both the stream API (`writer_head` /
+// `writer_tail`) and the I/O API (`io_head` / `io_tail`) delegate to them,
 // so the logic lives in exactly one place.
+//
+// Pages are sized by the class in their own header, so the readers handle
+// chains that mix page classes; only the writer needs the slot's class.
+
+/// Makes sure the local mapping covers `[0, required_cap)`, remapping up to
+/// the global capacity if another process grew the SHM.  `false` if even the
+/// global capacity is short of it.
+pub(super) fn ensure_mapped(required_cap: ShmOffset) -> bool {
+    let local_cap = unsafe { super::LOCAL_CAPACITY };
+    if required_cap > local_cap {
+        let global_cap = ShmApi::superblock().global_capacity.load(Ordering::Acquire);
+        if global_cap < required_cap {
+            return false;
+        }
+        unsafe { host_remap(global_cap); super::LOCAL_CAPACITY = global_cap; }
+    }
+    true
+}
+
+/// The page at `offset`, with the whole page (all of it, for a larger
+/// class) mapped.  `None` if it lies past the SHM capacity.
+pub(super) fn map_page(offset: ShmOffset) -> Option<&'static Page> {
+    if !ensure_mapped(offset + PAGE_SIZE) { return None; }
+    let page = unsafe { &*((SHM_BASE + offset as usize) as *const Page) };
+    if !ensure_mapped(offset + page.span()) { return None; }
+    Some(page)
+}
 
 /// Append raw bytes into the page chain identified by `(head, tail)` atomics.
-/// Allocates new 4 KiB pages from the bump pool as needed.
-pub(super) fn chain_append_raw(head: &AtomicPageId, tail: &AtomicPageId, mut data: &[u8]) {
+/// Allocates new pages of `class` as needed; a partly filled tail page of
+/// another class (from a splice) is filled first.
+pub(super) fn chain_append_raw(head: &AtomicPageId, tail: &AtomicPageId, class: u32, mut data: &[u8]) {
     let mut tail_offset = tail.load(Ordering::Acquire) as ShmOffset;
     if tail_offset == 0 {
-        tail_offset = ShmApi::try_allocate_page();
-        let new_page = unsafe { &mut *((SHM_BASE + tail_offset as usize) as *mut Page) };
-        new_page.next_offset.store(0, Ordering::Relaxed);
-        new_page.cursor.store(0, Ordering::Relaxed);
+        tail_offset = ShmApi::try_allocate_page_in(class);
         head.store(tail_offset as u64, Ordering::Release);
         tail.store(tail_offset as u64, Ordering::Release);
     }
     while !data.is_empty() {
         let tail_page = unsafe { &mut *((SHM_BASE + tail_offset as usize) as *mut Page) };
         let current_cursor = tail_page.cursor.load(Ordering::Relaxed);
-        let space_left = PAGE_DATA_SIZE as ShmOffset - current_cursor;
+        let space_left = (tail_page.capacity() as ShmOffset).saturating_sub(current_cursor);
         if space_left == 0 {
-            let new_offset = ShmApi::try_allocate_page();
-            let new_page = unsafe { &mut *((SHM_BASE + new_offset as usize) as *mut Page) };
-            new_page.next_offset.store(0, Ordering::Relaxed);
-            new_page.cursor.store(0, Ordering::Relaxed);
+            let new_offset = ShmApi::try_allocate_page_in(class);
             tail_page.next_offset.store(new_offset as u64, Ordering::Release);
             tail.store(new_offset as u64, Ordering::Release);
             tail_offset = new_offset;
@@ -51,32 +73,25 @@ pub(super) fn chain_append_raw(head: &AtomicPageId, tail: &AtomicPageId, mut dat
 }
 
 /// Write a 4-byte LE length header, 4-byte LE origin, followed by `payload` into `(head, tail)`.
-pub(super) fn chain_append_prefixed(head: &AtomicPageId, tail: &AtomicPageId, origin: u32, payload: &[u8]) {
-    chain_append_raw(head, tail, &(payload.len() as u32).to_le_bytes());
-    chain_append_raw(head, tail, &origin.to_le_bytes());
-    chain_append_raw(head, tail, payload);
+pub(super) fn chain_append_prefixed(
+    head: &AtomicPageId, tail: &AtomicPageId, class: u32, origin: u32, payload: &[u8],
+) {
+    chain_append_raw(head, tail, class, &(payload.len() as u32).to_le_bytes());
+    chain_append_raw(head, tail, class, &origin.to_le_bytes());
+    chain_append_raw(head, tail, class, payload);
 }
 
 /// Walk the page chain starting at `head_offset` and return the last complete
 /// length-prefixed record as (origin, payload).  Returns `None` when the chain is empty.
 pub(super) fn chain_read_latest(head_offset: ShmOffset) -> Option<(u32, Vec<u8>)> {
     if head_offset == 0 { return None; }
-    let sb = ShmApi::superblock();
     let mut current_offset = head_offset;
     let mut cursor_in_page: ShmOffset = 0;
 
     let mut read_exact = |mut dest: &mut [u8]| -> bool {
         while !dest.is_empty() {
             if current_offset == 0 { return false; }
-            let required_cap = current_offset + PAGE_SIZE;
-            let local_cap = unsafe { super::LOCAL_CAPACITY };
-            if required_cap > local_cap {
-                let global_cap = sb.global_capacity.load(Ordering::Acquire);
-                if global_cap >= required_cap {
-                    unsafe { host_remap(global_cap); super::LOCAL_CAPACITY = global_cap; }
-                } else { return false; }
-            }
-            let page = unsafe { &*((SHM_BASE + current_offset as usize) as *const Page) };
+            let Some(page) = map_page(current_offset) else { return false };
             let page_written = page.cursor.load(Ordering::Acquire);
             let available = page_written.saturating_sub(cursor_in_page);
             if available == 0 {
@@ -115,7 +130,6 @@ pub(super) fn chain_read_latest(head_offset: ShmOffset) -> Option<(u32, Vec<u8>)
 /// length-prefixed record as (origin, payload) in order.  Returns an empty Vec when the chain is empty.
 pub(super) fn chain_read_all(head_offset: ShmOffset) -> Vec<(u32, Vec<u8>)> {
     if head_offset == 0 { return Vec::new(); }
-    let sb = ShmApi::superblock();
     let mut current_offset = head_offset;
     let mut cursor_in_page: ShmOffset = 0;
 
@@ -123,15 +137,7 @@ pub(super) fn chain_read_all(head_offset: ShmOffset) -> Vec<(u32, Vec<u8>)> {
         let mut dest = dest;
         while !dest.is_empty() {
             if current_offset == 0 { return false; }
-            let required_cap = current_offset + PAGE_SIZE;
-            let local_cap = unsafe { super::LOCAL_CAPACITY };
-            if required_cap > local_cap {
-                let global_cap = sb.global_capacity.load(Ordering::Acquire);
-                if global_cap >= required_cap {
-                    unsafe { host_remap(global_cap); super::LOCAL_CAPACITY = global_cap; }
-                } else { return false; }
-            }
-            let page = unsafe { &*((SHM_BASE + current_offset as usize) as *const Page) };
+            let Some(page) = map_page(current_offset) else { return false };
             let page_written = page.cursor.load(Ordering::Acquire);
             let available = page_written.saturating_sub(cursor_in_page);
             if available == 0 {
@@ -175,7 +181,6 @@ pub(super) fn chain_read_all(head_offset: ShmOffset) -> Vec<(u32, Vec<u8>)> {
 /// would need millions of simultaneous allocations).
 pub(super) fn chain_for_each<F: FnMut(u32, &[u8])>(head_offset: ShmOffset, mut on_record: F) {
     if head_offset == 0 { return; }
-    let sb = ShmApi::superblock();
     let mut current_offset = head_offset;
     let mut cursor_in_page: ShmOffset = 0;
 
@@ -183,15 +188,7 @@ pub(super) fn chain_for_each<F: FnMut(u32, &[u8])>(head_offset: ShmOffset, mut o
         let mut dest = dest;
         while !dest.is_empty() {
             if current_offset == 0 { return false; }
-            let required_cap = current_offset + PAGE_SIZE;
-            let local_cap = unsafe { super::LOCAL_CAPACITY };
-            if required_cap > local_cap {
-                let global_cap = sb.global_capacity.load(Ordering::Acquire);
-                if global_cap >= required_cap {
-                    unsafe { host_remap(global_cap); super::LOCAL_CAPACITY = global_cap; }
-                } else { return false; }
-            }
-            let page = unsafe { &*((SHM_BASE + current_offset as usize) as *const Page) };
+            let Some(page) = map_page(current_offset) else { return false };
             let page_written = page.cursor.load(Ordering::Acquire);
             let available = page_written.saturating_sub(cursor_in_page);
             if available == 0 {
@@ -230,32 +227,30 @@ pub(super) fn chain_for_each<F: FnMut(u32, &[u8])>(head_offset: ShmOffset, mut o
 
 impl ShmApi {
     // Use case: single-point output, log streams, one-to-one data pipelines (Map → Reduce).
-    // Stream slots (0..STREAM_SLOT_COUNT) are entirely free for application use —
+    // Stream slots (0..stream_slot_count) are entirely free for application use —
     // no slots are reserved.  Host input/output uses the separate I/O area instead.
 
     /// Append a length-prefixed record to stream `writer_id`.
     ///
-    /// All stream slot IDs in `[0, STREAM_SLOT_COUNT)` are valid.
+    /// All stream slot IDs below the region's `stream_slot_count` are valid;
+    /// others trap.  Wakes consumers blocked in `wait_for_records` on the slot.
     pub fn append_stream_data(writer_id: u32, payload: &[u8]) {
-        debug_assert!(
-            (writer_id as usize) < STREAM_SLOT_COUNT,
-            "stream slot {} out of range (STREAM_SLOT_COUNT={})",
-            writer_id, STREAM_SLOT_COUNT,
-        );
         let sb = Self::superblock();
         chain_append_prefixed(
-            &sb.writer_heads[writer_id as usize],
-            &sb.writer_tails[writer_id as usize],
+            sb.writer_head(writer_id as usize),
+            sb.writer_tail(writer_id as usize),
+            sb.stream_page_class(writer_id as usize).load(Ordering::Relaxed) as u32,
             writer_id,
             payload,
         );
+        Self::notify_append(writer_id);
     }
 
     /// Zero-copy contiguous split of input I/O slot `in_io_slot`'s page chain
     /// into `n` stream slots `out_base .. out_base + n`.
     ///
     /// The input is a chain of length-prefixed records (`[len:u32][origin:u32]
-    /// [payload]`) packed tightly across 4 KiB pages, so records straddle page
+    /// [payload]`) packed tightly across pages, so records straddle page
     /// boundaries. We partition it into `n` byte-balanced, **record-aligned**
     /// contiguous segments and install each as a stream slot's chain by
     /// relinking `next_offset` pointers — no payload is copied. The only data
@@ -273,12 +268,12 @@ impl ShmApi {
         let sb = Self::superblock();
         let set_slot = |w: u32, head: ShmOffset, tail: ShmOffset| {
             let s = (out_base + w) as usize;
-            sb.writer_heads[s].store(head as u64, Ordering::Release);
-            sb.writer_tails[s].store(tail as u64, Ordering::Release);
+            sb.writer_head(s).store(head as u64, Ordering::Release);
+            sb.writer_tail(s).store(tail as u64, Ordering::Release);
         };
 
         if n == 0 { return; }
-        let in_head = sb.io_heads[in_io_slot as usize].load(Ordering::Acquire) as ShmOffset;
+        let in_head = sb.io_head(in_io_slot as usize).load(Ordering::Acquire) as ShmOffset;
         if in_head == 0 {
             for w in 0..n { set_slot(w, 0, 0); }
             return;
@@ -384,7 +379,7 @@ impl ShmApi {
                         // Mid-page boundary — copy the [cur_off..pcursor] tail to
                         // a fresh page that heads the next segment, then truncate
                         // this page so it ends worker `worker`'s chain.
-                        let np = Self::try_allocate_page();
+                        let np = Self::try_allocate_page_in(page.class.load(Ordering::Relaxed));
                         let np_page = unsafe { &mut *((SHM_BASE + np as usize) as *mut Page) };
                         let taillen = pcursor - cur_off;
                         unsafe {
@@ -419,20 +414,20 @@ impl ShmApi {
         }
 
         // Ownership of every page has moved to the stream slots.
-        sb.io_heads[in_io_slot as usize].store(0, Ordering::Release);
-        sb.io_tails[in_io_slot as usize].store(0, Ordering::Release);
+        sb.io_head(in_io_slot as usize).store(0, Ordering::Release);
+        sb.io_tail(in_io_slot as usize).store(0, Ordering::Release);
     }
 
     /// Return the most recent record from stream `target_worker_id`.
     pub fn read_latest_stream_data(target_worker_id: u32) -> Option<(u32, Vec<u8>)> {
-        let head = Self::superblock().writer_heads[target_worker_id as usize]
+        let head = Self::superblock().writer_head(target_worker_id as usize)
             .load(Ordering::Acquire) as ShmOffset;
         chain_read_latest(head)
     }
 
     /// Return every record from stream `id` in order.
     pub fn read_all_stream_records(id: u32) -> Vec<(u32, Vec<u8>)> {
-        let head = Self::superblock().writer_heads[id as usize].load(Ordering::Acquire) as ShmOffset;
+        let head = Self::superblock().writer_head(id as usize).load(Ordering::Acquire) as ShmOffset;
         chain_read_all(head)
     }
 
@@ -443,7 +438,7 @@ impl ShmApi {
     /// (the data stays in the SHM page chain; only one record is materialised at
     /// a time).
     pub fn for_each_stream_record<F: FnMut(u32, &[u8])>(id: u32, f: F) {
-        let head = Self::superblock().writer_heads[id as usize].load(Ordering::Acquire) as ShmOffset;
+        let head = Self::superblock().writer_head(id as usize).load(Ordering::Acquire) as ShmOffset;
         chain_for_each(head, f);
     }
 
@@ -456,10 +451,10 @@ impl ShmApi {
         let name = alloc::format!("stream_cursor_{}", id);
         let atomic = Self::get_named_atomic(&name);
         let idx = atomic.load(Ordering::Acquire) as usize;
-        let records = Self::read_all_stream_records(id);
-        if idx >= records.len() { return None; }
+        // Skip the consumed records in place; only the returned one is copied.
+        let rec = Self::records(id).nth(idx)?;
         atomic.fetch_add(1, Ordering::Release);
-        records.into_iter().nth(idx)
+        Some((rec.origin(), rec.to_vec()))
     }
 
     /// Read `length` bytes from stream `writer_id` starting at absolute byte `offset`.
@@ -467,7 +462,7 @@ impl ShmApi {
     pub fn read_stream_range(writer_id: u32, offset: u32, length: usize) -> Option<Vec<u8>> {
         if length == 0 { return Some(Vec::new()); }
         let sb = Self::superblock();
-        let head_offset = sb.writer_heads[writer_id as usize].load(Ordering::Acquire) as ShmOffset;
+        let head_offset = sb.writer_head(writer_id as usize).load(Ordering::Acquire) as ShmOffset;
         if head_offset == 0 { return None; }
 
         let mut result: Vec<u8> = Vec::with_capacity(length);
@@ -477,15 +472,7 @@ impl ShmApi {
         let mut logical_start = 0usize;
 
         while bytes_read < length {
-            let required_cap = current_offset + PAGE_SIZE;
-            let local_cap = unsafe { super::LOCAL_CAPACITY };
-            if required_cap > local_cap {
-                let global_cap = sb.global_capacity.load(Ordering::Acquire);
-                if global_cap >= required_cap {
-                    unsafe { host_remap(global_cap); super::LOCAL_CAPACITY = global_cap; }
-                } else { break; }
-            }
-            let page = unsafe { &*((SHM_BASE + current_offset as usize) as *const Page) };
+            let Some(page) = map_page(current_offset) else { break };
             let written = page.cursor.load(Ordering::Acquire) as usize;
             let logical_end = logical_start + written;
             let copy_start = (offset as usize).max(logical_start);
@@ -520,7 +507,7 @@ impl ShmApi {
     pub fn write_stream_range(writer_id: u32, offset: u32, data: &[u8]) -> bool {
         if data.is_empty() { return true; }
         let sb = Self::superblock();
-        let head_offset = sb.writer_heads[writer_id as usize].load(Ordering::Acquire) as ShmOffset;
+        let head_offset = sb.writer_head(writer_id as usize).load(Ordering::Acquire) as ShmOffset;
         if head_offset == 0 { return false; }
 
         let mut bytes_written = 0usize;
@@ -565,7 +552,7 @@ impl ShmApi {
     /// ```
     pub fn lseek_stream(writer_id: u32, current_pos: u32, whence: SeekFrom) -> Option<u32> {
         let sb = Self::superblock();
-        let head_offset = sb.writer_heads[writer_id as usize].load(Ordering::Acquire) as ShmOffset;
+        let head_offset = sb.writer_head(writer_id as usize).load(Ordering::Acquire) as ShmOffset;
         if head_offset == 0 { return None; }
 
         let mut total: ShmOffset = 0;
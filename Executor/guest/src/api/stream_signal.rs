@@ -0,0 +1,93 @@
+use core::sync::atomic::Ordering;
+
+use common::*;
+
+use super::ShmApi;
+
+extern "C" {
+    fn host_stream_wait(slot: u32, observed: u32, timeout_ms: u32) -> u32;
+    fn host_stream_wake(slot: u32);
+}
+
+/// Outcome of [`ShmApi::wait_for_records`].  Each variant carries the number
+/// of complete records in the slot when the wait ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamWait {
+    /// At least `min_count` records are readable.
+    Ready(usize),
+    /// The producer closed the stream with fewer than `min_count` records.
+    Closed(usize),
+    /// The timeout expired first.
+    TimedOut(usize),
+}
+
+impl ShmApi {
+    /// Block until stream slot `slot` holds at least `min_count` complete
+    /// records, its producer calls `close_stream`, or `timeout_ms` elapses
+    /// (`u32::MAX` waits without a deadline).
+    ///
+    /// Lets a consumer run in the same wave as its producer: instead of
+    /// polling `records(slot)`, it sleeps on the slot's futex word (see
+    /// "Stream signals" in `common`) and is woken by `append_stream_data`,
+    /// stream `RecordWriter`s and `close_stream`.  A typical loop:
+    ///
+    /// ```ignore
+    /// let mut seen = 0;
+    /// loop {
+    ///     let (n, done) = match ShmApi::wait_for_records(slot, seen + 1, u32::MAX) {
+    ///         StreamWait::Ready(n) => (n, false),
+    ///         StreamWait::Closed(n) | StreamWait::TimedOut(n) => (n, true),
+    ///     };
+    ///     for rec in ShmApi::records(slot).skip(seen).take(n - seen) { /* ... */ }
+    ///     seen = n;
+    ///     if done { break; }
+    /// }
+    /// ```
+    ///
+    /// Only appends made through the guest API signal the slot; Python
+    /// producers and host routing do not, so wait on those with a timeout.
+    pub fn wait_for_records(slot: u32, min_count: usize, timeout_ms: u32) -> StreamWait {
+        let signal = Self::superblock().stream_signal(slot as usize);
+        let mut remaining = timeout_ms;
+        loop {
+            // Load the word before counting: an append that lands after the
+            // count changes it, so the futex wait below returns at once.
+            let observed = signal.load(Ordering::Acquire);
+            let count = Self::records(slot).count();
+            if count >= min_count {
+                return StreamWait::Ready(count);
+            }
+            if observed & STREAM_CLOSED != 0 {
+                return StreamWait::Closed(count);
+            }
+            if remaining == 0 {
+                return StreamWait::TimedOut(count);
+            }
+            let waiting = observed | STREAM_WAITERS;
+            if observed != waiting
+                && signal
+                    .compare_exchange(observed, waiting, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+            {
+                continue;
+            }
+            remaining = unsafe { host_stream_wait(slot, waiting, remaining) };
+        }
+    }
+
+    /// Mark stream slot `slot` as complete and wake every consumer blocked in
+    /// `wait_for_records` on it; they return `Closed` once they have read
+    /// what is there.  The host reopens the slot when it frees or clears it.
+    pub fn close_stream(slot: u32) {
+        if stream_signal_close(Self::superblock().stream_signal(slot as usize)) {
+            unsafe { host_stream_wake(slot) }
+        }
+    }
+
+    /// Bumps `slot`'s signal after a published append, waking any waiters.
+    pub(super) fn notify_append(slot: u32) {
+        if stream_signal_append(Self::superblock().stream_signal(slot as usize)) {
+            unsafe { host_stream_wake(slot) }
+        }
+    }
+}
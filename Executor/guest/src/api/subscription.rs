@@ -0,0 +1,110 @@
+// Guest side of stream subscriptions (see "Stream subscriptions" in `common`;
+// the host half is `host/src/runtime/mem_operation/subscriptions.rs`).
+//
+//   let me = ShmApi::subscriber(slot, "audit");
+//   while let Some(rec) = ShmApi::subscription_recv(slot, me, u32::MAX) { … }
+//
+// The producer writes the slot as usual (`append_stream_data`, a stream
+// `RecordWriter`) and ends it with `close_stream`.  Each subscriber reads
+// every record through its own cursor; one subscriber index must be used by
+// one caller at a time.
+
+use core::sync::atomic::Ordering;
+use alloc::vec::Vec;
+use common::*;
+use super::{ShmApi, SHM_BASE};
+use super::records::Records;
+use super::stream_area::ensure_mapped;
+
+extern "C" {
+    fn host_stream_wait(slot: u32, observed: u32, timeout_ms: u32) -> u32;
+}
+
+/// Subscriber table of stream slot `slot`; traps if the slot has none.
+fn table(slot: u32) -> &'static SubscriptionTable {
+    let table = ShmApi::superblock().stream_subscriptions(slot as usize).load(Ordering::Acquire);
+    if table == 0 {
+        panic!("stream slot {} has no subscribers", slot);
+    }
+    if !ensure_mapped(table + PAGE_SIZE) {
+        panic!("stream slot {}: subscriber table {:#x} lies past the SHM capacity", slot, table);
+    }
+    unsafe { &*((SHM_BASE + table as usize) as *const SubscriptionTable) }
+}
+
+fn cursor(slot: u32, sub: u32) -> &'static SubscriberCursor {
+    let t = table(slot);
+    match t.active().get(sub as usize) {
+        Some(c) => c,
+        None => panic!("stream slot {}: no subscriber {} ({} declared)", slot, sub, t.count),
+    }
+}
+
+impl ShmApi {
+    /// Index of the subscriber declared as `name` on stream slot `slot`.
+    ///
+    /// # Panics
+    /// Traps if the slot has no subscribers or none called `name`.
+    pub fn subscriber(slot: u32, name: &str) -> u32 {
+        match table(slot).find(name.as_bytes()) {
+            Some(i) => i as u32,
+            None => panic!("stream slot {}: no subscriber '{}'", slot, name),
+        }
+    }
+
+    /// Subscriber `sub`'s next record from stream slot `slot`, or `None` if
+    /// it has read every complete record.  Advances only this subscriber's
+    /// cursor.
+    ///
+    /// # Panics
+    /// Traps if the slot has no subscribers or `sub` is not below their
+    /// count.
+    pub fn try_subscription_recv(slot: u32, sub: u32) -> Option<Vec<u8>> {
+        let cur = cursor(slot, sub);
+        let page = match cur.page.load(Ordering::Relaxed) as ShmOffset {
+            0 => Self::superblock().writer_head(slot as usize).load(Ordering::Acquire) as ShmOffset,
+            page => page,
+        };
+        let mut records = Records::resume(page, cur.offset.load(Ordering::Relaxed) as usize);
+        let record = records.next()?.to_vec();
+        let (page, offset) = records.position();
+        cur.page.store(page as PageId, Ordering::Release);
+        cur.offset.store(offset as u32, Ordering::Release);
+        cur.records.fetch_add(1, Ordering::AcqRel);
+        Some(record)
+    }
+
+    /// Subscriber `sub`'s next record from stream slot `slot`, sleeping until
+    /// one arrives.  `None` once the producer has called `close_stream` and
+    /// this subscriber has read everything, or when `timeout_ms` elapses
+    /// first (`u32::MAX` waits without a deadline).  Only guest appends wake
+    /// it, as with `wait_for_records`.
+    pub fn subscription_recv(slot: u32, sub: u32, timeout_ms: u32) -> Option<Vec<u8>> {
+        let signal = Self::superblock().stream_signal(slot as usize);
+        let mut remaining = timeout_ms;
+        loop {
+            let observed = signal.load(Ordering::Acquire);
+            if let Some(record) = Self::try_subscription_recv(slot, sub) {
+                return Some(record);
+            }
+            if observed & STREAM_CLOSED != 0 || remaining == 0 {
+                return None;
+            }
+            let waiting = observed | STREAM_WAITERS;
+            if observed != waiting
+                && signal
+                    .compare_exchange(observed, waiting, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+            {
+                continue;
+            }
+            remaining = unsafe { host_stream_wait(slot, waiting, remaining) };
+        }
+    }
+
+    /// Records subscriber `sub` has read from stream slot `slot` since the
+    /// slot was last freed.
+    pub fn subscription_position(slot: u32, sub: u32) -> u64 {
+        cursor(slot, sub).records.load(Ordering::Acquire)
+    }
+}
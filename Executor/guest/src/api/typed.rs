@@ -0,0 +1,90 @@
+// Typed records: serde values behind a per-record schema tag.
+//
+// A type opts in by naming its schema descriptor (see "Typed records" in
+// `common`); the descriptor must list the serde fields in order:
+//
+//   #[derive(Serialize, Deserialize)]
+//   struct WordCount<S> { word: S, count: u64 }
+//   impl<S> Typed for WordCount<S> { const SCHEMA: &'static str = WORD_COUNT_SCHEMA; }
+//
+//   ShmApi::write_typed(slot, &WordCount { word: "a", count: 3 });
+//   ShmApi::for_each_typed(slot, |_origin, wc: WordCount<String>| { … });
+//
+// Records are encoded with postcard into one reused buffer and appended with
+// `RecordWriter`.  Reading decodes one record at a time, so a typed slot is
+// never materialised on the guest heap; it checks every record's tag and
+// traps on the first mismatch, naming the slot and both schemas — a
+// producer/consumer pair that disagrees fails loudly instead of folding
+// garbage.  The `StreamOut` / `StreamIn` workload handles wrap both calls.
+
+use alloc::vec::Vec;
+use common::{schema_tag, split_typed_record, SCHEMA_TAG_SIZE};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::ShmApi;
+use super::records::{RecordRef, RecordWriter};
+
+/// A record type with a schema descriptor.
+pub trait Typed {
+    /// Descriptor of the serde encoding of `Self`, e.g.
+    /// `"WordCount{word:str,count:u64}"`.
+    const SCHEMA: &'static str;
+    /// Tag written in front of every record of this type.
+    const TAG: u32 = schema_tag(Self::SCHEMA);
+}
+
+/// Reused encode buffer: one heap allocation for all typed writes.
+static mut ENCODE_BUFFER: Vec<u8> = Vec::new();
+
+/// Appends `value` as one typed record through `writer`.
+fn push_typed<T: Typed + Serialize>(writer: &mut RecordWriter, value: &T) {
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(ENCODE_BUFFER) };
+    buf.clear();
+    buf.extend_from_slice(&T::TAG.to_le_bytes());
+    let encoded = postcard::to_extend(value, core::mem::take(buf))
+        .unwrap_or_else(|e| panic!("typed record `{}`: encode failed: {}", T::SCHEMA, e));
+    writer.push(&encoded);
+    *buf = encoded;
+}
+
+/// Decodes one typed record of stream slot `slot`, trapping on a schema
+/// mismatch or a malformed body.
+fn decode<T: Typed + DeserializeOwned>(rec: RecordRef<'_>, scratch: &mut Vec<u8>, slot: u32) -> T {
+    let payload = rec.bytes(scratch);
+    let Some((tag, body)) = split_typed_record(payload) else {
+        panic!("stream slot {}: {}-byte record is not a typed record (expected `{}`)",
+               slot, payload.len(), T::SCHEMA);
+    };
+    if tag != T::TAG {
+        panic!("stream slot {}: record schema tag {:#010x}, expected `{}` ({:#010x})",
+               slot, tag, T::SCHEMA, T::TAG);
+    }
+    match postcard::take_from_bytes::<T>(body) {
+        Ok((value, [])) => value,
+        Ok((_, rest)) => panic!("stream slot {}: `{}` record has {} trailing bytes",
+                                slot, T::SCHEMA, rest.len()),
+        Err(e) => panic!("stream slot {}: `{}` record of {} bytes does not decode: {}",
+                         slot, T::SCHEMA, payload.len() - SCHEMA_TAG_SIZE, e),
+    }
+}
+
+impl ShmApi {
+    /// Append `value` to stream `writer_id` as a typed record.
+    pub fn write_typed<T: Typed + Serialize>(writer_id: u32, value: &T) {
+        push_typed(&mut Self::stream_writer(writer_id), value);
+    }
+
+    /// `f(origin, value)` for every record of stream `id`, decoded as `T`.
+    /// Traps on the first record of another schema.
+    pub fn for_each_typed<T, F>(id: u32, mut f: F)
+    where
+        T: Typed + DeserializeOwned,
+        F: FnMut(u32, T),
+    {
+        let mut scratch = Vec::new();
+        for rec in Self::records(id) {
+            f(rec.origin(), decode(rec, &mut scratch, id));
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// Typed arguments and slot handles for `#[workload]` exports.
+//
+// `guest_macros::workload` decodes the raw `u32` arguments the host passes
+// into these types and builds one handle per `#[slot(...)]` parameter, so a
+// workload body works with records instead of slot arithmetic:
+//
+//   #[workload]
+//   fn wc_map(slot: u32, #[slot(slot)] input: StreamIn, #[slot(slot + 100)] out: StreamOut) {
+//       for (_origin, rec) in input { … out.push(…); }
+//   }
+//
+// The handles are thin wrappers over the `ShmApi` calls of the same slot
+// kind; they add no buffering of their own.  Iterating `&input` borrows the
+// records from SHM (`records::RecordRef`) instead of copying them out.
+
+use alloc::vec::Vec;
+
+use super::ShmApi;
+use super::records::Records;
+use super::typed::Typed;
+
+// ── Arguments ──
+
+/// A fan-out argument: the `base | n << 16` packing of the partitioner's
+/// `slot_assigner`, or — high bits clear, as in hand-written single-node
+/// DAGs — a bare worker count with the base left at the default.
+#[derive(Clone, Copy, Debug)]
+pub struct Fanout {
+    /// First output slot.
+    pub base: u32,
+    /// Number of consumers (contiguous slots from `base`).
+    pub n: u32,
+}
+
+impl Fanout {
+    pub fn decode(arg: u32, default_base: u32) -> Self {
+        let base = arg & 0xFFFF;
+        let n = arg >> 16;
+        if n == 0 {
+            Fanout { base: default_base, n: base } // legacy: arg is the worker count
+        } else {
+            Fanout { base, n }
+        }
+    }
+}
+
+// ── Stream slots ──
+
+/// A stream slot the workload reads.  Iterating it yields every record as
+/// `(origin, payload)`, like `ShmApi::read_all_stream_records`; iterating
+/// `&StreamIn` borrows them instead, like `ShmApi::records`.
+pub struct StreamIn {
+    slot: u32,
+}
+
+impl StreamIn {
+    pub fn new(slot: u32) -> Self {
+        StreamIn { slot }
+    }
+
+    /// Every record, borrowed from SHM.
+    pub fn iter(&self) -> Records<'_> {
+        ShmApi::records(self.slot)
+    }
+
+    /// Every record, materialised.
+    pub fn records(&self) -> Vec<(u32, Vec<u8>)> {
+        ShmApi::read_all_stream_records(self.slot)
+    }
+
+    /// Every record through one reused buffer — bounded heap for large slots.
+    pub fn for_each<F: FnMut(u32, &[u8])>(&self, f: F) {
+        ShmApi::for_each_stream_record(self.slot, f)
+    }
+
+    /// Every record decoded as `T` (`ShmApi::for_each_typed`); traps on
+    /// another schema.
+    pub fn for_each_typed<T, F>(&self, f: F)
+    where
+        T: Typed + serde::de::DeserializeOwned,
+        F: FnMut(u32, T),
+    {
+        ShmApi::for_each_typed(self.slot, f)
+    }
+}
+
+impl IntoIterator for StreamIn {
+    type Item = (u32, Vec<u8>);
+    type IntoIter = alloc::vec::IntoIter<(u32, Vec<u8>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a StreamIn {
+    type Item = super::records::RecordRef<'a>;
+    type IntoIter = Records<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A stream slot the workload appends to.
+pub struct StreamOut {
+    slot: u32,
+}
+
+impl StreamOut {
+    pub fn new(slot: u32) -> Self {
+        StreamOut { slot }
+    }
+
+    pub fn push(&self, payload: &[u8]) {
+        ShmApi::append_stream_data(self.slot, payload);
+    }
+
+    pub fn push_str(&self, s: &str) {
+        self.push(s.as_bytes());
+    }
+
+    /// Appends `value` as a typed record (`ShmApi::write_typed`).
+    pub fn push_typed<T: Typed + serde::Serialize>(&self, value: &T) {
+        ShmApi::write_typed(self.slot, value);
+    }
+}
+
+// ── I/O slots ──
+
+/// An I/O slot the workload reads (e.g. `INPUT_IO_SLOT`, filled by `Input`).
+pub struct IoIn {
+    slot: u32,
+}
+
+impl IoIn {
+    pub fn new(slot: u32) -> Self {
+        IoIn { slot }
+    }
+
+    pub fn slot(&self) -> u32 {
+        self.slot
+    }
+
+    /// Every record, borrowed from SHM.
+    pub fn iter(&self) -> Records<'_> {
+        ShmApi::io_records(self.slot)
+    }
+
+    /// Every record, materialised.
+    pub fn records(&self) -> Vec<(u32, Vec<u8>)> {
+        ShmApi::read_all_inputs_from(self.slot)
+    }
+
+    /// Every record through one reused buffer — bounded heap for large inputs.
+    pub fn for_each<F: FnMut(u32, &[u8])>(&self, f: F) {
+        ShmApi::for_each_io_record(self.slot, f)
+    }
+}
+
+impl IntoIterator for IoIn {
+    type Item = (u32, Vec<u8>);
+    type IntoIter = alloc::vec::IntoIter<(u32, Vec<u8>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a IoIn {
+    type Item = super::records::RecordRef<'a>;
+    type IntoIter = Records<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An I/O slot the workload appends to (e.g. `OUTPUT_IO_SLOT`, saved by `Output`).
+pub struct IoOut {
+    slot: u32,
+}
+
+impl IoOut {
+    pub fn new(slot: u32) -> Self {
+        IoOut { slot }
+    }
+
+    pub fn push(&self, data: &[u8]) {
+        ShmApi::write_output_to(self.slot, data);
+    }
+
+    pub fn push_str(&self, s: &str) {
+        self.push(s.as_bytes());
+    }
+
+    /// Reserves a `len`-byte record and fills it in place (see `RecordWriter`).
+    pub fn push_with<F: FnOnce(&mut [u8])>(&self, len: usize, fill: F) {
+        ShmApi::io_writer(self.slot).push_with(len, fill);
+    }
+}
+
+// ── Return values ──
+
+/// Moves `bytes` into `common::READ_BUFFER`, which keeps it alive until the
+/// next fat-pointer return, and returns the packed `(ptr << 32 | len)` the
+/// host's `WasmFatPtr` decodes, or `0` when `bytes` is empty.
+pub fn fat_ptr(bytes: Vec<u8>) -> u64 {
+    if bytes.is_empty() {
+        return 0;
+    }
+    unsafe {
+        common::READ_BUFFER = bytes;
+        let buf = &*core::ptr::addr_of!(common::READ_BUFFER);
+        ((buf.as_ptr() as u64) << 32) | buf.len() as u64
+    }
+}
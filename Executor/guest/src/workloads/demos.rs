@@ -125,3 +125,149 @@ pub extern "C" fn write_final_output(id: u32) {
         id, id * 10, id * id
     ));
 }
+
+/// Key-value demo: records worker `id` under `worker:{id}`, bumps the shared
+/// `kv_demo_hits` counter with compare-and-put, and drops worker `id - 1`'s key.
+#[no_mangle]
+pub extern "C" fn kv_demo_writer(id: u32) {
+    ShmApi::kv_put(format!("worker:{}", id).as_bytes(), format!("done by {}", id).as_bytes());
+    loop {
+        let old = ShmApi::kv_get(b"kv_demo_hits");
+        let hits = old.as_deref().map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap_or([0; 8])));
+        if ShmApi::kv_compare_and_put(b"kv_demo_hits", old.as_deref(), &(hits + 1).to_le_bytes()) {
+            break;
+        }
+    }
+    if id > 0 {
+        ShmApi::kv_delete(format!("worker:{}", id - 1).as_bytes());
+    }
+}
+
+/// Key-value demo: writes every `worker:` entry to the output slot.
+#[no_mangle]
+pub extern "C" fn kv_demo_report(_id: u32) {
+    for (key, value) in ShmApi::kv_scan(b"worker:") {
+        ShmApi::write_output_str(&format!(
+            "{}={}",
+            alloc::string::String::from_utf8_lossy(&key),
+            alloc::string::String::from_utf8_lossy(&value),
+        ));
+    }
+}
+
+/// Stream slot the blocking-read demo streams through.
+const STREAM_DEMO_SLOT: u32 = 100;
+
+/// Blocking-read demo, producer half: appends eight records to
+/// `STREAM_DEMO_SLOT` and closes it.  Run in the same wave as
+/// `stream_demo_consumer`.
+#[no_mangle]
+pub extern "C" fn stream_demo_producer(id: u32) {
+    for i in 0..8u32 {
+        ShmApi::append_stream_data(STREAM_DEMO_SLOT, format!("record {} from {}", i, id).as_bytes());
+    }
+    ShmApi::close_stream(STREAM_DEMO_SLOT);
+}
+
+/// Blocking-read demo, consumer half: reads `STREAM_DEMO_SLOT` as records
+/// arrive until the producer closes it, then writes the count to the output
+/// slot.  Gives up after 10 s without a new record.
+#[no_mangle]
+pub extern "C" fn stream_demo_consumer(_id: u32) {
+    use crate::api::StreamWait;
+    let mut seen = 0;
+    let mut bytes = 0;
+    loop {
+        let (count, done) = match ShmApi::wait_for_records(STREAM_DEMO_SLOT, seen + 1, 10_000) {
+            StreamWait::Ready(n) => (n, false),
+            StreamWait::Closed(n) | StreamWait::TimedOut(n) => (n, true),
+        };
+        bytes += ShmApi::records(STREAM_DEMO_SLOT).skip(seen).take(count - seen).map(|r| r.len()).sum::<usize>();
+        seen = count;
+        if done {
+            break;
+        }
+    }
+    ShmApi::write_output_str(&format!("stream_demo records={} bytes={}", seen, bytes));
+}
+
+/// Ring slot the backpressure demo streams through; declare it in the DAG
+/// with `"rings": [{ "slot": 101, "pages": 4, "consumers": 2 }]`.
+const RING_DEMO_SLOT: u32 = 101;
+
+/// Ring demo, producer half: pushes 10 000 records through the four-page
+/// `RING_DEMO_SLOT`, sleeping whenever it gets a ring ahead of the slower
+/// consumer, then closes the slot.
+#[no_mangle]
+pub extern "C" fn ring_demo_producer(id: u32) {
+    for i in 0..10_000u32 {
+        ShmApi::ring_push(RING_DEMO_SLOT, format!("record {} from {}", i, id).as_bytes());
+    }
+    ShmApi::close_stream(RING_DEMO_SLOT);
+}
+
+/// Ring demo, consumer half: consumer `id` reads `RING_DEMO_SLOT` until the
+/// producer closes it and writes the count to the output slot.  Gives up
+/// after 10 s without a new record.
+#[no_mangle]
+pub extern "C" fn ring_demo_consumer(id: u32) {
+    let mut records = 0;
+    let mut bytes = 0;
+    while let Some(record) = ShmApi::ring_recv(RING_DEMO_SLOT, id, 10_000) {
+        records += 1;
+        bytes += record.len();
+    }
+    ShmApi::write_output_str(&format!("ring_demo consumer={} records={} bytes={}", id, records, bytes));
+}
+
+/// Stream slot the subscription demo publishes on; declare it in the DAG
+/// with `"subscriptions": [{ "slot": 102, "subscribers": ["audit", "index"] }]`.
+const PUBSUB_DEMO_SLOT: u32 = 102;
+
+/// Subscription demo, producer half: appends 1 000 records to
+/// `PUBSUB_DEMO_SLOT` and closes it.
+#[no_mangle]
+pub extern "C" fn pubsub_demo_producer(id: u32) {
+    for i in 0..1_000u32 {
+        ShmApi::append_stream_data(PUBSUB_DEMO_SLOT, format!("event {} from {}", i, id).as_bytes());
+    }
+    ShmApi::close_stream(PUBSUB_DEMO_SLOT);
+}
+
+/// Subscription demo, subscriber half: `id` 0 reads as `audit`, any other as
+/// `index`.  Reads every record until the producer closes the slot and
+/// writes the count to the output slot.  Gives up after 10 s without a new
+/// record.
+#[no_mangle]
+pub extern "C" fn pubsub_demo_subscriber(id: u32) {
+    let name = if id == 0 { "audit" } else { "index" };
+    let me = ShmApi::subscriber(PUBSUB_DEMO_SLOT, name);
+    let mut bytes = 0;
+    while let Some(record) = ShmApi::subscription_recv(PUBSUB_DEMO_SLOT, me, 10_000) {
+        bytes += record.len();
+    }
+    let records = ShmApi::subscription_position(PUBSUB_DEMO_SLOT, me);
+    ShmApi::write_output_str(&format!("pubsub_demo subscriber={} records={} bytes={}", name, records, bytes));
+}
+
+/// Tasks the remote-atomic demo's workers share out.
+const REMOTE_ATOMIC_DEMO_TASKS: u64 = 64;
+
+/// Remote-atomic demo: workers on every node of the mesh claim tasks from one
+/// counter owned by node 0 until none are left, then race to become leader
+/// with a compare-and-swap.  `id` must be unique across the mesh.  Writes
+/// what this worker got, and how many workers have finished so far, to the
+/// output slot.
+#[no_mangle]
+pub extern "C" fn remote_atomic_demo(id: u32) {
+    let mut tasks = 0;
+    while ShmApi::remote_fetch_add(0, "demo_next_task", 1) < REMOTE_ATOMIC_DEMO_TASKS {
+        tasks += 1;
+    }
+    let leader = ShmApi::remote_compare_swap(0, "demo_leader", 0, id as u64 + 1) == 0;
+    ShmApi::remote_fetch_add(0, "demo_finished", 1);
+    let finished = ShmApi::remote_read_atomic(0, "demo_finished");
+    ShmApi::write_output_str(&format!(
+        "remote_atomic_demo worker={} tasks={} leader={} finished_so_far={}", id, tasks, leader, finished
+    ));
+}
@@ -47,9 +47,9 @@ const RULE_OUT_BASE: u32 = 10;
 // distinct slots also avoid concurrent same-slot appends from parallel shards).
 // Slot = SHARD_OUT_BASE + rule_id*SHARD_STRIDE + shard_idx, kept clear of all
 // other finra slots (5,6,10..17,200,300,301) AND small enough that the
-// partitioner's slot bookkeeping stays under STREAM_SLOT_COUNT (2048). Only used
-// when n_shards > 1; the unsharded path still writes RULE_OUT_BASE+rule_id
-// (10..17), so existing DAGs are unaffected.
+// partitioner's slot bookkeeping stays under the stream slot count (2048 by
+// default). Only used when n_shards > 1; the unsharded path still writes
+// RULE_OUT_BASE+rule_id (10..17), so existing DAGs are unaffected.
 const SHARD_OUT_BASE: u32 = 400;
 const SHARD_STRIDE: u32 = 16; // max shards per rule
 
@@ -272,9 +272,9 @@ pub extern "C" fn finra_fetch_public(_unused: u32) {
 ///
 /// The single u32 arg is PACKED so the existing 1-param ABI can carry an optional
 /// data-shard spec (set by Scheduling_Policy/gen_variants.py's hybrid transform).
-/// The fields use SMALL bit positions on purpose: the partitioner's collect_slots
+/// The fields use SMALL bit positions on purpose: the partitioner's collect_dag_slots
 /// scans every integer in a node's kind JSON as a candidate slot, so a large arg
-/// would blow past STREAM_SLOT_COUNT (2048). Layout:
+/// would blow past the stream slot count (2048 by default). Layout:
 ///   bits 0..3   rule_id    (0..7)
 ///   bits 3..7   shard_idx  (which slice this worker handles, 0..15)
 ///   bits 7..11  n_shards   (0 or 1 = no sharding, run full data; up to 15)
@@ -7,15 +7,11 @@ mod finra;
 /// The partitioner packs `arg = base | (n_consumers << 16)` (see
 /// `partitioner::slot_assigner`).  Hand-authored single-node DAGs predate the
 /// packing and pass a bare worker count with the high bits clear; for those we
-/// fall back to `default_base` and treat `arg` as the count.
+/// fall back to `default_base` and treat `arg` as the count.  `#[workload]`
+/// exports take a `Fanout` argument instead, which decodes the same way.
 pub(crate) fn unpack_fanout_arg(arg: u32, default_base: u32) -> (u32, u32) {
-    let base = arg & 0xFFFF;
-    let n = arg >> 16;
-    if n == 0 {
-        (default_base, base) // legacy: arg is the worker count
-    } else {
-        (base, n)
-    }
+    let f = crate::api::workload::Fanout::decode(arg, default_base);
+    (f.base, f.n)
 }
 mod img_pipeline;
 mod matrix;
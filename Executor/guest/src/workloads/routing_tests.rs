@@ -8,15 +8,16 @@
 //   3. Calls `consume_routed_stream(id)` or `dump_stream_records(id)` to verify.
 // ─────────────────────────────────────────────────────────────────────────────
 
+use guest_macros::workload;
 use crate::api::ShmApi;
+use crate::api::workload::{StreamIn, StreamOut};
 use common::READ_BUFFER;
 
 /// Routing test (light): writes 3 labeled records to stream slot `id`.
-#[no_mangle]
-pub extern "C" fn produce_stream(id: u32) {
+#[workload]
+fn produce_stream(id: u32, #[slot(id)] out: StreamOut) {
     for seq in 0..3u32 {
-        let payload = alloc::format!("StreamPayload_P{}_seq{}", id, seq);
-        ShmApi::append_stream_data(id, payload.as_bytes());
+        out.push_str(&alloc::format!("StreamPayload_P{}_seq{}", id, seq));
     }
 }
 
@@ -34,9 +35,9 @@ pub extern "C" fn produce_stream_heavy(id: u32) {
 }
 
 /// Returns the total number of length-prefixed records in stream slot `id`.
-#[no_mangle]
-pub extern "C" fn count_stream_records(id: u32) -> u32 {
-    ShmApi::read_all_stream_records(id).len() as u32
+#[workload]
+fn count_stream_records(id: u32, #[slot(id)] input: StreamIn) -> u32 {
+    input.records().len() as u32
 }
 
 /// Returns the last complete record from stream slot `id` as a packed
@@ -57,19 +58,12 @@ pub extern "C" fn consume_routed_stream(id: u32) -> u64 {
 
 /// Reads ALL records from stream slot `id`, joins them with `\n`, and returns a
 /// packed `(ptr << 32 | len)` fat pointer into READ_BUFFER, or `0` if empty.
-#[no_mangle]
-pub extern "C" fn dump_stream_records(id: u32) -> u64 {
-    let records = ShmApi::read_all_stream_records(id);
-    if records.is_empty() { return 0; }
+#[workload]
+fn dump_stream_records(id: u32, #[slot(id)] input: StreamIn) -> Vec<u8> {
     let mut combined: Vec<u8> = Vec::new();
-    for (i, (_origin, rec)) in records.iter().enumerate() {
+    for (i, (_origin, rec)) in input.into_iter().enumerate() {
         if i > 0 { combined.push(b'\n'); }
-        combined.extend_from_slice(rec);
-    }
-    unsafe {
-        READ_BUFFER = combined;
-        let ptr = READ_BUFFER.as_ptr() as u64;
-        let len = READ_BUFFER.len() as u64;
-        (ptr << 32) | len
+        combined.extend_from_slice(&rec);
     }
+    combined
 }
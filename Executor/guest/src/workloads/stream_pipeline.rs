@@ -15,7 +15,9 @@
 
 use core::sync::atomic::{AtomicU64, Ordering};
 use alloc::vec::Vec;
+use guest_macros::workload;
 use crate::api::ShmApi;
+use crate::api::workload::StreamOut;
 
 const PIPELINE_BATCH: u32 = 20;
 
@@ -44,12 +46,11 @@ fn pipe_read_window(in_slot: u32, total: usize) -> (&'static AtomicU64, usize, u
 
 /// Stage 1 — source: appends `PIPELINE_BATCH` records to `out_slot`.
 /// Record format: "r={round},i={item:02},v={value:05}" where value = round*1000+item.
-#[no_mangle]
-pub extern "C" fn pipeline_source(out_slot: u32, round: u32) {
+#[workload]
+fn pipeline_source(out_slot: u32, round: u32, #[slot(out_slot)] out: StreamOut) {
     for i in 0..PIPELINE_BATCH {
         let v = round * 1000 + i;
-        let rec = alloc::format!("r={},i={:02},v={:05}", round, i, v);
-        ShmApi::append_stream_data(out_slot, rec.as_bytes());
+        out.push_str(&alloc::format!("r={},i={:02},v={:05}", round, i, v));
     }
 }
 
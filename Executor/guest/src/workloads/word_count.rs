@@ -16,19 +16,36 @@
 //   Output         → flush output I/O slot to file
 //
 // Record formats:
-//   wc_map  emits : "word=<w>\x1f<count>" (0x1F unit-separator)
+//   wc_map  emits : typed `WordCount { word, count }` records
+//                   (`common::WORD_COUNT_SCHEMA`, see `api::typed`)
 //   wc_reduce emits: "<word>: <count>"  (one line per unique word, sorted)
 //                    plus a header record "=== word_count ==="
 // ─────────────────────────────────────────────────────────────────────────────
 
+use alloc::string::String;
 use alloc::vec::Vec;
+use common::WORD_COUNT_SCHEMA;
+use guest_macros::workload;
 use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
 use crate::api::ShmApi;
+use crate::api::typed::Typed;
+use crate::api::workload::{Fanout, IoIn, IoOut, StreamIn, StreamOut};
 
 /// First stream slot used by the distribute stage.
 const WC_DIST_BASE: u32 = 10;
-/// Map output base: wc_map(slot) writes to stream slot `slot + WC_MAP_OUT_BASE`.
-const WC_MAP_OUT_BASE: u32 = 100;
+
+/// One `wc_map` output record: `word` occurred `count` times in the worker's
+/// partition.  Written with `S = &str`, read back with `S = String`.
+#[derive(Serialize, Deserialize)]
+struct WordCount<S> {
+    word: S,
+    count: u64,
+}
+
+impl<S> Typed for WordCount<S> {
+    const SCHEMA: &'static str = WORD_COUNT_SCHEMA;
+}
 
 /// Distribute the input across `n_workers` stream slots starting at
 /// `WC_DIST_BASE`, **zero-copy**: the input page chain is split into `n_workers`
@@ -40,77 +57,73 @@ const WC_MAP_OUT_BASE: u32 = 100;
 /// Word count is order-insensitive, so contiguous chunks (vs the old
 /// round-robin) yield identical aggregate counts; only which worker sees which
 /// lines changes.
-/// `arg` packs the output layout assigned by the partitioner:
-///   low 16 bits  = base stream slot (the first slot the map workers read)
-///   high 16 bits = number of map workers (= contiguous segments to produce)
-/// Legacy/native form (high bits == 0, used by hand-authored single-node DAGs):
-///   `arg` is the worker count and the base defaults to `WC_DIST_BASE`.
-#[no_mangle]
-pub extern "C" fn wc_distribute(arg: u32) {
-    let (out_base, n_workers) = super::unpack_fanout_arg(arg, WC_DIST_BASE);
-    if n_workers == 0 { return; }
-    ShmApi::split_input_contiguous(common::INPUT_IO_SLOT, out_base, n_workers);
+/// `out` is the output layout assigned by the partitioner: the base stream slot
+/// (the first slot the map workers read) and the number of map workers
+/// (= contiguous segments to produce).  Hand-authored single-node DAGs pass a
+/// bare worker count and the base defaults to `WC_DIST_BASE`.
+#[workload]
+fn wc_distribute(#[fanout(default_base = WC_DIST_BASE)] out: Fanout, #[slot(0)] input: IoIn) {
+    if out.n == 0 { return; }
+    ShmApi::split_input_contiguous(input.slot(), out.base, out.n);
 }
 
 /// Map: count occurrences of every unique word in stream slot `slot`.
-/// Emits one `"word=<w>\x1f<count>"` record per unique word to stream slot
-/// `slot + WC_MAP_OUT_BASE`.
-#[no_mangle]
-pub extern "C" fn wc_map(slot: u32) {
+/// Emits one `WordCount` record per unique word to stream slot `slot + 100`.
+///
+/// Lines are borrowed straight from the slot's pages (`&input` yields
+/// `RecordRef`s) and each token is normalised into one reused buffer, so the
+/// loop allocates only when it meets a new word (or a line split across pages
+/// longer than any before it).
+#[workload]
+fn wc_map(slot: u32, #[slot(slot)] input: StreamIn, #[slot(slot + 100)] out: StreamOut) {
     // Hash map (hashbrown::HashMap) — the same associative structure as the Cloudburst
     // & RMMap `Counter` (Python dict) and the Faasm `std::unordered_map`, so the four
     // systems count with identical O(1)-amortized-per-token complexity (fair-comparison
     // requirement — the previous Vec did an O(unique) linear scan per token).
     let mut counts: HashMap<alloc::string::String, u64> = HashMap::new();
+    let mut scratch: Vec<u8> = Vec::new();
+    let mut word = alloc::string::String::new();
 
-    let records = ShmApi::read_all_stream_records(slot);
-    for (_origin, rec) in &records {
-        let line = core::str::from_utf8(rec).unwrap_or("");
+    for rec in &input {
+        let line = core::str::from_utf8(rec.bytes(&mut scratch)).unwrap_or("");
         for token in line.split_whitespace() {
-            let word: alloc::string::String = token
-                .chars()
-                .filter(|c| c.is_alphabetic())
-                .map(|c| {
-                    if c >= 'A' && c <= 'Z' { (c as u8 + 32) as char } else { c }
-                })
-                .collect();
+            word.clear();
+            word.extend(
+                token
+                    .chars()
+                    .filter(|c| c.is_alphabetic())
+                    .map(|c| {
+                        if c >= 'A' && c <= 'Z' { (c as u8 + 32) as char } else { c }
+                    }),
+            );
             if word.is_empty() { continue; }
-            *counts.entry(word).or_insert(0) += 1;
+            match counts.get_mut(word.as_str()) {
+                Some(n) => *n += 1,
+                None => { counts.insert(word.clone(), 1); }
+            }
         }
     }
 
-    let out_slot = slot + WC_MAP_OUT_BASE;
     for (word, count) in &counts {
-        let rec = alloc::format!("word={}\x1f{}", word, count);
-        ShmApi::append_stream_data(out_slot, rec.as_bytes());
+        out.push_typed(&WordCount { word: word.as_str(), count: *count });
     }
 }
 
-/// Reduce: read all `"word=<w>\x1f<count>"` records from `stream_slot`,
-/// merge counts, sort alphabetically, and write to `OUTPUT_IO_SLOT`.
-#[no_mangle]
-pub extern "C" fn wc_reduce(stream_slot: u32) {
+/// Reduce: read all `WordCount` records from `stream_slot`, merge counts,
+/// sort alphabetically, and write to `OUTPUT_IO_SLOT`.
+#[workload]
+fn wc_reduce(stream_slot: u32, #[slot(stream_slot)] input: StreamIn, #[slot(1)] out: IoOut) {
     // Same hash-map reducer structure as Cloudburst/RMMap (`Counter.update`) and Faasm
     // (`std::unordered_map`): accumulate into a hash map, then materialize + sort for a
     // deterministic output.
     let mut acc: HashMap<alloc::string::String, u64> = HashMap::new();
 
-    let records = ShmApi::read_all_stream_records(stream_slot);
-    for (_origin, rec) in &records {
-        let s = core::str::from_utf8(rec).unwrap_or("");
-        let body = match s.strip_prefix("word=") {
-            Some(b) => b,
-            None => continue,
-        };
-        let sep = match body.find('\x1f') {
-            Some(i) => i,
-            None => continue,
-        };
-        let word = &body[..sep];
-        let count: u64 = body[sep + 1..].parse().unwrap_or(0);
-        if word.is_empty() { continue; }
-        *acc.entry(alloc::string::String::from(word)).or_insert(0) += count;
-    }
+    let mut received = 0usize;
+    input.for_each_typed(|_origin, rec: WordCount<String>| {
+        received += 1;
+        if rec.word.is_empty() { return; }
+        *acc.entry(rec.word).or_insert(0) += rec.count;
+    });
 
     let mut totals: Vec<(alloc::string::String, u64)> = acc.into_iter().collect();
     totals.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
@@ -118,11 +131,33 @@ pub extern "C" fn wc_reduce(stream_slot: u32) {
     let unique = totals.len();
     let total: u64 = totals.iter().map(|(_, n)| n).sum();
 
-    ShmApi::write_output_str("=== word_count ===");
-    ShmApi::write_output_str(&alloc::format!("map_records_received={}", records.len()));
-    ShmApi::write_output_str(&alloc::format!("unique_words={}", unique));
-    ShmApi::write_output_str(&alloc::format!("total_occurrences={}", total));
+    out.push_str("=== word_count ===");
+    out.push_str(&alloc::format!("map_records_received={}", received));
+    out.push_str(&alloc::format!("unique_words={}", unique));
+    out.push_str(&alloc::format!("total_occurrences={}", total));
+    // One line per word, written straight into the output pages: no
+    // `format!` string per unique word.
+    let mut digits = [0u8; 20];
     for (word, count) in &totals {
-        ShmApi::write_output_str(&alloc::format!("{}: {}", word, count));
+        let count = decimal(*count, &mut digits);
+        out.push_with(word.len() + 2 + count.len(), |line| {
+            let (w, rest) = line.split_at_mut(word.len());
+            w.copy_from_slice(word.as_bytes());
+            rest[..2].copy_from_slice(b": ");
+            rest[2..].copy_from_slice(count);
+        });
+    }
+}
+
+/// `n` in decimal, right-aligned in `buf`.
+fn decimal(mut n: u64, buf: &mut [u8; 20]) -> &[u8] {
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            return &buf[i..];
+        }
     }
 }
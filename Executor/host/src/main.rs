@@ -55,6 +55,90 @@ fn main() -> Result<()> {
         let output = &args[3];
         let partials = &args[4..];
         shard::run_merge(reducer, output, partials)
+    } else if args.len() > 1 && args[1] == "cache" {
+        // Result-cache maintenance:
+        //   host cache gc [--dir D] [--max-bytes N[K|M|G]] [--max-age-days D]
+        // Drops entries unused for longer than --max-age-days, then the least
+        // recently used ones until the cache fits in --max-bytes (default 1G).
+        let usage = || -> ! {
+            eprintln!("usage: host cache gc [--dir D] [--max-bytes N[K|M|G]] [--max-age-days D]");
+            std::process::exit(2)
+        };
+        if args.get(2).map(String::as_str) != Some("gc") {
+            usage();
+        }
+        let mut dir = runtime::dag_runner::DEFAULT_CACHE_DIR.to_string();
+        let mut max_bytes = runtime::dag_runner::DEFAULT_GC_MAX_BYTES;
+        let mut max_age = None;
+        let mut rest = args[3..].iter();
+        while let Some(flag) = rest.next() {
+            let value = rest.next().unwrap_or_else(|| usage());
+            match flag.as_str() {
+                "--dir" => dir = value.clone(),
+                "--max-bytes" => {
+                    max_bytes = runtime::dag_runner::parse_size(value).unwrap_or_else(|| usage())
+                }
+                "--max-age-days" => {
+                    let days: f64 = value.parse().ok()
+                        .filter(|d: &f64| d.is_finite() && *d >= 0.0)
+                        .unwrap_or_else(|| usage());
+                    max_age = Some(std::time::Duration::from_secs_f64(days * 86_400.0));
+                }
+                _ => usage(),
+            }
+        }
+        runtime::dag_runner::run_cache_gc(&dir, max_bytes, max_age)
+    } else if args.len() > 1 && args[1] == "shm" {
+        // Read-only region tools (live /dev/shm file or a post-crash copy):
+        //   host shm inspect <path> [--json] [--slot N [--io] [--dump]] [--log-tail BYTES]
+        //   host shm fsck <path> [--json] [--no-leaks]
+        use runtime::mem_operation::fsck::{run_fsck, FsckOptions};
+        use runtime::mem_operation::inspect::{run_inspect, InspectOptions, DEFAULT_LOG_TAIL};
+        use runtime::mem_operation::reclaimer::SlotKind;
+        let usage = || -> ! {
+            eprintln!("usage: host shm inspect <path> [--json] [--slot N [--io] [--dump]] [--log-tail BYTES]");
+            eprintln!("       host shm fsck <path> [--json] [--no-leaks]");
+            std::process::exit(2)
+        };
+        let path = args.get(3).unwrap_or_else(|| usage());
+        let mut rest = args[4..].iter();
+        match args[2].as_str() {
+            "inspect" => {
+                let mut opts = InspectOptions { log_tail: DEFAULT_LOG_TAIL, ..Default::default() };
+                let (mut slot, mut io) = (None, false);
+                while let Some(flag) = rest.next() {
+                    match flag.as_str() {
+                        "--json" => opts.json = true,
+                        "--io" => io = true,
+                        "--dump" => opts.dump = true,
+                        "--slot" => {
+                            slot = Some(rest.next().and_then(|v| v.parse::<usize>().ok()).unwrap_or_else(|| usage()))
+                        }
+                        "--log-tail" => {
+                            opts.log_tail = rest.next().and_then(|v| v.parse().ok()).unwrap_or_else(|| usage())
+                        }
+                        _ => usage(),
+                    }
+                }
+                if (opts.dump || io) && slot.is_none() {
+                    usage();
+                }
+                opts.slot = slot.map(|s| (if io { SlotKind::Io } else { SlotKind::Stream }, s));
+                run_inspect(path, &opts)
+            }
+            "fsck" => {
+                let mut opts = FsckOptions::default();
+                for flag in rest {
+                    match flag.as_str() {
+                        "--json" => opts.json = true,
+                        "--no-leaks" => opts.skip_leaks = true,
+                        _ => usage(),
+                    }
+                }
+                run_fsck(path, &opts)
+            }
+            _ => usage(),
+        }
     } else if args.len() > 1 && args[1] == "wasm-loop" {
         // Persistent pipeline worker: ./host wasm-loop <shm_path> <wasm_path> <func>
         // Reads "arg0 arg1\n" lines from stdin, calls func(arg0, arg1) for each,
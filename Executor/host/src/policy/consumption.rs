@@ -16,6 +16,8 @@ pub struct ConflictNode {
 pub enum ConsumptionResult {
     /// The winning writer ID and its payload decoded as a UTF-8 string.
     Winner(u32, String),
+    /// A new value combined from every node (merge policies); committed as-is.
+    Merged(Vec<u8>),
     /// No nodes were present; nothing to commit.
     None,
 }
@@ -24,6 +26,19 @@ pub enum ConsumptionResult {
 pub trait ConsumptionPolicy {
     /// Evaluates the set of conflicting nodes and returns the single winner.
     fn process(&self, nodes: &[ConflictNode]) -> ConsumptionResult;
+
+    /// `true` for merge policies, which the organizer calls through
+    /// `process_onto` so every wave's writes accumulate into one entry.
+    fn merges(&self) -> bool {
+        false
+    }
+
+    /// Combines the key's committed value (`None` before its first
+    /// resolution) with `nodes`.  Only called when `merges()` is `true`.
+    fn process_onto(&self, committed: Option<&[u8]>, nodes: &[ConflictNode]) -> ConsumptionResult {
+        let _ = committed;
+        self.process(nodes)
+    }
 }
 
 /// Conflict policy: selects the node written by the writer with the highest ID.
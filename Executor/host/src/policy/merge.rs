@@ -0,0 +1,291 @@
+// Mergeable (CRDT-style) shared-state policies.
+//
+// The policies in `consumption.rs` keep one write and drop the rest.  A merge
+// policy instead folds every write to a key — plus the value committed by the
+// previous resolution — into a new binary payload, so fan-out workers can each
+// contribute a partial aggregate (a counter, a histogram, a set of ids) to one
+// named entry and successive waves keep accumulating into it.
+//
+// Every merge sees the same input order (see `ordered`): the committed value
+// first, as writer `COMMITTED_WRITER`, then the writes by ascending writer id,
+// older writes first among equal ids.
+//
+// Usage:
+//   let sum = NumericMerge { op: NumericOp::Sum, int: IntType::U64 };
+//   organizer.resolve_shared_state(|_| &sum);
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Deserialize;
+
+use super::consumption::{ConflictNode, ConsumptionPolicy, ConsumptionResult};
+
+/// Writer id under which merge policies see a key's committed value.
+pub const COMMITTED_WRITER: u32 = u32::MAX;
+
+// -----------------------------------------------------------------------------
+// Trait
+// -----------------------------------------------------------------------------
+
+/// Folds a key's inputs into one payload.  Every `Merge` is a
+/// `ConsumptionPolicy` whose result is `ConsumptionResult::Merged`.
+pub trait Merge {
+    /// `inputs` is `(writer_id, payload)` in `ordered` order and never empty.
+    /// Returns `None` to leave the committed value as it is.
+    fn merge(&self, inputs: &[(u32, &[u8])]) -> Option<Vec<u8>>;
+}
+
+impl<M: Merge> ConsumptionPolicy for M {
+    fn process(&self, nodes: &[ConflictNode]) -> ConsumptionResult {
+        self.process_onto(None, nodes)
+    }
+
+    fn merges(&self) -> bool {
+        true
+    }
+
+    fn process_onto(&self, committed: Option<&[u8]>, nodes: &[ConflictNode]) -> ConsumptionResult {
+        if nodes.is_empty() {
+            return ConsumptionResult::None;
+        }
+        match self.merge(&ordered(committed, nodes)) {
+            Some(bytes) => ConsumptionResult::Merged(bytes),
+            None => ConsumptionResult::None,
+        }
+    }
+}
+
+/// `committed` (as `COMMITTED_WRITER`) followed by `nodes` by ascending writer
+/// id.  `nodes` arrive newest first, so they are reversed before the stable sort.
+fn ordered<'a>(committed: Option<&'a [u8]>, nodes: &'a [ConflictNode]) -> Vec<(u32, &'a [u8])> {
+    let mut writes: Vec<(u32, &[u8])> = nodes.iter().rev()
+        .map(|n| (n.writer_id, n.payload.as_slice()))
+        .collect();
+    writes.sort_by_key(|&(writer_id, _)| writer_id);
+    committed.map(|c| (COMMITTED_WRITER, c)).into_iter().chain(writes).collect()
+}
+
+// -----------------------------------------------------------------------------
+// NumericMerge
+// -----------------------------------------------------------------------------
+
+/// Element type of a `NumericMerge` payload.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IntType {
+    U32,
+    #[default]
+    U64,
+    I32,
+    I64,
+}
+
+impl IntType {
+    fn width(self) -> usize {
+        match self {
+            IntType::U32 | IntType::I32 => 4,
+            IntType::U64 | IntType::I64 => 8,
+        }
+    }
+
+    fn decode(self, b: &[u8]) -> i128 {
+        match self {
+            IntType::U32 => u32::from_le_bytes(b.try_into().unwrap()) as i128,
+            IntType::U64 => u64::from_le_bytes(b.try_into().unwrap()) as i128,
+            IntType::I32 => i32::from_le_bytes(b.try_into().unwrap()) as i128,
+            IntType::I64 => i64::from_le_bytes(b.try_into().unwrap()) as i128,
+        }
+    }
+
+    /// Appends `v` truncated to this width, so sums wrap like the native type.
+    fn encode(self, v: i128, out: &mut Vec<u8>) {
+        match self.width() {
+            4 => out.extend_from_slice(&(v as u32).to_le_bytes()),
+            _ => out.extend_from_slice(&(v as u64).to_le_bytes()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericOp {
+    Sum,
+    Min,
+    Max,
+}
+
+/// Element-wise sum / min / max over payloads read as arrays of little-endian
+/// `int`s.  A payload of one element is a counter, of N elements a histogram;
+/// shorter payloads only take part in the elements they have.  Payloads whose
+/// length is not a multiple of the element width are skipped.
+pub struct NumericMerge {
+    pub op: NumericOp,
+    pub int: IntType,
+}
+
+impl Merge for NumericMerge {
+    fn merge(&self, inputs: &[(u32, &[u8])]) -> Option<Vec<u8>> {
+        let width = self.int.width();
+        let mut acc: Vec<i128> = Vec::new();
+        let mut any = false;
+        for &(writer_id, payload) in inputs {
+            if payload.len() % width != 0 {
+                println!(
+                    "[NumericMerge] writer {}: {} bytes is not a multiple of {} — skipped",
+                    writer_id, payload.len(), width,
+                );
+                continue;
+            }
+            any = true;
+            for (i, b) in payload.chunks_exact(width).enumerate() {
+                let v = self.int.decode(b);
+                match acc.get_mut(i) {
+                    None => acc.push(v),
+                    Some(a) => *a = match self.op {
+                        NumericOp::Sum => a.wrapping_add(v),
+                        NumericOp::Min => (*a).min(v),
+                        NumericOp::Max => (*a).max(v),
+                    },
+                }
+            }
+        }
+        if !any {
+            return None;
+        }
+        let mut out = Vec::with_capacity(acc.len() * width);
+        for v in acc {
+            self.int.encode(v, &mut out);
+        }
+        Some(out)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// SetUnionMerge
+// -----------------------------------------------------------------------------
+
+/// Union of `delimiter`-separated items, sorted bytewise and joined with
+/// `delimiter`.  Empty items are dropped.  With `multiset`, duplicates are
+/// kept: an item appears as many times as all inputs together contain it.
+pub struct SetUnionMerge {
+    pub delimiter: u8,
+    pub multiset: bool,
+}
+
+impl Merge for SetUnionMerge {
+    fn merge(&self, inputs: &[(u32, &[u8])]) -> Option<Vec<u8>> {
+        let items = inputs.iter()
+            .flat_map(|&(_, payload)| payload.split(|&b| b == self.delimiter))
+            .filter(|item| !item.is_empty());
+        let joined: Vec<&[u8]> = if self.multiset {
+            let mut counts: BTreeMap<&[u8], usize> = BTreeMap::new();
+            for item in items {
+                *counts.entry(item).or_insert(0) += 1;
+            }
+            counts.into_iter().flat_map(|(item, n)| std::iter::repeat_n(item, n)).collect()
+        } else {
+            items.collect::<BTreeSet<_>>().into_iter().collect()
+        };
+        Some(joined.join(&self.delimiter))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// ConcatMerge
+// -----------------------------------------------------------------------------
+
+/// Every input in `ordered` order, joined with `separator`.
+pub struct ConcatMerge {
+    pub separator: Vec<u8>,
+}
+
+impl Merge for ConcatMerge {
+    fn merge(&self, inputs: &[(u32, &[u8])]) -> Option<Vec<u8>> {
+        let parts: Vec<&[u8]> = inputs.iter().map(|&(_, payload)| payload).collect();
+        Some(parts.join(self.separator.as_slice()))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// FnMerge
+// -----------------------------------------------------------------------------
+
+/// Merge through a caller-supplied fold — the `WasmMerge` DAG policy wraps a
+/// guest export in one.
+pub struct FnMerge<F>(pub F);
+
+impl<F: Fn(&[(u32, &[u8])]) -> Option<Vec<u8>>> Merge for FnMerge<F> {
+    fn merge(&self, inputs: &[(u32, &[u8])]) -> Option<Vec<u8>> {
+        (self.0)(inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(writer_id: u32, payload: &[u8]) -> ConflictNode {
+        ConflictNode {
+            offset: 0,
+            writer_id,
+            data_len: payload.len() as u32,
+            registry_index: 0,
+            payload: payload.to_vec(),
+        }
+    }
+
+    fn merged(policy: &dyn ConsumptionPolicy, committed: Option<&[u8]>, nodes: &[ConflictNode]) -> Vec<u8> {
+        match policy.process_onto(committed, nodes) {
+            ConsumptionResult::Merged(bytes) => bytes,
+            other => panic!("expected a merged payload, got {:?}", other),
+        }
+    }
+
+    fn u64s(values: &[u64]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn numeric_sum_is_element_wise_and_folds_committed() {
+        let sum = NumericMerge { op: NumericOp::Sum, int: IntType::U64 };
+        let nodes = [node(1, &u64s(&[1, 2, 3])), node(2, &u64s(&[10])), node(3, b"bad")];
+        assert_eq!(merged(&sum, Some(&u64s(&[100, 0])), &nodes), u64s(&[111, 2, 3]));
+    }
+
+    #[test]
+    fn numeric_min_max_respect_sign() {
+        let nodes = [node(1, &(-5i32).to_le_bytes()), node(2, &7i32.to_le_bytes())];
+        let min = NumericMerge { op: NumericOp::Min, int: IntType::I32 };
+        let max = NumericMerge { op: NumericOp::Max, int: IntType::I32 };
+        assert_eq!(merged(&min, None, &nodes), (-5i32).to_le_bytes());
+        assert_eq!(merged(&max, None, &nodes), 7i32.to_le_bytes());
+        let unsigned_min = NumericMerge { op: NumericOp::Min, int: IntType::U32 };
+        assert_eq!(merged(&unsigned_min, None, &nodes), 7u32.to_le_bytes());
+    }
+
+    #[test]
+    fn set_and_multiset_union() {
+        let nodes = [node(1, b"b\na\n"), node(2, b"a\nc")];
+        let set = SetUnionMerge { delimiter: b'\n', multiset: false };
+        let bag = SetUnionMerge { delimiter: b'\n', multiset: true };
+        assert_eq!(merged(&set, Some(b"d"), &nodes), b"a\nb\nc\nd");
+        assert_eq!(merged(&bag, None, &nodes), b"a\na\nb\nc");
+    }
+
+    #[test]
+    fn concat_orders_by_writer_then_write_order() {
+        // Newest first, as the organizer passes them.
+        let nodes = [node(2, b"2b"), node(1, b"1"), node(2, b"2a")];
+        let concat = ConcatMerge { separator: b",".to_vec() };
+        assert_eq!(merged(&concat, Some(b"base"), &nodes), b"base,1,2a,2b");
+    }
+
+    #[test]
+    fn fn_merge_sees_ordered_inputs_and_can_decline() {
+        let longest = FnMerge(|inputs: &[(u32, &[u8])]| {
+            inputs.iter().map(|&(_, p)| p).max_by_key(|p| p.len()).map(<[u8]>::to_vec)
+        });
+        assert_eq!(merged(&longest, None, &[node(4, b"ab"), node(9, b"abc")]), b"abc");
+        let decline = FnMerge(|_: &[(u32, &[u8])]| None);
+        assert!(matches!(decline.process(&[node(1, b"x")]), ConsumptionResult::None));
+    }
+}
@@ -1,7 +1,9 @@
 pub mod consumption;
+pub mod merge;
 pub mod shuffle_policy;
 pub mod slice_policy;
 
 pub use consumption::*;
+pub use merge::*;
 pub use shuffle_policy::*;
 pub use slice_policy::*;
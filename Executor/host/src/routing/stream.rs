@@ -25,14 +25,14 @@ impl StreamBridge {
     /// Returns `false` if upstream has not written anything yet.
     pub fn bridge(&self, upstream_id: usize, downstream_id: usize) -> bool {
         let sb = self.superblock();
-        if upstream_id >= sb.writer_heads.len() || downstream_id >= sb.writer_heads.len() {
+        if upstream_id >= sb.stream_slot_count() || downstream_id >= sb.stream_slot_count() {
             return false;
         }
-        let head = sb.writer_heads[upstream_id].load(Ordering::Acquire);
+        let head = sb.writer_head(upstream_id).load(Ordering::Acquire);
         if head == 0 { return false; }
-        let tail = sb.writer_tails[upstream_id].load(Ordering::Acquire);
-        sb.writer_heads[downstream_id].store(head, Ordering::Release);
-        sb.writer_tails[downstream_id].store(tail, Ordering::Release);
+        let tail = sb.writer_tail(upstream_id).load(Ordering::Acquire);
+        sb.writer_head(downstream_id).store(head, Ordering::Release);
+        sb.writer_tail(downstream_id).store(tail, Ordering::Release);
         true
     }
 }
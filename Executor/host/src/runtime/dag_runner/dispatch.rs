@@ -1,8 +1,11 @@
 use anyhow::{anyhow, Result};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use wasmtime::*;
 use crate::policy::{EqualSlice, FixedMapPartition, FixedSizeSlice, LineBoundarySlice, ModuloPartition, RoundRobinPartition};
+use crate::policy::{ConsumptionPolicy, LargestPayloadWinsPolicy, LastWriteWinsPolicy, MajorityWinsPolicy, MaxIdWinsPolicy, MinIdWinsPolicy};
+use crate::policy::{ConcatMerge, FnMerge, IntType, NumericMerge, NumericOp, SetUnionMerge};
 use crate::routing::aggregate::AggregateConnection;
 use crate::routing::broadcast::BroadcastConnection;
 use crate::routing::dispatch::{FileDispatcher, OwnedSlice};
@@ -11,12 +14,16 @@ use crate::routing::stream::StreamBridge;
 use crate::runtime::input_output::slot_loader::{SlotLoader, PrefetchHandle};
 use crate::runtime::input_output::logger::HostLogger;
 use crate::runtime::input_output::slot_flusher::SlotFlusher;
+use crate::runtime::input_output::file_set::glob_match;
 use crate::runtime::mem_operation::reclaimer::{self, SlotKind};
+use crate::runtime::mem_operation::kv_store::KvStore;
+use crate::runtime::mem_operation::organizer::BucketOrganizer;
 use crate::runtime::mem_operation::slicer::Slicer;
 use crate::runtime::worker::WorkerState;
 use crate::runtime::input_output::persistence::{PersistenceOptions, PersistenceWriter};
 use crate::runtime::remote::{execute_remote_recv, execute_remote_send};
-use common::{atomic_shm_offset, rdma_scratch_shm_offset, REGISTRY_OFFSET, RegistryEntry};
+use crate::shm::layout_at;
+use common::RegistryEntry;
 use super::types::*;
 use super::workers::{spawn_wasm_subprocess, spawn_python_subprocess};
 use super::grouping::{execute_wasm_grouping, execute_py_grouping};
@@ -35,7 +42,7 @@ fn resolve_atomic_index(splice_addr: usize, name: &str) -> Result<usize> {
     name_key[..src.len().min(52)].copy_from_slice(&src[..src.len().min(52)]);
 
     let sb            = unsafe { &*(splice_addr as *const Superblock) };
-    let registry_base = (splice_addr + REGISTRY_OFFSET as usize) as *const RegistryEntry;
+    let registry_base = (splice_addr + layout_at(splice_addr).registry_offset as usize) as *const RegistryEntry;
     let count         = sb.next_atomic_idx.load(Ordering::Acquire) as usize;
 
     for i in 0..count {
@@ -50,17 +57,91 @@ fn resolve_atomic_index(splice_addr: usize, name: &str) -> Result<usize> {
 /// Read the current value of a named AtomicU64 from the local SHM atomic arena.
 fn read_local_atomic(splice_addr: usize, idx: usize) -> u64 {
     use std::sync::atomic::{AtomicU64, Ordering};
-    let ptr = (splice_addr + atomic_shm_offset(idx) as usize) as *const AtomicU64;
+    let ptr = (splice_addr + layout_at(splice_addr).atomic_offset(idx) as usize) as *const AtomicU64;
     unsafe { (*ptr).load(Ordering::Acquire) }
 }
 
 /// Write a value into a named AtomicU64 in the local SHM atomic arena.
 fn write_local_atomic(splice_addr: usize, idx: usize, val: u64) {
     use std::sync::atomic::{AtomicU64, Ordering};
-    let ptr = (splice_addr + atomic_shm_offset(idx) as usize) as *mut AtomicU64;
+    let ptr = (splice_addr + layout_at(splice_addr).atomic_offset(idx) as usize) as *mut AtomicU64;
     unsafe { (*ptr).store(val, Ordering::Release) };
 }
 
+// ─── Shared-state resolution helpers ──────────────────────────────────────────
+
+fn consumption_policy<'a>(kind: &'a ConsumptionPolicyKind, guest: &'a GuestFold<'_>) -> Box<dyn ConsumptionPolicy + 'a> {
+    let numeric = |op, int: &IntType| -> Box<dyn ConsumptionPolicy> { Box::new(NumericMerge { op, int: *int }) };
+    match kind {
+        ConsumptionPolicyKind::MaxIdWins          => Box::new(MaxIdWinsPolicy),
+        ConsumptionPolicyKind::MinIdWins          => Box::new(MinIdWinsPolicy),
+        ConsumptionPolicyKind::MajorityWins       => Box::new(MajorityWinsPolicy),
+        ConsumptionPolicyKind::LastWriteWins      => Box::new(LastWriteWinsPolicy),
+        ConsumptionPolicyKind::LargestPayloadWins => Box::new(LargestPayloadWinsPolicy),
+        ConsumptionPolicyKind::SumMerge { int }   => numeric(NumericOp::Sum, int),
+        ConsumptionPolicyKind::MinMerge { int }   => numeric(NumericOp::Min, int),
+        ConsumptionPolicyKind::MaxMerge { int }   => numeric(NumericOp::Max, int),
+        ConsumptionPolicyKind::SetUnion { delimiter, multiset } => Box::new(SetUnionMerge {
+            // `validate_dag` checks the delimiter is one byte.
+            delimiter: delimiter.as_bytes()[0],
+            multiset:  *multiset,
+        }),
+        ConsumptionPolicyKind::Concat { separator } => Box::new(ConcatMerge {
+            separator: separator.as_bytes().to_vec(),
+        }),
+        ConsumptionPolicyKind::WasmMerge { func, slot } => Box::new(FnMerge(move |inputs: &[(u32, &[u8])]| {
+            guest.fold(func, *slot, inputs).unwrap_or_else(|e| {
+                guest.error.borrow_mut().get_or_insert(e);
+                None
+            })
+        })),
+    }
+}
+
+/// The runner's own WASM instance, lent to `WasmMerge` policies while the
+/// organizer runs.  The first failing call is kept in `error`.
+struct GuestFold<'a> {
+    store:    RefCell<&'a mut Store<WorkerState>>,
+    instance: &'a Instance,
+    memory:   &'a Memory,
+    error:    RefCell<Option<anyhow::Error>>,
+}
+
+impl GuestFold<'_> {
+    /// Load `inputs` into I/O slot `slot`, call `func(slot) -> u64` and copy
+    /// the fat-pointer result out.  The slot is freed again either way.
+    fn fold(&self, func: &str, slot: u32, inputs: &[(u32, &[u8])]) -> Result<Option<Vec<u8>>> {
+        let mut store = self.store.borrow_mut();
+        let splice_addr = store.data().splice_addr;
+        let records: Vec<(u32, Vec<u8>)> = inputs.iter().map(|&(w, p)| (w, p.to_vec())).collect();
+        let result = SlotLoader::new(splice_addr).load_records(slot, &records).and_then(|_| {
+            let f = self.instance.get_typed_func::<u32, u64>(&mut **store, func)
+                .map_err(|e| anyhow!("no export '{}': {}", func, e))?;
+            let packed = f.call(&mut **store, slot)?;
+            if packed == 0 {
+                return Ok(None);
+            }
+            let (ptr, len) = ((packed >> 32) as usize, (packed & 0xFFFF_FFFF) as usize);
+            self.memory.data(&**store).get(ptr..ptr + len)
+                .map(|bytes| Some(bytes.to_vec()))
+                .ok_or_else(|| anyhow!("'{}' returned {:#x}+{} outside guest memory", func, ptr, len))
+        });
+        reclaimer::free_io_slot(splice_addr, slot as usize);
+        reclaimer::reset_slot_cursor(splice_addr, SlotKind::Io, slot as usize);
+        result
+    }
+}
+
+/// Append `lines` to `path`, creating it and its parent directory if absent.
+fn append_report(path: &Path, lines: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::OpenOptions::new().create(true).append(true).open(path)?
+        .write_all(lines.as_bytes())
+}
+
 // ─── Node executor ────────────────────────────────────────────────────────────
 
 pub(super) fn execute_node(
@@ -129,23 +210,91 @@ pub(super) fn execute_node(
                     println!("  Watch: no writer available — skipped");
                     log("watch skipped: no persistence writer");
                 }
-                Some(w) => match (&p.stream, &p.shared) {
-                    (Some(slot), None) => {
+                Some(w) => match (&p.stream, &p.shared, &p.kv) {
+                    (Some(slot), None, None) => {
                         let slot = *slot;
-                        w.watch_stream(splice_addr, slot, &p.output);
+                        match &p.schema {
+                            Some(schema) => w.watch_stream_typed(splice_addr, slot, schema, &p.output)
+                                .map_err(|e| anyhow!("[{}] watch failed: {}", node.id, e))?,
+                            None => w.watch_stream(splice_addr, slot, &p.output),
+                        }
                         println!("  Watch stream {} → \"{}\" [background]", slot, p.output);
                         log(&format!("watch stream {} → \"{}\"", slot, p.output));
                     }
-                    (None, Some(name)) => {
+                    (None, Some(name), None) => {
                         w.watch_shared(splice_addr, name, &p.output);
                         println!("  Watch shared \"{}\" → \"{}\" [background]", name, p.output);
                         log(&format!("watch shared \"{}\" → \"{}\"", name, p.output));
                     }
-                    _ => println!("  Watch: set exactly one of `stream` or `shared`"),
+                    (None, None, Some(prefix)) => {
+                        w.watch_kv(splice_addr, prefix, &p.output);
+                        println!("  Watch kv \"{}\" → \"{}\" [background]", prefix, p.output);
+                        log(&format!("watch kv \"{}\" → \"{}\"", prefix, p.output));
+                    }
+                    _ => println!("  Watch: set exactly one of `stream`, `shared` or `kv`"),
                 },
             }
         }
 
+        // ── Shared-state conflict resolution ──────────────────────────────────
+        NodeKind::ResolveSharedState(p) => {
+            // Policy 0 is `default`, policy i + 1 is `rules[i]`.
+            let policy_index = |key: &str| {
+                p.rules.iter().position(|r| glob_match(&r.pattern, key)).map_or(0, |i| i + 1)
+            };
+            let kinds: Vec<&ConsumptionPolicyKind> =
+                std::iter::once(&p.default).chain(p.rules.iter().map(|r| &r.policy)).collect();
+            let guest = GuestFold {
+                store: RefCell::new(&mut *store),
+                instance,
+                memory,
+                error: RefCell::new(None),
+            };
+            let policies: Vec<Box<dyn ConsumptionPolicy + '_>> =
+                kinds.iter().map(|kind| consumption_policy(kind, &guest)).collect();
+            let organizer = BucketOrganizer::at(splice_addr);
+            let resolutions = unsafe {
+                organizer.resolve_shared_state(|key| policies[policy_index(key)].as_ref())
+            };
+            if let Some(e) = guest.error.borrow_mut().take() {
+                return Err(anyhow!("[{}] WasmMerge failed: {}", node.id, e));
+            }
+
+            let mut report = String::new();
+            for r in &resolutions {
+                let policy = kinds[policy_index(&r.key)].name();
+                match (r.winner, r.merged) {
+                    (Some(w), _) => println!(
+                        "  [ResolveSharedState] \"{}\" ({}): writer {} of {} ({} bytes)",
+                        r.key, policy, w, r.candidates, r.bytes,
+                    ),
+                    (None, true) => println!(
+                        "  [ResolveSharedState] \"{}\" ({}): merged {} writes ({} bytes)",
+                        r.key, policy, r.candidates, r.bytes,
+                    ),
+                    (None, false) => println!(
+                        "  [ResolveSharedState] \"{}\" ({}): nothing committed from {} writes",
+                        r.key, policy, r.candidates,
+                    ),
+                }
+                report.push_str(&serde_json::json!({
+                    "key":        r.key,
+                    "policy":     policy,
+                    "candidates": r.candidates,
+                    "winner":     r.winner,
+                    "merged":     r.merged,
+                    "bytes":      r.bytes,
+                }).to_string());
+                report.push('\n');
+            }
+            if let Some(path) = &p.report {
+                append_report(Path::new(path), &report)
+                    .map_err(|e| anyhow!("[{}] cannot write report \"{}\": {}", node.id, path, e))?;
+            }
+            println!("  ResolveSharedState: {} key(s) resolved", resolutions.len());
+            log(&format!("resolved {} shared-state key(s)", resolutions.len()));
+        }
+
         // ── Persistence snapshot (async background, or sync barrier) ──────────
         NodeKind::Persist(p) => {
             let opts = PersistenceOptions {
@@ -153,6 +302,7 @@ pub(super) fn execute_node(
                 atomics:      p.atomics,
                 stream_slots: p.stream_slots.clone(),
                 shared_state: p.shared_state,
+                kv:           p.kv,
             };
             match persist_writer {
                 Some(w) => {
@@ -219,7 +369,7 @@ pub(super) fn execute_node(
                 } else {
                     p.path.as_str()
                 };
-                let count = outputer.save_slot(Path::new(path), slot)
+                let count = outputer.save_slot_with(Path::new(path), slot, &p.options())
                     .map_err(|e| anyhow!("[{}] output save failed: {}", node.id, e))?;
                 println!("  Output slot {} → \"{}\" ({} records)", slot, path, count);
                 log(&format!("output slot {} saved to \"{}\" ({} records)", slot, path, count));
@@ -241,6 +391,14 @@ pub(super) fn execute_node(
                 println!("  FreeSlots: I/O slot {} freed", s);
                 log(&format!("freed I/O slot {}", s));
             }
+            if p.kv {
+                // Safety: like ResolveSharedState, the DAG orders this node after
+                // every guest that uses the store (see `FreeSlotsParams::kv`).
+                let c = unsafe { KvStore::at(splice_addr).compact() };
+                println!("  FreeSlots: key-value store compacted ({} kept, {} entries / {} pages freed)",
+                    c.kept, c.freed_entries, c.freed_pages);
+                log(&format!("compacted key-value store: {} kept, {} freed", c.kept, c.freed_entries));
+            }
         }
 
         // ── FileDispatch: load file → slice → parallel workers ───────────────
@@ -354,9 +512,40 @@ pub(super) fn execute_node(
             if p.chunk_bytes.is_some() {
                 // Chunked input is loaded by the run loop (one chunk per run);
                 // nothing to do here.
+            } else if let Some(set) = &p.files {
+                // Directory / glob input: `slice` picks this node's share of
+                // the file list, `cycle` one file of that share per run.
+                let all = set.resolve().map_err(|e| anyhow!("[{}] input: {}", node.id, e))?;
+                let total = all.len();
+                let mut files = set.shard(all, p.slice);
+                if p.cycle && !files.is_empty() {
+                    files = vec![files.swap_remove(run_index % files.len())];
+                }
+                let shard = p.slice
+                    .map(|[lo, hi]| format!(", slice {:.4}..{:.4} by {:?}", lo, hi, set.shard_by))
+                    .unwrap_or_default();
+                let kind = if p.binary { "binary" } else { p.format.label() };
+                if p.prefetch {
+                    println!("  Input ← \"{}\" slot {} [{} of {} files, {}{}, prefetch started]",
+                             set.path, slot, files.len(), total, kind, shard);
+                    log(&format!("input prefetch started: '{}' → slot {} ({} of {} files)",
+                                 set.path, slot, files.len(), total));
+                    let handle = SlotLoader::prefetch_files(
+                        splice_addr, files, slot, p.binary, p.zero_copy, p.format.clone(), p.compression,
+                    );
+                    prefetch_handles.insert(node.id.clone(), handle);
+                } else {
+                    let count = SlotLoader::new(splice_addr).zero_copy(p.zero_copy)
+                        .load_files(&files, slot, p.binary, &p.format, p.compression)
+                        .map_err(|e| anyhow!("[{}] file set input load failed: {}", node.id, e))?;
+                    println!("  Input ← \"{}\" slot {} [{} of {} files, {}{}] ({} records)",
+                             set.path, slot, files.len(), total, kind, shard, count);
+                    log(&format!("input loaded '{}' → slot {} ({} of {} files, {} records)",
+                                 set.path, slot, files.len(), total, count));
+                }
             } else if p.binary {
                 let paths: &[String] = if !p.paths.is_empty() { &p.paths } else { std::slice::from_ref(&p.path) };
-                let inputer = SlotLoader::new(splice_addr);
+                let inputer = SlotLoader::new(splice_addr).zero_copy(p.zero_copy);
                 if p.cycle && paths.len() > 1 {
                     // cycle: true — load exactly one path per run, rotating through the list.
                     let path = &paths[run_index % paths.len()];
@@ -382,18 +571,19 @@ pub(super) fn execute_node(
                 };
                 // Fire off the load in a background thread; the executor will
                 // join this handle before the first node that lists us as a dep.
-                let handle = match p.slice {
+                match p.slice {
                     Some([lo, hi]) => {
                         println!("  Input ← \"{}\" slot {} [prefetch started, slice {:.4}..{:.4}]", path, slot, lo, hi);
                         log(&format!("input prefetch started: '{}' → slot {} slice {:.4}..{:.4}", path, slot, lo, hi));
-                        SlotLoader::prefetch_slice(splice_addr, PathBuf::from(path), slot, lo, hi)
                     }
                     None => {
                         println!("  Input ← \"{}\" slot {} [prefetch started]", path, slot);
                         log(&format!("input prefetch started: '{}' → slot {}", path, slot));
-                        SlotLoader::prefetch(splice_addr, PathBuf::from(path), slot)
                     }
-                };
+                }
+                let handle = SlotLoader::prefetch_formatted(
+                    splice_addr, PathBuf::from(path), slot, p.format.clone(), p.compression, p.slice,
+                );
                 prefetch_handles.insert(node.id.clone(), handle);
             } else {
                 let path = if !p.paths.is_empty() {
@@ -401,14 +591,9 @@ pub(super) fn execute_node(
                 } else {
                     p.path.as_str()
                 };
-                let count = match p.slice {
-                    Some([lo, hi]) => SlotLoader::new(splice_addr)
-                        .load_slice(Path::new(path), slot, lo, hi)
-                        .map_err(|e| anyhow!("[{}] input slice load failed: {}", node.id, e))?,
-                    None => SlotLoader::new(splice_addr)
-                        .load(Path::new(path), slot)
-                        .map_err(|e| anyhow!("[{}] input load failed: {}", node.id, e))?,
-                };
+                let count = SlotLoader::new(splice_addr)
+                    .load_formatted(Path::new(path), slot, &p.format, p.compression, p.slice)
+                    .map_err(|e| anyhow!("[{}] input load failed: {}", node.id, e))?;
                 println!("  Input ← \"{}\" slot {} ({} records)", path, slot, count);
                 log(&format!("input loaded '{}' → slot {} ({} records)", path, slot, count));
             }
@@ -493,8 +678,8 @@ pub(super) fn execute_node(
             ))?;
             let splice_addr  = store.data().splice_addr;
             let idx          = resolve_atomic_index(splice_addr, &p.name)?;
-            let remote_off   = atomic_shm_offset(idx);
-            let result_off   = rdma_scratch_shm_offset(mesh.id, p.peer);
+            let remote_off   = layout_at(splice_addr).atomic_offset(idx);
+            let result_off   = layout_at(splice_addr).rdma_scratch_slot_offset(mesh.id, p.peer);
             log(&format!(
                 "remote FAA '{}' (idx={}) on peer {} += {}",
                 p.name, idx, p.peer, p.add
@@ -515,8 +700,8 @@ pub(super) fn execute_node(
             ))?;
             let splice_addr  = store.data().splice_addr;
             let idx          = resolve_atomic_index(splice_addr, &p.name)?;
-            let remote_off   = atomic_shm_offset(idx);
-            let result_off   = rdma_scratch_shm_offset(mesh.id, p.peer);
+            let remote_off   = layout_at(splice_addr).atomic_offset(idx);
+            let result_off   = layout_at(splice_addr).rdma_scratch_slot_offset(mesh.id, p.peer);
             log(&format!(
                 "remote CAS '{}' (idx={}) on peer {}: {} → {}",
                 p.name, idx, p.peer, p.compare, p.swap
@@ -541,8 +726,8 @@ pub(super) fn execute_node(
             let splice_addr = store.data().splice_addr;
             let idx         = resolve_atomic_index(splice_addr, &p.name)?;
             let local_val   = read_local_atomic(splice_addr, idx);
-            let remote_off  = atomic_shm_offset(idx);
-            let result_off  = rdma_scratch_shm_offset(mesh.id, p.peer);
+            let remote_off  = layout_at(splice_addr).atomic_offset(idx);
+            let result_off  = layout_at(splice_addr).rdma_scratch_slot_offset(mesh.id, p.peer);
             log(&format!(
                 "remote push '{}' (idx={}) → peer {}: local={} (adding to owner)",
                 p.name, idx, p.peer, local_val
@@ -37,16 +37,41 @@
 //! { "id": "load", "deps": [], "kind": { "Input": { "path": "/data/rows.csv", "slot": 42, "prefetch": true } } }
 //! ```
 //! Omitting `"slot"` defaults to `INPUT_IO_SLOT`.
+//! `"format"` selects the record framing (`Lines` default, `Jsonl`, `Csv`,
+//! `Fixed`, `LengthPrefixed`) and `"compression"` (`auto` default, `none`,
+//! `gzip`, `zstd`) streams compressed corpora without a decompressed copy:
+//! ```json
+//! { "id": "load", "deps": [], "kind": { "Input": { "path": "/data/rows.csv.gz", "format": { "type": "Csv", "header": true } } } }
+//! { "id": "load", "deps": [], "kind": { "Input": { "path": "/data/tera.bin.zst", "format": { "type": "Fixed", "record_bytes": 100 }, "slice": [0.0, 0.5] } } }
+//! ```
+//! `"files"` loads a directory or glob instead of one path — sorted, filtered,
+//! capped, and sharded by `slice` across whole files (by count or bytes):
+//! ```json
+//! { "id": "load", "deps": [], "kind": { "Input": { "files": { "path": "/data/imgs/*.png", "limit": 64 }, "binary": true } } }
+//! { "id": "load", "deps": [], "kind": { "Input": { "files": { "path": "/data/logs", "recursive": true, "include": ["*.log.gz"], "shard_by": "bytes" }, "slice": [0.0, 0.5] } } }
+//! ```
 //! With `"prefetch": true` the I/O runs in a background thread, overlapping
 //! with any independent nodes that run before the first node that depends on
-//! this `Input` node.
+//! this `Input` node.  `"zero_copy": true` (binary only) maps each file over
+//! its slot pages instead of copying it (see `input_output::mapped_input`).
+//! With the DAG-level `"io": { "backend": "uring" }`, binary file sets are
+//! read in batches on one shared io_uring (see `input_output::uring`).
 //!
 //! ## Output node
 //! ```json
 //! { "id": "save", "deps": ["worker"], "kind": { "Output": { "path": "/tmp/result.txt" } } }
 //! { "id": "save", "deps": ["worker"], "kind": { "Output": { "path": "/tmp/result.txt", "slot": 42 } } }
+//! { "id": "save", "deps": ["worker"], "kind": { "Output": { "path": "/tmp/result.jsonl.gz", "framing": "jsonl" } } }
+//! { "id": "save", "deps": ["worker"], "kind": { "Output": { "path": "/tmp/parts/r-{part}.txt", "partition": { "type": "KeyHash", "files": 8, "key_delimiter": "\t" } } } }
 //! ```
-//! Omitting `"slot"` defaults to `OUTPUT_IO_SLOT`.
+//! Omitting `"slot"` defaults to `OUTPUT_IO_SLOT`.  `framing` is `newline`
+//! (default), `length_prefixed`, `raw` or `jsonl`; `compression` defaults to
+//! `auto` (by extension).  Output is written to a temp file and renamed into
+//! place (`"atomic": false` writes in place).  Partitioned output also gets
+//! a manifest listing its parts, written after the last part is committed
+//! (see `input_output::slot_flusher`).  `"direct": true` writes output
+//! with O_DIRECT.  With the `uring` I/O backend, uncompressed unpartitioned
+//! non-JSONL output is written by gathered writes from the slot's pages.
 //!
 //! ## Watch node (lightweight)
 //! ```json
@@ -69,6 +94,90 @@
 //! }
 //! ```
 //!
+//! ## Named slots
+//! Any slot field (`slot`, `from`/`to`, `upstream`/`downstream`, `stream`,
+//! `stream_slots`, `io`, `persist_slots`, and guest `arg`/`arg0`/`arg1`/`arg2`)
+//! may hold a string name instead of a number.  Names are resolved to free
+//! indices at load time and the mapping is printed as `[DAG] Slot names:`.
+//! An optional top-level `"slots"` map pins a name's area and lifetime:
+//! ```json
+//! {
+//!   "slots": { "merged": { "kind": "Stream", "lifetime": "persist" }, "corpus": { "kind": "Io" } },
+//!   "nodes": [
+//!     { "id": "load", "deps": [],      "kind": { "Input":     { "path": "/data/in.txt", "slot": "corpus" } } },
+//!     { "id": "agg",  "deps": ["map"], "kind": { "Aggregate": { "upstream": ["m0", "m1"], "downstream": "merged" } } }
+//!   ]
+//! }
+//! ```
+//! Undeclared names take the area of the field they appear in (`Stream` for
+//! guest arguments).  Using one name in both areas, or `"persist"` on an `Io`
+//! slot, fails validation.
+//!
+//! ## Page classes
+//! A slot's chain is built from 4 KiB pages unless the slot is given a larger
+//! class — `"64k"` or `"2m"` — with `"page_class"` in its `slots` declaration
+//! or an entry in the top-level `"page_classes"` list.  Bulk slots (large
+//! records, `RemoteSend` payloads) then carry fewer page headers and links,
+//! and an RDMA transfer needs one SGE per page instead of one per 4 KiB:
+//! ```json
+//! {
+//!   "slots": { "blobs": { "kind": "Stream", "page_class": "2m" } },
+//!   "page_classes": [{ "kind": "Io", "slot": 0, "class": "64k" }]
+//! }
+//! ```
+//!
+//! ## Ring slots
+//! A stream slot given a `"ring"` in its `slots` declaration, or listed in the
+//! top-level `"rings"`, is a bounded ring of pages instead of a chain that
+//! grows until the slot is freed.  Every consumer reads every record; pages
+//! all consumers have left are reused, and a producer that gets `pages` pages
+//! ahead of the slowest consumer blocks (`ShmApi::ring_push`) or is refused
+//! (`ShmApi::try_ring_push`).  Freeing the slot empties the ring but keeps it:
+//! ```json
+//! {
+//!   "slots": { "feed": { "kind": "Stream", "ring": { "pages": 16, "consumers": 2 } } },
+//!   "rings": [{ "slot": 40, "pages": 4 }]
+//! }
+//! ```
+//!
+//! ## Stream subscriptions
+//! A stream slot declared with `"subscribers"`, or listed in the top-level
+//! `"subscriptions"`, keeps one named read cursor per subscriber in SHM.  Each
+//! subscriber reads every record at its own pace (`ShmApi::subscription_recv`)
+//! without `Broadcast` or `fan_out` copies.  After each wave the runner frees
+//! the pages every subscriber has read past; freeing the slot rewinds them all:
+//! ```json
+//! {
+//!   "slots": { "events": { "kind": "Stream", "subscribers": ["audit", "index"] } },
+//!   "subscriptions": [{ "slot": 41, "subscribers": ["a", "b", "c"] }]
+//! }
+//! ```
+//!
+//! ## Remote atomics from guests
+//! With a `"rdma"` block, the runner serves the guest imports behind
+//! `ShmApi::remote_fetch_add` / `remote_compare_swap` / `remote_read_atomic`,
+//! which act on a named atomic owned by another node mid-computation.  The
+//! service only starts when the module imports one of them.  Over
+//! `"transport": "rdma"` (default) they are one-sided RDMA atomics; `"tcp"`
+//! forwards them between the runners' atomics services and sets up no RDMA
+//! mesh, so a multi-node DAG can run on one machine:
+//! ```json
+//! { "rdma": { "node_id": 0, "total": 2, "ips": ["127.0.0.1", "127.0.0.1"],
+//!             "transport": "tcp", "atomic_port": 7300 } }
+//! ```
+//!
+//! ## Result cache
+//! WASM and Python call nodes may opt into memoization.  The key hashes the
+//! module bytes, function, arguments and the records of the `inputs` slots; a
+//! hit re-appends the records the node originally wrote to its `outputs`
+//! instead of running it:
+//! ```json
+//! { "id": "map_0", "deps": ["distribute"], "kind": { "WasmVoid": { "func": "wc_map", "arg": 10, "cache": true,
+//!     "inputs": [{ "slot": 10, "slot_kind": "Stream" }], "outputs": [{ "slot": 110, "slot_kind": "Stream" }] } } }
+//! ```
+//! Entries live under `"cache_dir"` (default `.dag_cache`); `host cache gc
+//! [--dir D] [--max-bytes N] [--max-age-days D]` bounds the directory.
+//!
 //! ## Shuffle policies
 //! ```json
 //! { "type": "Modulo" }
@@ -120,27 +229,42 @@ mod grouping;
 mod pipeline;
 mod stage_fanout;
 mod dispatch;
+mod slot_names;
+mod result_cache;
+mod spill;
+mod workload_meta;
 
 pub use types::*;
+pub use result_cache::{parse_size, run_cache_gc, DEFAULT_CACHE_DIR, DEFAULT_GC_MAX_BYTES};
 
 use anyhow::{anyhow, Result};
 use serde_json;
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 use std::fs::OpenOptions;
+use std::sync::Arc;
 use wasmtime::*;
-use crate::runtime::input_output::slot_loader::{PrefetchHandle, SlotLoader};
+use crate::runtime::engine_config;
+use crate::runtime::input_output::slot_loader::{ChunkCursor, PrefetchHandle, SlotLoader};
+use crate::runtime::input_output::record_format::{Compression, RecordFormat};
 use crate::runtime::input_output::logger::HostLogger;
+use crate::runtime::input_output::uring;
 use crate::runtime::mem_operation::reclaimer::{self, SlotKind};
+use crate::runtime::mem_operation::ring::RingSlot;
+use crate::runtime::mem_operation::subscriptions::Subscriptions;
 use crate::runtime::worker::{create_wasmtime_engine, setup_vma_environment, WorkerState};
 use crate::runtime::input_output::persistence::PersistenceWriter;
 use crate::shm::{format_shared_memory, sync_mapping_if_grown, sync_mapping_to_capacity};
 use common::WASM_PATH;
-use plan::{build_barrier_assignments, build_slot_refcounts, build_waves, is_oneshot_node, node_owned_slots, node_routed_upstream_slots, parse_level, topo_sort, validate_barrier_groups, validate_dag};
+use plan::{build_barrier_assignments, build_slot_refcounts, build_waves, is_oneshot_node, node_owned_slots, node_routed_upstream_slots, parse_level, topo_sort, validate_barrier_groups, validate_cache_outputs, validate_dag};
 use workers::{spawn_python_subprocess, spawn_wasm_subprocess};
 use dispatch::execute_node;
+use result_cache::{Lookup, PendingStore, ResultCache};
+use spill::SpillTier;
+use crate::runtime::remote::atomics::{AtomicService, AtomicTransport, REMOTE_ATOMIC_IMPORTS};
 use crate::runtime::remote::{execute_remote_recv, execute_remote_send, pre_alloc_staging, STAGE_BYTES_PER_PEER};
-use common::{atomic_shm_offset, rdma_scratch_shm_offset, REGISTRY_OFFSET, RegistryEntry};
+use crate::shm::layout_at;
+use common::RegistryEntry;
 
 // ─── Atomic arena helpers (used in wave loop thread spawns) ──────────────────
 
@@ -153,7 +277,7 @@ fn resolve_atomic_index_mod(splice_addr: usize, name: &str) -> Result<usize> {
     name_key[..src.len().min(52)].copy_from_slice(&src[..src.len().min(52)]);
 
     let sb    = unsafe { &*(splice_addr as *const Superblock) };
-    let base  = (splice_addr + REGISTRY_OFFSET as usize) as *const RegistryEntry;
+    let base  = (splice_addr + layout_at(splice_addr).registry_offset as usize) as *const RegistryEntry;
     let count = sb.next_atomic_idx.load(Ordering::Acquire) as usize;
     for i in 0..count {
         let entry = unsafe { &*base.add(i) };
@@ -167,17 +291,103 @@ fn resolve_atomic_index_mod(splice_addr: usize, name: &str) -> Result<usize> {
 #[inline]
 fn read_shm_atomic(splice_addr: usize, idx: usize) -> u64 {
     use std::sync::atomic::{AtomicU64, Ordering};
-    let ptr = (splice_addr + atomic_shm_offset(idx) as usize) as *const AtomicU64;
+    let ptr = (splice_addr + layout_at(splice_addr).atomic_offset(idx) as usize) as *const AtomicU64;
     unsafe { (*ptr).load(Ordering::Acquire) }
 }
 
 #[inline]
 fn write_shm_atomic(splice_addr: usize, idx: usize, val: u64) {
     use std::sync::atomic::{AtomicU64, Ordering};
-    let ptr = (splice_addr + atomic_shm_offset(idx) as usize) as *mut AtomicU64;
+    let ptr = (splice_addr + layout_at(splice_addr).atomic_offset(idx) as usize) as *mut AtomicU64;
     unsafe { (*ptr).store(val, Ordering::Release) };
 }
 
+/// Store each `page_classes` entry in the slot table, before anything writes
+/// to the slots.  `validate_dag` has checked the names and indices.
+fn apply_page_classes(splice_addr: usize, classes: &[SlotPageClass]) {
+    use std::sync::atomic::Ordering;
+
+    let sb = unsafe { &*(splice_addr as *const common::Superblock) };
+    for pc in classes {
+        let class = common::page_class_from_name(&pc.class).unwrap_or(common::PAGE_CLASS_SMALL) as u8;
+        match pc.kind {
+            RemoteSlotKind::Stream => sb.stream_page_class(pc.slot as usize).store(class, Ordering::Relaxed),
+            RemoteSlotKind::Io     => sb.io_page_class(pc.slot as usize).store(class, Ordering::Relaxed),
+        }
+        println!("[DAG] {:?} slot {} uses {} pages", pc.kind, pc.slot, pc.class.to_ascii_lowercase());
+    }
+}
+
+/// Turn each `rings` entry's stream slot into a ring, after the page classes
+/// (a ring's pages are allocated in its slot's class).  `validate_dag` has
+/// checked the slots and sizes.
+fn apply_rings(splice_addr: usize, rings: &[SlotRing]) -> Result<()> {
+    for r in rings {
+        RingSlot::create(splice_addr, r.slot as usize, r.pages, r.consumers)?;
+        println!("[DAG] Stream slot {} is a ring of {} pages, {} consumers", r.slot, r.pages, r.consumers);
+    }
+    Ok(())
+}
+
+/// Give each `subscriptions` entry's stream slot its subscriber table.
+/// `validate_dag` has checked the slots and names.
+fn apply_subscriptions(splice_addr: usize, subscriptions: &[SlotSubscriptions]) -> Result<()> {
+    for s in subscriptions {
+        Subscriptions::create(splice_addr, s.slot as usize, &s.subscribers)?;
+        println!("[DAG] Stream slot {} has subscribers {}", s.slot, s.subscribers.join(", "));
+    }
+    Ok(())
+}
+
+/// Free the pages of each subscribed slot that every subscriber has read.
+/// Runs between waves, when no guest is reading.
+fn reclaim_subscriptions(splice_addr: usize, subscriptions: &[SlotSubscriptions]) {
+    for s in subscriptions {
+        let Some(subs) = Subscriptions::at(splice_addr, s.slot as usize) else { continue };
+        match subs.reclaim() {
+            Ok(0) => {}
+            Ok(n) => println!("[DAG] Reclaimed {} page(s) of stream slot {} read by every subscriber", n, s.slot),
+            Err(e) => eprintln!("[DAG] Stream slot {}: {}", s.slot, e),
+        }
+    }
+}
+
+/// Start this node's service for guest-initiated remote atomics and publish
+/// its address for the workers spawned from here on (`WEBS_ATOMIC_ADDR`).
+/// With a mesh, requests go out as RDMA atomics and only local workers may
+/// connect; without one, peers forward to each other over TCP on their
+/// `rdma.ips` addresses.
+fn start_atomics_service(rdma: &RdmaConfig, splice_addr: usize, mesh: Option<&Arc<connect::MeshNode>>) -> Result<()> {
+    let port = |node: usize| -> Result<u16> {
+        u16::try_from(node).ok()
+            .and_then(|n| rdma.atomic_port.checked_add(n))
+            .ok_or_else(|| anyhow!("rdma.atomic_port {} + node {} is not a TCP port", rdma.atomic_port, node))
+    };
+    let local = port(rdma.node_id)?;
+    let (bind, transport) = match mesh {
+        Some(mesh) => ("127.0.0.1", AtomicTransport::Rdma(Arc::clone(mesh))),
+        None => {
+            let addrs = rdma.ips.iter().enumerate()
+                .map(|(i, ip)| Ok(format!("{}:{}", ip, port(i)?)))
+                .collect::<Result<Vec<_>>>()?;
+            // The service is unauthenticated: listen only on this node's
+            // mesh address, never on every interface.
+            let ip = rdma.ips.get(rdma.node_id)
+                .ok_or_else(|| anyhow!("rdma.ips has no address for node {}", rdma.node_id))?;
+            (ip.as_str(), AtomicTransport::Tcp { addrs })
+        }
+    };
+    let listener = std::net::TcpListener::bind((bind, local))
+        .map_err(|e| anyhow!("atomics service: cannot listen on {}:{}: {}", bind, local, e))?;
+    let service = AtomicService::start(rdma.node_id, rdma.total, splice_addr, listener, transport)?;
+    service.publish();
+    println!(
+        "[DAG] Remote atomics served on {} over {}",
+        service.addr(), if mesh.is_some() { "RDMA" } else { "TCP" }
+    );
+    Ok(())
+}
+
 // ─── Public entry points ──────────────────────────────────────────────────────
 
 /// Load a DAG from a JSON **file** and execute it.
@@ -188,8 +398,22 @@ pub fn run_dag_file(json_path: &str) -> Result<()> {
 }
 
 /// Load a DAG from a JSON **string** and execute it.
+///
+/// Symbolic slot names are resolved to indices here, before the JSON is
+/// deserialised, so [`run_dag`] only ever sees numeric slots.  Names are
+/// allocated within the slot counts the DAG's engine configuration formats
+/// the region with.
 pub fn run_dag_json(json: &str) -> Result<()> {
-    let dag: Dag = serde_json::from_str(json)
+    let mut value: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| anyhow!("Invalid DAG JSON: {}", e))?;
+    let overrides: Option<engine_config::EngineOverrides> = value.get("engine")
+        .map(|v| serde_json::from_value(v.clone()))
+        .transpose()
+        .map_err(|e| anyhow!("Invalid DAG JSON: engine: {}", e))?;
+    let engine_cfg = engine_config::resolve(overrides.as_ref())?;
+    let bindings = slot_names::resolve_slot_names(&mut value, &engine_cfg)?;
+    slot_names::print_slot_bindings(&bindings);
+    let dag: Dag = serde_json::from_value(value)
         .map_err(|e| anyhow!("Invalid DAG JSON: {}", e))?;
     run_dag(&dag)
 }
@@ -204,7 +428,21 @@ pub fn run_dag_json(json: &str) -> Result<()> {
 pub fn run_dag(dag: &Dag) -> Result<()> {
     println!("[DAG] Starting — shm: {} (mode: {:?})", dag.shm_path, dag.mode);
 
-    validate_dag(dag)?;
+    // Resolve the engine tunables before formatting: the region carries them
+    // to every worker subprocess, and its slot counts bound the DAG's slots.
+    let engine_cfg = engine_config::resolve(dag.engine.as_ref())?;
+    validate_dag(dag, &engine_cfg)?;
+    let wasm_path = dag.wasm_path.as_deref().unwrap_or(WASM_PATH);
+    workload_meta::check_module(dag, wasm_path, &engine_cfg)?;
+    engine_config::install(engine_cfg)?;
+    engine_config::print_effective(&engine_cfg);
+    if engine_cfg.huge_pages == common::HUGE_PAGES_HUGETLBFS
+        && dag.nodes.iter().any(|n| matches!(n.kind, NodeKind::PyFunc(_) | NodeKind::PyPipeline(_)))
+    {
+        return Err(anyhow!(
+            "huge_pages = 2 (hugetlbfs) cannot run Python nodes: shm.py writes the SHM file with write(2)"
+        ));
+    }
 
     // Format a fresh SHM region so prior data never leaks into the first run.
     format_shared_memory(&dag.shm_path)?;
@@ -217,7 +455,7 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
     let engine = create_wasmtime_engine()?;
     let mut store = Store::new(
         &engine,
-        WorkerState { file: file.try_clone()?, splice_addr: 0 },
+        WorkerState { file: file.try_clone()?, splice_addr: 0, atomics: None },
     );
     let mut linker = Linker::new(&engine);
     let memory = setup_vma_environment(&mut store, &mut linker, &file)?;
@@ -226,10 +464,13 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
     // and the reclaimer all call shm::try_grow_shm when the bump overflows).
     let splice_addr = store.data().splice_addr;
     crate::shm::register_shm_for_growth(file.try_clone()?, splice_addr);
+    apply_page_classes(splice_addr, &dag.page_classes);
+    apply_rings(splice_addr, &dag.rings)?;
+    apply_subscriptions(splice_addr, &dag.subscriptions)?;
 
-    let wasm_path = dag.wasm_path.as_deref().unwrap_or(WASM_PATH);
     let module = crate::runtime::worker::load_guest_module(&engine, wasm_path)?;
     let instance = linker.instantiate(&mut store, &module)?;
+    crate::runtime::worker::check_guest_layout(&mut store, &instance, wasm_path)?;
     let py_script = dag.python_script.as_deref().unwrap_or("");
     let py_wasm   = dag.python_wasm.as_deref();
 
@@ -257,7 +498,9 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
 
     // Validate and assign intra-wave barrier groups.
     validate_barrier_groups(&dag.nodes, &waves)?;
-    let (wave_barriers, barrier_groups) = build_barrier_assignments(&dag.nodes, &waves);
+    validate_cache_outputs(&dag.nodes, &waves)?;
+    let (wave_barriers, barrier_groups) =
+        build_barrier_assignments(&dag.nodes, &waves, engine_cfg.barrier_count as usize);
     if !barrier_groups.is_empty() {
         println!("[DAG] Barrier groups:");
         for (name, (bid, count)) in &barrier_groups {
@@ -265,7 +508,8 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
         }
     }
 
-    // Set up the RDMA full-mesh if any RemoteSend/RemoteRecv nodes are present.
+    // Set up the RDMA full-mesh if any RemoteSend/RemoteRecv nodes are present
+    // (the `tcp` transport has none: only guest remote atomics cross nodes).
     //
     // Pre-allocate staging pages FIRST (before any DAG nodes run), then
     // register the SHM itself as the RDMA Memory Region via connect_all_on_shm.
@@ -273,7 +517,7 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
     // initial SHM states (format_shared_memory), so the staging page offsets
     // are byte-for-byte identical on every machine — enabling the receiver to
     // read data that the sender RDMA-WROTEinto the same SHM offset.
-    let mesh: Option<std::sync::Arc<connect::MeshNode>> = if let Some(ref rdma) = dag.rdma {
+    let mesh: Option<Arc<connect::MeshNode>> = if let Some(rdma) = dag.rdma.as_ref().filter(|r| r.transport == MeshTransport::Rdma) {
         let splice_addr = store.data().splice_addr;
 
         // Reserve staging pages only when RDMA data transfer is enabled.
@@ -309,14 +553,26 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
             NodeKind::PyFunc(_) | NodeKind::PyPipeline(_)
         ));
         node.set_python_compat(has_python);
+        node.set_mr2_config(
+            engine_cfg.rdma_mr2_initial_size,
+            engine_cfg.mr2_idle_timeout_nanos,
+            engine_cfg.huge_pages,
+        );
         println!(
             "[DAG] RDMA mesh ready (node {} of {}), python_compat={}",
             rdma.node_id, rdma.total, has_python
         );
-        Some(std::sync::Arc::new(node))
+        Some(Arc::new(node))
     } else {
         None
     };
+    // Only modules that call the remote-atomic imports get a service, so
+    // other RDMA DAGs claim no extra port.
+    let uses_remote_atomics = module.imports()
+        .any(|i| i.module() == "env" && REMOTE_ATOMIC_IMPORTS.contains(&i.name()));
+    if let (Some(rdma), true) = (&dag.rdma, uses_remote_atomics) {
+        start_atomics_service(rdma, store.data().splice_addr, mesh.as_ref())?;
+    }
 
     let has_persistence = dag.nodes.iter().any(|n| matches!(n.kind, NodeKind::Persist(_) | NodeKind::Watch(_)));
 
@@ -405,18 +661,30 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
     // run (instead of whole-file) so inputs larger than the guest SHM window can
     // be processed incrementally.  We drive the run loop until every chunked
     // input reaches EOF; the generic Input dispatch skips these (loaded here).
-    struct ChunkedInput { slot: u32, path: String, chunk_bytes: usize, file_len: u64 }
-    let chunked_inputs: Vec<ChunkedInput> = dag.nodes.iter().filter_map(|n| {
+    //
+    // Plain newline-delimited files are chunked straight from an mmap by byte
+    // offset.  Framed (`format`) or compressed inputs keep a `ChunkCursor`
+    // whose decoder state carries over between runs instead.
+    struct ChunkedInput { slot: u32, path: String, chunk_bytes: usize, file_len: u64, cursor: Option<ChunkCursor> }
+    let mut chunked_inputs: Vec<ChunkedInput> = Vec::new();
+    for n in &dag.nodes {
         if let NodeKind::Input(p) = &n.kind {
             if let Some(cb) = p.chunk_bytes {
                 use common::INPUT_IO_SLOT;
                 let path = if !p.paths.is_empty() { p.paths[0].clone() } else { p.path.clone() };
                 let file_len = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-                return Some(ChunkedInput { slot: p.slot.unwrap_or(INPUT_IO_SLOT), path, chunk_bytes: cb, file_len });
+                let plain = p.format == RecordFormat::Lines
+                    && p.compression.resolve(std::path::Path::new(&path))? == Compression::None;
+                let cursor = if plain {
+                    None
+                } else {
+                    Some(ChunkCursor::open(std::path::Path::new(&path), &p.format, p.compression)
+                        .map_err(|e| anyhow!("[{}] chunked input: {}", n.id, e))?)
+                };
+                chunked_inputs.push(ChunkedInput { slot: p.slot.unwrap_or(INPUT_IO_SLOT), path, chunk_bytes: cb, file_len, cursor });
             }
         }
-        None
-    }).collect();
+    }
     let chunked_mode = !chunked_inputs.is_empty();
     let mut chunk_offsets: Vec<u64> = vec![0; chunked_inputs.len()];
     if chunked_mode {
@@ -426,6 +694,23 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
                  chunked_inputs[0].chunk_bytes / (1024 * 1024));
     }
 
+    // Result cache for `cache: true` WASM / Python nodes (see `result_cache`).
+    let mut result_cache = ResultCache::new(
+        dag.cache_dir.as_deref().unwrap_or(DEFAULT_CACHE_DIR), wasm_path, py_script,
+    );
+
+    // File I/O backend (`io` block): the shared io_uring, or blocking I/O.
+    let io = dag.io.as_ref();
+    uring::configure(
+        io.is_some_and(|io| io.backend == IoBackend::Uring),
+        io.map_or(0, |io| io.queue_depth),
+    );
+
+    // Spill tier for cold stream slots (`spill` block), planned over the waves.
+    let mut spill = dag.spill.as_ref()
+        .map(|cfg| SpillTier::new(cfg, dag, &waves))
+        .transpose()?;
+
     let mut run_count = 0u32;
 
     loop {
@@ -441,7 +726,15 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
             // file; re-sync this process's mapping before loading/walking pages.
             sync_mapping_to_capacity(splice_addr)?;
             let loader = SlotLoader::new(splice_addr);
-            for (k, c) in chunked_inputs.iter().enumerate() {
+            for (k, c) in chunked_inputs.iter_mut().enumerate() {
+                if let Some(cursor) = c.cursor.as_mut() {
+                    if !cursor.is_done() {
+                        loader.load_next_chunk(cursor, c.slot, c.chunk_bytes)?;
+                        chunk_offsets[k] = cursor.offset();
+                    }
+                    if !cursor.is_done() { all_chunks_done = false; }
+                    continue;
+                }
                 if chunk_offsets[k] < c.file_len {
                     let (_recs, consumed) = loader
                         .load_chunk(std::path::Path::new(&c.path), c.slot, chunk_offsets[k], c.chunk_bytes)?;
@@ -486,12 +779,17 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
         // Run each wave
         for (wave_idx, wave) in waves.iter().enumerate() {
             let wave_start = Instant::now();
-            // 0. Reset barrier counters for groups active in this wave.
+            // 0. Bring back spilled slots this wave may read.
+            if let Some(tier) = spill.as_mut() {
+                tier.reload_for_wave(store.data().splice_addr, wave_idx)?;
+            }
+
+            // Reset barrier counters for groups active in this wave.
             {
                 let splice_addr = store.data().splice_addr;
                 let sb = unsafe { &*(splice_addr as *const common::Superblock) };
                 for &bid in &wave_barriers[wave_idx] {
-                    sb.barriers[bid].store(0, std::sync::atomic::Ordering::Release);
+                    sb.barrier(bid).store(0, std::sync::atomic::Ordering::Release);
                 }
             }
 
@@ -525,21 +823,30 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
             }
 
             // 3a. Spawn all subprocess nodes (WASM + PyFunc) in parallel.
-            let mut children: Vec<(String, std::process::Child)> = sub_idxs
-                .iter()
-                .map(|&idx| {
-                    let node = &dag.nodes[idx];
-                    println!("[DAG] ── Node: {} ──", node.id);
-                    let child = match &node.kind {
-                        NodeKind::WasmVoid(_) | NodeKind::WasmU32(_) | NodeKind::WasmFatPtr(_) =>
-                            spawn_wasm_subprocess(node, dag.shm_path.as_str(), wasm_path)?,
-                        NodeKind::PyFunc(_) =>
-                            spawn_python_subprocess(node, dag.shm_path.as_str(), py_script, py_wasm)?,
-                        _ => unreachable!(),
-                    };
-                    Ok((node.id.clone(), child))
-                })
-                .collect::<Result<Vec<_>>>()?;
+            //     `cache: true` nodes are looked up first: a hit restores their
+            //     outputs and skips the spawn; a miss is stored after the wait.
+            let mut children: Vec<(String, std::process::Child)> = Vec::new();
+            let mut cache_misses: Vec<(String, PendingStore)> = Vec::new();
+            for &idx in &sub_idxs {
+                let node = &dag.nodes[idx];
+                println!("[DAG] ── Node: {} ──", node.id);
+                match result_cache.lookup(node, store.data().splice_addr)? {
+                    Some(Lookup::Hit) => {
+                        println!("  [{}] → ok (cached)", node.id);
+                        continue;
+                    }
+                    Some(Lookup::Miss(pending)) => cache_misses.push((node.id.clone(), pending)),
+                    None => {}
+                }
+                let child = match &node.kind {
+                    NodeKind::WasmVoid(_) | NodeKind::WasmU32(_) | NodeKind::WasmFatPtr(_) =>
+                        spawn_wasm_subprocess(node, dag.shm_path.as_str(), wasm_path)?,
+                    NodeKind::PyFunc(_) =>
+                        spawn_python_subprocess(node, dag.shm_path.as_str(), py_script, py_wasm)?,
+                    _ => unreachable!(),
+                };
+                children.push((node.id.clone(), child));
+            }
 
             // 3b. Partition host nodes: threaded (RDMA + StreamOutput sink) vs serial.
             //     StreamOutput runs as a thread so its per-round rdma_recv can
@@ -597,8 +904,8 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
                                 ))?;
                                 let idx        = resolve_atomic_index_mod(splice_addr, &p.name)?;
                                 let ch         = mesh.atomic_channel(p.peer);
-                                let remote_off = atomic_shm_offset(idx);
-                                let result_off = rdma_scratch_shm_offset(mesh.id, p.peer);
+                                let remote_off = layout_at(splice_addr).atomic_offset(idx);
+                                let result_off = layout_at(splice_addr).rdma_scratch_slot_offset(mesh.id, p.peer);
                                 let add_val    = p.add;
                                 let log_id     = id.clone();
                                 std::thread::spawn(move || {
@@ -614,8 +921,8 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
                                 ))?;
                                 let idx        = resolve_atomic_index_mod(splice_addr, &p.name)?;
                                 let ch         = mesh.atomic_channel(p.peer);
-                                let remote_off = atomic_shm_offset(idx);
-                                let result_off = rdma_scratch_shm_offset(mesh.id, p.peer);
+                                let remote_off = layout_at(splice_addr).atomic_offset(idx);
+                                let result_off = layout_at(splice_addr).rdma_scratch_slot_offset(mesh.id, p.peer);
                                 let compare    = p.compare;
                                 let swap       = p.swap;
                                 let log_id     = id.clone();
@@ -636,8 +943,8 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
                                 let idx        = resolve_atomic_index_mod(splice_addr, &p.name)?;
                                 let local_val  = read_shm_atomic(splice_addr, idx);
                                 let ch         = mesh.atomic_channel(p.peer);
-                                let remote_off = atomic_shm_offset(idx);
-                                let result_off = rdma_scratch_shm_offset(mesh.id, p.peer);
+                                let remote_off = layout_at(splice_addr).atomic_offset(idx);
+                                let result_off = layout_at(splice_addr).rdma_scratch_slot_offset(mesh.id, p.peer);
                                 let log_id     = id.clone();
                                 std::thread::spawn(move || {
                                     ch.rdma_fetch_add(remote_off, result_off, local_val)?;
@@ -718,6 +1025,13 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
             // grew. This is the one-shot/unrolled-DAG analogue of the chunked
             // loop's sync_mapping_to_capacity calls.
             sync_mapping_if_grown(splice_addr)?;
+            // Store cache misses now that their outputs are complete and mapped.
+            // A failed store only costs a future hit, so it never fails the run.
+            for (id, pending) in cache_misses {
+                if let Err(e) = result_cache.store(&id, pending, splice_addr) {
+                    eprintln!("[Cache] '{}' store failed: {}", id, e);
+                }
+            }
             for &idx in wave {
                 let node = &dag.nodes[idx];
 
@@ -839,10 +1153,19 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
                 }
             }
 
+            // Subscribed slots give back the pages all their subscribers
+            // have read.
+            reclaim_subscriptions(splice_addr, &dag.subscriptions);
+
             // After all per-node reclamation in this wave, check whether the
             // free list has grown past the configured threshold and trim it.
             reclaimer::trim_free_list(splice_addr);
 
+            // Still above the spill watermark: move cold slots to disk.
+            if let Some(tier) = spill.as_mut() {
+                tier.after_wave(splice_addr, wave_idx)?;
+            }
+
             wave_times.push((wave_idx, wave_start.elapsed(), wave.len()));
         }
 
@@ -885,6 +1208,10 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
         // Wait for all background persistence writes to complete.
         if let Some(ref mut w) = persist_writer { w.join(); }
 
+        if let Some(tier) = spill.as_mut() {
+            tier.finish_run(store.data().splice_addr, run_count)?;
+        }
+
         if let Some(ref lg) = logger {
             lg.info("DAG", &format!("run #{} completed", run_count));
         }
@@ -897,22 +1224,46 @@ pub fn run_dag(dag: &Dag) -> Result<()> {
         // needs.  Compared against CAPACITY_HARD_LIMIT (the wasm32 SHM window)
         // it shows how much headroom remains before a larger input overflows.
         {
-            use common::{Superblock, BUMP_ALLOCATOR_START, CAPACITY_HARD_LIMIT, MIB};
+            use common::{Superblock, CAPACITY_HARD_LIMIT, MIB};
             use std::sync::atomic::Ordering;
             let splice_addr = store.data().splice_addr;
             let sb = unsafe { &*(splice_addr as *const Superblock) };
+            let heap_start = sb.layout.bump_allocator_start;
             let used = sb.bump_allocator.load(Ordering::Acquire)
-                .saturating_sub(BUMP_ALLOCATOR_START);
+                .saturating_sub(heap_start);
             let cap = sb.global_capacity.load(Ordering::Acquire);
             println!(
                 "[DAG][shm] peak arena: {:.1} MiB used / {:.1} MiB mapped (hard limit {:.1} MiB) — headroom {:.1} MiB",
                 used as f64 / MIB as f64,
                 cap as f64 / MIB as f64,
                 CAPACITY_HARD_LIMIT as f64 / MIB as f64,
-                CAPACITY_HARD_LIMIT.saturating_sub(BUMP_ALLOCATOR_START.saturating_add(used)) as f64 / MIB as f64,
+                CAPACITY_HARD_LIMIT.saturating_sub(heap_start.saturating_add(used)) as f64 / MIB as f64,
             );
         }
 
+        // ── Page accounting (`fsck: true`) ───────────────────────────────────
+        // Every node has finished, so the region is quiescent.  Leak accounting
+        // is skipped while the extended pool is paged: its resolution buffer
+        // holds direct pages that no region structure points at.
+        if dag.fsck {
+            use crate::runtime::extended_pool;
+            use crate::runtime::mem_operation::fsck;
+            use crate::runtime::mem_operation::inspect::ShmView;
+            let splice_addr = store.data().splice_addr;
+            sync_mapping_to_capacity(splice_addr)?;
+            let sb = unsafe { &*(splice_addr as *const common::Superblock) };
+            let cap = sb.global_capacity.load(std::sync::atomic::Ordering::Acquire) as usize;
+            let bytes = unsafe { std::slice::from_raw_parts(splice_addr as *const u8, cap) };
+            let view = ShmView::new(bytes)?;
+            let paged = extended_pool::runtime::current_mode() == extended_pool::Mode::Paged;
+            let report = fsck::fsck(&format!("run #{}", run_count), &view, paged);
+            if !report.is_clean() {
+                fsck::print_report(&report);
+                return Err(anyhow!("[DAG][fsck] run #{}: {}", run_count, report.summary()));
+            }
+            println!("[DAG][fsck] run #{}: {}", run_count, report.summary());
+        }
+
         // Chunked input drives the loop: keep running until every input hits EOF,
         // regardless of mode.
         if chunked_mode {
@@ -3,9 +3,11 @@ use super::types::{StreamPipelineParams, PyPipelineParams, StreamOutputParams, R
 use super::stage_fanout;
 use super::workers::{WasmLoopWorker, PyLoopWorker};
 use crate::runtime::remote::{execute_remote_recv, execute_remote_send};
+use crate::runtime::remote::atomics::worker_env;
 use crate::runtime::mem_operation::reclaimer;
 use crate::runtime::input_output::persistence::PersistenceWriter;
-use common::{atomic_shm_offset, REGISTRY_OFFSET, RegistryEntry, Superblock};
+use crate::shm::layout_at;
+use common::{RegistryEntry, Superblock};
 
 // ─── Guest cursor reset helper ────────────────────────────────────────────────
 
@@ -35,12 +37,12 @@ fn reset_guest_slot_cursor(splice_addr: usize, slot: usize, slot_kind: RemoteSlo
     name_key[..src.len().min(52)].copy_from_slice(&src[..src.len().min(52)]);
 
     let sb    = unsafe { &*(splice_addr as *const Superblock) };
-    let base  = (splice_addr + REGISTRY_OFFSET as usize) as *const RegistryEntry;
+    let base  = (splice_addr + layout_at(splice_addr).registry_offset as usize) as *const RegistryEntry;
     let count = sb.next_atomic_idx.load(std::sync::atomic::Ordering::Acquire) as usize;
     for i in 0..count {
         let entry = unsafe { &*base.add(i) };
         if entry.name == name_key {
-            let ptr = (splice_addr + atomic_shm_offset(entry.index as usize) as usize)
+            let ptr = (splice_addr + layout_at(splice_addr).atomic_offset(entry.index as usize) as usize)
                 as *mut AtomicU64;
             unsafe { (*ptr).store(0, Ordering::Release) };
             return;
@@ -79,7 +81,7 @@ fn register_or_get_atomic(splice_addr: usize, name: &str) -> usize {
     key[..n].copy_from_slice(&src[..n]);
 
     let sb       = unsafe { &*(splice_addr as *const Superblock) };
-    let reg_base = (splice_addr + REGISTRY_OFFSET as usize) as *mut RegistryEntry;
+    let reg_base = (splice_addr + layout_at(splice_addr).registry_offset as usize) as *mut RegistryEntry;
 
     while sb.registry_lock
         .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
@@ -94,6 +96,10 @@ fn register_or_get_atomic(splice_addr: usize, name: &str) -> usize {
         if e.name == key { idx = e.index; break; }
     }
     if idx == u32::MAX {
+        if count as usize >= sb.layout.named_atomic_capacity() {
+            sb.registry_lock.store(0, Ordering::Release);
+            panic!("registry full ({} named atomics); raise registry_size / atomic_arena_size", count);
+        }
         idx = count;
         unsafe {
             core::ptr::write(reg_base.add(count as usize), RegistryEntry {
@@ -112,14 +118,14 @@ fn register_or_get_atomic(splice_addr: usize, name: &str) -> usize {
 /// Store `val` into the atomic-arena slot at `idx`.
 fn store_atomic(splice_addr: usize, idx: usize, val: u64) {
     use std::sync::atomic::{AtomicU64, Ordering};
-    let ptr = (splice_addr + atomic_shm_offset(idx) as usize) as *mut AtomicU64;
+    let ptr = (splice_addr + layout_at(splice_addr).atomic_offset(idx) as usize) as *mut AtomicU64;
     unsafe { (*ptr).store(val, Ordering::Release) };
 }
 
 /// Load the atomic-arena slot at `idx`.
 fn load_atomic(splice_addr: usize, idx: usize) -> u64 {
     use std::sync::atomic::{AtomicU64, Ordering};
-    let ptr = (splice_addr + atomic_shm_offset(idx) as usize) as *const AtomicU64;
+    let ptr = (splice_addr + layout_at(splice_addr).atomic_offset(idx) as usize) as *const AtomicU64;
     unsafe { (*ptr).load(Ordering::Acquire) }
 }
 
@@ -211,11 +217,13 @@ pub(super) fn execute_stream_pipeline(
         .map(|(s, stage)| stage.max_width.unwrap_or(0) > static_width[s])
         .collect();
     let max_spawn = spawn_w.iter().copied().max().unwrap_or(1);
-    if max_spawn > 1 && !stage_fanout::fits_band(depth, max_spawn) {
+    let stream_slots = layout_at(splice_addr).stream_slot_count as usize;
+    if max_spawn > 1 && !stage_fanout::fits_band(depth, max_spawn, stream_slots) {
         return Err(anyhow!(
             "[{}] StreamPipeline width too large: depth {} × width {} exceeds the \
-             reserved sub-slot band (max width {}, depth ≤ 8)",
-            node_id, depth, max_spawn, stage_fanout::MAX_STAGE_WIDTH
+             reserved sub-slot band (max width {}, depth ≤ 8, stream_slot_count ≥ {})",
+            node_id, depth, max_spawn, stage_fanout::MAX_STAGE_WIDTH,
+            stage_fanout::SUB_SLOT_BASE + 256,
         ));
     }
 
@@ -554,6 +562,7 @@ pub(super) fn execute_stream_pipeline(
         let status = std::process::Command::new(exe)
             .args(["wasm-call", shm_path, wasm_path, "dump_stream_records", "fatptr",
                    &summary_slot.to_string()])
+            .envs(worker_env())
             .status()
             .map_err(|e| anyhow!("[{}] dump_stream_records spawn: {}", node_id, e))?;
         if !status.success() {
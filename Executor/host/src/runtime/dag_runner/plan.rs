@@ -1,8 +1,10 @@
 use anyhow::{anyhow, Result};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use crate::runtime::mem_operation::reclaimer::SlotKind;
 use crate::runtime::input_output::logger::Level;
 use super::types::*;
+use super::result_cache::cache_spec;
+use common::{EngineConfig, HUGE_PAGES_HUGETLBFS};
 
 // ─── Logger helpers ───────────────────────────────────────────────────────────
 
@@ -20,14 +22,15 @@ pub(super) fn parse_level(s: &str) -> Option<Level> {
 
 // ─── Slot bounds validation ───────────────────────────────────────────────────
 
-/// Verify that all explicitly declared stream slot IDs are within
-/// `[0, STREAM_SLOT_COUNT)` and all I/O slot IDs are within `[0, IO_SLOT_COUNT)`.
+/// Verify that all explicitly declared stream and I/O slot IDs are below the
+/// slot counts the region will be formatted with (`cfg.stream_slot_count`,
+/// `cfg.io_slot_count`).
 ///
 /// Stream slots and I/O slots are now completely separate — no slot in either
 /// range is reserved for framework use, so the only constraint is that IDs
 /// stay in bounds.
-pub(super) fn validate_dag(dag: &Dag) -> Result<()> {
-    use common::{IO_SLOT_COUNT, STREAM_SLOT_COUNT};
+pub(super) fn validate_dag(dag: &Dag, cfg: &EngineConfig) -> Result<()> {
+    let (stream_slot_count, io_slot_count) = (cfg.stream_slot_count as usize, cfg.io_slot_count as usize);
     let mut errors: Vec<String> = Vec::new();
 
     for node in &dag.nodes {
@@ -61,8 +64,62 @@ pub(super) fn validate_dag(dag: &Dag) -> Result<()> {
             NodeKind::Persist(p) => {
                 stream_slots.extend_from_slice(&p.stream_slots);
             }
+            NodeKind::ResolveSharedState(p) => {
+                for kind in std::iter::once(&p.default).chain(p.rules.iter().map(|r| &r.policy)) {
+                    match kind {
+                        ConsumptionPolicyKind::SetUnion { delimiter, .. } if delimiter.len() != 1 => {
+                            errors.push(format!(
+                                "node '{}' (ResolveSharedState): SetUnion delimiter {:?} must be exactly one byte.",
+                                node.id, delimiter
+                            ));
+                        }
+                        ConsumptionPolicyKind::WasmMerge { slot, .. } => io_slots.push((*slot as usize, "WasmMerge")),
+                        _ => {}
+                    }
+                }
+            }
+            NodeKind::WasmVoid(_) | NodeKind::WasmU32(_) | NodeKind::WasmFatPtr(_) | NodeKind::PyFunc(_) => {
+                if let Some((inputs, outputs)) = cache_spec(&node.kind) {
+                    if outputs.is_empty() {
+                        errors.push(format!(
+                            "node '{}': `cache: true` requires at least one `outputs` slot to store.",
+                            node.id
+                        ));
+                    }
+                    for s in inputs.iter().chain(outputs) {
+                        match s.slot_kind {
+                            RemoteSlotKind::Stream => stream_slots.push(s.slot),
+                            RemoteSlotKind::Io    => io_slots.push((s.slot, "cache")),
+                        }
+                    }
+                }
+            }
             NodeKind::Input(p) => {
                 if let Some(s) = p.slot { io_slots.push((s as usize, "Input")); }
+                if p.files.is_some() && (p.chunk_bytes.is_some() || !p.path.is_empty() || !p.paths.is_empty()) {
+                    errors.push(format!(
+                        "node '{}' (Input): `files` cannot be combined with `path`, `paths` or `chunk_bytes`.",
+                        node.id
+                    ));
+                }
+                if p.zero_copy {
+                    // Overlays are per-process mappings: the NIC's MR, a
+                    // hugetlbfs mapping and python.wasm's file view can't see them.
+                    let conflict = if !p.binary {
+                        Some("requires `binary: true`")
+                    } else if dag.rdma.is_some() {
+                        Some("is not supported with dag.rdma")
+                    } else if cfg.huge_pages == HUGE_PAGES_HUGETLBFS {
+                        Some("is not supported with hugetlbfs huge pages")
+                    } else if dag.python_wasm.is_some() {
+                        Some("is not supported with python_wasm")
+                    } else {
+                        None
+                    };
+                    if let Some(why) = conflict {
+                        errors.push(format!("node '{}' (Input): `zero_copy` {}.", node.id, why));
+                    }
+                }
             }
             NodeKind::Output(p) => {
                 if let Some(s) = p.slot { io_slots.push((s as usize, "Output")); }
@@ -82,9 +139,9 @@ pub(super) fn validate_dag(dag: &Dag) -> Result<()> {
                         node.id
                     ));
                 }
-                if dag.rdma.as_ref().map_or(true, |r| !r.transfer) {
+                if dag.rdma.as_ref().is_none_or(|r| !r.transfer || r.transport != MeshTransport::Rdma) {
                     errors.push(format!(
-                        "node '{}' (RemoteSend): requires dag.rdma with transfer=true.",
+                        "node '{}' (RemoteSend): requires dag.rdma with transfer=true and transport \"rdma\".",
                         node.id
                     ));
                 }
@@ -94,9 +151,9 @@ pub(super) fn validate_dag(dag: &Dag) -> Result<()> {
                     RemoteSlotKind::Stream => stream_slots.push(p.slot),
                     RemoteSlotKind::Io    => io_slots.push((p.slot, "RemoteRecv")),
                 }
-                if dag.rdma.as_ref().map_or(true, |r| !r.transfer) {
+                if dag.rdma.as_ref().is_none_or(|r| !r.transfer || r.transport != MeshTransport::Rdma) {
                     errors.push(format!(
-                        "node '{}' (RemoteRecv): requires dag.rdma with transfer=true.",
+                        "node '{}' (RemoteRecv): requires dag.rdma with transfer=true and transport \"rdma\".",
                         node.id
                     ));
                 }
@@ -104,21 +161,112 @@ pub(super) fn validate_dag(dag: &Dag) -> Result<()> {
             _ => {}
         }
 
+        // Everything but guest remote atomics needs the RDMA mesh, which the
+        // `tcp` transport does not set up.
+        if dag.rdma.as_ref().is_some_and(|r| r.transport == MeshTransport::Tcp) {
+            let mesh_use = match &node.kind {
+                NodeKind::RemoteAtomicFetchAdd(_)
+                | NodeKind::RemoteAtomicCmpSwap(_)
+                | NodeKind::RemoteAtomicPush(_) => Some("this node kind"),
+                NodeKind::StreamPipeline(p) if p.rdma_recv.is_some() || p.rdma_send.is_some() => Some("rdma_recv / rdma_send"),
+                NodeKind::PyPipeline(p) if p.rdma_recv.is_some() || p.rdma_send.is_some() => Some("rdma_recv / rdma_send"),
+                NodeKind::StreamOutput(p) if p.rdma_recv.is_some() => Some("rdma_recv"),
+                _ => None,
+            };
+            if let Some(what) = mesh_use {
+                errors.push(format!(
+                    "node '{}': {} requires dag.rdma with transport \"rdma\".",
+                    node.id, what
+                ));
+            }
+        }
+
         for s in stream_slots {
-            if s >= STREAM_SLOT_COUNT {
+            if s >= stream_slot_count {
                 errors.push(format!(
-                    "node '{}': stream slot {} ≥ STREAM_SLOT_COUNT ({})",
-                    node.id, s, STREAM_SLOT_COUNT
+                    "node '{}': stream slot {} ≥ stream_slot_count ({})",
+                    node.id, s, stream_slot_count
                 ));
             }
         }
         for (s, label) in io_slots {
-            if s >= IO_SLOT_COUNT {
+            if s >= io_slot_count {
+                errors.push(format!(
+                    "node '{}': {} I/O slot {} ≥ io_slot_count ({})",
+                    node.id, label, s, io_slot_count
+                ));
+            }
+        }
+    }
+
+    for pc in &dag.page_classes {
+        let count = match pc.kind {
+            RemoteSlotKind::Stream => stream_slot_count,
+            RemoteSlotKind::Io     => io_slot_count,
+        };
+        if pc.slot as usize >= count {
+            errors.push(format!(
+                "page_classes: {:?} slot {} out of range (count {})",
+                pc.kind, pc.slot, count
+            ));
+        }
+        if common::page_class_from_name(&pc.class).is_none() {
+            errors.push(format!(
+                "page_classes: {:?} slot {}: unknown class \"{}\" (expected one of {})",
+                pc.kind, pc.slot, pc.class, common::PAGE_CLASS_NAMES.join(", ")
+            ));
+        }
+    }
+
+    let mut ring_slots = HashSet::new();
+    for r in &dag.rings {
+        if r.slot as usize >= stream_slot_count {
+            errors.push(format!("rings: stream slot {} out of range (count {})", r.slot, stream_slot_count));
+        }
+        if !ring_slots.insert(r.slot) {
+            errors.push(format!("rings: stream slot {} is listed twice", r.slot));
+        }
+        if !(2..=common::RING_MAX_PAGES as u32).contains(&r.pages) {
+            errors.push(format!(
+                "rings: stream slot {}: {} pages (expected 2..={})", r.slot, r.pages, common::RING_MAX_PAGES
+            ));
+        }
+        if !(1..=common::RING_MAX_CONSUMERS as u32).contains(&r.consumers) {
+            errors.push(format!(
+                "rings: stream slot {}: {} consumers (expected 1..={})",
+                r.slot, r.consumers, common::RING_MAX_CONSUMERS
+            ));
+        }
+    }
+
+    let mut subscribed_slots = HashSet::new();
+    for sub in &dag.subscriptions {
+        if sub.slot as usize >= stream_slot_count {
+            errors.push(format!("subscriptions: stream slot {} out of range (count {})", sub.slot, stream_slot_count));
+        }
+        if !subscribed_slots.insert(sub.slot) {
+            errors.push(format!("subscriptions: stream slot {} is listed twice", sub.slot));
+        }
+        if ring_slots.contains(&sub.slot) {
+            errors.push(format!("subscriptions: stream slot {} is a ring, which has its own consumers", sub.slot));
+        }
+        if !(1..=common::SUBSCRIBER_MAX).contains(&sub.subscribers.len()) {
+            errors.push(format!(
+                "subscriptions: stream slot {}: {} subscribers (expected 1..={})",
+                sub.slot, sub.subscribers.len(), common::SUBSCRIBER_MAX
+            ));
+        }
+        let mut names = HashSet::new();
+        for name in &sub.subscribers {
+            if name.is_empty() || name.len() > common::SUBSCRIBER_NAME_LEN || name.contains('\0') {
                 errors.push(format!(
-                    "node '{}': {} I/O slot {} ≥ IO_SLOT_COUNT ({})",
-                    node.id, label, s, IO_SLOT_COUNT
+                    "subscriptions: stream slot {}: subscriber name {:?} must be 1..={} bytes without NUL",
+                    sub.slot, name, common::SUBSCRIBER_NAME_LEN
                 ));
             }
+            if !names.insert(name) {
+                errors.push(format!("subscriptions: stream slot {}: subscriber '{}' is listed twice", sub.slot, name));
+            }
         }
     }
 
@@ -194,6 +342,82 @@ pub(super) fn build_slot_refcounts(dag: &Dag) -> HashMap<(SlotKind, usize), usiz
     counts
 }
 
+/// Every stream slot `node` names in its params, or `None` when the node can
+/// touch slots it does not name (`FileDispatch`, `OwnedDispatch`).
+///
+/// Guest arguments (`arg`, `arg0`, …) are listed as slots even when a guest
+/// uses them as plain numbers: the spill tier only needs an over-approximation
+/// of which slots a wave may read.
+pub(super) fn node_stream_refs(kind: &NodeKind) -> Option<Vec<usize>> {
+    let stream = |slot: usize, k: RemoteSlotKind| (k == RemoteSlotKind::Stream).then_some(slot);
+    let cache_slots = |slots: &[CacheSlot]| -> Vec<usize> {
+        slots.iter().filter_map(|s| stream(s.slot, s.slot_kind)).collect()
+    };
+    let rdma = |recv: &Option<RdmaPipelineRecv>, send: &Option<RdmaPipelineSend>| -> Vec<usize> {
+        recv.iter().filter_map(|r| stream(r.slot, r.slot_kind))
+            .chain(send.iter().filter_map(|s| stream(s.slot, s.slot_kind)))
+            .collect()
+    };
+    let refs = match kind {
+        NodeKind::WasmVoid(c) | NodeKind::WasmU32(c) | NodeKind::WasmFatPtr(c) => {
+            let mut v = vec![c.arg as usize];
+            v.extend(cache_slots(&c.inputs));
+            v.extend(cache_slots(&c.outputs));
+            v
+        }
+        NodeKind::PyFunc(p) => {
+            let mut v: Vec<usize> = std::iter::once(p.arg).chain(p.arg2).map(|a| a as usize).collect();
+            v.extend(cache_slots(&p.inputs));
+            v.extend(cache_slots(&p.outputs));
+            v
+        }
+        NodeKind::Bridge(p)    => vec![p.from, p.to],
+        NodeKind::Aggregate(p) => p.upstream.iter().copied().chain([p.downstream]).collect(),
+        NodeKind::Shuffle(p)   => p.upstream.iter().chain(&p.downstream).copied().collect(),
+        NodeKind::Persist(p)   => p.stream_slots.clone(),
+        NodeKind::Watch(p)     => p.stream.into_iter().collect(),
+        NodeKind::FreeSlots(p) => p.stream.clone(),
+        NodeKind::StreamPipeline(p) => {
+            let mut v: Vec<usize> = p.stages.iter()
+                .flat_map(|s| std::iter::once(s.arg0).chain(s.arg1))
+                .map(|a| a as usize)
+                .collect();
+            v.extend(rdma(&p.rdma_recv, &p.rdma_send));
+            v
+        }
+        NodeKind::PyPipeline(p) => {
+            let mut v: Vec<usize> = p.stages.iter()
+                .flat_map(|s| std::iter::once(s.arg).chain(s.arg2))
+                .map(|a| a as usize)
+                .collect();
+            v.extend(rdma(&p.rdma_recv, &p.rdma_send));
+            v
+        }
+        NodeKind::WasmGrouping(p) => p.stages.iter()
+            .flat_map(|s| [s.arg0 as usize, s.arg1 as usize])
+            .collect(),
+        NodeKind::PyGrouping(p) => p.stages.iter()
+            .flat_map(|s| std::iter::once(s.arg).chain(s.arg2))
+            .map(|a| a as usize)
+            .collect(),
+        NodeKind::StreamOutput(p) => {
+            let mut v: Vec<usize> = stream(p.slot, p.slot_kind).into_iter().collect();
+            v.extend(rdma(&p.rdma_recv, &None));
+            v
+        }
+        NodeKind::RemoteSend(p) => stream(p.slot, p.slot_kind).into_iter().collect(),
+        NodeKind::RemoteRecv(p) => stream(p.slot, p.slot_kind).into_iter().collect(),
+        NodeKind::Input(_)
+        | NodeKind::Output(_)
+        | NodeKind::ResolveSharedState(_)
+        | NodeKind::RemoteAtomicFetchAdd(_)
+        | NodeKind::RemoteAtomicCmpSwap(_)
+        | NodeKind::RemoteAtomicPush(_) => vec![],
+        NodeKind::FileDispatch(_) | NodeKind::OwnedDispatch(_) => return None,
+    };
+    Some(refs)
+}
+
 // ─── Topological sort (Kahn's algorithm) ─────────────────────────────────────
 
 pub(super) fn topo_sort(nodes: &[DagNode]) -> Result<Vec<usize>> {
@@ -310,6 +534,58 @@ pub(super) fn validate_barrier_groups(nodes: &[DagNode], waves: &[Vec<usize>]) -
     }
 }
 
+/// Reject a `cache: true` node whose `outputs` slot another node of the same
+/// wave may write.  The runner counts a cached node's output records before
+/// the wave and reads the slot back after it, so a concurrent writer's records
+/// would be stored under the node's key and appended again on every hit.
+pub(super) fn validate_cache_outputs(nodes: &[DagNode], waves: &[Vec<usize>]) -> Result<()> {
+    let mut errors: Vec<String> = Vec::new();
+    for wave in waves {
+        for &idx in wave {
+            let Some((_, outputs)) = cache_spec(&nodes[idx].kind) else { continue };
+            for out in outputs {
+                for &other in wave.iter().filter(|&&o| o != idx) {
+                    if may_write_slot(&nodes[other].kind, *out) {
+                        errors.push(format!(
+                            "node '{}': cached output {:?} slot {} may also be written by '{}'                              in the same wave; add a dep between them or drop `cache`",
+                            nodes[idx].id, out.slot_kind, out.slot, nodes[other].id
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Result cache validation failed:\n  {}", errors.join("\n  ")))
+    }
+}
+
+/// Over-approximation of whether `kind` may append to `slot`: it names the
+/// slot (guest arguments included) other than to read it, or it can touch
+/// slots it does not name.
+fn may_write_slot(kind: &NodeKind, slot: CacheSlot) -> bool {
+    use common::INPUT_IO_SLOT;
+    if slot.slot_kind == RemoteSlotKind::Stream {
+        // Persist and Watch only read the slots they name.
+        return !matches!(kind, NodeKind::Persist(_) | NodeKind::Watch(_))
+            && node_stream_refs(kind).is_none_or(|refs| refs.contains(&slot.slot));
+    }
+    let declared = |outputs: &[CacheSlot]| {
+        outputs.iter().any(|o| o.slot_kind == RemoteSlotKind::Io && o.slot == slot.slot)
+    };
+    match kind {
+        NodeKind::WasmVoid(c) | NodeKind::WasmU32(c) | NodeKind::WasmFatPtr(c) =>
+            c.arg as usize == slot.slot || declared(&c.outputs),
+        NodeKind::PyFunc(p) =>
+            std::iter::once(p.arg).chain(p.arg2).any(|a| a as usize == slot.slot) || declared(&p.outputs),
+        NodeKind::Input(p) => p.slot.unwrap_or(INPUT_IO_SLOT) as usize == slot.slot,
+        NodeKind::FileDispatch(_) | NodeKind::OwnedDispatch(_) => true,
+        _ => false,
+    }
+}
+
 /// Compute per-wave barrier assignments: for each wave that contains nodes with
 /// `barrier_group`, assign sequential barrier slot IDs (0, 1, ...) and return
 /// the set of barrier IDs that need resetting before each wave.
@@ -318,6 +594,7 @@ pub(super) fn validate_barrier_groups(nodes: &[DagNode], waves: &[Vec<usize>]) -
 pub(super) fn build_barrier_assignments(
     nodes: &[DagNode],
     waves: &[Vec<usize>],
+    barrier_count: usize,
 ) -> (Vec<Vec<usize>>, HashMap<String, (usize, usize)>) {
     let mut global_id: usize = 0;
     let mut group_map: HashMap<String, (usize, usize)> = HashMap::new(); // name → (barrier_id, party_count)
@@ -335,8 +612,8 @@ pub(super) fn build_barrier_assignments(
 
         for (name, count) in wave_groups {
             if !group_map.contains_key(name) {
-                assert!(global_id < common::BARRIER_COUNT,
-                    "Too many barrier groups (max {})", common::BARRIER_COUNT);
+                assert!(global_id < barrier_count,
+                    "Too many barrier groups (max {}; raise barrier_count)", barrier_count);
                 group_map.insert(name.to_string(), (global_id, count));
                 wave_barriers[wi].push(global_id);
                 global_id += 1;
@@ -357,3 +634,38 @@ pub(super) fn is_oneshot_node(kind: &NodeKind) -> bool {
         | NodeKind::PyFunc(_)
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn waves_of(nodes: serde_json::Value) -> (Dag, Vec<Vec<usize>>) {
+        let dag: Dag = serde_json::from_value(json!({ "shm_path": "/dev/shm/x", "nodes": nodes })).unwrap();
+        let order = topo_sort(&dag.nodes).unwrap();
+        let waves = build_waves(&dag.nodes, &order);
+        (dag, waves)
+    }
+
+    #[test]
+    fn cached_output_with_a_same_wave_writer_is_rejected() {
+        let cached = json!({ "id": "a", "kind": { "WasmVoid": { "func": "map", "arg": 10, "cache": true,
+            "outputs": [{ "slot": 110, "slot_kind": "Stream" }, { "slot": 7, "slot_kind": "Io" }] } } });
+        let (dag, waves) = waves_of(json!([
+            cached,
+            { "id": "b", "kind": { "WasmVoid": { "func": "map", "arg": 110 } } },
+            { "id": "c", "kind": { "PyFunc": { "func": "emit", "arg": 3, "arg2": 7 } } }
+        ]));
+        let err = validate_cache_outputs(&dag.nodes, &waves).unwrap_err().to_string();
+        assert!(err.contains("Stream slot 110 may also be written by 'b'"), "{}", err);
+        assert!(err.contains("Io slot 7 may also be written by 'c'"), "{}", err);
+
+        // Ordered after the cached node, the same writers are fine.
+        let (dag, waves) = waves_of(json!([
+            cached,
+            { "id": "b", "deps": ["a"], "kind": { "WasmVoid": { "func": "map", "arg": 110 } } },
+            { "id": "c", "deps": ["a"], "kind": { "PyFunc": { "func": "emit", "arg": 3, "arg2": 7 } } }
+        ]));
+        validate_cache_outputs(&dag.nodes, &waves).unwrap();
+    }
+}
@@ -0,0 +1,514 @@
+//! Content-addressed result cache for `cache: true` WASM / Python nodes.
+//!
+//! Re-running a DAG after changing only a downstream step should not recompute
+//! every upstream task.  A cached node's key is the SHA-256 of
+//!
+//!   - the module: the guest `.wasm` bytes, or for `PyFunc` every `*.py` file
+//!     beside `python_script` (the runner and its workload modules),
+//!   - the call: node kind, `func` and arguments,
+//!   - the input: every record (origin + payload) of each declared `inputs` slot.
+//!
+//! On a **miss** the node runs normally; the runner remembers how many records
+//! each `outputs` slot held beforehand and, once the node has exited, stores
+//! only the records it appended.  On a **hit** the guest is not started — those
+//! records are appended to the output slots again, so the slot ends up exactly
+//! as if the node had run, including slots that earlier waves or runs already
+//! filled.  The count is taken before the wave and the slot read back after
+//! it, so no other node of the same wave may write a cached node's output
+//! slot; `validate_cache_outputs` rejects such DAGs.  Records are read with the
+//! same page-chain readers `Persist` uses.
+//!
+//! Entries live in `<cache_dir>/<key>.rec`, written to a temp file and renamed
+//! into place, and are touched on every hit so `host cache gc` can evict
+//! least-recently-used entries first.
+//!
+//! Entry layout (all integers little-endian):
+//! ```text
+//! "DAGCACHE" u32 version u32 n_slots
+//!   n_slots × { u8 kind (0 = Stream, 1 = Io) u32 slot u32 n_records
+//!               n_records × { u32 origin u32 len [len bytes] } }
+//! ```
+
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use common::Superblock;
+
+use super::stage_fanout::append_stream_record;
+use super::types::{CacheSlot, DagNode, NodeKind, RemoteSlotKind};
+use crate::runtime::input_output::persistence::{
+    count_io_records, count_stream_records, read_io_records, read_stream_records,
+};
+use crate::runtime::input_output::slot_loader::SlotLoader;
+
+/// Cache directory used when the DAG does not set `cache_dir`.
+pub const DEFAULT_CACHE_DIR: &str = ".dag_cache";
+/// Size bound applied by `host cache gc` when `--max-bytes` is omitted.
+pub const DEFAULT_GC_MAX_BYTES: u64 = 1 << 30;
+
+const MAGIC: &[u8; 8] = b"DAGCACHE";
+const VERSION: u32 = 1;
+const ENTRY_EXT: &str = "rec";
+
+type SlotRecords = Vec<(CacheSlot, Vec<(u32, Vec<u8>)>)>;
+
+/// Outcome of [`ResultCache::lookup`] for a `cache: true` node.
+pub(super) enum Lookup {
+    /// Outputs restored; the node must not run.
+    Hit,
+    /// Run the node, then hand this to [`ResultCache::store`].
+    Miss(PendingStore),
+}
+
+/// A miss waiting for its node to finish.
+pub(super) struct PendingStore {
+    key: String,
+    /// Output slots with their record count before the node ran.
+    outputs: Vec<(CacheSlot, usize)>,
+}
+
+pub(super) struct ResultCache {
+    dir: PathBuf,
+    wasm_path: String,
+    python_script: String,
+    /// Module digests, computed once per run.
+    modules: HashMap<&'static str, [u8; 32]>,
+}
+
+impl ResultCache {
+    pub(super) fn new(dir: &str, wasm_path: &str, python_script: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+            wasm_path: wasm_path.to_string(),
+            python_script: python_script.to_string(),
+            modules: HashMap::new(),
+        }
+    }
+
+    /// Key `node` and try to restore its outputs.  Returns `None` for nodes
+    /// without `cache: true`.
+    pub(super) fn lookup(&mut self, node: &DagNode, splice_addr: usize) -> Result<Option<Lookup>> {
+        let Some((inputs, outputs)) = cache_spec(&node.kind) else { return Ok(None) };
+        let key = self.key(node, inputs, splice_addr)?;
+        let path = self.entry_path(&key);
+
+        if path.exists() {
+            match read_entry(&path) {
+                Ok(slots) => {
+                    let mut records = 0usize;
+                    for (slot, recs) in &slots {
+                        append_slot(splice_addr, *slot, recs)?;
+                        records += recs.len();
+                    }
+                    // Touch for LRU eviction; a failure only weakens `gc` ordering.
+                    let _ = fs::File::options().write(true).open(&path)
+                        .and_then(|f| f.set_modified(SystemTime::now()));
+                    println!(
+                        "[Cache] '{}' hit {} → restored {} slot(s), {} records",
+                        node.id, short(&key), slots.len(), records
+                    );
+                    return Ok(Some(Lookup::Hit));
+                }
+                Err(e) => {
+                    eprintln!("[Cache] '{}' ignoring unreadable entry {}: {}", node.id, path.display(), e);
+                }
+            }
+        }
+
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        let outputs = outputs.iter().map(|&s| (s, count_slot(splice_addr, sb, s))).collect();
+        println!("[Cache] '{}' miss {}", node.id, short(&key));
+        Ok(Some(Lookup::Miss(PendingStore { key, outputs })))
+    }
+
+    /// Persist the records `node_id` appended to its output slots.
+    pub(super) fn store(&self, node_id: &str, pending: PendingStore, splice_addr: usize) -> Result<()> {
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        let slots: SlotRecords = pending.outputs.iter()
+            .map(|&(slot, before)| {
+                let all = read_slot(splice_addr, sb, slot);
+                // A slot that shrank was reset by the node: keep everything.
+                let skip = if all.len() >= before { before } else { 0 };
+                (slot, all.into_iter().skip(skip).collect())
+            })
+            .collect();
+
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| anyhow!("cannot create cache dir '{}': {}", self.dir.display(), e))?;
+        let path = self.entry_path(&pending.key);
+        let tmp = self.dir.join(format!(".{}.tmp-{}", pending.key, std::process::id()));
+        let written = write_entry(&tmp, &slots).and_then(|n| {
+            fs::rename(&tmp, &path)?;
+            Ok(n)
+        });
+        let bytes = match written {
+            Ok(n) => n,
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                return Err(e);
+            }
+        };
+        let records: usize = slots.iter().map(|(_, r)| r.len()).sum();
+        println!(
+            "[Cache] '{}' stored {} ({} slot(s), {} records, {} bytes)",
+            node_id, short(&pending.key), slots.len(), records, bytes
+        );
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, ENTRY_EXT))
+    }
+
+    fn key(&mut self, node: &DagNode, inputs: &[CacheSlot], splice_addr: usize) -> Result<String> {
+        let mut h = Sha256::new();
+        h.update(MAGIC);
+        h.update(VERSION.to_le_bytes());
+        match &node.kind {
+            NodeKind::WasmVoid(c) | NodeKind::WasmU32(c) | NodeKind::WasmFatPtr(c) => {
+                let label = match &node.kind {
+                    NodeKind::WasmVoid(_) => "void",
+                    NodeKind::WasmU32(_) => "u32",
+                    _ => "fatptr",
+                };
+                h.update(self.module_digest("wasm")?);
+                hash_str(&mut h, label);
+                hash_str(&mut h, &c.func);
+                h.update(c.arg.to_le_bytes());
+            }
+            NodeKind::PyFunc(c) => {
+                h.update(self.module_digest("python")?);
+                hash_str(&mut h, "py");
+                hash_str(&mut h, &c.func);
+                h.update(c.arg.to_le_bytes());
+                match c.arg2 {
+                    Some(a) => { h.update([1]); h.update(a.to_le_bytes()); }
+                    None => h.update([0]),
+                }
+            }
+            _ => return Err(anyhow!("[{}] node kind cannot be cached", node.id)),
+        }
+
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        h.update((inputs.len() as u32).to_le_bytes());
+        for &slot in inputs {
+            h.update([kind_tag(slot.slot_kind)]);
+            h.update((slot.slot as u32).to_le_bytes());
+            let records = read_slot(splice_addr, sb, slot);
+            h.update((records.len() as u64).to_le_bytes());
+            for (origin, payload) in &records {
+                h.update(origin.to_le_bytes());
+                h.update((payload.len() as u32).to_le_bytes());
+                h.update(payload);
+            }
+        }
+        Ok(h.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    fn module_digest(&mut self, which: &'static str) -> Result<[u8; 32]> {
+        if let Some(d) = self.modules.get(which) {
+            return Ok(*d);
+        }
+        let mut h = Sha256::new();
+        if which == "wasm" {
+            let bytes = fs::read(&self.wasm_path)
+                .map_err(|e| anyhow!("cache: cannot read module '{}': {}", self.wasm_path, e))?;
+            h.update(&bytes);
+        } else {
+            if self.python_script.is_empty() {
+                return Err(anyhow!("cache: PyFunc requires 'python_script' to be set"));
+            }
+            let dir = Path::new(&self.python_script).parent()
+                .filter(|d| !d.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let mut sources: Vec<PathBuf> = fs::read_dir(dir)
+                .map_err(|e| anyhow!("cache: cannot list '{}': {}", dir.display(), e))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|x| x == "py"))
+                .collect();
+            sources.sort();
+            for src in sources {
+                hash_str(&mut h, &src.file_name().unwrap_or_default().to_string_lossy());
+                let bytes = fs::read(&src)?;
+                h.update((bytes.len() as u64).to_le_bytes());
+                h.update(&bytes);
+            }
+        }
+        let digest: [u8; 32] = h.finalize().into();
+        self.modules.insert(which, digest);
+        Ok(digest)
+    }
+}
+
+/// `(inputs, outputs)` of a node with `cache: true`.
+pub(super) fn cache_spec(kind: &NodeKind) -> Option<(&[CacheSlot], &[CacheSlot])> {
+    match kind {
+        NodeKind::WasmVoid(c) | NodeKind::WasmU32(c) | NodeKind::WasmFatPtr(c) if c.cache =>
+            Some((&c.inputs, &c.outputs)),
+        NodeKind::PyFunc(c) if c.cache => Some((&c.inputs, &c.outputs)),
+        _ => None,
+    }
+}
+
+fn hash_str(h: &mut Sha256, s: &str) {
+    h.update((s.len() as u32).to_le_bytes());
+    h.update(s.as_bytes());
+}
+
+fn short(key: &str) -> &str {
+    &key[..key.len().min(12)]
+}
+
+fn kind_tag(kind: RemoteSlotKind) -> u8 {
+    match kind {
+        RemoteSlotKind::Stream => 0,
+        RemoteSlotKind::Io => 1,
+    }
+}
+
+fn read_slot(splice_addr: usize, sb: &Superblock, slot: CacheSlot) -> Vec<(u32, Vec<u8>)> {
+    match slot.slot_kind {
+        RemoteSlotKind::Stream => read_stream_records(splice_addr, sb, slot.slot),
+        RemoteSlotKind::Io => read_io_records(splice_addr, sb, slot.slot),
+    }
+}
+
+fn count_slot(splice_addr: usize, sb: &Superblock, slot: CacheSlot) -> usize {
+    match slot.slot_kind {
+        RemoteSlotKind::Stream => count_stream_records(splice_addr, sb, slot.slot),
+        RemoteSlotKind::Io => count_io_records(splice_addr, sb, slot.slot),
+    }
+}
+
+fn append_slot(splice_addr: usize, slot: CacheSlot, records: &[(u32, Vec<u8>)]) -> Result<()> {
+    match slot.slot_kind {
+        RemoteSlotKind::Stream => {
+            for (origin, payload) in records {
+                append_stream_record(splice_addr, slot.slot, *origin, payload)?;
+            }
+        }
+        RemoteSlotKind::Io => {
+            SlotLoader::new(splice_addr).load_records(slot.slot as u32, records)?;
+        }
+    }
+    Ok(())
+}
+
+// ─── Entry encoding ───────────────────────────────────────────────────────────
+
+/// Write an entry to `path`, fsynced; returns its size in bytes.
+fn write_entry(path: &Path, slots: &SlotRecords) -> Result<u64> {
+    let mut w = BufWriter::new(fs::File::create(path)?);
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    w.write_all(&(slots.len() as u32).to_le_bytes())?;
+    let mut bytes = 16u64;
+    for (slot, records) in slots {
+        w.write_all(&[kind_tag(slot.slot_kind)])?;
+        w.write_all(&(slot.slot as u32).to_le_bytes())?;
+        w.write_all(&(records.len() as u32).to_le_bytes())?;
+        bytes += 9;
+        for (origin, payload) in records {
+            w.write_all(&origin.to_le_bytes())?;
+            w.write_all(&(payload.len() as u32).to_le_bytes())?;
+            w.write_all(payload)?;
+            bytes += 8 + payload.len() as u64;
+        }
+    }
+    let file = w.into_inner().map_err(|e| anyhow!("cache write: {}", e.error()))?;
+    file.sync_all()?;
+    Ok(bytes)
+}
+
+fn read_entry(path: &Path) -> Result<SlotRecords> {
+    let mut r = BufReader::new(fs::File::open(path)?);
+    let mut magic = [0u8; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(anyhow!("bad magic"));
+    }
+    let u32_at = |r: &mut BufReader<fs::File>| -> Result<u32> {
+        let mut b = [0u8; 4];
+        r.read_exact(&mut b)?;
+        Ok(u32::from_le_bytes(b))
+    };
+    let version = u32_at(&mut r)?;
+    if version != VERSION {
+        return Err(anyhow!("unsupported version {}", version));
+    }
+    let n_slots = u32_at(&mut r)?;
+    let mut slots = Vec::with_capacity(n_slots as usize);
+    for _ in 0..n_slots {
+        let mut kind = [0u8; 1];
+        r.read_exact(&mut kind)?;
+        let slot_kind = match kind[0] {
+            0 => RemoteSlotKind::Stream,
+            1 => RemoteSlotKind::Io,
+            k => return Err(anyhow!("bad slot kind {}", k)),
+        };
+        let slot = u32_at(&mut r)? as usize;
+        let n = u32_at(&mut r)?;
+        let mut records = Vec::with_capacity(n.min(1 << 16) as usize);
+        for _ in 0..n {
+            let origin = u32_at(&mut r)?;
+            let len = u32_at(&mut r)? as usize;
+            let mut payload = vec![0u8; len];
+            r.read_exact(&mut payload)?;
+            records.push((origin, payload));
+        }
+        slots.push((CacheSlot { slot, slot_kind }, records));
+    }
+    Ok(slots)
+}
+
+// ─── host cache gc ────────────────────────────────────────────────────────────
+
+/// One on-disk cache file considered by [`run_cache_gc`].
+#[derive(Debug, Clone)]
+struct GcEntry {
+    path: PathBuf,
+    len: u64,
+    used: SystemTime,
+}
+
+/// `host cache gc`: delete entries older than `max_age` (by last use), then
+/// least-recently-used entries until the cache is at most `max_bytes`.
+/// Stale temp files from interrupted stores are always removed.
+pub fn run_cache_gc(dir: &str, max_bytes: u64, max_age: Option<Duration>) -> Result<()> {
+    let dir = Path::new(dir);
+    if !dir.is_dir() {
+        println!("[Cache] gc: '{}' does not exist — nothing to do", dir.display());
+        return Ok(());
+    }
+    let mut entries = Vec::new();
+    let mut stale_tmp = 0usize;
+    for e in fs::read_dir(dir)? {
+        let e = e?;
+        let path = e.path();
+        let name = e.file_name().to_string_lossy().into_owned();
+        let meta = e.metadata()?;
+        if !meta.is_file() {
+            continue;
+        }
+        if name.starts_with('.') && name.contains(".tmp-") {
+            if fs::remove_file(&path).is_ok() { stale_tmp += 1; }
+            continue;
+        }
+        if path.extension().is_some_and(|x| x == ENTRY_EXT) {
+            let used = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push(GcEntry { path, len: meta.len(), used });
+        }
+    }
+
+    let total: u64 = entries.iter().map(|e| e.len).sum();
+    let evict = select_evictions(entries, max_bytes, max_age, SystemTime::now());
+    let (mut removed, mut freed) = (0usize, 0u64);
+    for e in &evict {
+        match fs::remove_file(&e.path) {
+            Ok(()) => { removed += 1; freed += e.len; }
+            Err(err) => eprintln!("[Cache] gc: cannot remove {}: {}", e.path.display(), err),
+        }
+    }
+    println!(
+        "[Cache] gc '{}': removed {} entr{} ({} bytes){}, {} bytes remain (limit {})",
+        dir.display(), removed, if removed == 1 { "y" } else { "ies" }, freed,
+        if stale_tmp > 0 { format!(" + {} stale temp file(s)", stale_tmp) } else { String::new() },
+        total - freed, max_bytes,
+    );
+    Ok(())
+}
+
+/// Entries to delete: everything unused for longer than `max_age`, plus every
+/// entry used less recently than the point where the kept set would exceed
+/// `max_bytes` (strict LRU order).
+fn select_evictions(
+    mut entries: Vec<GcEntry>,
+    max_bytes: u64,
+    max_age: Option<Duration>,
+    now: SystemTime,
+) -> Vec<GcEntry> {
+    // Most recently used first; evict from the back.
+    entries.sort_by(|a, b| b.used.cmp(&a.used).then_with(|| a.path.cmp(&b.path)));
+    let mut keep_bytes = 0u64;
+    let mut full = false;
+    let mut evict = Vec::new();
+    for e in entries {
+        let expired = max_age.is_some_and(|age| now.duration_since(e.used).unwrap_or_default() > age);
+        full |= keep_bytes + e.len > max_bytes;
+        if expired || full {
+            evict.push(e);
+        } else {
+            keep_bytes += e.len;
+        }
+    }
+    evict
+}
+
+/// Parse a byte size with an optional `K`/`M`/`G` suffix (powers of 1024).
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (num, mul) = match s.chars().last()?.to_ascii_uppercase() {
+        'K' => (&s[..s.len() - 1], 1u64 << 10),
+        'M' => (&s[..s.len() - 1], 1 << 20),
+        'G' => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+    num.parse::<u64>().ok()?.checked_mul(mul)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("e.rec");
+        let slots: SlotRecords = vec![
+            (CacheSlot { slot: 110, slot_kind: RemoteSlotKind::Stream }, vec![(3, b"a 1".to_vec()), (3, Vec::new())]),
+            (CacheSlot { slot: 1, slot_kind: RemoteSlotKind::Io }, vec![]),
+        ];
+        let n = write_entry(&path, &slots).unwrap();
+        assert_eq!(n, fs::metadata(&path).unwrap().len());
+        let back = read_entry(&path).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!((back[0].0.slot, back[0].0.slot_kind), (110, RemoteSlotKind::Stream));
+        assert_eq!(back[0].1, slots[0].1);
+        assert_eq!(back[1].0.slot_kind, RemoteSlotKind::Io);
+
+        fs::write(&path, b"DAGCACHE\x09\0\0\0").unwrap();
+        assert!(read_entry(&path).is_err());
+    }
+
+    #[test]
+    fn gc_evicts_expired_then_least_recent() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let e = |name: &str, len: u64, age: u64| GcEntry {
+            path: PathBuf::from(name), len, used: now - Duration::from_secs(age),
+        };
+        let entries = vec![e("old", 10, 900), e("new", 60, 10), e("mid", 50, 100), e("ancient", 1, 99_999)];
+        let names = |v: Vec<GcEntry>| -> Vec<String> {
+            v.into_iter().map(|e| e.path.to_string_lossy().into_owned()).collect()
+        };
+
+        assert_eq!(names(select_evictions(entries.clone(), 1000, None, now)), Vec::<String>::new());
+        assert_eq!(names(select_evictions(entries.clone(), 100, None, now)), ["mid", "old", "ancient"]);
+        assert_eq!(
+            names(select_evictions(entries, 1000, Some(Duration::from_secs(500)), now)),
+            ["old", "ancient"]
+        );
+    }
+
+    #[test]
+    fn sizes_parse() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("512M"), Some(512 << 20));
+        assert_eq!(parse_size("2g"), Some(2 << 30));
+        assert_eq!(parse_size("x"), None);
+    }
+}
@@ -0,0 +1,569 @@
+//! Symbolic slot names for DAG JSON.
+//!
+//! Hand-written DAGs used to hard-code every stream/I/O slot index, and the
+//! producer/consumer pairs drifted apart whenever one side was renumbered.
+//! Any slot field may instead hold a string name:
+//!
+//! ```json
+//! { "slots": { "merged": { "kind": "Stream", "lifetime": "persist", "page_class": "64k" } },
+//!   "nodes": [
+//!     { "id": "agg", "kind": { "Aggregate": { "upstream": ["m0", "m1"], "downstream": "merged" } } },
+//!     { "id": "red", "kind": { "WasmVoid":  { "func": "wc_reduce", "arg": "merged" } } }
+//!   ] }
+//! ```
+//!
+//! [`resolve_slot_names`] runs on the raw JSON before it is deserialised into
+//! a [`Dag`](super::Dag): it picks a free index for every name, rewrites each
+//! reference in place, and returns the bindings so the runner can print them.
+//! Guests therefore still receive plain `u32` slot numbers.  Persistent names
+//! are appended to `persist_slots`, page classes to `page_classes`, rings to
+//! `rings` and subscribers to `subscriptions`, the numeric forms of the same
+//! settings.
+//!
+//! Free indices are found with the walker the partitioner uses,
+//! `common::collect_dag_slots`: every non-negative integer already present in
+//! the nodes is treated as taken, and names are allocated upward from the
+//! highest one.  Slot ranges
+//! that a guest derives arithmetically (e.g. `wc_map` writing `slot + 100`)
+//! are invisible here, so they stay above any explicitly numbered slot rather
+//! than in a gap below it.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use common::{collect_dag_slots, page_class_from_name, EngineConfig, PAGE_CLASS_NAMES, PAGE_CLASS_SMALL};
+use super::types::{RemoteSlotKind, RingDecl, SlotDecl, SlotLifetime};
+
+/// First index handed out in either area.  Matches the partitioner's
+/// `slot_assigner`, which keeps 0/1 clear for `INPUT_IO_SLOT`/`OUTPUT_IO_SLOT`.
+const FIRST_NAMED_SLOT: u32 = 2;
+
+/// One resolved name → index binding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct SlotBinding {
+    pub name: String,
+    pub kind: RemoteSlotKind,
+    pub index: u32,
+    pub lifetime: SlotLifetime,
+    /// Page class (`common::PAGE_CLASS_*`) the slot allocates in.
+    pub page_class: u32,
+    /// Set for a stream slot declared as a ring.
+    pub ring: Option<RingDecl>,
+    /// Subscriber names of a stream slot; empty unless declared.
+    pub subscribers: Vec<String>,
+}
+
+/// Which area a field position implies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Area {
+    Stream,
+    Io,
+    /// A guest function argument (`arg`, `arg0`, …): the host cannot tell
+    /// which area the guest indexes, so any declared kind is accepted and
+    /// undeclared names default to `Stream`.
+    Either,
+}
+
+/// Fields that always hold stream slot ids.
+const STREAM_KEYS: &[&str] = &["from", "to", "upstream", "downstream", "stream", "stream_slots"];
+/// Fields that hold guest function arguments (usually, but not always, slots).
+const ARG_KEYS: &[&str] = &["arg", "arg0", "arg1", "arg2"];
+
+/// Area of the slot reference stored under `key`, or `None` when `key` is not
+/// a slot field.  `slot` is an I/O slot on `Input`/`Output`; the RDMA and
+/// `StreamOutput` variants carry an explicit `slot_kind` next to it.
+fn key_area(key: &str, slot_kind: Option<&str>) -> Option<Area> {
+    match key {
+        "slot" => Some(match slot_kind {
+            Some("Stream") => Area::Stream,
+            _ => Area::Io,
+        }),
+        "io" => Some(Area::Io),
+        k if STREAM_KEYS.contains(&k) => Some(Area::Stream),
+        k if ARG_KEYS.contains(&k) => Some(Area::Either),
+        _ => None,
+    }
+}
+
+/// Call `f` on every slot-reference leaf under `v` (scalars and array items
+/// stored at slot fields).  `wasm_arg` (packed guest parameter) and `policy`
+/// (`FixedMap` pairs are upstream ids, not slots) are skipped.
+fn visit_refs(v: &mut Value, f: &mut dyn FnMut(Area, &mut Value) -> Result<()>) -> Result<()> {
+    match v {
+        Value::Array(arr) => {
+            for item in arr {
+                visit_refs(item, f)?;
+            }
+        }
+        Value::Object(map) => {
+            let slot_kind = map.get("slot_kind").and_then(Value::as_str).map(str::to_owned);
+            for (k, val) in map.iter_mut() {
+                if k == "wasm_arg" || k == "policy" {
+                    continue;
+                }
+                match key_area(k, slot_kind.as_deref()) {
+                    Some(area) => match val {
+                        Value::Array(items) => {
+                            for item in items {
+                                f(area, item)?;
+                            }
+                        }
+                        other => f(area, other)?,
+                    },
+                    None => visit_refs(val, f)?,
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn kind_label(kind: RemoteSlotKind) -> &'static str {
+    match kind {
+        RemoteSlotKind::Stream => "stream",
+        RemoteSlotKind::Io => "I/O",
+    }
+}
+
+/// The JSON spelling of `kind` (`RemoteSlotKind` is `PascalCase`).
+fn kind_name(kind: RemoteSlotKind) -> &'static str {
+    match kind {
+        RemoteSlotKind::Stream => "Stream",
+        RemoteSlotKind::Io => "Io",
+    }
+}
+
+/// Resolve every symbolic slot name in the raw DAG JSON `dag` to an index.
+///
+/// Rewrites the references in `nodes[*].kind` and `persist_slots` in place and
+/// appends names declared with `"lifetime": "persist"` to `persist_slots` and
+/// names declared with a `page_class` other than `4k` to `page_classes`,
+/// names declared with a `ring` to `rings` and names declared with
+/// `subscribers` to `subscriptions`.
+/// Returns the bindings in first-use order (empty when the DAG uses no names).
+///
+/// Errors — all collected before returning, like `validate_dag`:
+/// - a name used in both the stream and the I/O area,
+/// - a declared name used in a field of the other area,
+/// - `persist` lifetime, a `ring` or `subscribers` on an `Io` slot,
+/// - an unknown `page_class`,
+/// - running out of free indices in an area (the slot counts in `cfg`).
+pub(super) fn resolve_slot_names(dag: &mut Value, cfg: &EngineConfig) -> Result<Vec<SlotBinding>> {
+    let (stream_slot_count, io_slot_count) = (cfg.stream_slot_count as usize, cfg.io_slot_count as usize);
+    let decls: BTreeMap<String, SlotDecl> = match dag.get("slots") {
+        Some(v) => serde_json::from_value(v.clone())
+            .map_err(|e| anyhow!("Invalid DAG `slots` declaration: {}", e))?,
+        None => BTreeMap::new(),
+    };
+
+    let mut errors: Vec<String> = Vec::new();
+    let mut order: Vec<String> = Vec::new();
+    // name → (area, "node 'id'") for every use.
+    let mut uses: HashMap<String, Vec<(Area, String)>> = HashMap::new();
+    let mut taken: BTreeSet<u32> = BTreeSet::new();
+
+    // ── Pass 1: record every named reference and every numeric slot in use ──
+    let nodes = dag.get_mut("nodes").and_then(Value::as_array_mut);
+    for node in nodes.into_iter().flatten() {
+        let id = node.get("id").and_then(Value::as_str).unwrap_or("?").to_owned();
+        if let Some(kind) = node.get_mut("kind") {
+            collect_dag_slots(kind, &mut taken);
+            visit_refs(kind, &mut |area, leaf| {
+                if let Value::String(name) = leaf {
+                    if name.is_empty() {
+                        errors.push(format!("node '{}': empty slot name", id));
+                        return Ok(());
+                    }
+                    if !uses.contains_key(name.as_str()) {
+                        order.push(name.clone());
+                    }
+                    uses.entry(name.clone()).or_default().push((area, format!("node '{}'", id)));
+                }
+                Ok(())
+            })?;
+        }
+    }
+    if let Some(list) = dag.get("persist_slots").and_then(Value::as_array) {
+        for leaf in list {
+            match leaf {
+                Value::String(name) => {
+                    if !uses.contains_key(name.as_str()) {
+                        order.push(name.clone());
+                    }
+                    uses.entry(name.clone()).or_default()
+                        .push((Area::Stream, "persist_slots".to_owned()));
+                }
+                other => collect_dag_slots(other, &mut taken),
+            }
+        }
+    }
+    if order.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // ── Decide each name's area and check it against every use ──────────────
+    let mut kinds: Vec<(String, RemoteSlotKind, SlotLifetime, u32, Option<RingDecl>)> = Vec::new();
+    for name in &order {
+        let name_uses = &uses[name];
+        let decl = decls.get(name);
+        let strict = |want: Area| name_uses.iter().find(|(a, _)| *a == want);
+        let kind = match decl {
+            Some(d) => {
+                let wrong = match d.kind {
+                    RemoteSlotKind::Stream => strict(Area::Io),
+                    RemoteSlotKind::Io => strict(Area::Stream),
+                };
+                if let Some((_, at)) = wrong {
+                    errors.push(format!(
+                        "{}: slot '{}' is declared {} but used as {} slot",
+                        at, name, kind_label(d.kind),
+                        if d.kind == RemoteSlotKind::Stream { "an I/O" } else { "a stream" },
+                    ));
+                }
+                d.kind
+            }
+            None => match (strict(Area::Stream), strict(Area::Io)) {
+                (Some((_, s_at)), Some((_, io_at))) => {
+                    errors.push(format!(
+                        "slot '{}' is used as a stream slot ({}) and as an I/O slot ({}); \
+                         declare it in `slots` or use two names",
+                        name, s_at, io_at
+                    ));
+                    RemoteSlotKind::Stream
+                }
+                (None, Some(_)) => RemoteSlotKind::Io,
+                _ => RemoteSlotKind::Stream,
+            },
+        };
+        let lifetime = decl.map(|d| d.lifetime).unwrap_or_default();
+        if lifetime == SlotLifetime::Persist && kind == RemoteSlotKind::Io {
+            errors.push(format!(
+                "slot '{}': lifetime \"persist\" is only supported for Stream slots",
+                name
+            ));
+        }
+        let page_class = match decl.and_then(|d| d.page_class.as_deref()) {
+            None => PAGE_CLASS_SMALL,
+            Some(class) => page_class_from_name(class).unwrap_or_else(|| {
+                errors.push(format!(
+                    "slot '{}': unknown page_class \"{}\" (expected one of {})",
+                    name, class, PAGE_CLASS_NAMES.join(", ")
+                ));
+                PAGE_CLASS_SMALL
+            }),
+        };
+        let ring = decl.and_then(|d| d.ring);
+        if ring.is_some() && kind == RemoteSlotKind::Io {
+            errors.push(format!("slot '{}': a ring is only supported for Stream slots", name));
+        }
+        if decl.is_some_and(|d| !d.subscribers.is_empty()) && kind == RemoteSlotKind::Io {
+            errors.push(format!("slot '{}': subscribers are only supported for Stream slots", name));
+        }
+        kinds.push((name.clone(), kind, lifetime, page_class, ring));
+    }
+
+    // ── Allocate upward from the highest index already in use, per area ─────
+    let next_free = |count: usize| -> u32 {
+        taken.range(..count as u32).next_back().map_or(0, |&m| m + 1).max(FIRST_NAMED_SLOT)
+    };
+    let mut next_stream = next_free(stream_slot_count);
+    let mut next_io = next_free(io_slot_count);
+
+    let mut bindings: Vec<SlotBinding> = Vec::new();
+    for (name, kind, lifetime, page_class, ring) in kinds {
+        let (next, count) = match kind {
+            RemoteSlotKind::Stream => (&mut next_stream, stream_slot_count),
+            RemoteSlotKind::Io => (&mut next_io, io_slot_count),
+        };
+        if *next as usize >= count {
+            errors.push(format!(
+                "slot '{}': no free {} slot left (count {})",
+                name, kind_label(kind), count
+            ));
+            continue;
+        }
+        let subscribers = decls.get(&name).map(|d| d.subscribers.clone()).unwrap_or_default();
+        bindings.push(SlotBinding { name, kind, index: *next, lifetime, page_class, ring, subscribers });
+        *next += 1;
+    }
+
+    if !errors.is_empty() {
+        return Err(anyhow!("DAG slot resolution failed:\n  {}", errors.join("\n  ")));
+    }
+
+    // ── Pass 2: rewrite references ──────────────────────────────────────────
+    let index_of: HashMap<&str, u32> =
+        bindings.iter().map(|b| (b.name.as_str(), b.index)).collect();
+    let substitute = |leaf: &mut Value| {
+        if let Value::String(name) = leaf {
+            if let Some(&idx) = index_of.get(name.as_str()) {
+                *leaf = Value::from(idx);
+            }
+        }
+    };
+    if let Some(nodes) = dag.get_mut("nodes").and_then(Value::as_array_mut) {
+        for node in nodes {
+            if let Some(kind) = node.get_mut("kind") {
+                visit_refs(kind, &mut |_, leaf| {
+                    substitute(leaf);
+                    Ok(())
+                })?;
+            }
+        }
+    }
+
+    let persisted: Vec<Value> = bindings.iter()
+        .filter(|b| b.lifetime == SlotLifetime::Persist)
+        .map(|b| Value::from(b.index))
+        .collect();
+    if let Some(obj) = dag.as_object_mut() {
+        let list = obj.entry("persist_slots").or_insert_with(|| Value::Array(Vec::new()));
+        if let Some(arr) = list.as_array_mut() {
+            arr.iter_mut().for_each(&substitute);
+            for idx in persisted {
+                if !arr.contains(&idx) {
+                    arr.push(idx);
+                }
+            }
+        }
+        let classes: Vec<Value> = bindings.iter()
+            .filter(|b| b.page_class != PAGE_CLASS_SMALL)
+            .map(|b| serde_json::json!({
+                "kind": kind_name(b.kind),
+                "slot": b.index,
+                "class": PAGE_CLASS_NAMES[b.page_class as usize],
+            }))
+            .collect();
+        if !classes.is_empty() {
+            let list = obj.entry("page_classes").or_insert_with(|| Value::Array(Vec::new()));
+            if let Some(arr) = list.as_array_mut() {
+                arr.extend(classes);
+            }
+        }
+        let rings: Vec<Value> = bindings.iter()
+            .filter_map(|b| b.ring.map(|r| serde_json::json!({
+                "slot": b.index,
+                "pages": r.pages,
+                "consumers": r.consumers,
+            })))
+            .collect();
+        if !rings.is_empty() {
+            let list = obj.entry("rings").or_insert_with(|| Value::Array(Vec::new()));
+            if let Some(arr) = list.as_array_mut() {
+                arr.extend(rings);
+            }
+        }
+        let subscriptions: Vec<Value> = bindings.iter()
+            .filter(|b| !b.subscribers.is_empty())
+            .map(|b| serde_json::json!({ "slot": b.index, "subscribers": b.subscribers }))
+            .collect();
+        if !subscriptions.is_empty() {
+            let list = obj.entry("subscriptions").or_insert_with(|| Value::Array(Vec::new()));
+            if let Some(arr) = list.as_array_mut() {
+                arr.extend(subscriptions);
+            }
+        }
+    }
+
+    Ok(bindings)
+}
+
+/// Print the resolved bindings in the same style as the barrier-group table.
+pub(super) fn print_slot_bindings(bindings: &[SlotBinding]) {
+    if bindings.is_empty() {
+        return;
+    }
+    println!("[DAG] Slot names:");
+    for b in bindings {
+        let persist = if b.lifetime == SlotLifetime::Persist { " (persist)" } else { "" };
+        let pages = if b.page_class != PAGE_CLASS_SMALL {
+            format!(" ({} pages)", PAGE_CLASS_NAMES[b.page_class as usize])
+        } else {
+            String::new()
+        };
+        let ring = match b.ring {
+            Some(r) => format!(" (ring of {} pages, {} consumers)", r.pages, r.consumers),
+            None => String::new(),
+        };
+        let subscribers = if b.subscribers.is_empty() {
+            String::new()
+        } else {
+            format!(" (subscribers: {})", b.subscribers.join(", "))
+        };
+        println!(
+            "[DAG]   '{}' → {} slot {}{}{}{}{}",
+            b.name, kind_label(b.kind), b.index, persist, pages, ring, subscribers,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn dag_without_names_is_untouched() {
+        let mut dag = json!({
+            "shm_path": "/dev/shm/x",
+            "nodes": [ { "id": "b", "kind": { "Bridge": { "from": 0, "to": 20 } } } ]
+        });
+        let before = dag.clone();
+        assert!(resolve_slot_names(&mut dag, &EngineConfig::DEFAULT).unwrap().is_empty());
+        assert_eq!(dag, before);
+    }
+
+    #[test]
+    fn names_allocate_above_highest_numeric_slot() {
+        let mut dag = json!({
+            "shm_path": "/dev/shm/x",
+            "nodes": [
+                { "id": "agg", "kind": { "Aggregate": { "upstream": [110, 111], "downstream": "merged" } } },
+                { "id": "red", "deps": ["agg"], "kind": { "WasmVoid": { "func": "wc_reduce", "arg": "merged" } } },
+                { "id": "load", "kind": { "Input": { "path": "in.txt", "slot": "corpus" } } }
+            ]
+        });
+        let b = resolve_slot_names(&mut dag, &EngineConfig::DEFAULT).unwrap();
+        assert_eq!(b.len(), 2);
+        assert_eq!((b[0].name.as_str(), b[0].kind, b[0].index), ("merged", RemoteSlotKind::Stream, 112));
+        assert_eq!((b[1].name.as_str(), b[1].kind, b[1].index), ("corpus", RemoteSlotKind::Io, 112));
+        assert_eq!(dag["nodes"][0]["kind"]["Aggregate"]["downstream"], json!(112));
+        assert_eq!(dag["nodes"][1]["kind"]["WasmVoid"]["arg"], json!(112));
+        assert_eq!(dag["nodes"][2]["kind"]["Input"]["slot"], json!(112));
+    }
+
+    #[test]
+    fn slot_kind_selects_area_and_wasm_arg_is_ignored() {
+        let mut dag = json!({
+            "shm_path": "/dev/shm/x",
+            "nodes": [
+                { "id": "f", "kind": { "WasmVoid": { "func": "f", "arg": "a" } }, "wasm_arg": 70000 },
+                { "id": "s", "deps": ["f"], "kind": { "RemoteSend": { "slot": "a", "slot_kind": "Stream", "peer": 1 } } }
+            ]
+        });
+        let b = resolve_slot_names(&mut dag, &EngineConfig::DEFAULT).unwrap();
+        assert_eq!((b[0].kind, b[0].index), (RemoteSlotKind::Stream, FIRST_NAMED_SLOT));
+        assert_eq!(dag["nodes"][1]["kind"]["RemoteSend"]["slot"], json!(2));
+    }
+
+    #[test]
+    fn persist_lifetime_extends_persist_slots() {
+        let mut dag = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": { "acc": { "kind": "Stream", "lifetime": "persist" } },
+            "persist_slots": [5],
+            "nodes": [ { "id": "b", "kind": { "Bridge": { "from": 3, "to": "acc" } } } ]
+        });
+        let b = resolve_slot_names(&mut dag, &EngineConfig::DEFAULT).unwrap();
+        assert_eq!(b[0].index, 6);
+        assert_eq!(dag["persist_slots"], json!([5, 6]));
+    }
+
+    #[test]
+    fn page_class_extends_page_classes() {
+        let mut dag = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": {
+                "bulk": { "kind": "Stream", "page_class": "2M" },
+                "rows": { "kind": "Io", "page_class": "4k" }
+            },
+            "nodes": [
+                { "id": "in", "kind": { "Input": { "path": "p", "slot": "rows" } } },
+                { "id": "s", "kind": { "RemoteSend": { "slot": "bulk", "slot_kind": "Stream", "peer": 1 } } }
+            ]
+        });
+        let b = resolve_slot_names(&mut dag, &EngineConfig::DEFAULT).unwrap();
+        assert_eq!((b[0].page_class, b[1].page_class), (PAGE_CLASS_SMALL, common::PAGE_CLASS_LARGE));
+        assert_eq!(dag["page_classes"], json!([{ "kind": "Stream", "slot": 2, "class": "2m" }]));
+
+        let mut bad = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": { "x": { "kind": "Stream", "page_class": "8k" } },
+            "nodes": [ { "id": "b", "kind": { "Bridge": { "from": 3, "to": "x" } } } ]
+        });
+        let err = resolve_slot_names(&mut bad, &EngineConfig::DEFAULT).unwrap_err().to_string();
+        assert!(err.contains("unknown page_class \"8k\""), "{}", err);
+    }
+
+    #[test]
+    fn ring_extends_rings() {
+        let mut dag = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": { "feed": { "kind": "Stream", "ring": { "pages": 8 } } },
+            "nodes": [ { "id": "p", "kind": { "WasmVoid": { "func": "produce", "arg": "feed" } } } ]
+        });
+        let b = resolve_slot_names(&mut dag, &EngineConfig::DEFAULT).unwrap();
+        assert_eq!(b[0].ring, Some(RingDecl { pages: 8, consumers: 1 }));
+        assert_eq!(dag["rings"], json!([{ "slot": 2, "pages": 8, "consumers": 1 }]));
+
+        let mut bad = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": { "rows": { "kind": "Io", "ring": { "pages": 8, "consumers": 2 } } },
+            "nodes": [ { "id": "in", "kind": { "Input": { "path": "p", "slot": "rows" } } } ]
+        });
+        let err = resolve_slot_names(&mut bad, &EngineConfig::DEFAULT).unwrap_err().to_string();
+        assert!(err.contains("a ring is only supported for Stream slots"), "{}", err);
+    }
+
+    #[test]
+    fn subscribers_extend_subscriptions() {
+        let mut dag = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": { "events": { "kind": "Stream", "subscribers": ["audit", "index"] } },
+            "nodes": [ { "id": "p", "kind": { "WasmVoid": { "func": "produce", "arg": "events" } } } ]
+        });
+        let b = resolve_slot_names(&mut dag, &EngineConfig::DEFAULT).unwrap();
+        assert_eq!(b[0].subscribers, vec!["audit", "index"]);
+        assert_eq!(dag["subscriptions"], json!([{ "slot": 2, "subscribers": ["audit", "index"] }]));
+
+        let mut bad = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": { "rows": { "kind": "Io", "subscribers": ["a"] } },
+            "nodes": [ { "id": "in", "kind": { "Input": { "path": "p", "slot": "rows" } } } ]
+        });
+        let err = resolve_slot_names(&mut bad, &EngineConfig::DEFAULT).unwrap_err().to_string();
+        assert!(err.contains("subscribers are only supported for Stream slots"), "{}", err);
+    }
+
+    #[test]
+    fn misuse_is_reported() {
+        let mut declared = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": { "x": { "kind": "Io" } },
+            "nodes": [ { "id": "b", "kind": { "Bridge": { "from": "x", "to": 4 } } } ]
+        });
+        let err = resolve_slot_names(&mut declared, &EngineConfig::DEFAULT).unwrap_err().to_string();
+        assert!(err.contains("declared I/O"), "{}", err);
+
+        let mut mixed = json!({
+            "shm_path": "/dev/shm/x",
+            "nodes": [
+                { "id": "in",  "kind": { "Input": { "path": "p", "slot": "y" } } },
+                { "id": "out", "kind": { "Watch": { "stream": "y", "output": "o" } } }
+            ]
+        });
+        assert!(resolve_slot_names(&mut mixed, &EngineConfig::DEFAULT).is_err());
+
+        let mut io_persist = json!({
+            "shm_path": "/dev/shm/x",
+            "slots": { "z": { "kind": "Io", "lifetime": "persist" } },
+            "nodes": [ { "id": "in", "kind": { "Input": { "path": "p", "slot": "z" } } } ]
+        });
+        assert!(resolve_slot_names(&mut io_persist, &EngineConfig::DEFAULT).is_err());
+    }
+
+    #[test]
+    fn names_fit_the_configured_slot_counts() {
+        let cfg = EngineConfig { io_slot_count: 3, ..EngineConfig::DEFAULT };
+        let dag = |names: &[&str]| json!({
+            "shm_path": "/dev/shm/x",
+            "nodes": names.iter().map(|n| json!({ "id": n, "kind": { "Input": { "path": "p", "slot": n } } }))
+                .collect::<Vec<_>>()
+        });
+        let b = resolve_slot_names(&mut dag(&["a"]), &cfg).unwrap();
+        assert_eq!(b[0].index, 2);
+        let err = resolve_slot_names(&mut dag(&["a", "b"]), &cfg).unwrap_err().to_string();
+        assert!(err.contains("no free I/O slot left (count 3)"), "{}", err);
+    }
+}
@@ -0,0 +1,380 @@
+//! Spill tier: cold stream slots move to local disk under SHM memory pressure.
+//!
+//! Paged mode lifts the 2 GiB direct-window limit, but the `GlobalPool` is
+//! still tmpfs, so a large shuffle can exhaust RAM.  With a DAG `spill` block
+//! the runner checks live SHM usage after every wave; above the high watermark
+//! it evicts whole stream slots that no node needs in the next wave:
+//!
+//!   1. the waves that name each stream slot are known up front
+//!      ([`node_stream_refs`] over `build_waves`), so every non-empty slot has
+//!      a *next use*;
+//!   2. candidates are slots whose next use is at least two waves away —
+//!      farthest next use first, larger chains first among ties — until usage
+//!      is under the low watermark;
+//!   3. a victim's chain is copied byte-for-byte (each page's `data[..cursor]`)
+//!      to `<dir>/webs-spill-<pid>/stream-<slot>.spill`, freed, and the freed
+//!      RAM is returned to the OS;
+//!   4. at the top of the wave that next names it, the bytes are appended to
+//!      a fresh chain.  Records appended to the slot while it was on disk are
+//!      relinked after the reloaded ones, so record order — and with it the
+//!      `stream_cursor_N` record indices — is unchanged.
+//!
+//! Never spilled: slots in `pin` or `persist_slots`, `RemoteRecv` stream slots
+//! (a deferred receive thread may still be writing them), slots with
+//! `subscriptions` (their cursors point at pages) and slots with no named
+//! later use.  A wave containing `FileDispatch` / `OwnedDispatch` counts
+//! as a use of every slot.
+
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+
+use common::{PageId, Superblock, MIB, PAGE_SIZE};
+
+use super::plan::node_stream_refs;
+use super::stage_fanout::write_stream_bytes;
+use super::types::{Dag, NodeKind, RemoteSlotKind, SpillConfig};
+use crate::runtime::extended_pool;
+use crate::runtime::mem_operation::reclaimer;
+
+/// Read/write buffer for spill files.
+const IO_CHUNK: usize = 1 << 20;
+
+// ─── Wave planning ────────────────────────────────────────────────────────────
+
+/// The waves that may read each stream slot.
+struct WaveUses {
+    /// slot → waves naming it, ascending.
+    named: HashMap<usize, Vec<usize>>,
+    /// Waves with a node that may touch any slot.
+    opaque: Vec<usize>,
+}
+
+impl WaveUses {
+    fn build(dag: &Dag, waves: &[Vec<usize>]) -> Self {
+        let mut named: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut opaque = Vec::new();
+        for (w, wave) in waves.iter().enumerate() {
+            for &idx in wave {
+                match node_stream_refs(&dag.nodes[idx].kind) {
+                    Some(slots) => for s in slots {
+                        let uses = named.entry(s).or_default();
+                        if uses.last() != Some(&w) {
+                            uses.push(w);
+                        }
+                    },
+                    None => if opaque.last() != Some(&w) { opaque.push(w) },
+                }
+            }
+        }
+        Self { named, opaque }
+    }
+
+    /// First wave after `wave` that may read `slot`.  `None` when no later
+    /// wave names it: such a slot is dead or reached only by guest arithmetic,
+    /// and is left alone.
+    fn next_use(&self, slot: usize, wave: usize) -> Option<usize> {
+        let after = |v: &[usize]| v.iter().copied().find(|&w| w > wave);
+        let named = after(self.named.get(&slot)?)?;
+        Some(after(&self.opaque).map_or(named, |o| o.min(named)))
+    }
+
+    /// Whether `wave` may read `slot`.
+    fn reads(&self, slot: usize, wave: usize) -> bool {
+        self.opaque.contains(&wave)
+            || self.named.get(&slot).is_some_and(|v| v.contains(&wave))
+    }
+}
+
+/// A slot that may be spilled after the current wave.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    slot: usize,
+    next_use: usize,
+    bytes: u64,
+}
+
+/// Victims covering `excess` bytes: farthest next use first (the page that is
+/// needed last is the cheapest to evict), larger chains first among ties.
+fn pick_victims(mut candidates: Vec<Candidate>, excess: u64) -> Vec<usize> {
+    candidates.sort_by(|a, b| b.next_use.cmp(&a.next_use).then(b.bytes.cmp(&a.bytes)));
+    let mut covered = 0u64;
+    candidates.into_iter()
+        .take_while(|c| {
+            let take = covered < excess;
+            covered += c.bytes;
+            take
+        })
+        .map(|c| c.slot)
+        .collect()
+}
+
+// ─── Spill tier ───────────────────────────────────────────────────────────────
+
+#[derive(Default)]
+struct SpillStats {
+    spilled: usize,
+    bytes_out: u64,
+    reloaded: usize,
+    bytes_in: u64,
+}
+
+pub(super) struct SpillTier {
+    dir: PathBuf,
+    high: u64,
+    low: u64,
+    pinned: HashSet<usize>,
+    uses: WaveUses,
+    /// Spilled slot → bytes in its file.
+    spilled: BTreeMap<usize, u64>,
+    stats: SpillStats,
+}
+
+impl SpillTier {
+    pub(super) fn new(cfg: &SpillConfig, dag: &Dag, waves: &[Vec<usize>]) -> Result<Self> {
+        let high = cfg.high_watermark_mib * MIB as u64;
+        let low = cfg.low_watermark_mib.unwrap_or(cfg.high_watermark_mib * 3 / 4) * MIB as u64;
+        if high == 0 {
+            return Err(anyhow!("spill: high_watermark_mib must be > 0"));
+        }
+        if low > high {
+            return Err(anyhow!("spill: low_watermark_mib ({}) exceeds high_watermark_mib ({})",
+                low / MIB as u64, cfg.high_watermark_mib));
+        }
+        let dir = PathBuf::from(&cfg.dir).join(format!("webs-spill-{}", std::process::id()));
+        fs::create_dir_all(&dir)
+            .map_err(|e| anyhow!("spill: cannot create '{}': {}", dir.display(), e))?;
+
+        let mut pinned: HashSet<usize> = cfg.pin.iter().copied().collect();
+        pinned.extend(dag.persist_slots.iter().map(|&s| s as usize));
+        pinned.extend(dag.subscriptions.iter().map(|s| s.slot as usize));
+        pinned.extend(dag.nodes.iter().filter_map(|n| match &n.kind {
+            NodeKind::RemoteRecv(p) if p.slot_kind == RemoteSlotKind::Stream => Some(p.slot),
+            _ => None,
+        }));
+
+        println!("[Spill] enabled: dir {}, watermarks {}/{} MiB, {} pinned slot(s)",
+            dir.display(), high / MIB as u64, low / MIB as u64, pinned.len());
+        Ok(Self {
+            dir,
+            high,
+            low,
+            pinned,
+            uses: WaveUses::build(dag, waves),
+            spilled: BTreeMap::new(),
+            stats: SpillStats::default(),
+        })
+    }
+
+    /// Reload every spilled slot `wave` may read.  Call before the wave starts.
+    pub(super) fn reload_for_wave(&mut self, splice_addr: usize, wave: usize) -> Result<()> {
+        let due: Vec<usize> = self.spilled.keys().copied()
+            .filter(|&s| self.uses.reads(s, wave))
+            .collect();
+        for slot in due {
+            let bytes = self.reload_slot(splice_addr, slot)?;
+            println!("[Spill] slot {} ← disk ({:.1} MiB) for wave {}", slot, bytes as f64 / MIB as f64, wave);
+        }
+        Ok(())
+    }
+
+    /// Spill cold slots if live usage is above the high watermark.  Call after
+    /// `wave`'s reclamation, once every node of the wave has finished.
+    pub(super) fn after_wave(&mut self, splice_addr: usize, wave: usize) -> Result<()> {
+        let live = live_bytes(splice_addr);
+        if live <= self.high {
+            return Ok(());
+        }
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        let mut candidates = Vec::new();
+        for &slot in self.uses.named.keys() {
+            if slot >= sb.stream_slot_count() || self.pinned.contains(&slot) || self.spilled.contains_key(&slot) {
+                continue;
+            }
+            let head = sb.writer_head(slot).load(Ordering::Acquire);
+            if head == 0 {
+                continue;
+            }
+            match self.uses.next_use(slot, wave) {
+                Some(next_use) if next_use > wave + 1 => candidates.push(Candidate {
+                    slot,
+                    next_use,
+                    bytes: chain_bytes(splice_addr, head)?,
+                }),
+                _ => {}
+            }
+        }
+
+        let victims = pick_victims(candidates, live - self.low);
+        if victims.is_empty() {
+            println!("[Spill] live {:.1} MiB above high watermark but no cold slot to spill",
+                live as f64 / MIB as f64);
+            return Ok(());
+        }
+        let mut freed_pages = 0usize;
+        for slot in victims {
+            let next_use = self.uses.next_use(slot, wave).unwrap_or(wave);
+            let (bytes, pages) = self.spill_slot(splice_addr, slot)?;
+            freed_pages += pages;
+            println!("[Spill] slot {} → disk ({:.1} MiB, next use wave {})",
+                slot, bytes as f64 / MIB as f64, next_use);
+        }
+        let released = reclaimer::release_free_pages(splice_addr, freed_pages)
+            + extended_pool::runtime::trim_global_freelist();
+        println!("[Spill] live {:.1} → {:.1} MiB ({} pages released to OS)",
+            live as f64 / MIB as f64, live_bytes(splice_addr) as f64 / MIB as f64, released);
+        Ok(())
+    }
+
+    /// Reload anything still on disk and print the run's totals.  Normally a
+    /// no-op reload: every spilled slot has a later use in the same run.
+    pub(super) fn finish_run(&mut self, splice_addr: usize, run: u32) -> Result<()> {
+        let left: Vec<usize> = self.spilled.keys().copied().collect();
+        for slot in left {
+            self.reload_slot(splice_addr, slot)?;
+        }
+        let s = std::mem::take(&mut self.stats);
+        if s.spilled > 0 || s.reloaded > 0 {
+            println!("[Spill] run #{}: {} slot(s) spilled ({:.1} MiB), {} reloaded ({:.1} MiB)",
+                run, s.spilled, s.bytes_out as f64 / MIB as f64, s.reloaded, s.bytes_in as f64 / MIB as f64);
+        }
+        Ok(())
+    }
+
+    fn path(&self, slot: usize) -> PathBuf {
+        self.dir.join(format!("stream-{}.spill", slot))
+    }
+
+    /// Copy `slot`'s chain to its file and free it.  Returns (bytes, pages).
+    fn spill_slot(&mut self, splice_addr: usize, slot: usize) -> Result<(u64, usize)> {
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        let path = self.path(slot);
+        let file = fs::File::create(&path)
+            .map_err(|e| anyhow!("spill: cannot create '{}': {}", path.display(), e))?;
+        let mut w = BufWriter::with_capacity(IO_CHUNK, file);
+
+        let mut current: PageId = sb.writer_head(slot).load(Ordering::Acquire);
+        let (mut bytes, mut pages) = (0u64, 0usize);
+        while current != 0 {
+            let page = unsafe { &*extended_pool::runtime::resolve(current, splice_addr)? };
+            let used = (page.cursor.load(Ordering::Acquire) as usize).min(page.capacity());
+            // SAFETY: a page of any class holds `capacity()` data bytes.
+            let data = unsafe { std::slice::from_raw_parts(page.data.as_ptr(), used) };
+            w.write_all(data)
+                .map_err(|e| anyhow!("spill: write '{}': {}", path.display(), e))?;
+            bytes += used as u64;
+            pages += 1;
+            current = page.next_offset.load(Ordering::Acquire);
+        }
+        w.flush().map_err(|e| anyhow!("spill: write '{}': {}", path.display(), e))?;
+
+        reclaimer::free_stream_slot(splice_addr, slot);
+        self.spilled.insert(slot, bytes);
+        self.stats.spilled += 1;
+        self.stats.bytes_out += bytes;
+        Ok((bytes, pages))
+    }
+
+    /// Rebuild `slot`'s chain from its file.  Returns the bytes reloaded.
+    fn reload_slot(&mut self, splice_addr: usize, slot: usize) -> Result<u64> {
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        let path = self.path(slot);
+        let mut file = fs::File::open(&path)
+            .map_err(|e| anyhow!("spill: cannot open '{}': {}", path.display(), e))?;
+
+        // Records appended while the slot was on disk go after the reloaded ones.
+        let later_head = sb.writer_head(slot).swap(0, Ordering::AcqRel);
+        let later_tail = sb.writer_tail(slot).swap(0, Ordering::AcqRel);
+
+        let mut buf = vec![0u8; IO_CHUNK];
+        let mut bytes = 0u64;
+        loop {
+            let n = file.read(&mut buf)
+                .map_err(|e| anyhow!("spill: read '{}': {}", path.display(), e))?;
+            if n == 0 {
+                break;
+            }
+            write_stream_bytes(splice_addr, slot, &buf[..n])?;
+            bytes += n as u64;
+        }
+
+        if later_head != 0 {
+            let tail = sb.writer_tail(slot).load(Ordering::Acquire);
+            if tail == 0 {
+                sb.writer_head(slot).store(later_head, Ordering::Release);
+            } else {
+                let page = unsafe { &*extended_pool::runtime::resolve(tail, splice_addr)? };
+                page.next_offset.store(later_head, Ordering::Release);
+            }
+            sb.writer_tail(slot).store(later_tail, Ordering::Release);
+        }
+
+        let _ = fs::remove_file(&path);
+        self.spilled.remove(&slot);
+        self.stats.reloaded += 1;
+        self.stats.bytes_in += bytes;
+        Ok(bytes)
+    }
+}
+
+impl Drop for SpillTier {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Live SHM bytes: direct pages handed out minus the free list, plus this
+/// process's paged pages.
+fn live_bytes(splice_addr: usize) -> u64 {
+    let sb = unsafe { &*(splice_addr as *const Superblock) };
+    let bumped = sb.bump_allocator.load(Ordering::Acquire).saturating_sub(sb.layout.bump_allocator_start) as u64;
+    let free = reclaimer::count_free_list_pages(splice_addr) as u64 * PAGE_SIZE as u64
+        + reclaimer::count_class_free_bytes(splice_addr);
+    bumped.saturating_sub(free) + extended_pool::runtime::paged_live_pages() * PAGE_SIZE as u64
+}
+
+/// Bytes of SHM held by the chain at `head` (pages of any class).
+fn chain_bytes(splice_addr: usize, head: PageId) -> Result<u64> {
+    let mut bytes = 0u64;
+    let mut current = head;
+    while current != 0 {
+        let page = unsafe { &*extended_pool::runtime::resolve(current, splice_addr)? };
+        current = page.next_offset.load(Ordering::Acquire);
+        bytes += page.span() as u64;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uses(named: &[(usize, &[usize])], opaque: &[usize]) -> WaveUses {
+        WaveUses {
+            named: named.iter().map(|&(s, w)| (s, w.to_vec())).collect(),
+            opaque: opaque.to_vec(),
+        }
+    }
+
+    #[test]
+    fn next_use_is_bounded_by_opaque_waves() {
+        let u = uses(&[(5, &[0, 6]), (7, &[1])], &[3]);
+        assert_eq!(u.next_use(5, 1), Some(3));
+        assert_eq!(u.next_use(5, 4), Some(6));
+        // No named later use: never a candidate, even with an opaque wave ahead.
+        assert_eq!(u.next_use(7, 1), None);
+        assert!(u.reads(7, 3) && u.reads(5, 6) && !u.reads(5, 5));
+    }
+
+    #[test]
+    fn victims_are_farthest_next_use_first() {
+        let c = |slot, next_use, mib: u64| Candidate { slot, next_use, bytes: mib * MIB as u64 };
+        let cands = vec![c(1, 4, 100), c(2, 9, 10), c(3, 9, 50), c(4, 6, 300)];
+        assert_eq!(pick_victims(cands.clone(), 40 * MIB as u64), vec![3]);
+        assert_eq!(pick_victims(cands.clone(), 70 * MIB as u64), vec![3, 2, 4]);
+        assert!(pick_victims(cands, 0).is_empty());
+    }
+}
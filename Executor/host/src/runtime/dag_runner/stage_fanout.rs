@@ -13,22 +13,23 @@
 //!   4. GATHERS the `W` output sub-slots back into the stage's real output slot
 //!      in round-robin record order.
 //!
-//! Sub-slots come from a reserved high band (`SUB_SLOT_BASE..STREAM_SLOT_COUNT`)
+//! Sub-slots come from a reserved high band (`SUB_SLOT_BASE..SUB_SLOT_BASE + 256`)
 //! and are freed every tick, so they never collide with user DAG slots (which
 //! are numbered low) and don't leak.
 
 use anyhow::{anyhow, Result};
 use std::sync::atomic::Ordering;
 
-use common::{Page, PageId, PAGE_DATA_SIZE, ShmOffset, Superblock};
+use common::{Page, PageId, ShmOffset, Superblock};
 
 use crate::runtime::extended_pool;
 use crate::runtime::mem_operation::reclaimer;
 use crate::runtime::input_output::persistence::read_stream_records;
 
 /// First stream slot of the reserved scatter/gather band.  User DAG slots are
-/// numbered low (tens to low hundreds); this band sits just under
-/// `STREAM_SLOT_COUNT` (2048) so the two never overlap.
+/// numbered low (tens to low hundreds); this band sits just under the default
+/// stream slot count (2048) so the two never overlap.  Regions formatted with
+/// fewer stream slots have no band, and widened stages are rejected.
 pub(super) const SUB_SLOT_BASE: usize = 1792;
 /// Hard cap on a stage's width (bounds the reserved band usage).
 pub(super) const MAX_STAGE_WIDTH: usize = 16;
@@ -43,10 +44,11 @@ pub(super) fn sub_out_slot(s: usize, w: usize) -> usize {
     SUB_SLOT_BASE + 128 + s * MAX_STAGE_WIDTH + w
 }
 
-/// Returns `true` if every (stage, worker) sub-slot pair fits the reserved band.
-pub(super) fn fits_band(depth: usize, max_width: usize) -> bool {
+/// Returns `true` if every (stage, worker) sub-slot pair fits the reserved
+/// band of a region with `stream_slot_count` stream slots.
+pub(super) fn fits_band(depth: usize, max_width: usize, stream_slot_count: usize) -> bool {
     max_width <= MAX_STAGE_WIDTH && depth * MAX_STAGE_WIDTH <= 128
-        && SUB_SLOT_BASE + 256 <= common::STREAM_SLOT_COUNT
+        && SUB_SLOT_BASE + 256 <= stream_slot_count
 }
 
 /// Append one length-prefixed record (`len | origin | payload`, matching the
@@ -62,25 +64,28 @@ pub(super) fn append_stream_record(
     write_stream_bytes(splice_addr, slot, payload)
 }
 
-fn write_stream_bytes(splice_addr: usize, slot: usize, mut data: &[u8]) -> Result<()> {
+/// Append raw bytes to stream `slot`'s page chain, allocating pages in the
+/// slot's page class as needed.
+pub(super) fn write_stream_bytes(splice_addr: usize, slot: usize, mut data: &[u8]) -> Result<()> {
     if data.is_empty() { return Ok(()); }
     let sb = unsafe { &*(splice_addr as *const Superblock) };
+    let class = sb.stream_page_class(slot).load(Ordering::Relaxed) as u32;
 
-    let mut tail: PageId = sb.writer_tails[slot].load(Ordering::Acquire);
+    let mut tail: PageId = sb.writer_tail(slot).load(Ordering::Acquire);
     if tail == 0 {
-        tail = reclaimer::alloc_page(splice_addr).map_err(|e| anyhow!("stage_fanout alloc: {e}"))?;
-        sb.writer_heads[slot].store(tail, Ordering::Release);
-        sb.writer_tails[slot].store(tail, Ordering::Release);
+        tail = reclaimer::alloc_page_in(splice_addr, class).map_err(|e| anyhow!("stage_fanout alloc: {e}"))?;
+        sb.writer_head(slot).store(tail, Ordering::Release);
+        sb.writer_tail(slot).store(tail, Ordering::Release);
     }
 
     while !data.is_empty() {
         let page = unsafe { &mut *page_ptr(tail, splice_addr)? };
         let cursor = page.cursor.load(Ordering::Relaxed) as usize;
-        let space = PAGE_DATA_SIZE.saturating_sub(cursor);
+        let space = page.capacity().saturating_sub(cursor);
         if space == 0 {
-            let next = reclaimer::alloc_page(splice_addr).map_err(|e| anyhow!("stage_fanout alloc: {e}"))?;
+            let next = reclaimer::alloc_page_in(splice_addr, class).map_err(|e| anyhow!("stage_fanout alloc: {e}"))?;
             page.next_offset.store(next, Ordering::Release);
-            sb.writer_tails[slot].store(next, Ordering::Release);
+            sb.writer_tail(slot).store(next, Ordering::Release);
             tail = next;
             continue;
         }
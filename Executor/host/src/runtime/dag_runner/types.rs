@@ -1,4 +1,10 @@
 use serde::Deserialize;
+use crate::policy::IntType;
+use crate::runtime::engine_config::EngineOverrides;
+use crate::runtime::input_output::file_set::FileSet;
+use crate::runtime::input_output::record_format::{Compression, OutputFraming, RecordFormat};
+use crate::runtime::input_output::slot_flusher::{OutputOptions, OutputPartition};
+use crate::runtime::input_output::typed_record::RecordSchema;
 
 // ─── JSON schema ─────────────────────────────────────────────────────────────
 
@@ -17,8 +23,8 @@ pub enum DagMode {
 }
 
 /// RDMA full-mesh configuration — required when any node uses `RemoteSend`
-/// or `RemoteRecv`.  All machines must include an identical `ips` list and
-/// set `node_id` to their own index.
+/// or `RemoteRecv`, or a guest calls the remote-atomic imports.  All machines
+/// must include an identical `ips` list and set `node_id` to their own index.
 #[derive(Debug, Deserialize)]
 pub struct RdmaConfig {
     /// Index of this node in the mesh (0-based).
@@ -38,10 +44,84 @@ pub struct RdmaConfig {
     /// `RemoteSend` / `RemoteRecv` nodes will fail at runtime when `false`.
     #[serde(default = "default_transfer")]
     pub transfer: bool,
+    /// How guest-initiated remote atomics (`host_remote_faa` & co.) reach
+    /// other nodes.  `tcp` sets up no RDMA mesh at all, so a DAG that only
+    /// uses guest remote atomics runs without an RDMA NIC — several runners
+    /// on one machine included.  `RemoteSend`, `RemoteRecv` and the
+    /// `RemoteAtomic*` nodes need `rdma`.
+    #[serde(default)]
+    pub transport: MeshTransport,
+    /// Base TCP port of the runners' atomics services: node `i` listens on
+    /// `atomic_port + i`, so runners sharing a host do not collide.
+    #[serde(default = "default_atomic_port")]
+    pub atomic_port: u16,
 }
 
 fn default_transfer() -> bool { true }
 
+fn default_atomic_port() -> u16 { 7300 }
+
+/// Transport of an `RdmaConfig` mesh (`"rdma"` or `"tcp"`).
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MeshTransport {
+    /// RC queue pairs over the RDMA NIC; guest remote atomics are one-sided.
+    #[default]
+    Rdma,
+    /// Plain TCP between the runners' atomics services; carries only guest
+    /// remote atomics.
+    Tcp,
+}
+
+/// Spill tier for cold stream slots — see `spill.rs`.
+///
+/// When live SHM pages exceed `high_watermark_mib` after a wave, the runner
+/// writes the page chains of stream slots no node needs in the next wave to
+/// files under `dir`, farthest next use first, until usage is back under
+/// `low_watermark_mib`.  Each spilled slot is reloaded right before the first
+/// wave that names it.
+#[derive(Debug, Deserialize)]
+pub struct SpillConfig {
+    /// Local directory for spill files (a per-process subdirectory is created
+    /// and removed on exit).  Prefer a local SSD over tmpfs.
+    pub dir: String,
+    /// Live SHM usage (MiB) above which slots are spilled.
+    pub high_watermark_mib: u64,
+    /// Usage (MiB) to spill down to.  Defaults to 3/4 of the high watermark.
+    #[serde(default)]
+    pub low_watermark_mib: Option<u64>,
+    /// Stream slots never spilled — slots a guest reaches only by arithmetic
+    /// on its arguments (e.g. `slot + 100`) are invisible to the planner and
+    /// must be listed here if they are read while another slot is spilled.
+    #[serde(default)]
+    pub pin: Vec<usize>,
+}
+
+/// File I/O backend of `Input`, `Output` and `StreamOutput` nodes — see
+/// `input_output/uring.rs`.  Falls back to blocking I/O when the kernel
+/// refuses `io_uring_setup`.
+#[derive(Debug, Deserialize)]
+pub struct IoConfig {
+    #[serde(default)]
+    pub backend: IoBackend,
+    /// Submission-queue entries of the shared ring (the first DAG in a
+    /// process to enable it sizes it).
+    #[serde(default = "default_queue_depth")]
+    pub queue_depth: u32,
+}
+
+fn default_queue_depth() -> u32 { 256 }
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IoBackend {
+    /// One blocking read / write per file.
+    #[default]
+    Blocking,
+    /// Batched reads and writes on one process-wide io_uring.
+    Uring,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Dag {
     /// Path to the SHM file; created and formatted automatically.
@@ -77,11 +157,80 @@ pub struct Dag {
     /// for word count, where `wc_reduce` re-sums the accumulated records).
     #[serde(default)]
     pub persist_slots: Vec<u32>,
+    /// Page class of slots that should not use 4 KiB pages, applied right
+    /// after the region is formatted.  Named slots declared with a
+    /// `page_class` are appended here by the slot-name resolver.
+    ///
+    /// ```json
+    /// "page_classes": [{ "kind": "Stream", "slot": 40, "class": "2m" }]
+    /// ```
+    #[serde(default)]
+    pub page_classes: Vec<SlotPageClass>,
+    /// Stream slots that are bounded rings instead of growing chains (see
+    /// "Ring slots" in `common`), set up right after `page_classes`.  Named
+    /// slots declared with a `ring` are appended here by the slot-name
+    /// resolver.
+    ///
+    /// ```json
+    /// "rings": [{ "slot": 40, "pages": 16, "consumers": 2 }]
+    /// ```
+    #[serde(default)]
+    pub rings: Vec<SlotRing>,
+    /// Stream slots with named subscriber cursors (see "Stream subscriptions"
+    /// in `common`), set up after `rings`.  Pages every subscriber has read
+    /// are freed after each wave.  Named slots declared with `subscribers`
+    /// are appended here by the slot-name resolver.
+    ///
+    /// ```json
+    /// "subscriptions": [{ "slot": 41, "subscribers": ["audit", "index"] }]
+    /// ```
+    #[serde(default)]
+    pub subscriptions: Vec<SlotSubscriptions>,
     /// Optional RDMA full-mesh configuration.  Required when any node uses
     /// `RemoteSend` or `RemoteRecv`.  All nodes in the mesh must specify
     /// matching `total` / `ips` lists and distinct `node_id` values.
     #[serde(default)]
     pub rdma: Option<RdmaConfig>,
+    /// Engine tunables for this run (paged-mode thresholds, free-list trim,
+    /// RDMA MR sizing, extended-pool switches).  Overrides the
+    /// `WEBS_ENGINE_CONFIG` TOML file and is overridden by `WEBS_*`
+    /// environment variables; unset settings keep the `common` defaults.
+    ///
+    /// ```json
+    /// "engine": { "free_list_trim_threshold": 100000, "extended_rdma_enabled": false }
+    /// ```
+    #[serde(default)]
+    pub engine: Option<EngineOverrides>,
+    // `slots` (symbolic slot declarations, see `SlotDecl`) is read and
+    // resolved by `slot_names::resolve_slot_names` on the raw JSON before it
+    // is deserialised, so it has no field here: every node field below
+    // already holds a plain index.
+    /// Directory of the node result cache used by `cache: true` nodes.
+    /// Defaults to `.dag_cache` in the working directory; bound its size with
+    /// `host cache gc`.
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+    /// Check page accounting (`host shm fsck`) after every run and fail the
+    /// DAG on a chain fault, a doubly-owned page or a leaked page.  Meant for
+    /// debugging `reset`-mode workloads: a leak shows up on the run that
+    /// introduced it instead of as slow SHM growth many runs later.
+    #[serde(default)]
+    pub fsck: bool,
+    /// Optional spill tier for cold stream slots under memory pressure.
+    ///
+    /// ```json
+    /// "spill": { "dir": "/mnt/nvme/webs-spill", "high_watermark_mib": 6144 }
+    /// ```
+    #[serde(default)]
+    pub spill: Option<SpillConfig>,
+    /// File I/O backend.  Binary file-set inputs and plain outputs are then
+    /// batched on one shared io_uring.
+    ///
+    /// ```json
+    /// "io": { "backend": "uring", "queue_depth": 256 }
+    /// ```
+    #[serde(default)]
+    pub io: Option<IoConfig>,
     pub nodes: Vec<DagNode>,
 }
 
@@ -126,6 +275,17 @@ pub enum NodeKind {
     Persist(PersistParams),
     /// Lightweight: persist a single stream slot or a single shared-state entry.
     Watch(WatchParams),
+    /// Resolve pending `write_shared_state` writes: pick one winner per key
+    /// with a `ConsumptionPolicy`, commit it to the Registry, recycle the rest.
+    /// Place it after the wave that writes the keys and before their readers.
+    ///
+    /// ```json
+    /// { "kind": { "ResolveSharedState": {
+    ///     "default": "LastWriteWins",
+    ///     "rules": [ { "pattern": "vote_*", "policy": "MajorityWins" } ],
+    ///     "report": "out/resolutions.jsonl" } } }
+    /// ```
+    ResolveSharedState(ResolveSharedStateParams),
     /// Multi-round streaming pipeline: source → filter → transform → sink,
     /// each stage advancing its own SHM-atomic cursor each round.
     StreamPipeline(StreamPipelineParams),
@@ -220,6 +380,24 @@ pub enum NodeKind {
 pub struct WasmCallParams {
     pub func: String,
     pub arg: u32,
+    /// Memoize this call in the DAG result cache (see `result_cache`).  The
+    /// key covers the module bytes, `func`, `arg` and the content of
+    /// `inputs`; on a hit the records the call appended to `outputs` are
+    /// restored instead of running the guest.  Requires `outputs`.
+    ///
+    /// ```json
+    /// { "WasmVoid": { "func": "wc_map", "arg": 10, "cache": true,
+    ///                 "inputs":  [{ "slot": 10,  "slot_kind": "Stream" }],
+    ///                 "outputs": [{ "slot": 110, "slot_kind": "Stream" }] } }
+    /// ```
+    #[serde(default)]
+    pub cache: bool,
+    /// Slots the call reads; their records are hashed into the cache key.
+    #[serde(default)]
+    pub inputs: Vec<CacheSlot>,
+    /// Slots the call writes; stored on a miss, restored on a hit.
+    #[serde(default)]
+    pub outputs: Vec<CacheSlot>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -230,6 +408,24 @@ pub struct PyFuncParams {
     /// Optional second argument for two-parameter workload functions.
     #[serde(default)]
     pub arg2: Option<u32>,
+    /// Memoize this call; same semantics as `WasmCallParams::cache`, with the
+    /// Python workload sources (the `*.py` files beside `python_script`)
+    /// standing in for the module bytes.
+    #[serde(default)]
+    pub cache: bool,
+    #[serde(default)]
+    pub inputs: Vec<CacheSlot>,
+    #[serde(default)]
+    pub outputs: Vec<CacheSlot>,
+}
+
+/// A slot read or written by a cached node.  Only the declared slots are
+/// keyed and restored — a node that also mutates named atomics or shared
+/// state must not be cached.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CacheSlot {
+    pub slot: usize,
+    pub slot_kind: RemoteSlotKind,
 }
 
 #[derive(Debug, Deserialize)]
@@ -266,17 +462,124 @@ pub enum ShufflePolicy {
     Broadcast,
 }
 
-/// Lightweight single-item watch: persists exactly one stream slot or one
-/// named shared-state entry to the given output file path.
-/// Set exactly one of `stream` or `shared`; the other must be absent.
+/// Lightweight single-item watch: persists exactly one stream slot, one
+/// named shared-state entry or one key prefix of the key-value store to the
+/// given output file path.
+/// Set exactly one of `stream`, `shared` or `kv`; the others must be absent.
 #[derive(Debug, Deserialize)]
 pub struct WatchParams {
     /// Exact output file path (parent directory is created if absent).
     pub output: String,
-    /// Stream slot ID to persist (mutually exclusive with `shared`).
+    /// Stream slot ID to persist (mutually exclusive with `shared` and `kv`).
     pub stream: Option<usize>,
-    /// Named shared-state entry to persist (mutually exclusive with `stream`).
+    /// Named shared-state entry to persist (mutually exclusive with `stream`
+    /// and `kv`).
     pub shared: Option<String>,
+    /// Key prefix of the key-value store entries to persist, one
+    /// `key<TAB>value` line each (`""` for the whole store).
+    pub kv: Option<String>,
+    /// Typed-record schema descriptor of the stream's records, e.g.
+    /// `"WordCount{word:str,count:u64}"`.  Records are decoded and written as
+    /// JSON; one of another schema fails the node.
+    #[serde(default)]
+    pub schema: Option<RecordSchema>,
+}
+
+/// Parameters for a `ResolveSharedState` node.
+#[derive(Debug, Deserialize)]
+pub struct ResolveSharedStateParams {
+    /// Policy for keys no rule matches.
+    #[serde(default)]
+    pub default: ConsumptionPolicyKind,
+    /// Per-key overrides, tried in order; the first matching `pattern` wins.
+    #[serde(default)]
+    pub rules: Vec<ConsumptionRule>,
+    /// When set, one JSON line per resolved key (`key`, `policy`,
+    /// `candidates`, `winner`, `bytes`) is appended to this file.
+    pub report: Option<String>,
+}
+
+/// Selects the policy for registry keys matching `pattern` (shell-style
+/// glob, as in `Input` file sets: `*`, `?`, `[a-z]`).
+#[derive(Debug, Deserialize)]
+pub struct ConsumptionRule {
+    pub pattern: String,
+    pub policy: ConsumptionPolicyKind,
+}
+
+/// The built-in `ConsumptionPolicy` implementations (see `policy/consumption.rs`
+/// and `policy/merge.rs`).  Winner policies are plain strings; merge policies
+/// take an object, e.g. `{ "SumMerge": { "int": "u64" } }`.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum ConsumptionPolicyKind {
+    /// Highest `writer_id`.
+    MaxIdWins,
+    /// Lowest `writer_id`.
+    MinIdWins,
+    /// Most frequent payload.
+    MajorityWins,
+    /// The most recent write.
+    #[default]
+    LastWriteWins,
+    /// Largest payload; `writer_id` breaks ties.
+    LargestPayloadWins,
+    /// Element-wise sum of little-endian integer arrays (counters, histograms).
+    SumMerge {
+        #[serde(default)]
+        int: IntType,
+    },
+    /// Element-wise minimum of little-endian integer arrays.
+    MinMerge {
+        #[serde(default)]
+        int: IntType,
+    },
+    /// Element-wise maximum of little-endian integer arrays.
+    MaxMerge {
+        #[serde(default)]
+        int: IntType,
+    },
+    /// Sorted union of `delimiter`-separated items (one byte, default `"\n"`);
+    /// `multiset` keeps every occurrence.
+    SetUnion {
+        #[serde(default = "default_delimiter")]
+        delimiter: String,
+        #[serde(default)]
+        multiset: bool,
+    },
+    /// Writes joined with `separator`, ordered by writer id.
+    Concat {
+        #[serde(default)]
+        separator: String,
+    },
+    /// Fold with guest export `func(slot) -> u64`: the runner loads the inputs
+    /// into I/O slot `slot` as records (origin = writer id, the committed value
+    /// first as `u32::MAX`), calls `func` in-process, and commits the fat-pointer
+    /// result; `0` leaves the committed value as it is.
+    WasmMerge {
+        func: String,
+        slot: u32,
+    },
+}
+
+fn default_delimiter() -> String { "\n".to_string() }
+
+impl ConsumptionPolicyKind {
+    /// Variant name, as written in the DAG JSON.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConsumptionPolicyKind::MaxIdWins          => "MaxIdWins",
+            ConsumptionPolicyKind::MinIdWins          => "MinIdWins",
+            ConsumptionPolicyKind::MajorityWins       => "MajorityWins",
+            ConsumptionPolicyKind::LastWriteWins      => "LastWriteWins",
+            ConsumptionPolicyKind::LargestPayloadWins => "LargestPayloadWins",
+            ConsumptionPolicyKind::SumMerge { .. }    => "SumMerge",
+            ConsumptionPolicyKind::MinMerge { .. }    => "MinMerge",
+            ConsumptionPolicyKind::MaxMerge { .. }    => "MaxMerge",
+            ConsumptionPolicyKind::SetUnion { .. }    => "SetUnion",
+            ConsumptionPolicyKind::Concat { .. }      => "Concat",
+            ConsumptionPolicyKind::WasmMerge { .. }   => "WasmMerge",
+        }
+    }
 }
 
 /// One stage in a `StreamPipeline`.
@@ -410,6 +713,9 @@ pub struct PersistParams {
     /// Save all Manager-committed shared-state entries from the registry.
     #[serde(default)]
     pub shared_state: bool,
+    /// Save every key-value store entry to `kv.txt`.
+    #[serde(default)]
+    pub kv: bool,
     /// Restricted/synchronous mode: snapshot AND write the files inline (with
     /// fsync) before this node returns, so the checkpoint is durable on disk
     /// before any downstream stage starts. Default `false` = async background
@@ -456,7 +762,17 @@ pub struct OwnedDispatchParams {
 
 /// Read all records from a stream slot (written by the guest via
 /// `ShmApi::write_output` / `write_output_to`) and save them to `path`,
-/// one record per line.
+/// one record per line by default.
+///
+/// ```json
+/// { "type": "Output", "path": "out/result.jsonl.zst", "slot": 3,
+///   "framing": "jsonl", "compression": "auto",
+///   "partition": { "type": "KeyHash", "files": 8, "key_delimiter": "\t" } }
+/// ```
+///
+/// Files are committed atomically (temp file + rename) unless `atomic` is
+/// `false`; a partitioned output becomes visible only once every part is
+/// written.
 #[derive(Debug, Deserialize)]
 pub struct OutputParams {
     /// Destination file path (parent directories are created if absent).
@@ -477,6 +793,45 @@ pub struct OutputParams {
     /// Extra records beyond `paths.len()` wrap (`i % paths.len()`).
     #[serde(default)]
     pub split_records: bool,
+    /// Record framing on disk: `newline` (default), `length_prefixed`
+    /// (u32 LE length + payload), `raw` (concatenated) or `jsonl`.
+    #[serde(default)]
+    pub framing: OutputFraming,
+    /// `auto` (default) compresses by extension: `.gz` → gzip, `.zst` → zstd.
+    #[serde(default)]
+    pub compression: Compression,
+    /// Split the slot across N files by record index or key hash.  Part paths
+    /// substitute `{part}` in `path`, or insert `.part-NNNNN` before the
+    /// extension.
+    #[serde(default)]
+    pub partition: Option<OutputPartition>,
+    /// Write via temp file + rename so a crashed run never leaves a
+    /// half-written result (default `true`).
+    #[serde(default = "default_true")]
+    pub atomic: bool,
+    /// Write with O_DIRECT, bypassing the page cache — for large results
+    /// that should not evict the working set (default `false`).  Not
+    /// applied to `split_records` files.
+    #[serde(default)]
+    pub direct: bool,
+    /// Typed-record schema descriptor: decode every record and write it as
+    /// compact JSON (pair with `framing: "jsonl"`).  A record of another
+    /// schema fails the node.  Not applied to `split_records` files.
+    #[serde(default)]
+    pub schema: Option<RecordSchema>,
+}
+
+impl OutputParams {
+    pub fn options(&self) -> OutputOptions {
+        OutputOptions {
+            framing: self.framing,
+            compression: self.compression,
+            partition: self.partition.clone(),
+            atomic: self.atomic,
+            direct: self.direct,
+            schema: self.schema.clone(),
+        }
+    }
 }
 
 fn default_true() -> bool { true }
@@ -520,9 +875,10 @@ pub struct StreamOutputParams {
 /// Explicitly free a set of stream and/or I/O slots, returning their page
 /// chains to the SHM pool.  Use this between sequential pipeline runs that
 /// reuse the same fixed slot numbers, so the second run starts with empty slots.
+/// With `kv`, also compacts the key-value store.
 ///
 /// ```json
-/// { "kind": { "FreeSlots": { "stream": [20, 30, 40], "io": [10] } } }
+/// { "kind": { "FreeSlots": { "stream": [20, 30, 40], "io": [10], "kv": true } } }
 /// ```
 #[derive(Debug, Deserialize)]
 pub struct FreeSlotsParams {
@@ -532,6 +888,11 @@ pub struct FreeSlotsParams {
     /// I/O slot IDs whose page chains should be freed.
     #[serde(default)]
     pub io: Vec<usize>,
+    /// Compact the key-value store: free shadowed entries, tombstones and
+    /// empty directory pages.  No guest may use the store meanwhile, so list
+    /// every node that does in this node's `deps`.
+    #[serde(default)]
+    pub kv: bool,
 }
 
 /// Load a file into a stream slot so the guest can consume it via
@@ -571,6 +932,12 @@ pub struct InputParams {
     /// one path per run is loaded.  See `paths` for the full matrix.
     #[serde(default)]
     pub binary: bool,
+    /// Only meaningful when `binary: true`.  Overlay each file read-only onto
+    /// its slot pages instead of copying it (see `mapped_input`).  Files must
+    /// stay unmodified until the slot is freed.  Not supported with hugetlbfs
+    /// pages, an RDMA mesh or `python_wasm`.
+    #[serde(default)]
+    pub zero_copy: bool,
     /// Only meaningful when `binary: true` and `paths` has multiple entries.
     /// When `false` (default) all paths are loaded every run.
     /// When `true` one path is loaded per run, cycling via `paths[run % paths.len()]`.
@@ -593,6 +960,34 @@ pub struct InputParams {
     /// load paths; ignored when `chunk_bytes` or `binary` is set.
     #[serde(default)]
     pub slice: Option<[f64; 2]>,
+    /// Record framing of the (decompressed) file.  Defaults to `Lines`.
+    /// Honoured by the plain, prefetch, `slice` and `chunk_bytes` paths; every
+    /// cut lands on a record boundary of this format.  Ignored when `binary`.
+    ///
+    /// ```json
+    /// "format": { "type": "Csv", "header": true }
+    /// "format": { "type": "Fixed", "record_bytes": 100 }
+    /// ```
+    #[serde(default)]
+    pub format: RecordFormat,
+    /// `"auto"` (default, sniffs the magic bytes), `"none"`, `"gzip"` or
+    /// `"zstd"`.  Compressed files are decompressed as a stream while loading.
+    #[serde(default)]
+    pub compression: Compression,
+    /// **Directory / glob input.**  Load a set of files selected by directory
+    /// or glob pattern instead of `path`/`paths` — one binary record per file
+    /// with `binary: true`, otherwise each file framed by `format`.  `slice`
+    /// shards the *file list* (by count or total bytes, see `shard_by`), so a
+    /// partitioned job reads each file on exactly one node; `cycle: true`
+    /// loads one file of the (sharded) set per run.  Not combinable with
+    /// `chunk_bytes`.
+    ///
+    /// ```json
+    /// "files": { "path": "data/imgs/*.png", "sort": "name", "limit": 64 }
+    /// "files": { "path": "logs", "recursive": true, "include": ["*.log.gz"], "shard_by": "bytes" }
+    /// ```
+    #[serde(default)]
+    pub files: Option<FileSet>,
 }
 
 /// One stage in a `WasmGrouping`.
@@ -732,6 +1127,7 @@ pub enum RemoteProtocol {
 }
 
 /// Which SHM slot area a `RemoteSend` / `RemoteRecv` node operates on.
+/// Also used as the `kind` of a named slot declaration (`SlotDecl`).
 #[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "PascalCase")]
 pub enum RemoteSlotKind {
@@ -741,6 +1137,93 @@ pub enum RemoteSlotKind {
     Io,
 }
 
+/// Lifetime of a named slot declared in the DAG `slots` map.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotLifetime {
+    /// Reclaimed by the run loop once its last consumer finishes (default).
+    #[default]
+    Transient,
+    /// Exempt from per-run reclamation — the named equivalent of listing the
+    /// slot index in `persist_slots`.  Only valid for `Stream` slots.
+    Persist,
+}
+
+/// Declaration of a symbolic slot name in the DAG `slots` map.
+///
+/// ```json
+/// { "kind": "Stream", "lifetime": "persist", "page_class": "64k" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlotDecl {
+    /// Area the name resolves into.  Using the name in a field of the other
+    /// area (e.g. an `Io` name as an `Aggregate` upstream) is a load error.
+    pub kind: RemoteSlotKind,
+    /// Defaults to `transient`.
+    #[serde(default)]
+    pub lifetime: SlotLifetime,
+    /// Size of the pages the slot's chain is built from: `"4k"` (default),
+    /// `"64k"` or `"2m"`.  Larger pages suit bulk slots (big records,
+    /// `RemoteSend`): fewer page headers, chain links and RDMA SGEs.
+    #[serde(default)]
+    pub page_class: Option<String>,
+    /// Makes a `Stream` slot a bounded ring of `pages` pages: consumed pages
+    /// are reused and a producer that gets ahead of its slowest consumer
+    /// blocks.  Only the guest ring API reads and writes it.
+    #[serde(default)]
+    pub ring: Option<RingDecl>,
+    /// Names of independent readers of a `Stream` slot, each with its own
+    /// cursor in SHM (`ShmApi::subscription_recv`).  A page is freed once
+    /// every subscriber has read past it.
+    #[serde(default)]
+    pub subscribers: Vec<String>,
+}
+
+/// Size of a ring slot declared in the DAG `slots` map.
+///
+/// ```json
+/// { "kind": "Stream", "ring": { "pages": 16, "consumers": 2 } }
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct RingDecl {
+    /// Pages in the ring (`2..=common::RING_MAX_PAGES`).
+    pub pages: u32,
+    /// Consumers that each read every record; defaults to 1.
+    #[serde(default = "default_ring_consumers")]
+    pub consumers: u32,
+}
+
+fn default_ring_consumers() -> u32 { 1 }
+
+/// One entry of the DAG `rings` list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlotRing {
+    /// Stream slot index.
+    pub slot: u32,
+    pub pages: u32,
+    #[serde(default = "default_ring_consumers")]
+    pub consumers: u32,
+}
+
+/// One entry of the DAG `subscriptions` list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlotSubscriptions {
+    /// Stream slot index.
+    pub slot: u32,
+    /// Subscriber names, `1..=common::SUBSCRIBER_MAX` of them, each
+    /// `1..=common::SUBSCRIBER_NAME_LEN` bytes.
+    pub subscribers: Vec<String>,
+}
+
+/// One entry of the DAG `page_classes` list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlotPageClass {
+    pub kind: RemoteSlotKind,
+    pub slot: u32,
+    /// `"4k"`, `"64k"` or `"2m"`.
+    pub class: String,
+}
+
 /// Parameters for the `RemoteSend` node.
 #[derive(Debug, Deserialize)]
 pub struct RemoteSendParams {
@@ -1,6 +1,7 @@
 use anyhow::{anyhow, Result};
 use std::path::Path;
 use super::types::{DagNode, NodeKind};
+use crate::runtime::remote::atomics::worker_env;
 
 // ─── One-shot WASM subprocess helpers ────────────────────────────────────────
 
@@ -26,6 +27,7 @@ pub(super) fn spawn_wasm_subprocess(
         .arg(func)
         .arg(ret_type)
         .arg(arg.to_string())
+        .envs(worker_env())
         .spawn()
         .map_err(|e| anyhow!("[{}] failed to spawn WASM worker: {}", node.id, e))
 }
@@ -126,6 +128,7 @@ impl WasmLoopWorker {
             .map_err(|e| anyhow!("cannot find current exe: {}", e))?;
         let mut child = std::process::Command::new(exe)
             .args(["wasm-loop", shm_path, wasm_path, func])
+            .envs(worker_env())
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
             .spawn()
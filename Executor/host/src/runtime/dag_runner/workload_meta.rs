@@ -0,0 +1,251 @@
+//! Checks the DAG's guest calls against the module's workload metadata.
+//!
+//! Every `#[workload]` export (see `guest-macros`) records its signature as one
+//! JSON line in the module's `common::WORKLOAD_META_SECTION` custom section:
+//!
+//! ```json
+//! {"name":"wc_map","args":[{"name":"slot","type":"u32"}],"ret":"void",
+//!  "consumes":[{"kind":"Stream","slot":"slot"}],
+//!  "produces":[{"kind":"Stream","slot":"slot+100"}]}
+//! ```
+//!
+//! Before the SHM region is formatted, [`check_module`] matches every node that
+//! calls into the guest — `WasmVoid` / `WasmU32` / `WasmFatPtr`, the stages of
+//! `StreamPipeline` and `WasmGrouping`, and `WasmMerge` policies — against that
+//! signature: the argument count, the return kind the node decodes, and every
+//! consumed or produced slot the arguments resolve to must be in range.  A
+//! mismatch used to surface only when the worker subprocess failed its typed
+//! export lookup, or not at all when a slot was off.
+//!
+//! Hand-written `#[no_mangle]` exports carry no metadata and are not checked;
+//! neither are precompiled `.cwasm` modules, which have no custom sections.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use common::{workload_meta_lines, EngineConfig, WORKLOAD_META_SECTION};
+
+use super::types::{ConsumptionPolicyKind, Dag, NodeKind, RemoteSlotKind};
+
+/// One workload's metadata line.
+#[derive(Debug, Deserialize)]
+struct WorkloadMeta {
+    name: String,
+    args: Vec<WorkloadArg>,
+    /// `"void"`, `"u32"` or `"fatptr"`.
+    ret: String,
+    #[serde(default)]
+    consumes: Vec<WorkloadSlot>,
+    #[serde(default)]
+    produces: Vec<WorkloadSlot>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkloadArg {
+    name: String,
+    /// `"u32"` or `"fanout"` (packed `base | n << 16`).
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkloadSlot {
+    kind: RemoteSlotKind,
+    /// `"<arg>"`, `"<arg>+<n>"` or `"<n>"`.
+    slot: String,
+}
+
+/// Parses the workload metadata of the WebAssembly binary `module`, keyed by
+/// export name.  Empty for modules without the section.
+fn read_workload_meta(module: &[u8]) -> Result<HashMap<String, WorkloadMeta>> {
+    let mut metas = HashMap::new();
+    for line in workload_meta_lines(module) {
+        let meta: WorkloadMeta = serde_json::from_str(line)
+            .map_err(|e| anyhow!("bad {} entry {:?}: {}", WORKLOAD_META_SECTION, line, e))?;
+        metas.insert(meta.name.clone(), meta);
+    }
+    Ok(metas)
+}
+
+/// Reads `wasm_path` and checks `dag` against its workload metadata.  An
+/// unreadable module is left for `load_guest_module` to report.
+pub(super) fn check_module(dag: &Dag, wasm_path: &str, cfg: &EngineConfig) -> Result<()> {
+    let Ok(module) = std::fs::read(wasm_path) else { return Ok(()) };
+    let metas = read_workload_meta(&module)?;
+    if metas.is_empty() {
+        return Ok(());
+    }
+    println!("[DAG] {} workload signature(s) in {}", metas.len(), wasm_path);
+    validate_workload_calls(dag, &metas, cfg)
+}
+
+/// Every guest call in `node`: `(label, func, return kind the node decodes,
+/// argument values)`.  `None` is an argument the host fills in at run time
+/// (a `StreamPipeline` stage's round number).
+fn guest_calls(kind: &NodeKind) -> Vec<(&'static str, &str, &'static str, Vec<Option<u32>>)> {
+    match kind {
+        NodeKind::WasmVoid(p)   => vec![("WasmVoid", p.func.as_str(), "void", vec![Some(p.arg)])],
+        NodeKind::WasmU32(p)    => vec![("WasmU32", p.func.as_str(), "u32", vec![Some(p.arg)])],
+        NodeKind::WasmFatPtr(p) => vec![("WasmFatPtr", p.func.as_str(), "fatptr", vec![Some(p.arg)])],
+        NodeKind::StreamPipeline(p) => p.stages.iter()
+            .map(|s| ("StreamPipeline", s.func.as_str(), "void", vec![Some(s.arg0), s.arg1]))
+            .collect(),
+        NodeKind::WasmGrouping(p) => p.stages.iter()
+            .map(|s| ("WasmGrouping", s.func.as_str(), "void", vec![Some(s.arg0), Some(s.arg1)]))
+            .collect(),
+        NodeKind::ResolveSharedState(p) => std::iter::once(&p.default)
+            .chain(p.rules.iter().map(|r| &r.policy))
+            .filter_map(|k| match k {
+                ConsumptionPolicyKind::WasmMerge { func, slot } => {
+                    Some(("WasmMerge", func.as_str(), "fatptr", vec![Some(*slot)]))
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Value of a slot expression for the given arguments; `None` when it names a
+/// `fanout` argument or one the host fills in at run time.
+fn resolve_slot(expr: &str, meta: &WorkloadMeta, values: &[Option<u32>]) -> Option<u64> {
+    let (name, offset) = match expr.split_once('+') {
+        Some((name, offset)) => (name.trim(), offset.trim().parse::<u64>().ok()?),
+        None => (expr.trim(), 0),
+    };
+    if let Ok(n) = name.parse::<u64>() {
+        return Some(n + offset);
+    }
+    let i = meta.args.iter().position(|a| a.name == name && a.ty == "u32")?;
+    Some(values.get(i).copied()?? as u64 + offset)
+}
+
+fn validate_workload_calls(
+    dag: &Dag,
+    metas: &HashMap<String, WorkloadMeta>,
+    cfg: &EngineConfig,
+) -> Result<()> {
+    let mut errors: Vec<String> = Vec::new();
+
+    for node in &dag.nodes {
+        for (label, func, ret, values) in guest_calls(&node.kind) {
+            let Some(meta) = metas.get(func) else { continue };
+            if meta.args.len() != values.len() {
+                errors.push(format!(
+                    "node '{}' ({}): workload `{}` takes {} argument(s), the node passes {}",
+                    node.id, label, func, meta.args.len(), values.len()
+                ));
+                continue;
+            }
+            if meta.ret != ret {
+                errors.push(format!(
+                    "node '{}' ({}): workload `{}` returns {}, the node expects {}",
+                    node.id, label, func, meta.ret, ret
+                ));
+            }
+            let slots = meta.consumes.iter().map(|s| ("consumes", s))
+                .chain(meta.produces.iter().map(|s| ("produces", s)));
+            for (verb, s) in slots {
+                let Some(slot) = resolve_slot(&s.slot, meta, &values) else { continue };
+                let count = match s.kind {
+                    RemoteSlotKind::Stream => cfg.stream_slot_count as u64,
+                    RemoteSlotKind::Io     => cfg.io_slot_count as u64,
+                };
+                if slot >= count {
+                    errors.push(format!(
+                        "node '{}' ({}): workload `{}` {} {:?} slot {} = {} ≥ slot count ({})",
+                        node.id, label, func, verb, s.kind, s.slot, slot, count
+                    ));
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("DAG does not match the guest module:\n  {}", errors.join("\n  ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A module with a type section, an unrelated custom section and the
+    /// metadata split over two sections, as separate objects can leave it.
+    fn module_with(lines: &[&str]) -> Vec<u8> {
+        fn section(id: u8, body: &[u8]) -> Vec<u8> {
+            let mut out = vec![id];
+            let mut n = body.len();
+            while n >= 0x80 {
+                out.push((n as u8 & 0x7F) | 0x80);
+                n >>= 7;
+            }
+            out.push(n as u8);
+            out.extend_from_slice(body);
+            out
+        }
+        fn custom(name: &str, payload: &[u8]) -> Vec<u8> {
+            section(0, &[&[name.len() as u8][..], name.as_bytes(), payload].concat())
+        }
+        let mut m = b"\0asm\x01\0\0\0".to_vec();
+        m.extend(section(1, &[0x01, 0x60, 0x00, 0x00]));
+        m.extend(custom("name", b"\0\x01x"));
+        for line in lines {
+            m.extend(custom(WORKLOAD_META_SECTION, format!("{}\n", line).as_bytes()));
+        }
+        m
+    }
+
+    fn metas() -> HashMap<String, WorkloadMeta> {
+        read_workload_meta(&module_with(&[
+            r#"{"name":"wc_map","args":[{"name":"slot","type":"u32"}],"ret":"void","consumes":[{"kind":"Stream","slot":"slot"}],"produces":[{"kind":"Stream","slot":"slot+100"}]}"#,
+            r#"{"name":"src","args":[{"name":"out","type":"u32"},{"name":"round","type":"u32"}],"ret":"void","consumes":[],"produces":[{"kind":"Stream","slot":"round"},{"kind":"Io","slot":"1"}]}"#,
+        ]))
+        .unwrap()
+    }
+
+    fn dag(nodes: serde_json::Value) -> Dag {
+        serde_json::from_value(json!({ "shm_path": "/dev/shm/x", "nodes": nodes })).unwrap()
+    }
+
+    #[test]
+    fn reads_every_metadata_section() {
+        let m = metas();
+        assert_eq!(m.len(), 2);
+        assert_eq!(m["wc_map"].produces[0].slot, "slot+100");
+        assert_eq!(m["src"].args[1].name, "round");
+        assert!(read_workload_meta(b"not a module").unwrap().is_empty());
+        assert!(read_workload_meta(&module_with(&["{"])).is_err());
+    }
+
+    #[test]
+    fn matching_calls_pass_and_runtime_args_are_skipped() {
+        let d = dag(json!([
+            { "id": "m", "kind": { "WasmVoid": { "func": "wc_map", "arg": 10 } } },
+            { "id": "p", "kind": { "StreamPipeline": { "rounds": 2, "stages": [
+                { "func": "src", "arg0": 200, "arg1": null } ] } } },
+            { "id": "u", "kind": { "WasmU32": { "func": "hand_written", "arg": 0 } } }
+        ]));
+        validate_workload_calls(&d, &metas(), &EngineConfig::DEFAULT).unwrap();
+    }
+
+    #[test]
+    fn reports_arity_return_and_slot_errors() {
+        let stream_slots = EngineConfig::DEFAULT.stream_slot_count;
+        let d = dag(json!([
+            { "id": "a", "kind": { "WasmGrouping": { "stages": [
+                { "func": "wc_map", "arg0": 1, "arg1": 2 } ] } } },
+            { "id": "b", "kind": { "WasmFatPtr": { "func": "wc_map", "arg": 1 } } },
+            { "id": "c", "kind": { "WasmVoid": { "func": "wc_map", "arg": stream_slots - 50 } } }
+        ]));
+        let err = validate_workload_calls(&d, &metas(), &EngineConfig::DEFAULT).unwrap_err().to_string();
+        assert!(err.contains("node 'a' (WasmGrouping): workload `wc_map` takes 1 argument(s), the node passes 2"), "{}", err);
+        assert!(err.contains("node 'b' (WasmFatPtr): workload `wc_map` returns void, the node expects fatptr"), "{}", err);
+        assert!(err.contains("node 'c' (WasmVoid): workload `wc_map` produces Stream slot slot+100"), "{}", err);
+        assert!(!err.contains("consumes"), "{}", err);
+    }
+}
@@ -0,0 +1,239 @@
+//! Runtime engine configuration.
+//!
+//! The tunables in [`common::EngineConfig`] — paged-mode flip thresholds,
+//! free-list trim threshold, RDMA MR1/MR2 sizing, the extended-pool sizes,
+//! the region geometry (slot counts, arena sizes) and the feature switches —
+//! are resolved once per DAG run, in increasing precedence:
+//!
+//! 1. the compile-time constants ([`EngineConfig::DEFAULT`]);
+//! 2. a TOML file named by `WEBS_ENGINE_CONFIG`;
+//! 3. the DAG's `engine` block;
+//! 4. one `WEBS_<SETTING>` environment variable per setting
+//!    (e.g. `WEBS_FREE_LIST_TRIM_THRESHOLD=100000`).
+//!
+//! The TOML file and the `engine` block use the setting names as keys:
+//!
+//! ```toml
+//! extended_pool_enabled = true
+//! paged_mode_enter_num  = 9
+//! paged_mode_enter_den  = 10
+//! rdma_mr2_initial_size = 1073741824
+//! ```
+//!
+//! The DAG runner [`install`]s the result before formatting the region, and
+//! `format_shared_memory` stores it in `Superblock::engine`.  Worker
+//! subprocesses (`wasm-call`, `wasm-loop`) never see the DAG file; they
+//! [`adopt_from_region`] when they attach, so every process sharing a region
+//! runs with the same thresholds.  Code that needs a setting calls
+//! [`current`], which falls back to the defaults when nothing was installed
+//! (unit tests, `host shm` tools).
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use common::{EngineConfig, Superblock};
+
+/// Environment variable naming an engine-config TOML file.
+pub const ENGINE_CONFIG_ENV: &str = "WEBS_ENGINE_CONFIG";
+
+static ENGINE: OnceLock<EngineConfig> = OnceLock::new();
+
+/// The process's engine configuration.
+#[inline]
+pub fn current() -> &'static EngineConfig {
+    ENGINE.get().unwrap_or(&EngineConfig::DEFAULT)
+}
+
+/// Makes `cfg` the process configuration.  Fails if a different one was
+/// installed earlier — thresholds must not change under a running allocator.
+pub fn install(cfg: EngineConfig) -> Result<()> {
+    cfg.validate().map_err(|e| anyhow!("invalid engine config: {}", e))?;
+    let installed = ENGINE.get_or_init(|| cfg);
+    if *installed != cfg {
+        return Err(anyhow!("engine config already installed with different values"));
+    }
+    Ok(())
+}
+
+/// Installs the configuration stored in the Superblock of the region mapped
+/// at `splice_addr`.  Called by `setup_vma_environment` after the layout
+/// check, so the block is known to be at the expected offset.
+pub fn adopt_from_region(splice_addr: usize) -> Result<()> {
+    let sb = unsafe { &*(splice_addr as *const Superblock) };
+    install(sb.engine).map_err(|e| anyhow!("SHM engine config: {}", e))
+}
+
+// ─── Overrides ───────────────────────────────────────────────────────────────
+
+/// Declares [`EngineOverrides`] with one optional field per `EngineConfig`
+/// setting, plus the code that applies them and reads them from the
+/// environment — so the setting list is written down once.
+macro_rules! engine_settings {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        /// A partial [`EngineConfig`]: the DAG `engine` block and the TOML file.
+        /// Flags are booleans; everything else is an integer in the unit of
+        /// the corresponding `common` constant.
+        #[derive(Clone, Debug, Default, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        pub struct EngineOverrides {
+            $(pub $field: Option<$ty>,)*
+        }
+
+        impl EngineOverrides {
+            fn apply(&self, cfg: &mut EngineConfig) {
+                $(
+                    if let Some(v) = self.$field {
+                        cfg.$field = v.into();
+                    }
+                )*
+            }
+
+            /// Reads the `WEBS_<SETTING>` variables that are set.
+            fn from_env() -> Result<Self> {
+                let mut o = Self::default();
+                $(
+                    let name = env_name(stringify!($field));
+                    if let Ok(raw) = std::env::var(&name) {
+                        o.$field = Some(parse_setting(&name, &raw)?);
+                    }
+                )*
+                Ok(o)
+            }
+        }
+
+        /// `(setting, value)` pairs in declaration order, for [`print_effective`].
+        fn settings(cfg: &EngineConfig) -> Vec<(&'static str, String)> {
+            vec![$((stringify!($field), cfg.$field.to_string())),*]
+        }
+    };
+}
+
+engine_settings! {
+    extended_pool_enabled: bool,
+    extended_rdma_enabled: bool,
+    paged_mode_enter_num: u32,
+    paged_mode_enter_den: u32,
+    paged_mode_exit_num: u32,
+    paged_mode_exit_den: u32,
+    huge_pages: u32,
+    stream_slot_count: u32,
+    io_slot_count: u32,
+    barrier_count: u32,
+    registry_size: u32,
+    atomic_arena_size: u32,
+    log_arena_size: u32,
+    free_list_trim_threshold: u64,
+    rdma_mr1_budget: u64,
+    rdma_mr2_initial_size: u64,
+    mr2_idle_timeout_nanos: u64,
+    global_pool_initial_size: u64,
+    flip_seed_slots: u64,
+}
+
+fn env_name(setting: &str) -> String {
+    format!("WEBS_{}", setting.to_ascii_uppercase())
+}
+
+fn parse_setting<T: FromStr>(name: &str, raw: &str) -> Result<T> {
+    raw.trim().parse().map_err(|_| anyhow!("{}: cannot parse '{}'", name, raw))
+}
+
+impl EngineOverrides {
+    fn from_toml_file(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read engine config '{}': {}", path, e))?;
+        toml::from_str(&text).map_err(|e| anyhow!("invalid engine config '{}': {}", path, e))
+    }
+}
+
+// ─── Resolution ──────────────────────────────────────────────────────────────
+
+/// Resolves the effective configuration: defaults, then the `WEBS_ENGINE_CONFIG`
+/// TOML file, then `dag_block`, then `WEBS_*` variables.
+pub fn resolve(dag_block: Option<&EngineOverrides>) -> Result<EngineConfig> {
+    let mut cfg = EngineConfig::DEFAULT;
+    if let Ok(path) = std::env::var(ENGINE_CONFIG_ENV) {
+        EngineOverrides::from_toml_file(&path)?.apply(&mut cfg);
+    }
+    if let Some(block) = dag_block {
+        block.apply(&mut cfg);
+    }
+    EngineOverrides::from_env()?.apply(&mut cfg);
+    cfg.validate().map_err(|e| anyhow!("invalid engine config: {}", e))?;
+    Ok(cfg)
+}
+
+/// Prints every setting, marking the ones that differ from the defaults.
+pub fn print_effective(cfg: &EngineConfig) {
+    let defaults = settings(&EngineConfig::DEFAULT);
+    println!("[Engine] effective configuration:");
+    for ((name, value), (_, default)) in settings(cfg).into_iter().zip(defaults) {
+        if value == default {
+            println!("[Engine]   {:<26} {}", name, value);
+        } else {
+            println!("[Engine]   {:<26} {}   (default {})", name, value, default);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_apply_on_top_of_defaults() {
+        let block: EngineOverrides = serde_json::from_str(
+            r#"{ "extended_rdma_enabled": false, "free_list_trim_threshold": 1000 }"#,
+        ).unwrap();
+        let mut cfg = EngineConfig::DEFAULT;
+        block.apply(&mut cfg);
+        assert_eq!(cfg.extended_rdma_enabled, 0);
+        assert_eq!(cfg.free_list_trim_threshold, 1000);
+        assert_eq!(cfg.rdma_mr1_budget, EngineConfig::DEFAULT.rdma_mr1_budget);
+    }
+
+    #[test]
+    fn toml_uses_the_same_keys() {
+        let o: EngineOverrides = toml::from_str("paged_mode_enter_num = 9\nflip_seed_slots = 16\n").unwrap();
+        assert_eq!((o.paged_mode_enter_num, o.flip_seed_slots), (Some(9), Some(16)));
+        assert!(toml::from_str::<EngineOverrides>("page_size = 8192\n").is_err());
+    }
+
+    #[test]
+    fn flapping_thresholds_are_rejected() {
+        let mut cfg = EngineConfig::DEFAULT;
+        cfg.paged_mode_exit_num = cfg.paged_mode_enter_num;
+        cfg.paged_mode_exit_den = cfg.paged_mode_enter_den;
+        assert!(cfg.validate().is_err());
+        assert_eq!(env_name("rdma_mr1_budget"), "WEBS_RDMA_MR1_BUDGET");
+    }
+
+    #[test]
+    fn hugetlbfs_excludes_the_extended_pool() {
+        let mut cfg = EngineConfig { huge_pages: common::HUGE_PAGES_HUGETLBFS, ..EngineConfig::DEFAULT };
+        assert!(cfg.validate().is_err());
+        cfg.extended_pool_enabled = 0;
+        assert!(cfg.validate().is_ok());
+        cfg.huge_pages = 3;
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn geometry_settings_are_validated() {
+        let o: EngineOverrides = toml::from_str("stream_slot_count = 256
+log_arena_size = 65536
+").unwrap();
+        let mut cfg = EngineConfig::DEFAULT;
+        o.apply(&mut cfg);
+        assert!(cfg.validate().is_ok());
+        assert_eq!(common::ShmLayout::for_config(&cfg).stream_slot_count, 256);
+
+        cfg.io_slot_count = 1;
+        assert!(cfg.validate().is_err(), "the output I/O slot must exist");
+        cfg.io_slot_count = EngineConfig::DEFAULT.io_slot_count;
+        cfg.registry_size = 1000;
+        assert!(cfg.validate().is_err(), "arena sizes are whole pages");
+    }
+}
@@ -1,9 +1,10 @@
 //! Global pool — second, large backing file for paged-mode pages.
 //!
 //! The global pool is a host-side-only mmap that holds pages whose
-//! PageIds are `>= DIRECT_LIMIT`.  The pool starts at
-//! [`GLOBAL_POOL_INITIAL_SIZE`] bytes and doubles on demand, capped at
-//! [`GLOBAL_POOL_HARD_LIMIT`].
+//! PageIds are `>= DIRECT_LIMIT`.  The pool starts at the
+//! `global_pool_initial_size` engine setting (default
+//! `common::GLOBAL_POOL_INITIAL_SIZE`, 256 MiB) and doubles on demand,
+//! capped at [`GLOBAL_POOL_HARD_LIMIT`].
 //!
 //! ## Growth strategy — reserve huge, commit incrementally
 //!
@@ -27,15 +28,12 @@
 //! simple `base + (id - DIRECT_LIMIT)` and stays valid forever.
 
 use anyhow::{anyhow, Context, Result};
-use common::{DIRECT_LIMIT, PAGE_SIZE, PageId};
+use common::{DIRECT_LIMIT, HUGE_PAGES_OFF, HUGE_PAGE_SIZE, PAGE_SIZE, PageId};
 use nix::sys::mman::{madvise, mmap, munmap, MapFlags, MmapAdvise, ProtFlags};
 use std::fs::{File, OpenOptions};
 use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 
-/// Initial committed size of a new global pool (256 MiB).
-pub const GLOBAL_POOL_INITIAL_SIZE: usize = 256 * 1024 * 1024;
-
 /// Hard upper bound for a global pool's total committed size — also
 /// the size of the virtual-address reservation.  8 GiB by default,
 /// which gives 5 doublings from the 256 MiB starting size.  Raising
@@ -104,20 +102,11 @@ impl GlobalPool {
         file.set_len(initial as u64)
             .context("sizing global pool backing file")?;
 
-        // 2. Reserve the full hard-limit VA range as PROT_NONE.
-        //    Linux treats this as a pure virtual reservation until we
-        //    overlay pages.  No physical memory is committed here.
-        let base = unsafe {
-            mmap(
-                None,
-                NonZeroUsize::new(GLOBAL_POOL_HARD_LIMIT).unwrap(),
-                ProtFlags::PROT_NONE,
-                MapFlags::MAP_PRIVATE | MapFlags::MAP_ANONYMOUS,
-                None::<&File>,
-                0,
-            )
-            .context("reserving global pool virtual address range")?
-        } as *mut u8;
+        // 2. Reserve the full hard-limit VA range as PROT_NONE, huge-page
+        //    aligned.  Linux treats this as a pure virtual reservation
+        //    until we overlay pages.  No physical memory is committed here.
+        let base = crate::shm::reserve_huge_aligned(GLOBAL_POOL_HARD_LIMIT)
+            .context("reserving global pool virtual address range")?;
 
         // 3. Overlay the backing file on the first `initial` bytes.
         unsafe {
@@ -131,6 +120,7 @@ impl GlobalPool {
             )
             .context("overlaying global pool backing file")?;
         }
+        crate::shm::advise_huge_pages(base as usize, initial)?;
 
         Ok(Self {
             path: path.to_path_buf(),
@@ -219,6 +209,9 @@ impl GlobalPool {
     /// idle and we flip back to direct mode, `Drop` handles the
     /// big-release path by unmapping the entire reservation.
     pub fn trim_freelist(&mut self) -> usize {
+        if crate::shm::huge_pages() != HUGE_PAGES_OFF {
+            return self.trim_free_extents();
+        }
         let mut advised = 0usize;
         for &id in &self.freelist {
             let offset = (id - DIRECT_LIMIT) as usize;
@@ -237,6 +230,24 @@ impl GlobalPool {
         advised
     }
 
+    /// Huge-page variant of [`Self::trim_freelist`]: a 4 KiB
+    /// `MADV_DONTNEED` would split a huge page, so only extents whose
+    /// pages are all on the freelist are advised.
+    fn trim_free_extents(&mut self) -> usize {
+        let offsets: Vec<u64> = self.freelist.iter().map(|&id| id - DIRECT_LIMIT).collect();
+        let mut advised = 0usize;
+        for start in crate::shm::whole_huge_extents(&offsets) {
+            if start as usize + HUGE_PAGE_SIZE as usize > self.committed {
+                continue;
+            }
+            let addr = unsafe { self.base.add(start as usize) } as *mut libc::c_void;
+            if unsafe { madvise(addr, HUGE_PAGE_SIZE as usize, MmapAdvise::MADV_DONTNEED) }.is_ok() {
+                advised += (HUGE_PAGE_SIZE / PAGE_SIZE) as usize;
+            }
+        }
+        advised
+    }
+
     /// Double the committed region, capped at `GLOBAL_POOL_HARD_LIMIT`.
     fn expand(&mut self) -> Result<()> {
         let new_committed = (self.committed * 2).min(GLOBAL_POOL_HARD_LIMIT);
@@ -265,6 +276,7 @@ impl GlobalPool {
             )
             .context("overlaying grown global pool region")?;
         }
+        crate::shm::advise_huge_pages(overlay_addr, overlay_len)?;
 
         self.committed = new_committed;
         Ok(())
@@ -33,7 +33,8 @@ use anyhow::{anyhow, Result};
 
 use common::{DIRECT_LIMIT, PAGE_SIZE, PageId, ShmOffset, Superblock};
 
-use global_pool::{GlobalPool, GLOBAL_POOL_INITIAL_SIZE};
+use global_pool::GlobalPool;
+use crate::runtime::engine_config;
 use resolution_buffer::ResolutionBuffer;
 
 /// Number of wasm32-window slots to reserve at flip time as initial
@@ -42,8 +43,8 @@ use resolution_buffer::ResolutionBuffer;
 /// to the resolution buffer).  Without this seeding, the first
 /// `alloc_paged_page` would fail with "no free slots" because the
 /// buffer starts empty and there's no prior direct-free activity to
-/// populate it.
-pub const FLIP_SEED_SLOTS: usize = 4096;
+/// populate it.  Default of the `flip_seed_slots` engine setting.
+pub const FLIP_SEED_SLOTS: usize = common::FLIP_SEED_SLOTS as usize;
 
 /// Per-subprocess extended-pool state.
 ///
@@ -99,6 +100,13 @@ impl ExtendedPool {
         self.last_bump
     }
 
+    /// Release the physical backing of the global pool's freelist (see
+    /// [`GlobalPool::trim_freelist`]).  Returns the number of pages advised;
+    /// 0 while `Direct`.
+    pub fn trim_global_freelist(&mut self) -> usize {
+        self.global_pool.as_mut().map_or(0, |g| g.trim_freelist())
+    }
+
     /// Update the pool's view of the direct bump pointer and, if the
     /// 80% threshold has now been crossed, flip to `Paged` mode.
     /// Idempotent — repeated calls past the threshold while already
@@ -121,13 +129,14 @@ impl ExtendedPool {
 
     /// Transition from `Direct` to `Paged`.  Constructs the
     /// `GlobalPool` at `/dev/shm/webs-global-<pid>` with the initial
-    /// committed size from `global_pool::GLOBAL_POOL_INITIAL_SIZE`,
+    /// committed size from the `global_pool_initial_size` engine setting,
     /// builds a fresh `ResolutionBuffer` rooted at `splice_addr`,
-    /// and seeds the buffer with [`FLIP_SEED_SLOTS`] residency slots
-    /// drawn from the direct bump allocator.  Idempotent — repeated
-    /// calls while already `Paged` are no-ops.
+    /// and seeds the buffer with `flip_seed_slots` (default
+    /// [`FLIP_SEED_SLOTS`]) residency slots drawn from the direct bump
+    /// allocator.  Idempotent — repeated calls while already `Paged`
+    /// are no-ops.
     ///
-    /// Seeding reserves `FLIP_SEED_SLOTS * PAGE_SIZE` bytes of direct
+    /// Seeding reserves `flip_seed_slots * PAGE_SIZE` bytes of direct
     /// capacity that will never be returned to the direct allocator.
     /// This is the cost of having paged allocation available from
     /// the moment of the flip.
@@ -138,7 +147,8 @@ impl ExtendedPool {
 
         let pid = std::process::id();
         let path: PathBuf = PathBuf::from(format!("/dev/shm/webs-global-{pid}"));
-        let pool = GlobalPool::new(&path, GLOBAL_POOL_INITIAL_SIZE)
+        let cfg = engine_config::current();
+        let pool = GlobalPool::new(&path, cfg.global_pool_initial_size as usize)
             .map_err(|e| anyhow!(
                 "flip_to_paged: cannot create global pool at {}: {e}",
                 path.display(),
@@ -152,7 +162,7 @@ impl ExtendedPool {
         // Seed residency slots from the direct bump allocator.  Any
         // failure here leaves the pool in Paged mode with an empty
         // buffer — callers can still seed later via `on_direct_free`.
-        if let Err(e) = self.seed_residency(splice_addr, FLIP_SEED_SLOTS) {
+        if let Err(e) = self.seed_residency(splice_addr, cfg.flip_seed_slots as usize) {
             eprintln!("[ExtendedPool] flip seeding failed: {e}");
         }
 
@@ -446,13 +456,13 @@ mod tests {
     /// seeding.  The returned base is populated with a valid
     /// superblock pointing at `global_capacity == base + window_len`.
     fn make_superblock_window(extra_pages: usize) -> (usize, usize) {
-        use common::{Superblock, BUMP_ALLOCATOR_START, PAGE_SIZE};
+        use common::{ShmLayout, Superblock, PAGE_SIZE};
 
-        let sb_size = std::mem::size_of::<Superblock>();
+        let layout = ShmLayout::DEFAULT;
         let extra_bytes = extra_pages * PAGE_SIZE as usize;
-        // Cover Superblock + enough arena/page space so that BUMP_ALLOCATOR_START
+        // Cover Superblock + enough arena/page space so that the heap start
         // lies inside the window and we still have `extra_pages` of room past it.
-        let total = (BUMP_ALLOCATOR_START as usize + extra_bytes).max(sb_size);
+        let total = layout.bump_allocator_start as usize + extra_bytes;
         // Round up to PAGE_SIZE.
         let total = (total + PAGE_SIZE as usize - 1) / PAGE_SIZE as usize
                     * PAGE_SIZE as usize;
@@ -462,7 +472,8 @@ mod tests {
         // zero-filled, so we only need to set the non-zero fields.
         let sb = unsafe { &mut *(base as *mut Superblock) };
         sb.magic = 0xDEADBEEF;
-        sb.bump_allocator.store(BUMP_ALLOCATOR_START, Ordering::Release);
+        sb.layout = layout;
+        sb.bump_allocator.store(layout.bump_allocator_start, Ordering::Release);
         sb.global_capacity.store(total as ShmOffset, Ordering::Release);
 
         (base, size)
@@ -480,10 +491,9 @@ mod tests {
     fn reclaimer_falls_back_to_paged_when_direct_exhausted() {
         use crate::runtime::extended_pool::runtime;
         use crate::runtime::mem_operation::reclaimer;
-        use common::BUMP_ALLOCATOR_START;
 
         // 1. Build a window with only a few pages of direct capacity
-        //    past BUMP_ALLOCATOR_START, plus enough room for seeding.
+        //    past the heap start, plus enough room for seeding.
         //    `global_capacity` covers the whole window so seeding can
         //    succeed but only a handful of normal allocs fit before
         //    exhaustion.
@@ -3,11 +3,11 @@
 //! Two-state FSM:
 //!
 //! ```text
-//!                 bump >= 80% of DIRECT_LIMIT (1.6 GiB)
+//!                 bump >= 80% of DIRECT_LIMIT (default)
 //!        Direct ──────────────────────────────────────▶ Paged
 //!           ▲                                              │
 //!           │  global_live == 0                            │
-//!           │  && bump < 50% of DIRECT_LIMIT               │
+//!           │  && bump < 50% of DIRECT_LIMIT (default)     │
 //!           └──────────────────────────────────────────────┘
 //! ```
 //!
@@ -21,11 +21,15 @@
 //! correctness gate (no paged PageIds exist anywhere, so nothing can
 //! reference the resolution buffer), while the 50% fill is hysteresis
 //! to avoid flapping near the 80% entry threshold.
+//!
+//! Both fractions come from the engine configuration
+//! (`paged_mode_enter_*` / `paged_mode_exit_*`, see `engine_config`).
 
 use std::sync::OnceLock;
 
-use common::{DIRECT_LIMIT, PAGED_MODE_ENTER_DEN, PAGED_MODE_ENTER_NUM,
-             PAGED_MODE_EXIT_DEN, PAGED_MODE_EXIT_NUM, ShmOffset};
+use common::{DIRECT_LIMIT, ShmOffset};
+
+use crate::runtime::engine_config;
 
 /// Current allocation mode for one subprocess's page pool.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -60,7 +64,8 @@ fn debug_enter_threshold() -> Option<u64> {
 /// Should we flip `Direct -> Paged` given the current bump offset?
 ///
 /// Honors the `WEBS_FORCE_FLIP_AT` environment override when set,
-/// otherwise fires at 80% of `DIRECT_LIMIT` (1.6 GiB).  The SHM
+/// otherwise fires at `paged_mode_enter_num / paged_mode_enter_den` of
+/// `DIRECT_LIMIT` (80% by default).  The SHM
 /// file grows dynamically from `INITIAL_SHM_SIZE` up to `DIRECT_LIMIT`
 /// as demand increases; this threshold ensures the GlobalPool only
 /// takes over for workloads that genuinely exhaust the full 2 GiB
@@ -70,11 +75,12 @@ pub fn should_enter_paged(bump_offset: ShmOffset) -> bool {
     if let Some(thresh) = debug_enter_threshold() {
         return (bump_offset as u64) >= thresh;
     }
-    // Compare `bump / DIRECT_LIMIT >= 8/10` without floats.
-    //   bump * 10 >= DIRECT_LIMIT * 8
+    // Compare `bump / DIRECT_LIMIT >= num/den` without floats.
+    //   bump * den >= DIRECT_LIMIT * num
     // Both sides widened to u64 to avoid overflow.
-    (bump_offset as u64) * PAGED_MODE_ENTER_DEN
-        >= DIRECT_LIMIT * PAGED_MODE_ENTER_NUM
+    let cfg = engine_config::current();
+    (bump_offset as u64) * (cfg.paged_mode_enter_den as u64)
+        >= DIRECT_LIMIT * (cfg.paged_mode_enter_num as u64)
 }
 
 /// Should we flip `Paged -> Direct` given the current bump offset and
@@ -84,8 +90,9 @@ pub fn should_exit_paged(bump_offset: ShmOffset, global_live: u64) -> bool {
     if global_live != 0 {
         return false;
     }
-    (bump_offset as u64) * PAGED_MODE_EXIT_DEN
-        < DIRECT_LIMIT * PAGED_MODE_EXIT_NUM
+    let cfg = engine_config::current();
+    (bump_offset as u64) * (cfg.paged_mode_exit_den as u64)
+        < DIRECT_LIMIT * (cfg.paged_mode_exit_num as u64)
 }
 
 #[cfg(test)]
@@ -7,7 +7,8 @@
 //! mechanism.  It differs only in:
 //!
 //! - **Backing file path**: `/dev/shm/webs-rdma-<pid>` so the two
-//!   pools never collide on the same tmpfs entry.
+//!   pools never collide on the same tmpfs entry (`$WEBS_HUGETLBFS_DIR`,
+//!   default `/dev/hugepages`, with `huge_pages = 2`).
 //! - **Initial / hard-limit sizes**: drawn from
 //!   `common::RDMA_MR2_INITIAL_SIZE` / `common::RDMA_MR2_HARD_LIMIT`
 //!   (512 MiB starting, 16 GiB ceiling) rather than the general
@@ -32,7 +33,7 @@
 //! pass is a sensible follow-up when Path C is actually exercised.
 
 use anyhow::{anyhow, Context, Result};
-use common::{DIRECT_LIMIT, PAGE_SIZE, PageId, RDMA_MR2_HARD_LIMIT as _MR2_HARD_LIMIT_U64};
+use common::{DIRECT_LIMIT, HUGE_PAGES_OFF, HUGE_PAGE_SIZE, PAGE_SIZE, PageId, RDMA_MR2_HARD_LIMIT as _MR2_HARD_LIMIT_U64};
 
 /// `common::RDMA_MR2_HARD_LIMIT` is `u64` (for wasm32 compat).  The
 /// host-side code uses it as a `usize` length throughout; do the
@@ -107,22 +108,15 @@ impl RdmaPool {
             .read(true).write(true).create(true).truncate(true)
             .open(path)
             .with_context(|| format!("open {}", path.display()))?;
+        crate::shm::check_huge_page_backing(&file, crate::shm::huge_pages(), &path.display().to_string())?;
         file.set_len(initial as u64)
             .context("sizing RdmaPool backing file")?;
 
-        // Reserve the full hard-limit VA range as PROT_NONE.  Same
-        // reserve-once / commit-incrementally pattern as GlobalPool.
-        let base = unsafe {
-            mmap(
-                None,
-                NonZeroUsize::new(RDMA_MR2_HARD_LIMIT).unwrap(),
-                ProtFlags::PROT_NONE,
-                MapFlags::MAP_PRIVATE | MapFlags::MAP_ANONYMOUS,
-                None::<&File>,
-                0,
-            )
-            .context("reserving RdmaPool virtual address range")?
-        } as *mut u8;
+        // Reserve the full hard-limit VA range as PROT_NONE (huge-page
+        // aligned).  Same reserve-once / commit-incrementally pattern as
+        // GlobalPool.
+        let base = crate::shm::reserve_huge_aligned(RDMA_MR2_HARD_LIMIT)
+            .context("reserving RdmaPool virtual address range")?;
 
         unsafe {
             mmap(
@@ -135,6 +129,7 @@ impl RdmaPool {
             )
             .context("overlaying RdmaPool backing file")?;
         }
+        crate::shm::advise_huge_pages(base as usize, initial)?;
 
         Ok(Self {
             path: path.to_path_buf(),
@@ -240,6 +235,9 @@ impl RdmaPool {
     /// `MADV_DONTNEED`.  Freelist length and reuse order are
     /// preserved; only the kernel's page cache is shrunk.
     pub fn trim_freelist(&mut self) -> usize {
+        if crate::shm::huge_pages() != HUGE_PAGES_OFF {
+            return self.trim_free_extents();
+        }
         let mut advised = 0usize;
         for &id in &self.freelist {
             let offset = (id - RDMA_MR2_MARKER) as usize;
@@ -256,6 +254,24 @@ impl RdmaPool {
         advised
     }
 
+    /// Huge-page variant of [`Self::trim_freelist`]: a 4 KiB
+    /// `MADV_DONTNEED` would split a huge page, so only extents whose
+    /// pages are all on the freelist are advised.
+    fn trim_free_extents(&mut self) -> usize {
+        let offsets: Vec<u64> = self.freelist.iter().map(|&id| id - RDMA_MR2_MARKER).collect();
+        let mut advised = 0usize;
+        for start in crate::shm::whole_huge_extents(&offsets) {
+            if start as usize + HUGE_PAGE_SIZE as usize > self.committed {
+                continue;
+            }
+            let addr = unsafe { self.base.add(start as usize) } as *mut libc::c_void;
+            if unsafe { madvise(addr, HUGE_PAGE_SIZE as usize, MmapAdvise::MADV_DONTNEED) }.is_ok() {
+                advised += (HUGE_PAGE_SIZE / PAGE_SIZE) as usize;
+            }
+        }
+        advised
+    }
+
     /// Double the committed region, capped at `RDMA_MR2_HARD_LIMIT`.
     fn expand(&mut self) -> Result<()> {
         let new_committed = (self.committed * 2).min(RDMA_MR2_HARD_LIMIT);
@@ -283,6 +299,7 @@ impl RdmaPool {
             )
             .context("overlaying grown RdmaPool region")?;
         }
+        crate::shm::advise_huge_pages(overlay_addr, overlay_len)?;
         self.committed = new_committed;
         Ok(())
     }
@@ -8,7 +8,7 @@
 //!   NOT reset on free (because the RDMA pool is a budget, not a
 //!   refcounted live set — we measure cumulative pressure, which
 //!   governs when MR2 registration fires).  Compare against
-//!   `common::RDMA_MR1_BUDGET` to decide if the next allocation
+//!   the `rdma_mr1_budget` engine setting to decide if the next allocation
 //!   should spill into MR2.
 //!
 //! - `pool: OnceLock<Mutex<RdmaPool>>` — the dedicated MR2 pool.
@@ -45,26 +45,21 @@ use std::sync::{Mutex, OnceLock};
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use anyhow::{anyhow, Result};
-use common::{
-    EXTENDED_RDMA_ENABLED, Page, PageId,
-    RDMA_MR1_BUDGET, RDMA_MR2_INITIAL_SIZE, RDMA_MR2_REG_THRESHOLD,
-};
-
-/// Host-side cast of the u64 constant — see the similar alias in
-/// `rdma_pool.rs`.  `common` types these as `u64` for wasm32 compat.
-const RDMA_MR2_INITIAL_SIZE_USIZE: usize = RDMA_MR2_INITIAL_SIZE as usize;
+use common::{Page, PageId, RDMA_MR2_REG_THRESHOLD};
 
+use crate::runtime::engine_config;
 use super::rdma_pool::{RdmaPool, RDMA_MR2_MARKER};
 
-/// Feature-flag guard.  Every entry point checks this first; the
-/// compiler eliminates every function body when the flag is off.
+/// Feature-flag guard.  Every entry point checks this first.  Set by
+/// `extended_rdma_enabled` in the engine configuration (default
+/// `common::EXTENDED_RDMA_ENABLED`).
 #[inline(always)]
-pub const fn enabled() -> bool { EXTENDED_RDMA_ENABLED }
+pub fn enabled() -> bool { engine_config::current().extended_rdma() }
 
 /// Cumulative bytes the RDMA receive paths have allocated from MR1.
 /// Never decremented — this is a pressure counter, not a live-set
 /// size.  Used only to trigger `RDMA_MR2_REG_THRESHOLD` and
-/// `RDMA_MR1_BUDGET` decisions.
+/// MR1-budget decisions.
 static RDMA_MR1_USED: AtomicU64 = AtomicU64::new(0);
 
 /// The lazily-created MR2 pool.  Wrapped in `OnceLock<Mutex<_>>`
@@ -75,8 +70,8 @@ static POOL: OnceLock<Mutex<RdmaPool>> = OnceLock::new();
 
 /// Lock-free check: would the receive path want to allocate from
 /// MR2 for an incoming transfer of `needed_bytes`?  Returns `true`
-/// if adding `needed_bytes` to `rdma_mr1_used` would cross
-/// `RDMA_MR1_BUDGET`.
+/// if adding `needed_bytes` to `rdma_mr1_used` would cross the
+/// `rdma_mr1_budget` engine setting.
 ///
 /// This is a fast predicate used BEFORE committing an allocation.
 /// Returns false immediately when the feature flag is off.
@@ -86,7 +81,7 @@ pub fn should_use_mr2(needed_bytes: u64) -> bool {
         return false;
     }
     let used = RDMA_MR1_USED.load(Ordering::Acquire);
-    used.saturating_add(needed_bytes) > RDMA_MR1_BUDGET
+    used.saturating_add(needed_bytes) > engine_config::current().rdma_mr1_budget
 }
 
 /// Lock-free check: should we proactively register MR2 now, even
@@ -132,15 +127,23 @@ pub fn mr1_used() -> u64 {
 pub fn ensure_pool() -> Result<()> {
     if !enabled() {
         return Err(anyhow!(
-            "EXTENDED_RDMA_ENABLED is false — RdmaPool unavailable",
+            "extended_rdma_enabled is off — RdmaPool unavailable",
         ));
     }
     if POOL.get().is_some() {
         return Ok(());
     }
     let pid = std::process::id();
-    let path = std::path::PathBuf::from(format!("/dev/shm/webs-rdma-{pid}"));
-    let pool = RdmaPool::new(&path, RDMA_MR2_INITIAL_SIZE_USIZE)
+    let cfg = engine_config::current();
+    // In hugetlbfs mode MR2 lives on the huge-page mount, like the SHM.
+    let dir = if cfg.huge_pages == common::HUGE_PAGES_HUGETLBFS {
+        std::env::var(common::HUGETLBFS_DIR_ENV).unwrap_or_else(|_| common::DEFAULT_HUGETLBFS_DIR.to_string())
+    } else {
+        "/dev/shm".to_string()
+    };
+    let path = std::path::PathBuf::from(format!("{dir}/webs-rdma-{pid}"));
+    let initial = cfg.rdma_mr2_initial_size as usize;
+    let pool = RdmaPool::new(&path, initial)
         .map_err(|e| anyhow!("ensure_pool: RdmaPool::new failed: {e}"))?;
     let _ = POOL.set(Mutex::new(pool));
 
@@ -185,7 +188,7 @@ pub fn try_lock() -> Option<std::sync::MutexGuard<'static, RdmaPool>> {
 pub fn alloc_contiguous(n: usize) -> Result<PageId> {
     if !enabled() {
         return Err(anyhow!(
-            "EXTENDED_RDMA_ENABLED is false — alloc_contiguous rejected",
+            "extended_rdma_enabled is off — alloc_contiguous rejected",
         ));
     }
     ensure_pool()?;
@@ -197,7 +200,7 @@ pub fn alloc_contiguous(n: usize) -> Result<PageId> {
 pub fn free(id: PageId) -> Result<()> {
     if !enabled() {
         return Err(anyhow!(
-            "EXTENDED_RDMA_ENABLED is false — free rejected",
+            "extended_rdma_enabled is off — free rejected",
         ));
     }
     debug_assert!(id >= RDMA_MR2_MARKER);
@@ -211,7 +214,7 @@ pub fn free(id: PageId) -> Result<()> {
 /// freshly-allocated MR1 direct pages.
 pub fn host_addr_of(id: PageId) -> Result<*mut Page> {
     if !enabled() {
-        return Err(anyhow!("EXTENDED_RDMA_ENABLED is false"));
+        return Err(anyhow!("extended_rdma_enabled is off"));
     }
     debug_assert!(id >= RDMA_MR2_MARKER);
     let pool = lock();
@@ -236,13 +239,13 @@ mod tests {
         reset_for_test();
         // When flag is off, never spills — verify unconditionally.
         if !enabled() {
-            assert!(!should_use_mr2(RDMA_MR1_BUDGET * 10));
+            assert!(!should_use_mr2(common::RDMA_MR1_BUDGET * 10));
             return;
         }
         // At zero used, a below-budget request doesn't spill.
         assert!(!should_use_mr2(1024));
         // A request larger than the whole budget spills.
-        assert!(should_use_mr2(RDMA_MR1_BUDGET + 1));
+        assert!(should_use_mr2(common::RDMA_MR1_BUDGET + 1));
     }
 
     #[test]
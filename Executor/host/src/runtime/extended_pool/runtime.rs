@@ -27,7 +27,9 @@ use std::sync::{Mutex, OnceLock};
 use std::sync::atomic::{AtomicU8, Ordering};
 
 use anyhow::{anyhow, Result};
-use common::{EXTENDED_POOL_ENABLED, Page, PageId, ShmOffset, DIRECT_LIMIT};
+use common::{Page, PageId, ShmOffset, DIRECT_LIMIT};
+
+use crate::runtime::engine_config;
 
 use super::{ExtendedPool, Mode};
 use super::mode::should_enter_paged;
@@ -43,12 +45,11 @@ static MODE: AtomicU8 = AtomicU8::new(0);
 const MODE_DIRECT: u8 = 0;
 const MODE_PAGED: u8 = 1;
 
-/// Whether the feature is compiled in.  All public entry points
-/// short-circuit when this is `false` and the compiler eliminates
-/// their bodies as dead code.  Single source of truth lives in
-/// `common::EXTENDED_POOL_ENABLED`.
+/// Whether the feature is switched on.  All public entry points
+/// short-circuit when this is `false`.  Set by `extended_pool_enabled`
+/// in the engine configuration (default `common::EXTENDED_POOL_ENABLED`).
 #[inline(always)]
-pub const fn enabled() -> bool { EXTENDED_POOL_ENABLED }
+pub fn enabled() -> bool { engine_config::current().extended_pool() }
 
 /// Lock and return a mutable handle to the process-global extended pool.
 /// Lazily initializes on first call.  Callers should guard with
@@ -83,7 +84,7 @@ pub fn current_mode() -> Mode {
 /// is needed by `flip_to_paged` to anchor its `ResolutionBuffer`.
 /// The reclaimer already has it in scope at the call site.
 ///
-/// Entirely compiled out when `EXTENDED_POOL_ENABLED == false`.
+/// A single config read and return when `extended_pool_enabled` is off.
 #[inline]
 pub fn notify_bump_advance(new_bump: ShmOffset, splice_addr: usize) {
     if !enabled() {
@@ -118,7 +119,7 @@ pub fn notify_bump_advance(new_bump: ShmOffset, splice_addr: usize) {
 pub fn flip_to_paged(splice_addr: usize) -> Result<Mode> {
     if !enabled() {
         return Err(anyhow::anyhow!(
-            "extended_pool feature is disabled (common::EXTENDED_POOL_ENABLED = false)"
+            "extended_pool feature is disabled (engine config extended_pool_enabled = false)"
         ));
     }
     let mut pool = lock();
@@ -153,7 +154,7 @@ pub fn resolve(id: PageId, splice_addr: usize) -> Result<*mut Page> {
 pub fn alloc_paged_page(rdma_bound: bool) -> Result<(PageId, *mut Page)> {
     if !enabled() {
         return Err(anyhow!(
-            "extended_pool feature is disabled (common::EXTENDED_POOL_ENABLED = false)"
+            "extended_pool feature is disabled (engine config extended_pool_enabled = false)"
         ));
     }
     let mut pool = lock();
@@ -170,7 +171,7 @@ pub fn alloc_paged_page(rdma_bound: bool) -> Result<(PageId, *mut Page)> {
 pub fn free_paged_page(id: PageId) -> Result<()> {
     if !enabled() {
         return Err(anyhow!(
-            "extended_pool feature is disabled (common::EXTENDED_POOL_ENABLED = false)"
+            "extended_pool feature is disabled (engine config extended_pool_enabled = false)"
         ));
     }
     debug_assert!(id >= DIRECT_LIMIT);
@@ -193,6 +194,22 @@ pub fn notify_direct_free(id: PageId) {
     pool.on_direct_free(id);
 }
 
+/// Live paged-mode pages held by this process; 0 while `Direct`.
+pub fn paged_live_pages() -> u64 {
+    if current_mode() != Mode::Paged {
+        return 0;
+    }
+    lock().global_live()
+}
+
+/// Return the RAM behind freed paged pages to the OS.  No-op while `Direct`.
+pub fn trim_global_freelist() -> usize {
+    if current_mode() != Mode::Paged {
+        return 0;
+    }
+    lock().trim_global_freelist()
+}
+
 /// Test-only: reset the singleton back to a fresh `Direct` state.
 /// Because `OnceLock` cannot be re-initialized, this reaches through
 /// the mutex and overwrites the inner value.  Not exposed in release
@@ -0,0 +1,355 @@
+// Directory / glob input enumeration and file-level sharding.
+//
+// # FileSet
+//
+// An `Input` node with a `files` block loads a *set* of files instead of one
+// `path`.  `FileSet::resolve` expands it deterministically:
+//
+//   1. enumerate — `path` is either a directory (its regular files, recursively
+//      with `recursive: true`) or a glob pattern (`*`, `?`, `[abc]`, `**`);
+//   2. filter    — keep files matching any `include` pattern (all when empty)
+//      and none of the `exclude` patterns;
+//   3. sort      — by `name` (default), `size` or `mtime`, optionally reversed;
+//   4. cap       — keep the first `limit` files.
+//
+// Hidden entries (leading `.`) are skipped, so temp files from an in-progress
+// atomic `Output` never leak into a later input.  The cap is applied before
+// sharding, so every node of a cluster job resolves the same file list.
+//
+// # Sharding
+//
+// `FileSet::shard` applies the Input's `slice: [lo, hi]` at file granularity:
+// `shard_by: "count"` owns files `[⌊lo·n⌋, ⌊hi·n⌋)`, `shard_by: "bytes"` owns the
+// files whose cumulative start offset falls in `[lo·total, hi·total)`.  Either
+// way adjacent slices cover the set exactly once and no file is split.
+//
+// Usage:
+//   let set: FileSet = serde_json::from_value(json!({ "path": "imgs/*.png" }))?;
+//   let files = set.shard(set.resolve()?, Some([0.0, 0.5]));
+//   SlotLoader::new(splice_addr).load_files(&files, slot, true, &format, compression)?;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+// ─── FileSet ──────────────────────────────────────────────────────────────────
+
+/// Set of input files selected by directory or glob pattern.
+///
+/// ```json
+/// "files": { "path": "data/imgs", "include": ["*.png", "*.jpg"], "exclude": ["*_thumb.*"],
+///            "sort": "size", "reverse": true, "limit": 500, "shard_by": "bytes" }
+/// "files": { "path": "logs/**/*.log.gz" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileSet {
+    /// Directory to list, or a glob pattern over file paths.
+    pub path: String,
+    /// Descend into subdirectories when `path` is a directory.  Glob patterns
+    /// recurse only through `**`.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Keep only files matching at least one pattern.  A pattern containing
+    /// `/` matches the path relative to the listing root; otherwise it matches
+    /// the file name.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Drop files matching any pattern (same matching rules as `include`).
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub sort: FileSort,
+    /// Reverse the sort order.
+    #[serde(default)]
+    pub reverse: bool,
+    /// Keep at most this many files (after sorting, before sharding).
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub shard_by: ShardBy,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSort {
+    /// Lexicographic by relative path.
+    #[default]
+    Name,
+    Size,
+    /// Modification time, oldest first; ties broken by name.
+    Mtime,
+}
+
+/// Unit a fractional `slice` is applied to.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardBy {
+    /// Equal file counts per shard.
+    #[default]
+    Count,
+    /// Equal total bytes per shard (better balance for skewed file sizes).
+    Bytes,
+}
+
+/// One resolved input file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub len: u64,
+}
+
+impl FileSet {
+    /// Enumerate, filter, sort and cap the set.  Errors if `path` is neither
+    /// a directory nor a glob pattern, or if nothing matches.
+    pub fn resolve(&self) -> Result<Vec<FileEntry>> {
+        let (root, pattern) = if is_glob(&self.path) {
+            split_glob(&self.path)
+        } else {
+            let root = PathBuf::from(&self.path);
+            if !root.is_dir() {
+                return Err(anyhow!("files: '{}' is not a directory or glob pattern", self.path));
+            }
+            (root, None)
+        };
+
+        let max_depth = match &pattern {
+            Some(p) if p.contains("**") => usize::MAX,
+            Some(p) => p.split('/').count(),
+            None if self.recursive => usize::MAX,
+            None => 1,
+        };
+        let mut found: Vec<(String, FileEntry, SystemTime)> = Vec::new();
+        walk(&root, "", max_depth, &mut found)?;
+
+        found.retain(|(rel, _, _)| {
+            pattern.as_deref().is_none_or(|p| glob_match(p, rel))
+                && (self.include.is_empty() || self.include.iter().any(|p| filter_match(p, rel)))
+                && !self.exclude.iter().any(|p| filter_match(p, rel))
+        });
+        if found.is_empty() {
+            return Err(anyhow!("files: '{}' matched no files", self.path));
+        }
+
+        match self.sort {
+            FileSort::Name => found.sort_by(|a, b| a.0.cmp(&b.0)),
+            FileSort::Size => found.sort_by(|a, b| a.1.len.cmp(&b.1.len).then_with(|| a.0.cmp(&b.0))),
+            FileSort::Mtime => found.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0))),
+        }
+        if self.reverse {
+            found.reverse();
+        }
+        if let Some(limit) = self.limit {
+            found.truncate(limit);
+        }
+        Ok(found.into_iter().map(|(_, e, _)| e).collect())
+    }
+
+    /// Restrict `files` to the fractional `slice` by `shard_by`; the whole set
+    /// when `slice` is `None`.
+    pub fn shard(&self, files: Vec<FileEntry>, slice: Option<[f64; 2]>) -> Vec<FileEntry> {
+        let Some([lo, hi]) = slice else { return files };
+        let frac = |f: f64, total: u64| (f.clamp(0.0, 1.0) * total as f64).floor() as u64;
+        match self.shard_by {
+            ShardBy::Count => {
+                let n = files.len() as u64;
+                let start = frac(lo, n) as usize;
+                let end = if hi >= 1.0 { files.len() } else { frac(hi, n) as usize };
+                files.into_iter().take(end).skip(start).collect()
+            }
+            ShardBy::Bytes => {
+                let total: u64 = files.iter().map(|f| f.len).sum();
+                let start = frac(lo, total);
+                let end = if hi >= 1.0 { u64::MAX } else { frac(hi, total) };
+                let mut offset = 0u64;
+                files.into_iter()
+                    .filter(|f| {
+                        let at = offset;
+                        offset += f.len;
+                        at >= start && at < end
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Total bytes of `files`.
+pub fn total_len(files: &[FileEntry]) -> u64 {
+    files.iter().map(|f| f.len).sum()
+}
+
+// ─── internals ────────────────────────────────────────────────────────────────
+
+/// Collect regular files under `dir` (up to `depth` levels) as
+/// `(relative path with '/' separators, entry, mtime)`.
+fn walk(dir: &Path, rel: &str, depth: usize, out: &mut Vec<(String, FileEntry, SystemTime)>) -> Result<()> {
+    if depth == 0 {
+        return Ok(());
+    }
+    let entries = fs::read_dir(dir).map_err(|e| anyhow!("files: cannot list '{}': {}", dir.display(), e))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let child_rel = if rel.is_empty() { name } else { format!("{}/{}", rel, name) };
+        let path = entry.path();
+        // Follow symlinks: a linked file or directory counts as its target.
+        let Ok(meta) = fs::metadata(&path) else { continue };
+        if meta.is_dir() {
+            walk(&path, &child_rel, depth - 1, out)?;
+        } else if meta.is_file() {
+            let mtime = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            out.push((child_rel, FileEntry { path, len: meta.len() }, mtime));
+        }
+    }
+    Ok(())
+}
+
+fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// Split a glob into its literal directory prefix and the pattern relative to
+/// it: `data/imgs/*.png` → (`data/imgs`, `*.png`).
+fn split_glob(s: &str) -> (PathBuf, Option<String>) {
+    let parts: Vec<&str> = s.split('/').collect();
+    let first_wild = parts.iter().position(|p| is_glob(p)).unwrap_or(parts.len());
+    let root = match parts[..first_wild].join("/") {
+        r if r.is_empty() && s.starts_with('/') => "/".to_string(),
+        r if r.is_empty() => ".".to_string(),
+        r => r,
+    };
+    (PathBuf::from(root), Some(parts[first_wild..].join("/")))
+}
+
+fn filter_match(pattern: &str, rel: &str) -> bool {
+    if pattern.contains('/') {
+        glob_match(pattern, rel)
+    } else {
+        glob_match(pattern, rel.rsplit('/').next().unwrap_or(rel))
+    }
+}
+
+/// Shell-style match of `text` against `pattern`: `*` and `?` stay within one
+/// path component, `**` spans any number of components (`a/**/b` also matches
+/// `a/b`), `[abc]` / `[a-z]` / `[!a]` match one character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    match_from(&p, &t)
+}
+
+fn match_from(p: &[char], t: &[char]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            // `**/` may also match zero directories.
+            let rest = if p.get(2) == Some(&'/') { &p[3..] } else { &p[2..] };
+            (0..=t.len()).any(|i| (i == 0 || t[i - 1] == '/') && match_from(rest, &t[i..]))
+                || (0..=t.len()).any(|i| match_from(&p[2..], &t[i..]))
+        }
+        Some('*') => {
+            (0..=t.len()).take_while(|&i| i == 0 || t[i - 1] != '/').any(|i| match_from(&p[1..], &t[i..]))
+        }
+        Some('?') => t.first().is_some_and(|&c| c != '/') && match_from(&p[1..], &t[1..]),
+        Some('[') => {
+            let Some(close) = p.iter().skip(1).position(|&c| c == ']').map(|i| i + 1) else {
+                return t.first() == Some(&'[') && match_from(&p[1..], &t[1..]);
+            };
+            let Some(&c) = t.first() else { return false };
+            let (negate, class) = match p[1] {
+                '!' | '^' => (true, &p[2..close]),
+                _ => (false, &p[1..close]),
+            };
+            let mut hit = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    hit |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    hit |= class[i] == c;
+                    i += 1;
+                }
+            }
+            hit != negate && c != '/' && match_from(&p[close + 1..], &t[1..])
+        }
+        Some(&c) => t.first() == Some(&c) && match_from(&p[1..], &t[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(path: &str) -> FileSet {
+        serde_json::from_value(serde_json::json!({ "path": path })).unwrap()
+    }
+
+    fn entries(lens: &[u64]) -> Vec<FileEntry> {
+        lens.iter().enumerate()
+            .map(|(i, &len)| FileEntry { path: PathBuf::from(format!("f{i}")), len })
+            .collect()
+    }
+
+    #[test]
+    fn glob_matching() {
+        assert!(glob_match("*.png", "a.png"));
+        assert!(!glob_match("*.png", "dir/a.png"));
+        assert!(glob_match("**/*.png", "a.png"));
+        assert!(glob_match("**/*.png", "x/y/a.png"));
+        assert!(glob_match("x/**/a.?ng", "x/a.png"));
+        assert!(glob_match("img_[0-9][!a].jpg", "img_7b.jpg"));
+        assert!(!glob_match("img_[0-9][!a].jpg", "img_7a.jpg"));
+    }
+
+    #[test]
+    fn resolves_dir_and_glob_with_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("sub")).unwrap();
+        for (name, body) in [("b.png", "bb"), ("a.png", "aaaa"), ("c.txt", "c"), (".hidden.png", "h"), ("sub/d.png", "d")] {
+            fs::write(root.join(name), body).unwrap();
+        }
+        let names = |files: Vec<FileEntry>| -> Vec<String> {
+            files.iter().map(|f| f.path.strip_prefix(root).unwrap().to_string_lossy().into_owned()).collect()
+        };
+
+        let mut s = set(root.to_str().unwrap());
+        assert_eq!(names(s.resolve().unwrap()), ["a.png", "b.png", "c.txt"]);
+        s.recursive = true;
+        s.exclude = vec!["*.txt".into()];
+        assert_eq!(names(s.resolve().unwrap()), ["a.png", "b.png", "sub/d.png"]);
+        s.sort = FileSort::Size;
+        s.reverse = true;
+        s.limit = Some(2);
+        assert_eq!(names(s.resolve().unwrap()), ["a.png", "b.png"]);
+
+        let g = set(&format!("{}/**/*.png", root.display()));
+        assert_eq!(names(g.resolve().unwrap()), ["a.png", "b.png", "sub/d.png"]);
+        assert!(set(&format!("{}/*.gif", root.display())).resolve().is_err());
+    }
+
+    #[test]
+    fn shards_cover_set_exactly_once() {
+        let files = entries(&[10, 1, 1, 1, 1, 1, 1, 4]);
+        for by in [ShardBy::Count, ShardBy::Bytes] {
+            let s = FileSet { shard_by: by, ..set("x") };
+            let cuts = [0.0, 0.3, 0.55, 1.0];
+            let mut seen: Vec<FileEntry> = Vec::new();
+            for w in cuts.windows(2) {
+                seen.extend(s.shard(files.clone(), Some([w[0], w[1]])));
+            }
+            assert_eq!(seen, files, "{by:?}");
+        }
+        let by_bytes = FileSet { shard_by: ShardBy::Bytes, ..set("x") };
+        // 20 bytes total: the 10-byte file alone fills the first half.
+        assert_eq!(by_bytes.shard(files.clone(), Some([0.0, 0.5])).len(), 1);
+        assert_eq!(set("x").shard(files, Some([0.0, 0.5])).len(), 4);
+    }
+}
@@ -28,7 +28,7 @@
 
 use std::sync::atomic::Ordering;
 
-use common::{LOG_ARENA_OFFSET, LOG_ARENA_SIZE, ShmOffset, Superblock};
+use common::{ShmOffset, Superblock};
 
 // -----------------------------------------------------------------------------
 // Log level
@@ -125,7 +125,7 @@ impl HostLogger {
         let superblock = unsafe { &*(self.splice_addr as *const Superblock) };
         let log_cursor = &superblock.log_offset;
 
-        let max = LOG_ARENA_SIZE;
+        let max = superblock.layout.log_arena_size();
 
         // Reserve space atomically; multiple threads get non-overlapping slots.
         let old_offset = log_cursor.fetch_add(entry_len, Ordering::AcqRel);
@@ -142,7 +142,7 @@ impl HostLogger {
         let write_len = bytes.len().min(available);
 
         let log_base = unsafe {
-            (self.splice_addr as *mut u8).add(LOG_ARENA_OFFSET as usize)
+            (self.splice_addr as *mut u8).add(superblock.layout.log_arena_offset as usize)
         };
 
         unsafe {
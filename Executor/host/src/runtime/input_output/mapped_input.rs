@@ -0,0 +1,261 @@
+// Zero-copy input overlays.
+//
+// A zero-copy `Input` (see `SlotLoader::zero_copy`) does not copy the file
+// into SHM pages.  It reserves a contiguous extent of `MAPPED_PAGE_CLASS`
+// pages and maps the file read-only over everything but the first 4 KiB unit
+// of each page (layout in common's "Mapped inputs" section).  A mapping is
+// per process, so the overlay is described in `Superblock::mapped_inputs`
+// and every process that maps the region installs it itself:
+//
+//   - the DAG runner, in `map_file`, before the pages are linked into a slot;
+//   - workers, in `setup_vma_environment` (`apply`);
+//   - long-lived `wasm-loop` workers, before each call when
+//     `Superblock::mapped_generation` moved (`sync`);
+//   - anyone re-mapping the region (`shm::expand_mapping`,
+//     `shm::sync_mapping_to_capacity`), because the `MAP_FIXED` remap
+//     replaces the overlays with the backing file again.
+//
+// # Lifetime
+//
+// The entry goes `FREE → LIVE` in `map_file`.  Freeing the slot chain hands
+// each extent page to `release_page` (from `reclaimer`) instead of the class
+// free list; once the last one is back the entry is `RETIRED`.  A retired
+// extent is only ever reused by the next zero-copy load — another process
+// may still have the old overlay in place, and a page recycled as ordinary
+// memory would read the file instead of what is written to it.  When no
+// entry fits, `map_file` returns `None` and the caller copies the file.
+//
+// The file must not be truncated while it is mapped: touching an overlay
+// page past EOF raises SIGBUS.
+
+use std::fs::File;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use nix::sys::mman::{mmap, MapFlags, ProtFlags};
+
+use common::{page_class_size, MappedInput, ShmOffset, Superblock, MAPPED_INPUT_COUNT,
+             MAPPED_INPUT_FREE, MAPPED_INPUT_LIVE, MAPPED_INPUT_PATH_LEN, MAPPED_INPUT_RETIRED,
+             MAPPED_PAGE_CLASS, MIB};
+
+use crate::runtime::mem_operation::reclaimer;
+
+/// Serialises this process's changes to `Superblock::mapped_inputs`.
+static TABLE: Mutex<()> = Mutex::new(());
+
+/// `Superblock::mapped_generation` this process last applied.
+static APPLIED: AtomicU32 = AtomicU32::new(0);
+
+fn superblock<'a>(splice_addr: usize) -> &'a Superblock {
+    unsafe { &*(splice_addr as *const Superblock) }
+}
+
+fn extent_bytes(pages: u32) -> ShmOffset {
+    pages * page_class_size(MAPPED_PAGE_CLASS)
+}
+
+// ─── Mapping ─────────────────────────────────────────────────────────────────
+
+/// Overlay `path` onto `pages` mapped-input pages and return the offset of
+/// the first one, or `None` when every entry is live or too small (the
+/// caller then copies the file).  The pages are not initialised: the caller
+/// writes each header and first unit, then links them into a slot chain.
+pub fn map_file(splice_addr: usize, path: &Path, pages: u32) -> Result<Option<ShmOffset>> {
+    let path = path.canonicalize()
+        .map_err(|e| anyhow!("Cannot resolve '{}': {}", path.display(), e))?;
+    let name = path.to_str()
+        .ok_or_else(|| anyhow!("'{}' is not valid UTF-8", path.display()))?;
+    if name.len() > MAPPED_INPUT_PATH_LEN {
+        return Err(anyhow!(
+            "'{}' is longer than the {} bytes a mapped input can record",
+            name, MAPPED_INPUT_PATH_LEN,
+        ));
+    }
+
+    let _guard = TABLE.lock().expect("mapped-input table poisoned");
+    let sb = superblock(splice_addr);
+    let state = |i: usize| sb.mapped_inputs[i].state.load(Ordering::Acquire);
+
+    // Smallest retired extent that fits, else a free entry with a new extent.
+    let reuse = (0..MAPPED_INPUT_COUNT)
+        .filter(|&i| state(i) == MAPPED_INPUT_RETIRED)
+        .filter(|&i| sb.mapped_inputs[i].capacity.load(Ordering::Relaxed) >= pages)
+        .min_by_key(|&i| sb.mapped_inputs[i].capacity.load(Ordering::Relaxed));
+    let (index, offset, capacity) = match reuse {
+        Some(i) => {
+            let e = &sb.mapped_inputs[i];
+            (i, e.offset.load(Ordering::Relaxed), e.capacity.load(Ordering::Relaxed))
+        }
+        None => match (0..MAPPED_INPUT_COUNT).find(|&i| state(i) == MAPPED_INPUT_FREE) {
+            Some(i) => (i, reclaimer::alloc_span(splice_addr, extent_bytes(pages))?, pages),
+            None => {
+                println!("[MappedInput] no entry for '{}' ({} pages) — copying", name, pages);
+                return Ok(None);
+            }
+        },
+    };
+
+    // The entry is FREE or RETIRED, so no process overlays it from the
+    // table while the fields change.
+    let entry = &sb.mapped_inputs[index];
+    entry.offset.store(offset, Ordering::Relaxed);
+    entry.capacity.store(capacity, Ordering::Relaxed);
+    entry.pages.store(pages, Ordering::Relaxed);
+    entry.live_pages.store(pages, Ordering::Relaxed);
+    unsafe {
+        let dst = std::ptr::addr_of!(entry.path) as *mut u8;
+        std::ptr::write_bytes(dst, 0, MAPPED_INPUT_PATH_LEN);
+        std::ptr::copy_nonoverlapping(name.as_ptr(), dst, name.len());
+    }
+
+    overlay(splice_addr, entry)?;
+    entry.state.store(MAPPED_INPUT_LIVE, Ordering::Release);
+    sb.mapped_generation.fetch_add(1, Ordering::AcqRel);
+
+    println!(
+        "[MappedInput] '{}' → entry {} ({} pages @ {:#x}, {} MiB overlaid{})",
+        name, index, pages, offset,
+        pages as u64 * page_class_size(MAPPED_PAGE_CLASS) as u64 / MIB as u64,
+        if reuse.is_some() { ", extent reused" } else { "" },
+    );
+    Ok(Some(offset))
+}
+
+/// Hand a freed page back to the mapped input it belongs to.  Returns
+/// `false` when `offset` is not in a live extent (an ordinary large page).
+pub fn release_page(splice_addr: usize, offset: ShmOffset) -> bool {
+    let _guard = TABLE.lock().expect("mapped-input table poisoned");
+    let sb = superblock(splice_addr);
+    for entry in &sb.mapped_inputs {
+        if entry.state.load(Ordering::Acquire) != MAPPED_INPUT_LIVE {
+            continue;
+        }
+        let start = entry.offset.load(Ordering::Relaxed);
+        let end = start + extent_bytes(entry.pages.load(Ordering::Relaxed));
+        if (start..end).contains(&offset) {
+            if entry.live_pages.fetch_sub(1, Ordering::AcqRel) == 1 {
+                entry.state.store(MAPPED_INPUT_RETIRED, Ordering::Release);
+                println!(
+                    "[MappedInput] '{}' retired ({} pages @ {:#x})",
+                    entry.path_str().unwrap_or("?"), entry.capacity.load(Ordering::Relaxed), start,
+                );
+            }
+            return true;
+        }
+    }
+    false
+}
+
+// ─── Other processes ─────────────────────────────────────────────────────────
+
+/// Install the overlay of every live entry in this process.  Call after
+/// mapping or re-mapping the region.
+pub fn apply(splice_addr: usize) -> Result<()> {
+    let sb = superblock(splice_addr);
+    let generation = sb.mapped_generation.load(Ordering::Acquire);
+    for entry in &sb.mapped_inputs {
+        if entry.state.load(Ordering::Acquire) == MAPPED_INPUT_LIVE {
+            overlay(splice_addr, entry)?;
+        }
+    }
+    APPLIED.store(generation, Ordering::Release);
+    Ok(())
+}
+
+/// [`apply`] if an input was mapped since this process last did.  One
+/// atomic load when nothing changed.
+pub fn sync(splice_addr: usize) -> Result<()> {
+    let generation = superblock(splice_addr).mapped_generation.load(Ordering::Acquire);
+    if generation == APPLIED.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    apply(splice_addr)
+}
+
+/// `MAP_FIXED` the file of `entry` over units 1.. of each of its pages.
+fn overlay(splice_addr: usize, entry: &MappedInput) -> Result<()> {
+    let path = entry.path_str().ok_or_else(|| anyhow!("mapped input has a corrupt path"))?;
+    let file = File::open(path)
+        .map_err(|e| anyhow!("Cannot open mapped input '{}': {}", path, e))?;
+    for j in 0..entry.pages.load(Ordering::Relaxed) {
+        let (start, end) = entry.overlay_range(j);
+        let len = NonZeroUsize::new(end - start).expect("large page spans more than one unit");
+        unsafe {
+            mmap(
+                NonZeroUsize::new(splice_addr + start),
+                len,
+                ProtFlags::PROT_READ,
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED,
+                Some(&file),
+                MappedInput::overlay_file_offset(j) as libc::off_t,
+            )
+            .map_err(|e| anyhow!("overlay of '{}' page {} failed: {}", path, j, e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+
+    use nix::sys::mman::munmap;
+
+    use common::{MAPPED_PAGE_FILL, PAGE_HEADER_SIZE, INITIAL_SHM_SIZE};
+
+    use crate::runtime::input_output::persistence::read_io_records;
+    use crate::runtime::input_output::slot_loader::SlotLoader;
+    use crate::shm::format_shared_memory;
+
+    #[test]
+    fn zero_copy_record_reads_back_and_extent_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let shm = dir.path().join("region");
+        format_shared_memory(shm.to_str().unwrap()).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&shm).unwrap();
+        let len = NonZeroUsize::new(INITIAL_SHM_SIZE as usize).unwrap();
+        let base = unsafe {
+            mmap(None, len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, Some(&file), 0)
+        }.unwrap() as usize;
+        let sb = superblock(base);
+
+        // Two whole mapped pages plus a copied remainder.
+        let input = dir.path().join("input.bin");
+        let data: Vec<u8> = (0..PAGE_HEADER_SIZE + 2 * MAPPED_PAGE_FILL + 1000)
+            .map(|i| (i * 31 % 251) as u8)
+            .collect();
+        std::fs::write(&input, &data).unwrap();
+        let loader = SlotLoader::new(base).zero_copy(true);
+
+        loader.load_as_single_record(&input, 3).unwrap();
+        let entry = &sb.mapped_inputs[0];
+        assert_eq!(entry.state.load(Ordering::Acquire), MAPPED_INPUT_LIVE);
+        assert_eq!(entry.pages.load(Ordering::Relaxed), 2);
+        assert!(read_io_records(base, sb, 3) == vec![(3, data.clone())]);
+
+        let offset = entry.offset.load(Ordering::Relaxed);
+        reclaimer::free_io_slot(base, 3);
+        assert_eq!(entry.state.load(Ordering::Acquire), MAPPED_INPUT_RETIRED);
+        assert_eq!(reclaimer::count_class_free_bytes(base), 0);
+
+        // The next load takes the retired extent back.
+        loader.load_as_single_record(&input, 4).unwrap();
+        assert_eq!(entry.state.load(Ordering::Acquire), MAPPED_INPUT_LIVE);
+        assert_eq!(entry.offset.load(Ordering::Relaxed), offset);
+        assert!(read_io_records(base, sb, 4) == vec![(4, data.clone())]);
+
+        // Shorter than one mapped page: copied, no entry taken.
+        let small = dir.path().join("small.bin");
+        std::fs::write(&small, &data[..MAPPED_PAGE_FILL]).unwrap();
+        loader.load_as_single_record(&small, 5).unwrap();
+        assert!((1..MAPPED_INPUT_COUNT)
+            .all(|i| sb.mapped_inputs[i].state.load(Ordering::Relaxed) == MAPPED_INPUT_FREE));
+        assert!(read_io_records(base, sb, 5) == vec![(5, data[..MAPPED_PAGE_FILL].to_vec())]);
+
+        unsafe { munmap(base as *mut _, len.get()).unwrap() };
+    }
+}
@@ -1,4 +1,9 @@
 pub mod slot_loader;
+pub mod record_format;
+pub mod file_set;
 pub mod slot_flusher;
 pub mod logger;
 pub mod persistence;
+pub mod mapped_input;
+pub mod uring;
+pub mod typed_record;
@@ -6,7 +6,7 @@
 //   writer.join();                          // wait for all background writes to finish
 //
 // The calling thread takes a heap snapshot of the requested SHM regions (atomics,
-// stream page-chains, shared-state payloads) and sends it through an mpsc channel.
+// stream page-chains, shared-state payloads, the key-value store) and sends it through an mpsc channel.
 // A single background thread processes the queue and writes the files, so main
 // execution is never blocked waiting for disk I/O.
 //
@@ -14,9 +14,11 @@
 //   atomics.txt          — one "name=value" line per named atomic variable
 //   stream_{id}.txt      — one "[  N] <record>" line per length-prefixed record
 //   shared_{name}.bin    — raw committed payload bytes for each registry entry
+//   kv.txt               — one "key<TAB>value" line per key-value store entry
 
 use std::fs;
 use std::io::Write;
+use std::os::fd::AsRawFd;
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc;
@@ -24,6 +26,11 @@ use std::thread;
 
 use common::*;
 
+use crate::runtime::mem_operation::kv_store::KvStore;
+
+use super::typed_record::RecordSchema;
+use super::uring;
+
 // -----------------------------------------------------------------------------
 // Public options
 // -----------------------------------------------------------------------------
@@ -39,6 +46,8 @@ pub struct PersistenceOptions {
     pub stream_slots: Vec<usize>,
     /// Persist all Manager-committed shared-state payloads visible in the Registry.
     pub shared_state: bool,
+    /// Persist every entry of the key-value store.
+    pub kv: bool,
 }
 
 // -----------------------------------------------------------------------------
@@ -54,13 +63,15 @@ struct Snapshot {
     atomics: Vec<AtomicEntry>,
     streams: Vec<StreamEntry>,
     shared:  Vec<SharedEntry>,
+    /// `None` unless requested; an empty store still writes an empty file.
+    kv:      Option<Vec<(Vec<u8>, Vec<u8>)>>,
 }
 
 // -----------------------------------------------------------------------------
 // Lightweight watch: a single stream slot or shared-state entry
 // -----------------------------------------------------------------------------
 
-/// The item captured by a single `watch_stream` / `watch_shared` call.
+/// The item captured by a single `watch_stream` / `watch_shared` / `watch_kv` call.
 /// Carries only heap-allocated data — no raw pointers, fully `Send`.
 enum WatchItem {
     /// `raw = false`: one `"[  i][src=o] <record>"` text line per record.
@@ -68,6 +79,7 @@ enum WatchItem {
     ///                image payload per round for a `StreamOutput` sink).
     Stream { slot_id: usize, records: Vec<(u32, Vec<u8>)>, output: PathBuf, raw: bool },
     Shared { name: String,   payload: Vec<u8>,             output: PathBuf },
+    Kv     { prefix: String, entries: Vec<(Vec<u8>, Vec<u8>)>, output: PathBuf },
 }
 
 // -----------------------------------------------------------------------------
@@ -126,6 +138,35 @@ impl PersistenceWriter {
         }));
     }
 
+    /// Typed variant of [`watch_stream`]: every record is decoded as `schema`
+    /// in the calling thread and written as its JSON form.  Fails — writing
+    /// nothing — on the first record of another schema.
+    pub fn watch_stream_typed(
+        &self,
+        splice_addr: usize,
+        slot_id: usize,
+        schema: &RecordSchema,
+        output: impl Into<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let sb      = unsafe { &*(splice_addr as *const Superblock) };
+        let records = read_stream_records(splice_addr, sb, slot_id)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (origin, rec))| {
+                let value = schema.decode(&rec)
+                    .map_err(|e| anyhow::anyhow!("stream {} record {}: {}", slot_id, i, e))?;
+                Ok((origin, serde_json::to_vec(&value)?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let _ = self.tx.send(PersistMsg::Watch(WatchItem::Stream {
+            slot_id,
+            records,
+            output: output.into(),
+            raw: false,
+        }));
+        Ok(())
+    }
+
     /// Binary-safe variant of [`watch_stream`]: copies the records of `slot_id`
     /// (Stream or I/O area) and writes their raw concatenated bytes to `output`,
     /// with no text framing.  Use for a `StreamOutput` sink writing one binary
@@ -158,7 +199,7 @@ impl PersistenceWriter {
     pub fn watch_shared(&self, splice_addr: usize, name: &str, output: impl Into<PathBuf>) {
         let sb       = unsafe { &*(splice_addr as *const Superblock) };
         let count    = sb.next_atomic_idx.load(Ordering::Acquire) as usize;
-        let reg_base = splice_addr + REGISTRY_OFFSET as usize;
+        let reg_base = splice_addr + sb.layout.registry_offset as usize;
 
         for i in 0..count {
             let entry      = unsafe { &*((reg_base + i * 64) as *const RegistryEntry) };
@@ -183,6 +224,17 @@ impl PersistenceWriter {
         eprintln!("[PersistenceWriter] watch_shared: '{}' not found in registry", name);
     }
 
+    /// Copy every key-value store entry whose key starts with `prefix` and
+    /// write them to `output` (an exact file path) in the background.
+    pub fn watch_kv(&self, splice_addr: usize, prefix: &str, output: impl Into<PathBuf>) {
+        let entries = KvStore::at(splice_addr).scan(prefix.as_bytes());
+        let _ = self.tx.send(PersistMsg::Watch(WatchItem::Kv {
+            prefix: prefix.to_string(),
+            entries,
+            output: output.into(),
+        }));
+    }
+
     /// Signal the background thread to stop and block until it finishes all
     /// queued writes.
     pub fn join(&mut self) {
@@ -206,8 +258,8 @@ fn take_snapshot(splice_addr: usize, opts: &PersistenceOptions) -> Snapshot {
     // ── Atomics ──────────────────────────────────────────────────────────────
     let mut atomics = Vec::new();
     if opts.atomics {
-        let reg_base  = splice_addr + REGISTRY_OFFSET as usize;
-        let atom_base = splice_addr + ATOMIC_ARENA_OFFSET as usize;
+        let reg_base  = splice_addr + sb.layout.registry_offset as usize;
+        let atom_base = splice_addr + sb.layout.atomic_arena_offset as usize;
         for i in 0..count {
             let entry = unsafe { &*((reg_base + i * std::mem::size_of::<RegistryEntry>()) as *const RegistryEntry) };
             let name_len = entry.name.iter().position(|&b| b == 0).unwrap_or(52);
@@ -229,7 +281,7 @@ fn take_snapshot(splice_addr: usize, opts: &PersistenceOptions) -> Snapshot {
     // ── Shared state ──────────────────────────────────────────────────────────
     let mut shared = Vec::new();
     if opts.shared_state {
-        let reg_base = splice_addr + REGISTRY_OFFSET as usize;
+        let reg_base = splice_addr + sb.layout.registry_offset as usize;
         for i in 0..count {
             let entry = unsafe { &*((reg_base + i * std::mem::size_of::<RegistryEntry>()) as *const RegistryEntry) };
             let payload_offset = entry.payload_offset.load(Ordering::Acquire);
@@ -242,11 +294,15 @@ fn take_snapshot(splice_addr: usize, opts: &PersistenceOptions) -> Snapshot {
         }
     }
 
+    // ── Key-value store ───────────────────────────────────────────────────────
+    let kv = opts.kv.then(|| KvStore::at(splice_addr).scan(b""));
+
     Snapshot {
         output_dir: PathBuf::from(&opts.output_dir),
         atomics,
         streams,
         shared,
+        kv,
     }
 }
 
@@ -256,12 +312,49 @@ fn take_snapshot(splice_addr: usize, opts: &PersistenceOptions) -> Snapshot {
 
 /// Walks the length-prefixed page chain for stream `slot` and returns every record.
 pub(crate) fn read_stream_records(base: usize, sb: &Superblock, slot: usize) -> Vec<(u32, Vec<u8>)> {
-    read_chain_records(base, sb.writer_heads[slot].load(Ordering::Acquire) as ShmOffset)
+    read_chain_records(base, sb.writer_head(slot).load(Ordering::Acquire) as ShmOffset)
 }
 
 /// Walks the length-prefixed page chain for I/O `slot` and returns every record.
 pub(crate) fn read_io_records(base: usize, sb: &Superblock, slot: usize) -> Vec<(u32, Vec<u8>)> {
-    read_chain_records(base, sb.io_heads[slot].load(Ordering::Acquire) as ShmOffset)
+    read_chain_records(base, sb.io_head(slot).load(Ordering::Acquire) as ShmOffset)
+}
+
+/// In-page location of some record bytes: `(address, len)`.
+pub(crate) type Span = (*const u8, usize);
+
+/// Zero-copy walk of I/O `slot`: calls `f(len_field, payload)` with the spans
+/// of each record's 4-byte length field and of its payload.  The spans point
+/// into SHM and stay valid only until the slot is freed.  Returns the number
+/// of records.
+pub(crate) fn io_record_spans(
+    base: usize,
+    sb: &Superblock,
+    slot: usize,
+    mut f: impl FnMut(&[Span], &[Span]),
+) -> usize {
+    let head = sb.io_head(slot).load(Ordering::Acquire) as ShmOffset;
+    if head == 0 { return 0; }
+    let mut reader = PageReader::new(base, head);
+    let (mut len_field, mut payload) = (Vec::new(), Vec::new());
+    let mut n = 0usize;
+    loop {
+        len_field.clear();
+        payload.clear();
+        if !reader.spans(4, &mut len_field) { break; }
+        let mut len_buf = [0u8; 4];
+        let mut at = 0usize;
+        for &(p, len) in &len_field {
+            unsafe { std::ptr::copy_nonoverlapping(p, len_buf.as_mut_ptr().add(at), len) };
+            at += len;
+        }
+        let mut origin_buf = [0u8; 4];
+        if !reader.read(&mut origin_buf) { break; }
+        if !reader.spans(u32::from_le_bytes(len_buf) as usize, &mut payload) { break; }
+        f(&len_field, &payload);
+        n += 1;
+    }
+    n
 }
 
 /// Counts the committed records in stream `slot` without copying payloads.
@@ -273,7 +366,12 @@ pub(crate) fn read_io_records(base: usize, sb: &Superblock, slot: usize) -> Vec<
 /// stage cannot race ahead into records the next round's producer is
 /// concurrently appending to the same slot.
 pub(crate) fn count_stream_records(base: usize, sb: &Superblock, slot: usize) -> usize {
-    count_chain_records(base, sb.writer_heads[slot].load(Ordering::Acquire) as ShmOffset)
+    count_chain_records(base, sb.writer_head(slot).load(Ordering::Acquire) as ShmOffset)
+}
+
+/// Counts the committed records in I/O `slot` without copying payloads.
+pub(crate) fn count_io_records(base: usize, sb: &Superblock, slot: usize) -> usize {
+    count_chain_records(base, sb.io_head(slot).load(Ordering::Acquire) as ShmOffset)
 }
 
 /// Count-only variant of [`read_chain_records`]: walks the chain, reading each
@@ -395,6 +493,29 @@ impl PageReader {
         }
         true
     }
+
+    /// Like [`read`](Self::read) without the copy: push the in-page spans of
+    /// the next `len` bytes to `out`.  Returns `false` if the chain ends early.
+    fn spans(&mut self, len: usize, out: &mut Vec<Span>) -> bool {
+        let mut taken = 0usize;
+        while taken < len {
+            if self.page_offset == 0 { return false; }
+            let page = unsafe {
+                &*((self.base + self.page_offset as usize) as *const Page)
+            };
+            let available = page.cursor.load(Ordering::Acquire).saturating_sub(self.cursor_in_page);
+            if available == 0 {
+                self.page_offset    = page.next_offset.load(Ordering::Acquire) as ShmOffset;
+                self.cursor_in_page = 0;
+                continue;
+            }
+            let n = (available as usize).min(len - taken);
+            out.push((unsafe { page.data.as_ptr().add(self.cursor_in_page as usize) }, n));
+            self.cursor_in_page += n as ShmOffset;
+            taken               += n;
+        }
+        true
+    }
 }
 
 // -----------------------------------------------------------------------------
@@ -408,10 +529,20 @@ fn write_watch(item: WatchItem) {
             match fs::File::create(&output) {
                 Ok(mut f) => {
                     if raw {
-                        let mut bytes = 0usize;
-                        for (_origin, rec) in &records { let _ = f.write_all(rec); bytes += rec.len(); }
-                        println!("[PersistenceWriter] watch slot {} ({} records, {} bytes raw) → {}",
-                            slot_id, records.len(), bytes, output.display());
+                        let bytes: usize = records.iter().map(|(_, rec)| rec.len()).sum();
+                        // One gathered write per round on the shared ring.
+                        let written = match uring::shared() {
+                            Some(ring) => {
+                                let iov: Vec<_> = records.iter().map(|(_, rec)| uring::iovec(rec)).collect();
+                                uring::lock(ring).write_all_at(f.as_raw_fd(), 0, &iov)
+                            }
+                            None => records.iter().try_for_each(|(_, rec)| f.write_all(rec)),
+                        };
+                        match written {
+                            Ok(()) => println!("[PersistenceWriter] watch slot {} ({} records, {} bytes raw) → {}",
+                                slot_id, records.len(), bytes, output.display()),
+                            Err(e) => eprintln!("[PersistenceWriter] watch slot {} write failed: {}", slot_id, e),
+                        }
                     } else {
                         for (i, (origin, rec)) in records.iter().enumerate() {
                             let _ = writeln!(f, "[{:4}][src={}] {}", i, origin, String::from_utf8_lossy(rec));
@@ -431,7 +562,23 @@ fn write_watch(item: WatchItem) {
                 Err(e) => eprintln!("[PersistenceWriter] watch shared '{}' failed: {}", name, e),
             }
         }
+        WatchItem::Kv { prefix, entries, output } => {
+            if let Some(parent) = output.parent() { let _ = fs::create_dir_all(parent); }
+            match fs::File::create(&output).and_then(|mut f| write_kv_lines(&mut f, &entries)) {
+                Ok(()) => println!("[PersistenceWriter] watch kv '{}' ({} entries) → {}",
+                    prefix, entries.len(), output.display()),
+                Err(e) => eprintln!("[PersistenceWriter] watch kv '{}' failed: {}", prefix, e),
+            }
+        }
+    }
+}
+
+/// One `key<TAB>value` line per entry, both decoded as lossy UTF-8.
+fn write_kv_lines(f: &mut fs::File, entries: &[(Vec<u8>, Vec<u8>)]) -> std::io::Result<()> {
+    for (key, value) in entries {
+        writeln!(f, "{}\t{}", String::from_utf8_lossy(key), String::from_utf8_lossy(value))?;
     }
+    Ok(())
 }
 
 fn write_snapshot(s: Snapshot) {
@@ -482,4 +629,13 @@ fn write_snapshot(s: Snapshot) {
             Err(e) => eprintln!("[PersistenceWriter] shared '{}' write failed: {}", ss.name, e),
         }
     }
+
+    // kv.txt — one "key<TAB>value" per line
+    if let Some(entries) = &s.kv {
+        let path = s.output_dir.join("kv.txt");
+        match fs::File::create(&path).and_then(|mut f| { write_kv_lines(&mut f, entries)?; f.sync_all() }) {
+            Ok(()) => println!("[PersistenceWriter] kv ({} entries) → {}", entries.len(), path.display()),
+            Err(e) => eprintln!("[PersistenceWriter] kv write failed: {}", e),
+        }
+    }
 }
@@ -0,0 +1,597 @@
+// Input/output record framing and transparent (de)compression.
+//
+// # RecordFormat
+//
+// Describes how an input file is cut into records before `SlotLoader` appends
+// them to an I/O slot.  `Lines` is the historical behaviour (one record per
+// non-empty `\n`-terminated line); the others cover corpora that are not
+// plain text:
+//
+//   Lines           — one record per non-empty line (default).
+//   Jsonl           — JSON Lines: like `Lines`, but `\r` is stripped and
+//                     whitespace-only lines are skipped.
+//   Csv             — RFC 4180-style rows: a `\n` inside a quoted field does
+//                     NOT end the record; optional header row is skipped.
+//   Fixed           — fixed-size binary records (e.g. TeraSort's 100 bytes).
+//   LengthPrefixed  — `[len: u32 LE][payload]` frames.
+//
+// Framing is always driven from a record boundary (offset 0, or the end of the
+// previous record), so quote state and length prefixes never have to be
+// guessed from the middle of a file.  `resync` reports whether a format can
+// instead find the next boundary from an arbitrary byte offset, which lets
+// `load_slice` jump straight to its window for self-synchronising formats.
+//
+// # Compression
+//
+// `open_decoder` wraps a file in a streaming gzip (multi-member) or zstd
+// decoder.  `Compression::Auto` sniffs the magic bytes, so plain files keep
+// taking the zero-copy mmap path in `SlotLoader`.
+//
+// # Output side
+//
+// `OutputFraming` is the `SlotFlusher` counterpart of `RecordFormat`, and
+// `Encoder` the counterpart of `open_decoder`; for output, `Compression::Auto`
+// is resolved from the destination's extension (`.gz` / `.zst`).
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+// ─── Compression ─────────────────────────────────────────────────────────────
+
+/// Compression of an `Input` file.
+///
+/// ```json
+/// "compression": "auto" | "none" | "gzip" | "zstd"
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// Detect gzip (`1f 8b`) or zstd (`28 b5 2f fd`) from the magic bytes.
+    #[default]
+    Auto,
+    None,
+    Gzip,
+    Zstd,
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+impl Compression {
+    /// Resolve `Auto` by sniffing the first bytes of `path`.
+    pub fn resolve(self, path: &Path) -> Result<Compression> {
+        if self != Compression::Auto {
+            return Ok(self);
+        }
+        let mut head = [0u8; 4];
+        let mut f = File::open(path)
+            .map_err(|e| anyhow!("Cannot open '{}': {}", path.display(), e))?;
+        let mut n = 0;
+        while n < head.len() {
+            match f.read(&mut head[n..])? {
+                0 => break,
+                k => n += k,
+            }
+        }
+        Ok(if n >= 4 && head == ZSTD_MAGIC {
+            Compression::Zstd
+        } else if n >= 2 && head[..2] == GZIP_MAGIC {
+            Compression::Gzip
+        } else {
+            Compression::None
+        })
+    }
+}
+
+impl Compression {
+    /// Resolve `Auto` for an output file from its extension.
+    pub fn for_output(self, path: &Path) -> Compression {
+        if self != Compression::Auto {
+            return self;
+        }
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") | Some("gzip") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Open `path` as a streaming reader of its **decompressed** bytes.
+/// `compression` must already be resolved (not `Auto`).
+pub fn open_decoder(path: &Path, compression: Compression) -> Result<Box<dyn Read + Send>> {
+    let file = File::open(path)
+        .map_err(|e| anyhow!("Cannot open '{}': {}", path.display(), e))?;
+    let buffered = BufReader::with_capacity(1 << 20, file);
+    Ok(match compression {
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(buffered)),
+        Compression::Zstd => Box::new(
+            zstd::stream::read::Decoder::with_buffer(buffered)
+                .map_err(|e| anyhow!("zstd init failed for '{}': {}", path.display(), e))?,
+        ),
+        Compression::None | Compression::Auto => Box::new(buffered),
+    })
+}
+
+/// Compressing writer over an output file (or any sink, e.g. an O_DIRECT
+/// `DirectFile`).  Must be closed with [`finish`](Encoder::finish) —
+/// dropping it loses the compressed trailer.
+pub enum Encoder<W: Write = File> {
+    Plain(BufWriter<W>),
+    Gzip(flate2::write::GzEncoder<BufWriter<W>>),
+    Zstd(zstd::stream::write::Encoder<'static, BufWriter<W>>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Wrap `file`; `compression` must already be resolved (not `Auto`).
+    pub fn new(file: W, compression: Compression) -> Result<Self> {
+        let w = BufWriter::with_capacity(1 << 20, file);
+        Ok(match compression {
+            Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(w, flate2::Compression::default())),
+            Compression::Zstd => Encoder::Zstd(zstd::stream::write::Encoder::new(w, 0)?),
+            Compression::None | Compression::Auto => Encoder::Plain(w),
+        })
+    }
+
+    /// Flush every buffered and compressed byte and return the file.
+    pub fn finish(self) -> Result<W> {
+        let w = match self {
+            Encoder::Plain(w) => w,
+            Encoder::Gzip(e) => e.finish()?,
+            Encoder::Zstd(e) => e.finish()?,
+        };
+        w.into_inner().map_err(|e| anyhow!("flush failed: {}", e.error()))
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Encoder::Plain(w) => w.write(buf),
+            Encoder::Gzip(e) => e.write(buf),
+            Encoder::Zstd(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Encoder::Plain(w) => w.flush(),
+            Encoder::Gzip(e) => e.flush(),
+            Encoder::Zstd(e) => e.flush(),
+        }
+    }
+}
+
+// ─── OutputFraming ───────────────────────────────────────────────────────────
+
+/// Record framing of an `Output` file.
+///
+/// ```json
+/// "framing": "newline" | "length_prefixed" | "raw" | "jsonl"
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFraming {
+    /// Record bytes followed by `\n` (default, historical behaviour).
+    #[default]
+    Newline,
+    /// `[len: u32 LE][payload]` — readable back with `RecordFormat::LengthPrefixed`.
+    LengthPrefixed,
+    /// Records concatenated verbatim, no separator.
+    Raw,
+    /// One JSON value per line.  Records that already parse as JSON are
+    /// written as-is (compacted if multi-line); anything else is written as a
+    /// JSON string.
+    Jsonl,
+}
+
+impl OutputFraming {
+    /// Write one framed record to `w`.
+    pub fn write_record<W: Write>(self, w: &mut W, rec: &[u8]) -> Result<()> {
+        match self {
+            OutputFraming::Newline => {
+                w.write_all(rec)?;
+                w.write_all(b"\n")?;
+            }
+            OutputFraming::LengthPrefixed => {
+                w.write_all(&(rec.len() as u32).to_le_bytes())?;
+                w.write_all(rec)?;
+            }
+            OutputFraming::Raw => w.write_all(rec)?,
+            OutputFraming::Jsonl => {
+                match serde_json::from_slice::<serde_json::Value>(rec) {
+                    Ok(_) if !rec.contains(&b'\n') => w.write_all(rec)?,
+                    // Pretty-printed JSON: re-serialise compactly to keep one value per line.
+                    Ok(v) => serde_json::to_writer(&mut *w, &v)?,
+                    Err(_) => serde_json::to_writer(&mut *w, &String::from_utf8_lossy(rec))?,
+                }
+                w.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ─── RecordFormat ────────────────────────────────────────────────────────────
+
+/// Record framing of an `Input` file.
+///
+/// ```json
+/// "format": { "type": "Lines" }
+/// "format": { "type": "Jsonl" }
+/// "format": { "type": "Csv", "header": true }
+/// "format": { "type": "Fixed", "record_bytes": 100 }
+/// "format": { "type": "LengthPrefixed" }
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, Default)]
+#[serde(tag = "type")]
+pub enum RecordFormat {
+    #[default]
+    Lines,
+    Jsonl,
+    Csv {
+        /// Skip the first row of the file.
+        #[serde(default)]
+        header: bool,
+    },
+    Fixed { record_bytes: usize },
+    LengthPrefixed,
+}
+
+/// One framed record: `len` bytes of input consumed, of which
+/// `payload_start..payload_end` is the record body.  `payload` is `None` for
+/// frames that consume input but emit nothing (blank lines).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub len: usize,
+    pub payload: Option<(usize, usize)>,
+}
+
+impl RecordFormat {
+    /// Short name used in loader log lines.
+    pub fn label(&self) -> &'static str {
+        match self {
+            RecordFormat::Lines            => "lines",
+            RecordFormat::Jsonl            => "jsonl",
+            RecordFormat::Csv { .. }       => "csv",
+            RecordFormat::Fixed { .. }     => "fixed",
+            RecordFormat::LengthPrefixed   => "length-prefixed",
+        }
+    }
+
+    /// Reject parameter combinations that can never frame anything.
+    pub fn validate(&self) -> Result<()> {
+        if let RecordFormat::Fixed { record_bytes: 0 } = self {
+            return Err(anyhow!("format Fixed: record_bytes must be > 0"));
+        }
+        Ok(())
+    }
+
+    /// Whether the record starting at absolute offset `start` is a header row
+    /// that must be skipped.
+    #[inline]
+    pub fn is_header(&self, start: u64) -> bool {
+        start == 0 && matches!(self, RecordFormat::Csv { header: true })
+    }
+
+    /// Frame the record at the start of `buf`.
+    ///
+    /// `buf` must begin on a record boundary.  Returns `Ok(None)` when `buf`
+    /// holds no complete record yet and more input may follow (`eof == false`),
+    /// or when `buf` is empty.  At `eof` an unterminated trailing line is a
+    /// record; a truncated binary frame is an error.
+    pub fn next_frame(&self, buf: &[u8], eof: bool) -> Result<Option<Frame>> {
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        match self {
+            RecordFormat::Lines | RecordFormat::Jsonl => {
+                let (body, len) = match buf.iter().position(|&b| b == b'\n') {
+                    Some(nl) => (nl, nl + 1),
+                    None if eof => (buf.len(), buf.len()),
+                    None => return Ok(None),
+                };
+                Ok(Some(Frame { len, payload: self.line_payload(&buf[..body]) }))
+            }
+            RecordFormat::Csv { .. } => {
+                let mut in_quotes = false;
+                let mut end = None;
+                for (i, &b) in buf.iter().enumerate() {
+                    match b {
+                        b'"' => in_quotes = !in_quotes,
+                        b'\n' if !in_quotes => { end = Some(i); break; }
+                        _ => {}
+                    }
+                }
+                let (body, len) = match end {
+                    Some(nl) => (nl, nl + 1),
+                    None if eof => (buf.len(), buf.len()),
+                    None => return Ok(None),
+                };
+                let mut stop = body;
+                if stop > 0 && buf[stop - 1] == b'\r' { stop -= 1; }
+                let payload = if stop == 0 { None } else { Some((0, stop)) };
+                Ok(Some(Frame { len, payload }))
+            }
+            RecordFormat::Fixed { record_bytes } => {
+                let rb = *record_bytes;
+                if buf.len() >= rb {
+                    Ok(Some(Frame { len: rb, payload: Some((0, rb)) }))
+                } else if eof {
+                    Err(anyhow!(
+                        "format Fixed: trailing {} bytes do not form a whole {}-byte record",
+                        buf.len(), rb
+                    ))
+                } else {
+                    Ok(None)
+                }
+            }
+            RecordFormat::LengthPrefixed => {
+                if buf.len() < 4 {
+                    return if eof {
+                        Err(anyhow!("format LengthPrefixed: truncated length prefix ({} bytes)", buf.len()))
+                    } else {
+                        Ok(None)
+                    };
+                }
+                let n = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+                if buf.len() - 4 >= n {
+                    Ok(Some(Frame { len: 4 + n, payload: Some((4, 4 + n)) }))
+                } else if eof {
+                    Err(anyhow!(
+                        "format LengthPrefixed: record of {} bytes truncated at {} bytes",
+                        n, buf.len() - 4
+                    ))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// For self-synchronising formats, the first record boundary at or after
+    /// byte `p` of `data`.  `None` means the format can only be framed from
+    /// the start (`Csv`: quote state; `LengthPrefixed`: prefixes).
+    pub fn resync(&self, data: &[u8], p: usize) -> Option<usize> {
+        let total = data.len();
+        match self {
+            RecordFormat::Lines | RecordFormat::Jsonl => {
+                if p == 0 { return Some(0); }
+                if p >= total { return Some(total); }
+                if data[p - 1] == b'\n' { return Some(p); }
+                Some(match data[p..].iter().position(|&b| b == b'\n') {
+                    Some(rel) => p + rel + 1,
+                    None => total,
+                })
+            }
+            RecordFormat::Fixed { record_bytes } => {
+                Some(p.div_ceil(*record_bytes).saturating_mul(*record_bytes).min(total))
+            }
+            RecordFormat::Csv { .. } | RecordFormat::LengthPrefixed => None,
+        }
+    }
+
+    fn line_payload(&self, line: &[u8]) -> Option<(usize, usize)> {
+        match self {
+            RecordFormat::Jsonl => {
+                let mut stop = line.len();
+                if stop > 0 && line[stop - 1] == b'\r' { stop -= 1; }
+                if line[..stop].iter().all(|b| b.is_ascii_whitespace()) { None } else { Some((0, stop)) }
+            }
+            _ => if line.is_empty() { None } else { Some((0, line.len())) },
+        }
+    }
+}
+
+// ─── Streaming framer ────────────────────────────────────────────────────────
+
+/// Pulls framed records out of a decompressing reader, keeping at most one
+/// read block plus one partial record buffered.  Tracks the absolute
+/// (decompressed) offset of the next record so slice windows and chunk
+/// progress can be expressed in the same units as for plain files.
+pub struct StreamFramer {
+    reader: Box<dyn Read + Send>,
+    format: RecordFormat,
+    buf: Vec<u8>,
+    pos: usize,
+    offset: u64,
+    eof: bool,
+}
+
+const READ_BLOCK: usize = 1 << 20;
+
+impl StreamFramer {
+    pub fn new(reader: Box<dyn Read + Send>, format: RecordFormat) -> Self {
+        Self { reader, format, buf: Vec::new(), pos: 0, offset: 0, eof: false }
+    }
+
+    /// Absolute offset of the next unread record.
+    #[inline] pub fn offset(&self) -> u64 { self.offset }
+
+    /// True once the underlying stream is exhausted and every record consumed.
+    #[inline] pub fn is_done(&self) -> bool { self.eof && self.pos >= self.buf.len() }
+
+    /// Read ahead until either a byte is buffered or the stream ends, so
+    /// `is_done` is accurate right after the last record is consumed.
+    pub fn is_exhausted(&mut self) -> Result<bool> {
+        while !self.eof && self.pos >= self.buf.len() {
+            self.fill()?;
+        }
+        Ok(self.is_done())
+    }
+
+    /// Advance to the next record and hand `(start_offset, payload)` to `f`.
+    /// Frames without a payload (blank lines) are skipped transparently.
+    /// Returns `Ok(false)` at end of input.
+    pub fn next_record<F: FnMut(u64, &[u8]) -> Result<()>>(&mut self, mut f: F) -> Result<bool> {
+        loop {
+            match self.format.next_frame(&self.buf[self.pos..], self.eof)? {
+                Some(frame) => {
+                    let start = self.offset;
+                    let base = self.pos;
+                    self.pos += frame.len;
+                    self.offset += frame.len as u64;
+                    if let Some((a, b)) = frame.payload {
+                        f(start, &self.buf[base + a..base + b])?;
+                        return Ok(true);
+                    }
+                }
+                None if self.eof => return Ok(false),
+                None => self.fill()?,
+            }
+        }
+    }
+
+    fn fill(&mut self) -> Result<()> {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        let old = self.buf.len();
+        self.buf.resize(old + READ_BLOCK, 0);
+        let n = self.reader.read(&mut self.buf[old..])
+            .map_err(|e| anyhow!("decompression failed: {}", e))?;
+        self.buf.truncate(old + n);
+        if n == 0 { self.eof = true; }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frame_all(fmt: &RecordFormat, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut p = 0;
+        while let Some(fr) = fmt.next_frame(&data[p..], true).unwrap() {
+            if let Some((a, b)) = fr.payload { out.push(data[p + a..p + b].to_vec()); }
+            p += fr.len;
+        }
+        out
+    }
+
+    #[test]
+    fn csv_keeps_quoted_newlines_together() {
+        let data = b"id,text\r\n1,\"a\nb\"\r\n2,\"say \"\"hi\"\"\"\n\n3,c";
+        let recs = frame_all(&RecordFormat::Csv { header: true }, data);
+        assert_eq!(recs, vec![
+            b"id,text".to_vec(),
+            b"1,\"a\nb\"".to_vec(),
+            b"2,\"say \"\"hi\"\"\"".to_vec(),
+            b"3,c".to_vec(),
+        ]);
+        assert!(RecordFormat::Csv { header: true }.is_header(0));
+        assert!(RecordFormat::Csv { header: false }.resync(data, 5).is_none());
+    }
+
+    #[test]
+    fn jsonl_strips_cr_and_blank_lines() {
+        let recs = frame_all(&RecordFormat::Jsonl, b"{\"a\":1}\r\n  \n{\"b\":2}");
+        assert_eq!(recs, vec![b"{\"a\":1}".to_vec(), b"{\"b\":2}".to_vec()]);
+    }
+
+    #[test]
+    fn fixed_and_length_prefixed_frames() {
+        let fixed = RecordFormat::Fixed { record_bytes: 3 };
+        assert_eq!(frame_all(&fixed, b"abcdef"), vec![b"abc".to_vec(), b"def".to_vec()]);
+        assert!(fixed.next_frame(b"ab", true).is_err());
+        assert_eq!(fixed.next_frame(b"ab", false).unwrap(), None);
+        assert_eq!(fixed.resync(b"abcdefgh", 4), Some(6));
+
+        let mut lp = Vec::new();
+        for p in [&b"xy"[..], b"", b"hello"] {
+            lp.extend_from_slice(&(p.len() as u32).to_le_bytes());
+            lp.extend_from_slice(p);
+        }
+        assert_eq!(
+            frame_all(&RecordFormat::LengthPrefixed, &lp),
+            vec![b"xy".to_vec(), b"".to_vec(), b"hello".to_vec()]
+        );
+        assert!(RecordFormat::LengthPrefixed.next_frame(&lp[10..16], true).is_err());
+        assert!(RecordFormat::LengthPrefixed.next_frame(&lp[10..12], true).is_err());
+    }
+
+    #[test]
+    fn line_resync_owns_records_starting_in_window() {
+        let data = b"aa\nbb\ncc\n";
+        let f = RecordFormat::Lines;
+        assert_eq!(f.resync(data, 0), Some(0));
+        assert_eq!(f.resync(data, 3), Some(3));
+        assert_eq!(f.resync(data, 4), Some(6));
+        assert_eq!(f.resync(data, 9), Some(9));
+    }
+
+    #[test]
+    fn stream_framer_handles_records_across_reads() {
+        let mut data = Vec::new();
+        for i in 0..50_000u32 {
+            data.extend_from_slice(format!("line-{}\n", i).as_bytes());
+        }
+        let mut fr = StreamFramer::new(Box::new(Cursor::new(data.clone())), RecordFormat::Lines);
+        let mut n = 0u32;
+        let mut last_start = 0u64;
+        while fr.next_record(|start, rec| {
+            assert_eq!(rec, format!("line-{}", n).as_bytes());
+            last_start = start;
+            Ok(())
+        }).unwrap() {
+            n += 1;
+        }
+        assert_eq!(n, 50_000);
+        assert!(fr.is_done());
+        assert_eq!(fr.offset(), data.len() as u64);
+        assert_eq!(&data[last_start as usize..], b"line-49999\n");
+    }
+
+    #[test]
+    fn output_framing_round_trips() {
+        let mut lp = Vec::new();
+        OutputFraming::LengthPrefixed.write_record(&mut lp, b"ab").unwrap();
+        OutputFraming::LengthPrefixed.write_record(&mut lp, b"").unwrap();
+        assert_eq!(frame_all(&RecordFormat::LengthPrefixed, &lp), vec![b"ab".to_vec(), b"".to_vec()]);
+
+        let mut jl = Vec::new();
+        OutputFraming::Jsonl.write_record(&mut jl, br#"{"k":1}"#).unwrap();
+        OutputFraming::Jsonl.write_record(&mut jl, b"say \"hi\"").unwrap();
+        assert_eq!(jl, b"{\"k\":1}\n\"say \\\"hi\\\"\"\n".to_vec());
+    }
+
+    #[test]
+    fn zstd_encoder_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.zst");
+        let c = Compression::Auto.for_output(&path);
+        assert_eq!(c, Compression::Zstd);
+        let mut enc = Encoder::new(File::create(&path).unwrap(), c).unwrap();
+        OutputFraming::Newline.write_record(&mut enc, b"x").unwrap();
+        enc.finish().unwrap();
+
+        assert_eq!(Compression::Auto.resolve(&path).unwrap(), Compression::Zstd);
+        let mut out = String::new();
+        open_decoder(&path, c).unwrap().read_to_string(&mut out).unwrap();
+        assert_eq!(out, "x\n");
+    }
+
+    #[test]
+    fn gzip_is_detected_and_decoded() {
+        use std::io::Write;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("in.gz");
+        let mut enc = flate2::write::GzEncoder::new(File::create(&path).unwrap(), flate2::Compression::fast());
+        enc.write_all(b"one\ntwo\n").unwrap();
+        enc.finish().unwrap();
+
+        let c = Compression::Auto.resolve(&path).unwrap();
+        assert_eq!(c, Compression::Gzip);
+        let mut fr = StreamFramer::new(open_decoder(&path, c).unwrap(), RecordFormat::Lines);
+        let mut recs = Vec::new();
+        while fr.next_record(|_, r| { recs.push(r.to_vec()); Ok(()) }).unwrap() {}
+        assert_eq!(recs, vec![b"one".to_vec(), b"two".to_vec()]);
+    }
+}
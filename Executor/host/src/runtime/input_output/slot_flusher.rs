@@ -3,28 +3,325 @@
 // The guest calls `ShmApi::write_output` / `ShmApi::write_output_str` to
 // append final result records into `OUTPUT_SLOT_ID`, or calls
 // `ShmApi::write_output_to(slot, data)` to target any other slot.  After
-// the producing DAG node completes, an `Output` node triggers `SlotFlusher::save`
-// (or `save_slot` for a non-default slot) which reads those records from SHM
-// and writes them to the given file path, one record per line.
+// the producing DAG node completes, an `Output` node triggers
+// `SlotFlusher::save_slot_with` with the node's framing, compression and
+// partition options, which reads those records from SHM and writes them to
+// the given file path (by default one record per line).
 //
 // No background threads: the read and write happen synchronously so the output
 // file is guaranteed to exist before the next node runs.
 //
+// # Commit
+//
+// Files are written to a hidden temp file in the destination directory,
+// fsynced, and renamed over the destination only once every byte (and, for
+// partitioned output, every part) is written.  A crash mid-write therefore
+// leaves either the previous result or no result — never a truncated one that
+// downstream tooling could mistake for valid output.  Temp files of a failed
+// write are removed.
+//
+// The parts of a partitioned output are renamed one by one, so the set is
+// made atomic by a manifest (`manifest_path`): it is removed before the first
+// rename and rewritten after the last, once stale parts from an earlier run
+// with more partitions are deleted.  Readers that need a consistent part set
+// read the manifest and treat a missing one as an incomplete output.
+//
+// # io_uring and O_DIRECT
+//
+// With the `uring` I/O backend up, a newline / length-prefixed / raw output
+// with no compression and no partition is written by gathered WRITEV ops
+// whose iovecs point into the slot's pages — the records are never copied to
+// the heap (see `uring`).  `direct: true` encodes into a `DirectFile`
+// instead, bypassing the page cache; the two do not combine, since O_DIRECT
+// needs aligned buffers.
+//
 // Usage:
 //   let outputer = SlotFlusher::new(splice_addr);
 //   // Default slot (OUTPUT_SLOT_ID):
 //   let n = outputer.save(Path::new("/tmp/result.txt"))?;
-//   // Explicit slot:
-//   let n = outputer.save_slot(Path::new("/tmp/result.txt"), 42)?;
+//   // Explicit slot, framing / compression / partitioning:
+//   let opts = OutputOptions { compression: Compression::Gzip, ..Default::default() };
+//   let n = outputer.save_slot_with(Path::new("/tmp/result.txt.gz"), 42, &opts)?;
 
 use std::fs;
-use std::io::Write;
-use std::path::Path;
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
 use common::{OUTPUT_IO_SLOT, Superblock};
 
-use super::persistence::read_io_records;
+use super::persistence::{io_record_spans, read_io_records, Span};
+use super::record_format::{Compression, Encoder, OutputFraming};
+use super::typed_record::RecordSchema;
+use super::uring::{self, DirectFile, Ring};
+
+// ─── Output options ───────────────────────────────────────────────────────────
+
+/// Split one slot's records across `files` output files.
+///
+/// ```json
+/// "partition": { "type": "Index",   "files": 4 }
+/// "partition": { "type": "KeyHash", "files": 4, "key_delimiter": "\t" }
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum OutputPartition {
+    /// Contiguous ranges by record index: part `k` holds records
+    /// `[k·n/files, (k+1)·n/files)`, so concatenating the parts in order
+    /// reproduces the slot (keeps sorted output sorted).
+    Index { files: usize },
+    /// FNV-1a hash of the record key, modulo `files`.  The key is the bytes
+    /// before the first `key_delimiter`, or the whole record when absent.
+    /// Stable across runs and machines, so equal keys always share a part.
+    KeyHash {
+        files: usize,
+        #[serde(default)]
+        key_delimiter: Option<String>,
+    },
+}
+
+impl OutputPartition {
+    fn files(&self) -> usize {
+        match self {
+            OutputPartition::Index { files } | OutputPartition::KeyHash { files, .. } => *files,
+        }
+    }
+
+    /// Part index of record `i` (of `n`) with payload `rec`.
+    fn part_of(&self, i: usize, n: usize, rec: &[u8]) -> usize {
+        match self {
+            OutputPartition::Index { files } => i * files / n.max(1),
+            OutputPartition::KeyHash { files, key_delimiter } => {
+                let key = match key_delimiter.as_deref().map(str::as_bytes) {
+                    Some(d) if !d.is_empty() => rec.windows(d.len())
+                        .position(|w| w == d)
+                        .map_or(rec, |at| &rec[..at]),
+                    _ => rec,
+                };
+                (fnv1a(key) % *files as u64) as usize
+            }
+        }
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
+
+/// How `SlotFlusher::save_slot_with` encodes and commits a slot.
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    pub framing: OutputFraming,
+    /// `Auto` picks gzip/zstd from the destination extension.
+    pub compression: Compression,
+    pub partition: Option<OutputPartition>,
+    /// Write to a temp file and rename into place (default `true`).
+    pub atomic: bool,
+    /// Open output files with O_DIRECT (see `uring::DirectFile`).
+    pub direct: bool,
+    /// Decode every record as this typed-record schema and write it as
+    /// compact JSON (see `typed_record`).
+    pub schema: Option<RecordSchema>,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            framing: OutputFraming::Newline,
+            compression: Compression::Auto,
+            partition: None,
+            atomic: true,
+            direct: false,
+            schema: None,
+        }
+    }
+}
+
+/// Path of part `k` of a partitioned output: `{part}` in `path` is replaced
+/// by the zero-padded index, otherwise `.part-NNNNN` is inserted before the
+/// last extension (`out.txt.gz` → `out.txt.part-00003.gz`).
+pub fn part_path(path: &Path, k: usize) -> PathBuf {
+    let tag = format!("{:05}", k);
+    let s = path.to_string_lossy();
+    if s.contains("{part}") {
+        return PathBuf::from(s.replace("{part}", &tag));
+    }
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.part-{}.{}", stem, tag, ext.to_string_lossy()),
+        None => format!("{}.part-{}", stem, tag),
+    };
+    path.with_file_name(name)
+}
+
+/// Create the parent directories of `dest` and return the path to write:
+/// a hidden temp file next to it in atomic mode, `dest` itself otherwise.
+fn write_path(dest: &Path, atomic: bool) -> Result<(PathBuf, Option<PathBuf>)> {
+    if let Some(parent) = dest.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let tmp = atomic.then(|| {
+        let name = dest.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        dest.with_file_name(format!(".{}.tmp-{}", name, std::process::id()))
+    });
+    Ok((tmp.clone().unwrap_or_else(|| dest.to_path_buf()), tmp))
+}
+
+/// Sink under an output `Encoder`.
+enum OutputFile {
+    Plain(fs::File),
+    Direct(DirectFile),
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputFile::Plain(f) => f.write(buf),
+            OutputFile::Direct(f) => f.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::Plain(f) => f.flush(),
+            OutputFile::Direct(f) => f.flush(),
+        }
+    }
+}
+
+/// An output file being written; `commit` makes it visible at `dest`.
+struct PendingFile {
+    dest: PathBuf,
+    tmp: Option<PathBuf>,
+    enc: Encoder<OutputFile>,
+}
+
+impl PendingFile {
+    fn create(dest: &Path, compression: Compression, atomic: bool, direct: bool) -> Result<Self> {
+        let (target, tmp) = write_path(dest, atomic)?;
+        let file = if direct {
+            OutputFile::Direct(DirectFile::create(&target)
+                .map_err(|e| anyhow!("cannot create '{}': {}", dest.display(), e))?)
+        } else {
+            OutputFile::Plain(fs::File::create(&target)
+                .map_err(|e| anyhow!("cannot create '{}': {}", dest.display(), e))?)
+        };
+        Ok(Self { dest: dest.to_path_buf(), tmp, enc: Encoder::new(file, compression)? })
+    }
+
+    /// Finish compression, then (atomic mode) fsync and rename into place.
+    fn finish(self) -> Result<Option<(PathBuf, PathBuf)>> {
+        let file = match self.enc.finish()? {
+            OutputFile::Plain(f) => f,
+            OutputFile::Direct(f) => f.finish()?,
+        };
+        match self.tmp {
+            Some(tmp) => {
+                file.sync_all()?;
+                Ok(Some((tmp, self.dest)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Rename every finished temp file over its destination, then fsync the
+/// directories so the renames themselves survive a crash.
+fn commit_all(renames: Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for (tmp, dest) in renames {
+        fs::rename(&tmp, &dest)
+            .map_err(|e| anyhow!("commit '{}' → '{}' failed: {}", tmp.display(), dest.display(), e))?;
+        let dir = parent_dir(&dest);
+        if !dirs.iter().any(|d| d == dir) { dirs.push(dir.to_path_buf()); }
+    }
+    dirs.iter().try_for_each(|d| sync_dir(d))
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    }
+}
+
+fn sync_dir(dir: &Path) -> Result<()> {
+    fs::File::open(dir)
+        .and_then(|d| d.sync_all())
+        .map_err(|e| anyhow!("cannot sync directory '{}': {}", dir.display(), e))
+}
+
+/// Manifest of a partitioned output: `{part}` in `path` is replaced by
+/// `manifest`, otherwise `.manifest` is appended (`out.txt.gz` →
+/// `out.txt.gz.manifest`).  It lists the part file names of the last complete
+/// commit, one per line.
+pub fn manifest_path(path: &Path) -> PathBuf {
+    let s = path.to_string_lossy();
+    if s.contains("{part}") {
+        return PathBuf::from(s.replace("{part}", "manifest"));
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".manifest");
+    path.with_file_name(name)
+}
+
+/// Remove the manifest of partitioned output `path`, durably, before any of
+/// its parts change.
+fn remove_manifest(path: &Path) -> Result<()> {
+    let manifest = manifest_path(path);
+    match fs::remove_file(&manifest) {
+        Ok(()) => sync_dir(parent_dir(&manifest)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(anyhow!("cannot remove '{}': {}", manifest.display(), e)),
+    }
+}
+
+/// Commit the `files` parts of partitioned output `path`.  A set of renames
+/// is not atomic, so the manifest goes first and is rewritten last: whenever
+/// it exists, the parts it lists come from one complete write.  Parts past
+/// `files` — left by an earlier run with more partitions — are deleted.
+fn commit_parts(path: &Path, files: usize, renames: Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    remove_manifest(path)?;
+    commit_all(renames)?;
+
+    let mut stale_dirs: Vec<PathBuf> = Vec::new();
+    for k in files.. {
+        let stale = part_path(path, k);
+        match fs::remove_file(&stale) {
+            Ok(()) => {
+                let dir = parent_dir(&stale);
+                if !stale_dirs.iter().any(|d| d == dir) { stale_dirs.push(dir.to_path_buf()); }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+            Err(e) => return Err(anyhow!("cannot remove stale part '{}': {}", stale.display(), e)),
+        }
+    }
+    stale_dirs.iter().try_for_each(|d| sync_dir(d))?;
+
+    let manifest = manifest_path(path);
+    let listing: String = (0..files)
+        .map(|k| format!("{}\n", part_path(path, k).file_name().unwrap_or_default().to_string_lossy()))
+        .collect();
+    let (tmp, _) = write_path(&manifest, true)?;
+    let written = fs::File::create(&tmp)
+        .and_then(|mut f| { f.write_all(listing.as_bytes())?; f.sync_all() })
+        .map_err(|e| anyhow!("cannot write '{}': {}", manifest.display(), e));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    commit_all(vec![(tmp, manifest)])
+}
 
 pub struct SlotFlusher {
     splice_addr: usize,
@@ -37,34 +334,99 @@ impl SlotFlusher {
 
     /// Read all records from `OUTPUT_SLOT_ID` and write them to `path`.
     ///
-    /// Convenience wrapper around `save_slot(path, OUTPUT_IO_SLOT)`.
+    /// Each record occupies one line (terminated by `\n`).  Parent directories
+    /// are created if absent.  Returns the number of records written, which is
+    /// zero when no worker wrote to the slot during the run.
     pub fn save(&self, path: &Path) -> Result<usize> {
-        self.save_slot(path, OUTPUT_IO_SLOT)
+        self.save_slot_with(path, OUTPUT_IO_SLOT, &OutputOptions::default())
     }
 
-    /// Read all records from `slot` and write them to `path`.
-    ///
-    /// Each record occupies one line (terminated by `\n`).  Parent directories
-    /// are created if absent.  Returns the number of records written, which is
-    /// zero when no worker wrote to this slot during the run.
-    pub fn save_slot(&self, path: &Path, slot: u32) -> Result<usize> {
+    /// Read all records from `slot` and write them to `path` using `opts`
+    /// (framing, compression, partitioning, atomic commit).  With a partition
+    /// every part file is written — empty parts included, so the part set is
+    /// always complete — and nothing is renamed into place until all parts
+    /// succeeded.  Returns the number of records written.
+    pub fn save_slot_with(&self, path: &Path, slot: u32, opts: &OutputOptions) -> Result<usize> {
+        let compression = opts.compression.for_output(path);
+        let gatherable = opts.framing != OutputFraming::Jsonl
+            && compression == Compression::None
+            && opts.partition.is_none()
+            && opts.schema.is_none()
+            && !opts.direct;
+        if let Some(ring) = uring::shared().filter(|_| gatherable) {
+            return self.save_gathered(ring, path, slot, opts);
+        }
+
         let records = self.collect_slot(slot);
         let count = records.len();
 
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+        let dests: Vec<PathBuf> = match &opts.partition {
+            Some(p) if p.files() == 0 => return Err(anyhow!("output partition: files must be > 0")),
+            Some(p) => (0..p.files()).map(|k| part_path(path, k)).collect(),
+            None => vec![path.to_path_buf()],
+        };
+
+        // In place, parts change as they are written: drop the manifest first.
+        if opts.partition.is_some() && !opts.atomic {
+            remove_manifest(path)?;
         }
 
-        let mut f = fs::File::create(path)?;
-        for (_origin, rec) in &records {
-            f.write_all(rec)?;
-            f.write_all(b"\n")?;
+        let mut pending: Vec<PendingFile> = Vec::with_capacity(dests.len());
+        let result = (|| -> Result<()> {
+            for dest in &dests {
+                pending.push(PendingFile::create(dest, compression, opts.atomic, opts.direct)?);
+            }
+            let mut decoded = Vec::new();
+            for (i, (_origin, rec)) in records.iter().enumerate() {
+                let rec = match &opts.schema {
+                    Some(schema) => {
+                        let value = schema.decode(rec)
+                            .map_err(|e| anyhow!("slot {} record {}: {}", slot, i, e))?;
+                        decoded.clear();
+                        serde_json::to_writer(&mut decoded, &value)?;
+                        &decoded
+                    }
+                    None => rec,
+                };
+                let k = opts.partition.as_ref().map_or(0, |p| p.part_of(i, count, rec));
+                opts.framing.write_record(&mut pending[k].enc, rec)?;
+            }
+            Ok(())
+        })();
+
+        let mut renames = Vec::new();
+        let mut failure = result.err();
+        for f in pending {
+            let tmp = f.tmp.clone();
+            match f.finish() {
+                Ok(Some(r)) if failure.is_none() => renames.push(r),
+                Ok(_) => { if let Some(t) = tmp { let _ = fs::remove_file(t); } }
+                Err(e) => {
+                    if let Some(t) = tmp { let _ = fs::remove_file(t); }
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+        if let Some(e) = failure {
+            for (tmp, _) in renames { let _ = fs::remove_file(tmp); }
+            return Err(e);
+        }
+        match &opts.partition {
+            Some(p) => commit_parts(path, p.files(), renames)?,
+            None => commit_all(renames)?,
         }
 
-        println!(
-            "[SlotFlusher] slot {} ({} records) → {}",
-            slot, count, path.display()
-        );
+        if dests.len() == 1 {
+            println!(
+                "[SlotFlusher] slot {} ({} records) → {}",
+                slot, count, path.display()
+            );
+        } else {
+            println!(
+                "[SlotFlusher] slot {} ({} records) → {} parts ({} … {})",
+                slot, count, dests.len(), dests[0].display(), dests[dests.len() - 1].display()
+            );
+        }
         Ok(count)
     }
 
@@ -73,9 +435,11 @@ impl SlotFlusher {
     ///
     /// Use when a single run produces N records that should land in N separate
     /// files (e.g. a Pipeline emitting one processed image per round).  Records
-    /// are written verbatim (no trailing `\n`, unlike `save_slot`, since these
+    /// are written verbatim (no trailing `\n`, unlike the default framing of `save_slot_with`, since these
     /// are typically whole binary payloads).  Returns the number of records
-    /// written.  Paths that receive no record are left untouched.
+    /// written.  Paths that receive no record are left untouched.  Files are
+    /// written in place: at one file per record, a temp file, fsync and rename
+    /// each would dominate the run.
     pub fn save_slot_split(&self, paths: &[std::path::PathBuf], slot: u32) -> Result<usize> {
         let records = self.collect_slot(slot);
         if paths.is_empty() {
@@ -95,6 +459,49 @@ impl SlotFlusher {
         Ok(records.len())
     }
 
+    /// [`save_slot_with`](Self::save_slot_with) on the shared ring (see
+    /// "io_uring and O_DIRECT" above): one iovec per in-page span, plus the
+    /// length prefix or newline the framing adds.
+    fn save_gathered(&self, ring: &Mutex<Ring>, path: &Path, slot: u32, opts: &OutputOptions) -> Result<usize> {
+        static NEWLINE: [u8; 1] = *b"\n";
+        let sb = unsafe { &*(self.splice_addr as *const Superblock) };
+        let iovec = |&(p, n): &Span| libc::iovec { iov_base: p as *mut _, iov_len: n };
+        let mut iov = Vec::new();
+        let count = io_record_spans(self.splice_addr, sb, slot as usize, |len_field, payload| {
+            if opts.framing == OutputFraming::LengthPrefixed {
+                iov.extend(len_field.iter().map(iovec));
+            }
+            iov.extend(payload.iter().map(iovec));
+            if opts.framing == OutputFraming::Newline {
+                iov.push(uring::iovec(&NEWLINE));
+            }
+        });
+
+        let (target, tmp) = write_path(path, opts.atomic)?;
+        let written = fs::File::create(&target)
+            .and_then(|file| {
+                uring::lock(ring).write_all_at(file.as_raw_fd(), 0, &iov)?;
+                if tmp.is_some() { file.sync_all()?; }
+                Ok(())
+            })
+            .map_err(|e| anyhow!("write '{}' failed: {}", path.display(), e));
+        match (written, tmp) {
+            (Err(e), tmp) => {
+                if let Some(t) = tmp { let _ = fs::remove_file(t); }
+                return Err(e);
+            }
+            (Ok(()), Some(tmp)) => commit_all(vec![(tmp, path.to_path_buf())])?,
+            (Ok(()), None) => {}
+        }
+
+        let bytes: usize = iov.iter().map(|v| v.iov_len).sum();
+        println!(
+            "[SlotFlusher] slot {} ({} records, {} bytes, io_uring) → {}",
+            slot, count, bytes, path.display()
+        );
+        Ok(count)
+    }
+
     /// Collect all records from the default `OUTPUT_IO_SLOT` into memory without writing to disk.
     pub fn collect(&self) -> Vec<(u32, Vec<u8>)> {
         self.collect_slot(OUTPUT_IO_SLOT)
@@ -106,3 +513,78 @@ impl SlotFlusher {
         read_io_records(self.splice_addr, sb, slot as usize)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_paths() {
+        assert_eq!(part_path(Path::new("out/r.txt.gz"), 3), PathBuf::from("out/r.txt.part-00003.gz"));
+        assert_eq!(part_path(Path::new("out/r"), 0), PathBuf::from("out/r.part-00000"));
+        assert_eq!(part_path(Path::new("out/r-{part}.bin"), 12), PathBuf::from("out/r-00012.bin"));
+    }
+
+    #[test]
+    fn manifest_paths() {
+        assert_eq!(manifest_path(Path::new("out/r.txt.gz")), PathBuf::from("out/r.txt.gz.manifest"));
+        assert_eq!(manifest_path(Path::new("out/r-{part}.bin")), PathBuf::from("out/r-manifest.bin"));
+    }
+
+    /// Stage `contents[k]` as the temp file of part `k`, as `save_slot_with` does.
+    fn staged(path: &Path, contents: &[&str]) -> Vec<(PathBuf, PathBuf)> {
+        contents.iter().enumerate().map(|(k, c)| {
+            let dest = part_path(path, k);
+            let (tmp, _) = write_path(&dest, true).unwrap();
+            fs::write(&tmp, c).unwrap();
+            (tmp, dest)
+        }).collect()
+    }
+
+    #[test]
+    fn commit_parts_removes_stale_parts_and_writes_manifest_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        commit_parts(&path, 4, staged(&path, &["a", "b", "c", "d"])).unwrap();
+        assert_eq!(fs::read_to_string(manifest_path(&path)).unwrap().lines().count(), 4);
+
+        // A rerun with fewer partitions leaves no part of the old set behind.
+        commit_parts(&path, 2, staged(&path, &["x", "y"])).unwrap();
+        assert_eq!(fs::read_to_string(part_path(&path, 1)).unwrap(), "y");
+        assert!(!part_path(&path, 2).exists() && !part_path(&path, 3).exists());
+        assert_eq!(
+            fs::read_to_string(manifest_path(&path)).unwrap(),
+            "out.part-00000.txt\nout.part-00001.txt\n"
+        );
+    }
+
+    #[test]
+    fn failed_commit_leaves_no_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        commit_parts(&path, 2, staged(&path, &["a", "b"])).unwrap();
+
+        // Part 1's temp file vanishes: part 0 is already renamed when the
+        // commit fails, so the old manifest must not vouch for the mix.
+        let renames = staged(&path, &["x", "y"]);
+        fs::remove_file(&renames[1].0).unwrap();
+        assert!(commit_parts(&path, 2, renames).is_err());
+        assert_eq!(fs::read_to_string(part_path(&path, 0)).unwrap(), "x");
+        assert!(!manifest_path(&path).exists());
+    }
+
+    #[test]
+    fn index_partition_is_contiguous() {
+        let p = OutputPartition::Index { files: 3 };
+        let parts: Vec<usize> = (0..7).map(|i| p.part_of(i, 7, b"")).collect();
+        assert_eq!(parts, vec![0, 0, 0, 1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn key_hash_partition_groups_equal_keys() {
+        let p = OutputPartition::KeyHash { files: 4, key_delimiter: Some("\t".into()) };
+        assert_eq!(p.part_of(0, 2, b"alice\t1"), p.part_of(1, 2, b"alice\t99"));
+        let whole = OutputPartition::KeyHash { files: 4, key_delimiter: None };
+        assert_eq!(whole.part_of(0, 1, b"alice"), p.part_of(0, 1, b"alice\tx"));
+    }
+}
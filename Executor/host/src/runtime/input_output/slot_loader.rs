@@ -19,14 +19,15 @@
 // page-chain of a chosen slot, making the records readable by the WASM guest
 // via `ShmApi::read_all_inputs_from(slot)`.
 //
-// All page allocation goes through `reclaimer::alloc_page` (free-list first,
-// then bump), so freed pages from earlier DAG nodes are reused before new
-// bump space is consumed.
+// All page allocation goes through `reclaimer::alloc_page_in` in the slot's
+// page class (free-list first, then bump), so freed pages from earlier DAG
+// nodes are reused before new bump space is consumed.
 //
 // # Prefetch
 //
-// `SlotLoader::prefetch` spawns a background thread that loads a file into a
-// slot while the DAG executor continues with independent nodes.  The caller
+// `SlotLoader::prefetch_formatted` / `prefetch_files` spawn a background
+// thread that loads into a slot while the DAG executor continues with
+// independent nodes.  The caller
 // receives a `PrefetchHandle`; calling `.join()` before the consuming node
 // runs ensures the data is ready without blocking any sooner than necessary.
 //
@@ -35,25 +36,70 @@
 //   let n = SlotLoader::new(splice_addr).load(Path::new("/data/rows.csv"), 0)?;
 //
 //   // Prefetch:
-//   let h = SlotLoader::prefetch(splice_addr, PathBuf::from("/data/rows.csv"), 42);
+//   let h = SlotLoader::prefetch_formatted(
+//       splice_addr, PathBuf::from("/data/rows.csv"), 42, RecordFormat::Lines, Compression::Auto, None,
+//   );
 //   // ... run independent nodes ...
 //   let n = h.join()?;
+//
+// # Formats and compression
+//
+// `load_formatted` / `prefetch_formatted` / `load_next_chunk` accept a
+// `RecordFormat` (lines, JSONL, CSV, fixed-size, length-prefixed) and a
+// `Compression` (see `record_format`).  Plain newline-delimited files still
+// take the mmap paths above; framed formats are cut from a record boundary
+// and compressed files are decompressed as a stream, so neither ever needs a
+// decompressed copy on disk.
+//
+// # File sets
+//
+// `load_files` / `prefetch_files` load a list resolved by `file_set::FileSet`
+// (directory or glob input), either one binary record per file or each file
+// framed like a single-path input.
+//
+// # Zero-copy
+//
+// With `SlotLoader::zero_copy(true)` a binary record is not copied: all but
+// the first 4 KiB of each 2 MiB page is the file itself, overlaid read-only
+// (see `mapped_input`).  Inputs shorter than one such page, or with no free
+// mapped-input entry, are copied as usual.
+//
+// # io_uring
+//
+// With the `uring` I/O backend up (see `uring`), binary `load_files` reads
+// each batch of `URING_BATCH` files through the shared ring, straight into a
+// detached chain of fresh pages that is linked after the slot tail once every
+// read completed — no per-file syscalls and no intermediate copy.  The
+// slot's previous tail page is left as it was, so each batch may leave up to
+// one page partly unused.  Zero-copy loads keep the per-file path.
 
 use std::fs::File;
+use std::io;
 use std::num::NonZeroUsize;
+use std::os::fd::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::ptr::NonNull;
 use std::sync::atomic::Ordering;
+use std::sync::Mutex;
 use std::thread;
 
 use anyhow::{anyhow, Result};
 use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
 
-use common::{Page, PageId, ShmOffset, Superblock};
+use common::{page_class_size, Page, PageId, ShmOffset, Superblock, MAPPED_PAGE_CLASS,
+             MAPPED_PAGE_FILL, PAGE_DATA_SIZE, PAGE_HEADER_SIZE};
 
 use crate::runtime::extended_pool;
 use crate::runtime::mem_operation::reclaimer;
 
+use super::record_format::{open_decoder, Compression, RecordFormat, StreamFramer};
+use super::file_set::{total_len, FileEntry};
+use super::mapped_input;
+use super::uring::{self, Op, Ring};
+
+/// Files read per ring batch by binary `load_files`; bounds open descriptors.
+const URING_BATCH: usize = 256;
+
 // ─── MappedFile ───────────────────────────────────────────────────────────────
 
 /// A read-only memory-mapped view of a file on disk.
@@ -143,6 +189,7 @@ pub fn mmap_file(path: &Path) -> Result<MappedFile> {
 
 pub struct SlotLoader {
     splice_addr: usize,
+    zero_copy: bool,
 }
 
 // SAFETY: all mutations go through atomics; concurrent writes to different
@@ -152,7 +199,15 @@ unsafe impl Sync for SlotLoader {}
 
 impl SlotLoader {
     pub fn new(splice_addr: usize) -> Self {
-        Self { splice_addr }
+        Self { splice_addr, zero_copy: false }
+    }
+
+    /// Overlay binary records onto their pages instead of copying them
+    /// (see "Zero-copy" above).  Only [`load_as_single_record`](Self::load_as_single_record)
+    /// and binary [`load_files`](Self::load_files) honour it.
+    pub fn zero_copy(mut self, on: bool) -> Self {
+        self.zero_copy = on;
+        self
     }
 
     /// Memory-map `path` and write each non-empty line as one length-prefixed
@@ -281,19 +336,123 @@ impl SlotLoader {
         Ok(count)
     }
 
-    /// Spawn a background thread to load a fractional slice into `slot`
-    /// (slice-aware variant of [`prefetch`](Self::prefetch)).
-    pub fn prefetch_slice(splice_addr: usize, path: PathBuf, slot: u32, lo: f64, hi: f64) -> PrefetchHandle {
-        let handle = thread::spawn(move || SlotLoader::new(splice_addr).load_slice(&path, slot, lo, hi));
+    /// Load `path` into `slot` framed by `format`, decompressing on the fly,
+    /// optionally restricted to the fractional `slice` `[lo, hi)`.
+    ///
+    /// Plain `Lines` input delegates to [`load`](Self::load) /
+    /// [`load_slice`](Self::load_slice) unchanged.  Every other combination
+    /// owns the records whose **start offset** falls in `[lo·len, hi·len)` of
+    /// the decompressed stream, so adjacent slices still cover the file exactly
+    /// once.  Compressed slices first decompress the file once, without
+    /// framing, to learn `len`, then stop framing at the end of their window;
+    /// records are never buffered beyond the one being framed.
+    pub fn load_formatted(
+        &self,
+        path: &Path,
+        slot: u32,
+        format: &RecordFormat,
+        compression: Compression,
+        slice: Option<[f64; 2]>,
+    ) -> Result<usize> {
+        format.validate().map_err(|e| anyhow!("SlotLoader: {}", e))?;
+        let compression = compression.resolve(path).map_err(|e| anyhow!("SlotLoader: {}", e))?;
+
+        if compression == Compression::None {
+            if *format == RecordFormat::Lines {
+                return match slice {
+                    Some([lo, hi]) => self.load_slice(path, slot, lo, hi),
+                    None => self.load(path, slot),
+                };
+            }
+            return self.load_framed_mapped(path, slot, format, slice);
+        }
+
+        let total = match slice {
+            Some(_) => io::copy(&mut open_decoder(path, compression)?, &mut io::sink())
+                .map_err(|e| anyhow!("SlotLoader: decompressing '{}': {}", path.display(), e))?,
+            None => 0,
+        };
+        let (raw_start, raw_end) = slice_window(slice, total);
+
+        let mut framer = StreamFramer::new(open_decoder(path, compression)?, format.clone());
+        let mut count = 0usize;
+        while framer.offset() < raw_end {
+            let more = framer.next_record(|start, rec| {
+                if start >= raw_start && start < raw_end && !format.is_header(start) {
+                    self.append_record(slot, rec)?;
+                    count += 1;
+                }
+                Ok(())
+            }).map_err(|e| anyhow!("SlotLoader: '{}': {}", path.display(), e))?;
+            if !more { break; }
+        }
+        println!(
+            "[SlotLoader] '{}' ({:?}, {}) {}({} records) → slot {}",
+            path.display(), compression, format.label(),
+            slice.map(|[lo, hi]| format!("slice [{:.4}..{:.4}) ", lo, hi)).unwrap_or_default(),
+            count, slot,
+        );
+        Ok(count)
+    }
+
+    /// Spawn a background thread running [`load_formatted`](Self::load_formatted).
+    pub fn prefetch_formatted(
+        splice_addr: usize,
+        path: PathBuf,
+        slot: u32,
+        format: RecordFormat,
+        compression: Compression,
+        slice: Option<[f64; 2]>,
+    ) -> PrefetchHandle {
+        let handle = thread::spawn(move || {
+            SlotLoader::new(splice_addr).load_formatted(&path, slot, &format, compression, slice)
+        });
         PrefetchHandle { slot, handle }
     }
 
+    /// Load the next chunk of a framed and/or compressed input into `slot`.
+    ///
+    /// Streaming counterpart of [`load_chunk`](Self::load_chunk): records are
+    /// appended until at least `max_bytes` of (decompressed) input have been
+    /// consumed, always stopping on a record boundary.  Returns
+    /// `(records_written, bytes_consumed)`; the cursor keeps the decoder state
+    /// between runs, so each chunk costs only its own bytes.
+    pub fn load_next_chunk(&self, cursor: &mut ChunkCursor, slot: u32, max_bytes: usize) -> Result<(usize, u64)> {
+        let begin = cursor.framer.offset();
+        let mut count = 0usize;
+        let format = &cursor.format;
+        while cursor.framer.offset() - begin < max_bytes.max(1) as u64 {
+            let more = cursor.framer.next_record(|start, rec| {
+                if !format.is_header(start) {
+                    self.append_record(slot, rec)?;
+                    count += 1;
+                }
+                Ok(())
+            }).map_err(|e| anyhow!("SlotLoader: '{}': {}", cursor.path.display(), e))?;
+            if !more { break; }
+        }
+        cursor.framer.is_exhausted()?;
+        let end = cursor.framer.offset();
+        println!(
+            "[SlotLoader] chunk '{}' ({}) [{}..{}) ({} bytes, {} records) → slot {}",
+            cursor.path.display(), format.label(), begin, end, end - begin, count, slot,
+        );
+        Ok((count, end - begin))
+    }
+
     /// Memory-map `path` and write the entire file as a single record into
     /// `slot`.  Use for binary payloads where line-splitting is inappropriate.
     pub fn load_as_single_record(&self, path: &Path, slot: u32) -> Result<()> {
         let loaded = mmap_file(path)
             .map_err(|e| anyhow!("SlotLoader: {}", e))?;
         let len = loaded.len();
+        if self.zero_copy && self.append_mapped_record(path, slot, loaded.as_bytes())? {
+            println!(
+                "[SlotLoader] '{}' ({} bytes) → slot {} (1 record, zero-copy)",
+                path.display(), len, slot,
+            );
+            return Ok(());
+        }
         self.append_record(slot, loaded.as_bytes())?;
         println!(
             "[SlotLoader] '{}' ({} bytes) → slot {} (1 record)",
@@ -302,17 +461,104 @@ impl SlotLoader {
         Ok(())
     }
 
-    /// Spawn a background thread to load `path` into `slot`.
-    ///
-    /// Returns a `PrefetchHandle`; call `.join()` before the consuming node
-    /// executes to ensure the data is ready.
-    pub fn prefetch(splice_addr: usize, path: PathBuf, slot: u32) -> PrefetchHandle {
-        let handle = thread::spawn(move || SlotLoader::new(splice_addr).load(&path, slot));
+    /// Load every file of a resolved [`FileSet`](super::file_set::FileSet)
+    /// into `slot`, in order: one record per file when `binary`, otherwise
+    /// each file framed by `format` (compression resolved per file).  Returns
+    /// the total number of records written.
+    pub fn load_files(
+        &self,
+        files: &[FileEntry],
+        slot: u32,
+        binary: bool,
+        format: &RecordFormat,
+        compression: Compression,
+    ) -> Result<usize> {
+        let mut count = 0usize;
+        if binary && !self.zero_copy {
+            if let Some(ring) = uring::shared() {
+                self.load_files_uring(ring, files, slot)?;
+                count = files.len();
+            }
+        }
+        for f in &files[count..] {
+            if binary {
+                self.load_as_single_record(&f.path, slot)?;
+                count += 1;
+            } else {
+                count += self.load_formatted(&f.path, slot, format, compression, None)?;
+            }
+        }
+        println!(
+            "[SlotLoader] file set ({} files, {} bytes, {} records{}) → slot {}",
+            files.len(), total_len(files), count,
+            if binary && uring::shared().is_some() && !self.zero_copy { ", io_uring" } else { "" },
+            slot,
+        );
+        Ok(count)
+    }
+
+    /// Spawn a background thread running [`load_files`](Self::load_files).
+    pub fn prefetch_files(
+        splice_addr: usize,
+        files: Vec<FileEntry>,
+        slot: u32,
+        binary: bool,
+        zero_copy: bool,
+        format: RecordFormat,
+        compression: Compression,
+    ) -> PrefetchHandle {
+        let handle = thread::spawn(move || {
+            SlotLoader::new(splice_addr).zero_copy(zero_copy)
+                .load_files(&files, slot, binary, &format, compression)
+        });
         PrefetchHandle { slot, handle }
     }
 
+    /// Append already-framed `(origin, payload)` records to `slot`, keeping
+    /// each record's origin (e.g. records read back with `read_io_records`).
+    /// Returns the number of records written.
+    pub fn load_records(&self, slot: u32, records: &[(u32, Vec<u8>)]) -> Result<usize> {
+        for (origin, payload) in records {
+            self.append_record_from(slot, *origin, payload)?;
+        }
+        Ok(records.len())
+    }
+
     // ── internals ──────────────────────────────────────────────────────────
 
+    /// Uncompressed framed input: frame the mmap in place.  Self-synchronising
+    /// formats jump straight to the slice window; the rest walk from byte 0.
+    fn load_framed_mapped(&self, path: &Path, slot: u32, format: &RecordFormat, slice: Option<[f64; 2]>) -> Result<usize> {
+        let loaded = mmap_file(path).map_err(|e| anyhow!("SlotLoader: {}", e))?;
+        let data = loaded.as_bytes();
+        let (raw_start, raw_end) = slice_window(slice, data.len() as u64);
+        let (raw_start, raw_end) = (raw_start as usize, raw_end.min(data.len() as u64) as usize);
+
+        let mut p = format.resync(data, raw_start).unwrap_or(0);
+        let mut count = 0usize;
+        while p < raw_end {
+            let frame = match format.next_frame(&data[p..], true)
+                .map_err(|e| anyhow!("SlotLoader: '{}' @ {}: {}", path.display(), p, e))? {
+                Some(f) => f,
+                None => break,
+            };
+            if p >= raw_start && !format.is_header(p as u64) {
+                if let Some((a, b)) = frame.payload {
+                    self.append_record(slot, &data[p + a..p + b])?;
+                    count += 1;
+                }
+            }
+            p += frame.len;
+        }
+        println!(
+            "[SlotLoader] '{}' ({}) {}({} bytes, {} records) → slot {}",
+            path.display(), format.label(),
+            slice.map(|[lo, hi]| format!("slice [{:.4}..{:.4}) ", lo, hi)).unwrap_or_default(),
+            loaded.len(), count, slot,
+        );
+        Ok(count)
+    }
+
     fn sb(&self) -> &Superblock {
         unsafe { &*(self.splice_addr as *const Superblock) }
     }
@@ -322,38 +568,191 @@ impl SlotLoader {
             .map_err(|e| anyhow!("SlotLoader: resolve {id:#x}: {e}"))
     }
 
-    fn alloc_page(&self) -> Result<PageId> {
-        reclaimer::alloc_page(self.splice_addr)
+    /// Allocates a page in the page class of I/O slot `slot`.
+    fn alloc_page(&self, slot: usize) -> Result<PageId> {
+        let class = self.sb().io_page_class(slot).load(Ordering::Relaxed) as u32;
+        reclaimer::alloc_page_in(self.splice_addr, class)
             .map_err(|e| anyhow!("SlotLoader: {}", e))
     }
 
     fn append_record(&self, slot: u32, payload: &[u8]) -> Result<()> {
+        self.append_record_from(slot, slot, payload)  // origin = slot
+    }
+
+    fn append_record_from(&self, slot: u32, origin: u32, payload: &[u8]) -> Result<()> {
         self.write_bytes(slot, &(payload.len() as u32).to_le_bytes())?;
-        self.write_bytes(slot, &slot.to_le_bytes())?;  // origin = slot
+        self.write_bytes(slot, &origin.to_le_bytes())?;
         self.write_bytes(slot, payload)
     }
 
+    /// Binary `load_files` through the shared ring (see "io_uring" above).
+    fn load_files_uring(&self, ring: &Mutex<Ring>, files: &[FileEntry], slot: u32) -> Result<()> {
+        let s = slot as usize;
+        for batch in files.chunks(URING_BATCH) {
+            let opened = batch.iter()
+                .map(|f| File::open(&f.path)
+                    .map_err(|e| anyhow!("SlotLoader: cannot open '{}': {}", f.path.display(), e)))
+                .collect::<Result<Vec<_>>>()?;
+
+            let mut chain = DetachedChain::default();
+            let mut ops = Vec::with_capacity(batch.len());
+            let laid_out = (|| -> Result<()> {
+                for (f, file) in batch.iter().zip(&opened) {
+                    let len = file.metadata()?.len();
+                    if len == 0 {
+                        return Err(anyhow!("SlotLoader: File '{}' is empty — nothing to load", f.path.display()));
+                    }
+                    let len = u32::try_from(len).map_err(|_| {
+                        anyhow!("SlotLoader: '{}' ({} bytes) exceeds one record", f.path.display(), len)
+                    })?;
+                    let mut header = [0u8; 8];
+                    header[..4].copy_from_slice(&len.to_le_bytes());
+                    header[4..].copy_from_slice(&slot.to_le_bytes());
+                    self.chain_claim(&mut chain, s, header.len(), |at, done, n| unsafe {
+                        std::ptr::copy_nonoverlapping(header[done..].as_ptr(), at, n)
+                    })?;
+                    let mut iov = Vec::new();
+                    self.chain_claim(&mut chain, s, len as usize, |at, _, n| {
+                        iov.push(libc::iovec { iov_base: at as *mut _, iov_len: n })
+                    })?;
+                    ops.push(Op::read(file.as_raw_fd(), 0, iov));
+                }
+                uring::lock(ring).run(&mut ops)
+                    .map_err(|e| anyhow!("SlotLoader: io_uring: {}", e))?;
+                for (op, f) in ops.drain(..).zip(batch) {
+                    op.result().map_err(|e| anyhow!("SlotLoader: read '{}': {}", f.path.display(), e))?;
+                }
+                Ok(())
+            })();
+            if let Err(e) = laid_out {
+                reclaimer::free_page_chain(self.splice_addr, chain.head);
+                return Err(e);
+            }
+            self.attach_chain(s, chain)?;
+        }
+        Ok(())
+    }
+
+    /// Claim the next `len` bytes of `chain`, allocating pages as needed,
+    /// and hand each in-page span to `f(at, done, n)` (`done` bytes claimed
+    /// before it).
+    fn chain_claim(
+        &self,
+        chain: &mut DetachedChain,
+        s: usize,
+        len: usize,
+        mut f: impl FnMut(*mut u8, usize, usize),
+    ) -> Result<()> {
+        let mut done = 0usize;
+        while done < len {
+            let mut page = (chain.tail != 0).then(|| self.page_ptr(chain.tail)).transpose()?;
+            let full = |p: *mut Page| unsafe {
+                (*p).cursor.load(Ordering::Relaxed) as usize >= (*p).capacity()
+            };
+            if page.is_none_or(full) {
+                let id = self.alloc_page(s)?;
+                match page {
+                    Some(p) => unsafe { (*p).next_offset.store(id, Ordering::Relaxed) },
+                    None => chain.head = id,
+                }
+                chain.tail = id;
+                page = Some(self.page_ptr(id)?);
+            }
+            let page = unsafe { &mut *page.expect("tail page") };
+            let cursor = page.cursor.load(Ordering::Relaxed) as usize;
+            let n = (page.capacity() - cursor).min(len - done);
+            f(unsafe { page.data.as_mut_ptr().add(cursor) }, done, n);
+            page.cursor.store((cursor + n) as ShmOffset, Ordering::Relaxed);
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// Link a filled `chain` after the tail of I/O slot `s`.
+    fn attach_chain(&self, s: usize, chain: DetachedChain) -> Result<()> {
+        if chain.head == 0 {
+            return Ok(());
+        }
+        let sb = self.sb();
+        let tail = sb.io_tail(s).load(Ordering::Acquire);
+        if tail == 0 {
+            sb.io_head(s).store(chain.head, Ordering::Release);
+        } else {
+            unsafe { &*self.page_ptr(tail)? }.next_offset.store(chain.head, Ordering::Release);
+        }
+        sb.io_tail(s).store(chain.tail, Ordering::Release);
+        Ok(())
+    }
+
+    /// Append `data` (the contents of `path`) as one record whose whole
+    /// pages are overlaid from the file.  The record header and the first
+    /// `PAGE_HEADER_SIZE` bytes go to the current tail, which keeps every
+    /// overlay on a page-aligned file offset; the remainder and anything
+    /// appended later go to a fresh page after the extent.  Returns `false`
+    /// (nothing written) when the record must be copied instead.
+    fn append_mapped_record(&self, path: &Path, slot: u32, data: &[u8]) -> Result<bool> {
+        let pages = data.len().saturating_sub(PAGE_HEADER_SIZE) / MAPPED_PAGE_FILL;
+        if pages == 0 || u32::try_from(data.len()).is_err() {
+            return Ok(false);
+        }
+        let Some(extent) = mapped_input::map_file(self.splice_addr, path, pages as u32)
+            .map_err(|e| anyhow!("SlotLoader: {}", e))? else {
+            return Ok(false);
+        };
+
+        self.write_bytes(slot, &(data.len() as u32).to_le_bytes())?;
+        self.write_bytes(slot, &slot.to_le_bytes())?;
+        self.write_bytes(slot, &data[..PAGE_HEADER_SIZE])?;
+
+        let page_size = page_class_size(MAPPED_PAGE_CLASS) as usize;
+        for j in 0..pages {
+            let offset = extent as usize + j * page_size;
+            let page = unsafe { &mut *((self.splice_addr + offset) as *mut Page) };
+            let next = if j + 1 < pages { (offset + page_size) as PageId } else { 0 };
+            let src = PAGE_HEADER_SIZE + j * MAPPED_PAGE_FILL;
+            page.next_offset.store(next, Ordering::Relaxed);
+            page.class.store(MAPPED_PAGE_CLASS, Ordering::Relaxed);
+            unsafe {
+                std::ptr::copy_nonoverlapping(data.as_ptr().add(src), page.data.as_mut_ptr(), PAGE_DATA_SIZE);
+            }
+            page.cursor.store(MAPPED_PAGE_FILL as ShmOffset, Ordering::Release);
+        }
+
+        let sb = self.sb();
+        let s = slot as usize;
+        let last = extent as usize + (pages - 1) * page_size;
+        let fresh = self.alloc_page(s)?;
+        unsafe { &*((self.splice_addr + last) as *const Page) }
+            .next_offset.store(fresh, Ordering::Release);
+        let tail = unsafe { &*self.page_ptr(sb.io_tail(s).load(Ordering::Acquire))? };
+        tail.next_offset.store(extent as PageId, Ordering::Release);
+        sb.io_tail(s).store(fresh, Ordering::Release);
+
+        self.write_bytes(slot, &data[PAGE_HEADER_SIZE + pages * MAPPED_PAGE_FILL..])?;
+        Ok(true)
+    }
+
     fn write_bytes(&self, slot: u32, mut data: &[u8]) -> Result<()> {
         if data.is_empty() { return Ok(()); }
         let sb = self.sb();
         let s = slot as usize;
 
-        let mut tail: PageId = sb.io_tails[s].load(Ordering::Acquire);
+        let mut tail: PageId = sb.io_tail(s).load(Ordering::Acquire);
         if tail == 0 {
-            tail = self.alloc_page()?;
-            sb.io_heads[s].store(tail, Ordering::Release);
-            sb.io_tails[s].store(tail, Ordering::Release);
+            tail = self.alloc_page(s)?;
+            sb.io_head(s).store(tail, Ordering::Release);
+            sb.io_tail(s).store(tail, Ordering::Release);
         }
 
         while !data.is_empty() {
             let page = unsafe { &mut *self.page_ptr(tail)? };
             let cursor = page.cursor.load(Ordering::Relaxed) as usize;
-            let space = common::PAGE_DATA_SIZE.saturating_sub(cursor);
+            let space = page.capacity().saturating_sub(cursor);
 
             if space == 0 {
-                let next = self.alloc_page()?;
+                let next = self.alloc_page(s)?;
                 page.next_offset.store(next, Ordering::Release);
-                sb.io_tails[s].store(next, Ordering::Release);
+                sb.io_tail(s).store(next, Ordering::Release);
                 tail = next;
                 continue;
             }
@@ -369,9 +768,59 @@ impl SlotLoader {
     }
 }
 
+/// Pages filled by one `load_files_uring` batch: linked to each other but
+/// not yet to the slot, so no reader sees them before the reads complete.
+#[derive(Default)]
+struct DetachedChain {
+    head: PageId,
+    tail: PageId,
+}
+
+/// Byte window `[lo·total, hi·total)` of a fractional slice; the whole input
+/// (`[0, u64::MAX)`) when `slice` is `None`.
+fn slice_window(slice: Option<[f64; 2]>, total: u64) -> (u64, u64) {
+    match slice {
+        Some([lo, hi]) => {
+            let at = |f: f64| (f.clamp(0.0, 1.0) * total as f64).floor() as u64;
+            let end = if hi >= 1.0 { u64::MAX } else { at(hi) };
+            (at(lo), end)
+        }
+        None => (0, u64::MAX),
+    }
+}
+
+// ─── ChunkCursor ─────────────────────────────────────────────────────────────
+
+/// Decoder state of one chunked framed/compressed `Input`, kept by the DAG run
+/// loop across runs and advanced by [`SlotLoader::load_next_chunk`].
+pub struct ChunkCursor {
+    path: PathBuf,
+    format: RecordFormat,
+    framer: StreamFramer,
+}
+
+impl ChunkCursor {
+    pub fn open(path: &Path, format: &RecordFormat, compression: Compression) -> Result<Self> {
+        format.validate()?;
+        let compression = compression.resolve(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            format: format.clone(),
+            framer: StreamFramer::new(open_decoder(path, compression)?, format.clone()),
+        })
+    }
+
+    /// Decompressed bytes consumed so far.
+    #[inline] pub fn offset(&self) -> u64 { self.framer.offset() }
+
+    /// True once every record has been loaded.
+    #[inline] pub fn is_done(&self) -> bool { self.framer.is_done() }
+}
+
 // ─── PrefetchHandle ───────────────────────────────────────────────────────────
 
-/// Handle to a background prefetch started by `SlotLoader::prefetch`.
+/// Handle to a background prefetch started by `SlotLoader::prefetch_formatted`
+/// or `SlotLoader::prefetch_files`.
 /// Call `join()` before the consuming node executes.
 pub struct PrefetchHandle {
     pub slot: u32,
@@ -386,3 +835,59 @@ impl PrefetchHandle {
             .map_err(|_| anyhow!("prefetch thread for slot {} panicked", self.slot))?
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use common::INITIAL_SHM_SIZE;
+
+    use crate::runtime::input_output::persistence::read_io_records;
+    use crate::shm::format_shared_memory;
+
+    #[test]
+    fn compressed_slices_match_the_uncompressed_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let shm = dir.path().join("region");
+        format_shared_memory(shm.to_str().unwrap()).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&shm).unwrap();
+        let len = NonZeroUsize::new(INITIAL_SHM_SIZE as usize).unwrap();
+        let base = unsafe {
+            mmap(None, len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, Some(&file), 0)
+        }.unwrap() as usize;
+        let sb = unsafe { &*(base as *const Superblock) };
+
+        // Uneven record lengths so no slice boundary lands on a record start.
+        let data: String = (0..400).map(|i| format!("{{\"id\":{},\"pad\":\"{}\"}}\n", i, "x".repeat(i % 13))).collect();
+        let plain = dir.path().join("rows.jsonl");
+        std::fs::write(&plain, &data).unwrap();
+        let gz = dir.path().join("rows.jsonl.gz");
+        let mut enc = flate2::write::GzEncoder::new(File::create(&gz).unwrap(), flate2::Compression::fast());
+        enc.write_all(data.as_bytes()).unwrap();
+        enc.finish().unwrap();
+        let zst = dir.path().join("rows.jsonl.zst");
+        std::fs::write(&zst, zstd::encode_all(data.as_bytes(), 0).unwrap()).unwrap();
+
+        let loader = SlotLoader::new(base);
+        let mut slot = 2;
+        let mut load = |path: &Path, slice: [f64; 2]| {
+            slot += 1;
+            loader.load_formatted(path, slot, &RecordFormat::Jsonl, Compression::Auto, Some(slice)).unwrap();
+            read_io_records(base, sb, slot as usize).into_iter().map(|(_, r)| r).collect::<Vec<_>>()
+        };
+        let mut all = Vec::new();
+        for slice in [[0.0, 0.3], [0.3, 0.7], [0.7, 1.0]] {
+            let expected = load(&plain, slice);
+            assert!(!expected.is_empty());
+            assert_eq!(load(&gz, slice), expected, "gzip {:?}", slice);
+            assert_eq!(load(&zst, slice), expected, "zstd {:?}", slice);
+            all.extend(expected);
+        }
+        let lines: Vec<Vec<u8>> = data.lines().map(|l| l.as_bytes().to_vec()).collect();
+        assert_eq!(all, lines);
+
+        unsafe { munmap(base as *mut _, len.get()).unwrap() };
+    }
+}
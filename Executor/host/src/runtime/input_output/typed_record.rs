@@ -0,0 +1,327 @@
+// Host-side decoding of typed records (see "Typed records" in `common`).
+//
+// Guests write typed records as `[schema tag: u32 LE][postcard body]`.  The
+// host has no Rust type for them, so it decodes by walking the schema
+// descriptor instead:
+//
+//   WordCount{word:str,count:u64}   →   {"word":"the","count":42}
+//
+// `RecordSchema` deserialises from the descriptor string, so a bad descriptor
+// in a DAG (`Output.schema`, `Watch.schema`) is a load error.  `decode`
+// checks the tag before touching the body and rejects trailing bytes, so a
+// slot written with another schema fails on its first record.
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use common::{schema_tag, split_typed_record};
+
+// ─── Schema descriptors ──────────────────────────────────────────────────────
+
+/// Type of one field in a schema descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    /// UTF-8 string.
+    Str,
+    /// Byte string, rendered as an array of numbers.
+    Bytes,
+    /// `opt<T>`: `null` or `T`.
+    Opt(Box<FieldType>),
+    /// `seq<T>`: array of `T`.
+    Seq(Box<FieldType>),
+}
+
+impl FieldType {
+    fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let inner = |prefix: &str| {
+            s.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix('>'))
+                .map(FieldType::parse)
+        };
+        if let Some(t) = inner("opt<") {
+            return Ok(FieldType::Opt(Box::new(t?)));
+        }
+        if let Some(t) = inner("seq<") {
+            return Ok(FieldType::Seq(Box::new(t?)));
+        }
+        Ok(match s {
+            "bool" => FieldType::Bool,
+            "u8" => FieldType::U8,
+            "u16" => FieldType::U16,
+            "u32" => FieldType::U32,
+            "u64" => FieldType::U64,
+            "i8" => FieldType::I8,
+            "i16" => FieldType::I16,
+            "i32" => FieldType::I32,
+            "i64" => FieldType::I64,
+            "f32" => FieldType::F32,
+            "f64" => FieldType::F64,
+            "str" => FieldType::Str,
+            "bytes" => FieldType::Bytes,
+            other => bail!("unknown field type `{}`", other),
+        })
+    }
+}
+
+/// A parsed schema descriptor: `Name{field:type,…}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct RecordSchema {
+    descriptor: String,
+    tag: u32,
+    fields: Vec<(String, FieldType)>,
+}
+
+impl TryFrom<String> for RecordSchema {
+    type Error = anyhow::Error;
+
+    fn try_from(descriptor: String) -> Result<Self> {
+        RecordSchema::parse(&descriptor)
+    }
+}
+
+impl RecordSchema {
+    pub fn parse(descriptor: &str) -> Result<Self> {
+        let bad = |why: String| anyhow!("schema `{}`: {}", descriptor, why);
+        let (name, rest) = descriptor.split_once('{')
+            .ok_or_else(|| bad("expected `Name{field:type,…}`".into()))?;
+        let body = rest.strip_suffix('}')
+            .ok_or_else(|| bad("missing closing `}`".into()))?;
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(bad(format!("bad type name `{}`", name)));
+        }
+
+        let mut fields: Vec<(String, FieldType)> = Vec::new();
+        for field in split_top_level(body).into_iter().filter(|f| !f.trim().is_empty()) {
+            let (fname, ftype) = field.split_once(':')
+                .ok_or_else(|| bad(format!("field `{}` has no `:type`", field.trim())))?;
+            let fname = fname.trim();
+            if fields.iter().any(|(n, _)| n == fname) {
+                return Err(bad(format!("duplicate field `{}`", fname)));
+            }
+            let ty = FieldType::parse(ftype).map_err(|e| bad(format!("field `{}`: {}", fname, e)))?;
+            fields.push((fname.to_owned(), ty));
+        }
+        Ok(RecordSchema { descriptor: descriptor.to_owned(), tag: schema_tag(descriptor), fields })
+    }
+
+    /// The postcard body of `payload` after checking its schema tag.
+    pub fn body<'a>(&self, payload: &'a [u8]) -> Result<&'a [u8]> {
+        let (tag, body) = split_typed_record(payload).ok_or_else(|| anyhow!(
+            "{}-byte record is not a typed record (expected `{}`)", payload.len(), self.descriptor
+        ))?;
+        if tag != self.tag {
+            bail!("record schema tag {:#010x}, expected `{}` ({:#010x})", tag, self.descriptor, self.tag);
+        }
+        Ok(body)
+    }
+
+    /// Decode one typed record into a JSON object keyed by field name.
+    pub fn decode(&self, payload: &[u8]) -> Result<Value> {
+        let mut r = Reader { buf: self.body(payload)?, pos: 0 };
+        let mut obj = Map::new();
+        for (name, ty) in &self.fields {
+            let v = r.value(ty).map_err(|e| anyhow!("`{}` field `{}`: {}", self.descriptor, name, e))?;
+            obj.insert(name.clone(), v);
+        }
+        if r.pos != r.buf.len() {
+            bail!("`{}` record has {} trailing bytes", self.descriptor, r.buf.len() - r.pos);
+        }
+        Ok(Value::Object(obj))
+    }
+}
+
+/// Split `s` on the commas not nested inside `<…>`.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(&s[start..]);
+    out
+}
+
+// ─── postcard body reader ────────────────────────────────────────────────────
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8]> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.buf.len())
+            .ok_or_else(|| anyhow!("record truncated at byte {}", self.buf.len()))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// LEB128 varint of at most `max_bits` bits.
+    fn varint(&mut self, max_bits: u32) -> Result<u64> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            if shift > 63 || (shift == 63 && b & 0x7F > 1) {
+                bail!("varint overflows 64 bits");
+            }
+            value |= ((b & 0x7F) as u64) << shift;
+            if b & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        if max_bits < 64 && value >> max_bits != 0 {
+            bail!("varint {} overflows {} bits", value, max_bits);
+        }
+        Ok(value)
+    }
+
+    fn zigzag(&mut self, max_bits: u32) -> Result<i64> {
+        let n = self.varint(max_bits)?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        let n = self.varint(32)? as usize;
+        if n > self.buf.len() - self.pos {
+            bail!("length {} exceeds the {} bytes left", n, self.buf.len() - self.pos);
+        }
+        Ok(n)
+    }
+
+    fn value(&mut self, ty: &FieldType) -> Result<Value> {
+        Ok(match ty {
+            FieldType::Bool => match self.byte()? {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                b => bail!("bad bool byte {}", b),
+            },
+            FieldType::U8 => Value::from(self.byte()?),
+            FieldType::U16 => Value::from(self.varint(16)?),
+            FieldType::U32 => Value::from(self.varint(32)?),
+            FieldType::U64 => Value::from(self.varint(64)?),
+            FieldType::I8 => Value::from(self.byte()? as i8),
+            FieldType::I16 => Value::from(self.zigzag(16)?),
+            FieldType::I32 => Value::from(self.zigzag(32)?),
+            FieldType::I64 => Value::from(self.zigzag(64)?),
+            FieldType::F32 => {
+                let b = self.take(4)?;
+                Value::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            }
+            FieldType::F64 => {
+                let b = self.take(8)?;
+                Value::from(f64::from_le_bytes(b.try_into().unwrap()))
+            }
+            FieldType::Str => {
+                let n = self.len()?;
+                let s = std::str::from_utf8(self.take(n)?).map_err(|e| anyhow!("bad UTF-8: {}", e))?;
+                Value::from(s)
+            }
+            FieldType::Bytes => {
+                let n = self.len()?;
+                Value::from(self.take(n)?.to_vec())
+            }
+            FieldType::Opt(inner) => match self.byte()? {
+                0 => Value::Null,
+                1 => self.value(inner)?,
+                b => bail!("bad option tag {}", b),
+            },
+            FieldType::Seq(inner) => {
+                let n = self.varint(32)? as usize;
+                let mut items = Vec::with_capacity(n.min(self.buf.len() - self.pos));
+                for _ in 0..n {
+                    items.push(self.value(inner)?);
+                }
+                Value::Array(items)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::WORD_COUNT_SCHEMA;
+    use serde_json::json;
+
+    fn typed(descriptor: &str, body: &[u8]) -> Vec<u8> {
+        [&schema_tag(descriptor).to_le_bytes()[..], body].concat()
+    }
+
+    #[test]
+    fn parses_descriptors() {
+        let s = RecordSchema::parse("Row{id:u32, tags:seq<str>, score:opt<f64>}").unwrap();
+        assert_eq!(s.fields, vec![
+            ("id".to_owned(), FieldType::U32),
+            ("tags".to_owned(), FieldType::Seq(Box::new(FieldType::Str))),
+            ("score".to_owned(), FieldType::Opt(Box::new(FieldType::F64))),
+        ]);
+        assert_eq!(RecordSchema::parse("Unit{}").unwrap().fields, vec![]);
+        for bad in ["Row", "Row{id}", "Row{id:u128}", "Row{a:u8,a:u8}", "{a:u8}", "Row{a:seq<u8}"] {
+            assert!(RecordSchema::parse(bad).is_err(), "{}", bad);
+        }
+        let from_json: RecordSchema = serde_json::from_value(json!(WORD_COUNT_SCHEMA)).unwrap();
+        assert_eq!(from_json.tag, schema_tag(WORD_COUNT_SCHEMA));
+    }
+
+    #[test]
+    fn decodes_postcard_bodies() {
+        let wc = RecordSchema::parse(WORD_COUNT_SCHEMA).unwrap();
+        // "hi", 300 = varint [0xAC, 0x02]
+        let rec = typed(WORD_COUNT_SCHEMA, &[2, b'h', b'i', 0xAC, 0x02]);
+        assert_eq!(wc.decode(&rec).unwrap(), json!({ "word": "hi", "count": 300 }));
+
+        let d = "Mixed{a:i32,b:bool,c:opt<u16>,d:seq<i8>,e:f32,f:bytes}";
+        let m = RecordSchema::parse(d).unwrap();
+        let mut body = vec![0x03, 1, 1, 0x80, 0x01, 2, 0xFF, 0x05];
+        body.extend(1.5f32.to_le_bytes());
+        body.extend([2, 9, 8]);
+        assert_eq!(m.decode(&typed(d, &body)).unwrap(), json!({
+            "a": -2, "b": true, "c": 128, "d": [-1, 5], "e": 1.5, "f": [9, 8]
+        }));
+    }
+
+    #[test]
+    fn rejects_other_schemas_and_malformed_bodies() {
+        let wc = RecordSchema::parse(WORD_COUNT_SCHEMA).unwrap();
+        let other = typed("Other{x:u8}", &[1]);
+        let err = wc.decode(&other).unwrap_err().to_string();
+        assert!(err.contains("expected `WordCount{word:str,count:u64}`"), "{}", err);
+        assert!(wc.decode(b"hi").unwrap_err().to_string().contains("not a typed record"));
+
+        let trailing = typed(WORD_COUNT_SCHEMA, &[1, b'a', 1, 0]);
+        assert!(wc.decode(&trailing).unwrap_err().to_string().contains("1 trailing bytes"));
+        let truncated = typed(WORD_COUNT_SCHEMA, &[5, b'a']);
+        assert!(wc.decode(&truncated).is_err());
+        let overflow = RecordSchema::parse("S{v:u16}").unwrap();
+        assert!(overflow.decode(&typed("S{v:u16}", &[0xFF, 0xFF, 0x04])).is_err());
+    }
+}
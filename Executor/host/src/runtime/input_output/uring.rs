@@ -0,0 +1,650 @@
+// io_uring backend for batched file I/O.
+//
+// By default every file an `Input`, `Output` or `StreamOutput` node touches
+// costs its own blocking read or write.  With `"io": { "backend": "uring" }`
+// in the DAG, those paths submit batches to one process-wide ring instead:
+//
+//   - binary file-set inputs (`SlotLoader::load_files`) read every file of a
+//     batch straight into freshly allocated pages of the slot's class;
+//   - `Output` with newline / length-prefixed / raw framing, no compression
+//     and no partition (`SlotFlusher::save_slot_with`) writes from iovecs that
+//     point into the slot's page chain, length prefixes included;
+//   - raw `StreamOutput` rounds (`PersistenceWriter::watch_slot_binary`)
+//     write all records of a round as one gathered write;
+//   - `direct: true` outputs (`DirectFile`) send their aligned blocks
+//     through the ring.
+//
+// The ring is hand-rolled and minimal: `io_uring_setup` / `io_uring_enter`
+// through `libc::syscall`, the three mmap'd areas, and READV / WRITEV.  When
+// setup fails (old kernel, seccomp-filtered container) the failure is logged
+// once and every caller keeps the blocking path.
+//
+// One batch runs at a time: callers hold the `shared()` lock for a whole
+// `Ring::run`, so concurrent prefetches queue for the ring rather than for
+// the disk.
+//
+// # O_DIRECT
+//
+// `DirectFile` is the output sink of `direct: true`.  Bytes are staged in an
+// aligned buffer and leave it in whole `DIRECT_ALIGN` blocks at aligned
+// offsets; `finish` zero-pads the last block, writes it and truncates the
+// file back to its real length.  File systems that refuse O_DIRECT (tmpfs)
+// get the same block writes through the page cache.
+
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::{FileExt, OpenOptionsExt};
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+// ─── Kernel ABI ──────────────────────────────────────────────────────────────
+
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x0800_0000;
+const IORING_OFF_SQES: libc::off_t = 0x1000_0000;
+const IORING_ENTER_GETEVENTS: u32 = 1;
+const IORING_OP_READV: u8 = 1;
+const IORING_OP_WRITEV: u8 = 2;
+
+/// Most iovecs one READV / WRITEV submission carries (`UIO_MAXIOV`).
+pub const MAX_IOV: usize = 1024;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqOffsets,
+    cq_off: CqOffsets,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    rw_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+const _: () = assert!(size_of::<Params>() == 120);
+const _: () = assert!(size_of::<Sqe>() == 64);
+const _: () = assert!(size_of::<Cqe>() == 16);
+
+/// One mmap'd ring area, unmapped on drop.
+struct Area {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Area {
+    fn map(fd: RawFd, len: usize, offset: libc::off_t) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(), len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd, offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr: ptr as *mut u8, len })
+    }
+
+    fn at<T>(&self, offset: u32) -> *mut T {
+        unsafe { self.ptr.add(offset as usize) as *mut T }
+    }
+}
+
+impl Drop for Area {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr as *mut _, self.len) };
+    }
+}
+
+// ─── Op ──────────────────────────────────────────────────────────────────────
+
+/// One vectored read or write at a file offset, run to completion by
+/// [`Ring::run`].  Short transfers are resubmitted from where they stopped.
+pub struct Op {
+    fd: RawFd,
+    write: bool,
+    offset: u64,
+    iov: Vec<libc::iovec>,
+    /// First iovec not yet fully transferred.
+    next: usize,
+    error: Option<io::Error>,
+}
+
+impl Op {
+    /// Fill `iov` from `fd` starting at `offset`; reaching EOF first is an error.
+    pub fn read(fd: RawFd, offset: u64, iov: Vec<libc::iovec>) -> Self {
+        Self::new(fd, false, offset, iov)
+    }
+
+    /// Write `iov` to `fd` starting at `offset`.
+    pub fn write(fd: RawFd, offset: u64, iov: Vec<libc::iovec>) -> Self {
+        Self::new(fd, true, offset, iov)
+    }
+
+    fn new(fd: RawFd, write: bool, offset: u64, iov: Vec<libc::iovec>) -> Self {
+        let mut op = Self { fd, write, offset, iov, next: 0, error: None };
+        op.advance(0);
+        op
+    }
+
+    /// `Ok` once every byte was transferred.
+    pub fn result(self) -> io::Result<()> {
+        self.error.map_or(Ok(()), Err)
+    }
+
+    fn pending(&self) -> bool {
+        self.error.is_none() && self.next < self.iov.len()
+    }
+
+    /// Account for `n` transferred bytes.
+    fn advance(&mut self, mut n: usize) {
+        self.offset += n as u64;
+        while self.next < self.iov.len() {
+            let v = &mut self.iov[self.next];
+            if n < v.iov_len {
+                v.iov_base = unsafe { (v.iov_base as *mut u8).add(n) } as *mut _;
+                v.iov_len -= n;
+                return;
+            }
+            n -= v.iov_len;
+            self.next += 1;
+        }
+    }
+}
+
+// ─── Ring ────────────────────────────────────────────────────────────────────
+
+pub struct Ring {
+    fd: OwnedFd,
+    sq: Area,
+    cq: Area,
+    sqes: Area,
+    params: Params,
+}
+
+// SAFETY: the areas are owned by the ring; `run` takes `&mut self`, so one
+// batch at a time touches them.
+unsafe impl Send for Ring {}
+
+impl Ring {
+    /// Set up a ring with (at least) `entries` submission slots.
+    pub fn new(entries: u32) -> io::Result<Self> {
+        let mut params = Params::default();
+        let fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, entries.max(1), &mut params as *mut Params) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+        let raw = fd.as_raw_fd();
+        let sq_len = params.sq_off.array as usize + params.sq_entries as usize * size_of::<u32>();
+        let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * size_of::<Cqe>();
+        let sq = Area::map(raw, sq_len, IORING_OFF_SQ_RING)?;
+        let cq = Area::map(raw, cq_len, IORING_OFF_CQ_RING)?;
+        let sqes = Area::map(raw, params.sq_entries as usize * size_of::<Sqe>(), IORING_OFF_SQES)?;
+        Ok(Self { fd, sq, cq, sqes, params })
+    }
+
+    /// Submission slots; also the most ops kept in flight.
+    pub fn entries(&self) -> u32 {
+        self.params.sq_entries
+    }
+
+    /// Run every op to completion, at most `entries()` in flight.  A failed
+    /// op keeps its error (see [`Op::result`]) and does not stop the others;
+    /// `Err` here means the ring itself failed.
+    pub fn run(&mut self, ops: &mut [Op]) -> io::Result<()> {
+        let mut queue: VecDeque<usize> = (0..ops.len()).filter(|&i| ops[i].pending()).collect();
+        let mut in_flight = 0u32;
+        let mut unsubmitted = 0u32;
+        while in_flight > 0 || !queue.is_empty() {
+            while in_flight < self.entries() {
+                let Some(i) = queue.pop_front() else { break };
+                self.push(i, &ops[i]);
+                in_flight += 1;
+                unsubmitted += 1;
+            }
+            unsubmitted -= self.enter(unsubmitted, 1)?;
+            in_flight -= self.reap(ops, &mut queue);
+        }
+        Ok(())
+    }
+
+    /// Split `iov` into WRITEV ops of `MAX_IOV` entries at consecutive offsets
+    /// from `offset` and run them.
+    pub fn write_all_at(&mut self, fd: RawFd, offset: u64, iov: &[libc::iovec]) -> io::Result<()> {
+        let mut at = offset;
+        let mut ops: Vec<Op> = iov.chunks(MAX_IOV)
+            .map(|chunk| {
+                let op = Op::write(fd, at, chunk.to_vec());
+                at += chunk.iter().map(|v| v.iov_len as u64).sum::<u64>();
+                op
+            })
+            .collect();
+        self.run(&mut ops)?;
+        ops.into_iter().try_for_each(Op::result)
+    }
+
+    fn push(&self, i: usize, op: &Op) {
+        let p = &self.params;
+        let tail_ptr = self.sq.at::<AtomicU32>(p.sq_off.tail);
+        let tail = unsafe { &*tail_ptr }.load(Ordering::Relaxed);
+        let index = tail & unsafe { *self.sq.at::<u32>(p.sq_off.ring_mask) };
+        let iov = &op.iov[op.next..];
+        unsafe {
+            *self.sqes.at::<Sqe>(0).add(index as usize) = Sqe {
+                opcode: if op.write { IORING_OP_WRITEV } else { IORING_OP_READV },
+                fd: op.fd,
+                off: op.offset,
+                addr: iov.as_ptr() as u64,
+                len: iov.len().min(MAX_IOV) as u32,
+                user_data: i as u64,
+                ..Sqe::default()
+            };
+            *self.sq.at::<u32>(p.sq_off.array).add(index as usize) = index;
+            (*tail_ptr).store(tail.wrapping_add(1), Ordering::Release);
+        }
+    }
+
+    /// Submit `to_submit` queued entries and wait for `min_complete`
+    /// completions; returns how many entries the kernel consumed.
+    fn enter(&self, to_submit: u32, min_complete: u32) -> io::Result<u32> {
+        loop {
+            let r = unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_enter, self.fd.as_raw_fd(), to_submit, min_complete,
+                    IORING_ENTER_GETEVENTS, ptr::null::<libc::sigset_t>(), 0usize,
+                )
+            };
+            if r >= 0 {
+                return Ok(r as u32);
+            }
+            let e = io::Error::last_os_error();
+            match e.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::EAGAIN) | Some(libc::EBUSY) => std::thread::yield_now(),
+                _ => return Err(e),
+            }
+        }
+    }
+
+    /// Consume every available completion; returns how many there were.
+    fn reap(&self, ops: &mut [Op], queue: &mut VecDeque<usize>) -> u32 {
+        let p = &self.params;
+        let head_ptr = self.cq.at::<AtomicU32>(p.cq_off.head);
+        let mut head = unsafe { &*head_ptr }.load(Ordering::Relaxed);
+        let tail = unsafe { &*self.cq.at::<AtomicU32>(p.cq_off.tail) }.load(Ordering::Acquire);
+        let mask = unsafe { *self.cq.at::<u32>(p.cq_off.ring_mask) };
+        let cqes = self.cq.at::<Cqe>(p.cq_off.cqes);
+        let mut reaped = 0;
+        while head != tail {
+            let cqe = unsafe { &*cqes.add((head & mask) as usize) };
+            let i = cqe.user_data as usize;
+            let op = &mut ops[i];
+            match cqe.res {
+                r if r == -libc::EINTR || r == -libc::EAGAIN => queue.push_back(i),
+                r if r < 0 => op.error = Some(io::Error::from_raw_os_error(-r)),
+                0 => op.error = Some(if op.write {
+                    io::Error::new(io::ErrorKind::WriteZero, "write returned 0 bytes")
+                } else {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "file ended early")
+                }),
+                r => {
+                    op.advance(r as usize);
+                    if op.pending() { queue.push_back(i); }
+                }
+            }
+            head = head.wrapping_add(1);
+            reaped += 1;
+        }
+        unsafe { &*head_ptr }.store(head, Ordering::Release);
+        reaped
+    }
+}
+
+// ─── Shared ring ─────────────────────────────────────────────────────────────
+
+/// Set up on the first `configure(true, ..)`; `None` when setup failed.
+static RING: OnceLock<Option<Mutex<Ring>>> = OnceLock::new();
+
+/// Whether the current DAG asked for the ring.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Select the backend for the DAG about to run.  Only the first enabling
+/// call sets the ring up (with `queue_depth` entries); later DAGs in the
+/// same process reuse it.
+pub fn configure(uring: bool, queue_depth: u32) {
+    let up = uring && RING.get_or_init(|| match Ring::new(queue_depth) {
+        Ok(ring) => {
+            println!("[Uring] ring up ({} entries)", ring.entries());
+            Some(Mutex::new(ring))
+        }
+        Err(e) => {
+            println!("[Uring] io_uring_setup failed ({}) — using blocking I/O", e);
+            None
+        }
+    }).is_some();
+    ENABLED.store(up, Ordering::Release);
+}
+
+/// The process-wide ring, when the `uring` backend is selected and up.
+pub fn shared() -> Option<&'static Mutex<Ring>> {
+    if !ENABLED.load(Ordering::Acquire) {
+        return None;
+    }
+    RING.get().and_then(Option::as_ref)
+}
+
+/// Lock `ring` for one batch.
+pub fn lock(ring: &Mutex<Ring>) -> std::sync::MutexGuard<'_, Ring> {
+    ring.lock().expect("io_uring ring poisoned")
+}
+
+/// `iovec` for `bytes`, for ops that only read from it.
+pub fn iovec(bytes: &[u8]) -> libc::iovec {
+    libc::iovec { iov_base: bytes.as_ptr() as *mut _, iov_len: bytes.len() }
+}
+
+// ─── DirectFile ──────────────────────────────────────────────────────────────
+
+/// Block size and alignment of O_DIRECT writes.
+pub const DIRECT_ALIGN: usize = 4096;
+
+/// Staging buffer of a `DirectFile`; written as `DIRECT_CHUNK` ops at once.
+const DIRECT_BUFFER: usize = 8 << 20;
+const DIRECT_CHUNK: usize = 1 << 20;
+
+/// O_DIRECT output file (see "O_DIRECT" above).  Call [`finish`](Self::finish);
+/// dropping it loses the staged tail.
+pub struct DirectFile {
+    file: File,
+    buf: *mut u8,
+    fill: usize,
+    written: u64,
+}
+
+// SAFETY: `buf` is owned by the file and only touched through `&mut self`.
+unsafe impl Send for DirectFile {}
+
+impl DirectFile {
+    /// Create (truncate) `path` for O_DIRECT writes, or for plain block
+    /// writes where the file system rejects O_DIRECT.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let open = |flags: i32| OpenOptions::new()
+            .write(true).create(true).truncate(true).custom_flags(flags).open(path);
+        let file = match open(libc::O_DIRECT) {
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+                println!("[Uring] '{}' does not support O_DIRECT — writing through the page cache", path.display());
+                open(0)?
+            }
+            r => r?,
+        };
+        let buf = unsafe { alloc_zeroed(Self::layout()) };
+        if buf.is_null() {
+            return Err(io::Error::new(io::ErrorKind::OutOfMemory, "O_DIRECT buffer"));
+        }
+        Ok(Self { file, buf, fill: 0, written: 0 })
+    }
+
+    /// Write the staged bytes, zero-padded to a whole block, truncate the
+    /// file to the bytes actually written and return it.
+    pub fn finish(mut self) -> io::Result<File> {
+        let len = self.written + self.fill as u64;
+        let padded = self.fill.next_multiple_of(DIRECT_ALIGN);
+        unsafe { ptr::write_bytes(self.buf.add(self.fill), 0, padded - self.fill) };
+        self.fill = padded;
+        self.flush_blocks()?;
+        self.file.set_len(len)?;
+        let file = self.file.try_clone()?;
+        Ok(file)
+    }
+
+    fn layout() -> Layout {
+        Layout::from_size_align(DIRECT_BUFFER, DIRECT_ALIGN).expect("O_DIRECT buffer layout")
+    }
+
+    /// Write every whole block staged so far and keep the partial one.
+    fn flush_blocks(&mut self) -> io::Result<()> {
+        let whole = self.fill / DIRECT_ALIGN * DIRECT_ALIGN;
+        if whole == 0 {
+            return Ok(());
+        }
+        let staged = unsafe { std::slice::from_raw_parts(self.buf, whole) };
+        match shared() {
+            Some(ring) => {
+                let mut ops: Vec<Op> = staged.chunks(DIRECT_CHUNK).enumerate()
+                    .map(|(k, c)| Op::write(self.file.as_raw_fd(), self.written + (k * DIRECT_CHUNK) as u64, vec![iovec(c)]))
+                    .collect();
+                lock(ring).run(&mut ops)?;
+                ops.into_iter().try_for_each(Op::result)?;
+            }
+            None => self.file.write_all_at(staged, self.written)?,
+        }
+        self.written += whole as u64;
+        unsafe { ptr::copy(self.buf.add(whole), self.buf, self.fill - whole) };
+        self.fill -= whole;
+        Ok(())
+    }
+}
+
+impl Write for DirectFile {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.fill == DIRECT_BUFFER {
+            self.flush_blocks()?;
+        }
+        let n = data.len().min(DIRECT_BUFFER - self.fill);
+        unsafe { ptr::copy_nonoverlapping(data.as_ptr(), self.buf.add(self.fill), n) };
+        self.fill += n;
+        Ok(n)
+    }
+
+    /// Partial blocks cannot be written before `finish`; nothing to do.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for DirectFile {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.buf, Self::layout()) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::num::NonZeroUsize;
+
+    use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+
+    use common::{Superblock, INITIAL_SHM_SIZE};
+
+    use crate::runtime::input_output::file_set::FileEntry;
+    use crate::runtime::input_output::persistence::read_io_records;
+    use crate::runtime::input_output::record_format::{Compression, OutputFraming, RecordFormat};
+    use crate::runtime::input_output::slot_flusher::{OutputOptions, SlotFlusher};
+    use crate::runtime::input_output::slot_loader::SlotLoader;
+    use crate::shm::format_shared_memory;
+
+    #[test]
+    fn ring_reads_and_writes_across_many_ops() {
+        let Ok(mut ring) = Ring::new(8) else {
+            println!("io_uring unavailable — skipped");
+            return;
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<Vec<u8>> = (0..40).map(|i| vec![i as u8; 1000 + i * 37]).collect();
+
+        // More iovecs than one submission carries, more ops than entries.
+        let out = File::create(dir.path().join("out")).unwrap();
+        let iov: Vec<libc::iovec> = data.iter().cycle().take(3 * MAX_IOV).map(|d| iovec(d)).collect();
+        ring.write_all_at(out.as_raw_fd(), 0, &iov).unwrap();
+        let expected: Vec<u8> = data.iter().cycle().take(3 * MAX_IOV).flatten().copied().collect();
+        assert!(std::fs::read(dir.path().join("out")).unwrap() == expected);
+
+        let files: Vec<File> = (0..data.len())
+            .map(|i| {
+                let p = dir.path().join(format!("in{}", i));
+                std::fs::write(&p, &data[i]).unwrap();
+                File::open(p).unwrap()
+            })
+            .collect();
+        let mut bufs: Vec<Vec<u8>> = data.iter().map(|d| vec![0; d.len()]).collect();
+        let mut ops: Vec<Op> = files.iter().zip(bufs.iter_mut())
+            .map(|(f, b)| {
+                let (a, z) = b.split_at_mut(100);
+                Op::read(f.as_raw_fd(), 0, vec![
+                    libc::iovec { iov_base: a.as_mut_ptr() as *mut _, iov_len: a.len() },
+                    libc::iovec { iov_base: z.as_mut_ptr() as *mut _, iov_len: z.len() },
+                ])
+            })
+            .collect();
+        ring.run(&mut ops).unwrap();
+        assert!(ops.into_iter().all(|op| op.result().is_ok()));
+        assert!(bufs == data);
+
+        // Reading past EOF fails that op only.
+        let mut big = vec![0u8; 5000];
+        let mut ops = vec![Op::read(files[0].as_raw_fd(), 0, vec![libc::iovec {
+            iov_base: big.as_mut_ptr() as *mut _, iov_len: big.len(),
+        }])];
+        ring.run(&mut ops).unwrap();
+        let err = ops.pop().unwrap().result().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn direct_file_keeps_exact_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("direct.bin");
+        let data: Vec<u8> = (0..DIRECT_BUFFER + 3 * DIRECT_ALIGN + 123).map(|i| (i % 251) as u8).collect();
+        let mut f = DirectFile::create(&path).unwrap();
+        for piece in data.chunks(70_001) {
+            f.write_all(piece).unwrap();
+        }
+        f.finish().unwrap();
+        let mut back = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut back).unwrap();
+        assert!(back == data);
+    }
+
+    #[test]
+    fn file_set_loads_and_outputs_match_blocking_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let shm = dir.path().join("region");
+        format_shared_memory(shm.to_str().unwrap()).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&shm).unwrap();
+        let len = NonZeroUsize::new(INITIAL_SHM_SIZE as usize).unwrap();
+        let base = unsafe {
+            mmap(None, len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, Some(&file), 0)
+        }.unwrap() as usize;
+        let sb = unsafe { &*(base as *const Superblock) };
+
+        // Records straddling pages, one tiny and one multi-page file.
+        let files: Vec<FileEntry> = (0..300usize)
+            .map(|i| {
+                let path = dir.path().join(format!("img{:03}.bin", i));
+                let size = if i == 7 { 1 } else if i == 8 { 200_000 } else { 500 + i * 13 };
+                std::fs::write(&path, (0..size).map(|b| (b * 7 + i) as u8).collect::<Vec<_>>()).unwrap();
+                FileEntry { path, len: size as u64 }
+            })
+            .collect();
+        let lines = RecordFormat::Lines;
+        let load = |slot| SlotLoader::new(base).load_files(&files, slot, true, &lines, Compression::None).unwrap();
+
+        configure(false, 64);
+        assert_eq!(load(3), files.len());
+        configure(true, 64);
+        if shared().is_none() {
+            println!("io_uring unavailable — skipped");
+            unsafe { munmap(base as *mut _, len.get()).unwrap() };
+            return;
+        }
+        assert_eq!(load(4), files.len());
+        let payloads = |slot| read_io_records(base, sb, slot).into_iter().map(|(_, p)| p).collect::<Vec<_>>();
+        assert!(payloads(3) == payloads(4));
+        assert!(read_io_records(base, sb, 4).iter().all(|&(origin, _)| origin == 4));
+
+        // Gathered, direct and blocking outputs are byte-identical.
+        let flusher = SlotFlusher::new(base);
+        for framing in [OutputFraming::Newline, OutputFraming::LengthPrefixed, OutputFraming::Raw] {
+            let opts = OutputOptions { framing, compression: Compression::None, ..Default::default() };
+            let out = |name: &str, opts: &OutputOptions, uring: bool| {
+                configure(uring, 64);
+                let path = dir.path().join(format!("{:?}.{}", framing, name));
+                assert_eq!(flusher.save_slot_with(&path, 4, opts).unwrap(), files.len());
+                std::fs::read(path).unwrap()
+            };
+            let blocking = out("blocking", &opts, false);
+            assert!(out("uring", &opts, true) == blocking);
+            assert!(out("direct", &OutputOptions { direct: true, ..opts.clone() }, true) == blocking);
+        }
+        configure(false, 64);
+        unsafe { munmap(base as *mut _, len.get()).unwrap() };
+    }
+}
@@ -0,0 +1,718 @@
+// Page-accounting checker (`host shm fsck`, and `fsck: true` in a DAG file).
+//
+// Every page the bump allocator has handed out — `[bump_allocator_start,
+// bump_allocator)` — must be owned by exactly one of:
+//
+//   • a stream or I/O slot chain            (the slot table's head arrays)
+//   • a free-list shard                     (`free_list_heads`)
+//   • a large-class free list               (`class_free_lists`)
+//   • the shared-state bucket array         (`shared_map_base`)
+//   • a bucket's conflict list, including each entry's payload overflow pages
+//   • a committed registry payload          (`RegistryEntry::payload_offset`)
+//   • a zero-copy input extent              (`mapped_inputs`) — all of a
+//     retired one, the pages past `pages` of a live one (the rest is on
+//     the slot chain that loaded it)
+//   • a key-value directory page            (`kv_directory` and its leaves)
+//   • a key-value bucket's entries, including their overflow pages
+//   • a ring slot's header page and its pages (`stream_rings`)
+//   • a stream slot's subscriber table page (`stream_subscriptions`)
+//
+// The checker walks all of them with the bounds- and cycle-safe walkers from
+// `inspect`, builds a page → owner map, and reports:
+//
+//   • chain faults: cycles, links past capacity or into the fixed arenas,
+//     misaligned links, a slot tail that is not the last page of its chain,
+//     a subscriber cursor on a page that is not on its slot's chain;
+//   • pages owned twice: on a chain *and* a free list (use after free), on two
+//     free shards (double free), or on two different structures;
+//   • leaked pages: below the bump pointer but owned by nothing.
+//
+// Leaked pages are grouped into orphan chains by following their byte-0 link.
+// When an orphan chain runs into an owned page it is attributed to that owner
+// — the usual shape of a leak is a consumed prefix that was unlinked from a
+// slot without being freed, and it still points at the slot's live pages.
+//
+// Pages are counted in 4 KiB units: a 64 KiB or 2 MiB page on a slot chain or
+// a class free list owns every unit it spans.  Its class comes from the page
+// header (slots) or from the list it is on.
+//
+// Two stream slots may legitimately share pages: `Bridge` and `Aggregate`
+// point a downstream slot at an upstream chain and only clear the upstream
+// metadata when the node finishes.  Such pages are counted as `shared`, not
+// as problems.
+//
+// The region is read, never written, but the check is only meaningful on a
+// quiescent region: a page moving between a free list and a chain while the
+// walk runs shows up as a false double-ownership or leak.  Pages handed to the
+// extended pool's resolution buffer after a paged-mode flip are not visible in
+// the region either, so leak accounting is skipped while the pool is in use.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::mem::{offset_of, size_of};
+
+use common::*;
+
+use super::inspect::{ChainFault, ChainWalk, ShmImage, ShmView};
+use super::reclaimer::SlotKind;
+
+// ─── Owners ──────────────────────────────────────────────────────────────────
+
+/// The structure a page is reachable from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Owner {
+    Stream(usize),
+    Io(usize),
+    Free(usize),
+    FreeClass(u32),
+    BucketArray,
+    Bucket(usize),
+    Registry(u32),
+    Mapped(usize),
+    KvDirectory,
+    KvBucket(usize),
+    RingHeader(usize),
+    Ring(usize),
+    Subscribers(usize),
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Owner::Stream(s) => write!(f, "stream slot {}", s),
+            Owner::Io(s) => write!(f, "io slot {}", s),
+            Owner::Free(shard) => write!(f, "free shard {}", shard),
+            Owner::FreeClass(c) => write!(f, "{} free list", PAGE_CLASS_NAMES[c as usize]),
+            Owner::BucketArray => write!(f, "shared-state bucket array"),
+            Owner::Bucket(b) => write!(f, "bucket {}", b),
+            Owner::Registry(i) => write!(f, "registry entry {}", i),
+            Owner::Mapped(i) => write!(f, "mapped input {}", i),
+            Owner::KvDirectory => write!(f, "key-value directory"),
+            Owner::KvBucket(b) => write!(f, "key-value bucket {}", b),
+            Owner::RingHeader(s) => write!(f, "ring header of stream slot {}", s),
+            Owner::Ring(s) => write!(f, "ring of stream slot {}", s),
+            Owner::Subscribers(s) => write!(f, "subscriber table of stream slot {}", s),
+        }
+    }
+}
+
+// ─── Report ──────────────────────────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct FsckOptions {
+    /// Emit the report as JSON instead of text.
+    pub json: bool,
+    /// Skip the unowned-page scan (the extended pool holds pages the region
+    /// does not account for).
+    pub skip_leaks: bool,
+}
+
+#[derive(Serialize, Default)]
+pub struct FsckReport {
+    pub path: String,
+    /// Pages handed out by the bump allocator.
+    pub heap_pages: u64,
+    /// Pages reachable from some owner (shared pages counted once).
+    pub owned_pages: usize,
+    pub free_pages: usize,
+    /// Pages reachable from more than one stream slot (routing aliases).
+    pub shared_pages: usize,
+    /// Links into the extended pool, which is not part of the region.
+    pub paged_links: usize,
+    pub problems: Vec<Problem>,
+    /// `None` when leak accounting was skipped.
+    pub leaked_pages: Option<usize>,
+    pub leaks: Vec<Leak>,
+    /// Owned page count per owner, in owner order.
+    pub owners: Vec<OwnerPages>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty() && self.leaked_pages.unwrap_or(0) == 0
+    }
+
+    /// One-line summary for logs.
+    pub fn summary(&self) -> String {
+        let leaks = match self.leaked_pages {
+            Some(n) => format!("{} leaked", n),
+            None => "leaks not checked".to_string(),
+        };
+        format!(
+            "{} heap pages: {} owned, {} free, {}; {} problem(s)",
+            self.heap_pages, self.owned_pages, self.free_pages, leaks, self.problems.len(),
+        )
+    }
+}
+
+#[derive(Serialize)]
+pub struct Problem {
+    /// `chain`, `tail`, `cursor`, `double-owned` or `double-free`.
+    pub kind: &'static str,
+    pub page: PageId,
+    pub detail: String,
+}
+
+#[derive(Serialize)]
+pub struct Leak {
+    /// First page of the orphan chain.
+    pub head: PageId,
+    pub pages: usize,
+    /// Owner of the page the orphan chain links into, if any.
+    pub attributed_to: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct OwnerPages {
+    pub owner: String,
+    pub pages: usize,
+    pub leaked: usize,
+}
+
+// ─── Checker ─────────────────────────────────────────────────────────────────
+
+struct Checker<'v, 'a> {
+    view: &'v ShmView<'a>,
+    owner: HashMap<PageId, Owner>,
+    per_owner: BTreeMap<Owner, usize>,
+    report: FsckReport,
+}
+
+impl Checker<'_, '_> {
+    fn problem(&mut self, kind: &'static str, page: PageId, detail: String) {
+        self.report.problems.push(Problem { kind, page, detail });
+    }
+
+    /// 4 KiB units spanned by `page` as seen from `owner`.  Shared-state and
+    /// registry pages are always small; their byte 12 is payload, not a class.
+    fn units(&self, owner: Owner, page: PageId) -> u64 {
+        let class = match owner {
+            Owner::Stream(_) | Owner::Io(_) | Owner::Ring(_) => self.view.page_class(page),
+            Owner::FreeClass(c) => c,
+            Owner::Mapped(_) => MAPPED_PAGE_CLASS,
+            _ => PAGE_CLASS_SMALL,
+        };
+        (page_class_size(class) / PAGE_SIZE) as u64
+    }
+
+    /// Records the pages of `walk` as owned by `owner`, plus the walk's fault.
+    fn claim(&mut self, owner: Owner, walk: &ChainWalk) {
+        for &page in &walk.pages {
+            let units = self.units(owner, page);
+            *self.per_owner.entry(owner).or_insert(0) += units as usize;
+            for unit in (0..units).map(|k| page + k * PAGE_SIZE as PageId) {
+                match self.owner.get(&unit).copied() {
+                    None => { self.owner.insert(unit, owner); }
+                    Some(prev) if prev == owner => {}
+                    Some(Owner::Stream(_)) if matches!(owner, Owner::Stream(_)) => self.report.shared_pages += 1,
+                    Some(prev @ (Owner::Free(_) | Owner::FreeClass(_)))
+                        if matches!(owner, Owner::Free(_) | Owner::FreeClass(_)) =>
+                    {
+                        self.problem("double-free", unit, format!("on {} and {}", prev, owner));
+                        break;
+                    }
+                    Some(prev) => {
+                        self.problem("double-owned", unit, format!("on {} and {}", prev, owner));
+                        break;
+                    }
+                }
+            }
+        }
+        self.chain_fault(owner, walk);
+    }
+
+    fn chain_fault(&mut self, owner: Owner, walk: &ChainWalk) {
+        match walk.fault {
+            None => {}
+            Some(ChainFault::Paged(_)) => self.report.paged_links += 1,
+            Some(fault) => {
+                let page = walk.pages.last().copied().unwrap_or(PAGE_ID_NULL);
+                self.problem("chain", page, format!("{}: {}", owner, fault));
+            }
+        }
+    }
+
+    fn slots(&mut self, kind: SlotKind, count: usize) {
+        for slot in 0..count {
+            let (head, tail) = self.view.slot_ends(kind, slot);
+            if (head, tail) == (0, 0) {
+                continue;
+            }
+            let owner = match kind { SlotKind::Stream => Owner::Stream(slot), SlotKind::Io => Owner::Io(slot) };
+            let walk = self.view.walk(head, 0);
+            self.claim(owner, &walk);
+            let last = walk.pages.last().copied().unwrap_or(PAGE_ID_NULL);
+            if walk.fault.is_none() && tail != last {
+                self.problem("tail", tail, format!("{}: tail {:#x} but chain ends at {:#x}", owner, tail, last));
+            }
+        }
+    }
+
+    fn free_list(&mut self) {
+        for shard in 0..FREE_LIST_SHARD_COUNT {
+            let walk = self.view.walk(self.view.free_list_head(shard), 0);
+            self.report.free_pages += walk.pages.len();
+            self.claim(Owner::Free(shard), &walk);
+        }
+        for class in 1..PAGE_CLASS_COUNT as u32 {
+            let walk = self.view.walk(self.view.class_free_list_head(class), 0);
+            self.report.free_pages += walk.pages.len() * (page_class_size(class) / PAGE_SIZE) as usize;
+            self.claim(Owner::FreeClass(class), &walk);
+        }
+    }
+
+    fn shared_state(&mut self) {
+        let map_base = self.view.shared_map_base() as PageId;
+        if map_base == 0 {
+            return;
+        }
+        let off = match self.view.check_page(map_base) {
+            Ok(off) => off,
+            Err(fault) => {
+                self.problem("chain", map_base, format!("shared_map_base: {}", fault));
+                return;
+            }
+        };
+        self.claim(Owner::BucketArray, &ChainWalk { pages: vec![map_base], fault: None });
+        for bucket in 0..BUCKET_COUNT {
+            let head = self.view.u64_at(off + bucket * PAGE_ID_SIZE).unwrap_or(0);
+            let walk = self.view.walk(head, offset_of!(ChainNodeHeader, next_node));
+            // Each entry page is the first page of its own payload walk.
+            for &entry in &walk.pages {
+                let payload = self.view.walk_payload(entry);
+                self.claim(Owner::Bucket(bucket), &payload);
+            }
+            self.chain_fault(Owner::Bucket(bucket), &walk);
+        }
+    }
+
+    fn kv_store(&mut self) {
+        let root = self.view.kv_directory() as PageId;
+        if root == 0 {
+            return;
+        }
+        let root_off = match self.view.check_page(root) {
+            Ok(off) => off,
+            Err(fault) => {
+                self.problem("chain", root, format!("kv_directory: {}", fault));
+                return;
+            }
+        };
+        self.claim(Owner::KvDirectory, &ChainWalk { pages: vec![root], fault: None });
+        for r in 0..KV_FANOUT {
+            let leaf = self.view.u64_at(root_off + r * PAGE_ID_SIZE).unwrap_or(0);
+            if leaf == 0 {
+                continue;
+            }
+            let leaf_off = match self.view.check_page(leaf) {
+                Ok(off) => off,
+                Err(fault) => {
+                    self.problem("chain", leaf, format!("{} entry {}: {}", Owner::KvDirectory, r, fault));
+                    continue;
+                }
+            };
+            self.claim(Owner::KvDirectory, &ChainWalk { pages: vec![leaf], fault: None });
+            for l in 0..KV_FANOUT {
+                let bucket = r * KV_FANOUT + l;
+                let head = self.view.u64_at(leaf_off + l * PAGE_ID_SIZE).unwrap_or(0);
+                let walk = self.view.walk(head, offset_of!(KvEntryHeader, next_entry));
+                for &entry in &walk.pages {
+                    let payload = self.view.walk_payload(entry);
+                    self.claim(Owner::KvBucket(bucket), &payload);
+                }
+                self.chain_fault(Owner::KvBucket(bucket), &walk);
+            }
+        }
+    }
+
+    fn rings(&mut self) {
+        for slot in 0..self.view.slot_count(SlotKind::Stream) {
+            let header = self.view.stream_ring(slot) as PageId;
+            if header == 0 {
+                continue;
+            }
+            let off = match self.view.check_page(header) {
+                Ok(off) => off,
+                Err(fault) => {
+                    self.problem("chain", header, format!("{}: {}", Owner::RingHeader(slot), fault));
+                    continue;
+                }
+            };
+            self.claim(Owner::RingHeader(slot), &ChainWalk { pages: vec![header], fault: None });
+            let capacity = self.view.u32_at(off + offset_of!(RingHeader, capacity)).unwrap_or(0) as usize;
+            for i in 0..capacity.min(RING_MAX_PAGES) {
+                let page = self.view.u64_at(off + offset_of!(RingHeader, pages) + i * PAGE_ID_SIZE).unwrap_or(0);
+                let walk = match self.view.check_page(page) {
+                    Ok(_) => ChainWalk { pages: vec![page], fault: None },
+                    Err(fault) => ChainWalk { pages: Vec::new(), fault: Some(fault) },
+                };
+                self.claim(Owner::Ring(slot), &walk);
+            }
+        }
+    }
+
+    /// Claims each subscriber table page and checks that every cursor that
+    /// has left the head is on its slot's chain (so `reclaim` can find it).
+    fn subscriptions(&mut self) {
+        for slot in 0..self.view.slot_count(SlotKind::Stream) {
+            let table = self.view.stream_subscriptions(slot) as PageId;
+            if table == 0 {
+                continue;
+            }
+            let off = match self.view.check_page(table) {
+                Ok(off) => off,
+                Err(fault) => {
+                    self.problem("chain", table, format!("{}: {}", Owner::Subscribers(slot), fault));
+                    continue;
+                }
+            };
+            self.claim(Owner::Subscribers(slot), &ChainWalk { pages: vec![table], fault: None });
+            let chain: HashSet<PageId> = self.view.walk(self.view.slot_ends(SlotKind::Stream, slot).0, 0)
+                .pages.into_iter().collect();
+            let count = self.view.u32_at(off + offset_of!(SubscriptionTable, count)).unwrap_or(0) as usize;
+            for i in 0..count.min(SUBSCRIBER_MAX) {
+                let cursor = off + offset_of!(SubscriptionTable, subscribers) + i * size_of::<SubscriberCursor>();
+                let page = self.view.u64_at(cursor + offset_of!(SubscriberCursor, page)).unwrap_or(0);
+                if page != 0 && !chain.contains(&page) {
+                    self.problem("cursor", page, format!(
+                        "{}: subscriber {} is on page {:#x}, which is not on the slot's chain",
+                        Owner::Subscribers(slot), i, page,
+                    ));
+                }
+            }
+        }
+    }
+
+    fn registry(&mut self) {
+        let entry_size = size_of::<RegistryEntry>();
+        let layout = self.view.layout;
+        let count = (self.view.next_atomic_idx() as usize).min(layout.registry_size() as usize / entry_size);
+        for i in 0..count {
+            let base = layout.registry_offset as usize + i * entry_size;
+            let payload = self.view.u32_at(base + offset_of!(RegistryEntry, payload_offset)).unwrap_or(0);
+            if payload != 0 {
+                let walk = self.view.walk_payload(payload as PageId);
+                self.claim(Owner::Registry(i as u32), &walk);
+            }
+        }
+    }
+
+    fn mapped_inputs(&mut self) {
+        let span = page_class_size(MAPPED_PAGE_CLASS) as PageId;
+        for i in 0..MAPPED_INPUT_COUNT {
+            let base = offset_of!(Superblock, mapped_inputs) + i * size_of::<MappedInput>();
+            let field = |off: usize| self.view.u32_at(base + off).unwrap_or(0);
+            let first = match field(offset_of!(MappedInput, state)) {
+                MAPPED_INPUT_LIVE => field(offset_of!(MappedInput, pages)),
+                MAPPED_INPUT_RETIRED => 0,
+                _ => continue,
+            };
+            let offset = field(offset_of!(MappedInput, offset)) as PageId;
+            let pages = (first..field(offset_of!(MappedInput, capacity)))
+                .map(|j| offset + j as PageId * span)
+                .collect();
+            self.claim(Owner::Mapped(i), &ChainWalk { pages, fault: None });
+        }
+    }
+
+    /// Groups unowned heap pages into orphan chains and attributes each to the
+    /// owner of the page it links into.  Returns the leaked page count per owner.
+    fn leaks(&mut self) -> BTreeMap<Owner, usize> {
+        let heap_end = self.view.heap_end();
+        let leaked: HashSet<PageId> = (self.view.layout.bump_allocator_start as PageId..heap_end)
+            .step_by(PAGE_SIZE as usize)
+            .filter(|p| !self.owner.contains_key(p))
+            .collect();
+        let next = |p: PageId| self.view.u64_at(p as usize).unwrap_or(PAGE_ID_NULL);
+
+        let linked: HashSet<PageId> = leaked.iter().map(|&p| next(p)).filter(|n| leaked.contains(n)).collect();
+        let mut heads: Vec<PageId> = leaked.iter().copied().filter(|p| !linked.contains(p)).collect();
+        heads.sort_unstable();
+        let mut seen = HashSet::new();
+        let mut per_owner: BTreeMap<Owner, usize> = BTreeMap::new();
+
+        for head in heads {
+            let mut pages = 0;
+            let mut current = head;
+            while leaked.contains(&current) && seen.insert(current) {
+                pages += 1;
+                current = next(current);
+            }
+            let owner = self.owner.get(&current).copied();
+            if let Some(owner) = owner {
+                *per_owner.entry(owner).or_insert(0) += pages;
+            }
+            self.report.leaks.push(Leak { head, pages, attributed_to: owner.map(|o| o.to_string()) });
+        }
+        // Whatever is left forms cycles made only of leaked pages.
+        let mut rest: Vec<PageId> = leaked.iter().copied().filter(|p| !seen.contains(p)).collect();
+        rest.sort_unstable();
+        for head in rest {
+            let mut pages = 0;
+            let mut current = head;
+            while leaked.contains(&current) && seen.insert(current) {
+                pages += 1;
+                current = next(current);
+            }
+            if pages > 0 {
+                self.report.leaks.push(Leak { head, pages, attributed_to: None });
+            }
+        }
+
+        self.report.leaked_pages = Some(leaked.len());
+        per_owner
+    }
+}
+
+/// Checks page ownership across the whole region in `view`.
+pub fn fsck(path: &str, view: &ShmView, skip_leaks: bool) -> FsckReport {
+    let mut c = Checker {
+        view,
+        owner: HashMap::new(),
+        per_owner: BTreeMap::new(),
+        report: FsckReport {
+            path: path.to_string(),
+            heap_pages: view.heap_end().saturating_sub(view.layout.bump_allocator_start as u64) / PAGE_SIZE as u64,
+            ..Default::default()
+        },
+    };
+    c.slots(SlotKind::Stream, view.slot_count(SlotKind::Stream));
+    c.slots(SlotKind::Io, view.slot_count(SlotKind::Io));
+    c.free_list();
+    c.shared_state();
+    c.registry();
+    c.mapped_inputs();
+    c.kv_store();
+    c.rings();
+    c.subscriptions();
+
+    c.report.owned_pages = c.owner.len();
+    let leaked = if skip_leaks { BTreeMap::new() } else { c.leaks() };
+    let mut owners: BTreeMap<Owner, (usize, usize)> = c.per_owner.iter()
+        .filter(|(o, _)| !matches!(o, Owner::Free(_) | Owner::FreeClass(_)))
+        .map(|(&o, &pages)| (o, (pages, 0)))
+        .collect();
+    for (o, n) in leaked {
+        owners.entry(o).or_insert((0, 0)).1 = n;
+    }
+    c.report.owners = owners.into_iter()
+        .map(|(o, (pages, leaked))| OwnerPages { owner: o.to_string(), pages, leaked })
+        .collect();
+    c.report
+}
+
+pub fn print_report(r: &FsckReport) {
+    println!("[Fsck] {}", r.path);
+    println!("  {}", r.summary());
+    if r.shared_pages > 0 {
+        println!("  {} page(s) shared between stream slots (routing aliases)", r.shared_pages);
+    }
+    if r.paged_links > 0 {
+        println!("  {} chain(s) continue into the extended pool (not checked)", r.paged_links);
+    }
+
+    if !r.problems.is_empty() {
+        println!("\n── Problems: {}", r.problems.len());
+        for p in &r.problems {
+            println!("  {:<12} {:>#12x}  {}", p.kind, p.page, p.detail);
+        }
+    }
+
+    if !r.leaks.is_empty() {
+        println!("\n── Leaked chains: {}", r.leaks.len());
+        for l in &r.leaks {
+            let into = l.attributed_to.as_ref().map(|o| format!("  → {}", o)).unwrap_or_default();
+            println!("  {:>#12x}  {:>8} pages{}", l.head, l.pages, into);
+        }
+    }
+
+    if !r.owners.is_empty() {
+        println!("\n── Pages per owner");
+        for o in &r.owners {
+            print!("  {:<28} {:>8}", o.owner, o.pages);
+            if o.leaked > 0 {
+                print!("   ({} leaked)", o.leaked);
+            }
+            println!();
+        }
+    }
+}
+
+/// Entry point for `host shm fsck`.  Fails when any problem or leak is found.
+pub fn run_fsck(path: &str, opts: &FsckOptions) -> Result<()> {
+    let image = ShmImage::open(path)?;
+    let view = ShmView::new(image.bytes()).map_err(|e| anyhow!("{}: {}", path, e))?;
+    let report = fsck(path, &view, opts.skip_leaks);
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    if report.is_clean() {
+        Ok(())
+    } else {
+        Err(anyhow!("{}: {}", path, report.summary()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(pages: usize) -> Vec<u8> {
+        let len = ShmLayout::DEFAULT.bump_allocator_start as usize + pages * PAGE_SIZE as usize;
+        let mut b = vec![0u8; len];
+        let put32 = |b: &mut Vec<u8>, off: usize, v: u32| b[off..off + 4].copy_from_slice(&v.to_le_bytes());
+        put32(&mut b, 0, SHM_MAGIC);
+        put32(&mut b, offset_of!(Superblock, bump_allocator), len as u32);
+        put32(&mut b, offset_of!(Superblock, global_capacity), len as u32);
+        let layout = offset_of!(Superblock, layout);
+        put32(&mut b, offset_of!(Superblock, layout_offset), layout as u32);
+        b[layout..layout + SHM_LAYOUT_SIZE].copy_from_slice(&ShmLayout::DEFAULT.to_le_bytes());
+        b
+    }
+
+    fn page(i: usize) -> PageId {
+        ShmLayout::DEFAULT.bump_allocator_start as PageId + (i as PageId) * PAGE_SIZE as PageId
+    }
+
+    fn writer_head(slot: usize) -> usize {
+        ShmLayout::DEFAULT.writer_heads_offset() as usize + slot * PAGE_ID_SIZE
+    }
+
+    fn writer_tail(slot: usize) -> usize {
+        ShmLayout::DEFAULT.writer_tails_offset() as usize + slot * PAGE_ID_SIZE
+    }
+
+    fn put64(b: &mut [u8], off: usize, v: PageId) {
+        b[off..off + 8].copy_from_slice(&v.to_le_bytes());
+    }
+
+    /// Links `pages` in order and installs them as stream slot `slot`.
+    fn stream(b: &mut [u8], slot: usize, pages: &[PageId]) {
+        for w in pages.windows(2) {
+            put64(b, w[0] as usize, w[1]);
+        }
+        put64(b, writer_head(slot), pages[0]);
+        put64(b, writer_tail(slot), *pages.last().unwrap());
+    }
+
+    fn free_shard(b: &mut [u8], shard: usize, pages: &[PageId]) {
+        for w in pages.windows(2) {
+            put64(b, w[0] as usize, w[1]);
+        }
+        put64(b, offset_of!(Superblock, free_list_heads) + shard * PAGE_ID_SIZE, pages[0]);
+    }
+
+    #[test]
+    fn fully_owned_region_is_clean() {
+        let mut b = region(5);
+        stream(&mut b, 0, &[page(0), page(3)]);
+        stream(&mut b, 9, &[page(3)]);                  // aliases the tail of slot 0
+        free_shard(&mut b, 2, &[page(1), page(2), page(4)]);
+        let view = ShmView::new(&b).unwrap();
+        let r = fsck("t", &view, false);
+        assert!(r.is_clean(), "{:?}", r.problems.iter().map(|p| &p.detail).collect::<Vec<_>>());
+        assert_eq!((r.heap_pages, r.owned_pages, r.free_pages, r.shared_pages), (5, 5, 3, 1));
+        assert_eq!(r.leaked_pages, Some(0));
+    }
+
+    #[test]
+    fn large_pages_own_every_unit_they_span() {
+        let mut b = region(33);
+        let class_off = offset_of!(Page, class);
+        for p in [page(0), page(16)] {
+            b[p as usize + class_off..p as usize + class_off + 4].copy_from_slice(&PAGE_CLASS_MEDIUM.to_le_bytes());
+        }
+        stream(&mut b, 0, &[page(0), page(32)]);
+        put64(&mut b, offset_of!(Superblock, class_free_lists), page(16));
+        let view = ShmView::new(&b).unwrap();
+        let r = fsck("t", &view, false);
+        assert!(r.is_clean(), "{:?}", r.problems.iter().map(|p| &p.detail).collect::<Vec<_>>());
+        assert_eq!((r.heap_pages, r.owned_pages, r.free_pages), (33, 33, 16));
+
+        // Freeing the chain's 64 KiB page onto a 4 KiB shard overlaps it.
+        free_shard(&mut b, 1, &[page(5)]);
+        let view = ShmView::new(&b).unwrap();
+        let kinds: Vec<_> = fsck("t", &view, false).problems.iter().map(|p| (p.kind, p.page)).collect();
+        assert_eq!(kinds, vec![("double-owned", page(5))]);
+    }
+
+    #[test]
+    fn mapped_input_extents_are_owned() {
+        let mut b = region(4 * 512);
+        let put32 = |b: &mut [u8], off: usize, v: u32| b[off..off + 4].copy_from_slice(&v.to_le_bytes());
+        let entry = |i: usize, field: usize| offset_of!(Superblock, mapped_inputs) + i * size_of::<MappedInput>() + field;
+        // Entry 0: live, 1 of 2 pages overlaid and on io slot 3.
+        put32(&mut b, entry(0, offset_of!(MappedInput, state)), MAPPED_INPUT_LIVE);
+        put32(&mut b, entry(0, offset_of!(MappedInput, offset)), page(0) as u32);
+        put32(&mut b, entry(0, offset_of!(MappedInput, pages)), 1);
+        put32(&mut b, entry(0, offset_of!(MappedInput, capacity)), 2);
+        put32(&mut b, page(0) as usize + offset_of!(Page, class), MAPPED_PAGE_CLASS);
+        let io = ShmLayout::DEFAULT.io_heads_offset() as usize + 3 * PAGE_ID_SIZE;
+        put64(&mut b, io, page(0));
+        put64(&mut b, ShmLayout::DEFAULT.io_tails_offset() as usize + 3 * PAGE_ID_SIZE, page(0));
+        // Entry 2: retired, 2 pages.
+        put32(&mut b, entry(2, offset_of!(MappedInput, state)), MAPPED_INPUT_RETIRED);
+        put32(&mut b, entry(2, offset_of!(MappedInput, offset)), page(2 * 512) as u32);
+        put32(&mut b, entry(2, offset_of!(MappedInput, capacity)), 2);
+        let view = ShmView::new(&b).unwrap();
+        let r = fsck("t", &view, false);
+        assert!(r.is_clean(), "{:?}", r.problems.iter().map(|p| &p.detail).collect::<Vec<_>>());
+        assert_eq!((r.heap_pages, r.owned_pages), (2048, 2048));
+        let owners: Vec<_> = r.owners.iter().map(|o| (o.owner.as_str(), o.pages)).collect();
+        assert_eq!(owners, vec![("io slot 3", 512), ("mapped input 0", 512), ("mapped input 2", 1024)]);
+    }
+
+    #[test]
+    fn page_on_chain_and_free_list_is_reported() {
+        let mut b = region(3);
+        stream(&mut b, 1, &[page(0), page(1)]);
+        free_shard(&mut b, 0, &[page(1)]);
+        free_shard(&mut b, 3, &[page(2)]);
+        free_shard(&mut b, 5, &[page(2)]);
+        let view = ShmView::new(&b).unwrap();
+        let r = fsck("t", &view, false);
+        let kinds: Vec<_> = r.problems.iter().map(|p| (p.kind, p.page)).collect();
+        assert_eq!(kinds, vec![("double-owned", page(1)), ("double-free", page(2))]);
+    }
+
+    #[test]
+    fn leaked_prefix_is_attributed_to_its_slot() {
+        let mut b = region(6);
+        // A consumer advanced slot 4's head past pages 0 and 1 without freeing them.
+        stream(&mut b, 4, &[page(0), page(1), page(2), page(3)]);
+        put64(&mut b, writer_head(4), page(2));
+        free_shard(&mut b, 0, &[page(5)]);
+        // Page 4 was dropped on the floor entirely.
+        let view = ShmView::new(&b).unwrap();
+        let r = fsck("t", &view, false);
+        assert!(r.problems.is_empty());
+        assert_eq!(r.leaked_pages, Some(3));
+        let leaks: Vec<_> = r.leaks.iter().map(|l| (l.head, l.pages, l.attributed_to.clone())).collect();
+        assert_eq!(leaks, vec![
+            (page(0), 2, Some("stream slot 4".to_string())),
+            (page(4), 1, None),
+        ]);
+        let slot = r.owners.iter().find(|o| o.owner == "stream slot 4").unwrap();
+        assert_eq!((slot.pages, slot.leaked), (2, 2));
+
+        assert_eq!(fsck("t", &view, true).leaked_pages, None);
+    }
+
+    #[test]
+    fn stale_tail_and_dangling_link_are_reported() {
+        let mut b = region(3);
+        stream(&mut b, 0, &[page(0), page(1)]);
+        put64(&mut b, writer_tail(0), page(0));
+        stream(&mut b, 1, &[page(2)]);
+        put64(&mut b, page(2) as usize, page(7));       // past capacity
+        let view = ShmView::new(&b).unwrap();
+        let r = fsck("t", &view, false);
+        let kinds: Vec<_> = r.problems.iter().map(|p| p.kind).collect();
+        assert_eq!(kinds, vec!["tail", "chain"]);
+        assert!(r.problems[1].detail.contains("stream slot 1"));
+    }
+}
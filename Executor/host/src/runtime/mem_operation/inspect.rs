@@ -0,0 +1,1021 @@
+// Read-only SHM inspector (`host shm inspect`).
+//
+// Maps a region file — a live `/dev/shm/<region>` or a copy taken after a
+// crash — with PROT_READ and reports what the Superblock and the page heap
+// contain: bump/capacity, free-list shard lengths, per-slot chain page and
+// record counts (and closed streams), ring and subscriber positions, registry
+// entries with their atomic values, barrier counters, shared-state buckets and
+// the tail of the log arena.
+//
+// Unlike the runtime walkers (`persistence::read_chain_records`,
+// `reclaimer::count_free_list_pages`) nothing here trusts the region: every
+// PageId is range- and alignment-checked against the mapped length before it
+// is dereferenced, chains are walked with cycle detection, and a fault stops
+// only the chain it was found in.  A corrupted snapshot therefore produces a
+// report with per-chain faults instead of a SIGSEGV or an endless loop.
+//
+// Paged-mode PageIds (`>= DIRECT_LIMIT`) live in the extended-pool backing
+// file, which is not part of the region; chains that reach one are reported
+// as such and not followed.
+
+use anyhow::{anyhow, Result};
+use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::mem::{offset_of, size_of};
+use std::num::NonZeroUsize;
+
+use common::*;
+
+use super::reclaimer::SlotKind;
+
+// ─── Mapped image ────────────────────────────────────────────────────────────
+
+/// A region file mapped read-only at a kernel-chosen address.
+pub struct ShmImage {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl ShmImage {
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path).map_err(|e| anyhow!("open '{}': {}", path, e))?;
+        let len = file.metadata()?.len() as usize;
+        let size = NonZeroUsize::new(len).ok_or_else(|| anyhow!("'{}' is empty", path))?;
+        let ptr = unsafe {
+            mmap(None, size, ProtFlags::PROT_READ, MapFlags::MAP_SHARED, Some(&file), 0)
+        }
+        .map_err(|e| anyhow!("mmap '{}': {}", path, e))?;
+        Ok(Self { ptr: ptr as *mut u8, len })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for ShmImage {
+    fn drop(&mut self) {
+        unsafe { let _ = munmap(self.ptr as *mut _, self.len); }
+    }
+}
+
+// ─── Checked view ────────────────────────────────────────────────────────────
+
+/// Why a chain walk stopped early.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainFault {
+    /// PageId is not a multiple of `PAGE_SIZE`.
+    Misaligned(PageId),
+    /// PageId points into the fixed arenas, or past the mapped/committed end.
+    OutOfRange(PageId),
+    /// Paged-mode PageId — lives in the extended pool, not in this region.
+    Paged(PageId),
+    /// PageId already visited on this chain.
+    Cycle(PageId),
+    /// A page's `cursor` exceeds the data capacity of its page class.
+    BadCursor { page: PageId, cursor: u32, capacity: usize },
+    /// The chain ends in the middle of a record.
+    PartialRecord,
+}
+
+impl fmt::Display for ChainFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ChainFault::Misaligned(id) => write!(f, "misaligned page id {:#x}", id),
+            ChainFault::OutOfRange(id) => write!(f, "page id {:#x} outside the page heap", id),
+            ChainFault::Paged(id) => write!(f, "paged-mode page id {:#x} (extended pool, not inspectable)", id),
+            ChainFault::Cycle(id) => write!(f, "cycle back to page {:#x}", id),
+            ChainFault::BadCursor { page, cursor, capacity } =>
+                write!(f, "page {:#x} cursor {} exceeds {} data bytes", page, cursor, capacity),
+            ChainFault::PartialRecord => write!(f, "chain ends inside a record"),
+        }
+    }
+}
+
+/// Pages of one chain in link order, plus the fault that ended it early.
+pub struct ChainWalk {
+    pub pages: Vec<PageId>,
+    pub fault: Option<ChainFault>,
+}
+
+/// Bounds-checked accessor over a region's bytes.
+pub struct ShmView<'a> {
+    bytes: &'a [u8],
+    /// Descriptor stored in the region; slot-table and arena offsets come
+    /// from it, not from this build's defaults.
+    pub layout: ShmLayout,
+    /// End of the page heap: the smaller of the mapped length, the recorded
+    /// `global_capacity` and the bump pointer.
+    heap_end: u64,
+}
+
+impl<'a> ShmView<'a> {
+    /// Verifies the layout descriptor and wraps `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        let layout = check_shm_layout(bytes).map_err(|e| anyhow!("{}", e))?;
+        let mut view = Self { bytes, layout, heap_end: 0 };
+        let cap = view.u32_at(offset_of!(Superblock, global_capacity)).unwrap_or(0) as u64;
+        let bump = view.bump_allocator() as u64;
+        view.heap_end = (bytes.len() as u64).min(cap).min(bump.max(layout.bump_allocator_start as u64));
+        Ok(view)
+    }
+
+    pub fn mapped_len(&self) -> usize { self.bytes.len() }
+
+    pub fn u32_at(&self, off: usize) -> Option<u32> {
+        self.bytes.get(off..off + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    pub fn u64_at(&self, off: usize) -> Option<u64> {
+        self.bytes.get(off..off + 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    pub fn slice(&self, off: usize, len: usize) -> Option<&'a [u8]> {
+        self.bytes.get(off..off.checked_add(len)?)
+    }
+
+    fn sb_u32(&self, field: usize) -> u32 { self.u32_at(field).unwrap_or(0) }
+
+    pub fn bump_allocator(&self) -> u32 { self.sb_u32(offset_of!(Superblock, bump_allocator)) }
+    pub fn global_capacity(&self) -> u32 { self.sb_u32(offset_of!(Superblock, global_capacity)) }
+    pub fn log_offset(&self) -> u32 { self.sb_u32(offset_of!(Superblock, log_offset)) }
+    pub fn registry_lock(&self) -> u32 { self.sb_u32(offset_of!(Superblock, registry_lock)) }
+    pub fn next_atomic_idx(&self) -> u32 { self.sb_u32(offset_of!(Superblock, next_atomic_idx)) }
+    pub fn shared_map_base(&self) -> u32 { self.sb_u32(offset_of!(Superblock, shared_map_base)) }
+    pub fn kv_directory(&self) -> u32 { self.sb_u32(offset_of!(Superblock, kv_directory)) }
+
+    /// End of the page heap (exclusive): pages at or past it were never handed out.
+    pub fn heap_end(&self) -> u64 { self.heap_end }
+
+    pub fn free_list_head(&self, shard: usize) -> PageId {
+        self.u64_at(offset_of!(Superblock, free_list_heads) + shard * PAGE_ID_SIZE).unwrap_or(0)
+    }
+
+    /// Head of the free list of the large page `class` (`1..PAGE_CLASS_COUNT`).
+    pub fn class_free_list_head(&self, class: u32) -> PageId {
+        self.u64_at(offset_of!(Superblock, class_free_lists) + (class as usize - 1) * PAGE_ID_SIZE)
+            .unwrap_or(0)
+    }
+
+    /// Class recorded in the header of page `id` (small if unreadable).
+    pub fn page_class(&self, id: PageId) -> u32 {
+        self.u32_at(id as usize + offset_of!(Page, class)).unwrap_or(PAGE_CLASS_SMALL)
+    }
+
+    /// Page class new pages of a stream or I/O slot are allocated in.
+    pub fn slot_page_class(&self, kind: SlotKind, slot: usize) -> u32 {
+        let table = match kind {
+            SlotKind::Stream => self.layout.stream_page_classes_offset(),
+            SlotKind::Io => self.layout.io_page_classes_offset(),
+        };
+        self.bytes.get(table as usize + slot).copied().unwrap_or(0) as u32
+    }
+
+    /// Number of stream or I/O slots in the region.
+    pub fn slot_count(&self, kind: SlotKind) -> usize {
+        match kind {
+            SlotKind::Stream => self.layout.stream_slot_count as usize,
+            SlotKind::Io => self.layout.io_slot_count as usize,
+        }
+    }
+
+    /// `(head, tail)` of a stream or I/O slot.
+    pub fn slot_ends(&self, kind: SlotKind, slot: usize) -> (PageId, PageId) {
+        let (heads, tails) = match kind {
+            SlotKind::Stream => (self.layout.writer_heads_offset(), self.layout.writer_tails_offset()),
+            SlotKind::Io => (self.layout.io_heads_offset(), self.layout.io_tails_offset()),
+        };
+        (
+            self.u64_at(heads as usize + slot * PAGE_ID_SIZE).unwrap_or(0),
+            self.u64_at(tails as usize + slot * PAGE_ID_SIZE).unwrap_or(0),
+        )
+    }
+
+    pub fn barrier(&self, id: usize) -> u32 {
+        self.sb_u32(self.layout.barriers_offset() as usize + id * 4)
+    }
+
+    /// Offset of stream `slot`'s ring header page, 0 for a chain slot.
+    pub fn stream_ring(&self, slot: usize) -> u32 {
+        self.sb_u32(self.layout.stream_rings_offset() as usize + slot * SHM_OFFSET_SIZE)
+    }
+
+    /// Offset of stream `slot`'s subscriber table page, 0 without subscribers.
+    pub fn stream_subscriptions(&self, slot: usize) -> u32 {
+        self.sb_u32(self.layout.stream_subscriptions_offset() as usize + slot * SHM_OFFSET_SIZE)
+    }
+
+    /// Futex word of stream `slot` (see "Stream signals" in `common`).
+    pub fn stream_signal(&self, slot: usize) -> u32 {
+        self.sb_u32(self.layout.stream_signals_offset() as usize + slot * 4)
+    }
+
+    /// Validates a non-null PageId and returns its byte offset.
+    pub fn check_page(&self, id: PageId) -> Result<usize, ChainFault> {
+        if id >= DIRECT_LIMIT {
+            return Err(ChainFault::Paged(id));
+        }
+        if !id.is_multiple_of(PAGE_SIZE as u64) {
+            return Err(ChainFault::Misaligned(id));
+        }
+        if id < self.layout.bump_allocator_start as u64 || id + PAGE_SIZE as u64 > self.heap_end {
+            return Err(ChainFault::OutOfRange(id));
+        }
+        Ok(id as usize)
+    }
+
+    /// Follows the `PageId` stored at byte `link` of each page from `head`
+    /// (0 for `Page::next_offset` and `ChainNodeHeader::next_node`).
+    pub fn walk(&self, head: PageId, link: usize) -> ChainWalk {
+        let mut pages = Vec::new();
+        let mut seen = HashSet::new();
+        let mut current = head;
+        while current != PAGE_ID_NULL {
+            let off = match self.check_page(current) {
+                Ok(off) => off,
+                Err(fault) => return ChainWalk { pages, fault: Some(fault) },
+            };
+            if !seen.insert(current) {
+                return ChainWalk { pages, fault: Some(ChainFault::Cycle(current)) };
+            }
+            pages.push(current);
+            current = self.u64_at(off + link).unwrap_or(PAGE_ID_NULL);
+        }
+        ChainWalk { pages, fault: None }
+    }
+
+    /// Pages of a shared-state entry's payload: the entry page itself, then
+    /// the overflow pages linked from `next_payload_page` (each carrying its
+    /// own next PageId at byte 0).
+    pub fn walk_payload(&self, entry: PageId) -> ChainWalk {
+        let Ok(off) = self.check_page(entry) else {
+            return self.walk(entry, 0);
+        };
+        let next = self.u64_at(off + offset_of!(ChainNodeHeader, next_payload_page)).unwrap_or(0);
+        let mut rest = self.walk(next, 0);
+        if rest.pages.contains(&entry) {
+            rest.fault = Some(ChainFault::Cycle(entry));
+        }
+        rest.pages.insert(0, entry);
+        rest
+    }
+
+    /// Scans the length-prefixed `[len u32][origin u32][payload]` records of
+    /// a stream/IO chain.  Payloads are copied only when `keep` is set.
+    pub fn scan_records(&self, pages: &[PageId], keep: bool) -> RecordScan {
+        let mut scan = RecordScan::default();
+        let mut segs = Vec::with_capacity(pages.len());
+        for &page in pages {
+            let off = page as usize;
+            let cursor = self.u32_at(off + PAGE_ID_SIZE).unwrap_or(0);
+            let capacity = page_class_data_size(self.page_class(page));
+            if cursor as usize > capacity {
+                scan.fault = Some(ChainFault::BadCursor { page, cursor, capacity });
+                break;
+            }
+            if let Some(data) = self.slice(off + PAGE_HEADER_SIZE, cursor as usize) {
+                segs.push(data);
+            }
+        }
+        let mut reader = SegReader { segs, seg: 0, pos: 0 };
+        loop {
+            let mut hdr = [0u8; 8];
+            match reader.read(&mut hdr) {
+                0 => break,
+                8 => {}
+                _ => { scan.fault.get_or_insert(ChainFault::PartialRecord); break; }
+            }
+            let len = u32::from_le_bytes(hdr[..4].try_into().unwrap()) as usize;
+            let origin = u32::from_le_bytes(hdr[4..].try_into().unwrap());
+            if keep {
+                let mut payload = vec![0u8; len];
+                if reader.read(&mut payload) != len {
+                    scan.fault.get_or_insert(ChainFault::PartialRecord);
+                    break;
+                }
+                scan.records.push((origin, payload));
+            } else if !reader.skip(len) {
+                scan.fault.get_or_insert(ChainFault::PartialRecord);
+                break;
+            }
+            scan.count += 1;
+            scan.bytes += len as u64;
+        }
+        scan
+    }
+}
+
+#[derive(Default)]
+pub struct RecordScan {
+    pub count: usize,
+    pub bytes: u64,
+    pub records: Vec<(u32, Vec<u8>)>,
+    pub fault: Option<ChainFault>,
+}
+
+/// Sequential reader over the written part of each page's data area.
+struct SegReader<'a> {
+    segs: Vec<&'a [u8]>,
+    seg: usize,
+    pos: usize,
+}
+
+impl SegReader<'_> {
+    /// Fills as much of `dest` as the chain holds; returns the byte count.
+    fn read(&mut self, dest: &mut [u8]) -> usize {
+        let mut n = 0;
+        while n < dest.len() && self.seg < self.segs.len() {
+            let src = &self.segs[self.seg][self.pos..];
+            let take = src.len().min(dest.len() - n);
+            dest[n..n + take].copy_from_slice(&src[..take]);
+            n += take;
+            self.advance(take);
+        }
+        n
+    }
+
+    fn skip(&mut self, mut len: usize) -> bool {
+        while len > 0 && self.seg < self.segs.len() {
+            let take = (self.segs[self.seg].len() - self.pos).min(len);
+            len -= take;
+            self.advance(take);
+        }
+        len == 0
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.pos += n;
+        while self.seg < self.segs.len() && self.pos == self.segs[self.seg].len() {
+            self.seg += 1;
+            self.pos = 0;
+        }
+    }
+}
+
+// ─── Report ──────────────────────────────────────────────────────────────────
+
+/// What `host shm inspect` prints.
+#[derive(Default)]
+pub struct InspectOptions {
+    /// Emit the report as JSON instead of text.
+    pub json: bool,
+    /// Restrict the slot table to one slot.
+    pub slot: Option<(SlotKind, usize)>,
+    /// Include the records of `slot` in the report.
+    pub dump: bool,
+    /// Bytes of the log arena to show (from the end of what was written).
+    pub log_tail: usize,
+}
+
+pub const DEFAULT_LOG_TAIL: usize = 4096;
+
+#[derive(Serialize)]
+pub struct InspectReport {
+    pub path: String,
+    pub file_len: u64,
+    pub superblock: SuperblockInfo,
+    pub free_list: Vec<ChainInfo>,
+    pub free_pages: usize,
+    /// Free lists of the large page classes (64k, 2m).
+    pub class_free_lists: Vec<ClassListInfo>,
+    pub slots: Vec<SlotInfo>,
+    pub registry: Vec<RegistryInfo>,
+    pub barriers: Vec<BarrierInfo>,
+    pub rings: Vec<RingInfo>,
+    pub subscribers: Vec<SubscriberInfo>,
+    pub shared_buckets: Vec<BucketInfo>,
+    pub log_tail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub records: Option<Vec<RecordInfo>>,
+}
+
+#[derive(Serialize)]
+pub struct SuperblockInfo {
+    pub layout_version: u32,
+    pub page_size: u32,
+    pub layout_hash: String,
+    /// Geometry chosen when the region was formatted.
+    pub stream_slot_count: u32,
+    pub io_slot_count: u32,
+    pub barrier_count: u32,
+    pub bump_allocator_start: u32,
+    pub bump_allocator: u32,
+    pub global_capacity: u32,
+    /// Pages handed out by the bump allocator so far.
+    pub heap_pages: u64,
+    pub log_offset: u32,
+    pub registry_lock: u32,
+    pub next_atomic_idx: u32,
+    pub shared_map_base: u32,
+    pub kv_directory: u32,
+}
+
+#[derive(Serialize)]
+pub struct ChainInfo {
+    pub shard: usize,
+    pub pages: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fault: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ClassListInfo {
+    pub class: &'static str,
+    pub pages: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fault: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SlotInfo {
+    pub kind: &'static str,
+    pub slot: usize,
+    /// Page class the slot allocates in (`"4k"`, `"64k"`, `"2m"`).
+    pub page_class: &'static str,
+    pub head: PageId,
+    pub tail: PageId,
+    pub pages: usize,
+    pub records: usize,
+    pub bytes: u64,
+    /// Stream slot whose producer called `close_stream`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub closed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fault: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RegistryInfo {
+    pub index: u32,
+    pub name: String,
+    pub value: u64,
+    pub payload_offset: u32,
+    pub payload_len: u32,
+}
+
+#[derive(Serialize)]
+pub struct BarrierInfo {
+    pub id: usize,
+    pub count: u32,
+}
+
+/// A ring slot: the page the producer fills and the page each consumer reads.
+#[derive(Serialize)]
+pub struct RingInfo {
+    pub slot: usize,
+    pub capacity: u32,
+    pub tail_seq: u64,
+    pub consumer_seqs: Vec<u64>,
+}
+
+/// One named cursor of a stream slot with subscribers.  `page == 0` means
+/// the head of the chain.
+#[derive(Serialize)]
+pub struct SubscriberInfo {
+    pub slot: usize,
+    pub name: String,
+    pub records: u64,
+    pub page: PageId,
+    pub offset: u32,
+}
+
+#[derive(Serialize)]
+pub struct BucketInfo {
+    pub bucket: usize,
+    pub entries: usize,
+    pub writers: Vec<u32>,
+    pub bytes: u64,
+    pub pages: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fault: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RecordInfo {
+    pub index: usize,
+    pub origin: u32,
+    pub len: usize,
+    /// The payload as text when it is printable UTF-8, else a hex preview.
+    pub data: String,
+}
+
+fn kind_label(kind: SlotKind) -> &'static str {
+    match kind {
+        SlotKind::Stream => "stream",
+        SlotKind::Io => "io",
+    }
+}
+
+fn class_name(class: u32) -> &'static str {
+    PAGE_CLASS_NAMES.get(class as usize).copied().unwrap_or("?")
+}
+
+fn slot_info(view: &ShmView, kind: SlotKind, slot: usize) -> SlotInfo {
+    let (head, tail) = view.slot_ends(kind, slot);
+    let walk = view.walk(head, 0);
+    let scan = view.scan_records(&walk.pages, false);
+    SlotInfo {
+        kind: kind_label(kind),
+        slot,
+        page_class: class_name(view.slot_page_class(kind, slot)),
+        head,
+        tail,
+        pages: walk.pages.len(),
+        records: scan.count,
+        bytes: scan.bytes,
+        closed: kind == SlotKind::Stream && view.stream_signal(slot) & STREAM_CLOSED != 0,
+        fault: walk.fault.or(scan.fault).map(|f| f.to_string()),
+    }
+}
+
+/// Printable text, or a hex preview of the first 64 bytes.
+fn preview(payload: &[u8]) -> String {
+    match std::str::from_utf8(payload) {
+        Ok(s) if !s.chars().any(|c| c.is_control() && c != '\t') => s.to_string(),
+        Ok(s) if !s.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r')) =>
+            s.escape_default().to_string(),
+        _ => {
+            let hex: String = payload.iter().take(64).map(|b| format!("{:02x}", b)).collect();
+            if payload.len() > 64 { format!("0x{}…", hex) } else { format!("0x{}", hex) }
+        }
+    }
+}
+
+/// Builds the report for the region in `view`.
+pub fn inspect(path: &str, view: &ShmView, opts: &InspectOptions) -> InspectReport {
+    let layout = view.layout;
+    let bump = view.bump_allocator();
+    let superblock = SuperblockInfo {
+        layout_version: layout.version,
+        page_size: layout.page_size,
+        layout_hash: format!("{:#018x}", layout.layout_hash),
+        stream_slot_count: layout.stream_slot_count,
+        io_slot_count: layout.io_slot_count,
+        barrier_count: layout.barrier_count,
+        bump_allocator_start: layout.bump_allocator_start,
+        bump_allocator: bump,
+        global_capacity: view.global_capacity(),
+        heap_pages: bump.saturating_sub(layout.bump_allocator_start) as u64 / PAGE_SIZE as u64,
+        log_offset: view.log_offset(),
+        registry_lock: view.registry_lock(),
+        next_atomic_idx: view.next_atomic_idx(),
+        shared_map_base: view.shared_map_base(),
+        kv_directory: view.kv_directory(),
+    };
+
+    // ── Free list ────────────────────────────────────────────────────────────
+    let free_list: Vec<ChainInfo> = (0..FREE_LIST_SHARD_COUNT)
+        .map(|shard| {
+            let walk = view.walk(view.free_list_head(shard), 0);
+            ChainInfo { shard, pages: walk.pages.len(), fault: walk.fault.map(|f| f.to_string()) }
+        })
+        .collect();
+    let free_pages = free_list.iter().map(|c| c.pages).sum();
+    let class_free_lists = (1..PAGE_CLASS_COUNT as u32)
+        .map(|class| {
+            let walk = view.walk(view.class_free_list_head(class), 0);
+            ClassListInfo {
+                class: class_name(class),
+                pages: walk.pages.len(),
+                fault: walk.fault.map(|f| f.to_string()),
+            }
+        })
+        .collect();
+
+    // ── Slots ────────────────────────────────────────────────────────────────
+    let slots: Vec<SlotInfo> = match opts.slot {
+        Some((kind, slot)) => vec![slot_info(view, kind, slot)],
+        None => (0..view.slot_count(SlotKind::Stream))
+            .map(|s| (SlotKind::Stream, s))
+            .chain((0..view.slot_count(SlotKind::Io)).map(|s| (SlotKind::Io, s)))
+            .filter(|&(kind, s)| view.slot_ends(kind, s) != (0, 0))
+            .map(|(kind, s)| slot_info(view, kind, s))
+            .collect(),
+    };
+
+    // ── Registry + atomics ───────────────────────────────────────────────────
+    let entry_size = size_of::<RegistryEntry>();
+    let count = (view.next_atomic_idx() as usize).min(layout.registry_size() as usize / entry_size);
+    let registry = (0..count)
+        .filter_map(|i| {
+            let base = layout.registry_offset as usize + i * entry_size;
+            let name = view.slice(base + offset_of!(RegistryEntry, name), 52)?;
+            let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            Some(RegistryInfo {
+                index: view.u32_at(base + offset_of!(RegistryEntry, index))?,
+                name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+                value: view.u64_at(layout.atomic_offset(i) as usize)?,
+                payload_offset: view.u32_at(base + offset_of!(RegistryEntry, payload_offset))?,
+                payload_len: view.u32_at(base + offset_of!(RegistryEntry, payload_len))?,
+            })
+        })
+        .collect();
+
+    // ── Barriers ─────────────────────────────────────────────────────────────
+    let barriers = (0..layout.barrier_count as usize)
+        .map(|id| BarrierInfo { id, count: view.barrier(id) })
+        .filter(|b| b.count != 0)
+        .collect();
+
+    // ── Rings ────────────────────────────────────────────────────────────────
+    let rings = (0..view.slot_count(SlotKind::Stream))
+        .filter_map(|slot| {
+            let off = view.check_page(view.stream_ring(slot) as PageId).ok()?;
+            let consumers = view.u32_at(off + offset_of!(RingHeader, consumer_count))? as usize;
+            let cursors = off + offset_of!(RingHeader, consumers);
+            Some(RingInfo {
+                slot,
+                capacity: view.u32_at(off + offset_of!(RingHeader, capacity))?,
+                tail_seq: view.u64_at(off + offset_of!(RingHeader, tail_seq))?,
+                consumer_seqs: (0..consumers.min(RING_MAX_CONSUMERS))
+                    .filter_map(|c| view.u64_at(cursors + c * size_of::<RingCursor>()))
+                    .collect(),
+            })
+        })
+        .collect();
+
+    // ── Subscribers ──────────────────────────────────────────────────────────
+    let mut subscribers = Vec::new();
+    for slot in 0..view.slot_count(SlotKind::Stream) {
+        let Ok(off) = view.check_page(view.stream_subscriptions(slot) as PageId) else { continue };
+        let count = view.u32_at(off + offset_of!(SubscriptionTable, count)).unwrap_or(0) as usize;
+        for i in 0..count.min(SUBSCRIBER_MAX) {
+            let cursor = off + offset_of!(SubscriptionTable, subscribers) + i * size_of::<SubscriberCursor>();
+            let Some(name) = view.slice(cursor, SUBSCRIBER_NAME_LEN) else { break };
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            subscribers.push(SubscriberInfo {
+                slot,
+                name: String::from_utf8_lossy(name).into_owned(),
+                records: view.u64_at(cursor + offset_of!(SubscriberCursor, records)).unwrap_or(0),
+                page: view.u64_at(cursor + offset_of!(SubscriberCursor, page)).unwrap_or(0),
+                offset: view.u32_at(cursor + offset_of!(SubscriberCursor, offset)).unwrap_or(0),
+            });
+        }
+    }
+
+    // ── Shared-state buckets ─────────────────────────────────────────────────
+    let mut shared_buckets = Vec::new();
+    let map_base = view.shared_map_base() as PageId;
+    if map_base != 0 {
+        match view.check_page(map_base) {
+            Ok(off) => {
+                for bucket in 0..BUCKET_COUNT {
+                    let head = view.u64_at(off + bucket * PAGE_ID_SIZE).unwrap_or(0);
+                    if head == 0 {
+                        continue;
+                    }
+                    let walk = view.walk(head, offset_of!(ChainNodeHeader, next_node));
+                    let mut info = BucketInfo {
+                        bucket,
+                        entries: walk.pages.len(),
+                        writers: Vec::new(),
+                        bytes: 0,
+                        pages: 0,
+                        fault: walk.fault.map(|f| f.to_string()),
+                    };
+                    for &entry in &walk.pages {
+                        let e = entry as usize;
+                        info.writers.push(view.u32_at(e + offset_of!(ChainNodeHeader, writer_id)).unwrap_or(0));
+                        info.bytes += view.u32_at(e + offset_of!(ChainNodeHeader, data_len)).unwrap_or(0) as u64;
+                        let payload = view.walk_payload(entry);
+                        info.pages += payload.pages.len();
+                        if info.fault.is_none() {
+                            info.fault = payload.fault.map(|f| f.to_string());
+                        }
+                    }
+                    shared_buckets.push(info);
+                }
+            }
+            Err(fault) => shared_buckets.push(BucketInfo {
+                bucket: 0,
+                entries: 0,
+                writers: Vec::new(),
+                bytes: 0,
+                pages: 0,
+                fault: Some(format!("shared_map_base: {}", fault)),
+            }),
+        }
+    }
+
+    // ── Log arena tail ───────────────────────────────────────────────────────
+    let written = (view.log_offset() as usize).min(layout.log_arena_size() as usize);
+    let start = written.saturating_sub(opts.log_tail);
+    let log_tail = view
+        .slice(layout.log_arena_offset as usize + start, written - start)
+        .map(|b| String::from_utf8_lossy(b).into_owned())
+        .unwrap_or_default();
+
+    // ── Record dump ──────────────────────────────────────────────────────────
+    let records = match (opts.dump, opts.slot) {
+        (true, Some((kind, slot))) => {
+            let walk = view.walk(view.slot_ends(kind, slot).0, 0);
+            let scan = view.scan_records(&walk.pages, true);
+            Some(scan.records.iter().enumerate()
+                .map(|(index, (origin, payload))| RecordInfo {
+                    index,
+                    origin: *origin,
+                    len: payload.len(),
+                    data: preview(payload),
+                })
+                .collect())
+        }
+        _ => None,
+    };
+
+    InspectReport {
+        path: path.to_string(),
+        file_len: view.mapped_len() as u64,
+        superblock,
+        free_list,
+        free_pages,
+        class_free_lists,
+        slots,
+        registry,
+        barriers,
+        rings,
+        subscribers,
+        shared_buckets,
+        log_tail,
+        records,
+    }
+}
+
+fn print_report(r: &InspectReport) {
+    let sb = &r.superblock;
+    println!("[Inspect] {} ({} bytes)", r.path, r.file_len);
+    println!("  layout        v{}  page {}  hash {}", sb.layout_version, sb.page_size, sb.layout_hash);
+    println!(
+        "  geometry      {} stream / {} I/O slots   {} barriers   heap from {:#x}",
+        sb.stream_slot_count, sb.io_slot_count, sb.barrier_count, sb.bump_allocator_start,
+    );
+    println!(
+        "  bump          {} / capacity {}  ({} heap pages)",
+        sb.bump_allocator, sb.global_capacity, sb.heap_pages,
+    );
+    println!(
+        "  log_offset    {}   registry_lock {}   next_atomic_idx {}   shared_map_base {:#x}",
+        sb.log_offset, sb.registry_lock, sb.next_atomic_idx, sb.shared_map_base,
+    );
+    println!("  kv_directory  {:#x}", sb.kv_directory);
+
+    println!("\n── Free list: {} pages", r.free_pages);
+    for c in r.free_list.iter().filter(|c| c.pages > 0 || c.fault.is_some()) {
+        println!("  shard {:>2}  {:>8} pages{}", c.shard, c.pages, fault_suffix(&c.fault));
+    }
+    for c in r.class_free_lists.iter().filter(|c| c.pages > 0 || c.fault.is_some()) {
+        println!("  {:<8}  {:>8} pages{}", c.class, c.pages, fault_suffix(&c.fault));
+    }
+
+    println!("\n── Slots: {}", r.slots.len());
+    if !r.slots.is_empty() {
+        println!(
+            "  {:<6} {:>5} {:>5} {:>8} {:>10} {:>12}  {:>12} {:>12}",
+            "kind", "slot", "class", "pages", "records", "bytes", "head", "tail",
+        );
+    }
+    for s in &r.slots {
+        println!(
+            "  {:<6} {:>5} {:>5} {:>8} {:>10} {:>12}  {:>#12x} {:>#12x}{}{}",
+            s.kind, s.slot, s.page_class, s.pages, s.records, s.bytes, s.head, s.tail,
+            if s.closed { "  closed" } else { "" },
+            fault_suffix(&s.fault),
+        );
+    }
+
+    println!("\n── Registry: {} entries", r.registry.len());
+    for e in &r.registry {
+        print!("  [{:>4}] {:<40} = {}", e.index, e.name, e.value);
+        if e.payload_offset != 0 {
+            print!("   (payload {:#x}, {} bytes)", e.payload_offset, e.payload_len);
+        }
+        println!();
+    }
+
+    if !r.barriers.is_empty() {
+        println!("\n── Barriers");
+        for b in &r.barriers {
+            println!("  barrier {:>2}  count {}", b.id, b.count);
+        }
+    }
+
+    if !r.rings.is_empty() {
+        println!("\n── Rings");
+        for g in &r.rings {
+            println!(
+                "  stream {:>5}  {} pages  producer at page {}  consumers at {:?}",
+                g.slot, g.capacity, g.tail_seq, g.consumer_seqs,
+            );
+        }
+    }
+
+    if !r.subscribers.is_empty() {
+        println!("\n── Subscribers");
+        for s in &r.subscribers {
+            let at = if s.page == 0 { "head".to_string() } else { format!("{:#x}+{}", s.page, s.offset) };
+            println!("  stream {:>5}  {:<24}  {:>10} records read  at {}", s.slot, s.name, s.records, at);
+        }
+    }
+
+    if !r.shared_buckets.is_empty() {
+        println!("\n── Shared-state buckets: {}", r.shared_buckets.len());
+        for b in &r.shared_buckets {
+            println!(
+                "  bucket {:>4}  {} entries  {} bytes  {} pages  writers {:?}{}",
+                b.bucket, b.entries, b.bytes, b.pages, b.writers, fault_suffix(&b.fault),
+            );
+        }
+    }
+
+    if !r.log_tail.is_empty() {
+        println!("\n── Log tail ({} bytes)", r.log_tail.len());
+        for line in r.log_tail.lines() {
+            println!("  {}", line);
+        }
+    }
+
+    if let Some(records) = &r.records {
+        println!("\n── Records: {}", records.len());
+        for rec in records {
+            println!("  [{:>5}][src={}] {}", rec.index, rec.origin, rec.data);
+        }
+    }
+}
+
+fn fault_suffix(fault: &Option<String>) -> String {
+    fault.as_ref().map(|f| format!("   !! {}", f)).unwrap_or_default()
+}
+
+/// Entry point for `host shm inspect`.
+pub fn run_inspect(path: &str, opts: &InspectOptions) -> Result<()> {
+    let image = ShmImage::open(path)?;
+    let view = ShmView::new(image.bytes()).map_err(|e| anyhow!("{}: {}", path, e))?;
+    if let Some((kind, slot)) = opts.slot {
+        let count = view.slot_count(kind);
+        if slot >= count {
+            return Err(anyhow!("{} slot {} out of range (0..{})", kind_label(kind), slot, count));
+        }
+    }
+    let report = inspect(path, &view, opts);
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal formatted region in a heap buffer: superblock + arenas +
+    /// `pages` heap pages, with the bump pointer past all of them.
+    fn region(pages: usize) -> Vec<u8> {
+        region_with(ShmLayout::DEFAULT, pages)
+    }
+
+    fn region_with(layout_desc: ShmLayout, pages: usize) -> Vec<u8> {
+        let len = layout_desc.bump_allocator_start as usize + pages * PAGE_SIZE as usize;
+        let mut b = vec![0u8; len];
+        let put32 = |b: &mut Vec<u8>, off: usize, v: u32| b[off..off + 4].copy_from_slice(&v.to_le_bytes());
+        put32(&mut b, 0, SHM_MAGIC);
+        put32(&mut b, offset_of!(Superblock, bump_allocator), len as u32);
+        put32(&mut b, offset_of!(Superblock, global_capacity), len as u32);
+        let layout = offset_of!(Superblock, layout);
+        put32(&mut b, offset_of!(Superblock, layout_offset), layout as u32);
+        b[layout..layout + SHM_LAYOUT_SIZE].copy_from_slice(&layout_desc.to_le_bytes());
+        b
+    }
+
+    fn page(i: usize) -> PageId {
+        ShmLayout::DEFAULT.bump_allocator_start as PageId + (i as PageId) * PAGE_SIZE as PageId
+    }
+
+    fn link(b: &mut [u8], from: PageId, to: PageId) {
+        b[from as usize..from as usize + 8].copy_from_slice(&to.to_le_bytes());
+    }
+
+    /// Appends records across `pages` the way the guest does: fill each
+    /// page's data area, then continue on the next.
+    fn write_records(b: &mut [u8], pages: &[PageId], records: &[(u32, &[u8])]) {
+        let mut stream = Vec::new();
+        for (origin, payload) in records {
+            stream.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            stream.extend_from_slice(&origin.to_le_bytes());
+            stream.extend_from_slice(payload);
+        }
+        for (i, chunk) in stream.chunks(PAGE_DATA_SIZE).enumerate() {
+            let p = pages[i] as usize;
+            let cursor = p + offset_of!(Page, cursor);
+            b[cursor..cursor + 4].copy_from_slice(&(chunk.len() as u32).to_le_bytes());
+            b[p + PAGE_HEADER_SIZE..p + PAGE_HEADER_SIZE + chunk.len()].copy_from_slice(chunk);
+        }
+        for w in pages.windows(2) {
+            link(b, w[0], w[1]);
+        }
+    }
+
+    #[test]
+    fn scans_records_across_pages() {
+        let mut b = region(4);
+        let big = vec![b'x'; PAGE_DATA_SIZE + 100];
+        write_records(&mut b, &[page(0), page(2)], &[(7, b"hello"), (3, &big)]);
+        let slot_off = ShmLayout::DEFAULT.writer_heads_offset() as usize + 5 * PAGE_ID_SIZE;
+        b[slot_off..slot_off + 8].copy_from_slice(&page(0).to_le_bytes());
+
+        let view = ShmView::new(&b).unwrap();
+        let info = slot_info(&view, SlotKind::Stream, 5);
+        assert_eq!((info.pages, info.records), (2, 2));
+        assert_eq!(info.bytes, 5 + big.len() as u64);
+        assert!(info.fault.is_none());
+
+        let walk = view.walk(page(0), 0);
+        let scan = view.scan_records(&walk.pages, true);
+        assert_eq!(scan.records[0], (7, b"hello".to_vec()));
+        assert_eq!(scan.records[1].1.len(), big.len());
+    }
+
+    #[test]
+    fn walk_reports_faults_instead_of_following_them() {
+        let mut b = region(3);
+        link(&mut b, page(0), page(1));
+        link(&mut b, page(1), page(0));
+        let view = ShmView::new(&b).unwrap();
+        let walk = view.walk(page(0), 0);
+        assert_eq!(walk.pages, vec![page(0), page(1)]);
+        assert_eq!(walk.fault, Some(ChainFault::Cycle(page(0))));
+
+        link(&mut b, page(1), page(3));                 // one past the heap end
+        let view = ShmView::new(&b).unwrap();
+        assert_eq!(view.walk(page(0), 0).fault, Some(ChainFault::OutOfRange(page(3))));
+
+        link(&mut b, page(1), page(2) + 12);
+        let view = ShmView::new(&b).unwrap();
+        assert_eq!(view.walk(page(0), 0).fault, Some(ChainFault::Misaligned(page(2) + 12)));
+
+        link(&mut b, page(1), DIRECT_LIMIT);
+        let view = ShmView::new(&b).unwrap();
+        assert_eq!(view.walk(page(0), 0).fault, Some(ChainFault::Paged(DIRECT_LIMIT)));
+    }
+
+    #[test]
+    fn truncated_record_is_flagged() {
+        let mut b = region(1);
+        write_records(&mut b, &[page(0)], &[(1, b"abcdef")]);
+        // Claim the record is longer than what the chain holds.
+        let p = page(0) as usize + PAGE_HEADER_SIZE;
+        b[p..p + 4].copy_from_slice(&100u32.to_le_bytes());
+        let view = ShmView::new(&b).unwrap();
+        let scan = view.scan_records(&[page(0)], false);
+        assert_eq!((scan.count, scan.fault), (0, Some(ChainFault::PartialRecord)));
+    }
+
+    #[test]
+    fn slots_are_read_through_the_stored_geometry() {
+        let small = ShmLayout::new(8, 4, 2, PAGE_SIZE, PAGE_SIZE, 2 * PAGE_SIZE);
+        let mut b = region_with(small, 2);
+        let first = small.bump_allocator_start as PageId;
+        write_records(&mut b, &[first], &[(1, b"io")]);
+        let io = small.io_heads_offset() as usize + 3 * PAGE_ID_SIZE;
+        b[io..io + 8].copy_from_slice(&first.to_le_bytes());
+        b[io + 4 * PAGE_ID_SIZE..io + 4 * PAGE_ID_SIZE + 8].copy_from_slice(&first.to_le_bytes());
+
+        let view = ShmView::new(&b).unwrap();
+        assert_eq!((view.slot_count(SlotKind::Stream), view.slot_count(SlotKind::Io)), (8, 4));
+        let report = inspect("t", &view, &InspectOptions::default());
+        let slots: Vec<_> = report.slots.iter().map(|s| (s.slot, s.records)).collect();
+        assert_eq!(slots, vec![(3, 1)]);
+        assert_eq!(report.superblock.heap_pages, 2);
+        // A page below the small layout's heap start is rejected.
+        let arena = first - PAGE_SIZE as u64;
+        assert_eq!(view.check_page(arena), Err(ChainFault::OutOfRange(arena)));
+    }
+
+    #[test]
+    fn closed_stream_slots_are_flagged() {
+        let word = std::sync::atomic::AtomicU32::new(0);
+        assert!(!stream_signal_append(&word));
+        word.fetch_or(STREAM_WAITERS, std::sync::atomic::Ordering::Relaxed);
+        assert!(stream_signal_close(&word), "a sleeping consumer must be woken");
+        assert!(!stream_signal_append(&word));
+        let signal = word.into_inner();
+        assert_eq!((signal & STREAM_CLOSED, signal & STREAM_WAITERS, signal & STREAM_SEQUENCE_MASK),
+                   (STREAM_CLOSED, 0, 3));
+
+        let mut b = region(1);
+        write_records(&mut b, &[page(0)], &[(5, b"done")]);
+        for slot in [4, 5] {
+            let head = ShmLayout::DEFAULT.writer_heads_offset() as usize + slot * PAGE_ID_SIZE;
+            b[head..head + 8].copy_from_slice(&page(0).to_le_bytes());
+        }
+        let off = ShmLayout::DEFAULT.stream_signals_offset() as usize + 5 * 4;
+        b[off..off + 4].copy_from_slice(&signal.to_le_bytes());
+
+        let view = ShmView::new(&b).unwrap();
+        assert!(!slot_info(&view, SlotKind::Stream, 4).closed);
+        assert!(slot_info(&view, SlotKind::Stream, 5).closed);
+    }
+}
@@ -0,0 +1,382 @@
+// Host side of the key-value store (see "Key-value store" in `common`, and
+// `guest/src/api/kv_store.rs` for the guest half).
+//
+// Guests do all reads and writes.  The host only needs `scan`, which follows
+// the guest read protocol so `Watch` / `Persist` can dump the store while
+// guests run, and `compact`, which drops shadowed entries and tombstones and
+// frees empty directory pages, and needs a quiescent region (between DAG
+// nodes).
+
+use crate::runtime::mem_operation::reclaimer;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::Ordering;
+
+use common::{
+    AtomicPageId, KvEntryHeader, Page, PageId, ShmOffset, Superblock, KV_FANOUT, KV_TOMBSTONE, PAGE_CLASS_SMALL,
+    PAGE_SIZE,
+};
+
+const HEADER_SIZE: usize = std::mem::size_of::<KvEntryHeader>();
+const LINK_SIZE: usize = std::mem::size_of::<PageId>();
+
+/// What `compact` did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct KvCompaction {
+    /// Live entries left in the store.
+    pub kept: usize,
+    /// Shadowed entries and tombstones removed.
+    pub freed_entries: usize,
+    /// Pages returned to the free list, directory pages included.
+    pub freed_pages: usize,
+}
+
+pub struct KvStore {
+    base: usize,
+}
+
+impl KvStore {
+    /// The store of the region mapped at `splice_addr`.
+    pub fn at(splice_addr: usize) -> Self {
+        Self { base: splice_addr }
+    }
+
+    fn superblock(&self) -> &Superblock {
+        unsafe { &*(self.base as *const Superblock) }
+    }
+
+    fn ptr(&self, offset: ShmOffset) -> *mut u8 {
+        (self.base + offset as usize) as *mut u8
+    }
+
+    /// Entry `idx` of the directory page at `page`.
+    fn directory_slot(&self, page: ShmOffset, idx: usize) -> &AtomicPageId {
+        unsafe { &*(self.ptr(page) as *const AtomicPageId).add(idx) }
+    }
+
+    fn header(&self, entry: ShmOffset) -> &KvEntryHeader {
+        unsafe { &*(self.ptr(entry) as *const KvEntryHeader) }
+    }
+
+    // ─── Entry bytes ─────────────────────────────────────────────────────────
+
+    /// Pages of `entry`, head first.
+    fn entry_pages(&self, entry: ShmOffset) -> Vec<ShmOffset> {
+        let mut pages = vec![entry];
+        let mut next = self.header(entry).next_payload_page as ShmOffset;
+        while next != 0 {
+            pages.push(next);
+            next = unsafe { *(self.ptr(next) as *const PageId) } as ShmOffset;
+        }
+        pages
+    }
+
+    /// The key then value bytes of `entry`.
+    fn entry_bytes(&self, entry: ShmOffset) -> Vec<u8> {
+        let h = self.header(entry);
+        let len = h.key_len as usize + h.value_len as usize;
+        let mut bytes = Vec::with_capacity(len);
+        for (i, page) in self.entry_pages(entry).into_iter().enumerate() {
+            let skip = if i == 0 { HEADER_SIZE } else { LINK_SIZE };
+            let n = (len - bytes.len()).min(PAGE_SIZE as usize - skip);
+            bytes.extend_from_slice(unsafe { std::slice::from_raw_parts(self.ptr(page).add(skip), n) });
+        }
+        bytes
+    }
+
+    fn entry_key(&self, entry: ShmOffset) -> Vec<u8> {
+        let mut bytes = self.entry_bytes(entry);
+        bytes.truncate(self.header(entry).key_len as usize);
+        bytes
+    }
+
+    /// The value of a live entry, `None` for a tombstone.
+    fn entry_value(&self, entry: ShmOffset) -> Option<Vec<u8>> {
+        let h = self.header(entry);
+        if h.flags & KV_TOMBSTONE != 0 {
+            return None;
+        }
+        Some(self.entry_bytes(entry).split_off(h.key_len as usize))
+    }
+
+    /// Frees every page of an entry no bucket links to.  Returns the page count.
+    fn free_entry(&self, entry: ShmOffset) -> usize {
+        let pages = self.entry_pages(entry);
+        for &page in &pages {
+            self.free_page(page);
+        }
+        pages.len()
+    }
+
+    /// Pushes a single page back onto the free list.  Byte 0 holds a link or
+    /// a directory entry and byte 12 key bytes, so both are reset first (as
+    /// in `BucketOrganizer::push_to_free_list`).
+    fn free_page(&self, offset: ShmOffset) {
+        let page = unsafe { &*(self.ptr(offset) as *const Page) };
+        page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
+        page.next_offset.store(0, Ordering::Relaxed);
+        reclaimer::free_page_chain(self.base, offset as PageId);
+    }
+
+    // ─── Scan ────────────────────────────────────────────────────────────────
+
+    /// Every live `(key, value)` whose key starts with `prefix`, sorted by key.
+    pub fn scan(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut out = BTreeMap::new();
+        self.for_each_bucket(|_, head| {
+            let mut seen = HashSet::new();
+            let mut current = head.load(Ordering::Acquire) as ShmOffset;
+            while current != 0 {
+                let key = self.entry_key(current);
+                if key.starts_with(prefix) && seen.insert(key.clone()) {
+                    if let Some(value) = self.entry_value(current) {
+                        out.insert(key, value);
+                    }
+                }
+                current = self.header(current).next_entry.load(Ordering::Acquire) as ShmOffset;
+            }
+        });
+        out.into_iter().collect()
+    }
+
+    /// Calls `f(leaf, head)` for every bucket of every existing leaf page.
+    fn for_each_bucket(&self, mut f: impl FnMut(ShmOffset, &AtomicPageId)) {
+        let root = self.superblock().kv_directory.load(Ordering::Acquire);
+        if root == 0 {
+            return;
+        }
+        for r in 0..KV_FANOUT {
+            let leaf = self.directory_slot(root, r).load(Ordering::Acquire) as ShmOffset;
+            if leaf != 0 {
+                for l in 0..KV_FANOUT {
+                    f(leaf, self.directory_slot(leaf, l));
+                }
+            }
+        }
+    }
+
+    // ─── Compaction ──────────────────────────────────────────────────────────
+
+    /// Keeps only the newest live entry of each key: shadowed entries and
+    /// tombstones are freed, and so are leaf pages left with no bucket and the
+    /// root page once every leaf is gone.
+    ///
+    /// # Safety
+    /// No guest may be using the store: entries are unlinked and freed without
+    /// any protection against concurrent readers or writers.
+    pub unsafe fn compact(&self) -> KvCompaction {
+        let mut stats = KvCompaction::default();
+        let sb = self.superblock();
+        let root = sb.kv_directory.load(Ordering::Acquire);
+        if root == 0 {
+            return stats;
+        }
+        let mut leaves_left = 0;
+        for r in 0..KV_FANOUT {
+            let link = self.directory_slot(root, r);
+            let leaf = link.load(Ordering::Acquire) as ShmOffset;
+            if leaf == 0 {
+                continue;
+            }
+            let mut buckets_left = 0;
+            for l in 0..KV_FANOUT {
+                let head = self.directory_slot(leaf, l);
+                self.compact_bucket(head, &mut stats);
+                if head.load(Ordering::Relaxed) != 0 {
+                    buckets_left += 1;
+                }
+            }
+            if buckets_left == 0 {
+                link.store(0, Ordering::Release);
+                self.free_page(leaf);
+                stats.freed_pages += 1;
+            } else {
+                leaves_left += 1;
+            }
+        }
+        if leaves_left == 0 {
+            sb.kv_directory.store(0, Ordering::Release);
+            self.free_page(root);
+            stats.freed_pages += 1;
+        }
+        stats
+    }
+
+    /// Relinks `head` to the newest live entry of each key, in chain order.
+    unsafe fn compact_bucket(&self, head: &AtomicPageId, stats: &mut KvCompaction) {
+        let mut seen = HashSet::new();
+        let mut kept = Vec::new();
+        let mut current = head.load(Ordering::Acquire) as ShmOffset;
+        while current != 0 {
+            let next = self.header(current).next_entry.load(Ordering::Acquire) as ShmOffset;
+            let live = self.header(current).flags & KV_TOMBSTONE == 0;
+            if seen.insert(self.entry_key(current)) && live {
+                kept.push(current);
+            } else {
+                stats.freed_pages += self.free_entry(current);
+                stats.freed_entries += 1;
+            }
+            current = next;
+        }
+        stats.kept += kept.len();
+        let mut link = head;
+        for &entry in &kept {
+            link.store(entry as PageId, Ordering::Relaxed);
+            link = &self.header(entry).next_entry;
+        }
+        link.store(0, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::num::NonZeroUsize;
+
+    use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+
+    use common::{kv_bucket, kv_hash, INITIAL_SHM_SIZE};
+
+    use crate::runtime::mem_operation::fsck::fsck;
+    use crate::runtime::mem_operation::inspect::ShmView;
+    use crate::shm::format_shared_memory;
+
+    fn assert_clean(base: usize) {
+        let bytes = unsafe { std::slice::from_raw_parts(base as *const u8, INITIAL_SHM_SIZE as usize) };
+        let report = fsck("region", &ShmView::new(bytes).unwrap(), false);
+        assert!(report.is_clean(), "{:?}", report.problems.iter().map(|p| &p.detail).collect::<Vec<_>>());
+        assert_eq!(report.leaked_pages, Some(0));
+    }
+
+    /// Runs `f` on a freshly formatted region.
+    fn with_region(f: impl FnOnce(usize)) {
+        let dir = tempfile::tempdir().unwrap();
+        let shm = dir.path().join("region");
+        format_shared_memory(shm.to_str().unwrap()).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&shm).unwrap();
+        let len = NonZeroUsize::new(INITIAL_SHM_SIZE as usize).unwrap();
+        let base = unsafe {
+            mmap(None, len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, Some(&file), 0)
+        }.unwrap() as usize;
+        f(base);
+        unsafe { munmap(base as *mut _, len.get()).unwrap() };
+    }
+
+    /// The guest half's write path, so tests can fill the store.  Tests are
+    /// the only writer, so entries are published without a retry loop.
+    impl KvStore {
+        fn zeroed_page(&self) -> ShmOffset {
+            let page = reclaimer::alloc_page(self.base).unwrap() as ShmOffset;
+            unsafe { std::ptr::write_bytes(self.ptr(page), 0, PAGE_SIZE as usize) };
+            page
+        }
+
+        /// Head of the bucket for `hash`, creating missing directory pages.
+        fn bucket(&self, hash: u32) -> &AtomicPageId {
+            let (root_idx, leaf_idx) = kv_bucket(hash);
+            let sb = self.superblock();
+            if sb.kv_directory.load(Ordering::Acquire) == 0 {
+                sb.kv_directory.store(self.zeroed_page(), Ordering::Release);
+            }
+            let link = self.directory_slot(sb.kv_directory.load(Ordering::Acquire), root_idx);
+            if link.load(Ordering::Acquire) == 0 {
+                link.store(self.zeroed_page() as PageId, Ordering::Release);
+            }
+            self.directory_slot(link.load(Ordering::Acquire) as ShmOffset, leaf_idx)
+        }
+
+        /// Publishes `key` → `value`, or a tombstone for `None`.
+        fn put(&self, key: &[u8], value: Option<&[u8]>) {
+            let hash = kv_hash(key);
+            let flags = if value.is_some() { 0 } else { KV_TOMBSTONE };
+            let value = value.unwrap_or_default();
+            let entry = reclaimer::alloc_page(self.base).unwrap() as ShmOffset;
+            unsafe {
+                (self.ptr(entry) as *mut KvEntryHeader).write(KvEntryHeader {
+                    next_entry: AtomicPageId::new(0),
+                    hash,
+                    key_len: key.len() as u32,
+                    value_len: value.len() as u32,
+                    flags,
+                    next_payload_page: 0,
+                });
+            }
+            let bytes: Vec<u8> = [key, value].concat();
+            let (first, rest) = bytes.split_at(bytes.len().min(PAGE_SIZE as usize - HEADER_SIZE));
+            unsafe {
+                std::ptr::copy_nonoverlapping(first.as_ptr(), self.ptr(entry).add(HEADER_SIZE), first.len());
+                let mut link = &mut (*(self.ptr(entry) as *mut KvEntryHeader)).next_payload_page as *mut PageId;
+                for chunk in rest.chunks(PAGE_SIZE as usize - LINK_SIZE) {
+                    let page = reclaimer::alloc_page(self.base).unwrap() as ShmOffset;
+                    *(self.ptr(page) as *mut PageId) = 0;
+                    std::ptr::copy_nonoverlapping(chunk.as_ptr(), self.ptr(page).add(LINK_SIZE), chunk.len());
+                    *link = page as PageId;
+                    link = self.ptr(page) as *mut PageId;
+                }
+            }
+            let bucket = self.bucket(hash);
+            self.header(entry).next_entry.store(bucket.load(Ordering::Acquire), Ordering::Relaxed);
+            bucket.store(entry as PageId, Ordering::Release);
+        }
+    }
+
+    #[test]
+    fn scan_sees_the_newest_live_value_of_each_key() {
+        with_region(|base| {
+            let kv = KvStore::at(base);
+            assert_eq!(kv.scan(b""), vec![]);
+
+            kv.put(b"user:1", Some(b"alice"));
+            kv.put(b"user:2", Some(b"bob"));
+            kv.put(b"team:1", Some(b"red"));
+            kv.put(b"user:1", Some(b"carol"));
+            kv.put(b"team:1", None);
+            assert_eq!(kv.scan(b"team:"), vec![]);
+            assert_eq!(kv.scan(b"user:"), vec![
+                (b"user:1".to_vec(), b"carol".to_vec()),
+                (b"user:2".to_vec(), b"bob".to_vec()),
+            ]);
+            kv.put(b"team:1", Some(b"blue"));
+            assert_eq!(kv.scan(b"team:"), vec![(b"team:1".to_vec(), b"blue".to_vec())]);
+            assert_clean(base);
+        });
+    }
+
+    #[test]
+    fn values_spanning_pages_round_trip() {
+        with_region(|base| {
+            let kv = KvStore::at(base);
+            let key = vec![b'k'; PAGE_SIZE as usize];
+            let value: Vec<u8> = (0..3 * PAGE_SIZE).map(|i| i as u8).collect();
+            kv.put(&key, Some(&value));
+            kv.put(b"small", Some(b"v"));
+            assert_eq!(kv.scan(b"k"), vec![(key, value)]);
+            assert_clean(base);
+        });
+    }
+
+    #[test]
+    fn compaction_frees_shadowed_entries_and_empty_directories() {
+        with_region(|base| {
+            let kv = KvStore::at(base);
+            for round in 0..3u8 {
+                kv.put(b"a", Some(&[round]));
+            }
+            kv.put(b"b", Some(b"gone"));
+            kv.put(b"b", None);
+
+            let stats = unsafe { kv.compact() };
+            // Plus b's leaf page when the two keys do not share one.
+            let leaves = usize::from(kv_bucket(kv_hash(b"a")).0 != kv_bucket(kv_hash(b"b")).0);
+            assert_eq!(stats, KvCompaction { kept: 1, freed_entries: 4, freed_pages: 4 + leaves });
+            assert_eq!(kv.scan(b""), vec![(b"a".to_vec(), vec![2])]);
+            assert_clean(base);
+
+            kv.put(b"a", None);
+            let stats = unsafe { kv.compact() };
+            assert_eq!((stats.kept, stats.freed_entries, stats.freed_pages), (0, 2, 4));
+            assert_eq!(KvStore::at(base).superblock().kv_directory.load(Ordering::Acquire), 0);
+            assert_clean(base);
+        });
+    }
+}
@@ -1,3 +1,8 @@
+pub mod fsck;
+pub mod inspect;
+pub mod kv_store;
 pub mod organizer;
 pub mod reclaimer;
+pub mod ring;
 pub mod slicer;
+pub mod subscriptions;
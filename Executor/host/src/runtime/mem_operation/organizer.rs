@@ -1,15 +1,33 @@
-use crate::policy::{ConsumptionPolicy, ConsumptionResult, ConflictNode};
+use crate::policy::{ConsumptionPolicy, ConsumptionResult, ConflictNode, COMMITTED_WRITER};
+use crate::runtime::mem_operation::reclaimer;
 use crate::runtime::worker::WorkerState;
+use std::collections::BTreeMap;
 use std::sync::atomic::{Ordering};
 use wasmtime::{Memory, Store};
 
 use common::{
     TARGET_OFFSET, PAGE_SIZE, BUCKET_COUNT, ShmOffset, PageId, AtomicPageId,
-    Superblock, RegistryEntry, ChainNodeHeader,
+    Superblock, RegistryEntry, ChainNodeHeader, Page, PAGE_CLASS_SMALL, SHARED_STATE_TAG,
 };
 #[allow(unused_imports)]
 use common::DIRECT_LIMIT;
 
+/// What `resolve_shared_state` decided for one registry key.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Resolution {
+    /// Registry name the writers used.
+    pub key: String,
+    /// Number of pending writes that were competing for the key.
+    pub candidates: usize,
+    /// Writer whose payload was committed, or `None` when the policy chose none
+    /// or merged the writes.
+    pub winner: Option<u32>,
+    /// A merge policy committed a new payload combined from the writes.
+    pub merged: bool,
+    /// Length of the committed payload (0 when nothing was committed).
+    pub bytes: u32,
+}
+
 pub struct BucketOrganizer<'a> {
     base_ptr: *mut u8,
     _marker: std::marker::PhantomData<&'a ()>,
@@ -29,138 +47,292 @@ impl<'a> BucketOrganizer<'a> {
         }
     }
 
-    /// Scans every hash bucket, atomically detaches its conflict list, applies `policy` to
-    /// select a winner, commits the winner to the Registry, and recycles all losing pages.
-    /// Must be called after all writers have finished to ensure the bucket lists are stable.
+    /// Creates a `BucketOrganizer` over a region mapped at `splice_addr`
+    /// (the DAG runner's own mapping).
+    pub fn at(splice_addr: usize) -> Self {
+        Self {
+            base_ptr: splice_addr as *mut u8,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Resolves every pending `write_shared_state` key with `policy`.
+    ///
+    /// # Safety
+    /// As for [`Self::resolve_shared_state`].
     pub unsafe fn consume_all_buckets<P: ConsumptionPolicy>(&self, policy: P) {
-        let map_base_offset = self.superblock().shared_map_base.load(Ordering::Relaxed);
+        let policy: &dyn ConsumptionPolicy = &policy;
+        self.resolve_shared_state(|_| policy);
+    }
+
+    /// Scans every hash bucket for pending `write_shared_state` writes, groups them by
+    /// registry key, applies `policy_for(key)` to each group, commits the winner — or the
+    /// merged value of a merge policy — to the Registry (freeing the payload it replaces),
+    /// and recycles all other writes.
+    ///
+    /// Raw `insert_shared_data` entries sharing a bucket are left in place, in order.
+    /// Must be called after all writers of the resolved keys have finished; a reader
+    /// walking a bucket while it is being resolved may briefly see it empty.
+    ///
+    /// # Safety
+    /// The organizer must point at a mapped, formatted region, and no other
+    /// organizer may run on it at the same time.
+    pub unsafe fn resolve_shared_state<'p>(
+        &self,
+        policy_for: impl Fn(&str) -> &'p dyn ConsumptionPolicy,
+    ) -> Vec<Resolution> {
+        let mut resolutions = Vec::new();
+        let map_base_offset = self.superblock().shared_map_base.load(Ordering::Acquire);
         if map_base_offset == 0 {
-            return;
+            return resolutions;
         }
 
         for i in 0..BUCKET_COUNT {
-            let bucket_ptr =
-                self.base_ptr.add(map_base_offset as usize).add(i * std::mem::size_of::<AtomicPageId>()) as *const AtomicPageId;
-            let list_head: ShmOffset = (*bucket_ptr).swap(0, Ordering::SeqCst) as ShmOffset;
+            let bucket = &*(self.base_ptr.add(map_base_offset as usize)
+                .add(i * std::mem::size_of::<AtomicPageId>()) as *const AtomicPageId);
+            if !self.has_pending_state(bucket.load(Ordering::Acquire) as ShmOffset) {
+                continue;
+            }
 
-            if list_head != 0 {
-                self.process_detached_list(list_head, i, &policy);
+            // Detach the list, keep the raw entries, group the rest by key.
+            // The list is newest-first, and so is every group.
+            let mut pending: BTreeMap<u32, Vec<ConflictNode>> = BTreeMap::new();
+            let mut kept = Vec::new();
+            let mut current = bucket.swap(0, Ordering::AcqRel) as ShmOffset;
+            while current != 0 {
+                let hdr = self.header(current);
+                let next = hdr.next_node.load(Ordering::Relaxed) as ShmOffset;
+                if hdr.tag == SHARED_STATE_TAG {
+                    let node = self.read_node(current);
+                    pending.entry(node.registry_index).or_default().push(node);
+                } else {
+                    kept.push(current);
+                }
+                current = next;
+            }
+            self.reattach(bucket, &kept);
 
-                // After processing, reclaim memory
-                self.recycle_chain(list_head);
+            for (registry_index, nodes) in pending {
+                resolutions.push(self.resolve_key(registry_index, &nodes, &policy_for));
             }
         }
+        resolutions
     }
 
-    /// Applies `policy` to the already-detached linked list rooted at `head_offset`.
-    /// Deserializes each node's multi-page payload, invokes the policy, stores the winning
-    /// node's offset and length into the Registry, and frees all losing nodes' page chains.
-    unsafe fn process_detached_list<P: ConsumptionPolicy>(&self, head_offset: ShmOffset, _bucket_idx: usize, policy: &P) {
-        let mut nodes = Vec::new();
-        let mut current_offset: ShmOffset = head_offset;
-
-        while current_offset != 0 {
-            let node_ptr = self.base_ptr.add(current_offset as usize);
-
-            let hdr = &*(node_ptr as *const ChainNodeHeader);
-            let writer_id = hdr.writer_id;
-            let data_len = hdr.data_len;
-            let registry_index = hdr.registry_index;
-            let next_offset: ShmOffset = hdr.next_node.load(Ordering::Relaxed) as ShmOffset;
-
-
-            let mut payload = Vec::with_capacity(data_len as usize);
-            let mut lob_offset: ShmOffset = current_offset;
-            let mut bytes_read = 0;
-            let mut is_head = true;
-
-            while bytes_read < data_len as usize {
-                let page_ptr = self.base_ptr.add(lob_offset as usize);
-                // Both the head `ChainNodeHeader::next_payload_page` and the
-                // bare overflow-page header at byte 0 are now `PageId` (u64).
-                // Truncate to ShmOffset locally since direct-mode values fit.
-                let (header_size, next_page): (usize, ShmOffset) = if is_head {
-                    let hdr = &*(page_ptr as *const ChainNodeHeader);
-                    (std::mem::size_of::<ChainNodeHeader>(), hdr.next_payload_page as ShmOffset)
-                } else {
-                    (std::mem::size_of::<PageId>(), *(page_ptr as *const PageId) as ShmOffset)
-                };
+    /// Applies the key's policy to `nodes` (all writes to `registry_index`), stores the
+    /// winning node — or a merged payload — into the Registry, and frees every other chain
+    /// along with the payload the new value replaces.
+    unsafe fn resolve_key<'p>(
+        &self,
+        registry_index: u32,
+        nodes: &[ConflictNode],
+        policy_for: &impl Fn(&str) -> &'p dyn ConsumptionPolicy,
+    ) -> Resolution {
+        let candidates = nodes.len();
+        let Some(entry) = self.registry_entry(registry_index) else {
+            println!(
+                "[BucketOrganizer] registry index {} out of range — dropping {} writes",
+                registry_index, candidates,
+            );
+            for node in nodes {
+                self.free_payload_chain(node.offset);
+            }
+            return Resolution { key: format!("#{}", registry_index), candidates, winner: None, merged: false, bytes: 0 };
+        };
 
-                let read_len = std::cmp::min(data_len as usize - bytes_read, PAGE_SIZE as usize - header_size);
-                let chunk = std::slice::from_raw_parts(page_ptr.add(header_size), read_len);
-                payload.extend_from_slice(chunk);
+        let name_len = entry.name.iter().position(|&b| b == 0).unwrap_or(entry.name.len());
+        let key = String::from_utf8_lossy(&entry.name[..name_len]).into_owned();
+        let policy = policy_for(&key);
+        let previous = entry.payload_offset.load(Ordering::Acquire);
+        let result = if policy.merges() {
+            let committed = (previous != 0).then(|| self.read_node(previous).payload);
+            policy.process_onto(committed.as_deref(), nodes)
+        } else {
+            policy.process(nodes)
+        };
 
-                bytes_read += read_len;
-                lob_offset = next_page;
-                is_head = false;
+        let mut winner = None;
+        let commit = match result {
+            ConsumptionResult::Winner(winner_id, _content) => {
+                winner = nodes.iter().find(|n| n.writer_id == winner_id);
+                winner.map(|w| (w.offset, w.data_len))
             }
+            ConsumptionResult::Merged(bytes) => match self.write_payload(registry_index, &bytes) {
+                Ok(offset) => Some((offset, bytes.len() as u32)),
+                Err(e) => {
+                    println!("[BucketOrganizer] cannot store merged \"{}\" ({} bytes): {}", key, bytes.len(), e);
+                    None
+                }
+            },
+            ConsumptionResult::None => None,
+        };
 
-            nodes.push(ConflictNode { offset: current_offset, writer_id, data_len, registry_index, payload });
-            current_offset = next_offset;
+        if let Some((offset, len)) = commit {
+            entry.payload_offset.store(offset, Ordering::Release);
+            entry.payload_len.store(len, Ordering::Release);
+            if previous != 0 {
+                self.free_payload_chain(previous);
+            }
+        }
+        for node in nodes {
+            if winner.is_none_or(|w: &ConflictNode| w.offset != node.offset) {
+                self.free_payload_chain(node.offset);
+            }
         }
 
-        let result = policy.process(&nodes);
+        Resolution {
+            key,
+            candidates,
+            winner: winner.map(|w| w.writer_id),
+            merged: commit.is_some() && winner.is_none(),
+            bytes: commit.map_or(0, |(_, len)| len),
+        }
+    }
 
+    /// Copies `bytes` into a fresh payload chain laid out like a `write_shared_state`
+    /// node (writer `COMMITTED_WRITER`), ready to be stored in the Registry.
+    unsafe fn write_payload(&self, registry_index: u32, bytes: &[u8]) -> anyhow::Result<ShmOffset> {
+        let splice_addr = self.base_ptr as usize;
+        let head = reclaimer::alloc_page(splice_addr)? as ShmOffset;
+        let head_ptr = self.base_ptr.add(head as usize);
+        (head_ptr as *mut ChainNodeHeader).write(ChainNodeHeader {
+            next_node: AtomicPageId::new(0),
+            writer_id: COMMITTED_WRITER,
+            data_len: bytes.len() as u32,
+            registry_index,
+            tag: SHARED_STATE_TAG,
+            next_payload_page: 0,
+        });
+        let head_capacity = PAGE_SIZE as usize - std::mem::size_of::<ChainNodeHeader>();
+        let (first, rest) = bytes.split_at(bytes.len().min(head_capacity));
+        std::ptr::copy_nonoverlapping(first.as_ptr(), head_ptr.add(std::mem::size_of::<ChainNodeHeader>()), first.len());
 
-        let free_lob_chain = |start_offset: ShmOffset| {
-            let mut free_offset: ShmOffset = start_offset;
-            let mut is_head = true;
-            while free_offset != 0 {
-                let page_ptr = self.base_ptr.add(free_offset as usize);
-                let next_free: ShmOffset = if is_head {
-                    (*(page_ptr as *const ChainNodeHeader)).next_payload_page as ShmOffset
-                } else {
-                    *(page_ptr as *const PageId) as ShmOffset
-                };
-                self.push_to_free_list(free_offset);
-                free_offset = next_free;
-                is_head = false;
-            }
-        };
+        let mut prev_next = &mut (*(head_ptr as *mut ChainNodeHeader)).next_payload_page as *mut PageId;
+        for chunk in rest.chunks(PAGE_SIZE as usize - std::mem::size_of::<PageId>()) {
+            let page = match reclaimer::alloc_page(splice_addr) {
+                Ok(page) => page,
+                Err(e) => {
+                    self.free_payload_chain(head);
+                    return Err(e);
+                }
+            };
+            let page_ptr = self.base_ptr.add(page as usize);
+            *(page_ptr as *mut PageId) = 0;
+            std::ptr::copy_nonoverlapping(chunk.as_ptr(), page_ptr.add(std::mem::size_of::<PageId>()), chunk.len());
+            *prev_next = page;
+            prev_next = page_ptr as *mut PageId;
+        }
+        Ok(head)
+    }
 
+    unsafe fn header(&self, offset: ShmOffset) -> &ChainNodeHeader {
+        &*(self.base_ptr.add(offset as usize) as *const ChainNodeHeader)
+    }
 
-        if let ConsumptionResult::Winner(winner_id, _content) = result {
-            let winner_node = nodes.iter().find(|n| n.writer_id == winner_id).unwrap();
+    /// The Registry entry at `index`, or `None` past the registered entries.
+    unsafe fn registry_entry(&self, index: u32) -> Option<&RegistryEntry> {
+        let sb = self.superblock();
+        let entry_size = std::mem::size_of::<RegistryEntry>();
+        let count = (sb.next_atomic_idx.load(Ordering::Acquire) as usize)
+            .min(sb.layout.registry_size() as usize / entry_size);
+        if index as usize >= count {
+            return None;
+        }
+        let registry_base = self.base_ptr.add(sb.layout.registry_offset as usize);
+        Some(&*(registry_base.add(index as usize * entry_size) as *const RegistryEntry))
+    }
 
-            let registry_base = self.base_ptr.add(common::REGISTRY_OFFSET as usize);
-            let entry_ptr = registry_base.add(winner_node.registry_index as usize * std::mem::size_of::<RegistryEntry>()) as *const RegistryEntry;
-            let entry = &*entry_ptr;
+    /// Whether the list at `head` holds any `write_shared_state` node.  Read-only.
+    unsafe fn has_pending_state(&self, mut current: ShmOffset) -> bool {
+        while current != 0 {
+            let hdr = self.header(current);
+            if hdr.tag == SHARED_STATE_TAG {
+                return true;
+            }
+            current = hdr.next_node.load(Ordering::Acquire) as ShmOffset;
+        }
+        false
+    }
 
-            entry.payload_offset.store(winner_node.offset, Ordering::Release);
-            entry.payload_len.store(winner_node.data_len, Ordering::Release);
+    /// Links `kept` (oldest last) back into `bucket`, behind anything writers
+    /// prepended since the list was detached, so the bucket stays newest-first.
+    unsafe fn reattach(&self, bucket: &AtomicPageId, kept: &[ShmOffset]) {
+        let (Some(&first), Some(&last)) = (kept.first(), kept.last()) else {
+            return;
+        };
+        for pair in kept.windows(2) {
+            self.header(pair[0]).next_node.store(pair[1] as PageId, Ordering::Relaxed);
+        }
+        self.header(last).next_node.store(0, Ordering::Relaxed);
 
-            for node in &nodes {
-                if node.offset != winner_node.offset {
-                    free_lob_chain(node.offset);
+        if let Err(head) = bucket.compare_exchange(0, first as PageId, Ordering::Release, Ordering::Acquire) {
+            // Writers only ever swap the head, so the tail's link is ours to set.
+            let mut tail = head as ShmOffset;
+            loop {
+                let next = self.header(tail).next_node.load(Ordering::Acquire) as ShmOffset;
+                if next == 0 {
+                    break;
                 }
+                tail = next;
             }
-        } else {
-            for node in &nodes {
-                free_lob_chain(node.offset);
-            }
+            self.header(tail).next_node.store(first as PageId, Ordering::Release);
         }
     }
 
-    /// Returns every node in the chain starting at `list_head` to the free list.
-    /// Walks the top-level `next_node` links of the conflict list (not overflow payload pages).
-    unsafe fn recycle_chain(&self, list_head: ShmOffset) {
-        if list_head == 0 {
-            return;
-        }
+    /// Deserializes the multi-page payload of the node at `offset`.
+    unsafe fn read_node(&self, offset: ShmOffset) -> ConflictNode {
+        let hdr = self.header(offset);
+        let data_len = hdr.data_len;
 
-        let mut current: ShmOffset = list_head;
-        while current != 0 {
-            let node_ptr = self.base_ptr.add(current as usize);
-            // First field of a ChainNodeHeader is `next_node: AtomicPageId`.
-            let next_ptr = &*(node_ptr as *const AtomicPageId);
+        let mut payload = Vec::with_capacity(data_len as usize);
+        let mut lob_offset: ShmOffset = offset;
+        let mut bytes_read = 0;
+        let mut is_head = true;
+
+        while bytes_read < data_len as usize {
+            let page_ptr = self.base_ptr.add(lob_offset as usize);
+            // Both the head `ChainNodeHeader::next_payload_page` and the
+            // bare overflow-page header at byte 0 are now `PageId` (u64).
+            // Truncate to ShmOffset locally since direct-mode values fit.
+            let (header_size, next_page): (usize, ShmOffset) = if is_head {
+                let hdr = &*(page_ptr as *const ChainNodeHeader);
+                (std::mem::size_of::<ChainNodeHeader>(), hdr.next_payload_page as ShmOffset)
+            } else {
+                (std::mem::size_of::<PageId>(), *(page_ptr as *const PageId) as ShmOffset)
+            };
 
-            // Save next node offset before we modify current node's next
-            let next_node: ShmOffset = next_ptr.load(Ordering::Relaxed) as ShmOffset;
+            let read_len = std::cmp::min(data_len as usize - bytes_read, PAGE_SIZE as usize - header_size);
+            let chunk = std::slice::from_raw_parts(page_ptr.add(header_size), read_len);
+            payload.extend_from_slice(chunk);
 
-            // Push onto free list
-            self.push_to_free_list(current);
+            bytes_read += read_len;
+            lob_offset = next_page;
+            is_head = false;
+        }
+
+        ConflictNode {
+            offset,
+            writer_id: hdr.writer_id,
+            data_len,
+            registry_index: hdr.registry_index,
+            payload,
+        }
+    }
 
-            current = next_node;
+    /// Frees the node at `start_offset` and its overflow payload pages.
+    unsafe fn free_payload_chain(&self, start_offset: ShmOffset) {
+        let mut free_offset: ShmOffset = start_offset;
+        let mut is_head = true;
+        while free_offset != 0 {
+            let page_ptr = self.base_ptr.add(free_offset as usize);
+            let next_free: ShmOffset = if is_head {
+                (*(page_ptr as *const ChainNodeHeader)).next_payload_page as ShmOffset
+            } else {
+                *(page_ptr as *const PageId) as ShmOffset
+            };
+            self.push_to_free_list(free_offset);
+            free_offset = next_free;
+            is_head = false;
         }
     }
 
@@ -169,11 +341,209 @@ impl<'a> BucketOrganizer<'a> {
     /// spin_loop backoff, and ABA documentation are all in one place.
     unsafe fn push_to_free_list(&self, page_offset: ShmOffset) {
         let splice_addr = self.base_ptr as usize;
+        // Shared-state pages keep payload bytes where a `Page` keeps its
+        // class word; these are always 4 KiB pages, so say so before freeing.
+        let page = &*(self.base_ptr.add(page_offset as usize) as *const Page);
+        page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
+        // Byte 0 is `next_node` on a node page and the next payload page on
+        // an overflow page; both are read before this call, and either would
+        // make `free_page_chain` free pages it does not own.
+        page.next_offset.store(0, Ordering::Relaxed);
         // free_page_chain now takes PageId — extend the u32 offset to u64.
         // Direct-mode page offsets fit in the low half of the PageId space.
-        crate::runtime::mem_operation::reclaimer::free_page_chain(
+        reclaimer::free_page_chain(
             splice_addr,
             page_offset as PageId,
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::num::NonZeroUsize;
+
+    use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+
+    use common::INITIAL_SHM_SIZE;
+
+    use crate::policy::{ConcatMerge, IntType, LastWriteWinsPolicy, MaxIdWinsPolicy, NumericMerge, NumericOp};
+    use crate::runtime::mem_operation::fsck::fsck;
+    use crate::runtime::mem_operation::inspect::ShmView;
+    use crate::runtime::mem_operation::reclaimer::alloc_page;
+    use crate::shm::format_shared_memory;
+
+    fn superblock<'a>(base: usize) -> &'a Superblock {
+        unsafe { &*(base as *const Superblock) }
+    }
+
+    fn register(base: usize, name: &str) -> u32 {
+        let sb = superblock(base);
+        let index = sb.next_atomic_idx.fetch_add(1, Ordering::AcqRel);
+        let entry = unsafe {
+            &mut *((base + sb.layout.registry_offset as usize
+                + index as usize * std::mem::size_of::<RegistryEntry>()) as *mut RegistryEntry)
+        };
+        entry.name[..name.len()].copy_from_slice(name.as_bytes());
+        entry.index = index;
+        index
+    }
+
+    fn bucket<'a>(base: usize, index: usize) -> &'a AtomicPageId {
+        let map_base = superblock(base).shared_map_base.load(Ordering::Acquire) as usize;
+        unsafe { &*((base + map_base + index * std::mem::size_of::<AtomicPageId>()) as *const AtomicPageId) }
+    }
+
+    /// Same page layout and CAS prepend as the guest's `write_shared_state`.
+    fn write_state(base: usize, reg_idx: u32, writer_id: u32, data: &[u8]) -> ShmOffset {
+        let head = alloc_page(base).unwrap() as ShmOffset;
+        let node_ptr = (base + head as usize) as *mut u8;
+        unsafe {
+            let header = node_ptr as *mut ChainNodeHeader;
+            (*header).writer_id = writer_id;
+            (*header).data_len = data.len() as u32;
+            (*header).registry_index = reg_idx;
+            (*header).tag = SHARED_STATE_TAG;
+            (*header).next_payload_page = 0;
+            let head_capacity = PAGE_SIZE as usize - std::mem::size_of::<ChainNodeHeader>();
+            let mut written = data.len().min(head_capacity);
+            std::ptr::copy_nonoverlapping(data.as_ptr(), node_ptr.add(std::mem::size_of::<ChainNodeHeader>()), written);
+            let mut prev = &mut (*header).next_payload_page as *mut PageId;
+            while written < data.len() {
+                let page = alloc_page(base).unwrap();
+                *prev = page;
+                let page_ptr = (base + page as usize) as *mut u8;
+                *(page_ptr as *mut PageId) = 0;
+                let n = (data.len() - written).min(PAGE_SIZE as usize - std::mem::size_of::<PageId>());
+                std::ptr::copy_nonoverlapping(data.as_ptr().add(written), page_ptr.add(8), n);
+                written += n;
+                prev = page_ptr as *mut PageId;
+            }
+            let bucket = bucket(base, reg_idx as usize % BUCKET_COUNT);
+            (*header).next_node.store(bucket.load(Ordering::Acquire), Ordering::Relaxed);
+            bucket.store(head as PageId, Ordering::Release);
+        }
+        head
+    }
+
+    /// Same as the guest's `insert_shared_data`: a single untagged page.
+    fn insert_raw(base: usize, key_hash: u32, writer_id: u32) -> ShmOffset {
+        let node = alloc_page(base).unwrap() as ShmOffset;
+        let header = (base + node as usize) as *mut ChainNodeHeader;
+        unsafe {
+            (*header).writer_id = writer_id;
+            (*header).data_len = 0;
+            (*header).tag = 0;
+            (*header).next_payload_page = 0;
+            let bucket = bucket(base, key_hash as usize % BUCKET_COUNT);
+            (*header).next_node.store(bucket.load(Ordering::Acquire), Ordering::Relaxed);
+            bucket.store(node as PageId, Ordering::Release);
+        }
+        node
+    }
+
+    fn committed(base: usize, reg_idx: u32) -> Vec<u8> {
+        let organizer = BucketOrganizer::at(base);
+        unsafe {
+            let entry = organizer.registry_entry(reg_idx).unwrap();
+            organizer.read_node(entry.payload_offset.load(Ordering::Acquire)).payload
+        }
+    }
+
+    fn assert_clean(base: usize) {
+        let bytes = unsafe { std::slice::from_raw_parts(base as *const u8, INITIAL_SHM_SIZE as usize) };
+        let report = fsck("region", &ShmView::new(bytes).unwrap(), false);
+        assert!(report.is_clean(), "{:?}", report.problems.iter().map(|p| &p.detail).collect::<Vec<_>>());
+        assert_eq!(report.leaked_pages, Some(0));
+    }
+
+    /// Runs `f` on a freshly formatted region with a bucket array.
+    fn with_region(f: impl FnOnce(usize)) {
+        let dir = tempfile::tempdir().unwrap();
+        let shm = dir.path().join("region");
+        format_shared_memory(shm.to_str().unwrap()).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&shm).unwrap();
+        let len = NonZeroUsize::new(INITIAL_SHM_SIZE as usize).unwrap();
+        let base = unsafe {
+            mmap(None, len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, Some(&file), 0)
+        }.unwrap() as usize;
+        let map_base = alloc_page(base).unwrap() as ShmOffset;
+        superblock(base).shared_map_base.store(map_base, Ordering::Release);
+        f(base);
+        unsafe { munmap(base as *mut _, len.get()).unwrap() };
+    }
+
+    #[test]
+    fn resolves_per_key_keeps_raw_entries_and_frees_losers() {
+        with_region(|base| {
+            let alpha = register(base, "alpha");
+            let beta = register(base, "beta");
+            let big: Vec<u8> = (0..3 * PAGE_SIZE as usize).map(|i| (i % 251) as u8).collect();
+            let raw_old = insert_raw(base, alpha, 70);
+            write_state(base, alpha, 2, b"two");
+            write_state(base, alpha, 9, &big);
+            let raw_new = insert_raw(base, alpha, 71);
+            write_state(base, alpha, 5, b"five");
+            write_state(base, beta, 1, b"first");
+            write_state(base, beta, 0, b"latest");
+
+            let organizer = BucketOrganizer::at(base);
+            let resolutions = unsafe {
+                organizer.resolve_shared_state(|key| -> &dyn ConsumptionPolicy {
+                    if key == "alpha" { &MaxIdWinsPolicy } else { &LastWriteWinsPolicy }
+                })
+            };
+            let summary: Vec<_> = resolutions.iter()
+                .map(|r| (r.key.as_str(), r.candidates, r.winner, r.bytes))
+                .collect();
+            assert_eq!(summary, vec![("alpha", 3, Some(9), big.len() as u32), ("beta", 2, Some(0), 6)]);
+            assert_eq!(committed(base, alpha), big);
+            assert_eq!(committed(base, beta), b"latest");
+
+            // The raw entries stay in their bucket, newest first.
+            let head = bucket(base, alpha as usize).load(Ordering::Acquire) as ShmOffset;
+            assert_eq!(head, raw_new);
+            assert_eq!(unsafe { organizer.header(head) }.next_node.load(Ordering::Acquire), raw_old as PageId);
+            assert_eq!(unsafe { organizer.header(raw_old) }.next_node.load(Ordering::Acquire), 0);
+            assert_clean(base);
+
+            // A later wave replaces the committed payload and frees the old one.
+            write_state(base, alpha, 3, b"three");
+            let resolutions = unsafe { organizer.resolve_shared_state(|_| &MaxIdWinsPolicy) };
+            assert_eq!(resolutions.len(), 1);
+            assert_eq!(committed(base, alpha), b"three");
+            assert_clean(base);
+        });
+    }
+
+    #[test]
+    fn merge_policy_accumulates_across_waves() {
+        with_region(|base| {
+            let hits = register(base, "hits");
+            let organizer = BucketOrganizer::at(base);
+            let sum = NumericMerge { op: NumericOp::Sum, int: IntType::U64 };
+            let counts = |values: &[u64]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+
+            for (writer, partial) in [(1, [1, 0]), (2, [2, 5]), (3, [3, 0])] {
+                write_state(base, hits, writer, &counts(&partial));
+            }
+            let r = unsafe { organizer.resolve_shared_state(|_| &sum) };
+            assert_eq!((r[0].winner, r[0].merged, r[0].bytes), (None, true, 16));
+            assert_eq!(committed(base, hits), counts(&[6, 5]));
+
+            // The committed histogram is folded into the next wave's writes.
+            write_state(base, hits, 1, &counts(&[4, 1, 1]));
+            unsafe { organizer.resolve_shared_state(|_| &sum) };
+            assert_eq!(committed(base, hits), counts(&[10, 6, 1]));
+
+            // A merged value larger than one page gets overflow pages.
+            let concat = ConcatMerge { separator: Vec::new() };
+            let big = vec![7u8; 2 * PAGE_SIZE as usize];
+            write_state(base, hits, 9, &big);
+            unsafe { organizer.resolve_shared_state(|_| &concat) };
+            assert_eq!(committed(base, hits).len(), 24 + big.len());
+            assert_clean(base);
+        });
+    }
+}
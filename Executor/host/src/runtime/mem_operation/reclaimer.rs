@@ -49,10 +49,14 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use nix::sys::mman::{madvise, MmapAdvise};
 
-use common::{Page, PageId, ShmOffset, Superblock, DIRECT_LIMIT, FREE_LIST_SHARD_COUNT,
-             FREE_LIST_TRIM_ENABLED, FREE_LIST_TRIM_THRESHOLD, PAGE_SIZE};
-
-use crate::runtime::extended_pool;
+use common::{page_class_size, Page, PageId, ShmOffset, Superblock, DIRECT_LIMIT,
+             FREE_LIST_SHARD_COUNT, FREE_LIST_TRIM_ENABLED, HUGE_PAGES_OFF, HUGE_PAGE_SIZE,
+             MAPPED_PAGE_CLASS, PAGE_CLASS_COUNT, PAGE_CLASS_SMALL, PAGE_SIZE};
+
+use crate::runtime::input_output::mapped_input;
+use crate::runtime::mem_operation::ring::RingSlot;
+use crate::runtime::mem_operation::subscriptions::Subscriptions;
+use crate::runtime::{engine_config, extended_pool};
 use crate::shm;
 
 // ─── Global round-robin shard counter ────────────────────────────────────────
@@ -98,6 +102,7 @@ pub fn alloc_page(splice_addr: usize) -> Result<PageId> {
                 Ok(_) => {
                     page.next_offset.store(0, Ordering::Relaxed);
                     page.cursor.store(0, Ordering::Relaxed);
+                    page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
                     return Ok(head as PageId);
                 }
                 Err(_) => spin_loop(), // another thread won the CAS — retry
@@ -120,6 +125,7 @@ pub fn alloc_page(splice_addr: usize) -> Result<PageId> {
                 let page = unsafe { &mut *((splice_addr + offset as usize) as *mut Page) };
                 page.next_offset.store(0, Ordering::Relaxed);
                 page.cursor.store(0, Ordering::Relaxed);
+                page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
                 extended_pool::runtime::notify_bump_advance(offset + PAGE_SIZE, splice_addr);
                 return Ok(offset as PageId);
             }
@@ -130,7 +136,8 @@ pub fn alloc_page(splice_addr: usize) -> Result<PageId> {
             // Undo the speculative fetch_add so the bump pointer
             // doesn't march past `cap` for subsequent callers.
             sb.bump_allocator.fetch_sub(PAGE_SIZE, Ordering::AcqRel);
-            let (id, _ptr) = extended_pool::runtime::alloc_paged_page(false)?;
+            let (id, ptr) = extended_pool::runtime::alloc_paged_page(false)?;
+            unsafe { (*ptr).class.store(PAGE_CLASS_SMALL, Ordering::Relaxed) };
             return Ok(id);
         }
         return Err(anyhow!(
@@ -143,6 +150,7 @@ pub fn alloc_page(splice_addr: usize) -> Result<PageId> {
     let page = unsafe { &mut *((splice_addr + offset as usize) as *mut Page) };
     page.next_offset.store(0, Ordering::Relaxed);
     page.cursor.store(0, Ordering::Relaxed);
+    page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
 
     // Extended-pool flip trigger: observe the new bump high-water mark
     // and transition to paged mode once it crosses the 80% threshold.
@@ -152,6 +160,68 @@ pub fn alloc_page(splice_addr: usize) -> Result<PageId> {
     Ok(offset as PageId)
 }
 
+/// Claim one page of `class` from the SHM pool (see `common::PAGE_CLASS_SIZES`).
+///
+/// [`PAGE_CLASS_SMALL`] is [`alloc_page`].  A larger class pops its own free
+/// list (`Superblock::class_free_list`) and otherwise bump-allocates a whole
+/// page of that size, so large pages are always direct-mode offsets.  There
+/// is no paged-mode fallback: the extended pool only holds 4 KiB pages.
+pub fn alloc_page_in(splice_addr: usize, class: u32) -> Result<PageId> {
+    if class == PAGE_CLASS_SMALL {
+        return alloc_page(splice_addr);
+    }
+    let sb = unsafe { &*(splice_addr as *const Superblock) };
+    let list = sb.class_free_list(class);
+    loop {
+        let head = list.load(Ordering::Acquire) as ShmOffset;
+        if head == 0 {
+            break;
+        }
+        let page = unsafe { &*((splice_addr + head as usize) as *const Page) };
+        let next = page.next_offset.load(Ordering::Relaxed);
+        match list.compare_exchange(head as u64, next, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => {
+                page.next_offset.store(0, Ordering::Relaxed);
+                page.cursor.store(0, Ordering::Relaxed);
+                return Ok(head as PageId);
+            }
+            Err(_) => spin_loop(),
+        }
+    }
+
+    let offset = alloc_span(splice_addr, page_class_size(class))?;
+    let page = unsafe { &*((splice_addr + offset as usize) as *const Page) };
+    page.next_offset.store(0, Ordering::Relaxed);
+    page.cursor.store(0, Ordering::Relaxed);
+    page.class.store(class, Ordering::Relaxed);
+    Ok(offset as PageId)
+}
+
+/// Bump-allocate `size` contiguous bytes (a multiple of `PAGE_SIZE`),
+/// growing the region if needed.  Nothing is written to the span.  Used for
+/// large pages and for the extents of zero-copy inputs.
+pub fn alloc_span(splice_addr: usize, size: ShmOffset) -> Result<ShmOffset> {
+    let sb = unsafe { &*(splice_addr as *const Superblock) };
+    let offset = sb.bump_allocator.fetch_add(size, Ordering::AcqRel);
+    let end = offset.checked_add(size).ok_or_else(|| anyhow!(
+        "SHM capacity exhausted allocating {} KiB (bump at {:#x})", size / 1024, offset,
+    ))?;
+    let cap = sb.global_capacity.load(Ordering::Acquire);
+    if end > cap {
+        let grown = shm::try_grow_shm(splice_addr, end).is_ok_and(|c| end <= c);
+        if !grown {
+            return Err(anyhow!(
+                "SHM capacity exhausted allocating {} KiB ({} of {} bytes used)",
+                size / 1024,
+                end,
+                cap,
+            ));
+        }
+    }
+    extended_pool::runtime::notify_bump_advance(end, splice_addr);
+    Ok(offset)
+}
+
 // ─── Slot kind ────────────────────────────────────────────────────────────────
 
 /// Discriminates between the two independent slot arrays in the Superblock.
@@ -165,7 +235,8 @@ pub enum SlotKind {
 
 /// Push every page in the chain rooted at `head` back onto the SHM free list.
 ///
-/// The target shard is `(head / PAGE_SIZE) % FREE_LIST_SHARD_COUNT` —
+/// Pages of a larger class go back to their class free list; a chain that
+/// contains any is freed page by page.  Otherwise the target shard is `(head / PAGE_SIZE) % FREE_LIST_SHARD_COUNT` —
 /// deterministic per chain so the same chain always lands in the same shard.
 /// The entire chain is spliced as one unit (walk to tail, single CAS), so
 /// cost is O(chain_length) for the walk and O(1) amortised for the CAS.
@@ -200,14 +271,20 @@ pub fn free_page_chain(splice_addr: usize, head: PageId) {
 
     // Walk to the tail so we can splice the whole chain in one CAS.
     let mut tail = head;
+    let mut all_small = true;
     loop {
         let page = unsafe { &*((splice_addr + tail as usize) as *const Page) };
+        all_small &= is_small_class(page.class.load(Ordering::Relaxed));
         let next = page.next_offset.load(Ordering::Acquire) as ShmOffset;
         if next == 0 {
             break;
         }
         tail = next;
     }
+    if !all_small {
+        free_page_chain_by_page(splice_addr, head);
+        return;
+    }
 
     // Treiber-stack push onto the chosen shard.
     loop {
@@ -227,6 +304,18 @@ pub fn free_page_chain(splice_addr: usize, head: PageId) {
     }
 }
 
+/// Slow path for a direct-mode chain holding large-class pages: each page
+/// goes back to the list of its own class.
+fn free_page_chain_by_page(splice_addr: usize, head: ShmOffset) {
+    let mut current = head;
+    while current != 0 {
+        let page = unsafe { &*((splice_addr + current as usize) as *const Page) };
+        let next = page.next_offset.load(Ordering::Acquire) as ShmOffset;
+        push_single_direct_page(splice_addr, current);
+        current = next;
+    }
+}
+
 /// Slow path for paged mode: walk the chain one page at a time,
 /// freeing direct pages into the shard freelist (plus transitioning
 /// their slot into the resolution buffer via `notify_direct_free`) and
@@ -272,8 +361,11 @@ fn free_page_chain_mixed(splice_addr: usize, head: PageId) {
             // its wasm32 slot can now host residency.  We can't call
             // `free_page_chain` recursively because we already know
             // the chain structure is being walked here.
-            push_single_direct_page(splice_addr, current as ShmOffset);
-            extended_pool::runtime::notify_direct_free(current);
+            // Large pages never host residency: they span several
+            // 4 KiB slots and go back to their class free list.
+            if push_single_direct_page(splice_addr, current as ShmOffset) {
+                extended_pool::runtime::notify_direct_free(current);
+            }
         } else {
             // Paged page: return it to the global pool.
             if let Err(e) = extended_pool::runtime::free_paged_page(current) {
@@ -285,23 +377,41 @@ fn free_page_chain_mixed(splice_addr: usize, head: PageId) {
     }
 }
 
-/// Push a single direct page onto its deterministic shard (no chain
-/// walking).  Used by `free_page_chain_mixed` to dispose of direct
-/// pages one at a time when they're interleaved with paged pages.
-fn push_single_direct_page(splice_addr: usize, offset: ShmOffset) {
+/// Push a single direct page onto its deterministic shard, or onto its
+/// class free list for a large page (no chain walking).  Used to dispose
+/// of direct pages one at a time when they're interleaved with paged or
+/// large pages.  Returns `true` for a small page.
+///
+/// A page of a zero-copy input goes to neither: it is handed back to its
+/// mapped-input entry (see `input_output::mapped_input`).
+fn push_single_direct_page(splice_addr: usize, offset: ShmOffset) -> bool {
     let sb = unsafe { &*(splice_addr as *const Superblock) };
-    let shard = (offset / PAGE_SIZE) as usize % FREE_LIST_SHARD_COUNT;
     let page = unsafe { &*((splice_addr + offset as usize) as *const Page) };
+    let class = page.class.load(Ordering::Relaxed);
+    if class == MAPPED_PAGE_CLASS && mapped_input::release_page(splice_addr, offset) {
+        return false;
+    }
+    let small = is_small_class(class);
+    let list = if small {
+        &sb.free_list_heads[(offset / PAGE_SIZE) as usize % FREE_LIST_SHARD_COUNT]
+    } else {
+        sb.class_free_list(class)
+    };
     loop {
-        let old_head = sb.free_list_heads[shard].load(Ordering::Acquire);
+        let old_head = list.load(Ordering::Acquire);
         page.next_offset.store(old_head, Ordering::Relaxed);
-        match sb.free_list_heads[shard]
-            .compare_exchange(old_head, offset as u64, Ordering::SeqCst, Ordering::SeqCst)
-        {
+        match list.compare_exchange(old_head, offset as u64, Ordering::SeqCst, Ordering::SeqCst) {
             Ok(_) => break,
             Err(_) => spin_loop(),
         }
     }
+    small
+}
+
+/// Whether `class` pages live on the sharded 4 KiB free list.  An unknown
+/// class (a header that never went through `alloc_page_in`) counts as small.
+fn is_small_class(class: u32) -> bool {
+    page_class_size(class) == PAGE_SIZE
 }
 
 // ─── Slot-level helpers ───────────────────────────────────────────────────────
@@ -314,23 +424,37 @@ fn push_single_direct_page(splice_addr: usize, offset: ShmOffset) {
 /// here would corrupt those chains.
 ///
 /// After this call `writer_heads[slot] == 0` and `writer_tails[slot] == 0`,
-/// so the slot appears empty if inspected.
+/// so the slot appears empty if inspected; its stream signal is reopened and
+/// its subscribers, if any, are rewound to the head.
 pub fn clear_stream_slot(splice_addr: usize, slot: usize) {
     let sb = unsafe { &*(splice_addr as *const Superblock) };
-    sb.writer_heads[slot].store(0, Ordering::Release);
-    sb.writer_tails[slot].store(0, Ordering::Release);
+    sb.writer_head(slot).store(0, Ordering::Release);
+    sb.writer_tail(slot).store(0, Ordering::Release);
+    sb.stream_signal(slot).store(0, Ordering::Release);
+    if let Some(subs) = Subscriptions::at(splice_addr, slot) {
+        subs.reset();
+    }
 }
 
 /// Detach the page chain from stream `slot` and return it to the free pool.
-/// Resets `writer_heads[slot]` and `writer_tails[slot]` to `0`.
+/// Resets `writer_heads[slot]`, `writer_tails[slot]` and the slot's stream
+/// signal to `0`, empties the slot's ring if it is one (its pages stay with
+/// the ring) and rewinds its subscribers.
 ///
 /// **Only call this when the slot has exclusive ownership of its pages** —
 /// i.e. no routing operation has spliced those pages into another slot's
 /// chain.  For slots that have been routed, use `clear_stream_slot` instead.
 pub fn free_stream_slot(splice_addr: usize, slot: usize) {
     let sb = unsafe { &*(splice_addr as *const Superblock) };
-    let head: PageId = sb.writer_heads[slot].swap(0, Ordering::AcqRel);
-    sb.writer_tails[slot].store(0, Ordering::Release);
+    let head: PageId = sb.writer_head(slot).swap(0, Ordering::AcqRel);
+    sb.writer_tail(slot).store(0, Ordering::Release);
+    sb.stream_signal(slot).store(0, Ordering::Release);
+    if let Some(ring) = RingSlot::at(splice_addr, slot) {
+        ring.reset();
+    }
+    if let Some(subs) = Subscriptions::at(splice_addr, slot) {
+        subs.reset();
+    }
     free_page_chain(splice_addr, head);
 }
 
@@ -342,8 +466,8 @@ pub fn free_stream_slot(splice_addr: usize, slot: usize) {
 /// their pages is always safe.
 pub fn free_io_slot(splice_addr: usize, slot: usize) {
     let sb = unsafe { &*(splice_addr as *const Superblock) };
-    let head: PageId = sb.io_heads[slot].swap(0, Ordering::AcqRel);
-    sb.io_tails[slot].store(0, Ordering::Release);
+    let head: PageId = sb.io_head(slot).swap(0, Ordering::AcqRel);
+    sb.io_tail(slot).store(0, Ordering::Release);
     free_page_chain(splice_addr, head);
 }
 
@@ -353,18 +477,23 @@ pub fn free_io_slot(splice_addr: usize, slot: usize) {
 /// chunk, the output) while keeping accumulator slots (e.g. word-count slot 200)
 /// alive across runs.
 ///
-/// Slots whose head is already 0 are skipped (no-op), so it is safe to call even
-/// after the normal per-wave reclamation has freed some of them.
+/// Slots with no pages, ring, subscribers or stream signal to reset are
+/// skipped, so it is safe to call even after the normal per-wave reclamation
+/// has freed some of them.
 pub fn free_all_transient(splice_addr: usize, persist: &[u32]) {
     let sb = unsafe { &*(splice_addr as *const Superblock) };
-    for s in 0..common::STREAM_SLOT_COUNT {
+    for s in 0..sb.stream_slot_count() {
         if persist.contains(&(s as u32)) { continue; }
-        if sb.writer_heads[s].load(Ordering::Acquire) != 0 {
+        if sb.writer_head(s).load(Ordering::Acquire) != 0
+            || sb.stream_ring(s).load(Ordering::Acquire) != 0
+            || sb.stream_subscriptions(s).load(Ordering::Acquire) != 0
+            || sb.stream_signal(s).load(Ordering::Acquire) != 0
+        {
             free_stream_slot(splice_addr, s);
         }
     }
-    for s in 0..common::IO_SLOT_COUNT {
-        if sb.io_heads[s].load(Ordering::Acquire) != 0 {
+    for s in 0..sb.io_slot_count() {
+        if sb.io_head(s).load(Ordering::Acquire) != 0 {
             free_io_slot(splice_addr, s);
         }
     }
@@ -395,7 +524,7 @@ pub fn free_all_transient(splice_addr: usize, persist: &[u32]) {
 /// (the slot was never read with a cursor), the function is a no-op.
 pub fn reset_slot_cursor(splice_addr: usize, kind: SlotKind, slot: usize) {
     use std::sync::atomic::AtomicU64;
-    use common::{ATOMIC_ARENA_OFFSET, REGISTRY_OFFSET, RegistryEntry};
+    use common::RegistryEntry;
 
     let name_str = match kind {
         SlotKind::Stream => format!("stream_cursor_{}", slot),
@@ -408,8 +537,8 @@ pub fn reset_slot_cursor(splice_addr: usize, kind: SlotKind, slot: usize) {
     name_key[..src.len().min(52)].copy_from_slice(&src[..src.len().min(52)]);
 
     let sb            = unsafe { &*(splice_addr as *const Superblock) };
-    let registry_base = (splice_addr + REGISTRY_OFFSET as usize) as *const RegistryEntry;
-    let atomic_base   = (splice_addr + ATOMIC_ARENA_OFFSET as usize) as *mut AtomicU64;
+    let registry_base = (splice_addr + sb.layout.registry_offset as usize) as *const RegistryEntry;
+    let atomic_base   = (splice_addr + sb.layout.atomic_arena_offset as usize) as *mut AtomicU64;
 
     // Acquire the registry spinlock (same protocol as host_resolve_atomic in worker.rs).
     while sb.registry_lock
@@ -459,6 +588,22 @@ pub fn count_free_list_pages(splice_addr: usize) -> usize {
     total
 }
 
+/// Bytes sitting in the large-class free lists.  Best-effort, like
+/// [`count_free_list_pages`].
+pub fn count_class_free_bytes(splice_addr: usize) -> u64 {
+    let sb = unsafe { &*(splice_addr as *const Superblock) };
+    let mut total = 0u64;
+    for class in 1..PAGE_CLASS_COUNT as u32 {
+        let mut current = sb.class_free_list(class).load(Ordering::Acquire) as ShmOffset;
+        while current != 0 {
+            total += page_class_size(class) as u64;
+            let page = unsafe { &*((splice_addr + current as usize) as *const Page) };
+            current = page.next_offset.load(Ordering::Relaxed) as ShmOffset;
+        }
+    }
+    total
+}
+
 /// CAS-pop one page from the first non-empty shard.  Returns its byte offset
 /// from `splice_addr`, or `None` if all shards are empty.
 fn pop_one_free_page(splice_addr: usize) -> Option<ShmOffset> {
@@ -501,9 +646,10 @@ fn push_page_to_free_list(splice_addr: usize, offset: ShmOffset) {
 
 /// Release the physical memory backing of excess free-list pages to the OS.
 ///
-/// Controlled by two constants in `common`:
+/// Controlled by:
 ///   - [`FREE_LIST_TRIM_ENABLED`]  — master on/off switch (compile-time).
-///   - [`FREE_LIST_TRIM_THRESHOLD`] — page count at which trimming fires.
+///   - `free_list_trim_threshold` in the engine configuration — page count at
+///     which trimming fires (default `common::FREE_LIST_TRIM_THRESHOLD`).
 ///
 /// # What it does
 ///
@@ -511,7 +657,8 @@ fn push_page_to_free_list(splice_addr: usize, offset: ShmOffset) {
 /// 2. If the count ≤ threshold, returns immediately — nothing to do.
 /// 3. Otherwise pops **half the excess** pages one by one with a CAS-pop,
 ///    calls `madvise(MADV_DONTNEED)` on each to release physical RAM, then
-///    pushes them back onto the free list.
+///    pushes them back onto the free list.  With huge pages on, only whole
+///    free huge-page extents are advised (see `release_free_extents`).
 ///
 /// The pages are NOT removed from the virtual address space — they stay in
 /// the free list so future allocations can reuse their offsets without growing
@@ -529,16 +676,39 @@ pub fn trim_free_list(splice_addr: usize) {
         return;
     }
 
+    let threshold = engine_config::current().free_list_trim_threshold as usize;
     let total = count_free_list_pages(splice_addr);
-    if total <= FREE_LIST_TRIM_THRESHOLD {
+    if total <= threshold {
         return;
     }
 
     // Release physical backing for half the excess pages.
-    let to_advise = (total - FREE_LIST_TRIM_THRESHOLD) / 2;
-    let mut advised = 0usize;
+    let advised = release_free_pages(splice_addr, (total - threshold) / 2);
+
+    if advised > 0 {
+        println!(
+            "[Reclaimer] Trim: {advised} pages ({} KiB) released to OS \
+             (free-list had {total}, threshold {threshold})",
+            advised * PAGE_SIZE as usize / 1024,
+        );
+    }
+}
 
-    while advised < to_advise {
+/// `madvise(MADV_DONTNEED)` up to `count` free-list pages and push them back,
+/// so their offsets stay recyclable while the OS reclaims the RAM.  Returns the
+/// number of pages advised.
+///
+/// All pages are popped before any is pushed back, so a page is never advised
+/// twice in one call.  Which free pages are picked does not matter to callers
+/// such as the spill tier: any `count` resident free pages return the same
+/// amount of RAM (pages advised earlier and not rewritten since cost nothing).
+pub fn release_free_pages(splice_addr: usize, count: usize) -> usize {
+    if shm::huge_pages() != HUGE_PAGES_OFF {
+        return release_free_extents(splice_addr, count);
+    }
+    let mut advised = 0usize;
+    let mut popped = Vec::with_capacity(count.min(1 << 16));
+    while advised < count {
         let Some(offset) = pop_one_free_page(splice_addr) else { break };
 
         let page_ptr = (splice_addr + offset as usize) as *mut std::ffi::c_void;
@@ -548,17 +718,40 @@ pub fn trim_free_list(splice_addr: usize) {
         unsafe {
             let _ = madvise(page_ptr, PAGE_SIZE as usize, MmapAdvise::MADV_DONTNEED);
         }
-
-        // Push back so the virtual offset stays recyclable.
-        push_page_to_free_list(splice_addr, offset);
+        popped.push(offset);
         advised += 1;
     }
+    // Push back so the virtual offsets stay recyclable.
+    for offset in popped {
+        push_page_to_free_list(splice_addr, offset);
+    }
+    advised
+}
 
-    if advised > 0 {
-        println!(
-            "[Reclaimer] Trim: {advised} pages ({} KiB) released to OS \
-             (free-list had {total}, threshold {FREE_LIST_TRIM_THRESHOLD})",
-            advised * PAGE_SIZE as usize / 1024,
-        );
+/// Huge-page variant of [`release_free_pages`]: a 4 KiB `MADV_DONTNEED` would
+/// split a transparent huge page (and fails on hugetlbfs), so this drains the
+/// whole free list, advises the huge-page extents that are entirely free until
+/// at least `count` pages are released, and pushes every page back.
+fn release_free_extents(splice_addr: usize, count: usize) -> usize {
+    let mut popped: Vec<u64> = Vec::new();
+    while let Some(offset) = pop_one_free_page(splice_addr) {
+        popped.push(offset as u64);
+    }
+    let pages_per_extent = (HUGE_PAGE_SIZE / PAGE_SIZE) as usize;
+    let mut advised = 0usize;
+    for start in shm::whole_huge_extents(&popped) {
+        if advised >= count {
+            break;
+        }
+        let extent_ptr = (splice_addr + start as usize) as *mut std::ffi::c_void;
+        // SAFETY: every page of the extent was just removed from the free
+        // list, so nothing references its contents.
+        if unsafe { madvise(extent_ptr, HUGE_PAGE_SIZE as usize, MmapAdvise::MADV_DONTNEED) }.is_ok() {
+            advised += pages_per_extent;
+        }
+    }
+    for offset in popped {
+        push_page_to_free_list(splice_addr, offset as ShmOffset);
     }
+    advised
 }
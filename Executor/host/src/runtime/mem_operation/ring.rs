@@ -0,0 +1,172 @@
+// Host side of ring slots (see "Ring slots" in `common`, and
+// `guest/src/api/ring.rs` for the guest half).
+//
+// `create` allocates a ring's header and pages when the DAG runner applies
+// the slot declarations; `reset` empties it again (from `free_stream_slot`),
+// when no guest can be using it.  Records only ever move through the guest
+// half.
+
+use crate::runtime::mem_operation::reclaimer;
+use anyhow::{anyhow, Result};
+use std::sync::atomic::Ordering;
+
+use common::{
+    Page, PageId, RingHeader, ShmOffset, Superblock, DIRECT_LIMIT, PAGE_SIZE, RING_MAX_CONSUMERS, RING_MAX_PAGES,
+};
+
+pub struct RingSlot {
+    base: usize,
+    header: ShmOffset,
+}
+
+impl RingSlot {
+    /// The ring of stream `slot` in the region mapped at `splice_addr`, or
+    /// `None` if the slot is an ordinary chain.
+    pub fn at(splice_addr: usize, slot: usize) -> Option<Self> {
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        let header = sb.stream_ring(slot).load(Ordering::Acquire);
+        (header != 0).then_some(Self { base: splice_addr, header })
+    }
+
+    /// Turns stream `slot` into a ring of `capacity` pages read by
+    /// `consumers` consumers.  Its pages are allocated in the slot's page
+    /// class, so apply page classes first.
+    pub fn create(splice_addr: usize, slot: usize, capacity: u32, consumers: u32) -> Result<Self> {
+        if !(2..=RING_MAX_PAGES as u32).contains(&capacity) {
+            return Err(anyhow!("ring slot {}: {} pages (expected 2..={})", slot, capacity, RING_MAX_PAGES));
+        }
+        if !(1..=RING_MAX_CONSUMERS as u32).contains(&consumers) {
+            return Err(anyhow!(
+                "ring slot {}: {} consumers (expected 1..={})", slot, consumers, RING_MAX_CONSUMERS,
+            ));
+        }
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        if sb.stream_ring(slot).load(Ordering::Acquire) != 0 {
+            return Err(anyhow!("stream slot {} is already a ring", slot));
+        }
+        let class = sb.stream_page_class(slot).load(Ordering::Relaxed) as u32;
+
+        let header = direct(reclaimer::alloc_page(splice_addr)?)?;
+        unsafe { std::ptr::write_bytes((splice_addr + header as usize) as *mut u8, 0, PAGE_SIZE as usize) };
+        let ring = Self { base: splice_addr, header };
+        let h = unsafe { &mut *((splice_addr + header as usize) as *mut RingHeader) };
+        h.capacity = capacity;
+        h.consumer_count = consumers;
+        for i in 0..capacity as usize {
+            let page = direct(reclaimer::alloc_page_in(splice_addr, class)?)?;
+            h.pages[i].store(page as PageId, Ordering::Relaxed);
+        }
+        sb.stream_ring(slot).store(header, Ordering::Release);
+        Ok(ring)
+    }
+
+    fn header(&self) -> &RingHeader {
+        unsafe { &*((self.base + self.header as usize) as *const RingHeader) }
+    }
+
+    fn page(&self, seq: u64) -> &Page {
+        unsafe { &*((self.base + self.header().page(seq) as usize) as *const Page) }
+    }
+
+    /// Empties the ring: every page and cursor back to sequence 0.  Only
+    /// while no guest uses the slot.
+    pub fn reset(&self) {
+        let h = self.header();
+        h.tail_seq.store(0, Ordering::Relaxed);
+        h.space.store(0, Ordering::Relaxed);
+        for c in &h.consumers {
+            c.page_seq.store(0, Ordering::Relaxed);
+            c.offset.store(0, Ordering::Relaxed);
+        }
+        for seq in 0..h.capacity as u64 {
+            self.page(seq).cursor.store(0, Ordering::Release);
+        }
+    }
+}
+
+/// Rings are read by guests through plain offsets, so their pages must be in
+/// the direct window.
+fn direct(page: PageId) -> Result<ShmOffset> {
+    if page >= DIRECT_LIMIT {
+        return Err(anyhow!("ring pages must be direct-mode pages (got {:#x})", page));
+    }
+    Ok(page as ShmOffset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::num::NonZeroUsize;
+
+    use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+
+    use common::INITIAL_SHM_SIZE;
+
+    use crate::runtime::mem_operation::fsck::fsck;
+    use crate::runtime::mem_operation::inspect::ShmView;
+    use crate::shm::format_shared_memory;
+
+    /// Runs `f` on a freshly formatted region.
+    fn with_region(f: impl FnOnce(usize)) {
+        let dir = tempfile::tempdir().unwrap();
+        let shm = dir.path().join("region");
+        format_shared_memory(shm.to_str().unwrap()).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&shm).unwrap();
+        let len = NonZeroUsize::new(INITIAL_SHM_SIZE as usize).unwrap();
+        let base = unsafe {
+            mmap(None, len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, Some(&file), 0)
+        }.unwrap() as usize;
+        f(base);
+        unsafe { munmap(base as *mut _, len.get()).unwrap() };
+    }
+
+    fn assert_clean(base: usize) {
+        let bytes = unsafe { std::slice::from_raw_parts(base as *const u8, INITIAL_SHM_SIZE as usize) };
+        let report = fsck("region", &ShmView::new(bytes).unwrap(), false);
+        assert!(report.is_clean(), "{:?}", report.problems.iter().map(|p| &p.detail).collect::<Vec<_>>());
+        assert_eq!(report.leaked_pages, Some(0));
+    }
+
+    #[test]
+    fn create_checks_sizes_and_publishes_the_ring() {
+        with_region(|base| {
+            assert!(RingSlot::create(base, 7, 1, 1).is_err());
+            assert!(RingSlot::create(base, 7, 2, 0).is_err());
+            assert!(RingSlot::create(base, 7, RING_MAX_PAGES as u32 + 1, 1).is_err());
+            assert!(RingSlot::at(base, 7).is_none());
+
+            let ring = RingSlot::create(base, 7, 4, 2).unwrap();
+            assert_eq!((ring.header().capacity, ring.header().consumer_count), (4, 2));
+            assert!(RingSlot::create(base, 7, 4, 2).is_err());
+            assert_eq!(RingSlot::at(base, 7).map(|r| r.header), Some(ring.header));
+            assert_clean(base);
+        });
+    }
+
+    #[test]
+    fn reset_rewinds_every_cursor_and_keeps_the_pages() {
+        with_region(|base| {
+            let ring = RingSlot::create(base, 3, 4, 2).unwrap();
+            let h = ring.header();
+            let pages: Vec<PageId> = h.pages[..4].iter().map(|p| p.load(Ordering::Relaxed)).collect();
+            // State the guest protocol leaves behind after a few laps.
+            h.tail_seq.store(9, Ordering::Relaxed);
+            h.space.store(5, Ordering::Relaxed);
+            h.consumers[1].page_seq.store(7, Ordering::Relaxed);
+            h.consumers[1].offset.store(64, Ordering::Relaxed);
+            for seq in 0..4 {
+                ring.page(seq).cursor.store(128, Ordering::Relaxed);
+            }
+
+            ring.reset();
+            assert_eq!((h.tail_seq.load(Ordering::Relaxed), h.space.load(Ordering::Relaxed)), (0, 0));
+            assert!(h.consumers.iter().all(|c| c.page_seq.load(Ordering::Relaxed) == 0
+                && c.offset.load(Ordering::Relaxed) == 0));
+            assert!((0..4).all(|seq| ring.page(seq).cursor.load(Ordering::Relaxed) == 0));
+            let after: Vec<PageId> = h.pages[..4].iter().map(|p| p.load(Ordering::Relaxed)).collect();
+            assert_eq!(after, pages);
+            assert_clean(base);
+        });
+    }
+}
@@ -0,0 +1,279 @@
+// Host side of stream subscriptions (see "Stream subscriptions" in `common`,
+// and `guest/src/api/subscription.rs` for the guest half).
+//
+// `create` allocates a slot's subscriber table when the DAG runner applies
+// the slot declarations.  `reclaim` frees the part of the slot's chain every
+// subscriber has read; the runner calls it after each wave, when no guest is
+// reading.  `reset` rewinds every cursor to the head and runs from
+// `free_stream_slot` / `clear_stream_slot`.  Records are only read through
+// the guest half; `inspect` and `fsck` decode the table from a file view.
+
+use crate::runtime::extended_pool;
+use crate::runtime::mem_operation::reclaimer;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+
+use common::{
+    Page, PageId, ShmOffset, SubscriptionTable, Superblock, PAGE_SIZE, SUBSCRIBER_MAX, SUBSCRIBER_NAME_LEN,
+};
+
+pub struct Subscriptions {
+    base: usize,
+    slot: usize,
+    table: ShmOffset,
+}
+
+impl Subscriptions {
+    /// The subscriber table of stream `slot` in the region mapped at
+    /// `splice_addr`, or `None` if the slot has no subscribers.
+    pub fn at(splice_addr: usize, slot: usize) -> Option<Self> {
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        let table = sb.stream_subscriptions(slot).load(Ordering::Acquire);
+        (table != 0).then_some(Self { base: splice_addr, slot, table })
+    }
+
+    /// Gives stream `slot` one cursor per name in `names`, all at the head
+    /// of the chain.
+    pub fn create(splice_addr: usize, slot: usize, names: &[String]) -> Result<Self> {
+        if !(1..=SUBSCRIBER_MAX).contains(&names.len()) {
+            return Err(anyhow!(
+                "stream slot {}: {} subscribers (expected 1..={})", slot, names.len(), SUBSCRIBER_MAX,
+            ));
+        }
+        let mut seen = HashSet::new();
+        for name in names {
+            if name.is_empty() || name.len() > SUBSCRIBER_NAME_LEN || name.contains('\0') {
+                return Err(anyhow!(
+                    "stream slot {}: subscriber name {:?} must be 1..={} bytes without NUL",
+                    slot, name, SUBSCRIBER_NAME_LEN,
+                ));
+            }
+            if !seen.insert(name) {
+                return Err(anyhow!("stream slot {}: subscriber '{}' is listed twice", slot, name));
+            }
+        }
+        let sb = unsafe { &*(splice_addr as *const Superblock) };
+        if sb.stream_subscriptions(slot).load(Ordering::Acquire) != 0 {
+            return Err(anyhow!("stream slot {} already has subscribers", slot));
+        }
+
+        let page = reclaimer::alloc_page(splice_addr)?;
+        if page >= common::DIRECT_LIMIT {
+            return Err(anyhow!("subscriber tables must be direct-mode pages (got {:#x})", page));
+        }
+        let table = page as ShmOffset;
+        unsafe { std::ptr::write_bytes((splice_addr + table as usize) as *mut u8, 0, PAGE_SIZE as usize) };
+        let t = unsafe { &mut *((splice_addr + table as usize) as *mut SubscriptionTable) };
+        t.count = names.len() as u32;
+        for (cursor, name) in t.subscribers.iter_mut().zip(names) {
+            cursor.name[..name.len()].copy_from_slice(name.as_bytes());
+        }
+        sb.stream_subscriptions(slot).store(table, Ordering::Release);
+        Ok(Self { base: splice_addr, slot, table })
+    }
+
+    fn superblock(&self) -> &Superblock {
+        unsafe { &*(self.base as *const Superblock) }
+    }
+
+    fn table(&self) -> &SubscriptionTable {
+        unsafe { &*((self.base + self.table as usize) as *const SubscriptionTable) }
+    }
+
+    fn page(&self, id: PageId) -> Result<&Page> {
+        let ptr = extended_pool::runtime::resolve(id, self.base)
+            .map_err(|e| anyhow!("stream slot {}: resolve {:#x}: {}", self.slot, id, e))?;
+        Ok(unsafe { &*ptr })
+    }
+
+    /// Rewinds every cursor to the head of the chain.  Only while no guest
+    /// reads the slot.
+    pub fn reset(&self) {
+        for s in self.table().active() {
+            s.page.store(0, Ordering::Relaxed);
+            s.offset.store(0, Ordering::Relaxed);
+            s.records.store(0, Ordering::Release);
+        }
+    }
+
+    /// Frees the pages before the earliest subscriber cursor and moves the
+    /// slot's head to that cursor's page.  Returns how many pages were freed:
+    /// none while any subscriber is still at the head.  Only while no guest
+    /// uses the slot.
+    pub fn reclaim(&self) -> Result<usize> {
+        let mut cursor_pages = HashSet::new();
+        for s in self.table().active() {
+            match s.page.load(Ordering::Acquire) {
+                0 => return Ok(0),
+                p => cursor_pages.insert(p),
+            };
+        }
+        let sb = self.superblock();
+        let head = sb.writer_head(self.slot).load(Ordering::Acquire);
+        let tail = sb.writer_tail(self.slot).load(Ordering::Acquire);
+
+        let mut last_freed = None;
+        let mut freed = 0;
+        let mut page = head;
+        while !cursor_pages.contains(&page) {
+            if page == 0 || page == tail {
+                return Err(anyhow!("stream slot {}: a subscriber cursor is not on the slot's chain", self.slot));
+            }
+            last_freed = Some(page);
+            freed += 1;
+            page = self.page(page)?.next_offset.load(Ordering::Acquire);
+        }
+        let Some(last) = last_freed else { return Ok(0) };
+        sb.writer_head(self.slot).store(page, Ordering::Release);
+        self.page(last)?.next_offset.store(0, Ordering::Release);
+        reclaimer::free_page_chain(self.base, head);
+        Ok(freed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::num::NonZeroUsize;
+
+    use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+
+    use common::{INITIAL_SHM_SIZE, PAGE_DATA_SIZE};
+
+    use crate::runtime::mem_operation::fsck::fsck;
+    use crate::runtime::mem_operation::inspect::ShmView;
+    use crate::shm::format_shared_memory;
+
+    /// Runs `f` on a freshly formatted region.
+    fn with_region(f: impl FnOnce(usize)) {
+        let dir = tempfile::tempdir().unwrap();
+        let shm = dir.path().join("region");
+        format_shared_memory(shm.to_str().unwrap()).unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&shm).unwrap();
+        let len = NonZeroUsize::new(INITIAL_SHM_SIZE as usize).unwrap();
+        let base = unsafe {
+            mmap(None, len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, Some(&file), 0)
+        }.unwrap() as usize;
+        f(base);
+        unsafe { munmap(base as *mut _, len.get()).unwrap() };
+    }
+
+    /// Appends `[len][origin][payload]` to stream `slot`, spanning pages.
+    fn append(base: usize, slot: usize, origin: u32, payload: &[u8]) {
+        let sb = unsafe { &*(base as *const Superblock) };
+        let record = [&(payload.len() as u32).to_le_bytes()[..], &origin.to_le_bytes(), payload].concat();
+        let mut data = &record[..];
+        while !data.is_empty() {
+            let mut tail = sb.writer_tail(slot).load(Ordering::Acquire);
+            if tail == 0 {
+                tail = reclaimer::alloc_page(base).unwrap();
+                sb.writer_head(slot).store(tail, Ordering::Release);
+                sb.writer_tail(slot).store(tail, Ordering::Release);
+            }
+            let page = unsafe { &mut *((base + tail as usize) as *mut Page) };
+            let cursor = page.cursor.load(Ordering::Relaxed) as usize;
+            if cursor == page.capacity() {
+                let next = reclaimer::alloc_page(base).unwrap();
+                page.next_offset.store(next, Ordering::Release);
+                sb.writer_tail(slot).store(next, Ordering::Release);
+                continue;
+            }
+            let n = (page.capacity() - cursor).min(data.len());
+            page.data[cursor..cursor + n].copy_from_slice(&data[..n]);
+            page.cursor.store((cursor + n) as ShmOffset, Ordering::Release);
+            data = &data[n..];
+        }
+    }
+
+    fn chain_len(base: usize, slot: usize) -> usize {
+        let sb = unsafe { &*(base as *const Superblock) };
+        let mut page = sb.writer_head(slot).load(Ordering::Acquire);
+        let mut n = 0;
+        while page != 0 {
+            n += 1;
+            page = unsafe { &*((base + page as usize) as *const Page) }.next_offset.load(Ordering::Acquire);
+        }
+        n
+    }
+
+    fn assert_clean(base: usize) {
+        let bytes = unsafe { std::slice::from_raw_parts(base as *const u8, INITIAL_SHM_SIZE as usize) };
+        let report = fsck("region", &ShmView::new(bytes).unwrap(), false);
+        assert!(report.is_clean(), "{:?}", report.problems.iter().map(|p| &p.detail).collect::<Vec<_>>());
+        assert_eq!(report.leaked_pages, Some(0));
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Page `n` of stream `slot`'s chain, counting from the head.
+    fn nth_page(base: usize, slot: usize, n: usize) -> PageId {
+        let sb = unsafe { &*(base as *const Superblock) };
+        let mut page = sb.writer_head(slot).load(Ordering::Acquire);
+        for _ in 0..n {
+            page = unsafe { &*((base + page as usize) as *const Page) }.next_offset.load(Ordering::Acquire);
+        }
+        page
+    }
+
+    /// Moves subscriber `sub`'s cursor as the guest does after a read.
+    fn seek(subs: &Subscriptions, sub: usize, page: PageId, offset: u32) {
+        let cursor = &subs.table().active()[sub];
+        cursor.page.store(page, Ordering::Release);
+        cursor.offset.store(offset, Ordering::Release);
+    }
+
+    #[test]
+    fn pages_are_freed_once_every_subscriber_has_passed_them() {
+        with_region(|base| {
+            let subs = Subscriptions::create(base, 5, &names(&["audit", "index"])).unwrap();
+            // Records straddle page boundaries; twelve take five pages.
+            let record = vec![b's'; PAGE_DATA_SIZE / 3 + 92];
+            for i in 0..12 {
+                append(base, 5, i, &record);
+            }
+            assert_eq!(chain_len(base, 5), 5);
+
+            seek(&subs, 0, nth_page(base, 5, 4), 100);
+            assert_eq!(subs.reclaim().unwrap(), 0, "'index' has not started");
+            seek(&subs, 1, nth_page(base, 5, 0), 100);
+            assert_eq!(subs.reclaim().unwrap(), 0, "'index' is still on the head page");
+
+            let third = nth_page(base, 5, 2);
+            seek(&subs, 1, third, 40);
+            assert_eq!(subs.reclaim().unwrap(), 2);
+            assert_eq!(chain_len(base, 5), 3);
+            assert_eq!(nth_page(base, 5, 0), third);
+            assert_eq!(subs.reclaim().unwrap(), 0);
+            assert_clean(base);
+        });
+    }
+
+    #[test]
+    fn freeing_the_slot_rewinds_its_subscribers() {
+        with_region(|base| {
+            let subs = Subscriptions::create(base, 9, &names(&["a"])).unwrap();
+            append(base, 9, 1, b"one");
+            seek(&subs, 0, nth_page(base, 9, 0), 11);
+            subs.table().active()[0].records.store(1, Ordering::Release);
+            reclaimer::free_stream_slot(base, 9);
+            let cursor = &subs.table().active()[0];
+            assert_eq!(cursor.page.load(Ordering::Acquire), 0);
+            assert_eq!((cursor.offset.load(Ordering::Acquire), cursor.records.load(Ordering::Acquire)), (0, 0));
+            append(base, 9, 2, b"two");
+            assert_eq!(subs.reclaim().unwrap(), 0);
+
+            assert!(Subscriptions::create(base, 9, &names(&["b"])).is_err());
+            assert!(Subscriptions::create(base, 10, &names(&["b", "b"])).is_err());
+            assert!(Subscriptions::create(base, 10, &[]).is_err());
+            let at = Subscriptions::at(base, 9).unwrap();
+            assert_eq!(at.table().active().iter().map(|s| s.name()).collect::<Vec<_>>(), vec![b"a"]);
+            assert!(Subscriptions::at(base, 10).is_none());
+            reclaimer::free_stream_slot(base, 9);
+            assert_clean(base);
+        });
+    }
+}
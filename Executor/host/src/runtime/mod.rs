@@ -1,4 +1,5 @@
 pub mod dag_runner;
+pub mod engine_config;
 pub mod extended_pool;
 pub mod input_output;
 pub mod manager;
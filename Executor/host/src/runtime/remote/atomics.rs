@@ -0,0 +1,432 @@
+// Guest-initiated remote atomics: the runner-side service behind the
+// `host_remote_faa` / `host_remote_cas` / `host_remote_read_atomic` imports.
+//
+// WASM workers are subprocesses and cannot reach the runner's MeshNode, so
+// the runner listens on a TCP port (`WEBS_ATOMIC_ADDR` in the workers'
+// environment) and each worker sends it one fixed-size frame per operation:
+//
+//   request  [op u8][peer u32][index u32][a u64][b u64]    25 bytes, LE
+//   reply    [status u8][value u64]                         9 bytes
+//
+// `index` is the named atomic's slot in the atomic arena; like the
+// RemoteAtomic* DAG nodes, the owner's copy is assumed to sit at the same
+// index, so every node must register its remote atomics in the same order.
+//
+// A request for this node is applied to the local arena.  Others go over
+// the mesh transport:
+//
+//   Rdma — one-sided FAA / CAS on the peer's SHM through connect::MeshNode
+//          (a read is a FAA of 0); the peer's CPU is not involved.
+//   Tcp  — the frame is forwarded to the peer's service, which applies it
+//          to its own arena.  Needs no RDMA NIC, so a mesh of runners on
+//          one machine can use it.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use connect::MeshNode;
+
+use crate::shm::layout_at;
+
+/// Environment variable through which the runner hands its service address
+/// to the WASM workers it spawns.
+pub const ATOMIC_ADDR_ENV: &str = "WEBS_ATOMIC_ADDR";
+
+/// Guest imports backed by the service.  The runner only starts it for a
+/// module that imports at least one of them.
+pub const REMOTE_ATOMIC_IMPORTS: [&str; 3] = ["host_remote_faa", "host_remote_cas", "host_remote_read_atomic"];
+
+/// Address of the service this process started, handed to its workers.
+static SERVICE_ADDR: OnceLock<SocketAddr> = OnceLock::new();
+
+/// How long a forward to a peer's service retries before giving up, so
+/// runners of one mesh may start a little apart.
+const PEER_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const REQUEST_LEN: usize = 25;
+const REPLY_LEN: usize = 9;
+
+const STATUS_OK: u8 = 0;
+const STATUS_ERR: u8 = 1;
+
+// ── Wire format ───────────────────────────────────────────────────────────────
+
+/// Operation carried by an [`AtomicRequest`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtomicOp {
+    /// Add `a`; returns the old value.
+    FetchAdd,
+    /// Store `b` iff the value equals `a`; returns the old value.
+    CompareSwap,
+    /// Returns the current value.
+    Read,
+}
+
+impl AtomicOp {
+    fn code(self) -> u8 {
+        match self {
+            AtomicOp::FetchAdd    => 0,
+            AtomicOp::CompareSwap => 1,
+            AtomicOp::Read        => 2,
+        }
+    }
+
+    fn from_code(code: u8) -> Result<Self> {
+        match code {
+            0 => Ok(AtomicOp::FetchAdd),
+            1 => Ok(AtomicOp::CompareSwap),
+            2 => Ok(AtomicOp::Read),
+            _ => Err(anyhow!("unknown remote atomic op {}", code)),
+        }
+    }
+}
+
+/// One remote atomic: `op` on the arena word at `index` on node `peer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtomicRequest {
+    pub op:    AtomicOp,
+    pub peer:  u32,
+    pub index: u32,
+    pub a:     u64,
+    pub b:     u64,
+}
+
+impl AtomicRequest {
+    fn encode(&self) -> [u8; REQUEST_LEN] {
+        let mut buf = [0u8; REQUEST_LEN];
+        buf[0] = self.op.code();
+        buf[1..5].copy_from_slice(&self.peer.to_le_bytes());
+        buf[5..9].copy_from_slice(&self.index.to_le_bytes());
+        buf[9..17].copy_from_slice(&self.a.to_le_bytes());
+        buf[17..25].copy_from_slice(&self.b.to_le_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8; REQUEST_LEN]) -> Result<Self> {
+        Ok(AtomicRequest {
+            op:    AtomicOp::from_code(buf[0])?,
+            peer:  u32::from_le_bytes(buf[1..5].try_into().unwrap()),
+            index: u32::from_le_bytes(buf[5..9].try_into().unwrap()),
+            a:     u64::from_le_bytes(buf[9..17].try_into().unwrap()),
+            b:     u64::from_le_bytes(buf[17..25].try_into().unwrap()),
+        })
+    }
+}
+
+/// Sends `req` on `stream` and waits for the reply.  An error reply carries
+/// its message after the status byte in place of the value.
+fn round_trip(stream: &mut TcpStream, req: &AtomicRequest) -> Result<u64> {
+    stream.write_all(&req.encode())?;
+    let mut reply = [0u8; REPLY_LEN];
+    stream.read_exact(&mut reply)?;
+    let value = u64::from_le_bytes(reply[1..].try_into().unwrap());
+    if reply[0] == STATUS_OK {
+        return Ok(value);
+    }
+    let mut msg = vec![0u8; value as usize];
+    stream.read_exact(&mut msg)?;
+    Err(anyhow!("{}", String::from_utf8_lossy(&msg)))
+}
+
+fn write_reply(stream: &mut TcpStream, result: Result<u64>) -> std::io::Result<()> {
+    match result {
+        Ok(value) => {
+            let mut reply = [STATUS_OK; REPLY_LEN];
+            reply[1..].copy_from_slice(&value.to_le_bytes());
+            stream.write_all(&reply)
+        }
+        Err(e) => {
+            let msg = format!("{:#}", e);
+            let mut reply = [STATUS_ERR; REPLY_LEN];
+            reply[1..].copy_from_slice(&(msg.len() as u64).to_le_bytes());
+            stream.write_all(&reply)?;
+            stream.write_all(msg.as_bytes())
+        }
+    }
+}
+
+// ── Client ────────────────────────────────────────────────────────────────────
+
+/// A worker's connection to its runner's atomics service.
+pub struct AtomicClient {
+    stream: TcpStream,
+}
+
+impl AtomicClient {
+    pub fn connect(addr: &str) -> Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .with_context(|| format!("connect to atomics service at {}", addr))?;
+        stream.set_nodelay(true)?;
+        Ok(AtomicClient { stream })
+    }
+
+    /// Connects to the service named by `WEBS_ATOMIC_ADDR`.
+    pub fn from_env() -> Result<Self> {
+        let addr = std::env::var(ATOMIC_ADDR_ENV).map_err(|_| anyhow!(
+            "remote atomics need dag.rdma: {} is not set for this worker", ATOMIC_ADDR_ENV
+        ))?;
+        Self::connect(&addr)
+    }
+
+    /// Runs `req` and returns the word's value before the operation.
+    pub fn call(&mut self, req: &AtomicRequest) -> Result<u64> {
+        round_trip(&mut self.stream, req)
+    }
+}
+
+// ── Service ───────────────────────────────────────────────────────────────────
+
+/// How the service reaches atomics owned by other nodes.
+pub enum AtomicTransport {
+    /// One-sided RDMA atomics over the runner's mesh.
+    Rdma(Arc<MeshNode>),
+    /// Forward to each node's service; `addrs[i]` is node `i`'s `host:port`.
+    Tcp { addrs: Vec<String> },
+}
+
+struct ServiceState {
+    node_id:     usize,
+    splice_addr: usize,
+    transport:   AtomicTransport,
+    /// Open forwarding connection per peer (Tcp transport only).
+    peers:       Vec<Mutex<Option<TcpStream>>>,
+}
+
+/// Handle to a running atomics service.  The accept loop runs on a
+/// background thread for the rest of the process.
+pub struct AtomicService {
+    addr: SocketAddr,
+}
+
+impl AtomicService {
+    /// Serves requests arriving on `listener` for node `node_id`, whose SHM
+    /// is mapped at `splice_addr`.  `total` is the number of mesh nodes.
+    pub fn start(
+        node_id:     usize,
+        total:       usize,
+        splice_addr: usize,
+        listener:    TcpListener,
+        transport:   AtomicTransport,
+    ) -> Result<Self> {
+        if let AtomicTransport::Tcp { addrs } = &transport {
+            if addrs.len() != total {
+                return Err(anyhow!("atomics service: {} peer addresses for {} nodes", addrs.len(), total));
+            }
+        }
+        let addr = listener.local_addr()?;
+        let state = Arc::new(ServiceState {
+            node_id,
+            splice_addr,
+            transport,
+            peers: (0..total).map(|_| Mutex::new(None)).collect(),
+        });
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let state = Arc::clone(&state);
+                std::thread::spawn(move || serve_connection(&state, stream));
+            }
+        });
+        Ok(AtomicService { addr })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Makes this the service that workers spawned from now on connect to
+    /// (see [`worker_env`]).  Only the first call in a process takes effect.
+    pub fn publish(&self) {
+        let _ = SERVICE_ADDR.set(self.addr);
+    }
+}
+
+/// The `WEBS_ATOMIC_ADDR` entry for a worker's environment, if a service
+/// was published: `Command::new(..).envs(worker_env())`.
+pub fn worker_env() -> Option<(&'static str, String)> {
+    SERVICE_ADDR.get().map(|addr| (ATOMIC_ADDR_ENV, addr.to_string()))
+}
+
+fn serve_connection(state: &ServiceState, mut stream: TcpStream) {
+    stream.set_nodelay(true).ok();
+    let mut buf = [0u8; REQUEST_LEN];
+    while stream.read_exact(&mut buf).is_ok() {
+        let result = AtomicRequest::decode(&buf).and_then(|req| state.execute(&req));
+        if write_reply(&mut stream, result).is_err() {
+            return;
+        }
+    }
+}
+
+impl ServiceState {
+    fn execute(&self, req: &AtomicRequest) -> Result<u64> {
+        let peer = req.peer as usize;
+        if peer >= self.peers.len() {
+            return Err(anyhow!("remote atomic: no node {} (mesh of {})", peer, self.peers.len()));
+        }
+        let layout = layout_at(self.splice_addr);
+        if req.index as usize >= layout.named_atomic_capacity() {
+            return Err(anyhow!(
+                "remote atomic: index {} is past the atomic arena ({} atomics)",
+                req.index, layout.named_atomic_capacity()
+            ));
+        }
+        if peer == self.node_id {
+            return Ok(self.apply_local(req));
+        }
+        let remote_off = layout.atomic_offset(req.index as usize);
+        match &self.transport {
+            AtomicTransport::Rdma(mesh) => {
+                let result_off = layout.rdma_scratch_slot_offset(mesh.id, peer);
+                match req.op {
+                    AtomicOp::FetchAdd    => mesh.rdma_fetch_add_shm(peer, remote_off, result_off, req.a),
+                    AtomicOp::CompareSwap => mesh.rdma_compare_swap_shm(peer, remote_off, result_off, req.a, req.b),
+                    AtomicOp::Read        => mesh.rdma_read_atomic_shm(peer, remote_off, result_off),
+                }
+            }
+            AtomicTransport::Tcp { addrs } => self.forward(peer, &addrs[peer], req),
+        }
+    }
+
+    fn apply_local(&self, req: &AtomicRequest) -> u64 {
+        let off = layout_at(self.splice_addr).atomic_offset(req.index as usize);
+        let word = unsafe { &*((self.splice_addr + off as usize) as *const AtomicU64) };
+        match req.op {
+            AtomicOp::FetchAdd => word.fetch_add(req.a, Ordering::AcqRel),
+            AtomicOp::CompareSwap => word
+                .compare_exchange(req.a, req.b, Ordering::AcqRel, Ordering::Acquire)
+                .unwrap_or_else(|old| old),
+            AtomicOp::Read => word.load(Ordering::Acquire),
+        }
+    }
+
+    /// Sends `req` to `peer`'s service, connecting on first use.  A
+    /// connection that fails mid-request is dropped and re-opened by the
+    /// next one.
+    fn forward(&self, peer: usize, addr: &str, req: &AtomicRequest) -> Result<u64> {
+        let mut conn = self.peers[peer].lock().unwrap();
+        if conn.is_none() {
+            *conn = Some(connect_with_retry(addr)?);
+        }
+        let result = round_trip(conn.as_mut().unwrap(), req);
+        if result.as_ref().is_err_and(|e| e.downcast_ref::<std::io::Error>().is_some()) {
+            *conn = None;
+        }
+        result.with_context(|| format!("remote atomic on node {} ({})", peer, addr))
+    }
+}
+
+fn connect_with_retry(addr: &str) -> Result<TcpStream> {
+    let deadline = Instant::now() + PEER_CONNECT_TIMEOUT;
+    loop {
+        match TcpStream::connect(addr) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(e) if Instant::now() >= deadline => {
+                return Err(anyhow!("connect {}: {} (timed out after {:?})", addr, e, PEER_CONNECT_TIMEOUT));
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(200)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::num::NonZeroUsize;
+
+    use nix::sys::mman::{mmap, munmap, MapFlags, ProtFlags};
+
+    use common::INITIAL_SHM_SIZE;
+
+    use crate::shm::format_shared_memory;
+
+    /// A freshly formatted region, unmapped on drop.
+    struct Region {
+        _dir: tempfile::TempDir,
+        base: usize,
+    }
+
+    impl Region {
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let shm = dir.path().join("region");
+            format_shared_memory(shm.to_str().unwrap()).unwrap();
+            let file = OpenOptions::new().read(true).write(true).open(&shm).unwrap();
+            let len = NonZeroUsize::new(INITIAL_SHM_SIZE as usize).unwrap();
+            let base = unsafe {
+                mmap(None, len, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE, MapFlags::MAP_SHARED, Some(&file), 0)
+            }.unwrap() as usize;
+            Region { _dir: dir, base }
+        }
+
+        fn atomic(&self, index: usize) -> &AtomicU64 {
+            let off = layout_at(self.base).atomic_offset(index);
+            unsafe { &*((self.base + off as usize) as *const AtomicU64) }
+        }
+    }
+
+    impl Drop for Region {
+        fn drop(&mut self) {
+            unsafe { munmap(self.base as *mut _, INITIAL_SHM_SIZE as usize).unwrap() };
+        }
+    }
+
+    /// Starts a two-node TCP mesh on loopback and returns a worker client
+    /// for each node.
+    fn tcp_mesh(regions: &[Region]) -> Vec<AtomicClient> {
+        let listeners: Vec<TcpListener> =
+            regions.iter().map(|_| TcpListener::bind("127.0.0.1:0").unwrap()).collect();
+        let addrs: Vec<String> =
+            listeners.iter().map(|l| l.local_addr().unwrap().to_string()).collect();
+        listeners.into_iter().zip(regions).enumerate().map(|(id, (listener, region))| {
+            let transport = AtomicTransport::Tcp { addrs: addrs.clone() };
+            let service = AtomicService::start(id, regions.len(), region.base, listener, transport).unwrap();
+            AtomicClient::connect(&service.addr().to_string()).unwrap()
+        }).collect()
+    }
+
+    fn req(op: AtomicOp, peer: u32, index: u32, a: u64, b: u64) -> AtomicRequest {
+        AtomicRequest { op, peer, index, a, b }
+    }
+
+    #[test]
+    fn tcp_transport_applies_ops_on_the_owning_node() {
+        let regions = [Region::new(), Region::new()];
+        let mut clients = tcp_mesh(&regions);
+
+        // Node 0's worker adds to the counter owned by node 1, node 1's to
+        // its own; both see every earlier add.
+        assert_eq!(clients[0].call(&req(AtomicOp::FetchAdd, 1, 3, 5, 0)).unwrap(), 0);
+        assert_eq!(clients[1].call(&req(AtomicOp::FetchAdd, 1, 3, 2, 0)).unwrap(), 5);
+        assert_eq!(clients[0].call(&req(AtomicOp::Read, 1, 3, 0, 0)).unwrap(), 7);
+        assert_eq!(regions[1].atomic(3).load(Ordering::Acquire), 7);
+        assert_eq!(regions[0].atomic(3).load(Ordering::Acquire), 0);
+
+        // Leader election: only the first CAS from 0 succeeds.
+        assert_eq!(clients[1].call(&req(AtomicOp::CompareSwap, 0, 0, 0, 2)).unwrap(), 0);
+        assert_eq!(clients[0].call(&req(AtomicOp::CompareSwap, 0, 0, 0, 1)).unwrap(), 2);
+        assert_eq!(regions[0].atomic(0).load(Ordering::Acquire), 2);
+    }
+
+    #[test]
+    fn bad_requests_are_reported_to_the_worker() {
+        let regions = [Region::new(), Region::new()];
+        let mut clients = tcp_mesh(&regions);
+
+        let e = clients[0].call(&req(AtomicOp::FetchAdd, 2, 0, 1, 0)).unwrap_err();
+        assert!(e.to_string().contains("no node 2"), "{}", e);
+        let e = clients[0].call(&req(AtomicOp::Read, 1, u32::MAX, 0, 0)).unwrap_err();
+        assert!(e.to_string().contains("past the atomic arena"), "{}", e);
+
+        // The connection stays usable after an error reply.
+        assert_eq!(clients[0].call(&req(AtomicOp::FetchAdd, 1, 0, 1, 0)).unwrap(), 0);
+    }
+}
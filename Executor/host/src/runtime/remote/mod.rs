@@ -11,12 +11,16 @@
 // RemoteRecv uses RecvChannel.ctrl (ctrl_as_receiver) exclusively.
 // These are separate TCP connections per peer pair, so concurrent sends and
 // receives to the same peer never interleave their messages.
+//
+// Guest-initiated remote atomics (host_remote_faa & co.) are served by the
+// runner's atomics service — see atomics.rs.
 
 use anyhow::Result;
 use connect::{MeshNode, SendChannel, RecvChannel};
 
 use crate::runtime::dag_runner::{RemoteSlotKind, RemoteProtocol};
 
+pub mod atomics;
 mod shm;
 mod rdma;
 mod sender_initiated;
@@ -24,7 +28,8 @@ mod receiver_initiated;
 
 // ── Shared constant ───────────────────────────────────────────────────────────
 
-/// Usable data bytes per SHM page (PAGE_SIZE minus the header).
+/// Usable data bytes per 4 KiB SHM page (PAGE_SIZE minus the header).  The RI
+/// protocol always lands in small pages; SI follows the slot's page class.
 const PAGE_DATA: usize = common::PAGE_DATA_SIZE;
 
 // ── Compatibility shim ────────────────────────────────────────────────────────
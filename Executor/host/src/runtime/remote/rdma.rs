@@ -2,7 +2,8 @@
 //
 // Two write modes:
 //   page_chain — SI protocol: writes directly into a pre-structured page chain,
-//                splitting source data across 4088-byte dest page boundaries.
+//                splitting source data across dest page boundaries (the data
+//                size of the receiving slot's page class).
 //   flat       — RI protocol / MR2 destination: writes contiguous raw bytes
 //                into a flat buffer; the receiver structures the page chain
 //                in-place (RI) or memcpys into MR1 pages afterward (MR2).
@@ -17,27 +18,29 @@ use anyhow::Result;
 use connect::SendChannel;
 use connect::ffi::ibv_sge;
 use connect::rdma::queue_pair::{MAX_SEND_SGE, SIGNAL_BATCH};
-use common::{PAGE_SIZE, ShmOffset};
+use common::{page_class_data_size, page_class_size, ShmOffset};
 
-use super::PAGE_DATA;
 use super::shm::SrcSge;
 
 /// RDMA WRITE source SGEs into a pre-structured remote page chain (SI).
 ///
-/// The source data is sliced to respect 4088-byte dest page boundaries and
-/// the 8-byte per-page header offset.  Uses a `Vec<ibv_sge>` per dest page
-/// so any number of source page fragments are handled correctly.  Each
-/// source SGE carries its own lkey, allowing the source to span MR1 and
-/// the sender-side extension / staging MRs.
+/// The source data is sliced to respect the dest pages' data size and the
+/// per-page header offset; both follow `dest_class`, the page class the
+/// receiver allocated its chain in.  Uses a `Vec<ibv_sge>` per dest page,
+/// posted as one WR per `MAX_SEND_SGE` fragments, so any number of source
+/// page fragments are handled correctly.  Each source SGE carries its own
+/// lkey, allowing the source to span MR1 and the sender-side extension /
+/// staging MRs.
 pub(super) fn rdma_write_page_chain(
     ch:              &SendChannel,
     src_sges:        &[SrcSge],
     remote_dest_off: u64,
+    dest_class:      u32,
     total_bytes:     ShmOffset,
 ) -> Result<()> {
-    let page_size_u64: u64 = PAGE_SIZE as u64;
+    let page_size_u64: u64 = page_class_size(dest_class) as u64;
     let page_header:   u64 = common::PAGE_HEADER_SIZE as u64;
-    let page_data_shm: ShmOffset = PAGE_DATA as ShmOffset;
+    let page_data_shm: ShmOffset = page_class_data_size(dest_class) as ShmOffset;
 
     let n_dest_pages = (total_bytes + page_data_shm - 1) / page_data_shm;
     let remote_rkey  = ch.remote_rkey;
@@ -49,6 +52,7 @@ pub(super) fn rdma_write_page_chain(
     if let Some(&(a, l, k)) = src_iter.next() { src_addr = a; src_remain = l; src_lkey = k; }
 
     let qp = ch.qp.lock().unwrap();
+    let mut posted = 0usize;
     for dest_idx in 0..n_dest_pages {
         let dest_chunk  = page_data_shm.min(total_bytes - dest_idx * page_data_shm) as u32;
         let remote_addr = ch.remote_mr_base
@@ -80,12 +84,20 @@ pub(super) fn rdma_write_page_chain(
         // Chunk the submission: every `SIGNAL_BATCH` WRs we issue a signaled
         // WR and poll its completion.  RC QPs drain in order, so the poll
         // implicitly acks every unsignaled WR in the batch, freeing queue
-        // slots before the next batch fills them.
-        let batch_boundary = ((dest_idx + 1) as usize) % SIGNAL_BATCH == 0;
-        let signal         = is_last || batch_boundary;
-        qp.post_rdma_write_sge_list(&mut sges, remote_addr, remote_rkey, signal)?;
-        if signal && !is_last {
-            qp.poll_one_blocking()?;
+        // slots before the next batch fills them.  A large dest page fed by
+        // small source pages takes one WR per `MAX_SEND_SGE` fragments.
+        let n_wrs       = sges.len().div_ceil(MAX_SEND_SGE);
+        let mut wr_addr = remote_addr;
+        for (i, chunk) in sges.chunks_mut(MAX_SEND_SGE).enumerate() {
+            let last_wr = is_last && i + 1 == n_wrs;
+            posted += 1;
+            let signal  = last_wr || posted % SIGNAL_BATCH == 0;
+            let len: u64 = chunk.iter().map(|s| s.length as u64).sum();
+            qp.post_rdma_write_sge_list(chunk, wr_addr, remote_rkey, signal)?;
+            if signal && !last_wr {
+                qp.poll_one_blocking()?;
+            }
+            wr_addr += len;
         }
     }
     qp.poll_one_blocking()?;
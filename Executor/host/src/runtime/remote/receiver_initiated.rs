@@ -17,7 +17,7 @@ use std::sync::atomic::Ordering;
 use anyhow::{anyhow, Result};
 use connect::{MeshNode, SendChannel, RecvChannel};
 use connect::rdma::exchange;
-use common::{PAGE_SIZE, Page, ShmOffset, Superblock};
+use common::{PAGE_CLASS_SMALL, PAGE_SIZE, Page, ShmOffset, Superblock};
 
 use crate::runtime::dag_runner::RemoteSlotKind;
 
@@ -129,6 +129,7 @@ pub(super) fn recv_ri(
         let page = unsafe { &mut *((splice_addr + page_off as usize) as *mut Page) };
         let data_in_page = PAGE_DATA.min(total_bytes - i as usize * PAGE_DATA);
         page.cursor.store(data_in_page as ShmOffset, Ordering::Relaxed);
+        page.class.store(PAGE_CLASS_SMALL, Ordering::Relaxed);
         let next = if i + 1 < n_pages as ShmOffset { dest_off + (i + 1) * PAGE_SIZE } else { 0 };
         page.next_offset.store(next as u64, Ordering::Relaxed);
     }
@@ -6,8 +6,9 @@
 //   SingleMr (MR1 fits):
 //     Sender                              Receiver
 //     ──────                              ────────
-//     TCP send(total_bytes)       ──▶      alloc MR1 page chain, link to slot
-//                                 ◀──      TCP send(DestReply::SingleMr)
+//     TCP send(total_bytes)       ──▶      alloc MR1 page chain in the slot's
+//                                          page class, link to slot
+//                                 ◀──      TCP send(DestReply::SingleMr{off,class})
 //     RDMA write into page chain  ──▶      HCA writes chunks into page[i].data
 //     TCP send_done               ──▶      worker can now read from slot
 //
@@ -27,7 +28,7 @@ use connect::rdma::exchange::DestReply;
 
 use crate::runtime::dag_runner::RemoteSlotKind;
 
-use super::shm::{collect_src_sges, alloc_and_link, alloc_and_link_from_buf};
+use super::shm::{collect_src_sges, alloc_and_link, alloc_and_link_from_buf, chain_span, slot_page_class};
 use super::rdma::{rdma_write_page_chain, rdma_write_flat_to};
 
 /// Return how many bytes of MR1 bump capacity are still free.
@@ -72,8 +73,8 @@ pub(super) fn send_si(
 
     // Phase 3: RDMA write to the receiver's allocation
     match reply {
-        DestReply::SingleMr { dest_off } => {
-            rdma_write_page_chain(ch, &src_sges, dest_off as u64, total_bytes)?;
+        DestReply::SingleMr { dest_off, page_class } => {
+            rdma_write_page_chain(ch, &src_sges, dest_off as u64, page_class as u32, total_bytes)?;
         }
         DestReply::UseMr2 { dest_off: _, addr, rkey } => {
             // Peer's receive overflowed its MR1 and routed to MR2.  The
@@ -117,13 +118,14 @@ pub(super) fn recv_si(
 
     if total_bytes == 0 { return Ok(()); }
 
+    let class = slot_page_class(splice_addr, slot, slot_kind);
     let remaining = mr1_bump_remaining(splice_addr);
-    if (total_bytes as u64) <= remaining {
+    if chain_span(total_bytes, class) <= remaining {
         // MR1 fits — unchanged path.
-        let dest_off = alloc_and_link(splice_addr, slot, slot_kind, total_bytes)?;
+        let dest_off = alloc_and_link(splice_addr, slot, slot_kind, total_bytes, class)?;
         exchange::send_dest_reply(
             &mut *ch.ctrl.lock().unwrap(),
-            &DestReply::SingleMr { dest_off },
+            &DestReply::SingleMr { dest_off, page_class: class as u8 },
         )?;
         exchange::wait_done(&mut *ch.ctrl.lock().unwrap())?;
         return Ok(());
@@ -155,7 +157,7 @@ pub(super) fn recv_si(
     // lands in direct-mode pages — Python workloads read them without
     // paged-mode resolution.
     let src_slice = unsafe { reservation.as_slice() };
-    alloc_and_link_from_buf(splice_addr, slot, slot_kind, src_slice, mesh)?;
+    alloc_and_link_from_buf(splice_addr, slot, slot_kind, src_slice, class, mesh)?;
     mesh.mr2_touch();
 
     Ok(())
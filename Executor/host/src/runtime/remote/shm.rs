@@ -4,13 +4,11 @@
 use std::sync::atomic::Ordering;
 
 use anyhow::{anyhow, Result};
-use common::{PAGE_SIZE, Page, PageId, ShmOffset, Superblock, DIRECT_LIMIT};
+use common::{page_class_data_size, page_class_size, Page, PageId, ShmOffset, Superblock, DIRECT_LIMIT};
 use connect::MeshNode;
 
 use crate::runtime::dag_runner::RemoteSlotKind;
 
-use super::PAGE_DATA;
-
 /// One entry in the source SGE list — `(host_vaddr, length, lkey)`.  The
 /// lkey is per-SGE because a single transfer may span multiple MRs:
 /// MR1 for pages < `INITIAL_SHM_SIZE`, MR-src-ext for pages past that,
@@ -19,7 +17,8 @@ pub(super) type SrcSge = (u64, u32, u32);
 
 /// Walk the page chain for `slot` and collect source SGEs.
 ///
-/// For each page:
+/// One SGE per page whatever its class, so a slot of 2 MiB pages needs
+/// 512× fewer SGEs than the same bytes in 4 KiB pages.  For each page:
 ///   * Direct-mode, offset < INITIAL_SHM_SIZE: MR1 lkey, no memcpy.
 ///   * Direct-mode, offset ≥ INITIAL_SHM_SIZE: lazily register/grow
 ///     MR-src-ext over the SHM extension, pick up its lkey.
@@ -38,8 +37,8 @@ pub(super) fn collect_src_sges(
 ) -> Result<(Vec<SrcSge>, ShmOffset)> {
     let sb = unsafe { &*(splice_addr as *const Superblock) };
     let head: PageId = match slot_kind {
-        RemoteSlotKind::Stream => sb.writer_heads[slot].load(Ordering::Acquire),
-        RemoteSlotKind::Io     => sb.io_heads[slot].load(Ordering::Acquire),
+        RemoteSlotKind::Stream => sb.writer_head(slot).load(Ordering::Acquire),
+        RemoteSlotKind::Io     => sb.io_head(slot).load(Ordering::Acquire),
     };
     let shm_base: u64 = splice_addr as u64;
     let mr1_end: u64 = shm_base + common::INITIAL_SHM_SIZE as u64;
@@ -72,10 +71,12 @@ pub(super) fn collect_src_sges(
                     ext_sge_idxs.push(src_sges.len());
                     src_sges.push((data_vaddr, used, 0)); // placeholder lkey
                 } else {
-                    return Err(anyhow!(
-                        "collect_src_sges: page {:#x}+{} straddles the MR1/MR-src-ext \
-                         boundary {:#x}", data_vaddr, used, mr1_end
-                    ));
+                    // A large page can straddle the end of MR1: one SGE per
+                    // side, the second with the deferred ext lkey.
+                    let head = (mr1_end - data_vaddr) as u32;
+                    src_sges.push((data_vaddr, head, mesh.mr1_lkey()));
+                    ext_sge_idxs.push(src_sges.len());
+                    src_sges.push((mr1_end, used - head, 0));
                 }
                 total_bytes += used as ShmOffset;
             }
@@ -117,17 +118,35 @@ pub(super) fn collect_src_sges(
     Ok((src_sges, total_bytes))
 }
 
-/// Bump-allocate `n_pages` in SHM, initialise each page's cursor and
-/// `next_offset`, and link the head/tail into `slot`.
+/// Page class new pages of `slot` are allocated in.
+pub(super) fn slot_page_class(splice_addr: usize, slot: usize, slot_kind: RemoteSlotKind) -> u32 {
+    let sb = unsafe { &*(splice_addr as *const Superblock) };
+    let class = match slot_kind {
+        RemoteSlotKind::Stream => sb.stream_page_class(slot),
+        RemoteSlotKind::Io     => sb.io_page_class(slot),
+    };
+    class.load(Ordering::Relaxed) as u32
+}
+
+/// Bytes of SHM a chain of `class` pages holding `total_bytes` occupies.
+pub(super) fn chain_span(total_bytes: usize, class: u32) -> u64 {
+    total_bytes.div_ceil(page_class_data_size(class)) as u64 * page_class_size(class) as u64
+}
+
+/// Bump-allocate `n_pages` of `class` in SHM, initialise each page's cursor,
+/// class and `next_offset`, and link the head/tail into `slot`.
 /// Returns the SHM offset of the first (head) page.
 pub(super) fn alloc_and_link(
     splice_addr: usize,
     slot:        usize,
     slot_kind:   RemoteSlotKind,
     total_bytes: usize,
+    class:       u32,
 ) -> Result<ShmOffset> {
-    let n_pages        = (total_bytes + PAGE_DATA - 1) / PAGE_DATA;
-    let bytes_to_alloc = (n_pages as ShmOffset) * PAGE_SIZE;
+    let page_size      = page_class_size(class);
+    let page_data      = page_class_data_size(class);
+    let n_pages        = total_bytes.div_ceil(page_data);
+    let bytes_to_alloc = (n_pages as ShmOffset) * page_size;
 
     let sb       = unsafe { &*(splice_addr as *const Superblock) };
     let dest_off = sb.bump_allocator.fetch_add(bytes_to_alloc, Ordering::AcqRel);
@@ -139,21 +158,22 @@ pub(super) fn alloc_and_link(
     }
 
     for i in 0..n_pages as ShmOffset {
-        let page_off = dest_off + i * PAGE_SIZE;
+        let page_off = dest_off + i * page_size;
         let page = unsafe { &mut *((splice_addr + page_off as usize) as *mut Page) };
-        let data_in_page = PAGE_DATA.min(total_bytes - i as usize * PAGE_DATA);
+        let data_in_page = page_data.min(total_bytes - i as usize * page_data);
         page.cursor.store(data_in_page as ShmOffset, Ordering::Relaxed);
-        let next = if i + 1 < n_pages as ShmOffset { dest_off + (i + 1) * PAGE_SIZE } else { 0 };
+        page.class.store(class, Ordering::Relaxed);
+        let next = if i + 1 < n_pages as ShmOffset { dest_off + (i + 1) * page_size } else { 0 };
         page.next_offset.store(next as u64, Ordering::Relaxed);
     }
 
-    let tail_off = dest_off + (n_pages as ShmOffset - 1) * PAGE_SIZE;
+    let tail_off = dest_off + (n_pages as ShmOffset - 1) * page_size;
     link_to_slot(sb, slot, slot_kind, dest_off, tail_off);
     Ok(dest_off)
 }
 
-/// Memcpy `src` into a newly-allocated SHM page chain and link it into
-/// `slot`.  Used by the MR2 receive path: after RDMA WRITE has landed
+/// Memcpy `src` into a newly-allocated SHM page chain of `class` pages and
+/// link it into `slot`.  Used by the MR2 receive path: after RDMA WRITE has landed
 /// bytes in an MR2 region (outside the guest's direct window), we pull
 /// them back into an SHM page chain so the slot can be read normally.
 ///
@@ -180,31 +200,32 @@ pub(super) fn alloc_and_link_from_buf(
     slot:        usize,
     slot_kind:   RemoteSlotKind,
     src:         &[u8],
+    class:       u32,
     mesh:        &MeshNode,
 ) -> Result<common::PageId> {
-    let total_bytes = src.len();
-    let n_pages     = (total_bytes + PAGE_DATA - 1) / PAGE_DATA;
-    if n_pages == 0 { return Ok(0); }
+    if src.is_empty() { return Ok(0); }
 
     if mesh.python_compat() {
-        alloc_and_link_from_buf_direct(splice_addr, slot, slot_kind, src, mesh)
+        alloc_and_link_from_buf_direct(splice_addr, slot, slot_kind, src, class, mesh)
             .map(|off| off as common::PageId)
     } else {
-        alloc_and_link_from_buf_reclaimer(splice_addr, slot, slot_kind, src)
+        alloc_and_link_from_buf_reclaimer(splice_addr, slot, slot_kind, src, class)
     }
 }
 
-/// Rust-only path: uses `reclaimer::alloc_page` (free-list → direct bump →
-/// paged-mode fallback).  Chain may contain paged-mode PageIds; Rust
-/// guests handle them via `ResolutionBuffer`.
+/// Rust-only path: uses `reclaimer::alloc_page_in` (free-list → direct
+/// bump → paged-mode fallback for 4 KiB pages).  Chain may contain
+/// paged-mode PageIds; Rust guests handle them via `ResolutionBuffer`.
 fn alloc_and_link_from_buf_reclaimer(
     splice_addr: usize,
     slot:        usize,
     slot_kind:   RemoteSlotKind,
     src:         &[u8],
+    class:       u32,
 ) -> Result<common::PageId> {
     let total_bytes = src.len();
-    let n_pages     = (total_bytes + PAGE_DATA - 1) / PAGE_DATA;
+    let page_data   = page_class_data_size(class);
+    let n_pages     = total_bytes.div_ceil(page_data);
     let sb          = unsafe { &*(splice_addr as *const Superblock) };
 
     let mut head_id: common::PageId = 0;
@@ -212,13 +233,13 @@ fn alloc_and_link_from_buf_reclaimer(
     let mut tail_id: common::PageId = 0;
 
     for i in 0..n_pages {
-        let id = crate::runtime::mem_operation::reclaimer::alloc_page(splice_addr)
+        let id = crate::runtime::mem_operation::reclaimer::alloc_page_in(splice_addr, class)
             .map_err(|e| anyhow!("MR2 memcpy-back: alloc_page: {}", e))?;
         let page_ptr = crate::runtime::extended_pool::runtime::resolve(id, splice_addr)
             .map_err(|e| anyhow!("MR2 memcpy-back: resolve {:#x}: {}", id, e))?;
 
-        let src_start    = i * PAGE_DATA;
-        let data_in_page = PAGE_DATA.min(total_bytes - src_start);
+        let src_start    = i * page_data;
+        let data_in_page = page_data.min(total_bytes - src_start);
 
         unsafe {
             let page = &mut *page_ptr;
@@ -253,11 +274,14 @@ fn alloc_and_link_from_buf_direct(
     slot:        usize,
     slot_kind:   RemoteSlotKind,
     src:         &[u8],
+    class:       u32,
     mesh:        &MeshNode,
 ) -> Result<ShmOffset> {
     let total_bytes    = src.len();
-    let n_pages        = (total_bytes + PAGE_DATA - 1) / PAGE_DATA;
-    let bytes_to_alloc = (n_pages as ShmOffset) * PAGE_SIZE;
+    let page_size      = page_class_size(class);
+    let page_data      = page_class_data_size(class);
+    let n_pages        = total_bytes.div_ceil(page_data);
+    let bytes_to_alloc = (n_pages as ShmOffset) * page_size;
 
     let sb = unsafe { &*(splice_addr as *const Superblock) };
 
@@ -276,10 +300,10 @@ fn alloc_and_link_from_buf_direct(
     };
 
     for i in 0..n_pages {
-        let page_off     = dest_off + i as ShmOffset * PAGE_SIZE;
+        let page_off     = dest_off + i as ShmOffset * page_size;
         let page         = unsafe { &mut *((splice_addr + page_off as usize) as *mut Page) };
-        let src_start    = i * PAGE_DATA;
-        let data_in_page = PAGE_DATA.min(total_bytes - src_start);
+        let src_start    = i * page_data;
+        let data_in_page = page_data.min(total_bytes - src_start);
 
         unsafe {
             std::ptr::copy_nonoverlapping(
@@ -290,13 +314,14 @@ fn alloc_and_link_from_buf_direct(
         }
 
         page.cursor.store(data_in_page as ShmOffset, Ordering::Relaxed);
+        page.class.store(class, Ordering::Relaxed);
         let next = if i + 1 < n_pages {
-            dest_off + (i + 1) as ShmOffset * PAGE_SIZE
+            dest_off + (i + 1) as ShmOffset * page_size
         } else { 0 };
         page.next_offset.store(next as u64, Ordering::Relaxed);
     }
 
-    let tail_off = dest_off + (n_pages as ShmOffset - 1) * PAGE_SIZE;
+    let tail_off = dest_off + (n_pages as ShmOffset - 1) * page_size;
     link_to_slot(sb, slot, slot_kind, dest_off, tail_off);
     Ok(dest_off)
 }
@@ -324,12 +349,12 @@ pub(super) fn link_to_slot_pageid(
 ) {
     match kind {
         RemoteSlotKind::Stream => {
-            sb.writer_heads[slot].store(head, Ordering::Release);
-            sb.writer_tails[slot].store(tail, Ordering::Release);
+            sb.writer_head(slot).store(head, Ordering::Release);
+            sb.writer_tail(slot).store(tail, Ordering::Release);
         }
         RemoteSlotKind::Io => {
-            sb.io_heads[slot].store(head, Ordering::Release);
-            sb.io_tails[slot].store(tail, Ordering::Release);
+            sb.io_head(slot).store(head, Ordering::Release);
+            sb.io_tail(slot).store(tail, Ordering::Release);
         }
     }
 }
@@ -24,7 +24,7 @@ use std::thread;
 use std::time::Duration;
 use wasmtime::*;
 
-use common::{Superblock, REGISTRY_OFFSET, REGISTRY_SIZE, TARGET_OFFSET, WASM_PATH};
+use common::{Superblock, TARGET_OFFSET, WASM_PATH};
 
 use crate::runtime::mem_operation::organizer::BucketOrganizer;
 use crate::policy::{MajorityWinsPolicy, MaxIdWinsPolicy};
@@ -55,6 +55,7 @@ pub fn run_worker(role: &str, shm_path: &str, id: u32) -> Result<()> {
         WorkerState {
             file: file.try_clone()?,
             splice_addr: 0,
+            atomics: None,
         },
     );
 
@@ -79,10 +80,11 @@ pub fn run_worker(role: &str, shm_path: &str, id: u32) -> Result<()> {
             let packed_ptr_len = read_snapshot.call(&mut store, id)?;
 
             // Direct Host Read (Bypassing Wasm)
-            // Note: We skip the Registry area to read the first atomic variable (Index 0)
+            // Note: We read the first atomic variable (Index 0) from the atomic arena
             let base_ptr = memory.data_ptr(&store);
             let global_atomic_val = unsafe {
-                let offset = TARGET_OFFSET + (REGISTRY_OFFSET as usize) + (REGISTRY_SIZE as usize);
+                let layout = &(*(base_ptr.add(TARGET_OFFSET) as *const Superblock)).layout;
+                let offset = TARGET_OFFSET + layout.atomic_offset(0) as usize;
                 let ptr = base_ptr.add(offset) as *const std::sync::atomic::AtomicU64;
                 (*ptr).load(std::sync::atomic::Ordering::SeqCst)
             };
@@ -142,7 +144,7 @@ pub fn run_worker(role: &str, shm_path: &str, id: u32) -> Result<()> {
                 .log_offset.load(Ordering::Acquire);
 
             if log_offset > 0 {
-                let log_data_ptr = unsafe { target_base.add(common::LOG_ARENA_OFFSET as usize) };
+                let log_data_ptr = unsafe { target_base.add((*(target_base as *const Superblock)).layout.log_arena_offset as usize) };
                 let log_bytes =
                     unsafe { std::slice::from_raw_parts(log_data_ptr, log_offset as usize) };
                 let log_str = String::from_utf8_lossy(log_bytes);
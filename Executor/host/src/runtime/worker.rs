@@ -1,15 +1,19 @@
 use anyhow::Result;
 use std::fs::{File, OpenOptions};
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicU32, Ordering};
 use wasmtime::*;
 
-use crate::shm::{expand_mapping, map_into_memory};
+use crate::runtime::remote::atomics::{AtomicClient, AtomicOp, AtomicRequest};
+use crate::shm::{expand_mapping, map_into_memory, verify_mapped_layout};
 
-use common::{RegistryEntry, Superblock, REGISTRY_OFFSET, TARGET_OFFSET};
+use common::{RegistryEntry, RingHeader, Superblock, SHM_LAYOUT_HASH, TARGET_OFFSET};
 
 pub struct WorkerState {
     pub file: File,
     pub splice_addr: usize,
+    /// Connection to the runner's atomics service, opened by the first
+    /// remote-atomic import the guest calls.
+    pub atomics: Option<AtomicClient>,
 }
 
 /// Creates a Wasmtime engine configured for shared memory:
@@ -101,6 +105,18 @@ pub fn setup_vma_environment(
     }
 
     map_into_memory(file, splice_addr, map_size)?;
+    // Refuse a region formatted by a build with a different layout before
+    // any host import or guest code can touch it.
+    verify_mapped_layout(splice_addr, map_size)?;
+    // Run with the engine tunables the region was formatted with (no-op in
+    // the DAG runner, which installed them before formatting).
+    crate::runtime::engine_config::adopt_from_region(splice_addr)?;
+    // Zero-copy inputs loaded so far are overlays in the runner's mapping
+    // only; install them here too.
+    crate::runtime::input_output::mapped_input::apply(splice_addr)?;
+    // The mapping above predates the adopted huge-page mode; later remaps
+    // advise themselves.
+    crate::shm::advise_huge_pages(splice_addr, map_size)?;
 
     linker.func_wrap(
         "env",
@@ -123,68 +139,42 @@ pub fn setup_vma_environment(
     linker.func_wrap(
         "env",
         "host_resolve_atomic",
-        move |caller: Caller<'_, WorkerState>, ptr: u32, len: u32| -> u32 {
+        move |caller: Caller<'_, WorkerState>, ptr: u32, len: u32| -> Result<u32> {
             let base_ptr = memory_handle.data_ptr(&caller);
             let name_bytes =
                 unsafe { std::slice::from_raw_parts(base_ptr.add(ptr as usize), len as usize) };
 
-            let name_len = std::cmp::min(name_bytes.len(), 52);
-            let mut entry_name = [0u8; 52];
-            entry_name[..name_len].copy_from_slice(&name_bytes[..name_len]);
-
-            let host_base = unsafe { memory_handle.data_ptr(&caller).add(TARGET_OFFSET as usize) };
-            let superblock = unsafe { &*(host_base as *const Superblock) };
-            let lock = &superblock.registry_lock;
-            let registry_base = unsafe { host_base.add(REGISTRY_OFFSET as usize) };
-
-            // Spinlock acquire
-            while lock
-                .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
-                .is_err()
-            {
-                std::hint::spin_loop();
-            }
-
-            let mut result_index = u32::MAX;
-            let next_idx_atomic = &superblock.next_atomic_idx;
-            let current_count = next_idx_atomic.load(Ordering::Relaxed);
-
-            for i in 0..current_count {
-                let entry_ptr = unsafe {
-                    registry_base.add(i as usize * std::mem::size_of::<RegistryEntry>()) as *const RegistryEntry
-                };
-                let entry = unsafe { &*entry_ptr };
-                if entry.name == entry_name {
-                    result_index = entry.index;
-                    break;
-                }
-            }
-
-            if result_index == u32::MAX {
-                result_index = current_count;
-
-                let registry_entry_base = registry_base as *mut RegistryEntry;
-                let new_entry_ptr = unsafe { registry_entry_base.add(current_count as usize) };
-
-                unsafe {
-                    core::ptr::write(
-                        new_entry_ptr,
-                        RegistryEntry {
-                            name: entry_name,
-                            index: result_index,
-                            payload_offset: common::AtomicShmOffset::new(0),
-                            payload_len: core::sync::atomic::AtomicU32::new(0),
-                        },
-                    );
-                }
-
-                next_idx_atomic.store(current_count + 1, Ordering::Relaxed);
-            }
-
-            // Spinlock release
-            lock.store(0, Ordering::Release);
+            resolve_atomic(caller.data().splice_addr, name_bytes)
+        },
+    )?;
 
-            result_index
+    // ── Remote atomics (runner's atomics service) ───────────────────────────
+    //
+    // `host_remote_faa(peer, ptr, len, add)`, `host_remote_cas(peer, ptr, len,
+    // compare, swap)` and `host_remote_read_atomic(peer, ptr, len)` operate on
+    // the named atomic owned by mesh node `peer` and return its previous
+    // value.  The name is resolved in the local registry and the request goes
+    // to the runner's atomics service (see runtime/remote/atomics.rs), which
+    // this worker connects to on first use.
+    linker.func_wrap(
+        "env",
+        "host_remote_faa",
+        move |mut caller: Caller<'_, WorkerState>, peer: u32, ptr: u32, len: u32, add: u64| -> Result<u64> {
+            remote_atomic(&mut caller, memory_handle, AtomicOp::FetchAdd, peer, (ptr, len), add, 0)
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "host_remote_cas",
+        move |mut caller: Caller<'_, WorkerState>, peer: u32, ptr: u32, len: u32, compare: u64, swap: u64| -> Result<u64> {
+            remote_atomic(&mut caller, memory_handle, AtomicOp::CompareSwap, peer, (ptr, len), compare, swap)
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "host_remote_read_atomic",
+        move |mut caller: Caller<'_, WorkerState>, peer: u32, ptr: u32, len: u32| -> Result<u64> {
+            remote_atomic(&mut caller, memory_handle, AtomicOp::Read, peer, (ptr, len), 0, 0)
         },
     )?;
 
@@ -200,7 +190,7 @@ pub fn setup_vma_environment(
         |caller: Caller<'_, WorkerState>, barrier_id: u32, party_count: u32| {
             let splice_addr = caller.data().splice_addr;
             let sb = unsafe { &*(splice_addr as *const Superblock) };
-            let barrier = &sb.barriers[barrier_id as usize];
+            let barrier = sb.barrier(barrier_id as usize);
 
             // Arrive: atomically increment the counter.
             let arrived = barrier.fetch_add(1, Ordering::AcqRel) + 1;
@@ -239,6 +229,57 @@ pub fn setup_vma_environment(
         },
     )?;
 
+    // ── Stream signals (futex-backed) ───────────────────────────────────────
+    //
+    // `host_stream_wait(slot, observed, timeout_ms)` sleeps on the slot's
+    // signal word while it still equals `observed` (the guest has already set
+    // STREAM_WAITERS in it).  `timeout_ms == u32::MAX` waits without a
+    // deadline.  Returns the milliseconds left of the timeout, 0 once it has
+    // expired.  Wakeups may be spurious; the guest re-checks the stream.
+    linker.func_wrap(
+        "env",
+        "host_stream_wait",
+        |caller: Caller<'_, WorkerState>, slot: u32, observed: u32, timeout_ms: u32| -> u32 {
+            let sb = unsafe { &*(caller.data().splice_addr as *const Superblock) };
+            futex_wait(sb.stream_signal(slot as usize), observed, timeout_ms)
+        },
+    )?;
+
+    // `host_stream_wake(slot)` wakes every consumer asleep on the slot.  The
+    // guest calls it only when an append or close found STREAM_WAITERS set.
+    linker.func_wrap(
+        "env",
+        "host_stream_wake",
+        |caller: Caller<'_, WorkerState>, slot: u32| {
+            let sb = unsafe { &*(caller.data().splice_addr as *const Superblock) };
+            futex_wake(sb.stream_signal(slot as usize));
+        },
+    )?;
+
+    // ── Ring slots (futex-backed) ───────────────────────────────────────────
+    //
+    // A producer whose ring is full sleeps on the ring's `space` word with
+    // `host_ring_wait(slot, observed)`; a consumer that moves past a page
+    // wakes it with `host_ring_wake(slot)` (see "Ring slots" in `common`).
+    linker.func_wrap(
+        "env",
+        "host_ring_wait",
+        |caller: Caller<'_, WorkerState>, slot: u32, observed: u32| {
+            if let Some(ring) = ring_header(caller.data().splice_addr, slot) {
+                futex_wait(&ring.space, observed, u32::MAX);
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "host_ring_wake",
+        |caller: Caller<'_, WorkerState>, slot: u32| {
+            if let Some(ring) = ring_header(caller.data().splice_addr, slot) {
+                futex_wake(&ring.space);
+            }
+        },
+    )?;
+
     linker.define(&mut *store, "env", "memory", memory)?;
 
     // ── WASI stubs ────────────────────────────────────────────────────────────
@@ -270,6 +311,165 @@ pub fn setup_vma_environment(
     Ok(memory)
 }
 
+/// Index of the named atomic `name` in the SHM Registry, registering it on
+/// first use.  Backs `host_resolve_atomic` and the remote-atomic imports.
+fn resolve_atomic(splice_addr: usize, name_bytes: &[u8]) -> Result<u32> {
+    let name_len = std::cmp::min(name_bytes.len(), 52);
+    let mut entry_name = [0u8; 52];
+    entry_name[..name_len].copy_from_slice(&name_bytes[..name_len]);
+
+    let host_base = splice_addr as *mut u8;
+    let superblock = unsafe { &*(host_base as *const Superblock) };
+    let lock = &superblock.registry_lock;
+    let registry_base = unsafe { host_base.add(superblock.layout.registry_offset as usize) };
+
+    // Spinlock acquire
+    while lock
+        .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        std::hint::spin_loop();
+    }
+
+    let mut result_index = u32::MAX;
+    let next_idx_atomic = &superblock.next_atomic_idx;
+    let current_count = next_idx_atomic.load(Ordering::Relaxed);
+
+    for i in 0..current_count {
+        let entry_ptr = unsafe {
+            registry_base.add(i as usize * std::mem::size_of::<RegistryEntry>()) as *const RegistryEntry
+        };
+        let entry = unsafe { &*entry_ptr };
+        if entry.name == entry_name {
+            result_index = entry.index;
+            break;
+        }
+    }
+
+    if result_index == u32::MAX {
+        if current_count as usize >= superblock.layout.named_atomic_capacity() {
+            lock.store(0, Ordering::Release);
+            return Err(anyhow::anyhow!(
+                "host_resolve_atomic: registry full ({} named atomics); \
+                 raise registry_size / atomic_arena_size",
+                current_count,
+            ));
+        }
+        result_index = current_count;
+
+        let registry_entry_base = registry_base as *mut RegistryEntry;
+        let new_entry_ptr = unsafe { registry_entry_base.add(current_count as usize) };
+
+        unsafe {
+            core::ptr::write(
+                new_entry_ptr,
+                RegistryEntry {
+                    name: entry_name,
+                    index: result_index,
+                    payload_offset: common::AtomicShmOffset::new(0),
+                    payload_len: core::sync::atomic::AtomicU32::new(0),
+                },
+            );
+        }
+
+        next_idx_atomic.store(current_count + 1, Ordering::Relaxed);
+    }
+
+    // Spinlock release
+    lock.store(0, Ordering::Release);
+
+    Ok(result_index)
+}
+
+/// Runs `op` on the atomic named by the guest string at `name` (pointer,
+/// length) on mesh node `peer` through the runner's atomics service.
+fn remote_atomic(
+    caller: &mut Caller<'_, WorkerState>,
+    memory: Memory,
+    op: AtomicOp,
+    peer: u32,
+    name: (u32, u32),
+    a: u64,
+    b: u64,
+) -> Result<u64> {
+    let name_bytes = unsafe {
+        std::slice::from_raw_parts(memory.data_ptr(&*caller).add(name.0 as usize), name.1 as usize)
+    };
+    let index = resolve_atomic(caller.data().splice_addr, name_bytes)?;
+    let state = caller.data_mut();
+    if state.atomics.is_none() {
+        state.atomics = Some(AtomicClient::from_env()?);
+    }
+    let req = AtomicRequest { op, peer, index, a, b };
+    state.atomics.as_mut().unwrap().call(&req).map_err(|e| anyhow::anyhow!(
+        "remote atomic '{}' on node {}: {:#}", String::from_utf8_lossy(name_bytes), peer, e
+    ))
+}
+
+/// Sleeps on `word` while it equals `observed`, for at most `timeout_ms`
+/// (`u32::MAX`: no deadline).  Returns the milliseconds left of the timeout
+/// (`u32::MAX` without one), 0 once it has expired.
+fn futex_wait(word: &AtomicU32, observed: u32, timeout_ms: u32) -> u32 {
+    let start = std::time::Instant::now();
+    let timeout = (timeout_ms != u32::MAX).then(|| libc::timespec {
+        tv_sec: (timeout_ms / 1000) as libc::time_t,
+        tv_nsec: (timeout_ms % 1000) as libc::c_long * 1_000_000,
+    });
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word as *const _ as *const libc::c_int,
+            libc::FUTEX_WAIT,
+            observed as libc::c_int,
+            timeout.as_ref().map_or(std::ptr::null(), |t| t as *const libc::timespec),
+        );
+    }
+    if timeout_ms == u32::MAX {
+        return u32::MAX;
+    }
+    let elapsed = start.elapsed().as_millis().min(u32::MAX as u128) as u32;
+    timeout_ms.saturating_sub(elapsed)
+}
+
+/// Wakes every process asleep on `word`.
+fn futex_wake(word: &AtomicU32) {
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word as *const _ as *const libc::c_int,
+            libc::FUTEX_WAKE,
+            i32::MAX,
+            std::ptr::null::<libc::timespec>(),
+        );
+    }
+}
+
+/// Ring header of stream `slot`, `None` if the slot is not a ring.
+fn ring_header(splice_addr: usize, slot: u32) -> Option<&'static RingHeader> {
+    let sb = unsafe { &*(splice_addr as *const Superblock) };
+    let header = sb.stream_ring(slot as usize).load(Ordering::Acquire);
+    (header != 0).then(|| unsafe { &*((splice_addr + header as usize) as *const RingHeader) })
+}
+
+/// Compares the layout hash the guest module was compiled against (its
+/// `shm_layout_hash` export) with this host's.  Modules without the export —
+/// `python.wasm`, older guest builds — are let through; the superblock check
+/// in `setup_vma_environment` still covers the host side.
+pub fn check_guest_layout(store: &mut Store<WorkerState>, instance: &Instance, wasm_path: &str) -> Result<()> {
+    let Ok(f) = instance.get_typed_func::<(), u64>(&mut *store, "shm_layout_hash") else {
+        return Ok(());
+    };
+    let guest = f.call(&mut *store, ())?;
+    if guest != SHM_LAYOUT_HASH {
+        return Err(anyhow::anyhow!(
+            "guest '{}' was built for SHM layout {:#018x}, host expects {:#018x}; \
+             rebuild the guest against the same common/src/lib.rs",
+            wasm_path, guest, SHM_LAYOUT_HASH,
+        ));
+    }
+    Ok(())
+}
+
 /// Loads the guest WASM module and enters a persistent call loop.
 ///
 /// Reads lines from stdin with format `"<arg0> <arg1>\n"`, calls
@@ -285,11 +485,13 @@ pub fn run_wasm_loop(shm_path: &str, wasm_path: &str, func: &str) -> Result<()>
     let mut store = Store::new(&engine, WorkerState {
         file: file.try_clone()?,
         splice_addr: 0,
+        atomics: None,
     });
     let mut linker = Linker::new(&engine);
     let memory = setup_vma_environment(&mut store, &mut linker, &file)?;
     let module = load_guest_module(&engine, wasm_path)?;
     let instance = linker.instantiate(&mut store, &module)?;
+    check_guest_layout(&mut store, &instance, wasm_path)?;
     let f = instance
         .get_typed_func::<(u32, u32), ()>(&mut store, func)
         .map_err(|e| anyhow::anyhow!("no export '{}': {}", func, e))?;
@@ -305,6 +507,8 @@ pub fn run_wasm_loop(shm_path: &str, wasm_path: &str, func: &str) -> Result<()>
         let mut parts = line.split_whitespace();
         let arg0: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
         let arg1: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+        // The runner may have loaded a zero-copy input since the last call.
+        crate::runtime::input_output::mapped_input::sync(store.data().splice_addr)?;
         match f.call(&mut store, (arg0, arg1)) {
             Ok(())  => writeln!(out, "ok")?,
             Err(e)  => writeln!(out, "err: {}", e)?,
@@ -331,11 +535,13 @@ pub fn run_wasm_call(shm_path: &str, wasm_path: &str, func: &str, ret_type: &str
     let mut store = Store::new(&engine, WorkerState {
         file: file.try_clone()?,
         splice_addr: 0,
+        atomics: None,
     });
     let mut linker = Linker::new(&engine);
     let memory = setup_vma_environment(&mut store, &mut linker, &file)?;
     let module = load_guest_module(&engine, wasm_path)?;
     let instance = linker.instantiate(&mut store, &module)?;
+    check_guest_layout(&mut store, &instance, wasm_path)?;
 
     match ret_type {
         "void2" => {
@@ -20,6 +20,10 @@ use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 
+use common::{split_typed_record, WORD_COUNT_SCHEMA};
+
+use crate::runtime::input_output::typed_record::RecordSchema;
+
 /// `host split <input> <N> <out_prefix>`
 ///
 /// Splits `input` into `N` shards on line boundaries, so no record is torn
@@ -148,13 +152,29 @@ pub fn run_merge(reducer: &str, output: &str, partials: &[String]) -> Result<()>
 /// `total_occurrences` are summed; `unique_words` is recomputed from the merged
 /// map. Any header line we don't recognise is ignored (the body is the source of
 /// truth). Word lines are `"<word>: <count>"`.
+///
+/// A partial may instead be the raw `wc_map` output — typed `WordCount`
+/// records saved with `framing: "length_prefixed"`.  Each record then counts
+/// as one map record; a typed partial of any other schema is an error rather
+/// than a silently empty contribution.
 fn merge_wordcount(partials: &[String]) -> Result<String> {
     let mut counts: BTreeMap<String, u64> = BTreeMap::new();
     let mut map_records_received: u64 = 0;
+    let schema = RecordSchema::parse(WORD_COUNT_SCHEMA)?;
 
     for path in partials {
-        let f = File::open(path).with_context(|| format!("open partial: {}", path))?;
-        for line in BufReader::new(f).lines() {
+        let bytes = std::fs::read(path).with_context(|| format!("open partial: {}", path))?;
+        if let Some(records) = typed_records(&bytes) {
+            for (i, rec) in records.into_iter().enumerate() {
+                let value = schema.decode(rec)
+                    .with_context(|| format!("partial {} record {}", path, i))?;
+                let word = value["word"].as_str().unwrap_or_default();
+                *counts.entry(word.to_string()).or_insert(0) += value["count"].as_u64().unwrap_or(0);
+                map_records_received += 1;
+            }
+            continue;
+        }
+        for line in bytes.lines() {
             let line = line.with_context(|| format!("read partial line: {}", path))?;
             let line = line.trim();
             if line.is_empty() || line.starts_with("=== ") {
@@ -197,6 +217,22 @@ fn merge_wordcount(partials: &[String]) -> Result<String> {
     Ok(out)
 }
 
+/// The records of a length-prefixed partial (`[len: u32 LE][payload]` frames
+/// ending exactly at EOF) whose first record is typed; `None` for anything
+/// else, e.g. a text partial.
+fn typed_records(bytes: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut records = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let len = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let rec = rest.get(4..4 + len)?;
+        records.push(rec);
+        rest = &rest[4 + len..];
+    }
+    split_typed_record(records.first()?)?;
+    Some(records)
+}
+
 /// Generic counter reducer for `key=value` outputs (e.g. MediaReview's
 /// `total_events=…`, `login_ok=…`). Sums every numeric `key=value` line across
 /// partials; the first `=== header ===` line seen is preserved. Non-numeric
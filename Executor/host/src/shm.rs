@@ -1,7 +1,9 @@
 use anyhow::{anyhow, Result};
-use nix::sys::mman::{mmap, MapFlags, ProtFlags};
+use nix::sys::mman::{madvise, mmap, munmap, MapFlags, MmapAdvise, ProtFlags};
+use nix::sys::statfs::{fstatfs, HUGETLBFS_MAGIC};
+use std::collections::BTreeMap;
 use std::fs::{File, OpenOptions};
-use std::io::Write;
+use std::mem::size_of;
 use std::num::NonZeroUsize;
 use std::sync::{Mutex, OnceLock};
 use std::sync::atomic::{AtomicU32, Ordering};
@@ -10,34 +12,98 @@ use common::*;
 
 /// Creates and initializes the shared memory file at `path`.
 /// Truncates the file to `INITIAL_SHM_SIZE` and writes the superblock header fields,
-/// zeroing all atomic counters and pointers so they are ready for first use.
+/// zeroing all atomic counters and pointers so they are ready for first use, and
+/// stamps the layout descriptor ([`common::ShmLayout`]) that every attaching
+/// process verifies through [`verify_mapped_layout`], followed by the process's
+/// [`common::EngineConfig`].  The slot counts and arena sizes come from that
+/// config, so the descriptor fixes the region's geometry.
 pub fn format_shared_memory(path: &str) -> Result<()> {
-    let mut file = OpenOptions::new()
+    let file = OpenOptions::new()
         .read(true).write(true).create(true).truncate(true).open(path)?;
 
+    // Engine tunables this process resolved; attaching workers adopt them.
+    let engine = crate::runtime::engine_config::current();
+    check_huge_page_backing(&file, engine.huge_pages, path)?;
+
     file.set_len(INITIAL_SHM_SIZE as u64)?;
 
-    let magic: u32 = 0xDEADBEEF;
     let global_capacity: ShmOffset = INITIAL_SHM_SIZE;
+    let layout = ShmLayout::for_config(engine);
+    let layout_offset = std::mem::offset_of!(Superblock, layout);
+
+    // The fields are stored through a temporary mapping of the Superblock
+    // rather than write(2), which hugetlbfs does not support.  The mapping
+    // length is rounded to the huge-page size so it is valid there too.
+    let head_len = (layout.superblock_size as usize).next_multiple_of(HUGE_PAGE_SIZE as usize);
+    let head_ptr = unsafe {
+        mmap(
+            None,
+            NonZeroUsize::new(head_len).unwrap(),
+            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+            MapFlags::MAP_SHARED,
+            Some(&file),
+            0,
+        )?
+    };
+    let head = unsafe { std::slice::from_raw_parts_mut(head_ptr as *mut u8, head_len) };
+    let mut put = |offset: usize, bytes: &[u8]| head[offset..offset + bytes.len()].copy_from_slice(bytes);
 
-    // Initialize Superblock fields.  Only `magic` and `bump_allocator` need
-    // non-zero starts; every other field (including the widened
-    // `AtomicPageId` slot arrays) is already zero from `set_len`.  Keeping
-    // just the two explicit writes avoids re-encoding the byte layout here
-    // and lets `common::Superblock`'s compile-time offset asserts remain
-    // the single source of truth.
-    file.write_all(&magic.to_le_bytes())?;                       // magic            u32 @ 0
-    file.write_all(&BUMP_ALLOCATOR_START.to_le_bytes())?;        // bump_allocator   u32 @ 4
-    file.write_all(&global_capacity.to_le_bytes())?;             // global_capacity  u32 @ 8
-    // Everything from byte 12 onward is zero from set_len: log_offset,
-    // registry_lock, next_atomic_idx, shared_map_base, free_list_heads,
-    // writer_heads, writer_tails, io_heads, io_tails, barriers.
+    // Initialize Superblock fields.  Only `magic`, `bump_allocator`,
+    // `global_capacity`, the layout descriptor and the engine block need
+    // non-zero starts; every other field (including the slot table) is
+    // already zero from `set_len`.  Offsets come from `common::Superblock`,
+    // whose compile-time asserts remain the single source of truth.
+    put(0, &SHM_MAGIC.to_le_bytes());                             // magic            u32 @ 0
+    put(4, &layout.bump_allocator_start.to_le_bytes());           // bump_allocator   u32 @ 4
+    put(8, &global_capacity.to_le_bytes());                       // global_capacity  u32 @ 8
+    // Bytes 12..28 stay zero: log_offset, registry_lock, next_atomic_idx,
+    // shared_map_base.
+    put(std::mem::offset_of!(Superblock, layout_offset),
+        &(layout_offset as ShmOffset).to_le_bytes());              // layout_offset    u32 @ 28
+    put(layout_offset, &layout.to_le_bytes());                    // layout (after free lists)
+    let engine_bytes = unsafe {
+        std::slice::from_raw_parts(engine as *const EngineConfig as *const u8, size_of::<EngineConfig>())
+    };
+    put(std::mem::offset_of!(Superblock, engine), engine_bytes);  // engine (after layout)
 
+    unsafe { munmap(head_ptr, head_len)?; }
     Ok(())
 }
+
+/// Verifies the layout descriptor of the region mapped at `splice_addr`
+/// (`mapped` bytes long) against this build (see [`check_shm_layout`]).
+///
+/// Called before anything else touches a freshly attached mapping, so a host,
+/// guest or worker built with a different `PAGE_SIZE` or Superblock shape, or
+/// a region with a damaged descriptor, fails loudly instead of corrupting the
+/// region.
+pub fn verify_mapped_layout(splice_addr: usize, mapped: usize) -> Result<()> {
+    let head = unsafe { std::slice::from_raw_parts(splice_addr as *const u8, mapped) };
+    check_shm_layout(head).map(|_| ()).map_err(|e| anyhow!(
+        "SHM layout check failed: {} (every process attached to a region must be \
+         built from the same common/src/lib.rs)", e,
+    ))
+}
+
+/// Layout descriptor of the region mapped at `splice_addr`.  The region must
+/// have passed [`verify_mapped_layout`] (or have been formatted by this
+/// process); every slot-table and arena offset is read from here.
+pub fn layout_at(splice_addr: usize) -> &'static ShmLayout {
+    unsafe { &(*(splice_addr as *const Superblock)).layout }
+}
+
 /// Maps `file` into the process address space at the fixed virtual address `addr` with
 /// `MAP_SHARED | MAP_FIXED`, making the shared memory region visible to both host and WASM guest.
+///
+/// A file on hugetlbfs can only be mapped at a huge-page-aligned address; in
+/// THP mode the mapping is advised with `MADV_HUGEPAGE` (see [`advise_huge_pages`]).
 pub fn map_into_memory(file: &File, addr: usize, size: usize) -> Result<()> {
+    if !addr.is_multiple_of(HUGE_PAGE_SIZE as usize) && hugetlbfs_page_size(file)?.is_some() {
+        return Err(anyhow!(
+            "SHM file is on hugetlbfs but the mapping address {:#x} is not {} MiB aligned",
+            addr, HUGE_PAGE_SIZE / MIB,
+        ));
+    }
     unsafe {
         mmap(
             NonZeroUsize::new(addr),
@@ -48,7 +114,7 @@ pub fn map_into_memory(file: &File, addr: usize, size: usize) -> Result<()> {
             0,
         )?;
     }
-    Ok(())
+    advise_huge_pages(addr, size)
 }
 
 /// Expands the shared memory backing file to `new_size` and remaps the VMA in-place.
@@ -57,7 +123,100 @@ pub fn expand_mapping(file: &File, addr: usize, new_size: usize) -> Result<()> {
     // 1. Expand the underlying file
     file.set_len(new_size as u64)?;
     // 2. Use MAP_FIXED to expand the VMA in place
-    map_into_memory(file, addr, new_size)
+    map_into_memory(file, addr, new_size)?;
+    // 3. The remap replaced any zero-copy input overlays; put them back
+    crate::runtime::input_output::mapped_input::apply(addr)
+}
+
+// ── Huge pages ────────────────────────────────────────────────────────────────
+
+/// `EngineConfig::huge_pages` of this process (a `HUGE_PAGES_*` mode).
+pub fn huge_pages() -> u32 {
+    crate::runtime::engine_config::current().huge_pages
+}
+
+/// The page size of the hugetlbfs mount `file` lives on, or `None` when it
+/// is on any other filesystem.
+pub fn hugetlbfs_page_size(file: &File) -> Result<Option<usize>> {
+    let st = fstatfs(file)?;
+    Ok((st.filesystem_type() == HUGETLBFS_MAGIC).then_some(st.block_size() as usize))
+}
+
+/// Checks that a freshly created backing file at `path` suits `mode`: in
+/// hugetlbfs mode it must be on a hugetlbfs mount with [`HUGE_PAGE_SIZE`]
+/// pages (capacities are only ever aligned to that size).
+pub fn check_huge_page_backing(file: &File, mode: u32, path: &str) -> Result<()> {
+    if mode != HUGE_PAGES_HUGETLBFS {
+        return Ok(());
+    }
+    match hugetlbfs_page_size(file)? {
+        Some(size) if size == HUGE_PAGE_SIZE as usize => Ok(()),
+        Some(size) => Err(anyhow!(
+            "{}: hugetlbfs page size is {} KiB, huge_pages = 2 needs a {} MiB mount",
+            path, size / 1024, HUGE_PAGE_SIZE / MIB,
+        )),
+        None => Err(anyhow!(
+            "{}: huge_pages = 2 needs the file on a hugetlbfs mount (e.g. /dev/hugepages)",
+            path,
+        )),
+    }
+}
+
+/// `madvise(MADV_HUGEPAGE)` on `[addr, addr + len)` in THP mode; a no-op
+/// otherwise.  Whether tmpfs-backed mappings are then actually promoted is
+/// up to `/sys/kernel/mm/transparent_hugepage/shmem_enabled`.
+pub fn advise_huge_pages(addr: usize, len: usize) -> Result<()> {
+    if huge_pages() != HUGE_PAGES_THP || len == 0 {
+        return Ok(());
+    }
+    unsafe { madvise(addr as *mut std::ffi::c_void, len, MmapAdvise::MADV_HUGEPAGE)?; }
+    Ok(())
+}
+
+/// Reserves `len` bytes of `PROT_NONE` address space starting on a
+/// [`HUGE_PAGE_SIZE`] boundary, so that file overlays at huge-aligned
+/// offsets can be backed by huge pages.  Release with `munmap(base, len)`.
+pub fn reserve_huge_aligned(len: usize) -> Result<*mut u8> {
+    let align = HUGE_PAGE_SIZE as usize;
+    let raw = unsafe {
+        mmap(
+            None,
+            NonZeroUsize::new(len + align).unwrap(),
+            ProtFlags::PROT_NONE,
+            MapFlags::MAP_PRIVATE | MapFlags::MAP_ANONYMOUS,
+            None::<&File>,
+            0,
+        )?
+    } as usize;
+    // Trim the unaligned head and the surplus tail of the over-reservation.
+    let base = raw.next_multiple_of(align);
+    unsafe {
+        if base > raw {
+            munmap(raw as *mut std::ffi::c_void, base - raw)?;
+        }
+        munmap((base + len) as *mut std::ffi::c_void, raw + align - base)?;
+    }
+    Ok(base as *mut u8)
+}
+
+/// Starts of the huge-page extents all of whose pages are in `offsets`
+/// (byte offsets of free `PAGE_SIZE` pages, relative to a huge-aligned base),
+/// in ascending order.
+///
+/// With huge pages on, free-list trimming releases only these: a 4 KiB
+/// `MADV_DONTNEED` splits a THP (and fails outright on hugetlbfs), so the
+/// partially free extents keep their memory.
+pub fn whole_huge_extents(offsets: &[u64]) -> Vec<u64> {
+    let huge = HUGE_PAGE_SIZE as u64;
+    let pages_per_extent = (HUGE_PAGE_SIZE / PAGE_SIZE) as usize;
+    let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+    for &offset in offsets {
+        *counts.entry(offset - offset % huge).or_default() += 1;
+    }
+    counts.into_iter()
+        .filter(|&(_, n)| n == pages_per_extent)
+        .map(|(start, _)| start)
+        .collect()
 }
 
 // ── Host-driven SHM growth ────────────────────────────────────────────────────
@@ -128,6 +287,7 @@ pub fn sync_mapping_to_capacity(splice_addr: usize) -> Result<()> {
         file.set_len(cap as u64)?;
     }
     map_into_memory(file, *base, cap)?;
+    crate::runtime::input_output::mapped_input::apply(*base)?;
     MAPPED_CAP.store(cap as ShmOffset, Ordering::Release);
     Ok(())
 }
@@ -160,8 +320,11 @@ pub fn try_grow_shm(splice_addr: usize, required: ShmOffset) -> Result<ShmOffset
         return Ok(current);
     }
 
-    // Geometric growth capped at the hard limit.
-    let new_cap = current.saturating_mul(2).max(required).min(CAPACITY_HARD_LIMIT);
+    // Geometric growth capped at the hard limit, in whole huge pages when
+    // they are on (the hard limit is a multiple of `HUGE_PAGE_SIZE`).
+    let granularity = crate::runtime::engine_config::current().shm_granularity();
+    let new_cap = current.saturating_mul(2).max(required)
+        .next_multiple_of(granularity).min(CAPACITY_HARD_LIMIT);
     if new_cap < required {
         return Err(anyhow!(
             "SHM hard limit ({} GiB) reached — cannot grow to {} bytes",
@@ -180,3 +343,27 @@ pub fn try_grow_shm(splice_addr: usize, required: ShmOffset) -> Result<ShmOffset
     );
     Ok(new_cap)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_fully_free_extents_are_released() {
+        let huge = HUGE_PAGE_SIZE as u64;
+        let page = PAGE_SIZE as u64;
+        // Extent 1 is entirely free, extent 3 misses its last page.
+        let mut offsets: Vec<u64> = (0..huge / page).map(|i| huge + i * page).collect();
+        offsets.extend((0..huge / page - 1).map(|i| 3 * huge + i * page));
+        assert_eq!(whole_huge_extents(&offsets), vec![huge]);
+        assert!(whole_huge_extents(&[0, page]).is_empty());
+    }
+
+    #[test]
+    fn reservations_are_huge_aligned() {
+        let len = 4 * HUGE_PAGE_SIZE as usize;
+        let base = reserve_huge_aligned(len).unwrap();
+        assert_eq!(base as usize % HUGE_PAGE_SIZE as usize, 0);
+        unsafe { munmap(base as *mut std::ffi::c_void, len).unwrap(); }
+    }
+}
@@ -60,6 +60,17 @@ def _lookup(name):
     return None
 
 
+# Verify the SHM layout descriptor before any workload touches the region: a
+# host built with different common/src/lib.rs constants would otherwise have
+# every read and write land at the wrong offsets.
+import shm  # noqa: E402
+
+try:
+    shm.check_layout()
+except RuntimeError as e:
+    sys.exit(f"[runner] {e}")
+
+
 if len(sys.argv) > 1 and sys.argv[1] == "--loop":
     # ── Loop mode ──────────────────────────────────────────────────────────────
     for line in sys.stdin:
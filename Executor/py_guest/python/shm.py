@@ -24,6 +24,12 @@ Public API
   write_output_str(s)               -> None
   write_io(io_slot, data)           -> None
   write_fanout(io_slots, data)      -> None
+  kv_get(key)                       -> bytes | None
+  kv_put(key, value)                -> None
+  kv_compare_and_put(key, expected, value) -> bool   # expected None: key absent
+  kv_delete(key)                    -> bool
+  kv_scan(prefix=b"")               -> list[(key, value)]   # sorted by key
+  check_layout()                    -> None   # raises on a layout mismatch
 """
 
 import os
@@ -32,55 +38,124 @@ import struct
 # ── Constants (must match common/src/lib.rs) ─────────────────────────────────
 
 PAGE_SIZE          = 4096
-STREAM_SLOT_COUNT  = 2048
-IO_SLOT_COUNT      = 512
 FREE_LIST_SHARDS   = 16
 OUTPUT_IO_SLOT     = 1    # common::OUTPUT_IO_SLOT
 INPUT_IO_SLOT      = 0    # common::INPUT_IO_SLOT
 
+# Slot counts are chosen by the host at format time (engine settings
+# `stream_slot_count` / `io_slot_count`) and read from the layout descriptor
+# on attach; these are the defaults until then.
+STREAM_SLOT_COUNT  = 2048
+IO_SLOT_COUNT      = 512
+
 # Superblock field byte offsets (repr(C)).
 # bump_allocator/global_cap/log_off/registry_lock/next_atomic/shared_map_base
-# are all AtomicU32 (magic @ 0, bump @ 4, ...).  Slot arrays below are
-# AtomicU64 (AtomicPageId) — 8-byte stride.  Layout is asserted from the
+# are all AtomicU32 (magic @ 0, bump @ 4, ...).  Layout is asserted from the
 # Rust side in `common/src/lib.rs`; if those asserts fire, update these too.
 #   magic u32 @ 0
 #   bump  u32 @ 4
 #   ... [u32 fields to 28]
 #   (+4 pad to 8-align)
-#   free_list_heads[16]  AtomicU64 @ 32,    size 128
-#   writer_heads[2048]   AtomicU64 @ 160,   size 16384
-#   writer_tails[2048]   AtomicU64 @ 16544, size 16384
-#   io_heads[512]        AtomicU64 @ 32928, size 4096
-#   io_tails[512]        AtomicU64 @ 37024, size 4096
-#   barriers[64]         AtomicU32 @ 41120, size 256
-_SB_BUMP         = 4
+#   free_list_heads[16]  AtomicU64 @ 32,  size 128
+#   layout   ShmLayout             @ 160, size 56
+#   engine   EngineConfig          @ 216, size 104
+#   class_free_lists[2]  AtomicU64 @ 320, size 16
+#   mapped_generation    AtomicU32 @ 336
+#   mapped_inputs[4]  MappedInput  @ 340, size 4 * 224
+#   kv_directory         AtomicU32 @ 1236
+#   slot table                     @ 1240:
+#     writer_heads[stream_slot_count]  AtomicU64
+#     writer_tails[stream_slot_count]  AtomicU64
+#     io_heads[io_slot_count]          AtomicU64
+#     io_tails[io_slot_count]          AtomicU64
+#     barriers[barrier_count]          AtomicU32
+#     stream_signals[stream_slot_count]  AtomicU32
+#     stream_rings[stream_slot_count]    AtomicU32
+#     stream_subscriptions[stream_slot_count]  AtomicU32
+#     stream_page_classes[stream_slot_count]  AtomicU8
+#     io_page_classes[io_slot_count]          AtomicU8
+_SB_BUMP          = 4
 _SB_REGISTRY_LOCK = 16     # registry_lock  AtomicU32 @ 16
-_SB_NEXT_ATOMIC  = 20      # next_atomic_idx AtomicU32 @ 20
-_SB_WRITER_HEADS = 160
-_SB_WRITER_TAILS = 16544
-_SB_IO_HEADS     = 32928
-_SB_IO_TAILS     = 37024
-_SLOT_STRIDE     = 8       # bytes per slot atomic (was 4 before widening to u64)
-
-# Named-atomic registry + arena (must match common/src/lib.rs).
-#   REGISTRY_OFFSET    = SUPERBLOCK_SIZE                  (Superblock rounded to a page)
-#   ATOMIC_ARENA_OFFSET= REGISTRY_OFFSET + 1 MiB + 2 pages(RDMA scratch)
+_SB_NEXT_ATOMIC   = 20     # next_atomic_idx AtomicU32 @ 20
+_SB_LAYOUT_OFFSET = 28
+_SB_FREE_LISTS    = 32
+_SB_LAYOUT        = 160
+_SB_ENGINE        = 216
+_SB_CLASS_FREE_LISTS = 320
+_SB_MAPPED_GENERATION = 336
+_SB_MAPPED_INPUTS = 340
+_SB_KV_DIRECTORY  = 1236   # kv_directory AtomicU32 @ 1236
+_SB_SLOT_TABLE    = 1240   # common::SLOT_TABLE_OFFSET
+_SLOT_STRIDE      = 8      # bytes per slot atomic (was 4 before widening to u64)
+
+# Slot-table and arena offsets of the attached region, filled in from its
+# layout descriptor by `_verify_layout` (see `_apply_geometry`).
+_SB_WRITER_HEADS     = 0
+_SB_WRITER_TAILS     = 0
+_SB_IO_HEADS         = 0
+_SB_IO_TAILS         = 0
+_REGISTRY_OFFSET     = 0
+_ATOMIC_ARENA_OFFSET = 0
+
 # RegistryEntry (repr(C), 64 bytes): name[52] @0, index u32 @52, payload_* @56.
-# Atomic values are AtomicU64 at ATOMIC_ARENA_OFFSET + index*8.
-# These literals are verified against the live registry by Tests/Streaming.
-_REGISTRY_OFFSET     = 45056      # SUPERBLOCK_SIZE
-_ATOMIC_ARENA_OFFSET = 1101824    # REGISTRY_OFFSET + 1 MiB + 8 KiB
+# Atomic values are AtomicU64 at atomic_arena_offset + index*8.
 _REG_ENTRY_SIZE      = 64
 _REG_NAME_LEN        = 52
 
 # Page layout (repr(C, align(4096))):
 #   next_offset u64 @ 0   (PageId — widened from u32 for extended-pool support)
 #   cursor      u32 @ 8
-#   data[4084]      @ 12
+#   class       u32 @ 12  (page class: 0 = 4k, 1 = 64k, 2 = 2m)
+#   data[span - 16] @ 16
+# Pages of a larger class span several 4 KiB units; the class in the header
+# says how many bytes of data the page holds.  This module only allocates
+# 4 KiB pages, but appends into whatever tail page the host left.
 _PAGE_NEXT_OFF    = 0
 _PAGE_CURSOR_OFF  = 8
-_PAGE_DATA_OFFSET = 12
-_PAGE_DATA_SIZE   = 4084   # PAGE_SIZE - 12
+_PAGE_CLASS_OFF   = 12
+_PAGE_DATA_OFFSET = 16
+_PAGE_CLASS_SIZES = (4096, 65536, 2 * 1024 * 1024)   # common::PAGE_CLASS_SIZES
+
+# Zero-copy inputs (common "Mapped inputs").  Every unit but the first of each
+# 2 MiB page of a live extent is, in the host's mappings, a read-only overlay
+# of the input file; the region file underneath is empty.  This module reads
+# the region through the file, so `_read_bytes` reads those units from the
+# input file instead.  MappedInput (repr(C), 224 bytes): state u32 @0,
+# live_pages u32 @4, offset u32 @8, pages u32 @12, capacity u32 @16,
+# path[204] @20.
+_MAPPED_INPUT_COUNT = 4
+_MAPPED_INPUT_SIZE  = 224
+_MAPPED_INPUT_LIVE  = 1
+_MAPPED_PAGE_SPAN   = _PAGE_CLASS_SIZES[2]
+_MAPPED_PAGE_FILL   = _MAPPED_PAGE_SPAN - PAGE_SIZE   # common::MAPPED_PAGE_FILL
+_mapped = []                 # (start, end, file) per live extent
+_mapped_files = {}           # path -> open input file
+_mapped_generation = None    # Superblock::mapped_generation `_mapped` reflects
+
+# Layout descriptor (common::ShmLayout), located through the Superblock's
+# `layout_offset` u32 @ 28.  Twelve u32 fields then a u64 FNV-1a hash over
+# the build-fixed constants and Superblock offsets below.  The build-fixed
+# fields are compared on attach, so a host built with a different PAGE_SIZE
+# or Superblock layout is rejected instead of silently corrupting the
+# region; the geometry fields are taken from the region as stored.
+_SHM_MAGIC           = 0xDEADBEEF   # common::SHM_MAGIC
+_SHM_LAYOUT_VERSION  = 11           # common::SHM_LAYOUT_VERSION
+_CHAIN_HEADER_SIZE   = 32
+_KV_ENTRY_HEADER_SIZE = 32          # common::KvEntryHeader
+_RING_HEADER_SIZE    = 3864         # common::RingHeader (ring slots are Rust-guest only)
+_SUBSCRIPTION_TABLE_SIZE = 2312     # common::SubscriptionTable (also Rust-guest only)
+_LAYOUT_FIELDS = (
+    "version", "page_size", "stream_slot_count", "io_slot_count",
+    "free_list_shard_count", "barrier_count", "superblock_size",
+    "registry_offset", "atomic_arena_offset", "log_arena_offset",
+    "bump_allocator_start", "page_header_size",
+)
+_LAYOUT_BUILD_FIELDS = {
+    "version":               _SHM_LAYOUT_VERSION,
+    "page_size":             PAGE_SIZE,
+    "free_list_shard_count": FREE_LIST_SHARDS,
+    "page_header_size":      _PAGE_DATA_OFFSET,
+}
 
 # ── Module state ──────────────────────────────────────────────────────────────
 
@@ -97,6 +172,69 @@ def _init():
     # buffering=0 → raw unbuffered I/O; avoids stale-buffer issues when we
     # interleave reads and writes at arbitrary offsets.
     _f = open(path, "r+b", buffering=0)
+    try:
+        _verify_layout(path)
+    except Exception:
+        _f.close()
+        _f = None
+        raise
+
+
+def _layout_hash() -> int:
+    """FNV-1a 64 over the same words as `ShmLayout::hash_words`."""
+    words = [
+        _SHM_LAYOUT_VERSION, PAGE_SIZE, FREE_LIST_SHARDS, _PAGE_DATA_OFFSET,
+        _SB_LAYOUT_OFFSET, _SB_FREE_LISTS, _SB_LAYOUT, _SB_ENGINE,
+        _SB_CLASS_FREE_LISTS, _SB_MAPPED_INPUTS, _SB_SLOT_TABLE, _REG_ENTRY_SIZE,
+        _CHAIN_HEADER_SIZE, _SB_KV_DIRECTORY, _KV_ENTRY_HEADER_SIZE,
+        _RING_HEADER_SIZE, _SUBSCRIPTION_TABLE_SIZE,
+    ]
+    h = 0xCBF29CE484222325
+    for b in struct.pack("<%dI" % len(words), *words):
+        h = ((h ^ b) * 0x100000001B3) & 0xFFFFFFFFFFFFFFFF
+    return h
+
+
+def _verify_layout(path: str) -> None:
+    magic = _ru32(0)
+    if magic != _SHM_MAGIC:
+        raise RuntimeError(
+            f"{path}: bad superblock magic {magic:#010x}; region is not formatted")
+    off = _ru32(_SB_LAYOUT_OFFSET)
+    if off == 0:
+        raise RuntimeError(
+            f"{path}: no layout descriptor (formatted by an older host); re-format it")
+    stored = struct.unpack("<12IQ", _read_bytes(off, 56))
+    fields = dict(zip(_LAYOUT_FIELDS, stored))
+    for name, want in _LAYOUT_BUILD_FIELDS.items():
+        if fields[name] != want:
+            raise RuntimeError(
+                f"{path}: SHM layout mismatch on {name}: region has {fields[name]}, "
+                f"shm.py expects {want} (update the literals in shm.py)")
+    if stored[12] != _layout_hash():
+        raise RuntimeError(
+            f"{path}: SHM layout hash {stored[12]:#018x} != shm.py {_layout_hash():#018x} "
+            f"(Superblock offsets in shm.py are stale)")
+    _apply_geometry(fields)
+
+
+def _apply_geometry(fields: dict) -> None:
+    """Point the slot-table and arena offsets at the attached region's geometry."""
+    global STREAM_SLOT_COUNT, IO_SLOT_COUNT, _REGISTRY_OFFSET, _ATOMIC_ARENA_OFFSET
+    global _SB_WRITER_HEADS, _SB_WRITER_TAILS, _SB_IO_HEADS, _SB_IO_TAILS
+    STREAM_SLOT_COUNT = fields["stream_slot_count"]
+    IO_SLOT_COUNT = fields["io_slot_count"]
+    _SB_WRITER_HEADS = _SB_SLOT_TABLE
+    _SB_WRITER_TAILS = _SB_WRITER_HEADS + STREAM_SLOT_COUNT * _SLOT_STRIDE
+    _SB_IO_HEADS = _SB_WRITER_TAILS + STREAM_SLOT_COUNT * _SLOT_STRIDE
+    _SB_IO_TAILS = _SB_IO_HEADS + IO_SLOT_COUNT * _SLOT_STRIDE
+    _REGISTRY_OFFSET = fields["registry_offset"]
+    _ATOMIC_ARENA_OFFSET = fields["atomic_arena_offset"]
+
+
+def check_layout() -> None:
+    """Attach to SHM_PATH and verify its layout descriptor (raises RuntimeError)."""
+    _init()
 
 
 # ── Low-level helpers ─────────────────────────────────────────────────────────
@@ -122,8 +260,49 @@ def _wu64(off: int, v: int) -> None:
 
 
 def _read_bytes(off: int, n: int) -> bytes:
-    _f.seek(off)
-    return _f.read(n)
+    if not _mapped:
+        _f.seek(off)
+        return _f.read(n)
+    out = bytearray()
+    while n > 0:
+        src, take = _f, n
+        for start, end, f in _mapped:
+            if off < start:
+                take = min(take, start - off)
+            elif off < end:
+                j, r = divmod(off - start, _MAPPED_PAGE_SPAN)
+                if r < PAGE_SIZE:
+                    take = min(take, PAGE_SIZE - r)
+                else:
+                    # Overlay of page j starts at file offset PAGE_SIZE + j * fill.
+                    src, take = f, min(take, _MAPPED_PAGE_SPAN - r)
+                break
+        src.seek(j * _MAPPED_PAGE_FILL + r if src is not _f else off)
+        out += src.read(take)
+        off += take
+        n -= take
+    return bytes(out)
+
+
+def _refresh_mapped() -> None:
+    """Re-read the live zero-copy inputs if one was mapped since the last call."""
+    global _mapped, _mapped_files, _mapped_generation
+    generation = _ru32(_SB_MAPPED_GENERATION)
+    if generation == _mapped_generation:
+        return
+    mapped, files = [], {}
+    for i in range(_MAPPED_INPUT_COUNT):
+        base = _SB_MAPPED_INPUTS + i * _MAPPED_INPUT_SIZE
+        if _ru32(base) != _MAPPED_INPUT_LIVE:
+            continue
+        start, pages = _ru32(base + 8), _ru32(base + 12)
+        path = _read_bytes(base + 20, 204).split(b"\x00", 1)[0].decode()
+        f = _mapped_files.pop(path, None) or open(path, "rb", buffering=0)
+        files[path] = f
+        mapped.append((start, start + pages * _MAPPED_PAGE_SPAN, f))
+    for f in _mapped_files.values():
+        f.close()
+    _mapped, _mapped_files, _mapped_generation = mapped, files, generation
 
 
 def _write_bytes(off: int, data: bytes) -> None:
@@ -171,12 +350,14 @@ def _resolve_atomic(name: str) -> int:
 
 def atomic_get(name: str) -> int:
     """Read the u64 value of a named atomic."""
-    return _ru64(_ATOMIC_ARENA_OFFSET + _resolve_atomic(name) * 8)
+    idx = _resolve_atomic(name)   # attaches first, which sets the arena offset
+    return _ru64(_ATOMIC_ARENA_OFFSET + idx * 8)
 
 
 def atomic_set(name: str, value: int) -> None:
     """Write the u64 value of a named atomic."""
-    _wu64(_ATOMIC_ARENA_OFFSET + _resolve_atomic(name) * 8, value)
+    idx = _resolve_atomic(name)
+    _wu64(_ATOMIC_ARENA_OFFSET + idx * 8, value)
 
 
 def pipe_read_window(in_slot: int, total: int):
@@ -204,6 +385,7 @@ def _read_chain(head: int) -> list:
     """Walk a page chain starting at byte offset `head`.
     Returns list of (origin: int, payload: bytes) tuples.
     """
+    _refresh_mapped()
     records = []
     page_off = head
     cursor_in_page = 0
@@ -312,9 +494,15 @@ def _alloc_page() -> int:
     _wu32(_SB_BUMP, bump + PAGE_SIZE)
     _wu64(bump + _PAGE_NEXT_OFF,   0)   # next_offset (u64) = 0
     _wu32(bump + _PAGE_CURSOR_OFF, 0)   # cursor (u32) = 0
+    _wu32(bump + _PAGE_CLASS_OFF,  0)   # class (u32) = 4k
     return bump
 
 
+def _page_data_size(page_off: int) -> int:
+    """Data capacity of the page at `page_off`, from its header class."""
+    return _PAGE_CLASS_SIZES[_ru32(page_off + _PAGE_CLASS_OFF)] - _PAGE_DATA_OFFSET
+
+
 # ── Page-chain writer ─────────────────────────────────────────────────────────
 
 def _append(head_off: int, tail_off: int, origin: int, payload: bytes) -> None:
@@ -337,7 +525,7 @@ def _append(head_off: int, tail_off: int, origin: int, payload: bytes) -> None:
             _wu64(tail_off, tail)
 
         cursor = _ru32(tail + _PAGE_CURSOR_OFF)
-        space  = _PAGE_DATA_SIZE - cursor
+        space  = _page_data_size(tail) - cursor
         if space == 0:
             new = _alloc_page()
             _wu64(tail + _PAGE_NEXT_OFF, new)   # chain: current.next_offset (u64) = new
@@ -510,3 +698,160 @@ def reset_stream_cursor(slot: int) -> None:
     host via `rdma_recv` (free + replace pattern) rather than accumulated.
     """
     _stream_cursors.pop(slot, None)
+
+
+# ── Key-value store ───────────────────────────────────────────────────────────
+#
+# Same layout as the Rust guest (common "Key-value store"): kv_directory → root
+# page → leaf page → bucket chain of entries, newest first.  An entry page is
+#   next_entry u64 | hash u32 | key_len u32 | value_len u32 | flags u32 |
+#   next_payload_page u64 | key bytes | value bytes
+# with overflow pages carrying their next PageId at byte 0.  File I/O has no
+# CAS, so writes here are best-effort like `_append`: do not let a Python
+# worker race another writer on the same store.
+
+_KV_FANOUT       = PAGE_SIZE // 8
+_KV_BUCKET_COUNT = _KV_FANOUT * _KV_FANOUT
+_KV_TOMBSTONE    = 1
+
+
+def _kv_hash(key: bytes) -> int:
+    """FNV-1a 32 (`common::kv_hash`)."""
+    h = 0x811C9DC5
+    for b in key:
+        h = ((h ^ b) * 0x01000193) & 0xFFFFFFFF
+    return h
+
+
+def _kv_zeroed_page() -> int:
+    page = _alloc_page()
+    _write_bytes(page, bytes(PAGE_SIZE))
+    return page
+
+
+def _kv_bucket(h: int, create: bool):
+    """Offset of the bucket head (u64) for hash `h`, or None if never written."""
+    bucket = h % _KV_BUCKET_COUNT
+    root_idx, leaf_idx = divmod(bucket, _KV_FANOUT)
+    root = _ru32(_SB_KV_DIRECTORY)
+    if root == 0:
+        if not create:
+            return None
+        root = _kv_zeroed_page()
+        _wu32(_SB_KV_DIRECTORY, root)
+    leaf = _ru64(root + root_idx * 8)
+    if leaf == 0:
+        if not create:
+            return None
+        leaf = _kv_zeroed_page()
+        _wu64(root + root_idx * 8, leaf)
+    return leaf + leaf_idx * 8
+
+
+def _kv_header(entry: int):
+    """(next_entry, hash, key_len, value_len, flags, next_payload_page)."""
+    return struct.unpack("<QIIIIQ", _read_bytes(entry, _KV_ENTRY_HEADER_SIZE))
+
+
+def _kv_entry_bytes(entry: int, n: int) -> bytes:
+    """The first `n` key-then-value bytes of `entry`."""
+    hdr = _kv_header(entry)
+    out = bytearray(_read_bytes(entry + _KV_ENTRY_HEADER_SIZE, min(n, PAGE_SIZE - _KV_ENTRY_HEADER_SIZE)))
+    page = hdr[5]
+    while len(out) < n:
+        out += _read_bytes(page + 8, min(n - len(out), PAGE_SIZE - 8))
+        page = _ru64(page)
+    return bytes(out)
+
+
+def _kv_chain(head: int):
+    """Yields (entry, key, value | None) along a bucket chain, newest first."""
+    while head != 0:
+        nxt, _, key_len, value_len, flags, _ = _kv_header(head)
+        data = _kv_entry_bytes(head, key_len + value_len)
+        value = None if flags & _KV_TOMBSTONE else data[key_len:]
+        yield head, data[:key_len], value
+        head = nxt
+
+
+def _kv_lookup(link: int, key: bytes):
+    for _, k, value in _kv_chain(_ru64(link)):
+        if k == key:
+            return value
+    return None
+
+
+def _kv_prepend(link: int, h: int, key: bytes, value: bytes, flags: int) -> None:
+    """Writes an entry holding `key` then `value` and links it at the bucket head."""
+    data = key + value
+    entry = _alloc_page()
+    first = PAGE_SIZE - _KV_ENTRY_HEADER_SIZE
+    _write_bytes(entry + _KV_ENTRY_HEADER_SIZE, data[:first])
+    next_payload, prev = 0, None
+    for i in range(first, len(data), PAGE_SIZE - 8):
+        page = _alloc_page()
+        _wu64(page, 0)
+        _write_bytes(page + 8, data[i:i + PAGE_SIZE - 8])
+        if prev is None:
+            next_payload = page
+        else:
+            _wu64(prev, page)
+        prev = page
+    _write_bytes(entry, struct.pack("<QIIIIQ", _ru64(link), h, len(key), len(value), flags, next_payload))
+    _wu64(link, entry)
+
+
+def kv_get(key: bytes):
+    """The current value of `key`, or None."""
+    _init()
+    link = _kv_bucket(_kv_hash(key), False)
+    return None if link is None else _kv_lookup(link, key)
+
+
+def kv_put(key: bytes, value: bytes) -> None:
+    _init()
+    h = _kv_hash(key)
+    _kv_prepend(_kv_bucket(h, True), h, key, value, 0)
+
+
+def kv_compare_and_put(key: bytes, expected, value: bytes) -> bool:
+    """Set `key` to `value` only if its current value is `expected` (None: absent)."""
+    _init()
+    h = _kv_hash(key)
+    link = _kv_bucket(h, True)
+    if _kv_lookup(link, key) != expected:
+        return False
+    _kv_prepend(link, h, key, value, 0)
+    return True
+
+
+def kv_delete(key: bytes) -> bool:
+    """Remove `key`; returns whether it had a value."""
+    _init()
+    h = _kv_hash(key)
+    link = _kv_bucket(h, False)
+    if link is None or _kv_lookup(link, key) is None:
+        return False
+    _kv_prepend(link, h, key, b"", _KV_TOMBSTONE)
+    return True
+
+
+def kv_scan(prefix: bytes = b"") -> list:
+    """Every live (key, value) whose key starts with `prefix`, sorted by key."""
+    _init()
+    root = _ru32(_SB_KV_DIRECTORY)
+    if root == 0:
+        return []
+    out = {}
+    roots = struct.unpack("<%dQ" % _KV_FANOUT, _read_bytes(root, PAGE_SIZE))
+    for leaf in (l for l in roots if l):
+        heads = struct.unpack("<%dQ" % _KV_FANOUT, _read_bytes(leaf, PAGE_SIZE))
+        for head in (h for h in heads if h):
+            seen = set()
+            for _, key, value in _kv_chain(head):
+                if key in seen or not key.startswith(prefix):
+                    continue
+                seen.add(key)
+                if value is not None:
+                    out[key] = value
+    return sorted(out.items())
@@ -3,10 +3,72 @@
 All slot numbers are supplied by the DAG JSON as arg/arg2 — none are hardcoded here.
 """
 
+import struct
+
 import shm
 
 
 # ── Word-count demo ───────────────────────────────────────────────────────────
+#
+# Map records are typed `WordCount` records, the same bytes the wasm `wc_map`
+# writes (see "Typed records" in `common`): the FNV-1a tag of
+# `WORD_COUNT_SCHEMA`, then the postcard body — varint length + UTF-8 word,
+# varint count.  Python and wasm map/reduce stages therefore mix freely.
+
+WORD_COUNT_SCHEMA = b'WordCount{word:str,count:u64}'
+
+
+def _fnv1a_32(data):
+    h = 0x811c9dc5
+    for b in data:
+        h = ((h ^ b) * 0x01000193) & 0xffffffff
+    return h
+
+
+WORD_COUNT_TAG = _fnv1a_32(WORD_COUNT_SCHEMA)
+
+
+def _varint(n):
+    out = bytearray()
+    while n >= 0x80:
+        out.append((n & 0x7f) | 0x80)
+        n >>= 7
+    out.append(n)
+    return bytes(out)
+
+
+def _read_varint(buf, pos):
+    n, shift = 0, 0
+    while True:
+        if pos >= len(buf) or shift > 63:
+            raise ValueError('truncated varint')
+        b = buf[pos]
+        pos += 1
+        n |= (b & 0x7f) << shift
+        if b < 0x80:
+            return n, pos
+        shift += 7
+
+
+def encode_word_count(word, count):
+    w = word.encode('utf-8')
+    return struct.pack('<I', WORD_COUNT_TAG) + _varint(len(w)) + w + _varint(count)
+
+
+def decode_word_count(rec):
+    """`(word, count)` of one typed `WordCount` record; raises `ValueError` on
+    another schema or a malformed body, like the wasm reducer traps."""
+    if len(rec) < 4 or struct.unpack_from('<I', rec)[0] != WORD_COUNT_TAG:
+        raise ValueError('record is not a `%s` record' % WORD_COUNT_SCHEMA.decode())
+    n, pos = _read_varint(rec, 4)
+    word = rec[pos:pos + n]
+    if len(word) != n:
+        raise ValueError('truncated `%s` record' % WORD_COUNT_SCHEMA.decode())
+    count, pos = _read_varint(rec, pos + n)
+    if pos != len(rec):
+        raise ValueError('`%s` record has %d trailing bytes' % (WORD_COUNT_SCHEMA.decode(), len(rec) - pos))
+    return word.decode('utf-8'), count
+
 
 def wc_distribute(n_workers, base_slot):
     """Read all input records and distribute them round-robin to stream slots
@@ -17,8 +79,8 @@ def wc_distribute(n_workers, base_slot):
 
 
 def wc_map(in_slot, out_slot):
-    """Count word frequencies in stream slot `in_slot`, emit one record per
-    unique word to stream slot `out_slot`."""
+    """Count word frequencies in stream slot `in_slot`, emit one `WordCount`
+    record per unique word to stream slot `out_slot`."""
     counts = {}
     for origin, rec in shm.read_all_stream_records(in_slot):
         for token in rec.decode('utf-8', errors='replace').split():
@@ -27,25 +89,21 @@ def wc_map(in_slot, out_slot):
                 counts[word] = counts.get(word, 0) + 1
 
     for word, count in counts.items():
-        shm.append_stream_data(out_slot,
-                               ('word=' + word + '\x1f' + str(count)).encode())
+        shm.append_stream_data(out_slot, encode_word_count(word, count))
 
 
 def wc_reduce(in_slot):
-    """Merge all map records from stream slot `in_slot` and write the summary
-    to I/O slot 1 via write_output."""
+    """Merge all `WordCount` records from stream slot `in_slot` and write the
+    summary to I/O slot 1 via write_output."""
     # Count records before reading payloads (no allocation).
     n_records = shm.count_stream_records(in_slot)
 
     totals = {}
-    for origin, rec in shm.read_all_stream_records(in_slot):
-        s = rec.decode('utf-8', errors='replace')
-        if not s.startswith('word=') or '\x1f' not in s:
-            continue
-        body  = s[5:]
-        sep   = body.index('\x1f')
-        word  = body[:sep]
-        count = int(body[sep + 1:])
+    for i, (origin, rec) in enumerate(shm.read_all_stream_records(in_slot)):
+        try:
+            word, count = decode_word_count(rec)
+        except ValueError as e:
+            raise ValueError('stream slot %d record %d: %s' % (in_slot, i, e)) from None
         if word:
             totals[word] = totals.get(word, 0) + count
 
@@ -6,6 +6,7 @@
 //! Superblock.  Total job memory = rss_bytes + shm_bump_offset (SHM once).
 
 use anyhow::Result;
+use executor_common::LayoutError;
 use scheduler::ScxNodeSnapshot;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -40,6 +41,9 @@ pub struct MetricsCollector {
     prev_cpu_total: u64,
     prev_cpu_idle: u64,
     scx_client: Option<scheduler::ScxStatsClient>,
+    /// Set once a layout mismatch has been reported, so a mismatched region
+    /// is logged once rather than on every sample.
+    layout_warned: bool,
 }
 
 impl MetricsCollector {
@@ -49,6 +53,7 @@ impl MetricsCollector {
             prev_cpu_total: 0,
             prev_cpu_idle: 0,
             scx_client: None,
+            layout_warned: false,
         }
     }
 
@@ -59,6 +64,7 @@ impl MetricsCollector {
             prev_cpu_total: 0,
             prev_cpu_idle: 0,
             scx_client: Some(scheduler::ScxStatsClient::new(socket_path)),
+            layout_warned: false,
         }
     }
 
@@ -77,9 +83,23 @@ impl MetricsCollector {
         // daemon's tiny footprint regardless of the job.  Falls back to self
         // when no executor pid is given (idle) or its /proc entry is gone.
         let rss_bytes = sample_rss(executor_pid).unwrap_or(0);
-        let shm_bump_offset = shm_path
-            .and_then(|p| read_shm_bump_offset(p).ok())
-            .unwrap_or(0);
+        let shm_bump_offset = match shm_path.map(|p| (p, read_shm_bump_offset(p))) {
+            Some((_, Ok(v))) => v,
+            Some((p, Err(e))) => {
+                // BadMagic/Truncated just mean the executor has not formatted
+                // the region yet; only a real layout disagreement is reported.
+                let mismatch = matches!(
+                    e.downcast_ref::<LayoutError>(),
+                    Some(LayoutError::Mismatch { .. } | LayoutError::Unversioned),
+                );
+                if mismatch && !self.layout_warned {
+                    eprintln!("[metrics] not reading {}: {}", p, e);
+                    self.layout_warned = true;
+                }
+                0
+            }
+            None => 0,
+        };
 
         let timestamp_ms = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -225,16 +245,20 @@ fn proc_private_kb(pid: u32) -> Option<(u32, u64)> {
 
 /// Read the bump_allocator field from the SHM Superblock.
 ///
-/// The Superblock layout (from common/src/lib.rs):
+/// The Superblock layout (from Executor/common/src/lib.rs):
 ///   offset 0: magic (u32)
 ///   offset 4: bump_allocator (AtomicU32)
 ///
-/// We read the raw bytes at offset 4 as a little-endian u32.
+/// The region's layout descriptor is checked first (`check_shm_layout`): a
+/// region formatted by an executor built with different SHM constants is
+/// reported instead of being read at the wrong offsets.  A file the executor
+/// has not formatted yet (too short) reads as 0.
 fn read_shm_bump_offset(shm_path: &str) -> Result<u32> {
     let data = fs::read(shm_path)?;
     if data.len() < 8 {
         return Ok(0);
     }
+    executor_common::check_shm_layout(&data)?;
     // bump_allocator is at offset 4 (after the 4-byte magic field).
     let bytes: [u8; 4] = [data[4], data[5], data[6], data[7]];
     Ok(u32::from_le_bytes(bytes))
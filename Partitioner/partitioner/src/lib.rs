@@ -1,6 +1,7 @@
 mod slot;
 mod slot_assigner;
 mod splitter;
+pub mod module_meta;
 pub mod placer;
 pub mod policies;
 pub mod symbolic_dag;
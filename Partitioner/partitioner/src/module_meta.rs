@@ -0,0 +1,159 @@
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use common::{workload_meta_lines, WORKLOAD_META_SECTION};
+
+use crate::symbolic_dag::{SymbolicDag, SymbolicNode};
+
+/// One `#[workload]` export as recorded in the guest module's
+/// `WORKLOAD_META_SECTION` (one JSON line per export; see `guest-macros`).
+/// Only the fields the partitioner checks are kept.
+#[derive(Debug, Deserialize)]
+pub struct WorkloadMeta {
+    pub name: String,
+    pub args: Vec<WorkloadArg>,
+    /// `"void"`, `"u32"` or `"fatptr"`.
+    pub ret: String,
+    #[serde(default)]
+    pub produces: Vec<WorkloadSlot>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkloadArg {
+    pub name: String,
+    /// `"u32"`, or `"fanout"` for a `base | n << 16` packed argument.
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkloadSlot {
+    /// `"Stream"` or `"Io"`.
+    pub kind: String,
+    /// `"<arg>"`, `"<arg>+<n>"` or `"<n>"`.
+    pub slot: String,
+}
+
+/// Parse the workload metadata of a WebAssembly binary, keyed by export name.
+/// Empty for modules built without `#[workload]` exports.
+pub fn read_workload_meta(module: &[u8]) -> Result<HashMap<String, WorkloadMeta>> {
+    let mut metas = HashMap::new();
+    for line in workload_meta_lines(module) {
+        let meta: WorkloadMeta = serde_json::from_str(line)
+            .map_err(|e| anyhow!("bad {} entry {:?}: {}", WORKLOAD_META_SECTION, line, e))?;
+        metas.insert(meta.name.clone(), meta);
+    }
+    Ok(metas)
+}
+
+/// Check the DAG's `Func` nodes against the workload metadata of
+/// `dag.wasm_path`, before any node is expanded.
+///
+/// A `Func` becomes `WasmVoid { func, arg }`, so the workload must take one
+/// argument and return nothing.  Beyond what the executor checks on its own
+/// (it never sees `Func`), the partitioner's slot declarations must agree with
+/// the workload's:
+///
+/// - `out_base` packs `(base, n_consumers)` into `arg`, which only a `Fanout`
+///   argument decodes;
+/// - `output_offset: K` claims the workload writes stream slot `arg + K`, which
+///   must be one of its declared `produces` slots when it declares any.
+///
+/// Functions without metadata (hand-written exports) are not checked, and a
+/// DAG without `wasm_path` or whose module is not readable from here (e.g. a
+/// coordinator without the guest build) is passed through unchanged.
+pub fn check_symbolic_dag(dag: &SymbolicDag) -> Result<()> {
+    let Some(path) = dag.wasm_path.as_deref() else { return Ok(()) };
+    let Ok(module) = std::fs::read(path) else { return Ok(()) };
+    let metas = read_workload_meta(&module)?;
+    if metas.is_empty() {
+        return Ok(());
+    }
+    let errors: Vec<String> = dag.nodes.iter()
+        .flat_map(|n| func_node_errors(n, &metas))
+        .collect();
+    if !errors.is_empty() {
+        bail!("SymbolicDag does not match {}:\n  {}", path, errors.join("\n  "));
+    }
+    Ok(())
+}
+
+fn func_node_errors(node: &SymbolicNode, metas: &HashMap<String, WorkloadMeta>) -> Vec<String> {
+    let Some(func) = node.kind.get("Func").and_then(|v| v.as_object()) else { return Vec::new() };
+    let Some(meta) = func.get("func").and_then(|v| v.as_str()).and_then(|f| metas.get(f)) else {
+        return Vec::new();
+    };
+    let mut errors = Vec::new();
+    if meta.args.len() != 1 || meta.ret != "void" {
+        errors.push(format!(
+            "node '{}': workload `{}` takes {} argument(s) and returns {}; a Func calls it as func(arg) -> void",
+            node.id, meta.name, meta.args.len(), meta.ret
+        ));
+        return errors;
+    }
+    let arg = &meta.args[0];
+    if func.contains_key("out_base") && arg.ty != "fanout" {
+        errors.push(format!(
+            "node '{}': `out_base` packs (base, n) into the argument, but workload `{}` takes a plain u32 `{}`",
+            node.id, meta.name, arg.name
+        ));
+    }
+    // With `wasm_arg` the guest does not receive the slot `arg`, so the offset
+    // cannot be related to its declarations.
+    let offset = func.get("output_offset")
+        .and_then(|v| v.as_u64())
+        .filter(|_| !func.contains_key("wasm_arg"));
+    if let Some(k) = offset {
+        let declared: Vec<&str> = meta.produces.iter()
+            .filter(|s| s.kind == "Stream")
+            .map(|s| s.slot.as_str())
+            .collect();
+        let expected = format!("{}+{}", arg.name, k);
+        let matches = declared.iter().any(|s| *s == expected || (k == 0 && *s == arg.name));
+        if !declared.is_empty() && !matches {
+            errors.push(format!(
+                "node '{}': `output_offset: {}` but workload `{}` produces stream slot(s) {}",
+                node.id, k, meta.name, declared.join(", ")
+            ));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metas() -> HashMap<String, WorkloadMeta> {
+        [
+            r#"{"name":"wc_distribute","args":[{"name":"out","type":"fanout"}],"ret":"void","consumes":[{"kind":"Io","slot":"0"}],"produces":[]}"#,
+            r#"{"name":"wc_map","args":[{"name":"slot","type":"u32"}],"ret":"void","consumes":[{"kind":"Stream","slot":"slot"}],"produces":[{"kind":"Stream","slot":"slot+100"}]}"#,
+            r#"{"name":"count_stream_records","args":[{"name":"id","type":"u32"}],"ret":"u32","consumes":[{"kind":"Stream","slot":"id"}],"produces":[]}"#,
+        ]
+        .iter()
+        .map(|l| serde_json::from_str::<WorkloadMeta>(l).unwrap())
+        .map(|m| (m.name.clone(), m))
+        .collect()
+    }
+
+    fn node(id: &str, kind: serde_json::Value) -> SymbolicNode {
+        serde_json::from_value(json!({ "id": id, "kind": kind })).unwrap()
+    }
+
+    #[test]
+    fn func_nodes_match_workload_declarations() {
+        let m = metas();
+        assert!(func_node_errors(&node("d", json!({ "Func": { "func": "wc_distribute", "out_base": 10 } })), &m).is_empty());
+        assert!(func_node_errors(&node("m", json!({ "Func": { "func": "wc_map", "output_offset": 100 } })), &m).is_empty());
+        assert!(func_node_errors(&node("x", json!({ "Func": { "func": "hand_written", "out_base": 10 } })), &m).is_empty());
+
+        let off = func_node_errors(&node("m", json!({ "Func": { "func": "wc_map", "output_offset": 50 } })), &m);
+        assert_eq!(off, ["node 'm': `output_offset: 50` but workload `wc_map` produces stream slot(s) slot+100"]);
+        let packed = func_node_errors(&node("m", json!({ "Func": { "func": "wc_map", "out_base": 10 } })), &m);
+        assert!(packed[0].contains("takes a plain u32 `slot`"), "{:?}", packed);
+        let ret = func_node_errors(&node("c", json!({ "Func": { "func": "count_stream_records" } })), &m);
+        assert!(ret[0].contains("returns u32"), "{:?}", ret);
+    }
+}
@@ -1,5 +1,4 @@
 use serde_json::Value;
-use std::collections::BTreeSet;
 
 /// Auto-detect the output slot of a node from its kind JSON.
 ///
@@ -26,37 +25,3 @@ pub fn output_slot(kind: &Value) -> Option<u32> {
     }
     None
 }
-
-/// Recursively collect all non-negative integer values from a JSON value.
-///
-/// Used to find the current maximum slot number in use so the Partitioner
-/// can allocate fresh, non-conflicting slot IDs for generated RemoteRecv nodes.
-pub fn collect_slots(v: &Value, out: &mut BTreeSet<u32>) {
-    match v {
-        Value::Number(n) => {
-            if let Some(u) = n.as_u64() {
-                if u <= u32::MAX as u64 {
-                    out.insert(u as u32);
-                }
-            }
-        }
-        Value::Array(arr) => {
-            for item in arr {
-                collect_slots(item, out);
-            }
-        }
-        Value::Object(map) => {
-            for (k, val) in map {
-                // `wasm_arg` is a guest-side function parameter, NOT a slot — e.g.
-                // the SGD workloads pack (input_slot | output_slot<<16) into it,
-                // a huge integer. Scanning it would poison `next_slot` and push
-                // generated RemoteRecv slots out of range (deadlocking the gather).
-                if k == "wasm_arg" {
-                    continue;
-                }
-                collect_slots(val, out);
-            }
-        }
-        _ => {}
-    }
-}
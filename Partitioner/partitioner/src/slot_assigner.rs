@@ -146,7 +146,7 @@ pub(crate) fn assign_slots(nodes: &mut Vec<SymbolicNode>) -> Result<()> {
                 // Phase 1).  The splitter packs the consumer count into the high
                 // bits of `arg` when it emits the WasmVoid kind — doing it here
                 // would pollute the partitioner's slot-number bookkeeping
-                // (collect_slots would read the packed value as a used slot).
+                // (collect_dag_slots would read the packed value as a used slot).
                 let eff_base = fanout_adjusted_base
                     .get(&node_id)
                     .copied()
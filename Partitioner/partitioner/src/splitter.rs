@@ -1,10 +1,11 @@
 use anyhow::{anyhow, bail, Result};
 use serde_json::{json, Value};
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use common::collect_dag_slots;
 
 use crate::placer::{assign_nodes, PlacementHints};
 use crate::policies;
-use crate::slot::{collect_slots, output_slot};
+use crate::slot::output_slot;
 use crate::slot_assigner::assign_slots;
 use crate::symbolic_dag::{SymbolicDag, SymbolicNode};
 
@@ -129,6 +130,9 @@ fn inject_fanout_frees(nodes: &mut Vec<SymbolicNode>) {
 }
 
 pub fn partition(dag: &SymbolicDag, hints: Option<&PlacementHints>) -> Result<Value> {
+    // Func nodes vs. the guest's `#[workload]` declarations, when the module is here.
+    crate::module_meta::check_symbolic_dag(dag)?;
+
     // total_nodes is optional in the DAG: the coordinator sets it from the live
     // cluster size before partitioning; the standalone CLI defaults it.  Resolve
     // a concrete count here (≥1) and use it throughout.
@@ -182,7 +186,8 @@ pub fn partition(dag: &SymbolicDag, hints: Option<&PlacementHints>) -> Result<Va
     // its machine, so the N nodes process disjoint shards (result == 1×, not N×).
     let shared_paths: HashSet<String> =
         auto_shared_inputs.iter().map(|s| s.path.clone()).collect();
-    assign_input_slices(&mut nodes, total_nodes, &fanout_worker_ids, &shared_paths);
+    let file_set_replicas = file_set_replica_ids(&dag.nodes, total_nodes);
+    assign_input_slices(&mut nodes, total_nodes, &fanout_worker_ids, &shared_paths, &file_set_replicas);
 
     // ── 0b. Slot assignment ───────────────────────────────────────────────────
     assign_slots(&mut nodes)?;
@@ -228,7 +233,7 @@ pub fn partition(dag: &SymbolicDag, hints: Option<&PlacementHints>) -> Result<Va
 
     let mut all_slots: BTreeSet<u32> = BTreeSet::new();
     for node in &nodes {
-        collect_slots(&node.kind, &mut all_slots);
+        collect_dag_slots(&node.kind, &mut all_slots);
     }
     let mut next_slot: u32 = all_slots.iter().last().copied().unwrap_or(0) + 1;
 
@@ -559,7 +564,7 @@ fn resolve_output_kind(
 /// the high 16 bits of `arg` (base in the low 16): the guest reads the base
 /// slot to write from and the worker count to know how many contiguous slots to
 /// fill.  Packing here — rather than at slot-assignment time — keeps the bare
-/// base slot visible to the partitioner's slot bookkeeping (`collect_slots`).
+/// base slot visible to the partitioner's slot bookkeeping (`collect_dag_slots`).
 ///
 /// All other kind variants are returned unchanged.
 fn translate_func_kind(kind: Value, n_consumers: u32) -> Value {
@@ -1096,13 +1101,17 @@ fn split_stream_pipelines(nodes: Vec<SymbolicNode>, total_nodes: usize, hints: &
 /// host resolves the fractions to byte offsets against the actual file at load
 /// time.  Only Inputs whose file is replicated to every node (`shared_paths`,
 /// i.e. `placement:"all"` inputs) are sliced; single-machine Inputs load whole.
+/// Directory / glob Inputs (`files`) replicated by `placement:"all"`
+/// (`file_set_replicas`) are sliced too — the host shards their file list, not
+/// bytes within a file, so `binary` does not exclude them.
 fn assign_input_slices(
     nodes: &mut [SymbolicNode],
     total_nodes: usize,
     worker_ids: &HashSet<String>,
     shared_paths: &HashSet<String>,
+    file_set_replicas: &HashSet<String>,
 ) {
-    if total_nodes <= 1 || shared_paths.is_empty() {
+    if total_nodes <= 1 || (shared_paths.is_empty() && file_set_replicas.is_empty()) {
         return; // single node (or nothing replicated): load the whole file.
     }
 
@@ -1134,13 +1143,15 @@ fn assign_input_slices(
         let Some(m) = node.node_id else { continue };
         let m = m as usize;
         let Some(input_obj) = node.kind.get("Input").and_then(|v| v.as_object()) else { continue };
-        let is_shared = input_obj
+        let is_file_set = input_obj.contains_key("files") && file_set_replicas.contains(&node.id);
+        let is_shared = is_file_set || input_obj
             .get("path")
             .and_then(|v| v.as_str())
             .map(|p| shared_paths.contains(p))
             .unwrap_or(false);
         // Don't slice chunked/binary inputs — those have their own loading paths.
-        let special = input_obj.contains_key("chunk_bytes") || input_obj.contains_key("binary");
+        let special = input_obj.contains_key("chunk_bytes")
+            || (input_obj.contains_key("binary") && !is_file_set);
         // `replicate: true` → every node loads the FULL file (no slice). Required
         // for task-parallel workloads where each distributed worker needs the whole
         // dataset (e.g. finra's stateful audit rules), as opposed to data-parallel
@@ -1158,6 +1169,19 @@ fn assign_input_slices(
     }
 }
 
+/// Ids of the per-machine replicas (`{id}_{machine}`) of every
+/// `placement:"all"` directory / glob `Input`.  Unlike single-file inputs they
+/// are not staged by the coordinator: each machine must already see the files
+/// (shared filesystem or pre-staged data set).
+fn file_set_replica_ids(nodes: &[SymbolicNode], total_nodes: usize) -> HashSet<String> {
+    nodes
+        .iter()
+        .filter(|n| n.placement.as_deref() == Some("all"))
+        .filter(|n| n.kind.get("Input").and_then(|i| i.get("files")).is_some())
+        .flat_map(|n| (0..total_nodes).map(move |m| format!("{}_{}", n.id, m)))
+        .collect()
+}
+
 // ── Unit tests ────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -1193,7 +1217,7 @@ mod tests {
         for i in 0..2 { let id = format!("map_1_{i}"); worker_ids.insert(id.clone()); nodes.push(map_node(&id, 1)); }
         let shared: HashSet<String> = std::iter::once(path.to_string()).collect();
 
-        assign_input_slices(&mut nodes, 2, &worker_ids, &shared);
+        assign_input_slices(&mut nodes, 2, &worker_ids, &shared, &HashSet::new());
 
         let slice_of = |n: &SymbolicNode| n.kind["Input"]["slice"].clone();
         assert_eq!(slice_of(&nodes[0]), json!([0.0, 0.8]), "node 0 (8 maps) → 0.0..0.8");
@@ -1224,10 +1248,28 @@ mod tests {
         for m in 0..2 { for i in 0..8 { let id = format!("map_{m}_{i}"); worker_ids.insert(id.clone()); nodes.push(map_node(&id, m)); } }
         let shared: HashSet<String> = std::iter::once(path.to_string()).collect();
 
-        assign_input_slices(&mut nodes, 2, &worker_ids, &shared);
+        assign_input_slices(&mut nodes, 2, &worker_ids, &shared, &HashSet::new());
+
+        assert_eq!(nodes[0].kind["Input"]["slice"], json!([0.0, 0.5]));
+        assert_eq!(nodes[1].kind["Input"]["slice"], json!([0.5, 1.0]));
+    }
+
+    /// Replicated directory inputs are sliced even when `binary` (the host
+    /// shards the file list); the same spec on a non-replica is left whole.
+    #[test]
+    fn file_set_inputs_sliced_per_replica() {
+        let file_set = |id: &str, m: u32| SymbolicNode {
+            kind: json!({ "Input": { "files": { "path": "imgs/*.png" }, "binary": true } }),
+            ..input_node(id, m, "")
+        };
+        let mut nodes = vec![file_set("load_0", 0), file_set("load_1", 1), file_set("other", 1)];
+        let replicas: HashSet<String> = ["load_0", "load_1"].iter().map(|s| s.to_string()).collect();
+
+        assign_input_slices(&mut nodes, 2, &HashSet::new(), &HashSet::new(), &replicas);
 
         assert_eq!(nodes[0].kind["Input"]["slice"], json!([0.0, 0.5]));
         assert_eq!(nodes[1].kind["Input"]["slice"], json!([0.5, 1.0]));
+        assert!(nodes[2].kind["Input"].get("slice").is_none());
     }
 
     #[test]
@@ -75,7 +75,7 @@ POLICIES = ("pack", "balanced", "spread", "random")
 # writes a UNIQUE output slot per (rule,shard) so the aggregator splices each
 # partial once. The bit layout is deliberately SMALL: the partitioner's
 # collect_slots treats every integer in a node's kind as a candidate slot, so a
-# large packed arg (or output slot) would overflow STREAM_SLOT_COUNT (2048).
+# large packed arg (or output slot) would overflow the stream slot count (2048 by default).
 FINRA_STATEFUL = (2, 3, 4)            # WASH_TRADE, SPOOFING, CONCENTRATION
 FINRA_STATELESS = (0, 1, 5, 6, 7)     # PRICE_OUTLIER, LARGE_ORDER, AFTER_HOURS, PENNY_STOCK, ROUND_LOT
 FINRA_RULE_OUT_BASE = 10              # stateful rule i → slot 10+i (matches guest)